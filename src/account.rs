@@ -0,0 +1,208 @@
+//! Linking groups to the private chat with the bot: /link in a group sends
+//! a deep link that, opened by the same user, lets `/list all` in private
+//! list the reminders they created in the group. The links to open are
+//! only kept in memory, so they stop working after a restart.
+
+use crate::entity::account_link;
+use crate::format;
+use crate::lang::Language;
+use crate::share;
+use crate::tg;
+use chrono::{NaiveDateTime, TimeDelta};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+};
+use url::Url;
+
+const PAYLOAD_PREFIX: &str = "link";
+
+const CALLBACK_PREFIX: &str = "account::unlink::";
+
+/// Time the link sent in the group works for
+const LINK_WINDOW: TimeDelta = TimeDelta::hours(1);
+
+/// Links kept at most, the oldest ones are forgotten first
+const MAX_PENDING: usize = 1024;
+
+/// Group waiting to be linked by the user who asked for it
+struct Pending {
+    token: String,
+    time: NaiveDateTime,
+    chat_id: i64,
+    user_id: i64,
+    title: String,
+}
+
+lazy_static! {
+    static ref PENDING: Mutex<VecDeque<Pending>> = Mutex::new(VecDeque::new());
+}
+
+/// Keep the group to be linked, return the token of the start payload
+pub(crate) fn new_token(
+    chat_id: i64,
+    user_id: i64,
+    title: String,
+    now: NaiveDateTime,
+) -> String {
+    let mut pending = PENDING.lock().unwrap();
+    while pending.len() >= MAX_PENDING
+        || pending
+            .front()
+            .is_some_and(|link| link.time + LINK_WINDOW <= now)
+    {
+        pending.pop_front();
+    }
+    let token = share::new_token();
+    pending.push_back(Pending {
+        token: token.clone(),
+        time: now,
+        chat_id,
+        user_id,
+        title,
+    });
+    token
+}
+
+/// Take the group and its title the token was given for, `None` if it's
+/// too late or the token was given to another user, who could otherwise
+/// see the reminders by following a link forwarded to them
+pub(crate) fn take(
+    token: &str,
+    user_id: i64,
+    now: NaiveDateTime,
+) -> Option<(i64, String)> {
+    let mut pending = PENDING.lock().unwrap();
+    let idx = pending
+        .iter()
+        .position(|link| link.token == token && link.user_id == user_id)?;
+    let link = pending.remove(idx)?;
+    (link.time + LINK_WINDOW > now).then_some((link.chat_id, link.title))
+}
+
+/// Token of the start payload of a link
+pub(crate) fn parse_payload(payload: &str) -> Option<String> {
+    payload
+        .strip_prefix(PAYLOAD_PREFIX)
+        .filter(|token| {
+            !token.is_empty() && token.chars().all(|c| c.is_alphanumeric())
+        })
+        .map(str::to_owned)
+}
+
+/// Button of the group message opening the private chat with the bot
+pub(crate) fn link_button(
+    bot_username: &str,
+    token: &str,
+    lang: Language,
+) -> Option<InlineKeyboardButton> {
+    let url = Url::parse(&format!(
+        "https://t.me/{}?start={}{}",
+        bot_username, PAYLOAD_PREFIX, token
+    ))
+    .ok()?;
+    Some(InlineKeyboardButton::url(
+        lang.text("button_link", &[]),
+        url,
+    ))
+}
+
+/// Filter of the /list arguments asking for the reminders of all the
+/// linked chats, e.g. `all` or `all paused`
+pub(crate) fn parse_list_args(args: &str) -> Option<&str> {
+    let args = args.trim();
+    let rest = args.strip_prefix("all")?;
+    (rest.is_empty() || rest.starts_with(char::is_whitespace))
+        .then(|| rest.trim_start())
+}
+
+/// Buttons unlinking each of the linked groups
+pub(crate) fn unlink_markup(
+    links: &[account_link::Model],
+) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(links.iter().map(|link| {
+        vec![InlineKeyboardButton::new(
+            format::truncate(
+                &format!("❌ {}", link.title),
+                tg::BUTTON_LABEL_MAX_LEN,
+            ),
+            InlineKeyboardButtonKind::CallbackData(format!(
+                "{}{}",
+                CALLBACK_PREFIX, link.chat_id
+            )),
+        )]
+    }))
+}
+
+/// Group whose unlink button was pressed
+pub(crate) fn parse_callback(cb_data: &str) -> Option<i64> {
+    cb_data.strip_prefix(CALLBACK_PREFIX)?.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_take() {
+        let now = utc("2024-06-01 10:00");
+        let token = new_token(-100, 1, "Family".to_owned(), now);
+        assert!(take(&token, 2, now).is_none());
+        assert_eq!(
+            take(&token, 1, now + TimeDelta::minutes(1)),
+            Some((-100, "Family".to_owned()))
+        );
+        assert!(take(&token, 1, now).is_none());
+    }
+
+    #[test]
+    fn test_take_expired() {
+        let now = utc("2024-06-01 10:00");
+        let token = new_token(-100, 1, "Family".to_owned(), now);
+        assert!(take(&token, 1, now + LINK_WINDOW).is_none());
+    }
+
+    #[test_case("linkAbc123" => Some("Abc123".to_owned()) ; "token")]
+    #[test_case("link" => None ; "no token")]
+    #[test_case("link-1_2" => None ; "bad token")]
+    #[test_case("manage-1_2" => None ; "other prefix")]
+    fn test_parse_payload(payload: &str) -> Option<String> {
+        parse_payload(payload)
+    }
+
+    #[test_case("all" => Some("") ; "all")]
+    #[test_case(" all  paused " => Some("paused") ; "with filter")]
+    #[test_case("allergy" => None ; "word starting with all")]
+    #[test_case("paused" => None ; "filter only")]
+    fn test_parse_list_args(args: &str) -> Option<&str> {
+        parse_list_args(args)
+    }
+
+    #[test]
+    fn test_unlink_markup_round_trip() {
+        let links = [account_link::Model {
+            id: 1,
+            user_id: 1,
+            chat_id: -100,
+            title: "Family".to_owned(),
+        }];
+        let chat_ids: Vec<_> = unlink_markup(&links)
+            .inline_keyboard
+            .into_iter()
+            .flatten()
+            .filter_map(|button| match button.kind {
+                InlineKeyboardButtonKind::CallbackData(data) => {
+                    parse_callback(&data)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(chat_ids, vec![-100]);
+    }
+}
@@ -0,0 +1,85 @@
+//! Advance warnings: `!30m` after the time pattern, e.g.
+//! `25.12 18:00 !30m party`, warns that long before every occurrence
+
+use crate::grammar;
+use chrono::{NaiveDateTime, TimeDelta};
+
+const UNITS: [(i64, &str); 4] =
+    [(86400, "d"), (3600, "h"), (60, "m"), (1, "s")];
+
+/// Lead of the warning in seconds. Years and months vary in length,
+/// so they aren't allowed.
+pub(crate) fn lead_seconds(interval: &grammar::Interval) -> Option<i64> {
+    if interval.years != 0 || interval.months != 0 {
+        return None;
+    }
+    let lead = TimeDelta::weeks(interval.weeks as i64)
+        + TimeDelta::days(interval.days as i64)
+        + TimeDelta::hours(interval.hours as i64)
+        + TimeDelta::minutes(interval.minutes as i64)
+        + TimeDelta::seconds(interval.seconds as i64);
+    Some(lead.num_seconds())
+}
+
+/// Time to warn about the occurrence at the time, `None` if it's past
+pub(crate) fn warning_time(
+    time: NaiveDateTime,
+    lead: i64,
+    now: NaiveDateTime,
+) -> Option<NaiveDateTime> {
+    let warning_time = time - TimeDelta::seconds(lead);
+    (warning_time > now).then_some(warning_time)
+}
+
+/// Lead written the way it's entered, e.g. `1h30m`
+pub(crate) fn format_lead(lead: i64) -> String {
+    let mut rest = lead;
+    UNITS
+        .iter()
+        .filter_map(|&(unit_seconds, unit)| {
+            let count = rest / unit_seconds;
+            rest %= unit_seconds;
+            (count > 0).then(|| format!("{}{}", count, unit))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(1800 => "30m" ; "minutes")]
+    #[test_case(5400 => "1h30m" ; "hours and minutes")]
+    #[test_case(8 * 86400 => "8d" ; "week and a day")]
+    #[test_case(90 => "1m30s" ; "seconds")]
+    fn test_format_lead(lead: i64) -> String {
+        format_lead(lead)
+    }
+
+    #[test]
+    fn test_lead_seconds() {
+        let interval = grammar::Interval {
+            weeks: 1,
+            hours: 2,
+            ..Default::default()
+        };
+        assert_eq!(lead_seconds(&interval), Some(7 * 86400 + 2 * 3600));
+        let interval = grammar::Interval {
+            months: 1,
+            ..Default::default()
+        };
+        assert_eq!(lead_seconds(&interval), None);
+    }
+
+    #[test]
+    fn test_warning_time() {
+        let time = NaiveDateTime::default() + TimeDelta::hours(2);
+        let now = NaiveDateTime::default();
+        assert_eq!(
+            warning_time(time, 1800, now),
+            Some(time - TimeDelta::minutes(30))
+        );
+        assert_eq!(warning_time(time, 3 * 3600, now), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Fallback for the reminders the grammar fails to parse: a language model
+//! rewrites the text into the bot's syntax, and the result is confirmed by
+//! the user before saving
+
+#[cfg(not(test))]
+use crate::cli::{self, Feature, CLI};
+use crate::err::Error;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+const SYSTEM_PROMPT: &str = "\
+You convert reminder requests into the syntax of a Telegram reminder bot. \
+Reply with a single line in one of the following formats and nothing else:
+- one-time: `<day.month.year> <hour:minute> <description>`, \
+e.g. `15.06.2024 13:00 doctor appointment`
+- recurring: `<date_from>-<date_until>/<divisor> <time> <description>`, \
+where the divisor is like `1m`, `2w`, `mon-fri` or `sun`, \
+e.g. `-/mon-fri 10-20/1h30m take a break`, `20/1m 10:00 submit meter readings`
+- countdown: `<duration> <description>`, e.g. `1h30m grab tea`
+- cron: `<minute> <hour> <day> <month> <weekday> <description>`, \
+e.g. `55 10 * * 1-5 go to school`
+Keep the description in the user's language. \
+If the request isn't a reminder, reply with NONE.";
+
+/// Service turning a free-form request into a reminder in the bot's syntax
+pub(crate) trait Backend {
+    /// Rewrite the text, `None` if it doesn't look like a reminder
+    async fn suggest(
+        &self,
+        text: &str,
+        now: NaiveDateTime,
+    ) -> Result<Option<String>, Error>;
+}
+
+/// Backend for any API compatible with OpenAI's chat completions
+pub(crate) struct OpenAiBackend {
+    client: reqwest::Client,
+    endpoint: Url,
+    api_key: Option<String>,
+    model: String,
+}
+
+#[derive(Serialize)]
+struct ChatMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: [ChatMessage<'a>; 2],
+    temperature: f32,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatReply,
+}
+
+#[derive(Deserialize)]
+struct ChatReply {
+    content: String,
+}
+
+impl OpenAiBackend {
+    pub(crate) fn new(
+        api_url: &Url,
+        api_key: Option<String>,
+        model: String,
+    ) -> Option<Self> {
+        let mut api_url = api_url.clone();
+        if !api_url.path().ends_with('/') {
+            api_url.set_path(&format!("{}/", api_url.path()));
+        }
+        Some(Self {
+            client: reqwest::Client::new(),
+            endpoint: api_url.join("chat/completions").ok()?,
+            api_key,
+            model,
+        })
+    }
+}
+
+impl Backend for OpenAiBackend {
+    async fn suggest(
+        &self,
+        text: &str,
+        now: NaiveDateTime,
+    ) -> Result<Option<String>, Error> {
+        let user_prompt =
+            format!("Now is {}.\n{}", now.format("%A, %d.%m.%Y %H:%M"), text);
+        let request = ChatRequest {
+            model: &self.model,
+            messages: [
+                ChatMessage {
+                    role: "system",
+                    content: SYSTEM_PROMPT,
+                },
+                ChatMessage {
+                    role: "user",
+                    content: &user_prompt,
+                },
+            ],
+            temperature: 0.0,
+        };
+        let mut builder = self.client.post(self.endpoint.clone());
+        if let Some(ref api_key) = self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        let response: ChatResponse = builder
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.choices.into_iter().next().and_then(|choice| {
+            let suggestion = choice.message.content.trim().trim_matches('`');
+            (!suggestion.is_empty() && suggestion != "NONE")
+                .then(|| suggestion.to_owned())
+        }))
+    }
+}
+
+#[cfg(not(test))]
+lazy_static::lazy_static! {
+    static ref BACKEND: Option<OpenAiBackend> =
+        CLI.ai_api_url
+            .as_ref()
+            .filter(|_| cli::feature_enabled(Feature::Ai))
+            .and_then(|api_url| {
+            OpenAiBackend::new(
+                api_url,
+                CLI.ai_api_key.clone(),
+                CLI.ai_model.clone(),
+            )
+        });
+}
+
+/// Configured backend, if any
+#[cfg(not(test))]
+pub(crate) fn backend() -> Option<&'static OpenAiBackend> {
+    BACKEND.as_ref()
+}
+
+#[cfg(test)]
+pub(crate) fn backend() -> Option<&'static OpenAiBackend> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("https://api.openai.com/v1" ; "without slash")]
+    #[test_case("https://api.openai.com/v1/" ; "with slash")]
+    fn test_endpoint(api_url: &str) {
+        let backend = OpenAiBackend::new(
+            &Url::parse(api_url).unwrap(),
+            None,
+            "model".to_owned(),
+        )
+        .unwrap();
+        assert_eq!(
+            backend.endpoint.as_str(),
+            "https://api.openai.com/v1/chat/completions"
+        );
+    }
+}
@@ -0,0 +1,502 @@
+//! HTTP API to list, set and delete the reminders of a chat from scripts
+//! and home automation, served along with the shared reminder lists. The
+//! requests bear a token issued in the chat with /apitoken, which is kept
+//! only as a hash. The reminders set over it are delivered by the poller
+//! like the ones set in Telegram.
+
+#[cfg(not(test))]
+use crate::db::Database;
+#[cfg(test)]
+use crate::db::MockDatabase as Database;
+use crate::entity::api_token;
+use crate::generic_reminder::GenericReminder;
+use crate::{cli, parsers, share, tz};
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use chrono_tz::Tz;
+use sea_orm::{ActiveValue::NotSet, Set};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Action {
+    /// New token, the old one stops working
+    Issue,
+    Off,
+}
+
+pub(crate) fn parse_args(args: &str) -> Option<Action> {
+    match args.trim().to_lowercase().as_str() {
+        "" => Some(Action::Issue),
+        "off" => Some(Action::Off),
+        _ => None,
+    }
+}
+
+/// Hex SHA-256 of the token, under which it's stored
+pub(crate) fn hash_token(token: &str) -> String {
+    openssl::sha::sha256(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Token of the chat issued by the user, along with its stored hash
+pub(crate) fn new_token(
+    chat_id: i64,
+    user_id: i64,
+) -> (String, api_token::ActiveModel) {
+    let token = share::new_token();
+    let model = api_token::ActiveModel {
+        id: NotSet,
+        chat_id: Set(chat_id),
+        user_id: Set(user_id),
+        token_hash: Set(hash_token(&token)),
+    };
+    (token, model)
+}
+
+/// Address the reminders are listed and set at
+pub(crate) fn reminders_url(public_url: &Url) -> Option<Url> {
+    share::public_path_url(public_url, "api/reminders")
+}
+
+/// Reminder as listed and returned by the API
+#[derive(Debug, PartialEq, Serialize)]
+pub(crate) struct ApiReminder {
+    id: i64,
+    kind: &'static str,
+    /// Next firing in UTC, RFC 3339
+    time: String,
+    paused: bool,
+    /// Reminder as shown in `/list`
+    text: String,
+}
+
+impl ApiReminder {
+    fn new(reminder: &dyn GenericReminder, tz: Tz) -> Self {
+        Self {
+            id: reminder.get_id().unwrap_or_default(),
+            kind: match reminder.get_type() {
+                "cron_rem" => "cron_reminder",
+                _ => "reminder",
+            },
+            time: reminder.get_time().and_utc().to_rfc3339(),
+            paused: reminder.is_paused(),
+            text: reminder.to_unescaped_string(tz),
+        }
+    }
+}
+
+/// Body of a request to set a reminder, owned by the user who issued
+/// the token
+#[derive(Deserialize)]
+pub(crate) struct NewReminder {
+    /// Reminder as it's sent to the bot, e.g. `daily 9:00 take pills`
+    text: String,
+}
+
+fn error(
+    mut response: actix_web::HttpResponseBuilder,
+    msg: &str,
+) -> HttpResponse {
+    response.json(serde_json::json!({ "error": msg }))
+}
+
+fn internal_error(err: impl std::fmt::Display) -> HttpResponse {
+    tracing::error!("{}", err);
+    HttpResponse::InternalServerError().finish()
+}
+
+fn too_long(desc: &str) -> bool {
+    desc.chars().count() > cli::max_description_length()
+}
+
+/// Unlike in Telegram the rest of a long description isn't set
+/// as another reminder
+fn too_long_error() -> HttpResponse {
+    error(
+        HttpResponse::PayloadTooLarge(),
+        "the description is too long",
+    )
+}
+
+/// Token the request bears in the `Authorization` header, or the
+/// response to give if it's missing or revoked
+async fn authorize(
+    req: &HttpRequest,
+    db: &Database,
+) -> Result<api_token::Model, HttpResponse> {
+    let Some(token) = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return Err(HttpResponse::Unauthorized().finish());
+    };
+    match db.get_api_token(&hash_token(token)).await {
+        Ok(Some(token)) => Ok(token),
+        Ok(None) => Err(HttpResponse::Unauthorized().finish()),
+        Err(err) => Err(internal_error(err)),
+    }
+}
+
+async fn list_reminders(
+    req: HttpRequest,
+    db: web::Data<Database>,
+) -> HttpResponse {
+    let chat_id = match authorize(&req, &db).await {
+        Ok(token) => token.chat_id,
+        Err(response) => return response,
+    };
+    let tz = match tz::get_reminder_timezone(&db, None, chat_id).await {
+        Ok(tz) => tz.unwrap_or(Tz::UTC),
+        Err(err) => return internal_error(err),
+    };
+    match db.get_sorted_reminders(chat_id).await {
+        Ok(reminders) => HttpResponse::Ok().json(
+            reminders
+                .iter()
+                .map(|reminder| ApiReminder::new(reminder.as_ref(), tz))
+                .collect::<Vec<_>>(),
+        ),
+        Err(err) => internal_error(err),
+    }
+}
+
+async fn set_reminder(
+    body: web::Json<NewReminder>,
+    req: HttpRequest,
+    db: web::Data<Database>,
+) -> HttpResponse {
+    let (chat_id, user_id) = match authorize(&req, &db).await {
+        Ok(token) => (token.chat_id, token.user_id as u64),
+        Err(response) => return response,
+    };
+    let tz = match tz::get_reminder_timezone(&db, Some(user_id as i64), chat_id)
+        .await
+    {
+        Ok(Some(tz)) => tz,
+        Ok(None) => {
+            return error(
+                HttpResponse::UnprocessableEntity(),
+                "the timezone isn't set",
+            )
+        }
+        Err(err) => return internal_error(err),
+    };
+    if let Some(cron_reminder) =
+        parsers::parse_cron_reminder(&body.text, chat_id, user_id, tz).await
+    {
+        if too_long(cron_reminder.desc.as_ref()) {
+            return too_long_error();
+        }
+        return match db.insert_cron_reminder(cron_reminder).await {
+            Ok(cron_reminder) => HttpResponse::Created()
+                .json(ApiReminder::new(&cron_reminder, tz)),
+            Err(err) => internal_error(err),
+        };
+    }
+    match parsers::parse_reminder(&body.text, chat_id, user_id, tz).await {
+        Some(reminder) if too_long(reminder.desc.as_ref()) => too_long_error(),
+        Some(reminder) => match db.insert_reminder(reminder).await {
+            Ok(reminder) => {
+                HttpResponse::Created().json(ApiReminder::new(&reminder, tz))
+            }
+            Err(err) => internal_error(err),
+        },
+        None => error(
+            HttpResponse::UnprocessableEntity(),
+            "the reminder couldn't be parsed",
+        ),
+    }
+}
+
+async fn delete_reminder(
+    id: web::Path<i64>,
+    req: HttpRequest,
+    db: web::Data<Database>,
+) -> HttpResponse {
+    let chat_id = match authorize(&req, &db).await {
+        Ok(token) => token.chat_id,
+        Err(response) => return response,
+    };
+    let id = id.into_inner();
+    match db.get_reminder(id).await {
+        Ok(Some(reminder)) if reminder.chat_id == chat_id => {
+            match db.trash_reminder(id).await {
+                Ok(()) => HttpResponse::NoContent().finish(),
+                Err(err) => internal_error(err),
+            }
+        }
+        Ok(_) => HttpResponse::NotFound().finish(),
+        Err(err) => internal_error(err),
+    }
+}
+
+async fn delete_cron_reminder(
+    id: web::Path<i64>,
+    req: HttpRequest,
+    db: web::Data<Database>,
+) -> HttpResponse {
+    let chat_id = match authorize(&req, &db).await {
+        Ok(token) => token.chat_id,
+        Err(response) => return response,
+    };
+    let id = id.into_inner();
+    match db.get_cron_reminder(id).await {
+        Ok(Some(cron_reminder)) if cron_reminder.chat_id == chat_id => {
+            match db.trash_cron_reminder(id).await {
+                Ok(()) => HttpResponse::NoContent().finish(),
+                Err(err) => internal_error(err),
+            }
+        }
+        Ok(_) => HttpResponse::NotFound().finish(),
+        Err(err) => internal_error(err),
+    }
+}
+
+/// Routes of the API under `/api`, the database has to be in the app data
+pub(crate) fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/api")
+            .route("/reminders", web::get().to(list_reminders))
+            .route("/reminders", web::post().to(set_reminder))
+            .route("/reminders/{id}", web::delete().to(delete_reminder))
+            .route(
+                "/cron_reminders/{id}",
+                web::delete().to(delete_cron_reminder),
+            ),
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::reminder;
+    use actix_web::{http::StatusCode, test, App};
+    use chrono::{NaiveDate, NaiveTime};
+    use mockall::predicate::eq;
+    use sea_orm::IntoActiveModel;
+    use serde_json::json;
+    use test_case::test_case;
+
+    const TOKEN: &str = "secret";
+
+    /// Database knowing the token issued in the chat by its user
+    fn token_database(chat_id: i64) -> Database {
+        let mut db = Database::new();
+        db.expect_get_api_token()
+            .with(eq(hash_token(TOKEN)))
+            .returning(move |token_hash| {
+                Ok(Some(api_token::Model {
+                    id: 1,
+                    chat_id,
+                    user_id: 1,
+                    token_hash: token_hash.to_owned(),
+                }))
+            });
+        db.expect_get_api_token().returning(|_| Ok(None));
+        db
+    }
+
+    #[test_case("" => Some(Action::Issue) ; "issue")]
+    #[test_case(" OFF " => Some(Action::Off) ; "off")]
+    #[test_case("reset" => None ; "unknown")]
+    fn test_parse_args(args: &str) -> Option<Action> {
+        parse_args(args)
+    }
+
+    #[tokio::test]
+    async fn test_new_token() {
+        let (token, model) = new_token(-100, 1);
+        assert_eq!(model.token_hash, Set(hash_token(&token)));
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    fn mock_reminder(chat_id: i64) -> reminder::Model {
+        reminder::Model {
+            id: 1,
+            chat_id,
+            time: NaiveDate::from_ymd_opt(2024, 1, 1)
+                .unwrap()
+                .and_time(NaiveTime::from_hms_opt(0, 1, 0).unwrap()),
+            desc: "tea".to_owned(),
+            user_id: Some(chat_id),
+            paused: false,
+            pattern: None,
+            policy: None,
+            version: 0,
+            pomodoro_id: None,
+            target: None,
+            meeting: false,
+            advance: None,
+            advance_time: None,
+            media_kind: None,
+            media_file_id: None,
+            held_since: None,
+            failures: 0,
+            retry_time: None,
+            thread_id: None,
+            deleted_time: None,
+        }
+    }
+
+    async fn call(
+        db: Database,
+        req: test::TestRequest,
+    ) -> actix_web::dev::ServiceResponse {
+        let app = test::init_service(
+            App::new().app_data(web::Data::new(db)).configure(configure),
+        )
+        .await;
+        test::call_service(&app, req.to_request()).await
+    }
+
+    fn authorized_request() -> test::TestRequest {
+        test::TestRequest::default()
+            .insert_header((header::AUTHORIZATION, format!("Bearer {}", TOKEN)))
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized() {
+        for req in [
+            test::TestRequest::get(),
+            test::TestRequest::get()
+                .insert_header((header::AUTHORIZATION, "Bearer secreT")),
+        ] {
+            let resp = call(token_database(1), req.uri("/api/reminders")).await;
+            assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_reminders() {
+        let mut db = token_database(1);
+        db.expect_get_user_timezone_name()
+            .with(eq(1))
+            .returning(|_| Ok(Some("Europe/Amsterdam".to_owned())));
+        db.expect_get_sorted_reminders()
+            .with(eq(1))
+            .returning(|chat_id| {
+                Ok(vec![Box::new(mock_reminder(chat_id).into_active_model())])
+            });
+        let resp = call(
+            db,
+            authorized_request()
+                .method(actix_web::http::Method::GET)
+                .uri("/api/reminders"),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body[0]["id"], 1);
+        assert_eq!(body[0]["kind"], "reminder");
+        assert_eq!(body[0]["time"], "2024-01-01T00:01:00+00:00");
+        assert_eq!(body[0]["paused"], false);
+        // Shown in Amsterdam time
+        assert!(body[0]["text"].as_str().unwrap().ends_with("01:01 <tea>"));
+    }
+
+    #[tokio::test]
+    async fn test_set_reminder() {
+        let mut db = token_database(1);
+        db.expect_get_user_timezone_name()
+            .with(eq(1))
+            .returning(|_| Ok(Some("Europe/Amsterdam".to_owned())));
+        db.expect_insert_reminder()
+            .withf(|rem| {
+                rem.desc == Set("tea".to_owned())
+                    && rem.chat_id == Set(1)
+                    && rem.user_id == Set(Some(1))
+            })
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(7), ..rem }));
+        let resp = call(
+            db,
+            authorized_request()
+                .method(actix_web::http::Method::POST)
+                .uri("/api/reminders")
+                .set_json(json!({ "text": "10:00 tea" })),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["id"], 7);
+        assert_eq!(body["kind"], "reminder");
+    }
+
+    #[tokio::test]
+    async fn test_set_reminder_errors() {
+        let mut db = token_database(1);
+        db.expect_get_user_timezone_name()
+            .with(eq(1))
+            .returning(|_| Ok(Some("Europe/Amsterdam".to_owned())));
+        db.expect_insert_reminder().never();
+        let resp = call(
+            db,
+            authorized_request()
+                .method(actix_web::http::Method::POST)
+                .uri("/api/reminders")
+                .set_json(json!({ "text": "tea" })),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_set_group_reminder() {
+        // Owned by the user who issued the token in the group
+        let mut db = token_database(-100);
+        db.expect_get_chat_timezone_name()
+            .with(eq(-100))
+            .returning(|_| Ok(None));
+        db.expect_get_user_timezone_name()
+            .with(eq(1))
+            .returning(|_| Ok(Some("Europe/Amsterdam".to_owned())));
+        db.expect_insert_reminder()
+            .withf(|rem| {
+                rem.chat_id == Set(-100) && rem.user_id == Set(Some(1))
+            })
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(7), ..rem }));
+        let resp = call(
+            db,
+            authorized_request()
+                .method(actix_web::http::Method::POST)
+                .uri("/api/reminders")
+                .set_json(json!({ "text": "10:00 tea" })),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_delete_reminder() {
+        let mut db = token_database(1);
+        db.expect_get_reminder()
+            .returning(|id| Ok(Some(mock_reminder(id))));
+        db.expect_trash_reminder()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(()));
+        let db = web::Data::new(db);
+        let app = test::init_service(
+            App::new().app_data(db.clone()).configure(configure),
+        )
+        .await;
+        // The reminder of another chat isn't found
+        for (id, status) in
+            [(2, StatusCode::NOT_FOUND), (1, StatusCode::NO_CONTENT)]
+        {
+            let req = authorized_request()
+                .method(actix_web::http::Method::DELETE)
+                .uri(&format!("/api/reminders/{}", id))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), status);
+        }
+    }
+}
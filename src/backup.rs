@@ -0,0 +1,306 @@
+//! Backups of a chat's reminders: /export sends them as a JSON file,
+//! and uploading the file back recreates the reminders
+
+use crate::advance;
+use crate::cron;
+use crate::entity::{cron_reminder, reminder};
+use crate::policy::NotificationPolicy;
+use crate::serializers::Pattern;
+use chrono::{NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use sea_orm::ActiveValue::{NotSet, Set};
+use serde::{Deserialize, Serialize};
+
+/// Name of the sent backup file, uploaded files are recognized by extension
+pub(crate) const FILE_NAME: &str = "reminders.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct Backup {
+    timezone: String,
+    #[serde(default)]
+    reminders: Vec<ReminderEntry>,
+    #[serde(default)]
+    cron_reminders: Vec<CronReminderEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReminderEntry {
+    /// Time of the next occurrence in UTC
+    time: NaiveDateTime,
+    desc: String,
+    #[serde(default)]
+    paused: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pattern: Option<Pattern>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    policy: Option<NotificationPolicy>,
+    /// Username of the group member the reminder is for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    /// Whether to ask the chat who's coming before the time
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    meeting: bool,
+    /// Seconds before each occurrence to warn about it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    advance: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CronReminderEntry {
+    cron_expr: String,
+    desc: String,
+    #[serde(default)]
+    paused: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    policy: Option<NotificationPolicy>,
+}
+
+/// Reminders recreated from a backup
+pub(crate) struct Restored {
+    pub(crate) reminders: Vec<reminder::ActiveModel>,
+    pub(crate) cron_reminders: Vec<cron_reminder::ActiveModel>,
+    /// Number of entries that couldn't be restored
+    pub(crate) skipped: usize,
+}
+
+fn stored_policy(policy: Option<&str>) -> Option<NotificationPolicy> {
+    policy.map(|policy| NotificationPolicy::from_stored(Some(policy)))
+}
+
+fn restored_policy(policy: Option<NotificationPolicy>) -> Option<String> {
+    policy.and_then(|policy| serde_json::to_string(&policy).ok())
+}
+
+impl Backup {
+    /// Backup of the reminders, pomodoro phases are left out
+    pub(crate) fn new(
+        user_tz: Tz,
+        reminders: Vec<reminder::Model>,
+        cron_reminders: Vec<cron_reminder::Model>,
+    ) -> Self {
+        Self {
+            timezone: user_tz.name().to_owned(),
+            reminders: reminders
+                .into_iter()
+                .filter(|rem| rem.pomodoro_id.is_none())
+                .map(|rem| ReminderEntry {
+                    time: rem.time,
+                    pattern: rem
+                        .pattern
+                        .as_deref()
+                        .and_then(|pattern| serde_json::from_str(pattern).ok()),
+                    policy: stored_policy(rem.policy.as_deref()),
+                    desc: rem.desc,
+                    paused: rem.paused,
+                    target: rem.target,
+                    meeting: rem.meeting,
+                    advance: rem.advance,
+                })
+                .collect(),
+            cron_reminders: cron_reminders
+                .into_iter()
+                .map(|rem| CronReminderEntry {
+                    policy: stored_policy(rem.policy.as_deref()),
+                    cron_expr: rem.cron_expr,
+                    desc: rem.desc,
+                    paused: rem.paused,
+                })
+                .collect(),
+        }
+    }
+
+    /// Number of the backed up reminders
+    pub(crate) fn len(&self) -> usize {
+        self.reminders.len() + self.cron_reminders.len()
+    }
+
+    pub(crate) fn to_json(&self) -> Vec<u8> {
+        // all the fields are plain data with string keys
+        serde_json::to_vec_pretty(self).expect("backup is serializable")
+    }
+
+    pub(crate) fn from_json(content: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(content)
+    }
+
+    /// Timezone of the backup, if it's a valid one
+    pub(crate) fn tz(&self) -> Option<Tz> {
+        self.timezone.parse().ok()
+    }
+
+    /// Recreate the reminders in a chat, skipping the entries with empty
+    /// or too long descriptions and invalid cron expressions
+    pub(crate) fn restore(
+        self,
+        chat_id: i64,
+        user_id: u64,
+        user_tz: Tz,
+        max_desc_len: usize,
+    ) -> Restored {
+        let valid_desc = |desc: &str| {
+            !desc.is_empty() && desc.chars().count() <= max_desc_len
+        };
+        let total = self.reminders.len() + self.cron_reminders.len();
+        let now = Utc::now().naive_utc();
+        let reminders: Vec<_> = self
+            .reminders
+            .into_iter()
+            .filter(|rem| valid_desc(&rem.desc))
+            .map(|rem| reminder::ActiveModel {
+                id: NotSet,
+                chat_id: Set(chat_id),
+                user_id: Set(Some(user_id as i64)),
+                pattern: Set(rem
+                    .pattern
+                    .and_then(|pattern| pattern.to_stored(rem.time))),
+                time: Set(rem.time),
+                desc: Set(rem.desc),
+                paused: Set(rem.paused),
+                policy: Set(restored_policy(rem.policy)),
+                version: Set(0),
+                pomodoro_id: Set(None),
+                target: Set(rem.target),
+                meeting: Set(rem.meeting),
+                advance: Set(rem.advance),
+                advance_time: Set(rem.advance.and_then(|lead| {
+                    advance::warning_time(rem.time, lead, now)
+                })),
+                media_kind: Set(None),
+                media_file_id: Set(None),
+                held_since: Set(None),
+                failures: Set(0),
+                retry_time: Set(None),
+                thread_id: Set(None),
+                deleted_time: Set(None),
+            })
+            .collect();
+        let cron_reminders: Vec<_> = self
+            .cron_reminders
+            .into_iter()
+            .filter(|rem| valid_desc(&rem.desc))
+            .filter_map(|rem| {
+                let time =
+                    cron::next_time(&rem.cron_expr, now, user_tz).ok()?;
+                Some(cron_reminder::ActiveModel {
+                    id: NotSet,
+                    chat_id: Set(chat_id),
+                    user_id: Set(Some(user_id as i64)),
+                    cron_expr: Set(rem.cron_expr),
+                    time: Set(time),
+                    desc: Set(rem.desc),
+                    paused: Set(rem.paused),
+                    policy: Set(restored_policy(rem.policy)),
+                    version: Set(0),
+                    media_kind: Set(None),
+                    media_file_id: Set(None),
+                    failures: Set(0),
+                    retry_time: Set(None),
+                    thread_id: Set(None),
+                    deleted_time: Set(None),
+                })
+            })
+            .collect();
+        Restored {
+            skipped: total - reminders.len() - cron_reminders.len(),
+            reminders,
+            cron_reminders,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn reminder(pomodoro_id: Option<i64>) -> reminder::Model {
+        reminder::Model {
+            id: 1,
+            chat_id: 1,
+            time: NaiveDateTime::default(),
+            desc: "reminder".to_owned(),
+            user_id: Some(1),
+            paused: true,
+            pattern: None,
+            policy: Some(r#"{"silent":true}"#.to_owned()),
+            version: 3,
+            pomodoro_id,
+            target: None,
+            meeting: false,
+            advance: None,
+            advance_time: None,
+            media_kind: None,
+            media_file_id: None,
+            held_since: None,
+            failures: 0,
+            retry_time: None,
+            thread_id: None,
+            deleted_time: None,
+        }
+    }
+
+    fn cron_reminder(cron_expr: &str) -> cron_reminder::Model {
+        cron_reminder::Model {
+            id: 2,
+            chat_id: 1,
+            cron_expr: cron_expr.to_owned(),
+            time: NaiveDateTime::default(),
+            desc: "cron reminder".to_owned(),
+            user_id: Some(1),
+            paused: false,
+            policy: None,
+            version: 1,
+            media_kind: None,
+            media_file_id: None,
+            failures: 0,
+            retry_time: None,
+            thread_id: None,
+            deleted_time: None,
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let backup = Backup::new(
+            chrono_tz::Europe::Amsterdam,
+            vec![reminder(None), reminder(Some(1))],
+            vec![cron_reminder("0 9 * * 1-5"), cron_reminder("0 9 * *")],
+        );
+        let backup = Backup::from_json(&backup.to_json()).unwrap();
+        assert_eq!(backup.tz(), Some(chrono_tz::Europe::Amsterdam));
+
+        let restored = backup.restore(2, 3, chrono_tz::Europe::Amsterdam, 100);
+        assert_eq!(restored.reminders.len(), 1);
+        assert_eq!(restored.cron_reminders.len(), 1);
+        assert_eq!(restored.skipped, 1);
+        let rem = &restored.reminders[0];
+        assert_eq!(rem.chat_id, Set(2));
+        assert_eq!(rem.user_id, Set(Some(3)));
+        assert_eq!(rem.paused, Set(true));
+        assert_eq!(
+            NotificationPolicy::from_stored(
+                rem.policy.clone().unwrap().as_deref()
+            ),
+            NotificationPolicy {
+                silent: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            restored.cron_reminders[0].cron_expr,
+            Set("0 9 * * 1-5".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_long_description() {
+        let backup = Backup::new(
+            chrono_tz::UTC,
+            vec![reminder(None)],
+            vec![cron_reminder("0 9 * * *")],
+        );
+        let restored = backup.restore(1, 1, chrono_tz::UTC, 8);
+        assert_eq!(restored.reminders.len(), 1);
+        assert!(restored.cron_reminders.is_empty());
+        assert_eq!(restored.skipped, 1);
+    }
+}
@@ -0,0 +1,146 @@
+//! Bills are reminders described as `bill <amount> <name>`,
+//! e.g. `1/1m 10:00 bill 45.50 electricity`. Every time such a reminder
+//! fires, the amount is recorded to keep track of the yearly totals.
+
+use crate::entity::bill;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use std::collections::BTreeMap;
+
+/// Amount in cents and name of the bill if the description defines one
+pub(crate) fn parse_bill(desc: &str) -> Option<(i64, &str)> {
+    let rest = desc
+        .get(..5)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("bill "))
+        .map(|_| desc[5..].trim_start())?;
+    let (amount, name) = rest.split_once(char::is_whitespace)?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some((parse_amount(amount)?, name))
+}
+
+fn parse_amount(s: &str) -> Option<i64> {
+    let (units, cents) = s.split_once(['.', ',']).unwrap_or((s, "00"));
+    if units.is_empty()
+        || !(1..=2).contains(&cents.len())
+        || !units
+            .chars()
+            .chain(cents.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let cents: i64 = format!("{:0<2}", cents).parse().ok()?;
+    units
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(100)?
+        .checked_add(cents)
+}
+
+pub(crate) fn format_amount(cents: i64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+/// Local year of `now` along with its bounds in UTC
+pub(crate) fn year_bounds(
+    tz: Tz,
+    now: NaiveDateTime,
+) -> (i32, NaiveDateTime, NaiveDateTime) {
+    let year = tz.from_utc_datetime(&now).year();
+    let start_of = |year| {
+        let midnight = NaiveDate::from_ymd_opt(year, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        tz.from_local_datetime(&midnight)
+            .earliest()
+            .map_or(midnight, |dt| dt.naive_utc())
+    };
+    (year, start_of(year), start_of(year + 1))
+}
+
+/// Totals of a bill over some period
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Summary {
+    pub(crate) name: String,
+    pub(crate) total: i64,
+    pub(crate) paid: usize,
+    pub(crate) count: usize,
+}
+
+/// Summaries of the bills grouped by their names
+pub(crate) fn summarize(bills: &[bill::Model]) -> Vec<Summary> {
+    let mut summaries = BTreeMap::<&str, Summary>::new();
+    for bill in bills {
+        let summary = summaries.entry(&bill.name).or_insert_with(|| Summary {
+            name: bill.name.clone(),
+            ..Default::default()
+        });
+        summary.total += bill.amount;
+        summary.count += 1;
+        summary.paid += bill.paid as usize;
+    }
+    summaries.into_values().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("bill 45.50 electricity" => Some((4550, "electricity")) ; "with cents")]
+    #[test_case("Bill 45,5 water supply" => Some((4550, "water supply")) ; "comma and one digit")]
+    #[test_case("bill 120 rent" => Some((12000, "rent")) ; "whole")]
+    #[test_case("bill 45.505 electricity" => None ; "too precise")]
+    #[test_case("bill 45. electricity" => None ; "dangling point")]
+    #[test_case("bill 45.50" => None ; "no name")]
+    #[test_case("bill electricity" => None ; "no amount")]
+    #[test_case("billing 45 electricity" => None ; "other word")]
+    #[test_case("pay the bills" => None ; "not a bill")]
+    fn test_parse_bill(desc: &str) -> Option<(i64, &str)> {
+        parse_bill(desc)
+    }
+
+    #[test_case(4550 => "45.50")]
+    #[test_case(5 => "0.05")]
+    #[test_case(12000 => "120.00")]
+    fn test_format_amount(cents: i64) -> String {
+        format_amount(cents)
+    }
+
+    #[test]
+    fn test_summarize() {
+        let bill = |name: &str, amount, paid| bill::Model {
+            id: 0,
+            chat_id: 1,
+            name: name.to_owned(),
+            amount,
+            time: NaiveDateTime::default(),
+            paid,
+        };
+        assert_eq!(
+            summarize(&[
+                bill("rent", 100, true),
+                bill("electricity", 10, false),
+                bill("rent", 100, false),
+            ]),
+            vec![
+                Summary {
+                    name: "electricity".to_owned(),
+                    total: 10,
+                    paid: 0,
+                    count: 1,
+                },
+                Summary {
+                    name: "rent".to_owned(),
+                    total: 200,
+                    paid: 1,
+                    count: 2,
+                },
+            ]
+        );
+    }
+}
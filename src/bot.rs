@@ -1,435 +1,4874 @@
-use crate::cli::CLI;
+use crate::advance;
+use crate::bill::{format_amount, parse_bill, year_bounds};
+use crate::cli::{self, CLI};
+use crate::cron;
 #[cfg(not(test))]
 use crate::db::Database;
+use crate::db::LinkTarget;
 #[cfg(test)]
 use crate::db::MockDatabase as Database;
-use crate::entity::{cron_reminder, reminder};
+use crate::digest;
+use crate::entity::message_link::MessageRole;
+use crate::entity::reminder::MediaKind;
+use crate::entity::reminder_event::EventKind;
+use crate::entity::{bill, cron_reminder, occurrence, pomodoro, reminder};
 use crate::err::Error;
 use crate::format;
+use crate::generic_reminder::GenericReminder;
 use crate::handlers::{get_handler, Command, State};
+use crate::history;
+use crate::lang::Language;
+use crate::logging;
+use crate::maintenance;
+use crate::manage;
+use crate::meeting;
 use crate::parsers::now_time;
+use crate::policy::{self, NotificationPolicy};
+use crate::pomodoro::{progress, Progress};
+use crate::probe;
+use crate::retry;
 use crate::serializers::Pattern;
-use crate::tg::send_message;
-use crate::tz::get_user_timezone;
-use chrono::{NaiveDateTime, TimeDelta, Utc};
+use crate::settings::{self, CatchUp};
+use crate::share;
+use crate::tg::{self, TgResponse};
+use crate::tz::get_reminder_timezone;
+use crate::vacation;
+use chrono::{NaiveDateTime, TimeDelta, TimeZone, Timelike};
 use chrono_tz::Tz;
-use cron_parser::parse as parse_cron;
-use sea_orm::{ActiveValue::NotSet, IntoActiveModel};
-use serde_json::{from_str, to_string};
+use futures::stream::{self, StreamExt};
+use sea_orm::{ActiveValue::NotSet, IntoActiveModel, Set};
+use serde_json::from_str;
 use std::cmp::max;
+use std::collections::BTreeMap;
+use std::future::Future;
 use std::sync::Arc;
 use teloxide::dispatching::dialogue::serializer::Json;
 use teloxide::dispatching::dialogue::{ErasedStorage, SqliteStorage, Storage};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId};
 use teloxide::{prelude::*, utils::command::BotCommands};
 use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
-async fn send_reminder(
-    reminder: &reminder::Model,
-    user_timezone: Tz,
-    bot: &Bot,
-) -> Result<(), Error> {
-    let text = format::format_reminder(
-        &reminder.clone().into_active_model(),
-        user_timezone,
-    );
-    send_message(&text, bot, ChatId(reminder.chat_id))
-        .await
-        .map(|_| ())
-        .map_err(From::from)
-}
+/// Maximum number of times to resend a reminder that isn't marked done
+const MAX_NAGS: i32 = 10;
+/// Hours the "Snooze" button stays under a notification that isn't resent
+const SNOOZE_WINDOW_HOURS: i64 = 24;
+/// Days a notification pinned until it's marked done stays pinned at most
+/// if it isn't resent
+const PIN_WINDOW_DAYS: i64 = 7;
+/// Chats whose due reminders are sent at the same time at most
+const MAX_CONCURRENT_CHATS: usize = 16;
+/// Time a reminder may be overdue by to be delivered as usual, later it
+/// was missed while the bot was down
+const LATE_AFTER: TimeDelta = TimeDelta::minutes(5);
 
-async fn send_cron_reminder(
-    reminder: &cron_reminder::Model,
-    next_reminder: Option<&cron_reminder::Model>,
-    user_timezone: Tz,
-    bot: &Bot,
-) -> Result<(), Error> {
-    let text =
-        format::format_cron_reminder(reminder, next_reminder, user_timezone);
-    send_message(&text, bot, ChatId(reminder.chat_id))
-        .await
-        .map(|_| ())
-        .map_err(From::from)
+/// Chat to deliver a notification to, its forum topic if any, the
+/// message to reply to in it and the language of the buttons
+struct Destination {
+    chat_id: ChatId,
+    thread_id: Option<i32>,
+    reply: Option<tg::Reply>,
+    lang: Language,
 }
 
-async fn process_due_reminders(db: &Database, bot: &Bot) {
-    let reminders = db
-        .get_active_reminders()
-        .await
-        .expect("Failed to get reminders from database");
-    for reminder in reminders {
-        if let Some(user_id) = reminder.user_id.map(|x| UserId(x as u64)) {
-            if let Ok(Some(user_timezone)) =
-                get_user_timezone(db, user_id).await
-            {
-                let mut next_reminder = None;
-                if let Some(ref serialized) = reminder.pattern {
-                    let mut pattern: Pattern = from_str(serialized).unwrap();
-                    let lower_bound = max(reminder.time, now_time());
-                    if let Some(next_time) = pattern.next(lower_bound) {
-                        next_reminder = Some(reminder::Model {
-                            time: next_time,
-                            pattern: to_string(&pattern).ok(),
-                            ..reminder.clone()
-                        });
-                    }
-                }
-                if send_reminder(&reminder, user_timezone, bot).await.is_ok() {
-                    db.delete_reminder(reminder.id).await.unwrap_or_else(
-                        |err| {
-                            log::error!("{}", err);
-                        },
-                    );
-                    if let Some(next_reminder) = next_reminder {
-                        let mut next_reminder: reminder::ActiveModel =
-                            next_reminder.into();
-                        next_reminder.id = NotSet;
-                        db.insert_reminder(next_reminder)
-                            .await
-                            .map(|_| ())
-                            .unwrap_or_else(|err| {
-                                log::error!("{}", err);
-                            });
-                    }
-                }
-            }
-        }
-    }
-    let cron_reminders = db
-        .get_active_cron_reminders()
-        .await
-        .expect("Failed to get cron reminders from database");
-    for cron_reminder in cron_reminders {
-        if let Some(user_id) = cron_reminder.user_id.map(|x| UserId(x as u64)) {
-            if let Ok(Some(user_timezone)) =
-                get_user_timezone(db, user_id).await
-            {
-                let new_time = parse_cron(
-                    &cron_reminder.cron_expr,
-                    &Utc::now().with_timezone(&user_timezone),
-                )
-                .map(|user_time| user_time.with_timezone(&Utc));
-                let new_cron_reminder = match new_time {
-                    Ok(new_time) => Some(cron_reminder::Model {
-                        time: new_time.naive_utc(),
-                        ..cron_reminder.clone()
-                    }),
-                    Err(err) => {
-                        log::error!("{}", err);
-                        None
-                    }
-                };
-                match send_cron_reminder(
-                    &cron_reminder,
-                    new_cron_reminder.as_ref(),
-                    user_timezone,
-                    bot,
-                )
+impl Destination {
+    /// Chat of a reminder, replying to the message it's about if there's
+    /// one, otherwise quoting the description in the message it was
+    /// created from. The buttons are in the language of the creator.
+    async fn of(
+        chat_id: i64,
+        user_id: Option<i64>,
+        thread_id: Option<i32>,
+        target: LinkTarget,
+        desc: &str,
+        db: &Database,
+    ) -> Self {
+        let link = |role| async move {
+            db.get_message_links(target, role)
                 .await
-                {
-                    Ok(()) => {
-                        db.delete_cron_reminder(cron_reminder.id)
-                            .await
-                            .unwrap_or_else(|err| {
-                                log::error!("{}", err);
-                            });
-                        if let Some(new_cron_reminder) = new_cron_reminder {
-                            let mut new_cron_reminder: cron_reminder::ActiveModel = new_cron_reminder.into();
-                            new_cron_reminder.id = NotSet;
-                            db.insert_cron_reminder(new_cron_reminder)
-                                .await
-                                .map(|_| ())
-                                .unwrap_or_else(|err| {
-                                    log::error!("{}", err);
-                                });
-                        }
-                    }
-                    Err(err) => {
-                        log::error!("{}", err);
-                    }
-                }
+                .unwrap_or_else(|err| {
+                    tracing::error!("{}", err);
+                    vec![]
+                })
+                .into_iter()
+                .find(|link| link.chat_id == chat_id)
+        };
+        let reply = match link(MessageRole::Subject).await {
+            Some(subject) => Some(tg::Reply {
+                msg_id: MessageId(subject.msg_id),
+                quote: None,
+            }),
+            None => {
+                link(MessageRole::Creation).await.map(|creation| tg::Reply {
+                    msg_id: MessageId(creation.msg_id),
+                    quote: (!desc.is_empty()).then(|| {
+                        desc.chars().take(tg::QUOTE_MAX_LEN).collect()
+                    }),
+                })
             }
+        };
+        Self {
+            chat_id: ChatId(chat_id),
+            thread_id,
+            reply,
+            lang: notification_language(user_id, chat_id),
         }
     }
 }
 
-async fn deadline_from_datetime(dt: NaiveDateTime) -> Instant {
-    let now = now_time();
-
-    let duration = (dt - now).max(TimeDelta::zero()).to_std().unwrap();
-    Instant::now() + duration
+/// Buttons of a tracked notification: "Done" if it's resent or pinned
+/// until marked done, "Mute" if it's resent, "Snooze" if the creator
+/// wants to be reminded again later
+fn occurrence_buttons(
+    occ_id: i64,
+    policy: &NotificationPolicy,
+    lang: Language,
+) -> Vec<InlineKeyboardButton> {
+    let nags = policy.nag_interval().is_some();
+    (nags || policy.pin_until_done)
+        .then(|| tg::done_button(occ_id, lang))
+        .into_iter()
+        .chain(nags.then(|| tg::mute_button(occ_id, lang)))
+        .chain(
+            policy
+                .snooze
+                .map(|secs| tg::snooze_button(occ_id, secs, lang)),
+        )
+        .collect()
 }
 
-/// Wait for the next reminder to send or some change in the database.
-/// Send and update/delete reminders.
-async fn poll_reminders(db: Arc<Database>, bot: Bot) {
-    const DEFAULT_CHECK_INTERVAL: TimeDelta = TimeDelta::seconds(60);
-
-    let next_deadline = tokio::time::sleep_until(Instant::now());
-    tokio::pin!(next_deadline);
+/// How to deliver a reminder according to its creator's settings
+struct Delivery {
+    policy: NotificationPolicy,
+    /// End of the quiet hours to hold the reminder until
+    held_until: Option<NaiveDateTime>,
+}
 
-    let get_next_reminder_time = || async {
-        deadline_from_datetime(
-            db.get_next_reminder_time()
-                .await
-                .unwrap_or(None)
-                .unwrap_or(now_time() + DEFAULT_CHECK_INTERVAL),
-        )
-        .await
+/// Delivery options of a reminder completed with its creator's settings.
+/// The reminders of private chats due during the quiet hours are held
+/// until they end, or delivered silently if the creator prefers.
+async fn reminder_delivery(
+    stored_policy: Option<&str>,
+    user_id: Option<i64>,
+    chat_id: i64,
+    user_timezone: Tz,
+    db: &Database,
+) -> Delivery {
+    let mut delivery = Delivery {
+        policy: NotificationPolicy::from_stored(stored_policy),
+        held_until: None,
     };
-
-    loop {
-        tokio::select! {
-            _ = db.listen() => {
-                next_deadline.as_mut().reset(get_next_reminder_time().await);
-            }
-            () = &mut next_deadline => {
-                process_due_reminders(&db, &bot).await;
-
-                next_deadline.as_mut().reset(get_next_reminder_time().await);
-            }
+    let settings = match user_id {
+        Some(user_id) => db.get_user_settings(user_id).await,
+        None => Ok(None),
+    };
+    let settings = match settings {
+        Ok(Some(settings)) => settings,
+        Ok(None) => return delivery,
+        Err(err) => {
+            tracing::error!("{}", err);
+            return delivery;
+        }
+    };
+    delivery.policy = delivery.policy.with_settings(&settings);
+    if !ChatId(chat_id).is_user() {
+        return delivery;
+    }
+    let local = user_timezone.from_utc_datetime(&now_time()).naive_local();
+    if let Some(end) = settings::quiet_end(&settings, local) {
+        if settings.quiet_silent {
+            delivery.policy.silent = true;
+        } else {
+            delivery.held_until = Some(
+                user_timezone
+                    .from_local_datetime(&end)
+                    .earliest()
+                    .map_or(end, |end| end.naive_utc()),
+            );
         }
     }
+    delivery
 }
 
-async fn init_database() -> Database {
-    Database::new_with_path(&CLI.database)
-        .await
-        .unwrap_or_else(|err| {
-            panic!("Failed to connect to database {:?}: {}", CLI.database, err)
-        })
+/// Language of the notifications to a chat: the creator's, or the one
+/// of the user of a private chat if the creator isn't known
+fn notification_language(user_id: Option<i64>, chat_id: i64) -> Language {
+    settings::language(user_id.unwrap_or(chat_id))
 }
 
-async fn init_dialogue_storage() -> Arc<ErasedStorage<State>> {
-    SqliteStorage::open(CLI.database.to_str().unwrap(), Json)
-        .await
-        .unwrap_or_else(|err| {
-            panic!("Failed to connect to database {:?}: {}", CLI.database, err)
-        })
-        .erase()
+/// Time of the day a notification was due at in the creator's clock
+fn due_clock(
+    due: NaiveDateTime,
+    user_id: Option<i64>,
+    user_timezone: Tz,
+) -> String {
+    let due = user_timezone.from_utc_datetime(&due);
+    let clock_12h = user_id.is_some_and(settings::uses_12h_clock);
+    settings::format_clock(due.hour(), due.minute(), clock_12h)
 }
 
-pub(crate) async fn run() {
-    pretty_env_logger::init();
-    log::info!("Starting remindee-bot!");
-
-    let db = Arc::new(init_database().await);
-
-    db.apply_migrations()
-        .await
-        .expect("Failed to apply migrations");
-
-    let bot = Bot::new(&CLI.token);
-
-    bot.set_my_commands(Command::bot_commands())
-        .await
-        .expect("Failed to set bot commands");
-
-    let db_clone = db.clone();
-
-    tokio::spawn(poll_reminders(db_clone, bot.clone()));
-
-    let storage = init_dialogue_storage().await;
-
-    let handler = get_handler();
-
-    Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![storage, db])
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+/// Note on a notification held during the quiet hours of the time it
+/// was due at
+fn held_note(
+    held_since: NaiveDateTime,
+    user_id: Option<i64>,
+    chat_id: i64,
+    user_timezone: Tz,
+) -> String {
+    TgResponse::HeldDuringQuietHours(due_clock(
+        held_since,
+        user_id,
+        user_timezone,
+    ))
+    .to_string_in(notification_language(user_id, chat_id))
 }
 
-#[cfg(test)]
-mod test {
-    use std::sync::Arc;
+/// Note on a notification missed while the bot was down of the time it
+/// was due at
+fn missed_note(
+    due: NaiveDateTime,
+    user_id: Option<i64>,
+    chat_id: i64,
+    user_timezone: Tz,
+) -> String {
+    TgResponse::MissedDuringDowntime(due_clock(due, user_id, user_timezone))
+        .to_string_in(notification_language(user_id, chat_id))
+}
 
-    use crate::{
-        bot::Command, db::MockDatabase, entity::reminder,
-        generic_reminder::GenericReminder, handlers::get_handler,
-        parsers::test::TEST_TIMESTAMP, tg::TgResponse,
-    };
-    use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
-    use chrono_tz::Tz;
-    use dptree::deps;
-    use mockall::predicate::eq;
-    use sea_orm::IntoActiveModel;
-    use serial_test::serial;
-    use teloxide::{
-        dispatching::dialogue::InMemStorage,
-        prelude::*,
-        types::{
-            InlineKeyboardButton, InlineKeyboardButtonKind::CallbackData,
-            InlineKeyboardMarkup, MediaKind::Text, MediaText, MessageCommon,
-            MessageKind,
-        },
-        utils::command::BotCommands,
-    };
-    use teloxide_tests::{
-        IntoUpdate, MockBot, MockCallbackQuery, MockMessageText,
+/// How the creator wants a reminder missed while the bot was down to be
+/// delivered, `None` if it's on time. The retries of the reminders that
+/// failed to be sent weren't missed.
+async fn catch_up(
+    due: NaiveDateTime,
+    failures: i32,
+    user_id: Option<i64>,
+    db: &Database,
+) -> Option<CatchUp> {
+    let late = now_time() - due;
+    if late <= LATE_AFTER || failures > 0 {
+        return None;
+    }
+    let settings = match user_id {
+        Some(user_id) => db.get_user_settings(user_id).await,
+        None => Ok(None),
     };
+    match settings {
+        Ok(Some(settings)) => Some(settings::catch_up(&settings, late)),
+        Ok(None) => Some(CatchUp::Late),
+        Err(err) => {
+            tracing::error!("{}", err);
+            Some(CatchUp::Late)
+        }
+    }
+}
 
-    use super::State;
+/// Entry of a reminder in the summary of the ones missed while the bot
+/// was down
+fn catch_up_entry(
+    due: NaiveDateTime,
+    user_id: Option<i64>,
+    chat_id: i64,
+    desc: &str,
+    user_timezone: Tz,
+) -> String {
+    let day = user_timezone.from_utc_datetime(&due).format("%d.%m");
+    TgResponse::CatchUpEntry(
+        format!("{} {}", day, due_clock(due, user_id, user_timezone)),
+        desc.to_owned(),
+    )
+    .to_string_in(notification_language(user_id, chat_id))
+    .replace('@', "@\u{200B}")
+}
 
-    fn basic_mock_reminder() -> reminder::Model {
-        reminder::Model {
-            id: 1,
-            chat_id: 1,
-            time: NaiveDateTime::new(
-                NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
-                NaiveTime::from_hms_opt(0, 1, 2).unwrap(),
-            ),
-            desc: "".to_owned(),
-            user_id: None,
-            paused: false,
-            pattern: None,
-            msg_id: None,
-            reply_id: None,
+/// Sum up the reminders of a chat missed while the bot was down
+async fn send_catch_up_summary(chat_id: i64, entries: Vec<String>, bot: &Bot) {
+    let header = TgResponse::CatchUpSummary(entries.len())
+        .to_string_in(notification_language(None, chat_id));
+    for text in format::split_lines(
+        std::iter::once(header).chain(entries),
+        tg::MESSAGE_MAX_LEN,
+    ) {
+        if let Err(err) = tg::send_message(&text, bot, ChatId(chat_id)).await {
+            tracing::error!("{}", err);
         }
     }
+}
 
-    fn mock_timezone_name() -> String {
-        "Europe/Amsterdam".to_owned()
+/// Deliver a copy of a reminder due during the quiet hours once they end.
+/// The messages linked to a one-time reminder are linked to the copy.
+async fn hold(
+    copy: reminder::ActiveModel,
+    target: LinkTarget,
+    one_time: bool,
+    db: &Database,
+) -> Result<(), Error> {
+    let copy = db.insert_reminder(copy).await?;
+    if one_time {
+        db.move_message_links(target, LinkTarget::Reminder(copy.id.unwrap()))
+            .await?;
     }
+    Ok(())
+}
 
-    fn mock_timezone() -> Tz {
-        mock_timezone_name().parse::<Tz>().unwrap()
+/// Deliver a notification and keep track of it until it's marked done
+/// if the policy asks for nagging, or while it can be snoozed
+async fn notify(
+    text: &str,
+    desc: &str,
+    policy: NotificationPolicy,
+    mut buttons: Vec<InlineKeyboardButton>,
+    db: &Database,
+    bot: &Bot,
+    dest: Destination,
+) -> Result<Message, Error> {
+    let chat_id = dest.chat_id;
+    let msg = policy::deliver(
+        text,
+        &policy,
+        bot,
+        chat_id,
+        tg::thread(dest.thread_id),
+        dest.reply.as_ref(),
+    )
+    .await?;
+    let tracked_for = policy
+        .nag_interval()
+        .or(policy
+            .pin_until_done
+            .then(|| TimeDelta::days(PIN_WINDOW_DAYS)))
+        .or(policy.snooze.map(|_| TimeDelta::hours(SNOOZE_WINDOW_HOURS)));
+    if let Some(interval) = tracked_for {
+        let occ = db
+            .insert_occurrence(occurrence::ActiveModel {
+                id: NotSet,
+                chat_id: Set(chat_id.0),
+                msg_id: Set(msg.id.0),
+                text: Set(text.to_owned()),
+                policy: Set(policy.to_stored()),
+                nag_time: Set(now_time() + interval),
+                nag_count: Set(0),
+                desc: Set(Some(desc.to_owned())),
+                done_time: Set(None),
+                note: Set(None),
+                meeting_time: Set(None),
+                muted_until: Set(None),
+                thread_id: Set(dest.thread_id),
+            })
+            .await?;
+        buttons.splice(
+            0..0,
+            occurrence_buttons(occ.id.unwrap(), &policy, dest.lang),
+        );
+    }
+    if buttons.is_empty() {
+        return Ok(msg);
     }
+    tg::attach_markup(InlineKeyboardMarkup::new(vec![buttons]), bot, &msg)
+        .await
+        .map_err(From::from)
+}
 
-    fn mock_storage() -> Arc<InMemStorage<State>> {
-        InMemStorage::<State>::new()
+/// Record the bill defined by a reminder's description, if any.
+/// Return its id and the summary of the year's payments for it.
+async fn issue_bill(
+    desc: &str,
+    chat_id: ChatId,
+    user_timezone: Tz,
+    db: &Database,
+) -> Result<Option<(i64, String)>, Error> {
+    let Some((amount, name)) = parse_bill(desc) else {
+        return Ok(None);
+    };
+    let now = now_time();
+    let (year, from, until) = year_bounds(user_timezone, now);
+    let issued = db
+        .insert_bill(bill::ActiveModel {
+            id: NotSet,
+            chat_id: Set(chat_id.0),
+            name: Set(name.to_owned()),
+            amount: Set(amount),
+            time: Set(now),
+            paid: Set(false),
+        })
+        .await?;
+    let total: i64 = db
+        .get_chat_bills(chat_id.0, from, until)
+        .await?
+        .iter()
+        .filter(|bill| bill.name == name)
+        .map(|bill| bill.amount)
+        .sum();
+    let summary = TgResponse::BillIssued(
+        format_amount(amount),
+        format_amount(total),
+        year,
+    );
+    Ok(Some((
+        issued.id.unwrap(),
+        summary.to_string_in(notification_language(None, chat_id.0)),
+    )))
+}
+
+/// Deliver a reminder's notification along with the bill it defines
+async fn notify_reminder(
+    text: String,
+    desc: &str,
+    policy: NotificationPolicy,
+    user_timezone: Tz,
+    db: &Database,
+    bot: &Bot,
+    dest: Destination,
+) -> Result<Message, Error> {
+    let Some((bill_id, summary)) =
+        issue_bill(desc, dest.chat_id, user_timezone, db).await?
+    else {
+        return notify(&text, desc, policy, vec![], db, bot, dest).await;
+    };
+    let text = format!("{}\n\n{}", text, summary);
+    let buttons = vec![tg::paid_button(bill_id, dest.lang)];
+    let res = notify(&text, desc, policy, buttons, db, bot, dest).await;
+    if res.is_err() {
+        // The reminder will be sent again along with a new bill
+        db.delete_bill(bill_id)
+            .await
+            .unwrap_or_else(|err| tracing::error!("{}", err));
     }
+    res
+}
 
-    fn mock_bot<T>(db: MockDatabase, update: T) -> MockBot
-    where
-        T: IntoUpdate,
+/// Send the file attached to a reminder in reply to its notification.
+/// The notification is delivered even if the file can't be sent anymore.
+async fn send_attachment(
+    kind: Option<MediaKind>,
+    file_id: Option<&str>,
+    notification: &Message,
+    bot: &Bot,
+) {
+    let (Some(kind), Some(file_id)) = (kind, file_id) else {
+        return;
+    };
+    if let Err(err) = tg::send_media(
+        kind,
+        file_id,
+        bot,
+        notification.chat.id,
+        notification.id,
+    )
+    .await
     {
-        let bot = MockBot::new(update, get_handler());
-        bot.dependencies(deps![mock_storage(), Arc::new(db)]);
-        bot
+        tracing::error!("{}", err);
     }
+}
 
-    #[tokio::test]
-    async fn test_help() {
-        let message = MockMessageText::new().text("/help");
-        let db = MockDatabase::new();
-        let bot = mock_bot(db, message);
-        bot.dispatch_and_check_last_text(&Command::descriptions().to_string())
-            .await;
+/// Copy the delivered notification to the other chats the reminder is
+/// broadcast to, without the buttons that only work in its own chat
+async fn broadcast(
+    target: LinkTarget,
+    delivery: &Message,
+    db: &Database,
+    bot: &Bot,
+) {
+    let chat_ids = match db.get_broadcast_chats(target).await {
+        Ok(chat_ids) => chat_ids,
+        Err(err) => {
+            tracing::error!("{}", err);
+            return;
+        }
+    };
+    for chat_id in chat_ids {
+        if let Err(err) = bot
+            .copy_message(ChatId(chat_id), delivery.chat.id, delivery.id)
+            .reply_markup(InlineKeyboardMarkup::default())
+            .await
+        {
+            tracing::warn!(
+                "Failed to broadcast {:?} to {}: {}",
+                target,
+                chat_id,
+                err
+            );
+        }
     }
+}
 
-    #[tokio::test]
-    async fn test_start() {
-        let message = MockMessageText::new().text("/start");
-        let db = MockDatabase::new();
-        let bot = mock_bot(db, message);
-        bot.dispatch_and_check_last_text(&TgResponse::Hello.to_string())
-            .await;
+/// Move the messages linked to a fired reminder to its next occurrence
+/// and link the delivered notification to it as well, unless it was held
+async fn link_next_occurrence(
+    db: &Database,
+    delivery: Option<&Message>,
+    old_target: LinkTarget,
+    new_target: LinkTarget,
+) {
+    let res = async {
+        db.move_message_links(old_target, new_target).await?;
+        let Some(delivery) = delivery else {
+            return Ok(());
+        };
+        db.insert_message_link(
+            delivery.chat.id.0,
+            delivery.id.0,
+            MessageRole::Delivery,
+            new_target,
+        )
+        .await
     }
-
-    #[tokio::test]
-    async fn test_start_group() {
-        let mut message = MockMessageText::new().text("/start");
-        message.chat.id.0 = -1;
-        let db = MockDatabase::new();
-        let bot = mock_bot(db, message);
-        bot.dispatch_and_check_last_text(&TgResponse::HelloGroup.to_string())
-            .await;
+    .await;
+    if let Err(err) = res {
+        tracing::error!("{}", err);
     }
+}
 
-    #[tokio::test]
-    async fn test_timezone() {
-        let message = MockMessageText::new().text("/timezone");
-        let mut db = MockDatabase::new();
-        db.expect_get_user_timezone_name()
-            .returning(|_| Ok(Some(mock_timezone_name())));
-        let bot = mock_bot(db, message);
-        bot.dispatch_and_check_last_text(
-            &TgResponse::ChosenTimezone(mock_timezone_name()).to_string(),
+/// Announce the end of a focus session's phase
+async fn send_pomodoro_phase(
+    session: pomodoro::Model,
+    db: &Database,
+    bot: &Bot,
+) -> Result<Message, Error> {
+    let session = pomodoro::Model {
+        phase: session.phase + 1,
+        ..session
+    };
+    let chat_id = ChatId(session.chat_id);
+    let response = match progress(&session) {
+        Progress::WorkDone(round) => {
+            TgResponse::PomodoroWorkDone(round, session.rounds, session.rest)
+        }
+        Progress::BreakDone(round) => {
+            TgResponse::PomodoroBreakDone(round, session.rounds, session.work)
+        }
+        Progress::Finished => TgResponse::PomodoroFinished(session.rounds),
+    };
+    let msg = policy::deliver(
+        &response.to_string_in(notification_language(None, session.chat_id)),
+        &NotificationPolicy::default(),
+        bot,
+        chat_id,
+        None,
+        None,
+    )
+    .await?;
+    if progress(&session) == Progress::Finished {
+        db.delete_pomodoro(session.id).await?;
+    } else {
+        db.set_pomodoro_phase(session.id, session.phase).await?;
+        tg::edit_markup(
+            tg::pomodoro_markup(
+                session.id,
+                notification_language(None, session.chat_id),
+            ),
+            bot,
+            msg.id,
+            chat_id,
         )
-        .await;
+        .await?;
     }
+    Ok(msg)
+}
 
-    #[tokio::test]
-    async fn test_set_timezone() {
-        let message = MockMessageText::new().text("/settimezone");
-        let db = MockDatabase::new();
-        let bot = mock_bot(db, message);
-        bot.dispatch_and_check_last_text(
-            &TgResponse::SelectTimezone.to_string(),
+/// Invite the chat to a meeting and keep track of the answers
+/// until they're summed up
+async fn invite(
+    text: &str,
+    reminder: &reminder::Model,
+    db: &Database,
+    bot: &Bot,
+) -> Result<Message, Error> {
+    let chat_id = ChatId(reminder.chat_id);
+    let policy = NotificationPolicy::from_stored(reminder.policy.as_deref());
+    let msg = policy::deliver(
+        text,
+        &policy,
+        bot,
+        chat_id,
+        tg::thread(reminder.thread_id),
+        None,
+    )
+    .await?;
+    let occ = db
+        .insert_occurrence(occurrence::ActiveModel {
+            id: NotSet,
+            chat_id: Set(chat_id.0),
+            msg_id: Set(msg.id.0),
+            text: Set(text.to_owned()),
+            policy: Set(reminder.policy.clone()),
+            nag_time: Set(meeting::summary_time(reminder.time)),
+            nag_count: Set(0),
+            desc: Set(Some(reminder.desc.clone())),
+            done_time: Set(None),
+            note: Set(None),
+            meeting_time: Set(Some(reminder.time)),
+            muted_until: Set(None),
+            thread_id: Set(reminder.thread_id),
+        })
+        .await?;
+    tg::attach_markup(
+        meeting::rsvp_markup(
+            occ.id.unwrap(),
+            notification_language(None, reminder.chat_id),
+        ),
+        bot,
+        &msg,
+    )
+    .await
+    .map_err(From::from)
+}
+
+/// Post the answers to a meeting, it can't be answered anymore
+async fn sum_up_meeting(
+    occ: occurrence::Model,
+    start: NaiveDateTime,
+    db: &Database,
+    bot: &Bot,
+) {
+    let res = async {
+        let rsvps = db.get_rsvps(occ.id).await?;
+        let text = meeting::summary(
+            occ.desc.as_deref().unwrap_or_default(),
+            start,
+            now_time(),
+            &rsvps,
+            notification_language(None, occ.chat_id),
+        );
+        tg::send_message(&text, bot, ChatId(occ.chat_id)).await?;
+        Ok::<_, Error>(())
+    }
+    .await;
+    if let Err(err) = res {
+        tracing::error!("{}", err);
+    }
+    db.delete_occurrence(occ.id).await.unwrap_or_else(|err| {
+        tracing::error!("{}", err);
+    });
+}
+
+/// Deliver a reminder, `None` if it's held during the quiet hours. A
+/// reminder missed while the bot was down is noted to be late.
+async fn send_reminder(
+    reminder: &reminder::Model,
+    missed: bool,
+    user_timezone: Tz,
+    db: &Database,
+    bot: &Bot,
+) -> Result<Option<Message>, Error> {
+    if let Some(pomodoro_id) = reminder.pomodoro_id {
+        if let Some(session) = db.get_pomodoro(pomodoro_id).await? {
+            return send_pomodoro_phase(session, db, bot).await.map(Some);
+        }
+    }
+    let mut text = format::format_reminder(
+        &reminder.clone().into_active_model(),
+        user_timezone,
+    );
+    if reminder.meeting {
+        return invite(&text, reminder, db, bot).await.map(Some);
+    }
+    let delivery = reminder_delivery(
+        reminder.policy.as_deref(),
+        reminder.user_id,
+        reminder.chat_id,
+        user_timezone,
+        db,
+    )
+    .await;
+    if let Some(until) = delivery.held_until {
+        let copy = reminder::ActiveModel {
+            id: NotSet,
+            time: Set(until),
+            pattern: Set(None),
+            version: Set(0),
+            advance: Set(None),
+            advance_time: Set(None),
+            held_since: Set(Some(reminder.held_since.unwrap_or(reminder.time))),
+            ..reminder.clone().into_active_model()
+        };
+        let target = LinkTarget::Reminder(reminder.id);
+        hold(copy, target, reminder.pattern.is_none(), db).await?;
+        return Ok(None);
+    }
+    if let Some(held_since) = reminder.held_since {
+        text = format!(
+            "{}\n\n{}",
+            text,
+            held_note(
+                held_since,
+                reminder.user_id,
+                reminder.chat_id,
+                user_timezone,
+            )
+        );
+    }
+    if missed {
+        text = format!(
+            "{}\n\n{}",
+            text,
+            missed_note(
+                reminder.time,
+                reminder.user_id,
+                reminder.chat_id,
+                user_timezone,
+            )
+        );
+    }
+    let dest = Destination::of(
+        reminder.chat_id,
+        reminder.user_id,
+        reminder.thread_id,
+        LinkTarget::Reminder(reminder.id),
+        &reminder.desc,
+        db,
+    )
+    .await;
+    let msg = notify_reminder(
+        text,
+        &reminder.desc,
+        delivery.policy.with_priority(),
+        user_timezone,
+        db,
+        bot,
+        dest,
+    )
+    .await?;
+    send_attachment(
+        reminder.media_kind,
+        reminder.media_file_id.as_deref(),
+        &msg,
+        bot,
+    )
+    .await;
+    Ok(Some(msg))
+}
+
+/// Deliver a cron reminder, `None` if it's held during the quiet hours.
+/// A reminder missed while the bot was down is noted to be late.
+async fn send_cron_reminder(
+    reminder: &cron_reminder::Model,
+    next_reminder: Option<&cron_reminder::Model>,
+    missed: bool,
+    user_timezone: Tz,
+    db: &Database,
+    bot: &Bot,
+) -> Result<Option<Message>, Error> {
+    let delivery = reminder_delivery(
+        reminder.policy.as_deref(),
+        reminder.user_id,
+        reminder.chat_id,
+        user_timezone,
+        db,
+    )
+    .await;
+    if let Some(until) = delivery.held_until {
+        let copy = reminder::ActiveModel {
+            id: NotSet,
+            chat_id: Set(reminder.chat_id),
+            time: Set(until),
+            desc: Set(reminder.desc.clone()),
+            user_id: Set(reminder.user_id),
+            paused: Set(false),
+            pattern: Set(None),
+            policy: Set(reminder.policy.clone()),
+            version: Set(0),
+            pomodoro_id: Set(None),
+            target: Set(None),
+            meeting: Set(false),
+            advance: Set(None),
+            advance_time: Set(None),
+            media_kind: Set(reminder.media_kind),
+            media_file_id: Set(reminder.media_file_id.clone()),
+            held_since: Set(Some(reminder.time)),
+            failures: Set(0),
+            retry_time: Set(None),
+            thread_id: Set(reminder.thread_id),
+            deleted_time: Set(None),
+        };
+        let target = LinkTarget::CronReminder(reminder.id);
+        hold(copy, target, next_reminder.is_none(), db).await?;
+        return Ok(None);
+    }
+    let mut text =
+        format::format_cron_reminder(reminder, next_reminder, user_timezone);
+    if missed {
+        text = format!(
+            "{}\n\n{}",
+            text,
+            missed_note(
+                reminder.time,
+                reminder.user_id,
+                reminder.chat_id,
+                user_timezone,
+            )
+        );
+    }
+    let dest = Destination::of(
+        reminder.chat_id,
+        reminder.user_id,
+        reminder.thread_id,
+        LinkTarget::CronReminder(reminder.id),
+        &reminder.desc,
+        db,
+    )
+    .await;
+    let msg = notify_reminder(
+        text,
+        &reminder.desc,
+        delivery.policy,
+        user_timezone,
+        db,
+        bot,
+        dest,
+    )
+    .await?;
+    send_attachment(
+        reminder.media_kind,
+        reminder.media_file_id.as_deref(),
+        &msg,
+        bot,
+    )
+    .await;
+    Ok(Some(msg))
+}
+
+/// Delete the bot's menus and confirmations whose time has come
+async fn delete_expired_messages(db: &Database, bot: &Bot) {
+    let messages = match db.get_expired_messages().await {
+        Ok(messages) => messages,
+        Err(err) => {
+            tracing::error!("{}", err);
+            return;
+        }
+    };
+    for msg in messages {
+        // The message may have been deleted by hand already
+        tg::delete_message(bot, ChatId(msg.chat_id), MessageId(msg.msg_id))
+            .await
+            .unwrap_or_else(|err| tracing::warn!("{}", err));
+        db.delete_expiring_message(msg.id)
+            .await
+            .unwrap_or_else(|err| tracing::error!("{}", err));
+    }
+}
+
+/// Resend a notification that wasn't marked done in time,
+/// or sum up the answers to a meeting
+async fn nag(occ: occurrence::Model, db: &Database, bot: &Bot) {
+    let chat_id = ChatId(occ.chat_id);
+    // The previous message shouldn't be acknowledged anymore
+    tg::edit_markup(
+        InlineKeyboardMarkup::default(),
+        bot,
+        MessageId(occ.msg_id),
+        chat_id,
+    )
+    .await
+    .unwrap_or_else(|err| tracing::warn!("{}", err));
+    if let Some(start) = occ.meeting_time {
+        return sum_up_meeting(occ, start, db, bot).await;
+    }
+
+    let policy = NotificationPolicy::from_stored(occ.policy.as_deref());
+    if policy.pin_until_done {
+        tg::unpin_message(bot, chat_id, MessageId(occ.msg_id))
+            .await
+            .unwrap_or_else(|err| tracing::warn!("{}", err));
+    }
+    let interval = match policy.nag_interval() {
+        Some(interval) if occ.nag_count < MAX_NAGS => interval,
+        _ => {
+            db.delete_occurrence(occ.id).await.unwrap_or_else(|err| {
+                tracing::error!("{}", err);
+            });
+            return;
+        }
+    };
+    let markup = InlineKeyboardMarkup::new(vec![occurrence_buttons(
+        occ.id,
+        &policy,
+        notification_language(None, occ.chat_id),
+    )]);
+    let res = async {
+        let msg = policy::deliver(
+            &occ.text,
+            &policy,
+            bot,
+            chat_id,
+            tg::thread(occ.thread_id),
+            None,
         )
-        .await;
+        .await?;
+        tg::edit_markup(markup, bot, msg.id, chat_id).await?;
+        db.update_occurrence(occurrence::Model {
+            msg_id: msg.id.0,
+            nag_time: now_time() + interval,
+            nag_count: occ.nag_count + 1,
+            muted_until: None,
+            ..occ.clone()
+        })
+        .await
+    }
+    .await;
+    if let Err(err) = res {
+        tracing::error!("{}", err);
+        // Don't retry forever a notification that can't be delivered
+        db.delete_occurrence(occ.id).await.unwrap_or_else(|err| {
+            tracing::error!("{}", err);
+        });
+    }
+}
+
+/// Move the paused reminders that are overdue to their next occurrences,
+/// so they aren't fired late once resumed. The ones without occurrences
+/// left are removed with a notice to their chats.
+async fn reanchor_paused_reminders(db: &Database, bot: &Bot) {
+    let reminders = match db.get_overdue_paused_reminders().await {
+        Ok(reminders) => reminders,
+        Err(err) => {
+            tracing::error!("{}", err);
+            return;
+        }
+    };
+    let now = now_time();
+    for reminder in reminders {
+        let mut pattern = reminder
+            .pattern
+            .as_deref()
+            .and_then(|serialized| from_str::<Pattern>(serialized).ok());
+        let next_time =
+            pattern.as_mut().and_then(|pattern| pattern.next_after(now));
+        let res = match (next_time, pattern) {
+            (Some(time), Some(pattern)) => db
+                .reschedule_reminder(reminder::Model {
+                    time,
+                    pattern: pattern.to_stored(time),
+                    advance_time: reminder.advance.and_then(|lead| {
+                        advance::warning_time(time, lead, now)
+                    }),
+                    ..reminder
+                })
+                .await
+                .map(|_| ()),
+            _ => retire_reminder(reminder, db, bot).await,
+        };
+        if let Err(err) = res {
+            tracing::error!("{}", err);
+        }
     }
+}
 
-    macro_rules! resp {
-        ($bot:expr, $field:ident, $($subfields:tt)+) => {
-            $bot.get_responses().$field.iter().map(|m| (m.$($subfields)+).clone()).collect::<Vec<_>>()
+/// Remove a reminder that won't fire anymore and tell its chat
+async fn retire_reminder(
+    reminder: reminder::Model,
+    db: &Database,
+    bot: &Bot,
+) -> Result<(), Error> {
+    db.purge_reminder(reminder.id).await?;
+    let user_timezone =
+        get_reminder_timezone(db, reminder.user_id, reminder.chat_id)
+            .await?
+            .unwrap_or(Tz::UTC);
+    let chat_id = ChatId(reminder.chat_id);
+    let lang = notification_language(reminder.user_id, reminder.chat_id);
+    let response = TgResponse::PausedReminderEnded(
+        reminder
+            .into_active_model()
+            .to_unescaped_string(user_timezone),
+    );
+    tg::send_silent_message(&response.to_string_in(lang), bot, chat_id).await?;
+    Ok(())
+}
+
+/// Link a group notification of a recurring reminder to the private chat
+/// with the bot, where the members of the group can manage the reminder
+async fn offer_managing(delivery: &Message, lang: Language, bot: &Bot) {
+    if delivery.chat.id.is_user() {
+        return;
+    }
+    let res = async {
+        let username = tg::bot_username(bot).await?;
+        let Some(button) =
+            manage::link_button(username, delivery.chat.id, delivery.id, lang)
+        else {
+            return Ok(());
         };
+        let markup = delivery
+            .reply_markup()
+            .cloned()
+            .unwrap_or_default()
+            .append_row(vec![button]);
+        tg::edit_markup(markup, bot, delivery.id, delivery.chat.id).await
+    }
+    .await;
+    if let Err(err) = res {
+        tracing::error!("{}", err);
     }
+}
 
-    struct MockMarkup {
-        media_text: String,
-        markup: InlineKeyboardMarkup,
+/// End the vacations that are over and tell their chats, summing up
+/// the missed occurrences if asked to. Return the ends of the vacations
+/// of the chats still on vacation.
+async fn end_vacations(
+    db: &Database,
+    bot: &Bot,
+) -> BTreeMap<i64, NaiveDateTime> {
+    let vacations = match db.get_vacations().await {
+        Ok(vacations) => vacations,
+        Err(err) => {
+            tracing::error!("{}", err);
+            return BTreeMap::new();
+        }
+    };
+    let now = now_time();
+    let (ended, ongoing): (Vec<_>, Vec<_>) = vacations
+        .into_iter()
+        .partition(|vacation| vacation.until <= now);
+    for vacation in ended {
+        let res = async {
+            // Don't tell again if the summary can't be delivered
+            db.delete_vacation(vacation.chat_id).await?;
+            let user_timezone = get_reminder_timezone(
+                db,
+                Some(vacation.user_id),
+                vacation.chat_id,
+            )
+            .await?
+            .unwrap_or(Tz::UTC);
+            let missed = db
+                .get_missed_events(vacation.chat_id, vacation.since)
+                .await?;
+            for text in vacation::messages(
+                &vacation,
+                missed,
+                user_timezone,
+                settings::language(vacation.user_id),
+            ) {
+                tg::send_message(&text, bot, ChatId(vacation.chat_id)).await?;
+            }
+            Ok::<_, Error>(())
+        }
+        .await;
+        if let Err(err) = res {
+            tracing::error!("{}", err);
+        }
     }
+    ongoing
+        .into_iter()
+        .map(|vacation| (vacation.chat_id, vacation.until))
+        .collect()
+}
 
-    impl From<MockMarkup> for MessageKind {
-        fn from(val: MockMarkup) -> Self {
-            MessageKind::Common(MessageCommon {
-                author_signature: None,
-                forward_origin: None,
-                reply_to_message: None,
-                external_reply: None,
-                quote: None,
-                edit_date: None,
-                media_kind: Text(MediaText {
-                    text: val.media_text,
-                    entities: vec![],
-                    link_preview_options: None,
+/// Warn about the reminders coming up soon
+async fn send_advance_warnings(
+    vacations: &BTreeMap<i64, NaiveDateTime>,
+    db: &Database,
+    bot: &Bot,
+) {
+    let reminders = match db.get_due_advance_warnings().await {
+        Ok(reminders) => reminders,
+        Err(err) => {
+            tracing::error!("{}", err);
+            return;
+        }
+    };
+    for reminder in reminders {
+        let res = async {
+            // Don't warn again if the warning can't be delivered
+            db.clear_advance_time(reminder.id).await?;
+            if vacations.contains_key(&reminder.chat_id) {
+                return Ok(());
+            }
+            let user_timezone =
+                get_reminder_timezone(db, reminder.user_id, reminder.chat_id)
+                    .await?
+                    .unwrap_or(Tz::UTC);
+            let response = TgResponse::AdvanceWarning(
+                advance::format_lead(reminder.advance.unwrap_or_default()),
+                reminder
+                    .clone()
+                    .into_active_model()
+                    .to_unescaped_string(user_timezone),
+            );
+            tg::send_message(
+                &response.to_string_in(notification_language(
+                    reminder.user_id,
+                    reminder.chat_id,
+                )),
+                bot,
+                ChatId(reminder.chat_id),
+            )
+            .await?;
+            Ok::<_, Error>(())
+        }
+        .await;
+        if let Err(err) = res {
+            tracing::error!("{}", err);
+        }
+    }
+}
+
+/// Send the digests of the upcoming reminders that are due, the ones
+/// of the chats on vacation are only rescheduled
+async fn send_digests(
+    vacations: &BTreeMap<i64, NaiveDateTime>,
+    db: &Database,
+    bot: &Bot,
+) {
+    let digests = match db.get_due_digests().await {
+        Ok(digests) => digests,
+        Err(err) => {
+            tracing::error!("{}", err);
+            return;
+        }
+    };
+    let now = now_time();
+    for digest in digests {
+        let res = async {
+            let user_timezone =
+                get_reminder_timezone(db, Some(digest.user_id), digest.chat_id)
+                    .await?
+                    .unwrap_or(Tz::UTC);
+            let chat_id = ChatId(digest.chat_id);
+            let period = digest::period(&digest);
+            let style = settings::date_style(digest.user_id);
+            // Don't send again if the digest can't be delivered
+            db.insert_or_update_digest(digest::reschedule(
+                digest,
+                user_timezone,
+                now,
+            ))
+            .await?;
+            if vacations.contains_key(&chat_id.0) {
+                return Ok(());
+            }
+            let reminders = db
+                .search_reminders(
+                    chat_id.0,
+                    digest::filter(period, user_timezone, now),
+                )
+                .await?;
+            for text in
+                digest::messages(period, reminders, user_timezone, style, now)
+                    .unwrap_or_default()
+            {
+                tg::send_message(&text, bot, chat_id).await?;
+            }
+            Ok::<_, Error>(())
+        }
+        .await;
+        if let Err(err) = res {
+            tracing::error!("{}", err);
+        }
+    }
+}
+
+/// Send the reminder with the future unless its sending was interrupted,
+/// in which case it may have been sent already and is only rescheduled.
+/// The reminder is marked as being sent until it's rescheduled, a failed
+/// sending removes the mark to retry it.
+async fn send_once(
+    target: LinkTarget,
+    interrupted: &[LinkTarget],
+    db: &Database,
+    send: impl Future<Output = Result<Option<Message>, Error>>,
+) -> Result<Option<Message>, Error> {
+    if interrupted.contains(&target) {
+        tracing::warn!(
+            "Not resending {:?} interrupted while being sent",
+            target
+        );
+        return Ok(None);
+    }
+    db.start_sending(target).await?;
+    let sent = send.await;
+    if sent.is_err() {
+        db.stop_sending(target).await.unwrap_or_else(|err| {
+            tracing::error!("{}", err);
+        });
+    }
+    sent
+}
+
+/// Count the failure to send the reminder and schedule the next attempt,
+/// unless it's given up on after too many failures or the chat is gone
+async fn retry_later(
+    target: LinkTarget,
+    chat_id: i64,
+    desc: &str,
+    failures: i32,
+    err: Error,
+    db: &Database,
+) {
+    tracing::error!("{}", err);
+    if retry::is_unreachable(&err) {
+        tracing::warn!(
+            "Chat {} is unreachable, holding its reminders",
+            chat_id
+        );
+        history::record(db, chat_id, desc, EventKind::Failed).await;
+        db.fail_chat_reminders(chat_id)
+            .await
+            .unwrap_or_else(|err| tracing::error!("{}", err));
+        return;
+    }
+    let (failures, retry_time) =
+        retry::after_failure(failures, &err, now_time());
+    if retry_time.is_none() {
+        tracing::warn!(
+            "Gave up sending {:?} after {} failures",
+            target,
+            failures
+        );
+        history::record(db, chat_id, desc, EventKind::Failed).await;
+    }
+    db.record_send_failure(target, failures, retry_time)
+        .await
+        .unwrap_or_else(|err| tracing::error!("{}", err));
+}
+
+/// Send a due reminder and set its next occurrence if it recurs. On
+/// vacation the occurrence is only kept in the history to sum it up.
+/// Return the reminder's line of the catch-up summary if it was missed
+/// while the bot was down and its creator prefers a summary to it.
+#[tracing::instrument(skip_all, fields(reminder_id = reminder.id))]
+async fn send_due_reminder(
+    reminder: reminder::Model,
+    user_timezone: Tz,
+    on_vacation: bool,
+    interrupted: &[LinkTarget],
+    db: &Database,
+    bot: &Bot,
+) -> Option<String> {
+    let mut next_reminder = None;
+    if let Some(ref serialized) = reminder.pattern {
+        let mut pattern: Pattern = from_str(serialized).unwrap();
+        let lower_bound = max(reminder.time, now_time());
+        if let Some(next_time) = pattern.next(lower_bound) {
+            next_reminder = Some(reminder::Model {
+                time: next_time,
+                pattern: pattern.to_stored(next_time),
+                advance_time: reminder.advance.and_then(|lead| {
+                    advance::warning_time(next_time, lead, now_time())
                 }),
-                reply_markup: Some(val.markup),
-                is_automatic_forward: false,
-                has_protected_content: false,
+                failures: 0,
+                retry_time: None,
+                ..reminder.clone()
+            });
+        }
+    }
+    let catch_up = if on_vacation {
+        None
+    } else {
+        catch_up(reminder.time, reminder.failures, reminder.user_id, db).await
+    };
+    let mut summed_up = false;
+    let send = async {
+        let desc = &reminder.desc;
+        if on_vacation {
+            history::record(db, reminder.chat_id, desc, EventKind::Missed)
+                .await;
+            return Ok(None);
+        }
+        match catch_up {
+            Some(CatchUp::Skip) => {
+                history::record(db, reminder.chat_id, desc, EventKind::Skipped)
+                    .await;
+                return Ok(None);
+            }
+            Some(CatchUp::Summary) => {
+                summed_up = true;
+                return Ok(None);
+            }
+            Some(CatchUp::Late) | None => {}
+        }
+        let missed = catch_up.is_some();
+        send_reminder(&reminder, missed, user_timezone, db, bot).await
+    };
+    match send_once(LinkTarget::Reminder(reminder.id), interrupted, db, send)
+        .await
+    {
+        Ok(msg) => {
+            if let Some(ref msg) = msg {
+                history::record(
+                    db,
+                    reminder.chat_id,
+                    &reminder.desc,
+                    EventKind::Sent,
+                )
+                .await;
+                broadcast(LinkTarget::Reminder(reminder.id), msg, db, bot)
+                    .await;
+            }
+            let next_reminder = next_reminder.map(|next_reminder| {
+                let mut next_reminder: reminder::ActiveModel =
+                    next_reminder.into();
+                next_reminder.id = NotSet;
+                next_reminder
+            });
+            match db.advance_reminder(reminder.id, next_reminder).await {
+                Ok(Some(next_reminder)) => {
+                    link_next_occurrence(
+                        db,
+                        msg.as_ref(),
+                        LinkTarget::Reminder(reminder.id),
+                        LinkTarget::Reminder(next_reminder.id.unwrap()),
+                    )
+                    .await;
+                    if let (Some(msg), None) = (msg, reminder.pomodoro_id) {
+                        let lang = notification_language(
+                            reminder.user_id,
+                            reminder.chat_id,
+                        );
+                        offer_managing(&msg, lang, bot).await;
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => tracing::error!("{}", err),
+            }
+        }
+        Err(err) => {
+            retry_later(
+                LinkTarget::Reminder(reminder.id),
+                reminder.chat_id,
+                &reminder.desc,
+                reminder.failures,
+                err,
+                db,
+            )
+            .await;
+        }
+    }
+    summed_up.then(|| {
+        catch_up_entry(
+            reminder.time,
+            reminder.user_id,
+            reminder.chat_id,
+            &reminder.desc,
+            user_timezone,
+        )
+    })
+}
+
+/// Send a due cron reminder and set its next time. On vacation the
+/// occurrence is only kept in the history to sum it up. Return the
+/// reminder's line of the catch-up summary if it was missed while the
+/// bot was down and its creator prefers a summary to it.
+#[tracing::instrument(skip_all, fields(cron_reminder_id = cron_reminder.id))]
+async fn send_due_cron_reminder(
+    cron_reminder: cron_reminder::Model,
+    user_timezone: Tz,
+    on_vacation: bool,
+    interrupted: &[LinkTarget],
+    db: &Database,
+    bot: &Bot,
+) -> Option<String> {
+    let new_time = cron::next_time(
+        &cron_reminder.cron_expr,
+        max(cron_reminder.time, now_time()),
+        user_timezone,
+    );
+    let new_cron_reminder = match new_time {
+        Ok(new_time) => Some(cron_reminder::Model {
+            time: new_time,
+            failures: 0,
+            retry_time: None,
+            ..cron_reminder.clone()
+        }),
+        Err(err) => {
+            tracing::error!("{}", err);
+            None
+        }
+    };
+    let catch_up = if on_vacation {
+        None
+    } else {
+        catch_up(
+            cron_reminder.time,
+            cron_reminder.failures,
+            cron_reminder.user_id,
+            db,
+        )
+        .await
+    };
+    let mut summed_up = false;
+    let send = async {
+        let (chat_id, desc) = (cron_reminder.chat_id, &cron_reminder.desc);
+        if on_vacation {
+            history::record(db, chat_id, desc, EventKind::Missed).await;
+            return Ok(None);
+        }
+        match catch_up {
+            Some(CatchUp::Skip) => {
+                history::record(db, chat_id, desc, EventKind::Skipped).await;
+                return Ok(None);
+            }
+            Some(CatchUp::Summary) => {
+                summed_up = true;
+                return Ok(None);
+            }
+            Some(CatchUp::Late) | None => {}
+        }
+        send_cron_reminder(
+            &cron_reminder,
+            new_cron_reminder.as_ref(),
+            catch_up.is_some(),
+            user_timezone,
+            db,
+            bot,
+        )
+        .await
+    };
+    match send_once(
+        LinkTarget::CronReminder(cron_reminder.id),
+        interrupted,
+        db,
+        send,
+    )
+    .await
+    {
+        Ok(msg) => {
+            if let Some(ref msg) = msg {
+                history::record(
+                    db,
+                    cron_reminder.chat_id,
+                    &cron_reminder.desc,
+                    EventKind::Sent,
+                )
+                .await;
+                broadcast(
+                    LinkTarget::CronReminder(cron_reminder.id),
+                    msg,
+                    db,
+                    bot,
+                )
+                .await;
+            }
+            let new_cron_reminder =
+                new_cron_reminder.map(|new_cron_reminder| {
+                    let mut new_cron_reminder: cron_reminder::ActiveModel =
+                        new_cron_reminder.into();
+                    new_cron_reminder.id = NotSet;
+                    new_cron_reminder
+                });
+            match db
+                .advance_cron_reminder(cron_reminder.id, new_cron_reminder)
+                .await
+            {
+                Ok(Some(new_cron_reminder)) => {
+                    link_next_occurrence(
+                        db,
+                        msg.as_ref(),
+                        LinkTarget::CronReminder(cron_reminder.id),
+                        LinkTarget::CronReminder(new_cron_reminder.id.unwrap()),
+                    )
+                    .await;
+                    if let Some(msg) = msg {
+                        let lang = notification_language(
+                            cron_reminder.user_id,
+                            cron_reminder.chat_id,
+                        );
+                        offer_managing(&msg, lang, bot).await;
+                    }
+                }
+                Ok(None) => {}
+                Err(err) => tracing::error!("{}", err),
+            }
+        }
+        Err(err) => {
+            retry_later(
+                LinkTarget::CronReminder(cron_reminder.id),
+                cron_reminder.chat_id,
+                &cron_reminder.desc,
+                cron_reminder.failures,
+                err,
+                db,
+            )
+            .await;
+        }
+    }
+    summed_up.then(|| {
+        catch_up_entry(
+            cron_reminder.time,
+            cron_reminder.user_id,
+            cron_reminder.chat_id,
+            &cron_reminder.desc,
+            user_timezone,
+        )
+    })
+}
+
+/// Send the due reminders and nags. The first call at startup also
+/// catches up with the reminders that ended while the bot was down.
+async fn process_due_reminders(db: &Database, bot: &Bot) {
+    let vacations = end_vacations(db, bot).await;
+    reanchor_paused_reminders(db, bot).await;
+    send_advance_warnings(&vacations, db, bot).await;
+    send_digests(&vacations, db, bot).await;
+    delete_expired_messages(db, bot).await;
+
+    let interrupted = db
+        .get_interrupted_sending()
+        .await
+        .expect("Failed to get the reminders being sent from database");
+    let reminders = db
+        .get_active_reminders()
+        .await
+        .expect("Failed to get reminders from database");
+    let cron_reminders = db
+        .get_active_cron_reminders()
+        .await
+        .expect("Failed to get cron reminders from database");
+    let owners = reminders
+        .iter()
+        .map(|rem| (rem.user_id, rem.chat_id))
+        .chain(cron_reminders.iter().map(|rem| (rem.user_id, rem.chat_id)))
+        .collect();
+    let timezones = match db.get_reminder_timezones(owners).await {
+        Ok(timezones) => timezones,
+        Err(err) => {
+            tracing::error!("{}", err);
+            return;
+        }
+    };
+
+    // The chats are sent to concurrently so that a slow one doesn't hold
+    // up the rest, each chat's reminders are sent in order
+    let mut chats: BTreeMap<i64, (Vec<_>, Vec<_>)> = BTreeMap::new();
+    for reminder in reminders {
+        chats.entry(reminder.chat_id).or_default().0.push(reminder);
+    }
+    for cron_reminder in cron_reminders {
+        chats
+            .entry(cron_reminder.chat_id)
+            .or_default()
+            .1
+            .push(cron_reminder);
+    }
+    stream::iter(chats)
+        .map(|(chat_id, (reminders, cron_reminders))| {
+            let (interrupted, timezones) = (&interrupted, &timezones);
+            let on_vacation = vacations.contains_key(&chat_id);
+            let span = tracing::info_span!("chat", chat_id);
+            async move {
+                let mut missed = vec![];
+                for reminder in reminders {
+                    if let Some(user_timezone) =
+                        timezones.get(reminder.user_id, reminder.chat_id)
+                    {
+                        let entry = send_due_reminder(
+                            reminder,
+                            user_timezone,
+                            on_vacation,
+                            interrupted,
+                            db,
+                            bot,
+                        )
+                        .await;
+                        missed.extend(entry);
+                    }
+                }
+                for cron_reminder in cron_reminders {
+                    if let Some(user_timezone) = timezones
+                        .get(cron_reminder.user_id, cron_reminder.chat_id)
+                    {
+                        let entry = send_due_cron_reminder(
+                            cron_reminder,
+                            user_timezone,
+                            on_vacation,
+                            interrupted,
+                            db,
+                            bot,
+                        )
+                        .await;
+                        missed.extend(entry);
+                    }
+                }
+                if !missed.is_empty() {
+                    send_catch_up_summary(chat_id, missed, bot).await;
+                }
+            }
+            .instrument(span)
+        })
+        .buffer_unordered(MAX_CONCURRENT_CHATS)
+        .collect::<()>()
+        .await;
+
+    let occurrences = db
+        .get_due_occurrences()
+        .await
+        .expect("Failed to get occurrences from database");
+    for occ in occurrences {
+        // The notifications sent before the vacation are resent after it
+        if let Some(&until) = vacations.get(&occ.chat_id) {
+            db.update_occurrence(occurrence::Model {
+                nag_time: until,
+                ..occ
             })
+            .await
+            .unwrap_or_else(|err| tracing::error!("{}", err));
+            continue;
         }
+        nag(occ, db, bot).await;
+    }
+}
+
+async fn deadline_from_datetime(dt: NaiveDateTime) -> Instant {
+    let now = now_time();
+
+    let duration = (dt - now).max(TimeDelta::zero()).to_std().unwrap();
+    Instant::now() + duration
+}
+
+/// Wait for the next reminder to send or some change in the database.
+/// Send and update/delete reminders. Once shut down, the due reminders
+/// being sent are finished before returning.
+async fn poll_reminders(
+    db: Arc<Database>,
+    bot: Bot,
+    shutdown: CancellationToken,
+) {
+    const DEFAULT_CHECK_INTERVAL: TimeDelta = TimeDelta::seconds(60);
+
+    let next_deadline = tokio::time::sleep_until(Instant::now());
+    tokio::pin!(next_deadline);
+
+    // The deadline is measured by the monotonic clock, so don't sleep
+    // for longer than the interval in case the system clock is adjusted
+    let get_next_reminder_time = || async {
+        let max_time = now_time() + DEFAULT_CHECK_INTERVAL;
+        deadline_from_datetime(
+            db.get_next_reminder_time()
+                .await
+                .unwrap_or(None)
+                .map_or(max_time, |time| time.min(max_time)),
+        )
+        .await
+    };
+
+    loop {
+        tokio::select! {
+            () = shutdown.cancelled() => break,
+            _ = db.listen() => {
+                next_deadline.as_mut().reset(get_next_reminder_time().await);
+            }
+            () = &mut next_deadline => {
+                process_due_reminders(&db, &bot).await;
+
+                next_deadline.as_mut().reset(get_next_reminder_time().await);
+            }
+        }
+    }
+    tracing::info!("Stopped sending reminders");
+}
+
+/// Stop the dispatcher on SIGTERM like it stops on Ctrl-C
+#[cfg(unix)]
+async fn shutdown_on_sigterm(token: teloxide::dispatching::ShutdownToken) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let Ok(mut sigterm) = signal(SignalKind::terminate()) else {
+        tracing::warn!("Failed to listen for SIGTERM");
+        return;
+    };
+    sigterm.recv().await;
+    tracing::info!("SIGTERM received, shutting down");
+    match token.shutdown() {
+        Ok(stopped) => stopped.await,
+        Err(err) => tracing::warn!("{}", err),
+    }
+}
+
+pub(crate) async fn init_database() -> Database {
+    Database::new_with_path(&CLI.database)
+        .await
+        .unwrap_or_else(|err| {
+            panic!("Failed to connect to database {:?}: {}", CLI.database, err)
+        })
+}
+
+async fn init_dialogue_storage() -> Arc<ErasedStorage<State>> {
+    SqliteStorage::open(CLI.database.to_str().unwrap(), Json)
+        .await
+        .unwrap_or_else(|err| {
+            panic!("Failed to connect to database {:?}: {}", CLI.database, err)
+        })
+        .erase()
+}
+
+pub(crate) async fn run() {
+    logging::init(CLI.log_format);
+    tracing::info!("Starting remindee-bot!");
+
+    let db = Arc::new(init_database().await);
+
+    db.apply_migrations()
+        .await
+        .expect("Failed to apply migrations");
+
+    for user_id in db
+        .get_12h_clock_users()
+        .await
+        .expect("Failed to load the settings")
+    {
+        settings::set_clock(user_id, true);
+    }
+    for settings in db
+        .get_day_part_users()
+        .await
+        .expect("Failed to load the settings")
+    {
+        settings::set_day_parts(&settings);
+    }
+    for user_id in db
+        .get_sunday_first_users()
+        .await
+        .expect("Failed to load the settings")
+    {
+        settings::set_week_start(user_id, true);
+    }
+    for (user_id, language) in db
+        .get_user_languages()
+        .await
+        .expect("Failed to load the languages")
+    {
+        settings::set_language(user_id, language);
+    }
+
+    // the token is only optional with a subcommand
+    let token = CLI.token.as_deref().expect("Bot token is required");
+    let bot = match CLI.api_url {
+        Some(ref api_url) => {
+            tracing::info!("Using Bot API server at {}", api_url);
+            Bot::new(token).set_api_url(api_url.clone())
+        }
+        None => Bot::new(token),
+    };
+
+    bot.set_my_commands(
+        Command::bot_commands()
+            .into_iter()
+            .filter(|cmd| cli::command_enabled(&cmd.command)),
+    )
+    .await
+    .expect("Failed to set bot commands");
+
+    let shutdown = CancellationToken::new();
+    let poller =
+        tokio::spawn(poll_reminders(db.clone(), bot.clone(), shutdown.clone()));
+
+    if let Some(probe_chat) = CLI.probe_chat_id {
+        tracing::info!(
+            "Checking for the deleted messages in chat {}",
+            probe_chat
+        );
+        tokio::spawn(probe::poll(
+            db.clone(),
+            bot.clone(),
+            ChatId(probe_chat),
+            shutdown.clone(),
+        ));
+    }
+
+    if cli::feature_enabled(cli::Feature::Maintenance) {
+        tokio::spawn(maintenance::poll(
+            db.clone(),
+            CLI.maintenance_days,
+            shutdown.clone(),
+        ));
+    }
+
+    if let Some(addr) = cli::http_addr() {
+        let server = share::serve(addr, db.clone()).unwrap_or_else(|err| {
+            panic!("Failed to serve HTTP on {}: {}", addr, err)
+        });
+        tracing::info!(
+            "Serving shared reminder lists and the reminder API on {}",
+            addr
+        );
+        tokio::spawn(async move {
+            if let Err(err) = server.await {
+                tracing::error!("HTTP server failed: {}", err);
+            }
+        });
+    }
+
+    let storage = init_dialogue_storage().await;
+
+    let handler = get_handler();
+
+    let mut dispatcher = Dispatcher::builder(bot, handler)
+        .dependencies(dptree::deps![storage, db])
+        .enable_ctrlc_handler()
+        .build();
+    #[cfg(unix)]
+    tokio::spawn(shutdown_on_sigterm(dispatcher.shutdown_token()));
+    dispatcher.dispatch().await;
+
+    // Let the reminders being sent be rescheduled before exiting
+    shutdown.cancel();
+    if let Err(err) = poller.await {
+        tracing::error!("Reminder polling failed: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use crate::{
+        backup::{self, Backup},
+        bot::Command,
+        controller::EditMode,
+        db::LinkTarget,
+        db::MockDatabase,
+        digest,
+        entity::message_link::{self, MessageRole},
+        entity::reminder::MediaKind,
+        entity::reminder_event::{self, EventKind},
+        entity::{
+            api_token, bill, calendar_feed, cron_reminder, expiring_message,
+            occurrence, pomodoro, reminder, share_link, template,
+            user_settings, vacation,
+        },
+        flaky::{Failure, FlakyApi},
+        generic_reminder::GenericReminder,
+        grammar,
+        handlers::get_handler,
+        lang::Language,
+        meeting,
+        migration::DbErr,
+        parsers::{self, now_time, test::TEST_TIMESTAMP},
+        serializers::Pattern,
+        settings, text_menu,
+        tg::{self, TgResponse},
+    };
+    use chrono::{
+        NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc,
+    };
+    use chrono_tz::Tz;
+    use dptree::deps;
+    use mockall::predicate::{always, eq};
+    use sea_orm::{IntoActiveModel, Set, TryIntoModel};
+    use serial_test::serial;
+    use teloxide::{
+        dispatching::dialogue::InMemStorage,
+        prelude::*,
+        types::{
+            InlineKeyboardButton, InlineKeyboardButtonKind::CallbackData,
+            InlineKeyboardMarkup, MediaKind::Text, MediaText, MessageCommon,
+            MessageId, MessageKind, MessageOrigin, ThreadId,
+        },
+        utils::command::BotCommands,
+    };
+    use teloxide_tests::{
+        IntoUpdate, MockBot, MockCallbackQuery, MockMessageDocument,
+        MockMessagePhoto, MockMessageText, MockPhotoSize, MockUser,
+    };
+
+    use super::State;
+
+    fn basic_mock_reminder() -> reminder::Model {
+        reminder::Model {
+            id: 1,
+            chat_id: 1,
+            time: NaiveDateTime::new(
+                NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                NaiveTime::from_hms_opt(0, 1, 2).unwrap(),
+            ),
+            desc: "".to_owned(),
+            user_id: None,
+            paused: false,
+            pattern: None,
+            policy: None,
+            version: 0,
+            pomodoro_id: None,
+            target: None,
+            meeting: false,
+            advance: None,
+            advance_time: None,
+            media_kind: None,
+            media_file_id: None,
+            held_since: None,
+            failures: 0,
+            retry_time: None,
+            deleted_time: None,
+            thread_id: None,
+        }
+    }
+
+    /// Mock the pages of the chat's reminders offered to choose from
+    fn expect_reminders_pages(
+        db: &mut MockDatabase,
+        rems: Vec<reminder::Model>,
+    ) {
+        let count = rems.len() as u64;
+        db.expect_get_sorted_reminders_page().returning(
+            move |_, offset, limit| {
+                Ok(rems
+                    .iter()
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                    .map(|rem| -> Box<dyn GenericReminder> {
+                        Box::new(rem.clone().into_active_model())
+                    })
+                    .collect())
+            },
+        );
+        db.expect_count_chat_reminders()
+            .returning(move |_| Ok(count));
+    }
+
+    fn set_mock_now() {
+        *TEST_TIMESTAMP.write().unwrap() = mock_timezone()
+            .with_ymd_and_hms(2024, 6, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp();
+    }
+
+    fn mock_timezone_name() -> String {
+        "Europe/Amsterdam".to_owned()
+    }
+
+    fn mock_timezone() -> Tz {
+        mock_timezone_name().parse::<Tz>().unwrap()
+    }
+
+    fn mock_storage() -> Arc<InMemStorage<State>> {
+        InMemStorage::<State>::new()
+    }
+
+    fn mock_bot<T>(db: MockDatabase, update: T) -> MockBot
+    where
+        T: IntoUpdate,
+    {
+        let bot = MockBot::new(update, get_handler());
+        bot.dependencies(deps![mock_storage(), Arc::new(db)]);
+        bot
+    }
+
+    macro_rules! resp {
+        ($bot:expr, $field:ident, $($subfields:tt)+) => {
+            $bot.get_responses().$field.iter().map(|m| (m.$($subfields)+).clone()).collect::<Vec<_>>()
+        };
+    }
+
+    struct MockMarkup {
+        media_text: String,
+        markup: InlineKeyboardMarkup,
+    }
+
+    impl From<MockMarkup> for MessageKind {
+        fn from(val: MockMarkup) -> Self {
+            MessageKind::Common(MessageCommon {
+                author_signature: None,
+                forward_origin: None,
+                reply_to_message: None,
+                external_reply: None,
+                quote: None,
+                edit_date: None,
+                media_kind: Text(MediaText {
+                    text: val.media_text,
+                    entities: vec![],
+                    link_preview_options: None,
+                }),
+                reply_markup: Some(val.markup),
+                is_automatic_forward: false,
+                has_protected_content: false,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_help() {
+        let message = MockMessageText::new().text("/help");
+        let db = MockDatabase::new();
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&Command::descriptions().to_string())
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_start() {
+        let message = MockMessageText::new().text("/start");
+        let mut db = MockDatabase::new();
+        db.expect_resume_failed_reminders().returning(|_| Ok(0));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![
+                TgResponse::Hello.to_string(),
+                TgResponse::SelectTimezone.to_string()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_resumes_failed() {
+        let message = MockMessageText::new().text("/start");
+        let mut db = MockDatabase::new();
+        db.expect_resume_failed_reminders()
+            .with(eq(12345678))
+            .times(1)
+            .returning(|_| Ok(2));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![
+                TgResponse::Hello.to_string(),
+                TgResponse::FailedRemindersResumed(2).to_string(),
+                TgResponse::SelectTimezone.to_string()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_onboarding() {
+        let message = MockMessageText::new().text("/start");
+        let mut db = MockDatabase::new();
+        db.expect_resume_failed_reminders().returning(|_| Ok(0));
+        db.expect_insert_or_update_user_timezone()
+            .with(always(), eq(mock_timezone_name()))
+            .returning(|_, _| Ok(()));
+        db.expect_insert_or_update_user_language()
+            .with(always(), eq("en"))
+            .returning(|_, _| Ok(()));
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data(format!("seltz::tz::{}", mock_timezone_name()))
+                .message(bot.get_responses().sent_messages[1].clone()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, kind),
+            vec![MockMarkup {
+                media_text: TgResponse::OnboardingSelectLanguage(
+                    mock_timezone_name()
+                )
+                .to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: [
+                        ("🇬🇧 English", "en"),
+                        ("🇷🇺 Русский", "ru"),
+                        ("🇩🇪 Deutsch", "de"),
+                        ("🇪🇸 Español", "es"),
+                    ]
+                    .map(|(name, code)| {
+                        vec![InlineKeyboardButton {
+                            text: name.to_string(),
+                            kind: CallbackData(format!(
+                                "onboard::lang::{}",
+                                code
+                            )),
+                        }]
+                    })
+                    .to_vec(),
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("onboard::lang::en")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![TgResponse::OnboardingSampleReminder.to_string()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("onboard::skip")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::OnboardingDone.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_onboarding_app_language() {
+        let message = MockMessageText::new().text("/start");
+        let mut db = MockDatabase::new();
+        db.expect_resume_failed_reminders().returning(|_| Ok(0));
+        db.expect_insert_or_update_user_timezone()
+            .with(always(), eq(mock_timezone_name()))
+            .returning(|_, _| Ok(()));
+        db.expect_insert_or_update_user_language()
+            .with(always(), eq("en"))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+
+        let mut query = MockCallbackQuery::new()
+            .data(format!("seltz::tz::{}", mock_timezone_name()))
+            .message(bot.get_responses().sent_messages[1].clone());
+        query.from.language_code = Some("en-GB".to_owned());
+        bot.update(query);
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![
+                TgResponse::OnboardingLanguageDetected(
+                    mock_timezone_name(),
+                    "🇬🇧 English".to_owned()
+                )
+                .to_string(),
+                TgResponse::OnboardingSampleReminder.to_string()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_timezone_link() {
+        let message = MockMessageText::new().text(format!(
+            "/start tz_{}",
+            mock_timezone_name().replace('/', "_")
+        ));
+        let mut db = MockDatabase::new();
+        db.expect_resume_failed_reminders().returning(|_| Ok(0));
+        db.expect_insert_or_update_user_timezone()
+            .with(always(), eq(mock_timezone_name()))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![
+                TgResponse::HelloShortcut.to_string(),
+                TgResponse::OnboardingSelectLanguage(mock_timezone_name())
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_group() {
+        let mut message = MockMessageText::new().text("/start");
+        message.chat.id.0 = -1;
+        let mut db = MockDatabase::new();
+        db.expect_resume_failed_reminders()
+            .with(eq(-1))
+            .returning(|_| Ok(0));
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&TgResponse::HelloGroup.to_string())
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_timezone() {
+        let message = MockMessageText::new().text("/timezone");
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::ChosenTimezone(mock_timezone_name()).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_set_timezone() {
+        let message = MockMessageText::new().text("/settimezone");
+        let db = MockDatabase::new();
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SelectTimezone.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_search_timezone() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_or_update_user_timezone()
+            .with(always(), eq("Europe/Berlin"))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let bot = mock_bot(db, MockMessageText::new().text("/settimezone"));
+        bot.dispatch().await;
+        assert!(matches!(
+            bot.get_state::<State>().await,
+            State::TimezoneSearch
+        ));
+
+        bot.update(MockMessageText::new().text("berlin"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::FoundTimezones.to_string(),
+        )
+        .await;
+        let found = bot.get_responses().sent_messages[0].clone();
+        assert_eq!(
+            found.reply_markup().unwrap().inline_keyboard[0][0].text,
+            "Europe/Berlin (UTC+02:00)"
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("seltz::tz::Europe/Berlin")
+                .message(found),
+        );
+        bot.dispatch().await;
+        assert!(matches!(bot.get_state::<State>().await, State::Default));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_delete() {
+        set_mock_now();
+        let message = MockMessageText::new().text("/delete");
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let rem = basic_mock_reminder();
+        expect_reminders_pages(&mut db, vec![rem.clone()]);
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem_clone = rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem_clone.clone())));
+        db.expect_trash_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(()));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: vec![
+                        vec![InlineKeyboardButton {
+                            text: "01.01 01:01 <>".to_string(),
+                            kind: CallbackData(
+                                "delrem::rem_alt::1".to_string(),
+                            ),
+                        },],
+                        vec![],
+                    ],
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("delrem::page::1")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, edited_messages_reply_markup, message.kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: vec![vec![InlineKeyboardButton {
+                        text: "⬅️".to_string(),
+                        kind: CallbackData("delrem::page::0".to_string(),),
+                    },],],
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new().data("delrem::page::0").message(
+                bot.get_responses().edited_messages_reply_markup[0]
+                    .message
+                    .clone(),
+            ),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, edited_messages_reply_markup, message.kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: vec![
+                        vec![InlineKeyboardButton {
+                            text: "01.01 01:01 <>".to_string(),
+                            kind: CallbackData(
+                                "delrem::rem_alt::1".to_string(),
+                            ),
+                        },],
+                        vec![],
+                    ],
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new().data("delrem::rem_alt::1").message(
+                bot.get_responses().edited_messages_reply_markup[0]
+                    .message
+                    .clone(),
+            ),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessDelete(
+                rem.into_active_model().to_unescaped_string(mock_timezone()),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_delete_still_one_page() {
+        set_mock_now();
+        const REMINDERS_COUNT: i64 = 45;
+        let message = MockMessageText::new().text("/delete");
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let mut rems = vec![];
+        for i in 1..=REMINDERS_COUNT {
+            let mut rem = basic_mock_reminder();
+            rem.id = i;
+            rems.push(rem);
+        }
+        expect_reminders_pages(&mut db, rems.clone());
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        for rem in rems.iter() {
+            let rem_clone = rem.clone();
+            db.expect_get_reminder()
+                .with(eq(rem.id))
+                .returning(move |_| Ok(Some(rem_clone.clone())));
+            db.expect_trash_reminder()
+                .with(eq(rem.id))
+                .returning(move |_| Ok(()));
+        }
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        let mut page0_buttons = (1..=REMINDERS_COUNT)
+            .map(|i| {
+                vec![InlineKeyboardButton {
+                    text: "01.01 01:01 <>".to_string().to_string(),
+                    kind: CallbackData(
+                        format!("delrem::rem_alt::{}", i).to_string(),
+                    ),
+                }]
+            })
+            .collect::<Vec<_>>();
+        page0_buttons.push(vec![]);
+        assert_eq!(
+            resp!(bot, sent_messages, kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: page0_buttons.clone(),
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("delrem::page::1")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, edited_messages_reply_markup, message.kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: vec![vec![InlineKeyboardButton {
+                        text: "⬅️".to_string(),
+                        kind: CallbackData("delrem::page::0".to_string(),),
+                    },],],
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new().data("delrem::page::0").message(
+                bot.get_responses().edited_messages_reply_markup[0]
+                    .message
+                    .clone(),
+            ),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, edited_messages_reply_markup, message.kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: page0_buttons
+                },
+            }
+            .into()]
+        );
+
+        let rem = rems[0].clone();
+        bot.update(
+            MockCallbackQuery::new().data("delrem::rem_alt::1").message(
+                bot.get_responses().edited_messages_reply_markup[0]
+                    .message
+                    .clone(),
+            ),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessDelete(
+                rem.into_active_model().to_unescaped_string(mock_timezone()),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_delete_two_pages() {
+        set_mock_now();
+        const REMINDERS_COUNT: i64 = 46;
+        const PAGE_REMINDERS_COUNT: i64 = 45;
+        let message = MockMessageText::new().text("/delete");
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let mut rems = vec![];
+        for i in 1..=REMINDERS_COUNT {
+            let mut rem = basic_mock_reminder();
+            rem.id = i;
+            rem.desc = i.to_string();
+            rems.push(rem);
+        }
+        expect_reminders_pages(&mut db, rems.clone());
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        for rem in rems.iter() {
+            let rem_clone = rem.clone();
+            db.expect_get_reminder()
+                .with(eq(rem.id))
+                .returning(move |_| Ok(Some(rem_clone.clone())));
+            db.expect_trash_reminder()
+                .with(eq(rem.id))
+                .returning(move |_| Ok(()));
+        }
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        let mut page0_buttons = (1..=PAGE_REMINDERS_COUNT)
+            .map(|i| {
+                vec![InlineKeyboardButton {
+                    text: format!("01.01 01:01 <{}>", i).to_string(),
+                    kind: CallbackData(
+                        format!("delrem::rem_alt::{}", i).to_string(),
+                    ),
+                }]
+            })
+            .collect::<Vec<_>>();
+        page0_buttons.push(vec![InlineKeyboardButton {
+            text: "➡️".to_string(),
+            kind: CallbackData("delrem::page::1".to_string()),
+        }]);
+        let mut page1_buttons = (PAGE_REMINDERS_COUNT + 1..=REMINDERS_COUNT)
+            .map(|i| {
+                vec![InlineKeyboardButton {
+                    text: format!("01.01 01:01 <{}>", i).to_string(),
+                    kind: CallbackData(
+                        format!("delrem::rem_alt::{}", i).to_string(),
+                    ),
+                }]
+            })
+            .collect::<Vec<_>>();
+        page1_buttons.push(vec![InlineKeyboardButton {
+            text: "⬅️".to_string(),
+            kind: CallbackData("delrem::page::0".to_string()),
+        }]);
+        assert_eq!(
+            resp!(bot, sent_messages, kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: page0_buttons.clone(),
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("delrem::page::1")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, edited_messages_reply_markup, message.kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: page1_buttons,
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new().data("delrem::page::0").message(
+                bot.get_responses().edited_messages_reply_markup[0]
+                    .message
+                    .clone(),
+            ),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, edited_messages_reply_markup, message.kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: page0_buttons
+                },
+            }
+            .into()]
+        );
+
+        let rem = rems[0].clone();
+        bot.update(
+            MockCallbackQuery::new().data("delrem::rem_alt::1").message(
+                bot.get_responses().edited_messages_reply_markup[0]
+                    .message
+                    .clone(),
+            ),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessDelete(
+                rem.into_active_model().to_unescaped_string(mock_timezone()),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_list_no_timezone() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name().returning(|_| Ok(None));
+        let message = MockMessageText::new().text("/list");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SelectTimezone.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_list_no_reminders() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_search_reminders().returning(|_, _| Ok(vec![]));
+        let message = MockMessageText::new().text("/list");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::RemindersListHeader.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_list_database_error() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_search_reminders().returning(|_, _| {
+            Err(DbErr::Custom("connection lost".to_owned()).into())
+        });
+        let message = MockMessageText::new().text("/list");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::InternalError.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_list_all() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_account_links().returning(|_| Ok(vec![]));
+        db.expect_search_reminders().never();
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_search_user_reminders().returning(move |_, _, _| {
+            Ok(vec![Box::new(rem_clone.clone().into_active_model())])
+        });
+        let message = MockMessageText::new().text("/list all");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&format!(
+            "{}\n{}\n{}\n{}",
+            TgResponse::AllChatsListHeader,
+            TgResponse::ChatListHeader(
+                TgResponse::PrivateChatTitle.to_unescaped_string()
+            ),
+            TgResponse::ListDayHeader("Mon 01.01".to_owned()),
+            rem.into_active_model().to_string(tz)
+        ))
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_link_no_linked_chats() {
+        let mut db = MockDatabase::new();
+        db.expect_get_account_links().returning(|_| Ok(vec![]));
+        let message = MockMessageText::new().text("/link");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::NoLinkedChats.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_link_expired() {
+        let mut db = MockDatabase::new();
+        db.expect_save_account_link().never();
+        let message = MockMessageText::new().text("/start linkAbc123");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&TgResponse::LinkExpired.to_string())
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_edit_missing_reminder() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_reminder().with(eq(1)).returning(|_| Ok(None));
+        let cb_query = MockCallbackQuery::new()
+            .data("edit_rem_mode::rem_time_pattern::1")
+            .message(MockMessageText::new().build());
+        let bot = mock_bot(db, cb_query);
+        bot.dispatch_and_check_last_text(&TgResponse::ReminderGone.to_string())
+            .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_list_one_reminder() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_search_reminders().returning(move |_, _| {
+            Ok(vec![Box::new(rem_clone.clone().into_active_model())])
+        });
+        let message = MockMessageText::new().text("/list");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&format!(
+            "{}\n{}\n{}",
+            TgResponse::RemindersListHeader,
+            TgResponse::ListDayHeader("Mon 01.01".to_owned()),
+            rem.into_active_model().to_string(tz)
+        ))
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_today() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem = reminder::Model {
+            time: NaiveDateTime::new(
+                NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+                NaiveTime::from_hms_opt(10, 30, 0).unwrap(),
+            ),
+            ..basic_mock_reminder()
+        };
+        let rem_clone = rem.clone();
+        db.expect_search_reminders().returning(move |_, _| {
+            Ok(vec![Box::new(rem_clone.clone().into_active_model())])
+        });
+        let message = MockMessageText::new().text("/today");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&format!(
+            "{}\n{} {}",
+            TgResponse::AgendaHeader(false, "Sat 01.06".to_owned()),
+            rem.into_active_model().to_string(tz),
+            TgResponse::TimeLeft("12h30m".to_owned())
+        ))
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_tomorrow_empty() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_search_reminders().returning(|_, _| Ok(vec![]));
+        let message = MockMessageText::new().text("/tomorrow");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::EmptyAgenda(true).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_list_split_into_messages() {
+        set_mock_now();
+        const REMINDERS_COUNT: i64 = 50;
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rems = (1..=REMINDERS_COUNT)
+            .map(|i| {
+                let mut rem = basic_mock_reminder();
+                rem.id = i;
+                rem.desc = "a".repeat(100);
+                rem
+            })
+            .collect::<Vec<_>>();
+        let rems_clone = rems.clone();
+        db.expect_search_reminders().returning(move |_, _| {
+            Ok(rems_clone
+                .iter()
+                .map(|rem| -> Box<dyn GenericReminder> {
+                    Box::new(rem.clone().into_active_model())
+                })
+                .collect())
+        });
+        let message = MockMessageText::new().text("/list");
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        let texts = resp!(bot, sent_messages, text().unwrap().to_owned());
+        assert_eq!(texts.len(), 2);
+        assert!(texts
+            .iter()
+            .all(|text| text.chars().count() <= crate::tg::MESSAGE_MAX_LEN));
+        assert_eq!(
+            texts.join("\n"),
+            [
+                TgResponse::RemindersListHeader.to_string(),
+                TgResponse::ListDayHeader("Mon 01.01".to_owned()).to_string()
+            ]
+            .into_iter()
+            .chain(
+                rems.into_iter()
+                    .map(|rem| rem.into_active_model().to_string(tz))
+            )
+            .collect::<Vec<_>>()
+            .join("\n")
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_list_paginated() {
+        set_mock_now();
+        const REMINDERS_COUNT: i64 = 200;
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rems = (1..=REMINDERS_COUNT)
+            .map(|i| {
+                let mut rem = basic_mock_reminder();
+                rem.id = i;
+                rem.desc = "a".repeat(100);
+                rem
+            })
+            .collect::<Vec<_>>();
+        db.expect_search_reminders().returning(move |_, _| {
+            Ok(rems
+                .iter()
+                .map(|rem| -> Box<dyn GenericReminder> {
+                    Box::new(rem.clone().into_active_model())
+                })
+                .collect())
+        });
+        let message = MockMessageText::new().text("/list");
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        let sent = bot.get_responses().sent_messages;
+        assert_eq!(sent.len(), 1);
+        let page_buttons = |msg: &Message| {
+            msg.reply_markup().unwrap().inline_keyboard[0]
+                .iter()
+                .map(|button| button.text.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(page_buttons(&sent[0]), ["1/6", "➡️"]);
+        assert!(sent[0].text().unwrap().starts_with(
+            &TgResponse::RemindersListHeader.to_unescaped_string()
+        ));
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("list::page::5::")
+                .message(sent[0].clone()),
+        );
+        bot.dispatch().await;
+        let edited = bot.get_responses().edited_messages_text;
+        assert_eq!(edited.len(), 1);
+        assert_eq!(page_buttons(&edited[0].message), ["⬅️", "6/6"]);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_list_filtered() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let mut rem = basic_mock_reminder();
+        rem.desc = "meeting".to_owned();
+        let mut paused_rem = rem.clone();
+        paused_rem.id = 2;
+        paused_rem.paused = true;
+        let rems = vec![rem, paused_rem];
+        let rems_clone = rems.clone();
+        db.expect_search_reminders()
+            .withf(|&chat_id, filter| {
+                chat_id == 12345678 && filter.text.as_deref() == Some("meeting")
+            })
+            .returning(move |_, _| {
+                Ok(rems_clone
+                    .iter()
+                    .map(|rem| -> Box<dyn GenericReminder> {
+                        Box::new(rem.clone().into_active_model())
+                    })
+                    .collect())
+            });
+        let message = MockMessageText::new().text("/list meeting");
+        let bot = mock_bot(db, message);
+        let [rem, paused_rem] = <[_; 2]>::try_from(rems).unwrap();
+        bot.dispatch_and_check_last_text(&format!(
+            "{}\n{}\n{}\n{}\n{}",
+            TgResponse::RemindersListHeader,
+            TgResponse::ListDayHeader("Mon 01.01".to_owned()),
+            rem.into_active_model().to_string(tz),
+            TgResponse::ListPausedHeader,
+            paused_rem.into_active_model().to_string(tz)
+        ))
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_list_no_matches() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_search_reminders().returning(|_, _| Ok(vec![]));
+        let message = MockMessageText::new().text("/list today");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::NoMatchingReminders.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_new_reminder_in_topic() {
+        set_mock_now();
+        let topic = MockMessageText::new().id(9001).text("Chores").build();
+        let message = MockMessageText::new()
+            .text("10:00 tea")
+            .thread_id(ThreadId(MessageId(9001)))
+            .is_topic_message(true)
+            .reply_to_message(topic);
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_duplicate_reminder()
+            .returning(|_, _, _| Ok(None));
+        db.expect_insert_reminder()
+            .withf(|rem| {
+                rem.thread_id == Set(Some(9001))
+                    && rem.desc == Set("tea".to_owned())
+            })
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(1), ..rem }));
+        // The topic's first message isn't what the reminder is about
+        db.expect_insert_message_link()
+            .withf(|_, _, role, _| *role != MessageRole::Subject)
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_new_reminder_description_too_long() {
+        set_mock_now();
+        let max_len = crate::cli::DEFAULT_MAX_DESCRIPTION_LENGTH;
+        let message = MockMessageText::new()
+            .text(format!("10:00 {} rest", "a".repeat(max_len)));
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_duplicate_reminder()
+            .returning(|_, _, _| Ok(None));
+        db.expect_insert_reminder()
+            .withf(move |rem| rem.desc == Set("a".repeat(max_len)))
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(1), ..rem }));
+        db.expect_insert_message_link()
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::DescriptionTrimmed(max_len, "rest".to_owned())
+                .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_forwarded_not_reminder() {
+        set_mock_now();
+        let message = MockMessageText::new()
+            .text("see you at the party")
+            .forward_origin(MessageOrigin::HiddenUser {
+                date: Utc::now(),
+                sender_user_name: "Alice".to_owned(),
+            });
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::ForwardedHint.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_set_in_group_replying_to_forwarded() {
+        set_mock_now();
+        let forwarded = MockMessageText::new()
+            .id(9001)
+            .text("water the plants")
+            .forward_origin(MessageOrigin::HiddenUser {
+                date: Utc::now(),
+                sender_user_name: "Alice".to_owned(),
+            })
+            .build();
+        let mut message = MockMessageText::new()
+            .text("/set 10:00")
+            .reply_to_message(forwarded);
+        message.chat.id.0 = -1;
+        let user_id = message.from.as_ref().unwrap().id.0 as i64;
+        let mut db = MockDatabase::new();
+        db.expect_get_chat_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_reminder()
+            .withf(move |rem| {
+                rem.desc == Set("water the plants".to_owned())
+                    && rem.user_id == Set(Some(user_id))
+            })
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(1), ..rem }));
+        db.expect_insert_message_link()
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+    }
+
+    #[tokio::test]
+    async fn test_set_in_group_incorrect() {
+        let mut message = MockMessageText::new().text("/set tea");
+        message.chat.id.0 = -1;
+        let mut db = MockDatabase::new();
+        db.expect_get_chat_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::IncorrectRequest.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_save_template() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_save_template()
+            .withf(|_, name, text| {
+                name == "pills" && text == "daily 9:00 take pills"
+            })
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let message = MockMessageText::new()
+            .text("/savetemplate Pills daily 9:00 take pills");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::TemplateSaved("pills".to_owned()).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_template_favorites() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let pills = template::Model {
+            id: 1,
+            user_id: 1,
+            name: "pills".to_owned(),
+            text: "daily 9:00 take pills".to_owned(),
+        };
+        let pills_clone = pills.clone();
+        db.expect_get_templates()
+            .returning(move |_| Ok(vec![pills_clone.clone()]));
+        db.expect_get_template()
+            .withf(|_, name| name == "pills")
+            .returning(move |_, _| Ok(Some(pills.clone())));
+        db.expect_insert_reminder()
+            .withf(|rem| rem.desc == Set("take pills".to_owned()))
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(1), ..rem }));
+        db.expect_insert_message_link()
+            .returning(|_, _, _, _| Ok(()));
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let message = MockMessageText::new().text("/t");
+        let bot = mock_bot(db, message);
+
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![TgResponse::ChooseTemplate.to_string()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("template::pills")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch().await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_new_reminder_wizard() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        db.expect_insert_reminder()
+            .withf(|rem| {
+                rem.desc == Set("tea".to_owned())
+                    && rem.time
+                        == Set(mock_timezone()
+                            .with_ymd_and_hms(2024, 6, 2, 10, 15, 0)
+                            .unwrap()
+                            .naive_utc())
+            })
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(1), ..rem }));
+        db.expect_insert_message_link()
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(db, MockMessageText::new().text("/new"));
+        bot.dispatch_and_check_last_text(&TgResponse::PickDay.to_string())
+            .await;
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("new::time::2024-06-02::10::15")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(
+                bot,
+                edited_messages_text,
+                message.text().unwrap().to_owned()
+            ),
+            vec![TgResponse::AskNewReminderDescription(
+                "Sun 02.06 10:15".to_owned()
+            )
+            .to_string()]
+        );
+        assert!(matches!(
+            bot.get_state::<State>().await,
+            State::NewReminder { .. }
+        ));
+
+        bot.update(MockMessageText::new().text("tea"));
+        bot.dispatch().await;
+        assert!(matches!(bot.get_state::<State>().await, State::Default));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_pause() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem_clone.clone())));
+        db.expect_toggle_reminder_paused()
+            .with(eq(rem.id))
+            .times(1)
+            .returning(move |_| Ok(true));
+        db.expect_toggle_reminder_paused()
+            .with(eq(rem.id))
+            .times(1)
+            .returning(move |_| Ok(false));
+        expect_reminders_pages(&mut db, vec![rem.clone()]);
+        let message = MockMessageText::new().text("/pause");
+        let bot = mock_bot(db, message);
+
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChoosePauseReminder.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: vec![
+                        vec![InlineKeyboardButton {
+                            text: "01.01 01:01 <>".to_string(),
+                            kind: CallbackData(
+                                "pauserem::rem_alt::1".to_string(),
+                            ),
+                        },],
+                        vec![],
+                    ],
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("pauserem::rem_alt::1")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessPause(
+                rem.clone().into_active_model().to_unescaped_string(tz),
+            )
+            .to_string(),
+        )
+        .await;
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("pauserem::rem_alt::1")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessResume(
+                rem.into_active_model().to_unescaped_string(tz),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_skip() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let mut rem = parsers::parse_reminder("/1d 10:00 tea", 1, 1, tz)
+            .await
+            .unwrap();
+        rem.id = Set(1);
+        let rem = rem.try_into_model().unwrap();
+        let rem_clone = rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem_clone.clone())));
+        let skipped_time = rem.time + TimeDelta::days(1);
+        db.expect_reschedule_reminder()
+            .withf(move |skipped| skipped.time == skipped_time)
+            .times(1)
+            .returning(|_| Ok(true));
+        expect_reminders_pages(&mut db, vec![rem.clone()]);
+        let message = MockMessageText::new().text("/skip");
+        let bot = mock_bot(db, message);
+
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![TgResponse::ChooseSkipReminder.to_string()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("skiprem::rem_alt::1")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        let skipped = reminder::Model {
+            time: skipped_time,
+            ..rem
+        };
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessSkip(
+                skipped.into_active_model().to_unescaped_string(tz),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_edit_conflict() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .times(1)
+            .returning(move |_| Ok(Some(rem_clone.clone())));
+        // Someone else edits the reminder meanwhile
+        let changed_rem = reminder::Model {
+            desc: "changed".to_owned(),
+            version: rem.version + 1,
+            ..rem.clone()
+        };
+        let changed_rem_clone = changed_rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .times(1)
+            .returning(move |_| Ok(Some(changed_rem_clone.clone())));
+        db.expect_update_reminder().never();
+        let message = MockMessageText::new().text("new description");
+        let bot = mock_bot(db, MockCallbackQuery::new());
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("edit_rem_mode::rem_description::1")
+                .message(message.clone().build()),
+        );
+        bot.dispatch().await;
+
+        bot.update(message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::EditConflict(
+                changed_rem.into_active_model().to_unescaped_string(tz),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_edit_conflict_on_update() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let rem = basic_mock_reminder();
+        // Someone else edits the reminder after its version was checked
+        let changed_rem = reminder::Model {
+            desc: "changed".to_owned(),
+            version: rem.version + 1,
+            ..rem.clone()
+        };
+        let changed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let changed_clone = changed.clone();
+        let changed_rem_clone = changed_rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| {
+                if changed_clone.load(std::sync::atomic::Ordering::SeqCst) {
+                    Ok(Some(changed_rem_clone.clone()))
+                } else {
+                    Ok(Some(rem.clone()))
+                }
+            });
+        db.expect_update_reminder()
+            .withf(|rem| rem.version == 0)
+            .times(1)
+            .returning(move |_| {
+                changed.store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(false)
+            });
+        db.expect_insert_message_link().never();
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("editrem::rem_alt::1")
+                .message(MockMessageText::new().build()),
+        );
+        bot.dispatch().await;
+
+        bot.update(MockMessageText::new().text("10:00 water flowers"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::EditConflict(
+                changed_rem.into_active_model().to_unescaped_string(tz),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_edit_conflict_on_replace_kind() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let rem = basic_mock_reminder();
+        let changed_rem = reminder::Model {
+            desc: "changed".to_owned(),
+            version: rem.version + 1,
+            ..rem.clone()
+        };
+        let changed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let changed_clone = changed.clone();
+        let changed_rem_clone = changed_rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| {
+                if changed_clone.load(std::sync::atomic::Ordering::SeqCst) {
+                    Ok(Some(changed_rem_clone.clone()))
+                } else {
+                    Ok(Some(rem.clone()))
+                }
+            });
+        // The reminder isn't purged since it was changed meanwhile
+        db.expect_replace_reminder_with_cron()
+            .with(eq(1), eq(0), always())
+            .times(1)
+            .returning(move |_, _, _| {
+                changed.store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(None)
+            });
+        db.expect_insert_cron_reminder().never();
+        db.expect_purge_reminder().never();
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("editrem::rem_alt::1")
+                .message(MockMessageText::new().build()),
+        );
+        bot.dispatch().await;
+
+        bot.update(MockMessageText::new().text("0 9 * * 1-5 stretch"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::EditConflict(
+                changed_rem.into_active_model().to_unescaped_string(tz),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    fn mock_occurrence(id: i64) -> occurrence::Model {
+        occurrence::Model {
+            id,
+            chat_id: 1,
+            msg_id: 1,
+            text: "".to_owned(),
+            policy: None,
+            nag_time: NaiveDateTime::default(),
+            nag_count: 0,
+            desc: Some("take pills".to_owned()),
+            done_time: None,
+            note: None,
+            meeting_time: None,
+            muted_until: None,
+            thread_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_done() {
+        let mut db = MockDatabase::new();
+        db.expect_get_occurrence()
+            .with(eq(1))
+            .returning(|id| Ok(Some(mock_occurrence(id))));
+        db.expect_mark_occurrence_done()
+            .withf(|occ, _| occ.id == 1)
+            .times(1)
+            .returning(|_, _| Ok(()));
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_set_occurrence_note()
+            .with(eq(1), always(), eq("took 5mg".to_owned()))
+            .times(1)
+            .returning(|_, _, _| Ok(true));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("donerem::occ::1")
+                .message(MockMessageText::new().text("reminder").build()),
+        );
+        bot.dispatch().await;
+        let edited = bot.get_responses().edited_messages_reply_markup;
+        assert_eq!(edited.len(), 1);
+        assert!(edited[0]
+            .message
+            .reply_markup()
+            .is_none_or(|markup| markup.inline_keyboard.is_empty()));
+        assert_eq!(
+            resp!(bot, answered_callback_queries, text.clone()),
+            vec![Some(TgResponse::MarkedDone.to_unescaped_string())]
+        );
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![TgResponse::AskOccurrenceNote.to_string()]
+        );
+
+        bot.update(MockMessageText::new().text("took 5mg"));
+        bot.dispatch_and_check_last_text(&TgResponse::NoteSaved.to_string())
+            .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_snooze() {
+        set_mock_now();
+        let message = MockMessageText::new().text("reminder").build();
+        let chat_id = message.chat.id.0;
+        let mut db = MockDatabase::new();
+        db.expect_get_occurrence().with(eq(1)).returning(move |id| {
+            Ok(Some(occurrence::Model {
+                chat_id,
+                policy: Some(r#"{"snooze":900}"#.to_owned()),
+                ..mock_occurrence(id)
+            }))
+        });
+        db.expect_insert_reminder()
+            .withf(|rem| {
+                rem.desc == Set("take pills".to_owned())
+                    && rem.time == Set(now_time() + TimeDelta::minutes(15))
+                    && rem.policy == Set(Some(r#"{"snooze":900}"#.to_owned()))
+            })
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(1), ..rem }));
+        db.expect_delete_occurrence()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(()));
+        db.expect_add_reminder_event()
+            .withf(|event, _| {
+                event.kind == Set(EventKind::Snoozed)
+                    && event.desc == Set("take pills".to_owned())
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new().data("snooze::1").message(message),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, answered_callback_queries, text.clone()),
+            vec![Some(
+                TgResponse::Snoozed("15m".to_owned()).to_unescaped_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_mute() {
+        set_mock_now();
+        let message = MockMessageText::new()
+            .text("reminder")
+            .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+                tg::done_button(1, Language::English),
+                tg::mute_button(1, Language::English),
+            ]]))
+            .build();
+        let chat_id = message.chat.id.0;
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_occurrence().with(eq(1)).returning(move |_| {
+            Ok(Some(occurrence::Model {
+                chat_id,
+                ..nagged_occurrence()
+            }))
+        });
+        db.expect_update_occurrence()
+            .withf(|occ| {
+                // 8:00 of the next day in Amsterdam
+                let until = NaiveDate::from_ymd_opt(2024, 6, 2)
+                    .unwrap()
+                    .and_hms_opt(6, 0, 0)
+                    .unwrap();
+                occ.muted_until == Some(until)
+                    && occ.nag_time == until
+                    && occ.done_time.is_none()
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new().data("mute::1").message(message),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, edited_messages_reply_markup, message.reply_markup()),
+            vec![Some(&InlineKeyboardMarkup::new(vec![vec![
+                tg::done_button(1, Language::English)
+            ]]))]
+        );
+        assert_eq!(
+            resp!(bot, answered_callback_queries, text.clone()),
+            vec![Some(
+                TgResponse::Muted("08:00".to_owned()).to_unescaped_string()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_snooze_done() {
+        let message = MockMessageText::new().text("reminder").build();
+        let chat_id = message.chat.id.0;
+        let mut db = MockDatabase::new();
+        db.expect_get_occurrence().with(eq(1)).returning(move |id| {
+            Ok(Some(occurrence::Model {
+                chat_id,
+                policy: Some(r#"{"snooze":900}"#.to_owned()),
+                done_time: Some(NaiveDateTime::default()),
+                ..mock_occurrence(id)
+            }))
+        });
+        db.expect_insert_reminder().never();
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new().data("snooze::1").message(message),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, answered_callback_queries, text.clone()),
+            vec![Some(TgResponse::StaleButton.to_unescaped_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_settings() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        db.expect_insert_or_update_user_settings()
+            .withf(|settings| settings.silent && !settings.clock_12h)
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot = mock_bot(db, MockMessageText::new().text("/settings"));
+        bot.dispatch_and_check_last_text(&TgResponse::Settings.to_string())
+            .await;
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("settings::silent")
+                .message(MockMessageText::new().text("settings").build()),
+        );
+        bot.dispatch().await;
+        let edited = bot.get_responses().edited_messages_reply_markup;
+        assert_eq!(edited.len(), 1);
+        assert_eq!(
+            edited[0].message.reply_markup().unwrap().inline_keyboard[1][0]
+                .text,
+            "🔕 Silent notifications: on"
+        );
+        assert_eq!(
+            resp!(bot, answered_callback_queries, text.clone()),
+            vec![Some(TgResponse::SettingsSaved.to_unescaped_string())]
+        );
+    }
+
+    fn text_menus_settings(user_id: i64) -> user_settings::Model {
+        user_settings::Model {
+            text_menus: true,
+            ..settings::defaults(user_id)
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_delete_all() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_search_reminders()
+            .withf(|_, filter| filter.paused == Some(true))
+            .returning(|_, _| {
+                Ok(vec![Box::new(basic_mock_reminder().into_active_model())])
+            });
+        db.expect_delete_reminders()
+            .withf(|_, filter| filter.paused == Some(true))
+            .times(1)
+            .returning(|_, _| Ok(1));
+        let bot =
+            mock_bot(db, MockMessageText::new().text("/deleteall paused"));
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ConfirmDeleteAll(1).to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: vec![vec![
+                        InlineKeyboardButton {
+                            text: "🗑 Delete".to_string(),
+                            kind: CallbackData("bulk::del::paused".to_string()),
+                        },
+                        InlineKeyboardButton {
+                            text: "Cancel".to_string(),
+                            kind: CallbackData("bulk::cancel".to_string()),
+                        },
+                    ]],
+                },
+            }
+            .into()]
+        );
+        let confirmation = bot.get_responses().sent_messages[0].clone();
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("bulk::cancel")
+                .message(confirmation.clone()),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::BulkCancelled.to_string(),
+        )
+        .await;
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("bulk::del::paused")
+                .message(confirmation),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::DeletedAll(1).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_pause_all_nothing() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_search_reminders()
+            .withf(|_, filter| filter.paused == Some(false))
+            .returning(|_, _| Ok(vec![]));
+        let bot = mock_bot(db, MockMessageText::new().text("/pauseall"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::NoMatchingReminders.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_undo_delete() {
+        set_mock_now();
+        let rem = basic_mock_reminder();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem_clone = rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem_clone.clone())));
+        db.expect_trash_reminder()
+            .with(eq(rem.id))
+            .returning(|_| Ok(()));
+        expect_reminders_pages(&mut db, vec![]);
+        db.expect_restore_reminders()
+            .with(eq(None), eq(vec![rem.clone()]), eq(vec![]))
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("delrem::rem_alt::1")
+                .message(MockMessageText::new().text("list").build()),
+        );
+        bot.dispatch().await;
+        let deleted = bot.get_responses().sent_messages[0].clone();
+        let Some(CallbackData(undo_data)) = deleted
+            .reply_markup()
+            .and_then(|markup| markup.inline_keyboard.first()?.first())
+            .map(|button| button.kind.clone())
+        else {
+            panic!("no undo button");
+        };
+        assert!(undo_data.starts_with("undo::"));
+
+        let rem_str =
+            rem.into_active_model().to_unescaped_string(mock_timezone());
+        bot.update(
+            MockCallbackQuery::new()
+                .data(undo_data.clone())
+                .message(deleted.clone()),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::Undone(rem_str).to_string(),
+        )
+        .await;
+
+        // The reminder is restored only once
+        bot.update(MockCallbackQuery::new().data(undo_data).message(deleted));
+        bot.dispatch().await;
+        assert!(bot.get_responses().sent_messages.is_empty());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_delete_series() {
+        set_mock_now();
+        let parsed = grammar::parse_reminder("/fri,mon 11:00 tea")
+            .unwrap()
+            .pattern
+            .unwrap();
+        let mut pattern =
+            Pattern::from_with_tz(parsed, mock_timezone()).unwrap();
+        let time = pattern.next(now_time()).unwrap();
+        let rem = reminder::Model {
+            time,
+            pattern: pattern.to_stored(time),
+            desc: "tea".to_owned(),
+            ..basic_mock_reminder()
+        };
+        let rem_str = rem
+            .clone()
+            .into_active_model()
+            .to_unescaped_string(mock_timezone());
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem_clone = rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem_clone.clone())));
+        db.expect_trash_reminder()
+            .with(eq(rem.id))
+            .times(1)
+            .returning(|_| Ok(()));
+        expect_reminders_pages(&mut db, vec![rem]);
+        let menu = MockMessageText::new().text("list").build();
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("delrem::rem_alt::1")
+                .message(menu.clone()),
+        );
+        bot.dispatch().await;
+        assert!(bot.get_responses().sent_messages.is_empty());
+        assert_eq!(
+            resp!(
+                bot,
+                edited_messages_text,
+                message.text().unwrap().to_owned()
+            ),
+            vec![TgResponse::ConfirmDeleteSeries(rem_str.clone()).to_string()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("series::cancel")
+                .message(menu.clone()),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::NothingDeleted.to_string(),
+        )
+        .await;
+        assert_eq!(
+            resp!(
+                bot,
+                edited_messages_text,
+                message.text().unwrap().to_owned()
+            ),
+            vec![TgResponse::ChooseDeleteReminder.to_string()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("series::del::rem::1")
+                .message(menu),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessDelete(rem_str).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_delete_text_menu() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings()
+            .returning(|user_id| Ok(Some(text_menus_settings(user_id))));
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_get_sorted_reminders().returning(move |_| {
+            Ok(vec![Box::new(rem_clone.clone().into_active_model())])
+        });
+        let rem_clone = rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem_clone.clone())));
+        db.expect_trash_reminder()
+            .with(eq(rem.id))
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot = mock_bot(db, MockMessageText::new().text("/delete"));
+        bot.dispatch_and_check_last_text(
+            &text_menu::messages(
+                TgResponse::ChooseDeleteReminder,
+                ["01.01 01:01 <>".to_owned()],
+                Language::English,
+            )[0],
+        )
+        .await;
+
+        bot.update(MockMessageText::new().text("2"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::WrongNumber(1).to_string(),
+        )
+        .await;
+
+        bot.update(MockMessageText::new().text("1"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessDelete(
+                rem.into_active_model().to_unescaped_string(mock_timezone()),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_cleanup_menus() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|user_id| {
+            Ok(Some(user_settings::Model {
+                text_menus: true,
+                cleanup_after: Some(600),
+                ..settings::defaults(user_id)
+            }))
+        });
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_get_sorted_reminders().returning(move |_| {
+            Ok(vec![Box::new(rem_clone.clone().into_active_model())])
+        });
+        db.expect_get_reminder()
+            .returning(move |_| Ok(Some(rem.clone())));
+        db.expect_trash_reminder().returning(|_| Ok(()));
+        let delete_time = now_time() + TimeDelta::minutes(10);
+        db.expect_insert_expiring_message()
+            .with(eq(12345678), always(), eq(delete_time))
+            .times(2)
+            .returning(|_, _, _| Ok(()));
+        let bot = mock_bot(db, MockMessageText::new().text("/delete"));
+        bot.dispatch().await;
+        bot.update(MockMessageText::new().text("1"));
+        bot.dispatch().await;
+    }
+
+    #[tokio::test]
+    async fn test_delete_expired_messages() {
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_expired_messages().returning(|| {
+            Ok(vec![expiring_message::Model {
+                id: 3,
+                chat_id: 1,
+                msg_id: 42,
+                delete_time: NaiveDateTime::default(),
+            }])
+        });
+        db.expect_delete_expiring_message()
+            .with(eq(3))
+            .times(1)
+            .returning(|_| Ok(()));
+        super::delete_expired_messages(&db, &api.bot()).await;
+        let requests = api.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].0, "DeleteMessage");
+        assert_eq!(requests[0].1["message_id"], 42);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_edit_text_menu() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings()
+            .returning(|user_id| Ok(Some(text_menus_settings(user_id))));
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_get_sorted_reminders().returning(move |_| {
+            Ok(vec![Box::new(rem_clone.clone().into_active_model())])
+        });
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem.clone())));
+        let bot = mock_bot(db, MockMessageText::new().text("/edit"));
+        bot.dispatch().await;
+
+        bot.update(MockMessageText::new().text("1"));
+        bot.dispatch_and_check_last_text(
+            &text_menu::messages(
+                TgResponse::ChooseEditMode,
+                text_menu::EDIT_MODES
+                    .map(|key| Language::English.text(key, &[])),
+                Language::English,
+            )[0],
+        )
+        .await;
+
+        bot.update(MockMessageText::new().text("2"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::EnterNewDescription.to_string(),
+        )
+        .await;
+        assert!(matches!(
+            bot.get_state::<State>().await,
+            State::Edit {
+                id: 1,
+                mode: EditMode::Description,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_edit_keep_description() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem = reminder::Model {
+            desc: "call mom".to_owned(),
+            ..basic_mock_reminder()
+        };
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem.clone())));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("editrem::rem_alt::1")
+                .message(MockMessageText::new().build()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![
+                TgResponse::EnterEditedReminder.to_string(),
+                "`01.01 01:01 call mom`".to_owned()
+            ]
+        );
+        assert!(matches!(
+            bot.get_state::<State>().await,
+            State::Edit {
+                id: 1,
+                mode: EditMode::Whole,
+                ..
+            }
+        ));
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("edit_rem_mode::rem_time_pattern::1")
+                .message(bot.get_responses().sent_messages[1].clone()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![
+                TgResponse::EnterNewTimePattern.to_string(),
+                "`01.01 01:01`".to_owned()
+            ]
+        );
+        assert!(matches!(
+            bot.get_state::<State>().await,
+            State::Edit {
+                id: 1,
+                mode: EditMode::TimePattern,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_edit_move_on_calendar() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let rem = reminder::Model {
+            desc: "call mom".to_owned(),
+            paused: true,
+            ..basic_mock_reminder()
+        };
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem.clone())));
+        // The reminder is edited in place, keeping its id and pause
+        db.expect_update_reminder()
+            .withf(|rem| {
+                rem.id == 1
+                    && rem.paused
+                    && rem.desc == "call mom"
+                    && rem.time
+                        == mock_timezone()
+                            .with_ymd_and_hms(2024, 6, 5, 1, 1, 0)
+                            .unwrap()
+                            .naive_utc()
+            })
+            .times(1)
+            .returning(|_| Ok(true));
+        db.expect_insert_reminder().never();
+        db.expect_move_message_links().never();
+        db.expect_get_message_links().returning(|_, _| Ok(vec![]));
+        db.expect_insert_message_link()
+            .with(always(), always(), always(), eq(LinkTarget::Reminder(1)))
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("edit_rem_mode::rem_time_pattern::1")
+                .message(MockMessageText::new().build()),
+        );
+        bot.dispatch().await;
+        let sent = bot.get_responses().sent_messages;
+        assert_eq!(
+            sent[1].reply_markup().unwrap().inline_keyboard[0][1].text,
+            "June 2024"
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("cal::edit::1::day::2024-06-05")
+                .message(sent[1].clone()),
+        );
+        bot.dispatch().await;
+        assert!(matches!(bot.get_state::<State>().await, State::Default));
+    }
+
+    #[tokio::test]
+    async fn test_settings_text_menu() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_user_settings()
+            .returning(|user_id| Ok(Some(text_menus_settings(user_id))));
+        db.expect_insert_or_update_user_settings()
+            .withf(|settings| settings.silent && settings.text_menus)
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot = mock_bot(db, MockMessageText::new().text("/settings"));
+        bot.dispatch().await;
+
+        bot.update(MockMessageText::new().text("2"));
+        bot.dispatch().await;
+        let sent = bot.get_responses().sent_messages;
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0]
+            .text()
+            .unwrap()
+            .contains("5\\. 🔢 Numbered text menus: on"));
+        bot.update(MockMessageText::new().text("/cancel"));
+        bot.dispatch_and_check_last_text(&TgResponse::MenuClosed.to_string())
+            .await;
+    }
+
+    fn nagged_occurrence() -> occurrence::Model {
+        occurrence::Model {
+            policy: Some(r#"{"nag":60}"#.to_owned()),
+            ..mock_occurrence(1)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nag_blocked() {
+        let api = FlakyApi::start();
+        api.fail([Failure::TooManyRequests(1), Failure::Blocked]);
+        let mut db = MockDatabase::new();
+        db.expect_update_occurrence().never();
+        db.expect_delete_occurrence()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(()));
+        super::nag(nagged_occurrence(), &db, &api.bot()).await;
+        assert_eq!(
+            api.methods(),
+            vec!["EditMessageReplyMarkup", "SendMessage"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nag_after_timeout() {
+        let api = FlakyApi::start();
+        api.fail([Failure::Timeout]);
+        let mut db = MockDatabase::new();
+        db.expect_delete_occurrence().never();
+        db.expect_update_occurrence()
+            .withf(|occ| occ.id == 1 && occ.nag_count == 1)
+            .times(1)
+            .returning(|_| Ok(()));
+        super::nag(nagged_occurrence(), &db, &api.bot()).await;
+        assert_eq!(
+            api.methods(),
+            vec![
+                "EditMessageReplyMarkup",
+                "SendMessage",
+                "EditMessageReplyMarkup"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_skip_note() {
+        let mut db = MockDatabase::new();
+        db.expect_get_occurrence()
+            .with(eq(1))
+            .returning(|id| Ok(Some(mock_occurrence(id))));
+        db.expect_mark_occurrence_done().returning(|_, _| Ok(()));
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_set_occurrence_note().never();
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("donerem::occ::1")
+                .message(MockMessageText::new().text("reminder").build()),
+        );
+        bot.dispatch().await;
+        bot.update(
+            MockCallbackQuery::new()
+                .data("donerem::skipnote")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch().await;
+        bot.update(MockMessageText::new().text("took 5mg"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::IncorrectRequest.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_rsvp() {
+        let mut db = MockDatabase::new();
+        db.expect_get_occurrence().with(eq(1)).returning(|id| {
+            Ok(Some(occurrence::Model {
+                chat_id: 12345678,
+                meeting_time: Some(NaiveDateTime::default()),
+                ..mock_occurrence(id)
+            }))
+        });
+        db.expect_set_rsvp()
+            .withf(|occ_id, _, _, answer| {
+                *occ_id == 1 && *answer == meeting::Answer::Maybe
+            })
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("rsvp::1::maybe")
+                .message(MockMessageText::new().text("standup").build()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, answered_callback_queries, text.clone()),
+            vec![Some(TgResponse::RsvpSaved.to_unescaped_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rsvp_summed_up() {
+        let mut db = MockDatabase::new();
+        db.expect_get_occurrence()
+            .with(eq(1))
+            .returning(|_| Ok(None));
+        db.expect_set_rsvp().never();
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("rsvp::1::yes")
+                .message(MockMessageText::new().text("standup").build()),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, answered_callback_queries, text.clone()),
+            vec![Some(TgResponse::MeetingOver.to_unescaped_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_history() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_reminder_events()
+            .with(eq(12345678), always())
+            .returning(|chat_id, _| {
+                let time = |hour| {
+                    NaiveDate::from_ymd_opt(2024, 1, 1)
+                        .unwrap()
+                        .and_hms_opt(hour, 30, 0)
+                        .unwrap()
+                };
+                Ok(vec![
+                    reminder_event::Model {
+                        id: 2,
+                        chat_id,
+                        time: time(9),
+                        desc: "take pills".to_owned(),
+                        kind: EventKind::Done,
+                        occurrence_id: Some(1),
+                        note: Some("took 5mg".to_owned()),
+                    },
+                    reminder_event::Model {
+                        id: 1,
+                        chat_id,
+                        time: time(8),
+                        desc: "take pills".to_owned(),
+                        kind: EventKind::Sent,
+                        occurrence_id: None,
+                        note: None,
+                    },
+                ])
+            });
+        let message = MockMessageText::new().text("/history");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&format!(
+            "{}\n{}\n{}",
+            TgResponse::HistoryHeader,
+            TgResponse::HistoryEntry(
+                EventKind::Done,
+                "01.01 10:30".to_owned(),
+                "take pills".to_owned(),
+                Some("took 5mg".to_owned()),
+            ),
+            TgResponse::HistoryEntry(
+                EventKind::Sent,
+                "01.01 09:30".to_owned(),
+                "take pills".to_owned(),
+                None,
+            )
+        ))
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_pomodoro() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_pomodoro().times(1).returning(|session| {
+            Ok(pomodoro::ActiveModel {
+                id: Set(1),
+                ..session
+            })
+        });
+        let start = now_time();
+        db.expect_insert_reminder()
+            .withf(move |rem| {
+                rem.pomodoro_id == Set(Some(1))
+                    && rem.time == Set(start + TimeDelta::minutes(25))
+            })
+            .times(1)
+            .returning(Ok);
+        let message = MockMessageText::new().text("/pomodoro 25 5 x2");
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, kind),
+            vec![MockMarkup {
+                media_text: TgResponse::PomodoroStarted(25, 5, 2).to_string(),
+                markup: tg::pomodoro_markup(1, Language::English),
+            }
+            .into()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stop_pomodoro() {
+        let msg = MockMessageText::new().text("focus").build();
+        let chat_id = msg.chat.id.0;
+        let mut db = MockDatabase::new();
+        db.expect_get_pomodoro().with(eq(1)).returning(move |id| {
+            Ok(Some(pomodoro::Model {
+                id,
+                chat_id,
+                work: 25,
+                rest: 5,
+                rounds: 4,
+                phase: 3,
+            }))
+        });
+        db.expect_delete_pomodoro()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("pomodoro::stop::1")
+                .message(msg),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::PomodoroStopped(2, 4).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_stats() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_chat_bills().returning(|chat_id, from, _| {
+            Ok([(4550, true), (5000, false)]
+                .into_iter()
+                .map(|(amount, paid)| bill::Model {
+                    id: 0,
+                    chat_id,
+                    name: "electricity".to_owned(),
+                    amount,
+                    time: from,
+                    paid,
+                })
+                .collect())
+        });
+        let message = MockMessageText::new().text("/stats");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&format!(
+            "{}\n{}",
+            TgResponse::StatsHeader(2024),
+            TgResponse::StatsBill(
+                "electricity".to_owned(),
+                "95.50".to_owned(),
+                1,
+                2
+            )
+        ))
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_bill_paid() {
+        let msg = MockMessageText::new()
+            .text("bill 45.50 electricity")
+            .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+                tg::done_button(2, Language::English),
+                tg::paid_button(1, Language::English),
+            ]]))
+            .build();
+        let chat_id = msg.chat.id.0;
+        let mut db = MockDatabase::new();
+        db.expect_get_bill().with(eq(1)).returning(move |id| {
+            Ok(Some(bill::Model {
+                id,
+                chat_id,
+                name: "electricity".to_owned(),
+                amount: 4550,
+                time: NaiveDateTime::default(),
+                paid: false,
+            }))
+        });
+        db.expect_set_bill_paid()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new().data("bill::paid::1").message(msg),
+        );
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, edited_messages_reply_markup, message.reply_markup()),
+            vec![Some(&InlineKeyboardMarkup::new(vec![vec![
+                tg::done_button(2, Language::English)
+            ]]))]
+        );
+        assert_eq!(
+            resp!(bot, answered_callback_queries, text.clone()),
+            vec![Some(TgResponse::BillPaid.to_unescaped_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_crontab_too_large() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_cron_reminder().never();
+        let message = MockMessageDocument::new().file_size(1 << 20);
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::FileTooLarge(64 * 1024).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_import_crontab_report() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_cron_reminder().never();
+        // The mocked file content is "Hello, world!"
+        let message = MockMessageDocument::new();
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&format!(
+            "{}\n{}",
+            TgResponse::CrontabImportHeader(0, 1),
+            TgResponse::CrontabEntryFailed(1, "Hello, world!".to_owned())
+        ))
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_pending_chat_reminders().returning(|chat_id| {
+            Ok(vec![reminder::Model {
+                chat_id,
+                desc: "reminder".to_owned(),
+                ..basic_mock_reminder()
+            }])
+        });
+        db.expect_get_pending_chat_cron_reminders()
+            .returning(|_| Ok(vec![]));
+        let message = MockMessageText::new().text("/export");
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        let documents = resp!(bot, sent_messages_document, bot_request);
+        assert_eq!(documents.len(), 1);
+        assert_eq!(documents[0].file_name, backup::FILE_NAME);
+        assert_eq!(
+            documents[0].caption,
+            Some(TgResponse::ExportCaption(1).to_unescaped_string())
+        );
+        let backup =
+            Backup::from_json(documents[0].file_data.as_bytes()).unwrap();
+        assert_eq!(backup.len(), 1);
+        assert_eq!(backup.tz(), Some(mock_timezone()));
+    }
+
+    #[tokio::test]
+    async fn test_export_empty() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_pending_chat_reminders()
+            .returning(|_| Ok(vec![]));
+        db.expect_get_pending_chat_cron_reminders()
+            .returning(|_| Ok(vec![]));
+        let message = MockMessageText::new().text("/export");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&TgResponse::ExportEmpty.to_string())
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_share() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_share_link().returning(|link| {
+            Ok(share_link::Model {
+                id: 1,
+                chat_id: link.chat_id.unwrap(),
+                user_id: link.user_id.unwrap(),
+                token: "token".to_owned(),
+                expires_at: NaiveDateTime::default(),
+            })
+        });
+        let message = MockMessageText::new().text("/share 30");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::ShareLink(
+                "https://remindee.example/share/token".to_owned(),
+                "01.01.1970 01:00".to_owned(),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_share_usage() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_share_link().never();
+        let message = MockMessageText::new().text("/share forever");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&TgResponse::ShareUsage.to_string())
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_reply_in_user_language() {
+        let user_id = 7702;
+        settings::set_language(user_id, Language::Russian);
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_share_link().never();
+        let message = MockMessageText::new()
+            .text("/share forever")
+            .from(MockUser::new().id(user_id as u64).build());
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::ShareUsage.to_string_in(Language::Russian),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_unshare() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_delete_chat_share_links()
+            .times(1)
+            .returning(|_| Ok(2));
+        let message = MockMessageText::new().text("/unshare");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SharesRevoked(2).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_calendar_feed() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_user_calendar_feed()
+            .times(1)
+            .returning(|_| Ok(None));
+        db.expect_replace_calendar_feed()
+            .times(1)
+            .returning(|feed| {
+                Ok(calendar_feed::Model {
+                    id: 1,
+                    user_id: feed.user_id.unwrap(),
+                    token: "token".to_owned(),
+                })
+            });
+        let message = MockMessageText::new().text("/calendar");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::CalendarFeed(
+                "https://remindee.example/calendar/token.ics".to_owned(),
+            )
+            .to_string(),
+        )
+        .await;
+
+        bot.update(MockMessageText::new().text("/calendar on"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::CalendarFeedUsage.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_api_token() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let token_hash = Arc::new(std::sync::Mutex::new(String::new()));
+        let token_hash_clone = token_hash.clone();
+        db.expect_replace_api_token()
+            .times(1)
+            .returning(move |token| {
+                let token = api_token::Model {
+                    id: 1,
+                    chat_id: token.chat_id.unwrap(),
+                    user_id: token.user_id.unwrap(),
+                    token_hash: token.token_hash.unwrap(),
+                };
+                *token_hash_clone.lock().unwrap() = token.token_hash.clone();
+                Ok(token)
+            });
+        db.expect_delete_chat_api_token()
+            .times(1)
+            .returning(|_| Ok(true));
+        let bot = mock_bot(db, MockMessageText::new().text("/apitoken"));
+        bot.dispatch().await;
+        // Only the hash of the token shown is stored
+        let text = bot.get_responses().sent_messages[0]
+            .text()
+            .unwrap()
+            .to_owned();
+        let token = text
+            .split("Bearer ")
+            .nth(1)
+            .and_then(|rest| rest.split_whitespace().next())
+            .unwrap();
+        assert_eq!(crate::api::hash_token(token), *token_hash.lock().unwrap());
+        assert_eq!(
+            text,
+            TgResponse::ApiToken(
+                token.to_owned(),
+                "https://remindee.example/api/reminders".to_owned()
+            )
+            .to_string()
+        );
+
+        bot.update(MockMessageText::new().text("/apitoken off"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::ApiTokenRevoked(true).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_orphans() {
+        set_mock_now();
+        let message = MockMessageText::new().text("/orphans");
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let rem = basic_mock_reminder();
+        let claimed = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let claimed_clone = claimed.clone();
+        db.expect_get_orphaned_reminders().returning(move |_| {
+            if claimed_clone.load(std::sync::atomic::Ordering::SeqCst) {
+                Ok((vec![], vec![]))
+            } else {
+                Ok((vec![rem.clone()], vec![]))
+            }
+        });
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_claim_orphaned_reminders()
+            .with(always(), eq(Some(LinkTarget::Reminder(1))), always())
+            .times(1)
+            .returning(move |_, _, _| {
+                claimed.store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(1)
+            });
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+        assert_eq!(
+            resp!(bot, sent_messages, kind),
+            vec![MockMarkup {
+                media_text: TgResponse::ChooseOrphanedReminders.to_string(),
+                markup: InlineKeyboardMarkup {
+                    inline_keyboard: vec![
+                        vec![
+                            InlineKeyboardButton {
+                                text: "🙋 01.01 01:01 <>".to_string(),
+                                kind: CallbackData(
+                                    "orphan::claim::rem::1".to_string(),
+                                ),
+                            },
+                            InlineKeyboardButton {
+                                text: "🗑".to_string(),
+                                kind: CallbackData(
+                                    "orphan::del::rem::1".to_string(),
+                                ),
+                            },
+                        ],
+                        vec![
+                            InlineKeyboardButton {
+                                text: "🙋 Claim all".to_string(),
+                                kind: CallbackData(
+                                    "orphan::claim::all".to_string(),
+                                ),
+                            },
+                            InlineKeyboardButton {
+                                text: "🗑 Delete all".to_string(),
+                                kind: CallbackData(
+                                    "orphan::del::all".to_string(),
+                                ),
+                            },
+                        ],
+                    ],
+                },
+            }
+            .into()]
+        );
+
+        bot.update(
+            MockCallbackQuery::new()
+                .data("orphan::claim::rem::1")
+                .message(bot.get_responses().sent_messages[0].clone()),
+        );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::OrphansClaimed(1).to_string(),
+        )
+        .await;
+        let edited = bot.get_responses().edited_messages_reply_markup;
+        assert_eq!(edited.len(), 1);
+        assert!(edited[0]
+            .message
+            .reply_markup()
+            .is_none_or(|markup| markup.inline_keyboard.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_check_setup_private() {
+        let message = MockMessageText::new().text("/checksetup");
+        let bot = mock_bot(MockDatabase::new(), message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SetupPrivateChat.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_set_chat_timezone_private() {
+        let mut db = MockDatabase::new();
+        db.expect_insert_or_update_chat_timezone().never();
+        let message =
+            MockMessageText::new().text("/setchattimezone Europe/Berlin");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::ChatTimezonePrivateChat.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_orphans_none() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_orphaned_reminders()
+            .returning(|_| Ok((vec![], vec![])));
+        let message = MockMessageText::new().text("/orphans");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::NoOrphanedReminders.to_string(),
+        )
+        .await;
     }
 
     #[tokio::test]
-    async fn test_delete() {
-        let message = MockMessageText::new().text("/delete");
+    #[serial]
+    async fn test_trash() {
+        set_mock_now();
+        let message = MockMessageText::new().text("/trash");
         let mut db = MockDatabase::new();
-        let rem = basic_mock_reminder();
-        let rem_clone = rem.clone();
-        db.expect_get_sorted_reminders().returning(move |_| {
-            Ok(vec![Box::new(rem_clone.clone().into_active_model())])
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let rem = reminder::Model {
+            deleted_time: Some(Utc::now().naive_utc()),
+            ..basic_mock_reminder()
+        };
+        let restored = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let restored_clone = restored.clone();
+        db.expect_get_trashed_reminders().returning(move |_| {
+            if restored_clone.load(std::sync::atomic::Ordering::SeqCst) {
+                Ok((vec![], vec![]))
+            } else {
+                Ok((vec![rem.clone()], vec![]))
+            }
         });
         db.expect_get_user_timezone_name()
             .returning(|_| Ok(Some(mock_timezone_name())));
-        let rem_clone = rem.clone();
-        db.expect_get_reminder()
-            .with(eq(rem.id))
-            .returning(move |_| Ok(Some(rem_clone.clone())));
-        db.expect_delete_reminder()
-            .with(eq(rem.id))
-            .returning(move |_| Ok(()));
+        db.expect_restore_trashed_reminders()
+            .with(always(), eq(Some(LinkTarget::Reminder(1))))
+            .times(1)
+            .returning(move |_, _| {
+                restored.store(true, std::sync::atomic::Ordering::SeqCst);
+                Ok(1)
+            });
         let bot = mock_bot(db, message);
         bot.dispatch().await;
         assert_eq!(
             resp!(bot, sent_messages, kind),
             vec![MockMarkup {
-                media_text: TgResponse::ChooseDeleteReminder.to_string(),
+                media_text: TgResponse::ChooseTrashedReminders.to_string(),
                 markup: InlineKeyboardMarkup {
                     inline_keyboard: vec![
                         vec![InlineKeyboardButton {
-                            text: "01.01 01:01 <>".to_string(),
+                            text: "♻️ 01.01 01:01 <>".to_string(),
                             kind: CallbackData(
-                                "delrem::rem_alt::1".to_string(),
+                                "trash::restore::rem::1".to_string(),
                             ),
-                        },],
+                        }],
                         vec![InlineKeyboardButton {
-                            text: "➡️".to_string(),
-                            kind: CallbackData("delrem::page::1".to_string(),),
-                        },],
+                            text: "♻️ Restore all".to_string(),
+                            kind: CallbackData(
+                                "trash::restore::all".to_string(),
+                            ),
+                        }],
                     ],
                 },
             }
@@ -438,64 +4877,493 @@ mod test {
 
         bot.update(
             MockCallbackQuery::new()
-                .data("delrem::page::1")
+                .data("trash::restore::rem::1")
                 .message(bot.get_responses().sent_messages[0].clone()),
         );
+        bot.dispatch_and_check_last_text(
+            &TgResponse::RestoredReminders(1).to_string(),
+        )
+        .await;
+        let edited = bot.get_responses().edited_messages_reply_markup;
+        assert_eq!(edited.len(), 1);
+        assert!(edited[0]
+            .message
+            .reply_markup()
+            .is_none_or(|markup| markup.inline_keyboard.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_restore() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let now = Utc::now().naive_utc();
+        let older = reminder::Model {
+            deleted_time: Some(now - TimeDelta::hours(1)),
+            ..basic_mock_reminder()
+        };
+        let last = reminder::Model {
+            id: 2,
+            desc: "last".to_owned(),
+            deleted_time: Some(now),
+            ..basic_mock_reminder()
+        };
+        let last_str = last
+            .clone()
+            .into_active_model()
+            .to_unescaped_string(mock_timezone());
+        db.expect_get_trashed_reminders().returning(move |_| {
+            Ok((vec![older.clone(), last.clone()], vec![]))
+        });
+        db.expect_restore_trashed_reminders()
+            .with(always(), eq(Some(LinkTarget::Reminder(2))))
+            .times(1)
+            .returning(|_, _| Ok(1));
+        let message = MockMessageText::new().text("/restore");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::Undone(last_str).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_restore_empty() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_trashed_reminders()
+            .returning(|_| Ok((vec![], vec![])));
+        db.expect_restore_trashed_reminders().never();
+        let message = MockMessageText::new().text("/restore");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&TgResponse::EmptyTrash.to_string())
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_import_backup_invalid() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_reminders().never();
+        // The mocked file content is "Hello, world!"
+        let message = MockMessageDocument::new().file_name(backup::FILE_NAME);
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::BackupInvalid.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_import_calendar_invalid() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_reminder().never();
+        // The mocked file content is "Hello, world!"
+        let message = MockMessageDocument::new().file_name("calendar.ics");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::CalendarInvalid.to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_broadcast_chats() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let delivery = MockMessageText::new()
+            .id(1_000_043)
+            .text("reminder")
+            .build();
+        db.expect_get_message_link()
+            .with(eq(delivery.chat.id.0), eq(delivery.id.0))
+            .returning(|chat_id, msg_id| {
+                Ok(Some(message_link::Model {
+                    id: 1,
+                    chat_id,
+                    msg_id,
+                    role: MessageRole::Delivery,
+                    reminder_id: Some(1),
+                    cron_reminder_id: None,
+                }))
+            });
+        db.expect_get_reminder()
+            .with(eq(1))
+            .returning(|_| Ok(Some(basic_mock_reminder())));
+        db.expect_delete_broadcast_chat()
+            .with(eq(LinkTarget::Reminder(1)), eq(-100123))
+            .times(1)
+            .returning(|_, _| Ok(true));
+        let message = MockMessageText::new()
+            .text("/broadcast stop -100123")
+            .reply_to_message(delivery);
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::BroadcastStopped(-100123).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_broadcast_usage() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let message = MockMessageText::new().text("/broadcast -100123");
+        let chat_id = message.chat.id.0;
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::BroadcastUsage(chat_id).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_copy() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        // Far above the ids the mock server gives to the sent messages,
+        // which would otherwise collide with it
+        let delivery = MockMessageText::new()
+            .id(1_000_000)
+            .text("reminder")
+            .build();
+        db.expect_get_message_link().returning(|chat_id, msg_id| {
+            Ok(Some(message_link::Model {
+                id: 1,
+                chat_id,
+                msg_id,
+                role: MessageRole::Delivery,
+                reminder_id: Some(1),
+                cron_reminder_id: None,
+            }))
+        });
+        db.expect_get_reminder()
+            .with(eq(1))
+            .returning(|_| Ok(Some(basic_mock_reminder())));
+        let copy = reminder::Model {
+            id: 2,
+            ..basic_mock_reminder()
+        }
+        .into_active_model();
+        let copy_str = copy.to_unescaped_string(mock_timezone());
+        db.expect_duplicate_reminder()
+            .with(eq(1))
+            .times(1)
+            .returning(move |_| Ok(Some(copy.clone())));
+        db.expect_insert_message_link()
+            .with(
+                always(),
+                always(),
+                eq(MessageRole::Confirmation),
+                eq(LinkTarget::Reminder(2)),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let message = MockMessageText::new()
+            .text("/copy")
+            .reply_to_message(delivery);
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessCopy(copy_str).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_copy_usage() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_duplicate_reminder().never();
+        let message = MockMessageText::new().text("/copy");
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(&TgResponse::CopyUsage.to_string())
+            .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_duplicate_from_edit_menu() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let copy = reminder::Model {
+            id: 2,
+            ..basic_mock_reminder()
+        };
+        let copy_clone = copy.clone();
+        let copy_str = copy
+            .clone()
+            .into_active_model()
+            .to_unescaped_string(mock_timezone());
+        db.expect_duplicate_reminder()
+            .with(eq(1))
+            .times(1)
+            .returning(move |_| {
+                Ok(Some(copy_clone.clone().into_active_model()))
+            });
+        db.expect_insert_message_link()
+            .returning(|_, _, _, _| Ok(()));
+        db.expect_get_reminder()
+            .with(eq(2))
+            .returning(move |_| Ok(Some(copy.clone())));
+        let cb_query = MockCallbackQuery::new()
+            .data("duplicate::rem::1")
+            .message(MockMessageText::new().build());
+        let bot = mock_bot(db, cb_query);
         bot.dispatch().await;
-        assert_eq!(
-            resp!(bot, edited_messages_reply_markup, message.kind),
-            vec![MockMarkup {
-                media_text: TgResponse::ChooseDeleteReminder.to_string(),
-                markup: InlineKeyboardMarkup {
-                    inline_keyboard: vec![vec![InlineKeyboardButton {
-                        text: "⬅️".to_string(),
-                        kind: CallbackData("delrem::page::0".to_string(),),
-                    },],],
-                },
-            }
-            .into()]
-        );
+        let texts: Vec<_> = bot
+            .get_responses()
+            .sent_messages
+            .iter()
+            .filter_map(|msg| msg.text().map(str::to_owned))
+            .collect();
+        assert_eq!(texts[0], TgResponse::SuccessCopy(copy_str).to_string());
+        assert_eq!(texts[1], TgResponse::EnterNewTimePattern.to_string());
+    }
 
-        bot.update(
-            MockCallbackQuery::new().data("delrem::page::0").message(
-                bot.get_responses().edited_messages_reply_markup[0]
-                    .message
-                    .clone(),
-            ),
+    #[tokio::test]
+    #[serial]
+    async fn test_postpone_from_edit_menu() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let rem = reminder::Model {
+            desc: "call mom".to_owned(),
+            ..basic_mock_reminder()
+        };
+        let postponed = reminder::Model {
+            time: rem.time + TimeDelta::weeks(1),
+            ..rem.clone()
+        };
+        let postponed_str = postponed
+            .clone()
+            .into_active_model()
+            .to_unescaped_string(mock_timezone());
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem.clone())));
+        db.expect_reschedule_reminder()
+            .with(eq(postponed))
+            .times(1)
+            .returning(|_| Ok(true));
+        db.expect_get_message_links().returning(|_, _| Ok(vec![]));
+        db.expect_insert_message_link()
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("edit_rem_mode::rem_shift::1")
+                .message(MockMessageText::new().build()),
         );
         bot.dispatch().await;
         assert_eq!(
-            resp!(bot, edited_messages_reply_markup, message.kind),
-            vec![MockMarkup {
-                media_text: TgResponse::ChooseDeleteReminder.to_string(),
-                markup: InlineKeyboardMarkup {
-                    inline_keyboard: vec![
-                        vec![InlineKeyboardButton {
-                            text: "01.01 01:01 <>".to_string(),
-                            kind: CallbackData(
-                                "delrem::rem_alt::1".to_string(),
-                            ),
-                        },],
-                        vec![InlineKeyboardButton {
-                            text: "➡️".to_string(),
-                            kind: CallbackData("delrem::page::1".to_string(),),
-                        },],
-                    ],
-                },
-            }
-            .into()]
+            resp!(bot, sent_messages, text().unwrap().to_owned()),
+            vec![
+                TgResponse::EnterShift.to_string(),
+                "`01.01 01:01`".to_owned()
+            ]
         );
+        assert!(matches!(
+            bot.get_state::<State>().await,
+            State::Edit {
+                id: 1,
+                mode: EditMode::Shift,
+                ..
+            }
+        ));
 
-        bot.update(
-            MockCallbackQuery::new().data("delrem::rem_alt::1").message(
-                bot.get_responses().edited_messages_reply_markup[0]
-                    .message
-                    .clone(),
-            ),
-        );
+        bot.update(MockMessageText::new().text("1w"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessShift("1w".to_owned(), postponed_str)
+                .to_string(),
+        )
+        .await;
+        assert!(matches!(bot.get_state::<State>().await, State::Default));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_delivery() {
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_broadcast_chats()
+            .with(eq(LinkTarget::Reminder(1)))
+            .returning(|_| Ok(vec![-100123, -100456]));
+        let delivery = MockMessageText::new().id(42).text("reminder").build();
+        super::broadcast(LinkTarget::Reminder(1), &delivery, &db, &api.bot())
+            .await;
+        let requests = api.requests();
+        assert_eq!(api.methods(), vec!["CopyMessage"; 2]);
+        assert_eq!(requests[0].1["chat_id"], -100123);
+        assert_eq!(requests[0].1["message_id"], 42);
+        assert_eq!(requests[1].1["chat_id"], -100456);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_delete_by_reply_to_delivery() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        let tz = mock_timezone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let delivery = MockMessageText::new()
+            .id(1_000_042)
+            .text("reminder")
+            .build();
+        db.expect_get_message_link()
+            .with(eq(delivery.chat.id.0), eq(delivery.id.0))
+            .returning(|chat_id, msg_id| {
+                Ok(Some(message_link::Model {
+                    id: 1,
+                    chat_id,
+                    msg_id,
+                    role: MessageRole::Delivery,
+                    reminder_id: Some(1),
+                    cron_reminder_id: None,
+                }))
+            });
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_get_reminder()
+            .with(eq(rem.id))
+            .returning(move |_| Ok(Some(rem_clone.clone())));
+        db.expect_trash_reminder()
+            .with(eq(rem.id))
+            .times(1)
+            .returning(|_| Ok(()));
+        let message = MockMessageText::new()
+            .text("/delete")
+            .reply_to_message(delivery);
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::SuccessDelete(
+                rem.into_active_model().to_unescaped_string(tz),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_new_reminder_duplicate() {
+        set_mock_now();
+        let message = MockMessageText::new().text("10:00 Test");
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_duplicate_reminder()
+            .withf(|_, _, desc| desc == "Test")
+            .returning(move |_, _, _| Ok(Some(rem_clone.clone())));
+        db.expect_insert_reminder().never();
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::DuplicateReminder(
+                rem.into_active_model().to_unescaped_string(tz),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_new_cron_reminder_duplicate() {
+        set_mock_now();
+        let message = MockMessageText::new().text("0 9 * * 1-5 stretch");
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        let cron_rem = cron_reminder::Model {
+            id: 2,
+            chat_id: 1,
+            cron_expr: "0 9 * * 1-5".to_owned(),
+            time: NaiveDateTime::default(),
+            desc: "Stretch".to_owned(),
+            user_id: Some(1),
+            paused: false,
+            policy: None,
+            version: 1,
+            media_kind: None,
+            media_file_id: None,
+            failures: 0,
+            retry_time: None,
+            thread_id: None,
+            deleted_time: None,
+        };
+        let cron_rem_clone = cron_rem.clone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_duplicate_cron_reminder()
+            .withf(|_, cron_expr, desc| {
+                cron_expr == "0 9 * * 1-5" && desc == "stretch"
+            })
+            .returning(move |_, _, _| Ok(Some(cron_rem_clone.clone())));
+        db.expect_insert_cron_reminder().never();
+        let bot = mock_bot(db, message);
+        bot.dispatch_and_check_last_text(
+            &TgResponse::DuplicateReminder(
+                cron_rem.into_active_model().to_unescaped_string(tz),
+            )
+            .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_new_reminder() {
+        set_mock_now();
+        let message = MockMessageText::new().text("10:00 test");
+        let mut db = MockDatabase::new();
+        let tz = mock_timezone();
+        let rem = basic_mock_reminder();
+        let rem_clone = rem.clone();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_duplicate_reminder()
+            .returning(|_, _, _| Ok(None));
+        db.expect_insert_reminder()
+            .returning(move |_| Ok(rem_clone.clone().into()));
+        db.expect_insert_message_link()
+            .with(
+                always(),
+                always(),
+                eq(MessageRole::Creation),
+                eq(LinkTarget::Reminder(rem.id)),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        db.expect_insert_message_link()
+            .with(
+                always(),
+                always(),
+                eq(MessageRole::Confirmation),
+                eq(LinkTarget::Reminder(rem.id)),
+            )
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(db, message);
         bot.dispatch_and_check_last_text(
-            &TgResponse::SuccessDelete(
-                rem.into_active_model().to_unescaped_string(mock_timezone()),
+            &TgResponse::SuccessInsert(
+                rem.into_active_model().to_unescaped_string(tz),
             )
             .to_string(),
         )
@@ -503,113 +5371,36 @@ mod test {
     }
 
     #[tokio::test]
-    async fn test_delete_still_one_page() {
-        const REMINDERS_COUNT: i64 = 45;
-        let message = MockMessageText::new().text("/delete");
+    #[serial]
+    async fn test_new_periodic_reminder() {
+        set_mock_now();
+        let message = MockMessageText::new().text("/1d 10:00 tea");
         let mut db = MockDatabase::new();
-        let mut rems = vec![];
-        for i in 1..=REMINDERS_COUNT {
-            let mut rem = basic_mock_reminder();
-            rem.id = i;
-            rems.push(rem);
-        }
-        let rems_clone = rems.clone();
-        db.expect_get_sorted_reminders().returning(move |_| {
-            Ok(rems_clone
-                .iter()
-                .map(|rem| -> Box<dyn GenericReminder> {
-                    Box::new(rem.clone().into_active_model())
-                })
-                .collect())
-        });
+        let tz = mock_timezone();
         db.expect_get_user_timezone_name()
             .returning(|_| Ok(Some(mock_timezone_name())));
-        for rem in rems.iter() {
-            let rem_clone = rem.clone();
-            db.expect_get_reminder()
-                .with(eq(rem.id))
-                .returning(move |_| Ok(Some(rem_clone.clone())));
-            db.expect_delete_reminder()
-                .with(eq(rem.id))
-                .returning(move |_| Ok(()));
-        }
+        db.expect_get_duplicate_reminder()
+            .returning(|_, _, _| Ok(None));
+        db.expect_insert_reminder().returning(|mut rem| {
+            rem.id = Set(1);
+            Ok(rem)
+        });
+        db.expect_insert_message_link()
+            .returning(|_, _, _, _| Ok(()));
         let bot = mock_bot(db, message);
-        bot.dispatch().await;
-        let mut page0_buttons = (1..=REMINDERS_COUNT)
-            .map(|i| {
-                vec![InlineKeyboardButton {
-                    text: "01.01 01:01 <>".to_string().to_string(),
-                    kind: CallbackData(
-                        format!("delrem::rem_alt::{}", i).to_string(),
-                    ),
-                }]
-            })
-            .collect::<Vec<_>>();
-        page0_buttons.push(vec![InlineKeyboardButton {
-            text: "➡️".to_string(),
-            kind: CallbackData("delrem::page::1".to_string()),
-        }]);
-        assert_eq!(
-            resp!(bot, sent_messages, kind),
-            vec![MockMarkup {
-                media_text: TgResponse::ChooseDeleteReminder.to_string(),
-                markup: InlineKeyboardMarkup {
-                    inline_keyboard: page0_buttons.clone(),
-                },
-            }
-            .into()]
-        );
-
-        bot.update(
-            MockCallbackQuery::new()
-                .data("delrem::page::1")
-                .message(bot.get_responses().sent_messages[0].clone()),
-        );
-        bot.dispatch().await;
-        assert_eq!(
-            resp!(bot, edited_messages_reply_markup, message.kind),
-            vec![MockMarkup {
-                media_text: TgResponse::ChooseDeleteReminder.to_string(),
-                markup: InlineKeyboardMarkup {
-                    inline_keyboard: vec![vec![InlineKeyboardButton {
-                        text: "⬅️".to_string(),
-                        kind: CallbackData("delrem::page::0".to_string(),),
-                    },],],
-                },
-            }
-            .into()]
-        );
-
-        bot.update(
-            MockCallbackQuery::new().data("delrem::page::0").message(
-                bot.get_responses().edited_messages_reply_markup[0]
-                    .message
-                    .clone(),
-            ),
-        );
-        bot.dispatch().await;
-        assert_eq!(
-            resp!(bot, edited_messages_reply_markup, message.kind),
-            vec![MockMarkup {
-                media_text: TgResponse::ChooseDeleteReminder.to_string(),
-                markup: InlineKeyboardMarkup {
-                    inline_keyboard: page0_buttons
-                },
-            }
-            .into()]
-        );
-
-        let rem = rems[0].clone();
-        bot.update(
-            MockCallbackQuery::new().data("delrem::rem_alt::1").message(
-                bot.get_responses().edited_messages_reply_markup[0]
-                    .message
-                    .clone(),
-            ),
-        );
+        let mut rem = parsers::parse_reminder("/1d 10:00 tea", 1, 1, tz)
+            .await
+            .unwrap();
+        rem.id = Set(1);
         bot.dispatch_and_check_last_text(
-            &TgResponse::SuccessDelete(
-                rem.into_active_model().to_unescaped_string(mock_timezone()),
+            &TgResponse::SuccessPeriodicInsert(
+                rem.to_unescaped_string(tz),
+                vec![
+                    "Sat 01.06 10:00".to_owned(),
+                    "Sun 02.06 10:00".to_owned(),
+                    "Mon 03.06 10:00".to_owned(),
+                    "Tue 04.06 10:00".to_owned(),
+                ],
             )
             .to_string(),
         )
@@ -617,132 +5408,418 @@ mod test {
     }
 
     #[tokio::test]
-    async fn test_delete_two_pages() {
-        const REMINDERS_COUNT: i64 = 46;
-        const PAGE_REMINDERS_COUNT: i64 = 45;
-        let message = MockMessageText::new().text("/delete");
+    #[serial]
+    async fn test_invalid_cron() {
+        set_mock_now();
         let mut db = MockDatabase::new();
-        let mut rems = vec![];
-        for i in 1..=REMINDERS_COUNT {
-            let mut rem = basic_mock_reminder();
-            rem.id = i;
-            rem.desc = i.to_string();
-            rems.push(rem);
-        }
-        let rems_clone = rems.clone();
-        db.expect_get_sorted_reminders().returning(move |_| {
-            Ok(rems_clone
-                .iter()
-                .map(|rem| -> Box<dyn GenericReminder> {
-                    Box::new(rem.clone().into_active_model())
-                })
-                .collect())
-        });
         db.expect_get_user_timezone_name()
             .returning(|_| Ok(Some(mock_timezone_name())));
-        for rem in rems.iter() {
-            let rem_clone = rem.clone();
-            db.expect_get_reminder()
-                .with(eq(rem.id))
-                .returning(move |_| Ok(Some(rem_clone.clone())));
-            db.expect_delete_reminder()
-                .with(eq(rem.id))
-                .returning(move |_| Ok(()));
+        db.expect_insert_cron_reminder().never();
+        let bot =
+            mock_bot(db, MockMessageText::new().text("*/5 * * * stretch"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::InvalidCron("it has 4 fields instead of 5".to_owned())
+                .to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_new_reminders_from_lines() {
+        set_mock_now();
+        let message =
+            MockMessageText::new().text("10:00 standup\n\nnonsense\n18:30 gym");
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let mut ids = 0;
+        db.expect_insert_reminder().times(2).returning(move |rem| {
+            ids += 1;
+            Ok(reminder::ActiveModel {
+                id: Set(ids),
+                ..rem
+            })
+        });
+        for id in [1, 2] {
+            db.expect_insert_message_link()
+                .with(
+                    always(),
+                    always(),
+                    eq(MessageRole::Creation),
+                    eq(LinkTarget::Reminder(id)),
+                )
+                .times(1)
+                .returning(|_, _, _, _| Ok(()));
         }
         let bot = mock_bot(db, message);
         bot.dispatch().await;
-        let mut page0_buttons = (1..=PAGE_REMINDERS_COUNT)
-            .map(|i| {
-                vec![InlineKeyboardButton {
-                    text: format!("01.01 01:01 <{}>", i).to_string(),
-                    kind: CallbackData(
-                        format!("delrem::rem_alt::{}", i).to_string(),
-                    ),
-                }]
+        let responses = bot.get_responses();
+        let text = responses.sent_messages.last().unwrap().text().unwrap();
+        let expected = [
+            TgResponse::BatchInsertHeader(2, 3),
+            TgResponse::BatchLineInserted("10:00 <standup>".to_owned()),
+            TgResponse::BatchLineFailed("nonsense".to_owned()),
+            TgResponse::BatchLineInserted("18:30 <gym>".to_owned()),
+        ]
+        .map(|response| response.to_string());
+        assert_eq!(text, expected.join("\n"));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_new_reminder_with_photo() {
+        set_mock_now();
+        let message = MockMessageText::new()
+            .text("10:00 test")
+            .reply_to_message(MockMessagePhoto::new().build());
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_reminder()
+            .withf(|rem| {
+                rem.media_kind == Set(Some(MediaKind::Photo))
+                    && rem.media_file_id
+                        == Set(Some(MockPhotoSize::FILE_ID.to_owned()))
             })
-            .collect::<Vec<_>>();
-        page0_buttons.push(vec![InlineKeyboardButton {
-            text: "➡️".to_string(),
-            kind: CallbackData("delrem::page::1".to_string()),
-        }]);
-        let mut page1_buttons = (PAGE_REMINDERS_COUNT + 1..=REMINDERS_COUNT)
-            .map(|i| {
-                vec![InlineKeyboardButton {
-                    text: format!("01.01 01:01 <{}>", i).to_string(),
-                    kind: CallbackData(
-                        format!("delrem::rem_alt::{}", i).to_string(),
-                    ),
-                }]
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(1), ..rem }));
+        db.expect_insert_message_link()
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
+    }
+
+    #[tokio::test]
+    async fn test_send_attachment() {
+        let api = FlakyApi::start();
+        let reminder = reminder::Model {
+            media_kind: Some(MediaKind::Voice),
+            media_file_id: Some("voice".to_owned()),
+            ..basic_mock_reminder()
+        };
+        let mut db = MockDatabase::new();
+        db.expect_get_message_links().returning(|_, _| Ok(vec![]));
+        super::send_reminder(
+            &reminder,
+            false,
+            mock_timezone(),
+            &db,
+            &api.bot(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(api.methods(), vec!["SendMessage", "SendVoice"]);
+    }
+
+    #[tokio::test]
+    async fn test_send_to_topic() {
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_message_links().returning(|_, _| Ok(vec![]));
+        let reminder = reminder::Model {
+            thread_id: Some(7),
+            ..basic_mock_reminder()
+        };
+        super::send_reminder(
+            &reminder,
+            false,
+            mock_timezone(),
+            &db,
+            &api.bot(),
+        )
+        .await
+        .unwrap();
+        let requests = api.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].1["message_thread_id"], 7);
+    }
+
+    #[tokio::test]
+    async fn test_send_reply() {
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_message_links()
+            .with(eq(LinkTarget::Reminder(1)), eq(MessageRole::Subject))
+            .returning(|_, role| {
+                Ok(vec![message_link::Model {
+                    id: 1,
+                    chat_id: 1,
+                    msg_id: 42,
+                    role,
+                    reminder_id: Some(1),
+                    cron_reminder_id: None,
+                }])
+            });
+        super::send_reminder(
+            &basic_mock_reminder(),
+            false,
+            mock_timezone(),
+            &db,
+            &api.bot(),
+        )
+        .await
+        .unwrap();
+        let requests = api.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].1["reply_parameters"]["message_id"], 42);
+        assert!(requests[0].1["reply_parameters"]["quote"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_send_quote() {
+        let api = FlakyApi::start();
+        api.fail([Failure::BadRequest("Bad Request: QUOTE_TEXT_INVALID")]);
+        let mut db = MockDatabase::new();
+        db.expect_get_message_links()
+            .with(always(), eq(MessageRole::Subject))
+            .returning(|_, _| Ok(vec![]));
+        db.expect_get_message_links()
+            .with(eq(LinkTarget::Reminder(1)), eq(MessageRole::Creation))
+            .returning(|_, role| {
+                Ok(vec![message_link::Model {
+                    id: 1,
+                    chat_id: 1,
+                    msg_id: 42,
+                    role,
+                    reminder_id: Some(1),
+                    cron_reminder_id: None,
+                }])
+            });
+        let reminder = reminder::Model {
+            desc: "call mom".to_owned(),
+            ..basic_mock_reminder()
+        };
+        super::send_reminder(
+            &reminder,
+            false,
+            mock_timezone(),
+            &db,
+            &api.bot(),
+        )
+        .await
+        .unwrap();
+        let requests = api.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].1["reply_parameters"]["quote"], "call mom");
+        // The quote didn't match, the message is replied to without it
+        assert_eq!(requests[1].1["reply_parameters"]["message_id"], 42);
+        assert!(requests[1].1["reply_parameters"]["quote"].is_null());
+    }
+
+    fn quiet_settings(user_id: i64) -> user_settings::Model {
+        user_settings::Model {
+            quiet_from: Some(23 * 60),
+            quiet_until: Some(8 * 60),
+            ..settings::defaults(user_id)
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_send_held() {
+        set_mock_now();
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings()
+            .returning(|user_id| Ok(Some(quiet_settings(user_id))));
+        let reminder = reminder::Model {
+            user_id: Some(1),
+            ..basic_mock_reminder()
+        };
+        let due = reminder.time;
+        db.expect_insert_reminder()
+            .withf(move |rem| {
+                // 8:00 in Amsterdam
+                rem.time
+                    == Set(NaiveDate::from_ymd_opt(2024, 6, 1)
+                        .unwrap()
+                        .and_hms_opt(6, 0, 0)
+                        .unwrap())
+                    && rem.held_since == Set(Some(due))
+                    && rem.pattern == Set(None)
             })
-            .collect::<Vec<_>>();
-        page1_buttons.push(vec![
-            InlineKeyboardButton {
-                text: "⬅️".to_string(),
-                kind: CallbackData("delrem::page::0".to_string()),
-            },
-            InlineKeyboardButton {
-                text: "➡️".to_string(),
-                kind: CallbackData("delrem::page::2".to_string()),
-            },
-        ]);
-        assert_eq!(
-            resp!(bot, sent_messages, kind),
-            vec![MockMarkup {
-                media_text: TgResponse::ChooseDeleteReminder.to_string(),
-                markup: InlineKeyboardMarkup {
-                    inline_keyboard: page0_buttons.clone(),
-                },
-            }
-            .into()]
-        );
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(2), ..rem }));
+        db.expect_move_message_links()
+            .with(eq(LinkTarget::Reminder(1)), eq(LinkTarget::Reminder(2)))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let msg = super::send_reminder(
+            &reminder,
+            false,
+            mock_timezone(),
+            &db,
+            &api.bot(),
+        )
+        .await
+        .unwrap();
+        assert!(msg.is_none());
+        assert!(api.methods().is_empty());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_send_quietly() {
+        set_mock_now();
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_message_links().returning(|_, _| Ok(vec![]));
+        db.expect_get_user_settings().returning(|user_id| {
+            Ok(Some(user_settings::Model {
+                quiet_silent: true,
+                ..quiet_settings(user_id)
+            }))
+        });
+        db.expect_insert_reminder().never();
+        let reminder = reminder::Model {
+            user_id: Some(1),
+            ..basic_mock_reminder()
+        };
+        super::send_reminder(
+            &reminder,
+            false,
+            mock_timezone(),
+            &db,
+            &api.bot(),
+        )
+        .await
+        .unwrap();
+        let requests = api.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].1["disable_notification"], true);
+    }
 
-        bot.update(
-            MockCallbackQuery::new()
-                .data("delrem::page::1")
-                .message(bot.get_responses().sent_messages[0].clone()),
-        );
-        bot.dispatch().await;
-        assert_eq!(
-            resp!(bot, edited_messages_reply_markup, message.kind),
-            vec![MockMarkup {
-                media_text: TgResponse::ChooseDeleteReminder.to_string(),
-                markup: InlineKeyboardMarkup {
-                    inline_keyboard: page1_buttons,
-                },
-            }
-            .into()]
-        );
+    #[tokio::test]
+    async fn test_send_held_note() {
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_message_links().returning(|_, _| Ok(vec![]));
+        let reminder = reminder::Model {
+            held_since: Some(basic_mock_reminder().time),
+            ..basic_mock_reminder()
+        };
+        super::send_reminder(
+            &reminder,
+            false,
+            mock_timezone(),
+            &db,
+            &api.bot(),
+        )
+        .await
+        .unwrap();
+        let requests = api.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].1["text"].as_str().unwrap().ends_with(
+            &TgResponse::HeldDuringQuietHours("01:01".to_owned()).to_string()
+        ));
+    }
 
-        bot.update(
-            MockCallbackQuery::new().data("delrem::page::0").message(
-                bot.get_responses().edited_messages_reply_markup[0]
-                    .message
-                    .clone(),
-            ),
-        );
-        bot.dispatch().await;
+    #[tokio::test]
+    async fn test_send_missed_note() {
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_message_links().returning(|_, _| Ok(vec![]));
+        super::send_reminder(
+            &basic_mock_reminder(),
+            true,
+            mock_timezone(),
+            &db,
+            &api.bot(),
+        )
+        .await
+        .unwrap();
+        let requests = api.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].1["text"].as_str().unwrap().ends_with(
+            &TgResponse::MissedDuringDowntime("01:01".to_owned()).to_string()
+        ));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_catch_up_summary() {
+        set_mock_now();
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|user_id| {
+            Ok(Some(user_settings::Model {
+                catch_up: Some(0),
+                ..settings::defaults(user_id)
+            }))
+        });
+        db.expect_start_sending()
+            .with(eq(LinkTarget::Reminder(1)))
+            .times(1)
+            .returning(|_| Ok(()));
+        db.expect_advance_reminder()
+            .withf(|&id, next| id == 1 && next.is_none())
+            .times(1)
+            .returning(|_, _| Ok(None));
+        let reminder = reminder::Model {
+            user_id: Some(1),
+            desc: "water the plants".to_owned(),
+            ..basic_mock_reminder()
+        };
+        let entry = super::send_due_reminder(
+            reminder,
+            mock_timezone(),
+            false,
+            &[],
+            &db,
+            &api.bot(),
+        )
+        .await;
         assert_eq!(
-            resp!(bot, edited_messages_reply_markup, message.kind),
-            vec![MockMarkup {
-                media_text: TgResponse::ChooseDeleteReminder.to_string(),
-                markup: InlineKeyboardMarkup {
-                    inline_keyboard: page0_buttons
-                },
-            }
-            .into()]
+            entry,
+            Some(
+                TgResponse::CatchUpEntry(
+                    "01.01 01:01".to_owned(),
+                    "water the plants".to_owned()
+                )
+                .to_string()
+            )
         );
+        assert!(api.requests().is_empty());
+    }
 
-        let rem = rems[0].clone();
-        bot.update(
-            MockCallbackQuery::new().data("delrem::rem_alt::1").message(
-                bot.get_responses().edited_messages_reply_markup[0]
-                    .message
-                    .clone(),
-            ),
+    #[tokio::test]
+    async fn test_quiet() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        db.expect_insert_or_update_user_settings()
+            .withf(|settings| {
+                settings.quiet_from == Some(23 * 60)
+                    && settings.quiet_until == Some(8 * 60)
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot =
+            mock_bot(db, MockMessageText::new().text("/quiet 23:00-8:00"));
+        bot.dispatch_and_check_last_text(
+            &TgResponse::QuietHoursSet("23:00–08:00".to_owned()).to_string(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_day_times() {
+        let mut db = MockDatabase::new();
+        db.expect_get_user_settings().returning(|_| Ok(None));
+        db.expect_insert_or_update_user_settings()
+            .withf(|settings| {
+                settings.evening == Some(20 * 60) && settings.morning.is_none()
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockMessageText::new().text("/daytimes evening 20:00"),
         );
         bot.dispatch_and_check_last_text(
-            &TgResponse::SuccessDelete(
-                rem.into_active_model().to_unescaped_string(mock_timezone()),
+            &TgResponse::DayTimesSet(
+                "morning 09:00, afternoon 14:00, evening 20:00, night 22:00"
+                    .to_owned(),
             )
             .to_string(),
         )
@@ -750,156 +5827,398 @@ mod test {
     }
 
     #[tokio::test]
-    async fn test_list_no_timezone() {
+    #[serial]
+    async fn test_digest() {
+        set_mock_now();
         let mut db = MockDatabase::new();
-        db.expect_get_user_timezone_name().returning(|_| Ok(None));
-        let message = MockMessageText::new().text("/list");
-        let bot = mock_bot(db, message);
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_insert_or_update_digest()
+            .withf(|digest| {
+                digest.period == "daily"
+                    && digest.minute == 8 * 60 + 30
+                    // 8:30 in Amsterdam
+                    && digest.next_time
+                        == NaiveDate::from_ymd_opt(2024, 6, 1)
+                            .unwrap()
+                            .and_hms_opt(6, 30, 0)
+                            .unwrap()
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot =
+            mock_bot(db, MockMessageText::new().text("/digest daily 8:30"));
         bot.dispatch_and_check_last_text(
-            &TgResponse::SelectTimezone.to_string(),
+            &TgResponse::DigestSet("every day at 08:30".to_owned()).to_string(),
         )
         .await;
     }
 
     #[tokio::test]
-    async fn test_list_no_reminders() {
+    #[serial]
+    async fn test_send_digests() {
+        set_mock_now();
+        let api = FlakyApi::start();
         let mut db = MockDatabase::new();
+        db.expect_get_due_digests().returning(|| {
+            Ok(vec![digest::schedule(
+                1,
+                1,
+                digest::Period::Daily,
+                0,
+                mock_timezone(),
+                now_time() - TimeDelta::minutes(1),
+            )])
+        });
         db.expect_get_user_timezone_name()
             .returning(|_| Ok(Some(mock_timezone_name())));
-        db.expect_get_sorted_reminders().returning(|_| Ok(vec![]));
-        let message = MockMessageText::new().text("/list");
-        let bot = mock_bot(db, message);
+        db.expect_insert_or_update_digest()
+            .withf(|digest| digest.next_time == now_time() + TimeDelta::days(1))
+            .times(1)
+            .returning(|_| Ok(()));
+        db.expect_search_reminders()
+            .times(1)
+            .returning(|_, filter| {
+                assert_eq!(filter.until, Some(now_time() + TimeDelta::days(1)));
+                Ok(vec![Box::new(
+                    reminder::Model {
+                        time: now_time() + TimeDelta::hours(9),
+                        desc: "standup".to_owned(),
+                        ..basic_mock_reminder()
+                    }
+                    .into_active_model(),
+                )])
+            });
+        super::send_digests(&BTreeMap::new(), &db, &api.bot()).await;
+        let requests = api.requests();
+        assert_eq!(requests.len(), 1);
+        let text = requests[0].1["text"].as_str().unwrap();
+        assert!(text.starts_with(
+            &TgResponse::DigestHeader(false, "Sat 01.06".to_owned())
+                .to_string()
+        ));
+        assert!(text.contains("standup"));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_vacation() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        db.expect_get_vacation().returning(|_| Ok(None));
+        db.expect_insert_or_update_vacation()
+            .withf(|vacation| {
+                vacation.since == now_time()
+                    && vacation.digest
+                    // the start of 15.08 in Amsterdam
+                    && vacation.until
+                        == NaiveDate::from_ymd_opt(2024, 8, 14)
+                            .unwrap()
+                            .and_hms_opt(22, 0, 0)
+                            .unwrap()
+            })
+            .times(1)
+            .returning(|_| Ok(()));
+        let bot = mock_bot(
+            db,
+            MockMessageText::new().text("/vacation until 15.08 digest"),
+        );
         bot.dispatch_and_check_last_text(
-            &TgResponse::RemindersListHeader.to_string(),
+            &TgResponse::VacationSet("Thu 15.08".to_owned(), true).to_string(),
         )
         .await;
     }
 
     #[tokio::test]
-    async fn test_list_one_reminder() {
+    #[serial]
+    async fn test_end_vacations() {
+        set_mock_now();
+        let api = FlakyApi::start();
         let mut db = MockDatabase::new();
-        let tz = mock_timezone();
+        let vacation = |chat_id, until| vacation::Model {
+            chat_id,
+            user_id: chat_id,
+            since: now_time() - TimeDelta::days(7),
+            until,
+            digest: true,
+        };
+        let ongoing = now_time() + TimeDelta::days(1);
+        db.expect_get_vacations().returning(move || {
+            Ok(vec![vacation(1, now_time()), vacation(2, ongoing)])
+        });
+        db.expect_delete_vacation()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(()));
         db.expect_get_user_timezone_name()
             .returning(|_| Ok(Some(mock_timezone_name())));
-        let rem = basic_mock_reminder();
-        let rem_clone = rem.clone();
-        db.expect_get_sorted_reminders().returning(move |_| {
-            Ok(vec![Box::new(rem_clone.clone().into_active_model())])
-        });
-        let message = MockMessageText::new().text("/list");
-        let bot = mock_bot(db, message);
-        bot.dispatch_and_check_last_text(&format!(
-            "{}\n{}",
-            TgResponse::RemindersListHeader,
-            rem.into_active_model().to_string(tz)
-        ))
+        db.expect_get_missed_events()
+            .with(eq(1), eq(now_time() - TimeDelta::days(7)))
+            .returning(|chat_id, since| {
+                Ok(vec![reminder_event::Model {
+                    id: 1,
+                    chat_id,
+                    time: since + TimeDelta::days(1),
+                    desc: "water the plants".to_owned(),
+                    kind: EventKind::Missed,
+                    occurrence_id: None,
+                    note: None,
+                }])
+            });
+        let vacations = super::end_vacations(&db, &api.bot()).await;
+        assert_eq!(vacations, BTreeMap::from([(2, ongoing)]));
+        let requests = api.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].1["chat_id"], 1);
+        let text = requests[0].1["text"].as_str().unwrap();
+        assert!(text.starts_with(&TgResponse::VacationMissed(1).to_string()));
+        assert!(text.contains("water the plants"));
+    }
+
+    #[tokio::test]
+    async fn test_send_once_interrupted() {
+        let api = FlakyApi::start();
+        let bot = api.bot();
+        let db = MockDatabase::new();
+        let sent = super::send_once(
+            LinkTarget::Reminder(1),
+            &[LinkTarget::Reminder(1)],
+            &db,
+            async {
+                Ok(Some(tg::send_message("text", &bot, ChatId(1)).await?))
+            },
+        )
         .await;
+        assert!(matches!(sent, Ok(None)));
+        assert!(api.requests().is_empty());
     }
 
     #[tokio::test]
-    async fn test_pause() {
+    async fn test_send_once_failed() {
+        let api = FlakyApi::start();
+        let bot = api.bot();
+        api.fail([Failure::BadRequest("Bad Request: chat not found")]);
         let mut db = MockDatabase::new();
-        let tz = mock_timezone();
-        db.expect_get_user_timezone_name()
-            .returning(|_| Ok(Some(mock_timezone_name())));
-        let rem = basic_mock_reminder();
-        let rem_clone = rem.clone();
-        db.expect_get_reminder()
-            .with(eq(rem.id))
-            .returning(move |_| Ok(Some(rem_clone.clone())));
-        db.expect_toggle_reminder_paused()
-            .with(eq(rem.id))
+        db.expect_start_sending()
+            .with(eq(LinkTarget::CronReminder(2)))
             .times(1)
-            .returning(move |_| Ok(true));
-        db.expect_toggle_reminder_paused()
-            .with(eq(rem.id))
+            .returning(|_| Ok(()));
+        db.expect_stop_sending()
+            .with(eq(LinkTarget::CronReminder(2)))
             .times(1)
-            .returning(move |_| Ok(false));
-        let rem_clone = rem.clone();
-        db.expect_get_sorted_reminders().returning(move |_| {
-            Ok(vec![Box::new(rem_clone.clone().into_active_model())])
-        });
-        let message = MockMessageText::new().text("/pause");
-        let bot = mock_bot(db, message);
-
-        bot.dispatch().await;
-        assert_eq!(
-            resp!(bot, sent_messages, kind),
-            vec![
-                MockMarkup {
-                    media_text: TgResponse::ChoosePauseReminder.to_string(),
-                    markup: InlineKeyboardMarkup {
-                        inline_keyboard: vec![
-                            vec![InlineKeyboardButton {
-                                text: "01.01 01:01 <>".to_string(),
-                                kind: CallbackData(
-                                    "pauserem::rem_alt::1".to_string(),
-                                ),
-                            },],
-                            vec![InlineKeyboardButton {
-                                text: "➡️".to_string(),
-                                kind: CallbackData(
-                                    "pauserem::page::1".to_string(),
-                                ),
-                            },],
-                        ],
-                    },
-                }
-                .into()
-            ]
-        );
+            .returning(|_| Ok(()));
+        let sent = super::send_once(
+            LinkTarget::CronReminder(2),
+            &[LinkTarget::Reminder(2)],
+            &db,
+            async {
+                Ok(Some(tg::send_message("text", &bot, ChatId(1)).await?))
+            },
+        )
+        .await;
+        assert!(sent.is_err());
+        assert_eq!(api.methods(), vec!["SendMessage"]);
+    }
 
-        bot.update(
-            MockCallbackQuery::new()
-                .data("pauserem::rem_alt::1")
-                .message(bot.get_responses().sent_messages[0].clone()),
-        );
-        bot.dispatch_and_check_last_text(
-            &TgResponse::SuccessPause(
-                rem.clone().into_active_model().to_unescaped_string(tz),
+    #[tokio::test]
+    #[serial]
+    async fn test_retry_later() {
+        set_mock_now();
+        let mut db = MockDatabase::new();
+        db.expect_add_reminder_event()
+            .withf(|event, _| {
+                event.kind == Set(EventKind::Failed)
+                    && event.desc == Set("desc".to_owned())
+            })
+            .times(1)
+            .returning(|_, _| Ok(()));
+        db.expect_record_send_failure()
+            .with(
+                eq(LinkTarget::Reminder(1)),
+                eq(2),
+                eq(Some(now_time() + TimeDelta::seconds(60))),
             )
-            .to_string(),
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        db.expect_record_send_failure()
+            .with(eq(LinkTarget::CronReminder(2)), eq(5), eq(None))
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+        let err = || crate::err::Error::ReminderNotFound(1);
+        super::retry_later(LinkTarget::Reminder(1), 1, "desc", 1, err(), &db)
+            .await;
+        super::retry_later(
+            LinkTarget::CronReminder(2),
+            1,
+            "desc",
+            4,
+            err(),
+            &db,
         )
         .await;
+    }
 
-        bot.update(
-            MockCallbackQuery::new()
-                .data("pauserem::rem_alt::1")
-                .message(bot.get_responses().sent_messages[0].clone()),
+    #[tokio::test]
+    async fn test_retry_later_blocked() {
+        let mut db = MockDatabase::new();
+        db.expect_fail_chat_reminders()
+            .with(eq(1))
+            .times(1)
+            .returning(|_| Ok(()));
+        db.expect_add_reminder_event()
+            .withf(|event, _| event.kind == Set(EventKind::Failed))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        db.expect_record_send_failure().never();
+        let err = crate::err::Error::TeloxideRequest(
+            teloxide::RequestError::Api(teloxide::ApiError::BotBlocked),
         );
-        bot.dispatch_and_check_last_text(
-            &TgResponse::SuccessResume(
-                rem.into_active_model().to_unescaped_string(tz),
-            )
-            .to_string(),
+        super::retry_later(LinkTarget::Reminder(1), 1, "desc", 0, err, &db)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_send_with_settings() {
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_message_links().returning(|_, _| Ok(vec![]));
+        db.expect_get_user_settings()
+            .with(eq(1))
+            .returning(|user_id| {
+                Ok(Some(user_settings::Model {
+                    silent: true,
+                    snooze: Some(300),
+                    ..settings::defaults(user_id)
+                }))
+            });
+        db.expect_insert_occurrence()
+            .withf(|occ| {
+                occ.policy
+                    == Set(Some(
+                        r#"{"silent":true,"pin":false,"pin_until_done":false,"autodelete":null,"mention":"creator","nag":null,"snooze":300,"priority":null}"#
+                            .to_owned(),
+                    ))
+            })
+            .times(1)
+            .returning(|occ| Ok(occurrence::ActiveModel { id: Set(7), ..occ }));
+        let reminder = reminder::Model {
+            user_id: Some(1),
+            ..basic_mock_reminder()
+        };
+        super::send_reminder(
+            &reminder,
+            false,
+            mock_timezone(),
+            &db,
+            &api.bot(),
         )
-        .await;
+        .await
+        .unwrap();
+        let requests = api.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].1["disable_notification"], true);
+        let button = &requests[1].1["reply_markup"]["inline_keyboard"][0][0];
+        assert_eq!(button["text"], "💤 Snooze 5m");
+        assert_eq!(button["callback_data"], "snooze::7");
+    }
+
+    #[tokio::test]
+    async fn test_send_pinned_until_done() {
+        let api = FlakyApi::start();
+        let mut db = MockDatabase::new();
+        db.expect_get_message_links().returning(|_, _| Ok(vec![]));
+        db.expect_get_user_settings()
+            .with(eq(1))
+            .returning(|user_id| {
+                Ok(Some(user_settings::Model {
+                    pin_until_done: true,
+                    ..settings::defaults(user_id)
+                }))
+            });
+        db.expect_insert_occurrence()
+            .times(1)
+            .returning(|occ| Ok(occurrence::ActiveModel { id: Set(7), ..occ }));
+        let reminder = reminder::Model {
+            user_id: Some(1),
+            ..basic_mock_reminder()
+        };
+        super::send_reminder(
+            &reminder,
+            false,
+            mock_timezone(),
+            &db,
+            &api.bot(),
+        )
+        .await
+        .unwrap();
+        let requests = api.requests();
+        assert_eq!(
+            requests
+                .iter()
+                .map(|(method, _)| method.as_str())
+                .collect::<Vec<_>>(),
+            vec!["SendMessage", "PinChatMessage", "EditMessageReplyMarkup"]
+        );
+        let buttons = &requests[2].1["reply_markup"]["inline_keyboard"][0];
+        assert_eq!(buttons.as_array().unwrap().len(), 1);
+        assert_eq!(buttons[0]["callback_data"], "donerem::occ::7");
+    }
+
+    #[tokio::test]
+    async fn test_done_unpins() {
+        let message = MockMessageText::new().text("reminder").build();
+        let msg_id = message.id.0;
+        let mut db = MockDatabase::new();
+        db.expect_get_occurrence().with(eq(1)).returning(move |id| {
+            Ok(Some(occurrence::Model {
+                msg_id,
+                policy: Some(r#"{"pin_until_done":true}"#.to_owned()),
+                ..mock_occurrence(id)
+            }))
+        });
+        db.expect_mark_occurrence_done().returning(|_, _| Ok(()));
+        db.expect_get_user_timezone_name()
+            .returning(|_| Ok(Some(mock_timezone_name())));
+        let bot = mock_bot(
+            db,
+            MockCallbackQuery::new()
+                .data("donerem::occ::1")
+                .message(message),
+        );
+        bot.dispatch().await;
+        let unpinned = bot.get_responses().unpinned_chat_messages;
+        assert_eq!(unpinned.len(), 1);
+        assert_eq!(unpinned[0].message_id, Some(msg_id));
     }
 
     #[tokio::test]
     #[serial]
-    async fn test_new_reminder() {
-        *TEST_TIMESTAMP.write().unwrap() = mock_timezone()
-            .with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
-            .unwrap()
-            .timestamp();
-        let message = MockMessageText::new().text("10:00 test");
+    async fn test_new_reminder_about_reply() {
+        set_mock_now();
+        let message = MockMessageText::new()
+            .text("10:00")
+            .reply_to_message(MockMessageText::new().text("call mom").build());
         let mut db = MockDatabase::new();
-        let tz = mock_timezone();
-        let rem = basic_mock_reminder();
-        let rem_clone = rem.clone();
         db.expect_get_user_timezone_name()
             .returning(|_| Ok(Some(mock_timezone_name())));
         db.expect_insert_reminder()
-            .returning(move |_| Ok(rem_clone.clone().into()));
-        db.expect_set_reminder_reply_id().returning(|_, _| Ok(()));
-        let bot = mock_bot(db, message);
-        bot.dispatch_and_check_last_text(
-            &TgResponse::SuccessInsert(
-                rem.into_active_model().to_unescaped_string(tz),
+            .withf(|rem| rem.desc == Set("call mom".to_owned()))
+            .times(1)
+            .returning(|rem| Ok(reminder::ActiveModel { id: Set(1), ..rem }));
+        db.expect_insert_message_link()
+            .with(
+                always(),
+                always(),
+                eq(MessageRole::Subject),
+                eq(LinkTarget::Reminder(1)),
             )
-            .to_string(),
-        )
-        .await;
+            .times(1)
+            .returning(|_, _, _, _| Ok(()));
+        db.expect_insert_message_link()
+            .returning(|_, _, _, _| Ok(()));
+        let bot = mock_bot(db, message);
+        bot.dispatch().await;
     }
 }
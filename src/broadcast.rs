@@ -0,0 +1,40 @@
+//! Reminders broadcast to several chats: an administrator replies to
+//! a reminder with `/broadcast <chat id>` and each of its notifications
+//! is copied to that chat as well, e.g. to several team groups at once
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Action {
+    /// Show the chats the reminder is broadcast to
+    List,
+    Add(i64),
+    Remove(i64),
+}
+
+/// Action of the /broadcast arguments: a chat id adds the chat,
+/// `stop <chat id>` removes it and nothing lists the chats
+pub(crate) fn parse_args(args: &str) -> Option<Action> {
+    let words: Vec<_> = args.split_whitespace().collect();
+    match words[..] {
+        [] => Some(Action::List),
+        [chat_id] => chat_id.parse().ok().map(Action::Add),
+        [stop, chat_id] if stop.eq_ignore_ascii_case("stop") => {
+            chat_id.parse().ok().map(Action::Remove)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("" => Some(Action::List) ; "list")]
+    #[test_case("-1001234567890" => Some(Action::Add(-1001234567890)) ; "add")]
+    #[test_case("STOP -100123" => Some(Action::Remove(-100123)) ; "remove")]
+    #[test_case("team" => None ; "not a chat id")]
+    #[test_case("-100123 -100456" => None ; "several chats")]
+    fn test_parse_args(args: &str) -> Option<Action> {
+        parse_args(args)
+    }
+}
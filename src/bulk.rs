@@ -0,0 +1,163 @@
+//! Deleting or pausing the reminders of a chat all at once with /deleteall
+//! and /pauseall, or only the ones matching arguments like those of /list,
+//! e.g. `/deleteall paused` or `/deleteall before 01.01.2025`. Nothing
+//! changes until the button under the confirmation is pressed.
+
+use crate::lang::Language;
+use crate::search::Filter;
+use crate::tg;
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+};
+
+const CALLBACK_PREFIX: &str = "bulk::";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Action {
+    Delete,
+    /// Pause the active reminders, the paused ones stay as they are
+    Pause,
+}
+
+impl Action {
+    const ALL: [Self; 2] = [Self::Delete, Self::Pause];
+
+    fn code(self) -> &'static str {
+        match self {
+            Self::Delete => "del",
+            Self::Pause => "pause",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.code() == code)
+    }
+}
+
+/// Pressed button of the confirmation
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Callback {
+    /// Apply the action to the reminders matching the arguments
+    Confirm(Action, String),
+    Cancel,
+}
+
+/// Reminders the action applies to with the arguments
+pub(crate) fn filter(
+    action: Action,
+    args: &str,
+    user_tz: Tz,
+    now: NaiveDateTime,
+) -> Filter {
+    let filter = Filter::parse(args, user_tz, now);
+    match action {
+        Action::Delete => filter,
+        Action::Pause => Filter {
+            paused: Some(false),
+            ..filter
+        },
+    }
+}
+
+/// Buttons confirming or cancelling the action, `None` if the arguments
+/// don't fit into the callback data
+pub(crate) fn markup(
+    action: Action,
+    args: &str,
+    lang: Language,
+) -> Option<InlineKeyboardMarkup> {
+    let confirm_data =
+        format!("{}{}::{}", CALLBACK_PREFIX, action.code(), args.trim());
+    if confirm_data.len() > tg::CALLBACK_DATA_MAX_LEN {
+        return None;
+    }
+    let label = match action {
+        Action::Delete => lang.text("button_delete", &[]),
+        Action::Pause => lang.text("button_pause", &[]),
+    };
+    Some(InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::new(
+            label,
+            InlineKeyboardButtonKind::CallbackData(confirm_data),
+        ),
+        InlineKeyboardButton::new(
+            lang.text("button_cancel", &[]),
+            InlineKeyboardButtonKind::CallbackData(format!(
+                "{}cancel",
+                CALLBACK_PREFIX
+            )),
+        ),
+    ]]))
+}
+
+pub(crate) fn parse_callback(cb_data: &str) -> Option<Callback> {
+    let data = cb_data.strip_prefix(CALLBACK_PREFIX)?;
+    if data == "cancel" {
+        return Some(Callback::Cancel);
+    }
+    let (code, args) = data.split_once("::")?;
+    Some(Callback::Confirm(Action::from_code(code)?, args.to_owned()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("bulk::del::paused" => Some(Callback::Confirm(Action::Delete, "paused".to_owned())) ; "delete paused")]
+    #[test_case("bulk::pause::" => Some(Callback::Confirm(Action::Pause, "".to_owned())) ; "pause all")]
+    #[test_case("bulk::cancel" => Some(Callback::Cancel) ; "cancel")]
+    #[test_case("bulk::archive::" => None ; "unknown action")]
+    #[test_case("delrem::rem_alt::1" => None ; "other prefix")]
+    fn test_parse_callback(cb_data: &str) -> Option<Callback> {
+        parse_callback(cb_data)
+    }
+
+    #[test]
+    fn test_filter() {
+        let now =
+            NaiveDateTime::parse_from_str("2024-06-01 10:00", "%Y-%m-%d %H:%M")
+                .unwrap();
+        let tz = chrono_tz::Europe::Amsterdam;
+        assert_eq!(
+            filter(Action::Delete, "paused", tz, now).paused,
+            Some(true)
+        );
+        assert_eq!(
+            filter(Action::Pause, "paused", tz, now).paused,
+            Some(false)
+        );
+        assert!(filter(Action::Delete, "", tz, now).is_empty());
+    }
+
+    #[test]
+    fn test_markup_roundtrip() {
+        let markup =
+            markup(Action::Delete, " before 01.01.2025 ", Language::English)
+                .unwrap();
+        let InlineKeyboardButtonKind::CallbackData(ref data) =
+            markup.inline_keyboard[0][0].kind
+        else {
+            panic!("no callback data");
+        };
+        assert_eq!(
+            parse_callback(data),
+            Some(Callback::Confirm(
+                Action::Delete,
+                "before 01.01.2025".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_markup_too_long() {
+        assert!(markup(
+            Action::Pause,
+            &"meeting ".repeat(10),
+            Language::English
+        )
+        .is_none());
+    }
+}
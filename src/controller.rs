@@ -1,34 +1,244 @@
-use std::future::Future;
 use std::sync::Arc;
 
-use crate::db;
+use crate::account;
+use crate::advance::{self, format_lead};
+#[cfg(feature = "ai")]
+use crate::ai::{self, Backend};
+use crate::api;
+use crate::backup::{self, Backup};
+use crate::bill;
+use crate::broadcast;
+use crate::bulk;
+use crate::cli;
+use crate::cron;
 #[cfg(not(test))]
 use crate::db::Database;
+use crate::db::LinkTarget;
 #[cfg(test)]
 use crate::db::MockDatabase as Database;
+use crate::digest;
 use crate::err::Error;
+use crate::feed;
+use crate::format;
+use crate::grammar;
+use crate::history;
+use crate::ical;
+use crate::lang::Language;
+use crate::manage::{self, Action};
+use crate::meeting::Answer;
 use crate::parsers;
+use crate::picker;
+use crate::policy::NotificationPolicy;
+use crate::pomodoro;
+use crate::search;
+use crate::serializers::{self, Pattern};
+use crate::series;
+use crate::settings::{self, Setting};
+use crate::share;
+use crate::template;
+use crate::text_menu::{self, Menu};
 use crate::tg;
 use crate::tz;
+use crate::undo;
+use crate::vacation;
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Timelike};
 
-use crate::entity::{cron_reminder, reminder};
+use crate::entity::message_link::MessageRole;
+use crate::entity::reminder_event::EventKind;
+use crate::entity::{cron_reminder, occurrence, reminder, user_settings};
 use crate::generic_reminder::GenericReminder;
 use chrono_tz::Tz;
-use sea_orm::IntoActiveModel;
+use sea_orm::{ActiveValue::NotSet, IntoActiveModel, Set};
 use teloxide::prelude::*;
-use teloxide::types::MessageId;
 use teloxide::types::{
+    ChatKind, ChatMemberKind, ChatPermissions, ChatPublic,
     InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+    PublicChatKind, PublicChatSupergroup,
 };
+use teloxide::types::{Document, MessageId, ThreadId, User};
+use teloxide::utils::markdown;
 use teloxide::RequestError;
-use tg::TgResponse;
+use tg::{Localized, RepliedTo, TgResponse};
+
+/// Buttons switching the pages of a list, `None` if the arguments
+/// don't fit into the callback data
+fn list_page_markup(
+    page_num: usize,
+    pages: usize,
+    args: &str,
+) -> Option<InlineKeyboardMarkup> {
+    let args = args.trim();
+    let cb_data = |num: usize| format!("list::page::{}::{}", num, args);
+    if cb_data(pages).len() > tg::CALLBACK_DATA_MAX_LEN {
+        return None;
+    }
+    let mut buttons = vec![];
+    if page_num > 0 {
+        buttons.push(InlineKeyboardButton::new(
+            "⬅️",
+            InlineKeyboardButtonKind::CallbackData(cb_data(page_num - 1)),
+        ));
+    }
+    buttons.push(InlineKeyboardButton::new(
+        format!("{}/{}", page_num + 1, pages),
+        InlineKeyboardButtonKind::CallbackData(cb_data(page_num)),
+    ));
+    if page_num + 1 < pages {
+        buttons.push(InlineKeyboardButton::new(
+            "➡️",
+            InlineKeyboardButtonKind::CallbackData(cb_data(page_num + 1)),
+        ));
+    }
+    Some(InlineKeyboardMarkup::new([buttons]))
+}
+
+/// Checks of /checksetup for the bot's membership in a group whose
+/// members have the permissions
+fn setup_checks(
+    privacy_mode: bool,
+    member: &ChatMemberKind,
+    permissions: ChatPermissions,
+    is_forum: bool,
+) -> Vec<TgResponse> {
+    let (can_send, can_pin, can_manage_topics) = match member {
+        ChatMemberKind::Owner(_) => (true, true, true),
+        ChatMemberKind::Administrator(admin) => {
+            (true, admin.can_pin_messages, admin.can_manage_topics)
+        }
+        ChatMemberKind::Member => (
+            permissions.can_send_messages(),
+            permissions.can_pin_messages(),
+            permissions.can_manage_topics(),
+        ),
+        ChatMemberKind::Restricted(restricted) => (
+            restricted.can_send_messages,
+            restricted.can_pin_messages,
+            restricted.can_manage_topics,
+        ),
+        ChatMemberKind::Left | ChatMemberKind::Banned(_) => {
+            (false, false, false)
+        }
+    };
+    let mut checks = vec![
+        TgResponse::SetupPrivacyMode(privacy_mode),
+        TgResponse::SetupCanSend(can_send),
+        TgResponse::SetupCanPin(can_pin),
+    ];
+    if is_forum {
+        checks.push(TgResponse::SetupCanPostInTopics(can_manage_topics));
+    }
+    checks
+}
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub(crate) enum EditMode {
     TimePattern,
     Description,
+    /// Replacing the whole reminder, or only its time pattern if it's
+    /// sent without a description
+    Whole,
+    /// Moving all the occurrences of the reminder some time later
+    Shift,
+}
+
+/// Time pattern of the reminder as it can be entered again,
+/// e.g. `every mon 09:00` or `17.10 10:00`
+fn time_pattern_text(reminder: &reminder::Model, user_tz: Tz) -> String {
+    reminder
+        .pattern
+        .as_deref()
+        .and_then(|serialized| serde_json::from_str::<Pattern>(serialized).ok())
+        .map(|pattern| pattern.to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .unwrap_or_else(|| {
+            reminder
+                .clone()
+                .into_active_model()
+                .serialize_time_unescaped(user_tz)
+        })
+}
+
+/// Description of the reminder along with the mentioned group member
+fn description_text(reminder: &reminder::Model) -> String {
+    match reminder.target {
+        Some(ref target) => format!("@{} {}", target, reminder.desc),
+        None => reminder.desc.clone(),
+    }
+}
+
+/// Buttons to replace only the time pattern or only the description
+/// of the edited reminder, or to edit the time of its copy instead
+fn keep_markup(rem_id: i64, lang: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::default()
+        .append_row(vec![
+            InlineKeyboardButton::new(
+                lang.text("button_keep_time", &[]),
+                InlineKeyboardButtonKind::CallbackData(format!(
+                    "edit_rem_mode::rem_description::{}",
+                    rem_id
+                )),
+            ),
+            InlineKeyboardButton::new(
+                lang.text("button_keep_description", &[]),
+                InlineKeyboardButtonKind::CallbackData(format!(
+                    "edit_rem_mode::rem_time_pattern::{}",
+                    rem_id
+                )),
+            ),
+        ])
+        .append_row(vec![
+            InlineKeyboardButton::new(
+                lang.text("button_postpone", &[]),
+                InlineKeyboardButtonKind::CallbackData(format!(
+                    "edit_rem_mode::rem_shift::{}",
+                    rem_id
+                )),
+            ),
+            InlineKeyboardButton::new(
+                lang.text("button_duplicate", &[]),
+                InlineKeyboardButtonKind::CallbackData(format!(
+                    "duplicate::rem::{}",
+                    rem_id
+                )),
+            ),
+        ])
 }
 
+/// Maximum size of an imported crontab file in bytes
+const MAX_CRONTAB_SIZE: u32 = 64 * 1024;
+/// Maximum size of an imported backup file in bytes
+const MAX_BACKUP_SIZE: u32 = 1024 * 1024;
+/// Maximum size of an imported calendar file in bytes
+const MAX_CALENDAR_SIZE: u32 = 1024 * 1024;
+
+/// Maximum length of a note on a done occurrence
+const MAX_NOTE_LEN: usize = 200;
+/// Hour of the next day the muted notifications are resent at again
+const MUTED_UNTIL_HOUR: u32 = 8;
+
+/// Maximum number of orphaned reminders offered at once, the rest are
+/// shown as the offered ones are claimed or deleted
+const MAX_ORPHANS_SHOWN: usize = 45;
+/// Maximum number of deleted reminders offered to restore at once, the
+/// rest are shown as the offered ones are restored
+const MAX_TRASHED_SHOWN: usize = 45;
+/// Maximum number of reminders of /today and /tomorrow with buttons
+const MAX_AGENDA_BUTTONS: usize = 45;
+/// Reminders on a page of the buttons choosing one to alter
+const ALTERATION_PAGE_LEN: u64 = 45;
+
+/// Lists that take more messages are sent as a single message
+/// with buttons switching its pages
+const MAX_LIST_MESSAGES: usize = 3;
+
+/// Examples offered to try during onboarding: locale key of the button
+/// label and reminder text
+const SAMPLE_REMINDERS: [(&str, &str); 3] = [
+    ("button_sample_in_a_minute", "1m try out remindee 🎉"),
+    ("button_sample_weekdays", "-/mon-fri 9:00 plan the day"),
+    ("button_sample_monthly", "1/1m 10:00 pay the bills"),
+];
+
 #[derive(Clone)]
 pub(crate) struct TgMessageController {
     pub(crate) db: Arc<Database>,
@@ -37,17 +247,37 @@ pub(crate) struct TgMessageController {
     pub(crate) user_id: UserId,
     pub(crate) msg_id: MessageId,
     pub(crate) reply_to_id: Option<MessageId>,
+    pub(crate) replied_to: Option<RepliedTo>,
+    /// Language tag of the user's Telegram app, e.g. `en-GB`
+    pub(crate) locale: Option<String>,
+    /// Forum topic of the message, new reminders are sent there
+    pub(crate) thread_id: Option<ThreadId>,
+    /// Whether the message was forwarded, so that its text is someone
+    /// else's rather than a reminder written for the bot
+    pub(crate) forwarded: bool,
 }
 
 #[derive(Clone)]
 pub(crate) struct TgCallbackController {
     pub(crate) msg_ctl: TgMessageController,
     pub(crate) cb_id: String,
+    /// Buttons of the message the query came from
+    pub(crate) markup: Option<InlineKeyboardMarkup>,
+}
+
+/// Where onboarding goes after a timezone is chosen
+pub(crate) enum OnboardingStep {
+    /// The timezone wasn't saved and is chosen again
+    Timezone,
+    Language,
+    SampleReminder,
 }
 
 pub(crate) enum ReminderUpdate {
+    Reminder(i64, String),
     ReminderDescription(i64, String),
     ReminderTimePattern(i64, String),
+    ReminderShift(i64, String),
     CronReminder(i64, String),
 }
 
@@ -56,29 +286,167 @@ pub(crate) enum Reminder {
     CronReminder(cron_reminder::Model),
 }
 
+/// Messages listing the sorted reminders of the day, and the buttons
+/// to manage them for the last one, `None` if there are no reminders
+fn agenda_messages(
+    sorted_reminders: Vec<Box<dyn GenericReminder>>,
+    tomorrow: bool,
+    user_tz: Tz,
+    now: NaiveDateTime,
+    lang: Language,
+) -> Option<(Vec<String>, InlineKeyboardMarkup)> {
+    if sorted_reminders.is_empty() {
+        return None;
+    }
+    let day = user_tz.from_utc_datetime(&now).date_naive()
+        + TimeDelta::days(tomorrow.into());
+    let markup = InlineKeyboardMarkup::new(
+        sorted_reminders
+            .iter()
+            .take(MAX_AGENDA_BUTTONS)
+            .filter_map(|rem| {
+                let label = format::truncate(
+                    &format!("⚙️ {}", rem.to_unescaped_string(user_tz)),
+                    tg::BUTTON_LABEL_MAX_LEN,
+                );
+                Some(vec![manage::show_button(label, rem.link_target()?)])
+            }),
+    );
+    let lines = std::iter::once(
+        TgResponse::AgendaHeader(tomorrow, day.format("%a %d.%m").to_string())
+            .to_string_in(lang),
+    )
+    .chain(
+        sorted_reminders
+            .iter()
+            .map(|rem| format::agenda_line(rem.as_ref(), user_tz, now, lang)),
+    );
+    Some((format::split_lines(lines, tg::MESSAGE_MAX_LEN), markup))
+}
+
 pub(crate) enum ActiveReminder {
     Reminder(reminder::ActiveModel),
     CronReminder(cron_reminder::ActiveModel),
 }
 
-trait ReminderModel {
-    type R: GenericReminder;
-    fn into_active(self) -> Self::R;
-}
+impl ActiveReminder {
+    fn link_target(&self) -> LinkTarget {
+        match self {
+            Self::Reminder(reminder) => {
+                LinkTarget::Reminder(reminder.id.clone().unwrap())
+            }
+            Self::CronReminder(cron_reminder) => {
+                LinkTarget::CronReminder(cron_reminder.id.clone().unwrap())
+            }
+        }
+    }
 
-impl ReminderModel for reminder::Model {
-    type R = reminder::ActiveModel;
+    fn to_unescaped_string(&self, user_tz: Tz) -> String {
+        match self {
+            Self::Reminder(reminder) => reminder.to_unescaped_string(user_tz),
+            Self::CronReminder(cron_reminder) => {
+                cron_reminder.to_unescaped_string(user_tz)
+            }
+        }
+    }
+
+    /// Cut the description to at most `max_len` characters,
+    /// returning the rest if there's any
+    fn trim_desc(&mut self, max_len: usize) -> Option<String> {
+        let desc = match self {
+            Self::Reminder(reminder) => &mut reminder.desc,
+            Self::CronReminder(cron_reminder) => &mut cron_reminder.desc,
+        };
+        let (head, overflow) = format::split_overflow(desc.as_ref(), max_len);
+        *desc = Set(head);
+        overflow
+    }
+
+    fn set_thread(&mut self, thread_id: Option<ThreadId>) {
+        let thread_id = Set(thread_id.map(|thread_id| thread_id.0 .0));
+        match self {
+            Self::Reminder(reminder) => reminder.thread_id = thread_id,
+            Self::CronReminder(cron_reminder) => {
+                cron_reminder.thread_id = thread_id
+            }
+        }
+    }
 
-    fn into_active(self) -> Self::R {
-        self.into_active_model()
+    /// Make the reminder about the message replied to: attach its file,
+    /// and take its text if no description is given
+    fn describe(&mut self, replied_to: RepliedTo) {
+        let (desc, media_kind, media_file_id) = match self {
+            Self::Reminder(reminder) => (
+                &mut reminder.desc,
+                &mut reminder.media_kind,
+                &mut reminder.media_file_id,
+            ),
+            Self::CronReminder(cron_reminder) => (
+                &mut cron_reminder.desc,
+                &mut cron_reminder.media_kind,
+                &mut cron_reminder.media_file_id,
+            ),
+        };
+        if let Some((kind, file_id)) = replied_to.media {
+            *media_kind = Set(Some(kind));
+            *media_file_id = Set(Some(file_id));
+        }
+        match replied_to.text {
+            Some(text) if desc.as_ref().is_empty() => *desc = Set(text),
+            _ => {}
+        }
     }
 }
 
-impl ReminderModel for cron_reminder::Model {
-    type R = cron_reminder::ActiveModel;
+impl Reminder {
+    fn link_target(&self) -> LinkTarget {
+        match self {
+            Self::Reminder(reminder) => LinkTarget::Reminder(reminder.id),
+            Self::CronReminder(cron_reminder) => {
+                LinkTarget::CronReminder(cron_reminder.id)
+            }
+        }
+    }
+
+    fn chat_id(&self) -> ChatId {
+        match self {
+            Self::Reminder(reminder) => ChatId(reminder.chat_id),
+            Self::CronReminder(cron_reminder) => ChatId(cron_reminder.chat_id),
+        }
+    }
+
+    fn paused(&self) -> bool {
+        match self {
+            Self::Reminder(reminder) => reminder.paused,
+            Self::CronReminder(cron_reminder) => cron_reminder.paused,
+        }
+    }
+
+    fn deleted_time(&self) -> Option<NaiveDateTime> {
+        match self {
+            Self::Reminder(reminder) => reminder.deleted_time,
+            Self::CronReminder(cron_reminder) => cron_reminder.deleted_time,
+        }
+    }
+
+    fn recurring(&self) -> bool {
+        match self {
+            Self::Reminder(reminder) => reminder.pattern.is_some(),
+            Self::CronReminder(_) => true,
+        }
+    }
 
-    fn into_active(self) -> Self::R {
-        self.into_active_model()
+    fn to_unescaped_string(&self, user_tz: Tz) -> String {
+        match self {
+            Self::Reminder(reminder) => reminder
+                .clone()
+                .into_active_model()
+                .to_unescaped_string(user_tz),
+            Self::CronReminder(cron_reminder) => cron_reminder
+                .clone()
+                .into_active_model()
+                .to_unescaped_string(user_tz),
+        }
     }
 }
 
@@ -90,6 +458,7 @@ impl TgMessageController {
         user_id: UserId,
         msg_id: MessageId,
         reply_to_id: Option<MessageId>,
+        replied_to: Option<RepliedTo>,
     ) -> TgMessageController {
         Self {
             db,
@@ -98,6 +467,10 @@ impl TgMessageController {
             user_id,
             msg_id,
             reply_to_id,
+            replied_to,
+            locale: None,
+            thread_id: None,
+            forwarded: false,
         }
     }
 
@@ -106,14 +479,22 @@ impl TgMessageController {
         bot: Bot,
         msg: Message,
     ) -> Option<TgMessageController> {
-        Some(Self::new(
-            db,
-            bot,
-            msg.chat.id,
-            msg.clone().from?.id,
-            msg.id,
-            msg.reply_to_message().map(|msg| msg.id),
-        ))
+        // The user who forwarded the message owns the reminders set from it
+        let from = msg.from.clone()?;
+        Some(Self {
+            locale: from.language_code,
+            thread_id: tg::topic(&msg),
+            forwarded: msg.forward_origin().is_some(),
+            ..Self::new(
+                db,
+                bot,
+                msg.chat.id,
+                from.id,
+                msg.id,
+                tg::reply_to_message(&msg).map(|msg| msg.id),
+                tg::replied_to(&msg),
+            )
+        })
     }
 
     pub(crate) fn from_callback_query(
@@ -122,208 +503,966 @@ impl TgMessageController {
         cb_query: &CallbackQuery,
     ) -> Option<TgMessageController> {
         let msg = cb_query.message.as_ref()?;
-        Some(Self::new(
-            db,
-            bot,
-            msg.chat().id,
-            cb_query.from.id,
-            msg.id(),
-            None,
-        ))
+        Some(Self {
+            locale: cb_query.from.language_code.clone(),
+            thread_id: msg.regular_message().and_then(tg::topic),
+            ..Self::new(
+                db,
+                bot,
+                msg.chat().id,
+                cb_query.from.id,
+                msg.id(),
+                None,
+                None,
+            )
+        })
+    }
+
+    /// Language the user reads the replies in
+    pub(crate) fn lang(&self) -> Language {
+        settings::language(self.user_id.0 as i64)
     }
 
-    pub(crate) async fn reply<R: ToString>(
+    pub(crate) async fn reply<R: Localized>(
         &self,
         response: R,
     ) -> Result<Message, RequestError> {
-        tg::send_silent_message(&response.to_string(), &self.bot, self.chat_id)
-            .await
+        tg::send_silent_message(
+            &response.localized(self.lang()),
+            &self.bot,
+            self.chat_id,
+        )
+        .await
     }
 
+    /// Greet the user and start onboarding with selecting a timezone
     pub(crate) async fn start(&self) -> Result<(), RequestError> {
-        self.reply(TgResponse::Hello).await.map(|_| ())
+        self.reply(TgResponse::Hello).await?;
+        self.resume_failed_reminders().await?;
+        self.choose_timezone().await
+    }
+
+    /// Greet the user and select the timezone of the link they started
+    /// with, return the next onboarding step
+    pub(crate) async fn start_with_timezone(
+        &self,
+        tz_name: &str,
+    ) -> Result<OnboardingStep, RequestError> {
+        self.reply(TgResponse::HelloShortcut).await?;
+        self.resume_failed_reminders().await?;
+        self.onboarding_set_timezone(tz_name).await
+    }
+
+    /// Set the sample reminder of the link the user started with,
+    /// return whether there's such a sample
+    pub(crate) async fn start_with_sample(
+        &self,
+        idx: usize,
+        user_tz: Tz,
+    ) -> Result<bool, Error> {
+        let Some((_, text)) = SAMPLE_REMINDERS.get(idx) else {
+            return Ok(false);
+        };
+        self.set_new_reminder(text, user_tz).await?;
+        Ok(true)
     }
 
     pub(crate) async fn start_group(&self) -> Result<(), RequestError> {
-        self.reply(TgResponse::HelloGroup).await.map(|_| ())
+        self.reply(TgResponse::HelloGroup).await?;
+        self.resume_failed_reminders().await
     }
 
-    /// Send a list of all notifications
-    pub(crate) async fn list(&self, user_tz: Tz) -> Result<(), RequestError> {
-        // Format reminders
-        let text = match self.db.get_sorted_reminders(self.chat_id.0).await {
-            Ok(sorted_reminders) => {
-                std::iter::once(TgResponse::RemindersListHeader.to_string())
-                    .chain(sorted_reminders.into_iter().map(|rem| {
-                        rem.to_string(user_tz).replace('@', "@\u{200B}")
-                    }))
-                    .collect::<Vec<String>>()
-                    .join("\n")
-            }
+    /// Send the reminders that failed again, e.g. after the user
+    /// unblocked the bot or added it back to the group
+    async fn resume_failed_reminders(&self) -> Result<(), RequestError> {
+        match self.db.resume_failed_reminders(self.chat_id.0).await {
+            Ok(0) => Ok(()),
+            Ok(count) => self
+                .reply(TgResponse::FailedRemindersResumed(count))
+                .await
+                .map(|_| ()),
             Err(err) => {
-                log::error!("{}", err);
-                TgResponse::QueryingError.to_string()
+                tracing::error!("{}", err);
+                Ok(())
+            }
+        }
+    }
+
+    /// Messages listing the reminders matching the /list arguments,
+    /// grouped by day, `None` if the arguments filter out all of them
+    async fn list_messages(
+        &self,
+        args: &str,
+        user_tz: Tz,
+    ) -> Result<Option<Vec<String>>, Error> {
+        let filter = search::Filter::parse(args, user_tz, parsers::now_time());
+        let is_filtered = !filter.is_empty();
+        let sorted_reminders =
+            self.db.search_reminders(self.chat_id.0, filter).await?;
+        if is_filtered && sorted_reminders.is_empty() {
+            return Ok(None);
+        }
+        let lines = std::iter::once(
+            TgResponse::RemindersListHeader.to_string_in(self.lang()),
+        )
+        .chain(format::list_by_day(
+            sorted_reminders,
+            user_tz,
+            settings::date_style(self.user_id.0 as i64),
+            parsers::now_time(),
+        ));
+        Ok(Some(format::split_lines(lines, tg::MESSAGE_MAX_LEN)))
+    }
+
+    /// Send the reminders matching the /list arguments, split into several
+    /// messages if they don't fit into one, or paginated if there are
+    /// too many of them
+    pub(crate) async fn list(
+        &self,
+        args: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        if let (true, Some(args)) =
+            (self.chat_id.is_user(), account::parse_list_args(args))
+        {
+            return self.list_all(args, user_tz).await;
+        }
+        let Some(messages) = self.list_messages(args, user_tz).await? else {
+            self.reply(TgResponse::NoMatchingReminders).await?;
+            return Ok(());
+        };
+        if messages.len() > MAX_LIST_MESSAGES {
+            if let Some(markup) = list_page_markup(0, messages.len(), args) {
+                tg::send_markup(&messages[0], markup, &self.bot, self.chat_id)
+                    .await?;
+                return Ok(());
             }
+        }
+        for text in messages {
+            self.reply(&text).await?;
+        }
+        Ok(())
+    }
+
+    /// Send the active reminders of today or tomorrow with the time
+    /// left to each of them and the buttons to manage them
+    pub(crate) async fn agenda(
+        &self,
+        tomorrow: bool,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let now = parsers::now_time();
+        let filter = search::Filter::day(tomorrow.into(), user_tz, now);
+        let Some((mut messages, markup)) = agenda_messages(
+            self.db.search_reminders(self.chat_id.0, filter).await?,
+            tomorrow,
+            user_tz,
+            now,
+            self.lang(),
+        ) else {
+            self.reply(TgResponse::EmptyAgenda(tomorrow)).await?;
+            return Ok(());
         };
-        self.reply(&text).await.map(|_| ())
+        let last = messages.pop().unwrap_or_default();
+        for text in messages {
+            self.reply(&text).await?;
+        }
+        tg::send_markup(&last, markup, &self.bot, self.chat_id).await?;
+        Ok(())
+    }
+
+    /// Switch the page of a paginated list
+    pub(crate) async fn list_set_page(
+        &self,
+        page_num: usize,
+        args: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let messages =
+            self.list_messages(args, user_tz).await?.unwrap_or_else(|| {
+                vec![TgResponse::NoMatchingReminders.to_string_in(self.lang())]
+            });
+        let page_num = page_num.min(messages.len() - 1);
+        let markup = list_page_markup(page_num, messages.len(), args)
+            .unwrap_or_default();
+        tg::edit_text_markup(
+            &messages[page_num],
+            markup,
+            &self.bot,
+            self.msg_id,
+            self.chat_id,
+        )
+        .await?;
+        Ok(())
     }
 
     /// Send a markup with all timezones to select
     pub(crate) async fn choose_timezone(&self) -> Result<(), RequestError> {
         tg::send_markup(
-            &TgResponse::SelectTimezone.to_string(),
+            &TgResponse::SelectTimezone.to_string_in(self.lang()),
             self.get_markup_for_tz_page_idx(0),
             &self.bot,
             self.chat_id,
         )
         .await
+        .map(|_| ())
     }
 
-    /// Send user's timezone
-    pub(crate) async fn get_timezone(
+    /// Suggest the timezones matching the name of a place or an offset
+    pub(crate) async fn search_timezone(
         &self,
-        user_tz: Tz,
+        query: &str,
     ) -> Result<(), RequestError> {
-        self.reply(TgResponse::ChosenTimezone(user_tz.to_string()))
-            .await
-            .map(|_| ())
+        let now = parsers::now_time();
+        let tz_names = tz::search_timezones(query, now);
+        if tz_names.is_empty() {
+            return self
+                .reply(TgResponse::NoTimezonesFound(query.to_owned()))
+                .await
+                .map(|_| ());
+        }
+        let markup =
+            InlineKeyboardMarkup::new(tz_names.into_iter().map(|tz_name| {
+                let label = match tz_name.parse() {
+                    Ok(tz) => {
+                        format!("{} ({})", tz_name, tz::utc_offset(tz, now))
+                    }
+                    Err(_) => tz_name.to_owned(),
+                };
+                vec![InlineKeyboardButton::new(
+                    label,
+                    InlineKeyboardButtonKind::CallbackData(
+                        "seltz::tz::".to_owned() + tz_name,
+                    ),
+                )]
+            }));
+        tg::send_markup(
+            &TgResponse::FoundTimezones.to_string_in(self.lang()),
+            markup,
+            &self.bot,
+            self.chat_id,
+        )
+        .await
+        .map(|_| ())
     }
 
-    /// General way to send a markup to select a reminder for some operation
-    async fn start_alter(
+    /// Save the timezone chosen during onboarding and continue with
+    /// selecting a language, or with the sample reminders if the language
+    /// of the user's Telegram app is spoken, return the next step
+    pub(crate) async fn onboarding_set_timezone(
         &self,
-        response: TgResponse,
-        markup: InlineKeyboardMarkup,
-    ) -> Result<(), RequestError> {
-        tg::send_markup(&response.to_string(), markup, &self.bot, self.chat_id)
+        tz_name: &str,
+    ) -> Result<OnboardingStep, RequestError> {
+        match self
+            .db
+            .insert_or_update_user_timezone(self.user_id.0 as i64, tz_name)
             .await
-    }
-
-    /// Send a markup to select a reminder for deleting
-    pub(crate) async fn start_delete(&self, user_tz: Tz) -> Result<(), Error> {
-        if let Some(reply_to_id) = self.reply_to_id {
-            if let Ok(Some(generic_reminder)) =
-                self.get_reminder_by_msg_or_reply_id(reply_to_id).await
-            {
-                let response = match generic_reminder {
-                    Reminder::Reminder(reminder) => {
-                        match self.db.delete_reminder(reminder.id).await {
-                            Ok(()) => TgResponse::SuccessDelete(
-                                reminder
-                                    .into_active_model()
-                                    .to_unescaped_string(user_tz),
-                            ),
-                            Err(err) => {
-                                log::error!("{}", err);
-                                TgResponse::FailedDelete
-                            }
-                        }
-                    }
-                    Reminder::CronReminder(cron_reminder) => match self
+        {
+            Ok(()) => {
+                if let Some(lang) =
+                    self.locale.as_deref().and_then(Language::from_locale)
+                {
+                    match self
                         .db
-                        .delete_cron_reminder(cron_reminder.id)
+                        .insert_or_update_user_language(
+                            self.user_id.0 as i64,
+                            lang.code(),
+                        )
                         .await
                     {
-                        Ok(()) => TgResponse::SuccessDelete(
-                            cron_reminder
-                                .into_active_model()
-                                .to_unescaped_string(user_tz),
-                        ),
-                        Err(err) => {
-                            log::error!("{}", err);
-                            TgResponse::FailedDelete
+                        Ok(()) => {
+                            settings::set_language(self.user_id.0 as i64, lang);
+                            self.reply(TgResponse::OnboardingLanguageDetected(
+                                tz_name.to_owned(),
+                                lang.name().to_owned(),
+                            ))
+                            .await?;
+                            self.suggest_sample_reminders().await?;
+                            return Ok(OnboardingStep::SampleReminder);
                         }
-                    },
-                };
-
-                return self
-                    .reply(response)
-                    .await
-                    .map(|_| ())
-                    .map_err(From::from);
+                        Err(err) => tracing::error!("{}", err),
+                    }
+                }
+                let markup = InlineKeyboardMarkup::new(
+                    Language::ALL.into_iter().map(|lang| {
+                        vec![InlineKeyboardButton::new(
+                            lang.name(),
+                            InlineKeyboardButtonKind::CallbackData(
+                                "onboard::lang::".to_owned() + lang.code(),
+                            ),
+                        )]
+                    }),
+                );
+                tg::send_markup(
+                    &TgResponse::OnboardingSelectLanguage(tz_name.to_owned())
+                        .to_string_in(self.lang()),
+                    markup,
+                    &self.bot,
+                    self.chat_id,
+                )
+                .await?;
+                Ok(OnboardingStep::Language)
+            }
+            Err(err) => {
+                tracing::error!("{}", err);
+                self.reply(TgResponse::FailedSetTimezone(tz_name.to_owned()))
+                    .await?;
+                Ok(OnboardingStep::Timezone)
             }
         }
-
-        let markup = self
-            .get_markup_for_reminders_page_deletion(0, user_tz)
-            .await;
-        self.start_alter(TgResponse::ChooseDeleteReminder, markup)
-            .await
-            .map_err(From::from)
     }
 
-    /// Send a markup to select a reminder for editing
-    pub(crate) async fn start_edit(
+    /// Offer to create one of the sample reminders
+    pub(crate) async fn suggest_sample_reminders(
         &self,
-        user_tz: Tz,
     ) -> Result<(), RequestError> {
-        let markup =
-            self.get_markup_for_reminders_page_editing(0, user_tz).await;
-        self.start_alter(TgResponse::ChooseEditReminder, markup)
-            .await
+        let markup = InlineKeyboardMarkup::new(
+            SAMPLE_REMINDERS
+                .iter()
+                .enumerate()
+                .map(|(i, (label, _))| {
+                    vec![InlineKeyboardButton::new(
+                        self.lang().text(label, &[]),
+                        InlineKeyboardButtonKind::CallbackData(format!(
+                            "onboard::sample::{}",
+                            i
+                        )),
+                    )]
+                })
+                .chain(std::iter::once(vec![InlineKeyboardButton::new(
+                    self.lang().text("button_skip", &[]),
+                    InlineKeyboardButtonKind::CallbackData(
+                        "onboard::skip".to_owned(),
+                    ),
+                )])),
+        );
+        tg::send_markup(
+            &TgResponse::OnboardingSampleReminder.to_string_in(self.lang()),
+            markup,
+            &self.bot,
+            self.chat_id,
+        )
+        .await
+        .map(|_| ())
     }
 
-    /// Cancel ongoing reminder editing
-    pub(crate) async fn cancel_edit(&self) -> Result<(), RequestError> {
-        self.reply(TgResponse::CancelEdit).await.map(|_| ())
+    pub(crate) async fn finish_onboarding(&self) -> Result<(), RequestError> {
+        self.reply(TgResponse::OnboardingDone).await.map(|_| ())
     }
 
-    /// Send a markup to select a reminder for pausing
-    pub(crate) async fn start_pause(
+    /// Send user's timezone
+    pub(crate) async fn get_timezone(
         &self,
         user_tz: Tz,
     ) -> Result<(), RequestError> {
-        let markup =
-            self.get_markup_for_reminders_page_pausing(0, user_tz).await;
-        self.start_alter(TgResponse::ChoosePauseReminder, markup)
+        self.reply(TgResponse::ChosenTimezone(user_tz.to_string()))
             .await
+            .map(|_| ())
     }
 
-    async fn parse_reminder(
+    /// General way to send a markup to select a reminder for some operation
+    async fn start_alter(
         &self,
-        text: &str,
-        tz: Tz,
-    ) -> Option<ActiveReminder> {
-        parsers::parse_cron_reminder(
-            text,
-            self.chat_id.0,
-            self.user_id.0,
-            self.msg_id.0,
-            tz,
-        )
-        .await
-        .map(ActiveReminder::CronReminder)
-        .or(parsers::parse_reminder(
-            text,
-            self.chat_id.0,
-            self.user_id.0,
-            self.msg_id.0,
-            tz,
+        response: TgResponse,
+        markup: InlineKeyboardMarkup,
+    ) -> Result<(), RequestError> {
+        let msg = tg::send_markup(
+            &response.to_string_in(self.lang()),
+            markup,
+            &self.bot,
+            self.chat_id,
         )
-        .await
-        .map(ActiveReminder::Reminder))
+        .await?;
+        self.expire(&msg).await;
+        Ok(())
+    }
+
+    /// Have the bot's menu or confirmation deleted once the time the user
+    /// chose in /settings passes, if they chose one
+    async fn expire(&self, msg: &Message) {
+        let res = async {
+            let Some(secs) = self.user_settings().await?.cleanup_after else {
+                return Ok(());
+            };
+            self.db
+                .insert_expiring_message(
+                    self.chat_id.0,
+                    msg.id.0,
+                    parsers::now_time() + TimeDelta::seconds(secs),
+                )
+                .await
+        }
+        .await;
+        if let Err(err) = res {
+            tracing::warn!("{}", err);
+        }
+    }
+
+    /// Whether the user chose the numbered text menus over the buttons.
+    /// They are only sent in private chats, where the numbers are awaited.
+    async fn uses_text_menus(&self) -> Result<bool, Error> {
+        Ok(self.chat_id.is_user() && self.user_settings().await?.text_menus)
+    }
+
+    /// Send the reminders of the chat numbered to choose one by sending
+    /// its number, return them in the order of the numbers, `None` if
+    /// there are none to choose from
+    async fn reminders_text_menu(
+        &self,
+        header: TgResponse,
+        user_tz: Tz,
+    ) -> Result<Option<Vec<LinkTarget>>, Error> {
+        let (targets, options): (Vec<_>, Vec<_>) = self
+            .db
+            .get_sorted_reminders(self.chat_id.0)
+            .await?
+            .iter()
+            .filter_map(|rem| {
+                Some((rem.link_target()?, rem.to_unescaped_string(user_tz)))
+            })
+            .unzip();
+        if targets.is_empty() {
+            self.reply(TgResponse::NothingToChoose).await?;
+            return Ok(None);
+        }
+        for text in text_menu::messages(header, options, self.lang()) {
+            let msg = self.reply(&text).await?;
+            self.expire(&msg).await;
+        }
+        Ok(Some(targets))
+    }
+
+    /// Send a markup to select a reminder for deleting, or the numbered
+    /// menu returned to await the number
+    pub(crate) async fn start_delete(
+        &self,
+        user_tz: Tz,
+    ) -> Result<Option<Menu>, Error> {
+        if let Some(reply_to_id) = self.reply_to_id {
+            if let Ok(Some(reminder)) = self
+                .get_reminder_by_linked_msg(self.chat_id, reply_to_id)
+                .await
+            {
+                self.delete(reminder, user_tz).await?;
+                return Ok(None);
+            }
+        }
+
+        if self.uses_text_menus().await? {
+            return Ok(self
+                .reminders_text_menu(TgResponse::ChooseDeleteReminder, user_tz)
+                .await?
+                .map(Menu::Delete));
+        }
+        let markup = self
+            .get_markup_for_reminders_page_deletion(0, user_tz)
+            .await;
+        self.start_alter(TgResponse::ChooseDeleteReminder, markup)
+            .await?;
+        Ok(None)
+    }
+
+    /// Delete the reminder and reply how it went, offering to undo it
+    async fn delete(
+        &self,
+        reminder: Reminder,
+        user_tz: Tz,
+    ) -> Result<(), RequestError> {
+        let deleted = match reminder {
+            Reminder::Reminder(ref reminder) => {
+                self.db.trash_reminder(reminder.id).await
+            }
+            Reminder::CronReminder(ref cron_reminder) => {
+                self.db.trash_cron_reminder(cron_reminder.id).await
+            }
+        };
+        match deleted {
+            Ok(()) => {
+                let response = TgResponse::SuccessDelete(
+                    reminder.to_unescaped_string(user_tz),
+                );
+                let markup = self.undo_markup(reminder, None);
+                let msg = tg::send_markup(
+                    &response.to_string_in(self.lang()),
+                    markup,
+                    &self.bot,
+                    self.chat_id,
+                )
+                .await?;
+                self.expire(&msg).await;
+                Ok(())
+            }
+            Err(err) => {
+                tracing::error!("{}", err);
+                self.reply(TgResponse::FailedDelete).await.map(|_| ())
+            }
+        }
+    }
+
+    /// Delete the chosen reminder and reply how it went
+    pub(crate) async fn delete_target(
+        &self,
+        target: LinkTarget,
+        user_tz: Tz,
+    ) -> Result<(), RequestError> {
+        match self.get_reminder_by_target(target).await {
+            Ok(Some(reminder)) => self.delete(reminder, user_tz).await,
+            Ok(None) => {
+                tracing::error!("missing reminder: {:?}", target);
+                self.reply(TgResponse::FailedDelete).await.map(|_| ())
+            }
+            Err(err) => {
+                tracing::error!("{}", err);
+                self.reply(TgResponse::FailedDelete).await.map(|_| ())
+            }
+        }
+    }
+
+    /// Keep the reminder as it was before a change, return the "Undo"
+    /// button putting it back in place of its replacement
+    fn undo_markup(
+        &self,
+        previous: Reminder,
+        replacement: Option<LinkTarget>,
+    ) -> InlineKeyboardMarkup {
+        undo::markup(
+            undo::save(
+                undo::Snapshot {
+                    chat_id: self.chat_id.0,
+                    previous,
+                    replacement,
+                },
+                parsers::now_time(),
+            ),
+            self.lang(),
+        )
+    }
+
+    /// Pause or resume the chosen reminder, return the response telling
+    /// how it went
+    pub(crate) async fn pause_target(
+        &self,
+        target: LinkTarget,
+        user_tz: Tz,
+    ) -> TgResponse {
+        let Ok(Some(reminder)) = self.get_reminder_by_target(target).await
+        else {
+            tracing::error!("missing reminder: {:?}", target);
+            return TgResponse::FailedPause;
+        };
+        let paused = match target {
+            LinkTarget::Reminder(id) => {
+                self.db.toggle_reminder_paused(id).await
+            }
+            LinkTarget::CronReminder(id) => {
+                self.db.toggle_cron_reminder_paused(id).await
+            }
+        };
+        let reminder_str = reminder.to_unescaped_string(user_tz);
+        match paused {
+            Ok(true) => TgResponse::SuccessPause(reminder_str),
+            Ok(false) => TgResponse::SuccessResume(reminder_str),
+            Err(err) => {
+                tracing::error!("{}", err);
+                TgResponse::FailedPause
+            }
+        }
+    }
+
+    /// Move the chosen recurring reminder past its next occurrence without
+    /// pausing it, return the response telling how it went
+    pub(crate) async fn skip_target(
+        &self,
+        target: LinkTarget,
+        user_tz: Tz,
+    ) -> TgResponse {
+        let Ok(Some(reminder)) = self.get_reminder_by_target(target).await
+        else {
+            tracing::error!("missing reminder: {:?}", target);
+            return TgResponse::FailedSkip;
+        };
+        let reminder_str = reminder.to_unescaped_string(user_tz);
+        let now = parsers::now_time();
+        let skipped = match reminder {
+            Reminder::Reminder(reminder) => {
+                let mut pattern =
+                    reminder.pattern.as_deref().and_then(|serialized| {
+                        serde_json::from_str::<Pattern>(serialized).ok()
+                    });
+                let next_time = pattern.as_mut().and_then(|pattern| {
+                    pattern.next(std::cmp::max(reminder.time, now))
+                });
+                match (next_time, pattern) {
+                    (Some(time), Some(pattern)) => {
+                        let skipped = reminder::Model {
+                            time,
+                            pattern: pattern.to_stored(time),
+                            advance_time: reminder.advance.and_then(|lead| {
+                                advance::warning_time(time, lead, now)
+                            }),
+                            ..reminder
+                        };
+                        self.db.reschedule_reminder(skipped.clone()).await.map(
+                            |applied| {
+                                applied.then_some(Reminder::Reminder(skipped))
+                            },
+                        )
+                    }
+                    _ => return TgResponse::NothingToSkip(reminder_str),
+                }
+            }
+            Reminder::CronReminder(cron_reminder) => {
+                let Ok(time) = cron::next_time(
+                    &cron_reminder.cron_expr,
+                    std::cmp::max(cron_reminder.time, now),
+                    user_tz,
+                ) else {
+                    return TgResponse::NothingToSkip(reminder_str);
+                };
+                let skipped = cron_reminder::Model {
+                    time,
+                    ..cron_reminder
+                };
+                self.db.reschedule_cron_reminder(skipped.clone()).await.map(
+                    |applied| {
+                        applied.then_some(Reminder::CronReminder(skipped))
+                    },
+                )
+            }
+        };
+        match skipped {
+            Ok(Some(skipped)) => {
+                TgResponse::SuccessSkip(skipped.to_unescaped_string(user_tz))
+            }
+            Ok(None) => TgResponse::FailedSkip,
+            Err(err) => {
+                tracing::error!("{}", err);
+                TgResponse::FailedSkip
+            }
+        }
+    }
+
+    /// Send a markup to select a reminder for editing, or the numbered
+    /// menu returned to await the number
+    pub(crate) async fn start_edit(
+        &self,
+        user_tz: Tz,
+    ) -> Result<Option<Menu>, Error> {
+        if self.uses_text_menus().await? {
+            return Ok(self
+                .reminders_text_menu(TgResponse::ChooseEditReminder, user_tz)
+                .await?
+                .map(Menu::Edit));
+        }
+        let markup =
+            self.get_markup_for_reminders_page_editing(0, user_tz).await;
+        self.start_alter(TgResponse::ChooseEditReminder, markup)
+            .await?;
+        Ok(None)
+    }
+
+    /// Send the numbered parts of the reminder to choose the one to edit
+    pub(crate) async fn choose_edit_mode_text(
+        &self,
+        rem_id: i64,
+    ) -> Result<Menu, RequestError> {
+        for text in text_menu::messages(
+            TgResponse::ChooseEditMode,
+            text_menu::EDIT_MODES.map(|key| self.lang().text(key, &[])),
+            self.lang(),
+        ) {
+            self.reply(&text).await?;
+        }
+        Ok(Menu::EditMode(rem_id))
+    }
+
+    /// Ask for the new reminder, or only its time pattern or description,
+    /// followed by the current value to copy and change.
+    /// Return the current version of the reminder.
+    pub(crate) async fn start_edit_reminder(
+        &self,
+        rem_id: i64,
+        edit_mode: &EditMode,
+        user_tz: Tz,
+    ) -> Result<i32, Error> {
+        let reminder = self
+            .db
+            .get_reminder(rem_id)
+            .await?
+            .ok_or(Error::ReminderNotFound(rem_id))?;
+        let (response, current) = match edit_mode {
+            EditMode::TimePattern => (
+                TgResponse::EnterNewTimePattern,
+                time_pattern_text(&reminder, user_tz),
+            ),
+            EditMode::Description => {
+                (TgResponse::EnterNewDescription, description_text(&reminder))
+            }
+            EditMode::Whole => (
+                TgResponse::EnterEditedReminder,
+                format!(
+                    "{} {}",
+                    time_pattern_text(&reminder, user_tz),
+                    description_text(&reminder)
+                ),
+            ),
+            EditMode::Shift => (
+                TgResponse::EnterShift,
+                time_pattern_text(&reminder, user_tz),
+            ),
+        };
+        self.reply(response).await?;
+        let current = current.trim();
+        match edit_mode {
+            EditMode::Whole => {
+                tg::send_markup(
+                    &markdown::code_inline(current),
+                    keep_markup(rem_id, self.lang()),
+                    &self.bot,
+                    self.chat_id,
+                )
+                .await?;
+            }
+            // There's nothing to copy from an empty description
+            _ if current.is_empty() => {}
+            // A one-time reminder can be moved to another date on the calendar
+            EditMode::TimePattern if reminder.pattern.is_none() => {
+                let today = user_tz
+                    .from_utc_datetime(&parsers::now_time())
+                    .date_naive();
+                let date =
+                    user_tz.from_utc_datetime(&reminder.time).date_naive();
+                tg::send_markup(
+                    &markdown::code_inline(current),
+                    picker::calendar_markup(
+                        picker::Purpose::Edit(rem_id),
+                        date.max(today),
+                        today,
+                        settings::date_style(self.user_id.0 as i64),
+                    ),
+                    &self.bot,
+                    self.chat_id,
+                )
+                .await?;
+            }
+            _ => {
+                self.reply(markdown::code_inline(current)).await?;
+            }
+        }
+        Ok(reminder.version)
+    }
+
+    /// Insert a copy of the reminder and reply with its confirmation,
+    /// return the copy or `None` if the reminder is gone
+    async fn duplicate(
+        &self,
+        target: LinkTarget,
+        user_tz: Tz,
+    ) -> Result<Option<LinkTarget>, Error> {
+        let copy = match target {
+            LinkTarget::Reminder(id) => self
+                .db
+                .duplicate_reminder(id)
+                .await?
+                .map(ActiveReminder::Reminder),
+            LinkTarget::CronReminder(id) => self
+                .db
+                .duplicate_cron_reminder(id)
+                .await?
+                .map(ActiveReminder::CronReminder),
+        };
+        let Some(copy) = copy else {
+            return Ok(None);
+        };
+        let copy_str = match copy {
+            ActiveReminder::Reminder(ref reminder) => {
+                reminder.to_unescaped_string(user_tz)
+            }
+            ActiveReminder::CronReminder(ref cron_reminder) => {
+                cron_reminder.to_unescaped_string(user_tz)
+            }
+        };
+        let reply = self.reply(TgResponse::SuccessCopy(copy_str)).await?;
+        self.update_reply_link(&copy, &reply, None).await?;
+        Ok(Some(copy.link_target()))
+    }
+
+    /// Copy the reminder whose message is replied to with /copy
+    pub(crate) async fn copy_replied(&self, user_tz: Tz) -> Result<(), Error> {
+        let reminder = match self.reply_to_id {
+            Some(reply_to_id) => {
+                self.get_reminder_by_linked_msg(self.chat_id, reply_to_id)
+                    .await?
+            }
+            None => None,
+        };
+        let copy = match reminder {
+            Some(reminder) => {
+                self.duplicate(reminder.link_target(), user_tz).await?
+            }
+            None => None,
+        };
+        if copy.is_none() {
+            self.reply(TgResponse::CopyUsage).await?;
+        }
+        Ok(())
+    }
+
+    /// Copy the reminder chosen in the edit menu and ask for the time
+    /// pattern of the copy. Return the copy's id and version.
+    pub(crate) async fn start_edit_duplicate(
+        &self,
+        rem_id: i64,
+        user_tz: Tz,
+    ) -> Result<(i64, i32), Error> {
+        let Some(LinkTarget::Reminder(copy_id)) = self
+            .duplicate(LinkTarget::Reminder(rem_id), user_tz)
+            .await?
+        else {
+            return Err(Error::ReminderNotFound(rem_id));
+        };
+        let version = self
+            .start_edit_reminder(copy_id, &EditMode::TimePattern, user_tz)
+            .await?;
+        Ok((copy_id, version))
+    }
+
+    /// Ask for the new cron reminder, return its current version
+    pub(crate) async fn start_edit_cron_reminder(
+        &self,
+        cron_rem_id: i64,
+    ) -> Result<i32, Error> {
+        let cron_reminder = self
+            .db
+            .get_cron_reminder(cron_rem_id)
+            .await?
+            .ok_or(Error::CronReminderNotFound(cron_rem_id))?;
+        self.reply(TgResponse::EnterNewReminder).await?;
+        Ok(cron_reminder.version)
+    }
+
+    /// Cancel ongoing reminder editing
+    pub(crate) async fn cancel_edit(&self) -> Result<(), RequestError> {
+        self.reply(TgResponse::CancelEdit).await.map(|_| ())
+    }
+
+    /// Send a markup to select a reminder for pausing, or the numbered
+    /// menu returned to await the number
+    pub(crate) async fn start_pause(
+        &self,
+        user_tz: Tz,
+    ) -> Result<Option<Menu>, Error> {
+        if self.uses_text_menus().await? {
+            return Ok(self
+                .reminders_text_menu(TgResponse::ChoosePauseReminder, user_tz)
+                .await?
+                .map(Menu::Pause));
+        }
+        let markup =
+            self.get_markup_for_reminders_page_pausing(0, user_tz).await;
+        self.start_alter(TgResponse::ChoosePauseReminder, markup)
+            .await?;
+        Ok(None)
+    }
+
+    /// Send a markup to select a reminder to skip the next occurrence of,
+    /// or the numbered menu returned to await the number
+    pub(crate) async fn start_skip(
+        &self,
+        user_tz: Tz,
+    ) -> Result<Option<Menu>, Error> {
+        if self.uses_text_menus().await? {
+            return Ok(self
+                .reminders_text_menu(TgResponse::ChooseSkipReminder, user_tz)
+                .await?
+                .map(Menu::Skip));
+        }
+        let markup = self
+            .get_markup_for_reminders_page_skipping(0, user_tz)
+            .await;
+        self.start_alter(TgResponse::ChooseSkipReminder, markup)
+            .await?;
+        Ok(None)
+    }
+
+    async fn parse_reminder(
+        &self,
+        text: &str,
+        tz: Tz,
+    ) -> Option<ActiveReminder> {
+        parsers::parse_cron_reminder(text, self.chat_id.0, self.user_id.0, tz)
+            .await
+            .map(ActiveReminder::CronReminder)
+            .or(parsers::parse_reminder(
+                text,
+                self.chat_id.0,
+                self.user_id.0,
+                tz,
+            )
+            .await
+            .map(ActiveReminder::Reminder))
     }
 
-    /// Try to parse user's message into a one-time or periodic reminder and set it
+    /// Try to parse user's message into a one-time or periodic reminder and set it.
+    /// A description longer than allowed is trimmed, and the rest is returned.
+    /// A reminder given in reply to a message is about that message.
     async fn _set_reminder(
         &self,
         text: &str,
         user_tz: Tz,
-    ) -> (Option<ActiveReminder>, Option<TgResponse>) {
-        match self.parse_reminder(text, user_tz).await {
+    ) -> (Option<ActiveReminder>, Option<TgResponse>, Option<String>) {
+        let mut reminder = self.parse_reminder(text, user_tz).await;
+        if let (Some(reminder), Some(replied_to)) =
+            (reminder.as_mut(), self.replied_to.clone())
+        {
+            reminder.describe(replied_to);
+        }
+        if let Some(reminder) = reminder.as_mut() {
+            reminder.set_thread(self.thread_id);
+        }
+        let overflow = reminder
+            .as_mut()
+            .and_then(|r| r.trim_desc(cli::max_description_length()));
+        let (reminder, response) = match reminder {
             Some(ActiveReminder::Reminder(reminder)) => {
                 match self.db.insert_reminder(reminder.clone()).await {
                     Ok(reminder) => {
                         let rem_str = reminder
                             .to_unescaped_string(user_tz)
                             .replace('@', "@\u{200B}");
+                        let next_times = reminder
+                            .pattern
+                            .clone()
+                            .unwrap()
+                            .and_then(|serialized| {
+                                serde_json::from_str::<Pattern>(&serialized)
+                                    .ok()
+                            })
+                            .map(|mut pattern| {
+                                format::preview_times(
+                                    reminder.get_time(),
+                                    |cur| pattern.next(cur),
+                                    user_tz,
+                                    reminder.uses_12h_clock(),
+                                    reminder.date_style(),
+                                    parsers::now_time(),
+                                )
+                            });
+                        let response = match next_times {
+                            Some(next_times) => {
+                                TgResponse::SuccessPeriodicInsert(
+                                    rem_str, next_times,
+                                )
+                            }
+                            None => TgResponse::SuccessInsert(rem_str),
+                        };
                         (
                             Some(ActiveReminder::Reminder(reminder)),
-                            Some(TgResponse::SuccessInsert(rem_str)),
+                            Some(response),
                         )
                     }
                     Err(err) => {
-                        log::error!("{}", err);
+                        tracing::error!("{}", err);
                         (None, Some(TgResponse::FailedInsert))
                     }
                 }
@@ -334,47 +1473,63 @@ impl TgMessageController {
                     Ok(cron_reminder) => {
                         let rem_str =
                             cron_reminder.to_unescaped_string(user_tz);
+                        let cron_expr =
+                            cron_reminder.cron_expr.clone().unwrap();
+                        let next_times = format::preview_times(
+                            cron_reminder.get_time(),
+                            |cur| {
+                                cron::next_time(&cron_expr, cur, user_tz).ok()
+                            },
+                            user_tz,
+                            cron_reminder.uses_12h_clock(),
+                            cron_reminder.date_style(),
+                            parsers::now_time(),
+                        );
                         (
                             Some(ActiveReminder::CronReminder(cron_reminder)),
-                            Some(TgResponse::SuccessPeriodicInsert(rem_str)),
+                            Some(TgResponse::SuccessPeriodicInsert(
+                                rem_str, next_times,
+                            )),
                         )
                     }
                     Err(err) => {
-                        log::error!("{}", err);
+                        tracing::error!("{}", err);
                         (None, Some(TgResponse::FailedInsert))
                     }
                 }
             }
+            // Someone else's text rarely starts with a time, the user
+            // replies to it with /set instead
+            None if self.forwarded => (None, Some(TgResponse::ForwardedHint)),
             None => {
-                if self.user_id.0 == self.chat_id.0 as u64 {
-                    (None, Some(TgResponse::IncorrectRequest))
-                } else {
-                    (None, None)
-                }
+                let response = match cron::diagnose(text) {
+                    Some(problem) => TgResponse::InvalidCron(problem),
+                    None if parsers::too_frequent(
+                        text,
+                        self.user_id.0,
+                        user_tz,
+                    ) =>
+                    {
+                        TgResponse::TooFrequent(cli::min_interval())
+                    }
+                    None => match parsers::syntax_error(
+                        text,
+                        self.user_id.0,
+                        user_tz,
+                    )
+                    .and_then(|err| Some((err.pointer(text)?, err.hint())))
+                    {
+                        Some((pointer, hint)) => {
+                            TgResponse::SyntaxError(pointer, hint)
+                        }
+                        None => TgResponse::IncorrectRequest,
+                    },
+                };
+                (None, Some(response))
             }
-        }
-    }
-
-    async fn link_reminder_with_reply_msg(
-        &self,
-        reminder: reminder::ActiveModel,
-        reply: &Message,
-    ) -> Result<(), Error> {
-        self.db
-            .set_reminder_reply_id(reminder, reply.id.0)
-            .await
-            .map_err(From::from)
-    }
-
-    async fn link_cron_reminder_with_reply_msg(
-        &self,
-        reminder: cron_reminder::ActiveModel,
-        reply: &Message,
-    ) -> Result<(), Error> {
-        self.db
-            .set_cron_reminder_reply_id(reminder, reply.id.0)
-            .await
-            .map_err(From::from)
+        };
+        let overflow = overflow.filter(|_| reminder.is_some());
+        (reminder, response, overflow)
     }
 
     async fn set_reminder(
@@ -382,21 +1537,21 @@ impl TgMessageController {
         text: &str,
         user_tz: Tz,
     ) -> Result<(Option<ActiveReminder>, Option<Message>), RequestError> {
-        let (reminder, response) = self._set_reminder(text, user_tz).await;
-        match response {
-            Some(response) => {
-                self.reply(response).await.map(|msg| (reminder, Some(msg)))
-            }
-            None => Ok((reminder, None)),
+        let (reminder, response, overflow) =
+            self._set_reminder(text, user_tz).await;
+        let msg = match response {
+            Some(response) => Some(self.reply(response).await?),
+            None => None,
+        };
+        if let Some(overflow) = overflow {
+            // escaping at most doubles the length
+            self.reply(TgResponse::DescriptionTrimmed(
+                cli::max_description_length(),
+                format::truncate(&overflow, tg::MESSAGE_MAX_LEN / 2 - 200),
+            ))
+            .await?;
         }
-    }
-
-    async fn set_reminder_silently(
-        &self,
-        text: &str,
-        user_tz: Tz,
-    ) -> Option<ActiveReminder> {
-        self._set_reminder(text, user_tz).await.0
+        Ok((reminder, msg))
     }
 
     pub(crate) async fn incorrect_request(&self) -> Result<(), RequestError> {
@@ -435,13 +1590,68 @@ impl TgMessageController {
         self.alter_reminder_set_page(markup).await
     }
 
-    pub(crate) async fn edit_reminder_set_page(
+    /// Show the menu to choose a reminder to delete in place of the
+    /// question what to delete of a recurring one
+    pub(crate) async fn delete_reminder_reset_menu(
         &self,
-        page_num: usize,
         user_tz: Tz,
     ) -> Result<(), RequestError> {
         let markup = self
-            .get_markup_for_reminders_page_editing(page_num, user_tz)
+            .get_markup_for_reminders_page_deletion(0, user_tz)
+            .await;
+        tg::edit_text_markup(
+            &TgResponse::ChooseDeleteReminder.to_string_in(self.lang()),
+            markup,
+            &self.bot,
+            self.msg_id,
+            self.chat_id,
+        )
+        .await
+    }
+
+    /// Ask in place of the menu what to delete of the chosen reminder if
+    /// it recurs and the user wants to be asked, return whether it's asked
+    pub(crate) async fn ask_delete_series(
+        &self,
+        target: LinkTarget,
+        user_tz: Tz,
+    ) -> Result<bool, RequestError> {
+        let confirm = match self.user_settings().await {
+            Ok(settings) => settings.confirm_delete,
+            Err(err) => {
+                tracing::error!("{}", err);
+                true
+            }
+        };
+        let reminder = match self.get_reminder_by_target(target).await {
+            Ok(Some(reminder)) if confirm && reminder.recurring() => reminder,
+            Ok(_) => return Ok(false),
+            Err(err) => {
+                tracing::error!("{}", err);
+                return Ok(false);
+            }
+        };
+        let response = TgResponse::ConfirmDeleteSeries(
+            reminder.to_unescaped_string(user_tz),
+        );
+        tg::edit_text_markup(
+            &response.to_string_in(self.lang()),
+            series::markup(target, self.lang()),
+            &self.bot,
+            self.msg_id,
+            self.chat_id,
+        )
+        .await?;
+        Ok(true)
+    }
+
+    pub(crate) async fn edit_reminder_set_page(
+        &self,
+        page_num: usize,
+        user_tz: Tz,
+    ) -> Result<(), RequestError> {
+        let markup = self
+            .get_markup_for_reminders_page_editing(page_num, user_tz)
             .await;
         self.alter_reminder_set_page(markup).await
     }
@@ -457,6 +1667,17 @@ impl TgMessageController {
         self.alter_reminder_set_page(markup).await
     }
 
+    pub(crate) async fn skip_reminder_set_page(
+        &self,
+        page_num: usize,
+        user_tz: Tz,
+    ) -> Result<(), RequestError> {
+        let markup = self
+            .get_markup_for_reminders_page_skipping(page_num, user_tz)
+            .await;
+        self.alter_reminder_set_page(markup).await
+    }
+
     pub(crate) fn get_markup_for_tz_page_idx(
         &self,
         num: usize,
@@ -510,32 +1731,38 @@ impl TgMessageController {
         user_timezone: Tz,
     ) -> InlineKeyboardMarkup {
         let mut markup = InlineKeyboardMarkup::default();
-        let mut last_rem_page: bool = false;
-        let sorted_reminders =
-            self.db.get_sorted_reminders(self.chat_id.0).await;
-        if let Some(reminders) = sorted_reminders
-            .ok()
-            .as_ref()
-            .and_then(|rems| rems.chunks(45).nth(num))
-        {
-            for chunk in reminders.chunks(1) {
-                let mut row = vec![];
-                for rem in chunk {
-                    let rem_str = rem.to_unescaped_string(user_timezone);
-                    row.push(InlineKeyboardButton::new(
-                        rem_str,
-                        InlineKeyboardButtonKind::CallbackData(
-                            cb_prefix.to_owned()
-                                + &format!("::{}_alt::", rem.get_type())
-                                + &rem.get_id().unwrap().to_string(),
-                        ),
-                    ))
-                }
-                markup = markup.append_row(row);
-            }
-        } else {
-            last_rem_page = true;
+        let offset = num as u64 * ALTERATION_PAGE_LEN;
+        let page = self
+            .db
+            .get_sorted_reminders_page(
+                self.chat_id.0,
+                offset,
+                ALTERATION_PAGE_LEN,
+            )
+            .await
+            .unwrap_or_else(|err| {
+                tracing::error!("{}", err);
+                vec![]
+            });
+        for rem in page {
+            let rem_str = format::truncate(
+                &rem.to_unescaped_string(user_timezone),
+                tg::BUTTON_LABEL_MAX_LEN,
+            );
+            markup = markup.append_row(vec![InlineKeyboardButton::new(
+                rem_str,
+                InlineKeyboardButtonKind::CallbackData(
+                    cb_prefix.to_owned()
+                        + &format!("::{}_alt::", rem.get_type())
+                        + &rem.get_id().unwrap().to_string(),
+                ),
+            )]);
         }
+        let last_rem_page = self
+            .db
+            .count_chat_reminders(self.chat_id.0)
+            .await
+            .map_or(true, |count| offset + ALTERATION_PAGE_LEN >= count);
         let mut move_buttons = vec![];
         if num > 0 {
             move_buttons.push(InlineKeyboardButton::new(
@@ -595,82 +1822,139 @@ impl TgMessageController {
         .await
     }
 
-    async fn _replace_reminder<GetFut, DelFut, R>(
+    pub(crate) async fn get_markup_for_reminders_page_skipping(
+        &self,
+        num: usize,
+        user_timezone: Tz,
+    ) -> InlineKeyboardMarkup {
+        self.get_markup_for_reminders_page_alteration(
+            num,
+            "skiprem",
+            user_timezone,
+        )
+        .await
+    }
+
+    /// Apply the text to the edited reminder in place, keeping its id,
+    /// pause, settings and links. A text setting the other kind of
+    /// reminder replaces it with a new one.
+    async fn apply_edit(
         &self,
         text: &str,
-        rem_id: i64,
+        old_reminder: Reminder,
         user_tz: Tz,
-        get_reminder: impl FnOnce(i64) -> GetFut,
-        delete_reminder: impl FnOnce(i64) -> DelFut,
-    ) -> Result<(Option<ActiveReminder>, Message), RequestError>
-    where
-        GetFut: Future<Output = Result<Option<R>, db::Error>>,
-        DelFut: Future<Output = Result<(), db::Error>>,
-        R: ReminderModel,
-    {
-        let (reminder, response) = match get_reminder(rem_id).await {
-            Ok(Some(old_reminder)) => {
-                match self.set_reminder_silently(text, user_tz).await {
-                    Some(ActiveReminder::Reminder(new_reminder)) => {
-                        match delete_reminder(rem_id).await {
-                            Ok(()) => {
-                                let new_reminder_str =
-                                    new_reminder.to_unescaped_string(user_tz);
-                                (
-                                    Some(ActiveReminder::Reminder(
-                                        new_reminder,
-                                    )),
-                                    TgResponse::SuccessEdit(
-                                        old_reminder
-                                            .into_active()
-                                            .to_unescaped_string(user_tz),
-                                        new_reminder_str,
-                                    ),
-                                )
-                            }
-                            Err(err) => {
-                                log::error!("{}", err);
-                                (None, TgResponse::FailedEdit)
-                            }
-                        }
-                    }
-                    Some(ActiveReminder::CronReminder(new_cron_reminder)) => {
-                        match delete_reminder(rem_id).await {
-                            Ok(()) => {
-                                let new_cron_reminder_str = new_cron_reminder
-                                    .to_unescaped_string(user_tz);
-                                (
-                                    Some(ActiveReminder::CronReminder(
-                                        new_cron_reminder,
-                                    )),
-                                    TgResponse::SuccessEdit(
-                                        old_reminder
-                                            .into_active()
-                                            .to_unescaped_string(user_tz),
-                                        new_cron_reminder_str,
-                                    ),
-                                )
-                            }
-                            Err(err) => {
-                                log::error!("{}", err);
-                                (None, TgResponse::FailedEdit)
-                            }
-                        }
-                    }
-                    _ => (None, TgResponse::FailedEdit),
-                }
+    ) -> (Option<ActiveReminder>, TgResponse) {
+        let Some(mut parsed) = self.parse_reminder(text, user_tz).await else {
+            return (None, TgResponse::FailedEdit);
+        };
+        parsed.trim_desc(cli::max_description_length());
+        let old_target = old_reminder.link_target();
+        let old_reminder_str = old_reminder.to_unescaped_string(user_tz);
+        let edited = match (old_reminder, parsed) {
+            (Reminder::Reminder(old), ActiveReminder::Reminder(new)) => {
+                let time = new.time.unwrap();
+                let edited = reminder::Model {
+                    time,
+                    pattern: new.pattern.unwrap(),
+                    desc: new.desc.unwrap(),
+                    target: new.target.unwrap(),
+                    advance_time: old.advance.and_then(|lead| {
+                        advance::warning_time(time, lead, parsers::now_time())
+                    }),
+                    held_since: None,
+                    failures: 0,
+                    retry_time: None,
+                    ..old
+                };
+                self.db
+                    .update_reminder(edited.clone())
+                    .await
+                    .map(|applied| {
+                        applied.then(|| {
+                            ActiveReminder::Reminder(edited.into_active_model())
+                        })
+                    })
             }
-            Err(err) => {
-                log::error!("{}", err);
-                (None, TgResponse::FailedEdit)
+            (
+                Reminder::CronReminder(old),
+                ActiveReminder::CronReminder(new),
+            ) => {
+                let edited = cron_reminder::Model {
+                    cron_expr: new.cron_expr.unwrap(),
+                    time: new.time.unwrap(),
+                    desc: new.desc.unwrap(),
+                    failures: 0,
+                    retry_time: None,
+                    ..old
+                };
+                self.db.update_cron_reminder(edited.clone()).await.map(
+                    |applied| {
+                        applied.then(|| {
+                            ActiveReminder::CronReminder(
+                                edited.into_active_model(),
+                            )
+                        })
+                    },
+                )
             }
-            _ => {
-                log::error!("missing reminder with id: {}", rem_id);
-                (None, TgResponse::FailedEdit)
+            // The other kind replaces the reminder keeping its pause and
+            // thread
+            (Reminder::Reminder(old), ActiveReminder::CronReminder(new)) => {
+                self.db
+                    .replace_reminder_with_cron(
+                        old.id,
+                        old.version,
+                        cron_reminder::ActiveModel {
+                            paused: Set(old.paused),
+                            thread_id: Set(old.thread_id),
+                            ..new
+                        },
+                    )
+                    .await
+                    .map(|inserted| inserted.map(ActiveReminder::CronReminder))
+            }
+            (Reminder::CronReminder(old), ActiveReminder::Reminder(new)) => {
+                self.db
+                    .replace_cron_reminder_with_reminder(
+                        old.id,
+                        old.version,
+                        reminder::ActiveModel {
+                            paused: Set(old.paused),
+                            thread_id: Set(old.thread_id),
+                            ..new
+                        },
+                    )
+                    .await
+                    .map(|inserted| inserted.map(ActiveReminder::Reminder))
             }
         };
-
-        self.reply(response).await.map(|msg| (reminder, msg))
+        match edited {
+            Ok(Some(edited)) => {
+                let edited_str = edited.to_unescaped_string(user_tz);
+                (
+                    Some(edited),
+                    TgResponse::SuccessEdit(old_reminder_str, edited_str),
+                )
+            }
+            // Changed by someone else since it was read
+            Ok(None) => match self.get_reminder_by_target(old_target).await {
+                Ok(Some(current)) => (
+                    None,
+                    TgResponse::EditConflict(
+                        current.to_unescaped_string(user_tz),
+                    ),
+                ),
+                Ok(None) => (None, TgResponse::FailedEdit),
+                Err(err) => {
+                    tracing::error!("{}", err);
+                    (None, TgResponse::FailedEdit)
+                }
+            },
+            Err(err) => {
+                tracing::error!("{}", err);
+                (None, TgResponse::FailedEdit)
+            }
+        }
     }
 
     async fn replace_reminder(
@@ -678,15 +1962,21 @@ impl TgMessageController {
         text: &str,
         rem_id: i64,
         user_tz: Tz,
-    ) -> Result<(Option<ActiveReminder>, Message), RequestError> {
-        self._replace_reminder(
-            text,
-            rem_id,
-            user_tz,
-            |id: i64| self.db.get_reminder(id),
-            |id: i64| self.db.delete_reminder(id),
-        )
-        .await
+    ) -> (Option<ActiveReminder>, TgResponse) {
+        match self.db.get_reminder(rem_id).await {
+            Ok(Some(old_reminder)) => {
+                self.apply_edit(text, Reminder::Reminder(old_reminder), user_tz)
+                    .await
+            }
+            Ok(None) => {
+                tracing::error!("missing reminder with id: {}", rem_id);
+                (None, TgResponse::FailedEdit)
+            }
+            Err(err) => {
+                tracing::error!("{}", err);
+                (None, TgResponse::FailedEdit)
+            }
+        }
     }
 
     async fn replace_cron_reminder(
@@ -694,54 +1984,145 @@ impl TgMessageController {
         text: &str,
         cron_rem_id: i64,
         user_tz: Tz,
-    ) -> Result<(Option<ActiveReminder>, Message), RequestError> {
-        self._replace_reminder(
-            text,
-            cron_rem_id,
-            user_tz,
-            |id: i64| self.db.get_cron_reminder(id),
-            |id: i64| self.db.delete_cron_reminder(id),
-        )
-        .await
+    ) -> (Option<ActiveReminder>, TgResponse) {
+        match self.db.get_cron_reminder(cron_rem_id).await {
+            Ok(Some(old_cron_reminder)) => {
+                self.apply_edit(
+                    text,
+                    Reminder::CronReminder(old_cron_reminder),
+                    user_tz,
+                )
+                .await
+            }
+            Ok(None) => {
+                tracing::error!("missing reminder with id: {}", cron_rem_id);
+                (None, TgResponse::FailedEdit)
+            }
+            Err(err) => {
+                tracing::error!("{}", err);
+                (None, TgResponse::FailedEdit)
+            }
+        }
+    }
+
+    /// Reply that the reminder was changed by someone else since
+    /// the editing started
+    async fn edit_conflict(
+        &self,
+        current: String,
+    ) -> Result<
+        (Option<ActiveReminder>, Option<Reminder>, Option<Message>),
+        Error,
+    > {
+        self.reply(TgResponse::EditConflict(current))
+            .await
+            .map(|msg| (None, None, Some(msg)))
+            .map_err(From::from)
     }
 
+    /// Apply the update if the reminder is still of the given version
     pub(crate) async fn edit_reminder(
         &self,
         update: ReminderUpdate,
+        version: i32,
         user_tz: Tz,
     ) -> Result<(), Error> {
-        let (reminder, old_reply_id, reply) = match update {
+        let (reminder, previous, reply) = match update {
             ReminderUpdate::ReminderDescription(rem_id, desc) => {
                 let old_reminder = self
                     .db
                     .get_reminder(rem_id)
                     .await?
                     .ok_or(Error::ReminderNotFound(rem_id))?;
+                if old_reminder.version != version {
+                    return self
+                        .edit_conflict(
+                            old_reminder
+                                .into_active_model()
+                                .to_unescaped_string(user_tz),
+                        )
+                        .await
+                        .map(|_| ());
+                }
                 let mut new_reminder = old_reminder.clone();
                 desc.clone_into(&mut new_reminder.desc);
 
-                let (reminder, old_reply, response) =
-                    match self.db.update_reminder(new_reminder.clone()).await {
-                        Ok(()) => (
-                            Some(ActiveReminder::Reminder(
-                                new_reminder.clone().into_active_model(),
-                            )),
-                            old_reminder.reply_id,
-                            TgResponse::SuccessEdit(
-                                old_reminder
-                                    .clone()
-                                    .into_active_model()
-                                    .to_unescaped_string(user_tz),
-                                new_reminder
+                match self.db.update_reminder(new_reminder.clone()).await {
+                    Ok(true) => self
+                        .reply(TgResponse::SuccessEdit(
+                            old_reminder
+                                .clone()
+                                .into_active_model()
+                                .to_unescaped_string(user_tz),
+                            new_reminder
+                                .clone()
+                                .into_active_model()
+                                .to_unescaped_string(user_tz),
+                        ))
+                        .await
+                        .map(|msg| {
+                            (
+                                Some(ActiveReminder::Reminder(
+                                    new_reminder.into_active_model(),
+                                )),
+                                Some(Reminder::Reminder(old_reminder)),
+                                Some(msg),
+                            )
+                        })
+                        .map_err(From::from),
+                    Ok(false) => match self.db.get_reminder(rem_id).await? {
+                        Some(current) => {
+                            self.edit_conflict(
+                                current
                                     .into_active_model()
                                     .to_unescaped_string(user_tz),
-                            ),
-                        ),
-                        Err(_) => (None, None, TgResponse::FailedEdit),
-                    };
+                            )
+                            .await
+                        }
+                        None => Err(Error::ReminderNotFound(rem_id)),
+                    },
+                    Err(err) => {
+                        tracing::error!("{}", err);
+                        self.reply(TgResponse::FailedEdit)
+                            .await
+                            .map(|msg| (None, None, Some(msg)))
+                            .map_err(From::from)
+                    }
+                }
+            }
+            ReminderUpdate::Reminder(rem_id, text) => {
+                let old_reminder = self
+                    .db
+                    .get_reminder(rem_id)
+                    .await?
+                    .ok_or(Error::ReminderNotFound(rem_id))?;
+                if old_reminder.version != version {
+                    return self
+                        .edit_conflict(
+                            old_reminder
+                                .into_active_model()
+                                .to_unescaped_string(user_tz),
+                        )
+                        .await
+                        .map(|_| ());
+                }
+                let (set_result, response) = self
+                    .apply_edit(
+                        &text,
+                        Reminder::Reminder(old_reminder.clone()),
+                        user_tz,
+                    )
+                    .await;
                 self.reply(response)
                     .await
-                    .map(|msg| (reminder, old_reply, Some(msg)))
+                    .map(|msg| {
+                        (
+                            set_result,
+                            Some(Reminder::Reminder(old_reminder)),
+                            Some(msg),
+                        )
+                    })
+                    .map_err(From::from)
             }
             ReminderUpdate::ReminderTimePattern(rem_id, time_pattern) => {
                 let old_reminder = self
@@ -749,15 +2130,135 @@ impl TgMessageController {
                     .get_reminder(rem_id)
                     .await?
                     .ok_or(Error::ReminderNotFound(rem_id))?;
-                self.replace_reminder(
-                    &(time_pattern + " " + &old_reminder.desc),
-                    old_reminder.id,
-                    user_tz,
-                )
-                .await
-                .map(|(set_result, msg)| {
-                    (set_result, old_reminder.reply_id, Some(msg))
-                })
+                if old_reminder.version != version {
+                    return self
+                        .edit_conflict(
+                            old_reminder
+                                .into_active_model()
+                                .to_unescaped_string(user_tz),
+                        )
+                        .await
+                        .map(|_| ());
+                }
+                // Keep mentioning the same group member
+                let text = match old_reminder.target {
+                    Some(ref target) => format!(
+                        "{} @{} {}",
+                        time_pattern, target, old_reminder.desc
+                    ),
+                    None => time_pattern + " " + &old_reminder.desc,
+                };
+                let (set_result, response) = self
+                    .apply_edit(
+                        &text,
+                        Reminder::Reminder(old_reminder.clone()),
+                        user_tz,
+                    )
+                    .await;
+                self.reply(response)
+                    .await
+                    .map(|msg| {
+                        (
+                            set_result,
+                            Some(Reminder::Reminder(old_reminder)),
+                            Some(msg),
+                        )
+                    })
+                    .map_err(From::from)
+            }
+            ReminderUpdate::ReminderShift(rem_id, shift) => {
+                let old_reminder = self
+                    .db
+                    .get_reminder(rem_id)
+                    .await?
+                    .ok_or(Error::ReminderNotFound(rem_id))?;
+                if old_reminder.version != version {
+                    return self
+                        .edit_conflict(
+                            old_reminder
+                                .into_active_model()
+                                .to_unescaped_string(user_tz),
+                        )
+                        .await
+                        .map(|_| ());
+                }
+                let Some(offset) = grammar::parse_shift(&shift) else {
+                    return self
+                        .reply(TgResponse::IncorrectShift)
+                        .await
+                        .map(|_| ())
+                        .map_err(From::from);
+                };
+                let offset = offset.into();
+                let mut pattern =
+                    old_reminder.pattern.as_deref().and_then(|serialized| {
+                        serde_json::from_str::<Pattern>(serialized).ok()
+                    });
+                let time = match pattern.as_mut() {
+                    Some(pattern) => pattern.shift(&offset, old_reminder.time),
+                    None => serializers::shift_time(
+                        old_reminder.time,
+                        &offset,
+                        user_tz,
+                    ),
+                };
+                let Some(time) = time else {
+                    return self
+                        .reply(TgResponse::FailedEdit)
+                        .await
+                        .map(|_| ())
+                        .map_err(From::from);
+                };
+                let now = parsers::now_time();
+                let new_reminder = reminder::Model {
+                    time,
+                    pattern: pattern
+                        .and_then(|pattern| pattern.to_stored(time)),
+                    advance_time: old_reminder.advance.and_then(|lead| {
+                        advance::warning_time(time, lead, now)
+                    }),
+                    ..old_reminder.clone()
+                };
+
+                match self.db.reschedule_reminder(new_reminder.clone()).await {
+                    Ok(true) => self
+                        .reply(TgResponse::SuccessShift(
+                            offset.to_string(),
+                            new_reminder
+                                .clone()
+                                .into_active_model()
+                                .to_unescaped_string(user_tz),
+                        ))
+                        .await
+                        .map(|msg| {
+                            (
+                                Some(ActiveReminder::Reminder(
+                                    new_reminder.into_active_model(),
+                                )),
+                                Some(Reminder::Reminder(old_reminder)),
+                                Some(msg),
+                            )
+                        })
+                        .map_err(From::from),
+                    Ok(false) => match self.db.get_reminder(rem_id).await? {
+                        Some(current) => {
+                            self.edit_conflict(
+                                current
+                                    .into_active_model()
+                                    .to_unescaped_string(user_tz),
+                            )
+                            .await
+                        }
+                        None => Err(Error::ReminderNotFound(rem_id)),
+                    },
+                    Err(err) => {
+                        tracing::error!("{}", err);
+                        self.reply(TgResponse::FailedEdit)
+                            .await
+                            .map(|msg| (None, None, Some(msg)))
+                            .map_err(From::from)
+                    }
+                }
             }
             ReminderUpdate::CronReminder(cron_rem_id, text) => {
                 let old_cron_reminder = self
@@ -765,322 +2266,2949 @@ impl TgMessageController {
                     .get_cron_reminder(cron_rem_id)
                     .await?
                     .ok_or(Error::CronReminderNotFound(cron_rem_id))?;
-                self.replace_cron_reminder(&text, old_cron_reminder.id, user_tz)
+                if old_cron_reminder.version != version {
+                    return self
+                        .edit_conflict(
+                            old_cron_reminder
+                                .into_active_model()
+                                .to_unescaped_string(user_tz),
+                        )
+                        .await
+                        .map(|_| ());
+                }
+                let (set_result, response) = self
+                    .apply_edit(
+                        &text,
+                        Reminder::CronReminder(old_cron_reminder.clone()),
+                        user_tz,
+                    )
+                    .await;
+                self.reply(response)
                     .await
-                    .map(|(set_result, msg)| {
-                        (set_result, old_cron_reminder.reply_id, Some(msg))
+                    .map(|msg| {
+                        (
+                            set_result,
+                            Some(Reminder::CronReminder(old_cron_reminder)),
+                            Some(msg),
+                        )
                     })
+                    .map_err(From::from)
             }
         }?;
 
-        if let Some(ref reminder) = reminder {
-            if let Some(ref reply) = reply {
-                self.update_reply_link(
-                    reminder,
-                    reply,
-                    old_reply_id.map(MessageId),
-                )
+        if let (Some(reminder), Some(reply)) = (reminder, reply) {
+            let old_target = previous.as_ref().map(Reminder::link_target);
+            self.update_reply_link(&reminder, &reply, old_target)
                 .await?;
+            if let Some(previous) = previous {
+                let markup =
+                    self.undo_markup(previous, Some(reminder.link_target()));
+                tg::attach_markup(markup, &self.bot, &reply).await?;
             }
         }
 
         Ok(())
     }
 
-    pub(crate) async fn set_new_reminder(
+    /// Create cron reminders from the lines of an uploaded crontab file
+    /// and report the result for each of them
+    pub(crate) async fn import_crontab(
         &self,
-        text: &str,
+        document: &Document,
         user_tz: Tz,
     ) -> Result<(), Error> {
-        let (reminder, reply) = self.set_reminder(text, user_tz).await?;
+        if document.file.size > MAX_CRONTAB_SIZE {
+            self.reply(TgResponse::FileTooLarge(MAX_CRONTAB_SIZE))
+                .await?;
+            return Ok(());
+        }
+        let content = tg::download_file(&self.bot, &document.file.id).await?;
+        let Ok(content) = String::from_utf8(content) else {
+            self.reply(TgResponse::CrontabNotText).await?;
+            return Ok(());
+        };
 
-        if let Some(ref reminder) = reminder {
-            if let Some(ref reply) = reply {
-                self.update_reply_link(reminder, reply, None).await?;
-            }
+        let max_desc_len = cli::max_description_length();
+        let mut report = vec![];
+        let mut imported = 0;
+        for (line_no, line) in parsers::crontab_entries(&content) {
+            let cron_reminder = parsers::parse_cron_reminder(
+                line,
+                self.chat_id.0,
+                self.user_id.0,
+                user_tz,
+            )
+            .await
+            .filter(|rem| {
+                let desc = rem.desc.as_ref();
+                !desc.is_empty() && desc.chars().count() <= max_desc_len
+            });
+            let response = match cron_reminder {
+                Some(cron_reminder) => {
+                    match self.db.insert_cron_reminder(cron_reminder).await {
+                        Ok(cron_reminder) => {
+                            imported += 1;
+                            TgResponse::CrontabEntryImported(
+                                line_no,
+                                cron_reminder.to_unescaped_string(user_tz),
+                            )
+                        }
+                        Err(err) => {
+                            tracing::error!("{}", err);
+                            TgResponse::CrontabEntryFailed(
+                                line_no,
+                                line.to_owned(),
+                            )
+                        }
+                    }
+                }
+                None => {
+                    TgResponse::CrontabEntryFailed(line_no, line.to_owned())
+                }
+            };
+            report.push(response.to_string_in(self.lang()));
+        }
+        if report.is_empty() {
+            self.reply(TgResponse::CrontabEmpty).await?;
+            return Ok(());
         }
 
-        Ok(())
+        let header = TgResponse::CrontabImportHeader(imported, report.len())
+            .to_string_in(self.lang());
+        self.send_import_report(header, report).await
     }
 
-    pub(crate) async fn update_reply_link(
+    /// Create reminders from the upcoming events of an uploaded calendar,
+    /// reporting which of them were imported
+    pub(crate) async fn import_calendar(
         &self,
-        reminder: &ActiveReminder,
+        document: &Document,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        if document.file.size > MAX_CALENDAR_SIZE {
+            self.reply(TgResponse::FileTooLarge(MAX_CALENDAR_SIZE))
+                .await?;
+            return Ok(());
+        }
+        let content = tg::download_file(&self.bot, &document.file.id).await?;
+        let Some(events) = String::from_utf8(content)
+            .ok()
+            .and_then(|content| ical::events(&content))
+        else {
+            self.reply(TgResponse::CalendarInvalid).await?;
+            return Ok(());
+        };
+
+        let max_desc_len = cli::max_description_length();
+        let mut report = vec![];
+        let mut imported = 0;
+        for event in events {
+            let desc = format::truncate(&event.summary, max_desc_len);
+            let Some(pattern) = event.pattern(user_tz) else {
+                report.push(
+                    TgResponse::CalendarEventFailed(desc)
+                        .to_string_in(self.lang()),
+                );
+                continue;
+            };
+            // the events that already ended are left out
+            let Some(reminder) = parsers::new_reminder(
+                pattern,
+                desc.clone(),
+                self.chat_id.0,
+                self.user_id.0,
+            ) else {
+                continue;
+            };
+            let response = match self.db.insert_reminder(reminder).await {
+                Ok(reminder) => {
+                    imported += 1;
+                    TgResponse::CalendarEventImported(
+                        reminder.to_unescaped_string(user_tz),
+                    )
+                }
+                Err(err) => {
+                    tracing::error!("{}", err);
+                    TgResponse::CalendarEventFailed(desc)
+                }
+            };
+            report.push(response.to_string_in(self.lang()));
+        }
+        if report.is_empty() {
+            self.reply(TgResponse::CalendarEmpty).await?;
+            return Ok(());
+        }
+
+        let header = TgResponse::CalendarImportHeader(imported, report.len())
+            .to_string_in(self.lang());
+        self.send_import_report(header, report).await
+    }
+
+    /// Send the header and the lines of an import report
+    /// in as few messages as possible
+    async fn send_import_report(
+        &self,
+        header: String,
+        report: Vec<String>,
+    ) -> Result<(), Error> {
+        let lines = report.into_iter().map(|line| {
+            if line.chars().count() > tg::MESSAGE_MAX_LEN {
+                format::truncate(&line, tg::MESSAGE_MAX_LEN / 2)
+            } else {
+                line
+            }
+        });
+        for text in format::split_lines(
+            std::iter::once(header).chain(lines),
+            tg::MESSAGE_MAX_LEN,
+        ) {
+            self.reply(&text).await?;
+        }
+        Ok(())
+    }
+
+    /// Send all reminders of the chat as a backup file
+    pub(crate) async fn export(&self, user_tz: Tz) -> Result<(), Error> {
+        let reminders =
+            self.db.get_pending_chat_reminders(self.chat_id.0).await?;
+        let cron_reminders = self
+            .db
+            .get_pending_chat_cron_reminders(self.chat_id.0)
+            .await?;
+        let backup = Backup::new(user_tz, reminders, cron_reminders);
+        let count = backup.len();
+        if count == 0 {
+            self.reply(TgResponse::ExportEmpty).await?;
+            return Ok(());
+        }
+        tg::send_document(
+            backup.to_json(),
+            backup::FILE_NAME,
+            &TgResponse::ExportCaption(count)
+                .to_unescaped_string_in(self.lang()),
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Recreate the reminders from an uploaded backup file
+    /// along with the timezone they were made in
+    pub(crate) async fn import_backup(
+        &self,
+        document: &Document,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        if document.file.size > MAX_BACKUP_SIZE {
+            self.reply(TgResponse::FileTooLarge(MAX_BACKUP_SIZE))
+                .await?;
+            return Ok(());
+        }
+        let content = tg::download_file(&self.bot, &document.file.id).await?;
+        let Ok(backup) = Backup::from_json(&content) else {
+            self.reply(TgResponse::BackupInvalid).await?;
+            return Ok(());
+        };
+        let tz = backup.tz().unwrap_or(user_tz);
+        if tz != user_tz {
+            self.db
+                .insert_or_update_user_timezone(
+                    self.user_id.0 as i64,
+                    tz.name(),
+                )
+                .await?;
+        }
+        let restored = backup.restore(
+            self.chat_id.0,
+            self.user_id.0,
+            tz,
+            cli::max_description_length(),
+        );
+        let imported = restored.reminders.len() + restored.cron_reminders.len();
+        self.db
+            .insert_reminders(restored.reminders, restored.cron_reminders)
+            .await?;
+        self.reply(TgResponse::BackupImported(
+            imported,
+            restored.skipped,
+            tz.name().to_owned(),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Set a new reminder, return whether it was set
+    pub(crate) async fn set_new_reminder(
+        &self,
+        text: &str,
+        user_tz: Tz,
+    ) -> Result<bool, Error> {
+        if let Some(lines) = self.parse_reminder_lines(text, user_tz).await {
+            return self.set_new_reminders(lines, user_tz).await;
+        }
+        let (reminder, reply) = self.set_reminder(text, user_tz).await?;
+
+        if let Some(ref reminder) = reminder {
+            self.link_new_reminder(reminder).await?;
+            if let Some(ref reply) = reply {
+                self.update_reply_link(reminder, reply, None).await?;
+            }
+        }
+
+        Ok(reminder.is_some())
+    }
+
+    /// Ask whether to add the reminder of the message anyway if the same
+    /// one is already set, return the message asking it
+    pub(crate) async fn warn_duplicate(
+        &self,
+        text: &str,
+        user_tz: Tz,
+    ) -> Result<Option<Message>, Error> {
+        // The descriptions of the reminders set in reply to a message
+        // can't be set again from the text alone
+        if self.replied_to.is_some()
+            || self.parse_reminder_lines(text, user_tz).await.is_some()
+        {
+            return Ok(None);
+        }
+        let Some(mut reminder) = self.parse_reminder(text, user_tz).await
+        else {
+            return Ok(None);
+        };
+        reminder.trim_desc(cli::max_description_length());
+        let duplicate = match reminder {
+            ActiveReminder::Reminder(reminder) => self
+                .db
+                .get_duplicate_reminder(
+                    self.chat_id.0,
+                    reminder.time.unwrap(),
+                    &reminder.desc.unwrap(),
+                )
+                .await?
+                .map(Reminder::Reminder),
+            ActiveReminder::CronReminder(cron_reminder) => self
+                .db
+                .get_duplicate_cron_reminder(
+                    self.chat_id.0,
+                    &cron_reminder.cron_expr.unwrap(),
+                    &cron_reminder.desc.unwrap(),
+                )
+                .await?
+                .map(Reminder::CronReminder),
+        };
+        let Some(duplicate) = duplicate else {
+            return Ok(None);
+        };
+        let markup = InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::new(
+                self.lang().text("button_add_anyway", &[]),
+                InlineKeyboardButtonKind::CallbackData("dup::add".to_owned()),
+            ),
+            InlineKeyboardButton::new(
+                self.lang().text("button_cancel", &[]),
+                InlineKeyboardButtonKind::CallbackData(
+                    "dup::cancel".to_owned(),
+                ),
+            ),
+        ]]);
+        let msg = tg::send_markup(
+            &TgResponse::DuplicateReminder(
+                duplicate
+                    .to_unescaped_string(user_tz)
+                    .replace('@', "@\u{200B}"),
+            )
+            .to_string_in(self.lang()),
+            markup,
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(Some(msg))
+    }
+
+    /// Link the new reminder to the message it was created from
+    /// and the message it was given in reply to
+    async fn link_new_reminder(
+        &self,
+        reminder: &ActiveReminder,
+    ) -> Result<(), Error> {
+        self.db
+            .insert_message_link(
+                self.chat_id.0,
+                self.msg_id.0,
+                MessageRole::Creation,
+                reminder.link_target(),
+            )
+            .await?;
+        if let Some(reply_to_id) = self.reply_to_id {
+            self.db
+                .insert_message_link(
+                    self.chat_id.0,
+                    reply_to_id.0,
+                    MessageRole::Subject,
+                    reminder.link_target(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Reminders parsed from the lines of a message if at least two of its
+    /// lines are reminders on their own, otherwise the message is a single
+    /// reminder with a multi-line description
+    async fn parse_reminder_lines(
+        &self,
+        text: &str,
+        user_tz: Tz,
+    ) -> Option<Vec<(String, Option<ActiveReminder>)>> {
+        let lines: Vec<_> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        if lines.len() < 2 {
+            return None;
+        }
+        let mut parsed = Vec::with_capacity(lines.len());
+        for line in lines {
+            let reminder = self.parse_reminder(line, user_tz).await;
+            parsed.push((line.to_owned(), reminder));
+        }
+        let reminders = parsed.iter().filter(|(_, rem)| rem.is_some()).count();
+        (reminders >= 2).then_some(parsed)
+    }
+
+    /// Set the reminders of the lines of a message at once and reply which
+    /// of the lines were set, return whether any of them was set
+    async fn set_new_reminders(
+        &self,
+        lines: Vec<(String, Option<ActiveReminder>)>,
+        user_tz: Tz,
+    ) -> Result<bool, Error> {
+        let total = lines.len();
+        let mut report = vec![];
+        let mut inserted = 0;
+        for (line, reminder) in lines {
+            let mut reminder = match reminder {
+                Some(reminder) => reminder,
+                None => {
+                    report.push(
+                        TgResponse::BatchLineFailed(line)
+                            .to_string_in(self.lang()),
+                    );
+                    continue;
+                }
+            };
+            if let Some(replied_to) = self.replied_to.clone() {
+                reminder.describe(replied_to);
+            }
+            reminder.trim_desc(cli::max_description_length());
+            let result = match reminder {
+                ActiveReminder::Reminder(reminder) => {
+                    self.db.insert_reminder(reminder).await.map(|reminder| {
+                        let rem_str = reminder
+                            .to_unescaped_string(user_tz)
+                            .replace('@', "@\u{200B}");
+                        (ActiveReminder::Reminder(reminder), rem_str)
+                    })
+                }
+                ActiveReminder::CronReminder(cron_reminder) => self
+                    .db
+                    .insert_cron_reminder(cron_reminder)
+                    .await
+                    .map(|cron_reminder| {
+                        let rem_str =
+                            cron_reminder.to_unescaped_string(user_tz);
+                        (ActiveReminder::CronReminder(cron_reminder), rem_str)
+                    }),
+            };
+            let response = match result {
+                Ok((reminder, rem_str)) => {
+                    self.link_new_reminder(&reminder).await?;
+                    inserted += 1;
+                    TgResponse::BatchLineInserted(rem_str)
+                }
+                Err(err) => {
+                    tracing::error!("{}", err);
+                    TgResponse::BatchLineFailed(line)
+                }
+            };
+            report.push(response.to_string_in(self.lang()));
+        }
+
+        let header = TgResponse::BatchInsertHeader(inserted, total)
+            .to_string_in(self.lang());
+        self.send_import_report(header, report).await?;
+        Ok(inserted > 0)
+    }
+
+    /// Show the response in place of the confirmation of the reminder
+    /// replaced with the given one, and move the messages linked to it
+    /// over. Return whether there was a confirmation to edit.
+    async fn edit_confirmation(
+        &self,
+        reminder: &ActiveReminder,
+        response: &TgResponse,
+        old_target: LinkTarget,
+    ) -> Result<bool, Error> {
+        let Some(confirmation) = self
+            .db
+            .get_message_links(old_target, MessageRole::Confirmation)
+            .await?
+            .into_iter()
+            .next()
+        else {
+            return Ok(false);
+        };
+        // It may have been deleted since
+        if let Err(err) = tg::edit_message_text(
+            &response.to_string_in(self.lang()),
+            &self.bot,
+            MessageId(confirmation.msg_id),
+            self.chat_id,
+        )
+        .await
+        {
+            tracing::warn!("{}", err);
+            return Ok(false);
+        }
+        let target = reminder.link_target();
+        if old_target != target {
+            self.db.move_message_links(old_target, target).await?;
+        }
+        Ok(true)
+    }
+
+    /// Link the confirmation reply to the reminder. The messages linked
+    /// to the reminder it replaces are moved to it, except for the previous
+    /// confirmation which is deleted.
+    pub(crate) async fn update_reply_link(
+        &self,
+        reminder: &ActiveReminder,
         reply: &Message,
-        old_reply_id: Option<MessageId>,
+        old_target: Option<LinkTarget>,
+    ) -> Result<(), Error> {
+        let target = reminder.link_target();
+        if let Some(old_target) = old_target {
+            if old_target != target {
+                self.db.move_message_links(old_target, target).await?;
+            }
+            for link in self
+                .db
+                .get_message_links(target, MessageRole::Confirmation)
+                .await?
+            {
+                tg::delete_message(
+                    &self.bot,
+                    self.chat_id,
+                    MessageId(link.msg_id),
+                )
+                .await
+                .unwrap_or_else(|err| tracing::warn!("{}", err));
+                self.db.delete_message_link(link.id).await?;
+            }
+        }
+        self.db
+            .insert_message_link(
+                self.chat_id.0,
+                reply.id.0,
+                MessageRole::Confirmation,
+                target,
+            )
+            .await
+    }
+
+    /// Settings of the user, the defaults if they haven't changed any
+    async fn user_settings(&self) -> Result<user_settings::Model, Error> {
+        let user_id = self.user_id.0 as i64;
+        Ok(self
+            .db
+            .get_user_settings(user_id)
+            .await?
+            .unwrap_or_else(|| settings::defaults(user_id)))
+    }
+
+    /// Send the buttons changing the user's settings, or the numbered
+    /// menu returned to await the number
+    pub(crate) async fn settings(&self) -> Result<Option<Menu>, Error> {
+        let settings = self.user_settings().await?;
+        if self.chat_id.is_user() && settings.text_menus {
+            for text in text_menu::messages(
+                TgResponse::TextSettings,
+                Setting::ALL.map(|setting| setting.label(&settings)),
+                self.lang(),
+            ) {
+                self.reply(&text).await?;
+            }
+            return Ok(Some(Menu::Settings));
+        }
+        tg::send_markup(
+            &TgResponse::Settings.to_string_in(self.lang()),
+            settings::markup(&settings),
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(None)
+    }
+
+    /// Switch the setting of the user to its next value
+    pub(crate) async fn switch_setting(
+        &self,
+        setting: Setting,
+    ) -> Result<user_settings::Model, Error> {
+        let settings = setting.switch(self.user_settings().await?);
+        if setting == Setting::Language {
+            let langs = Language::ALL;
+            let current = settings::language(settings.user_id);
+            let next = langs
+                .iter()
+                .position(|&lang| lang == current)
+                .map_or(0, |idx| (idx + 1) % langs.len());
+            self.db
+                .insert_or_update_user_language(
+                    settings.user_id,
+                    langs[next].code(),
+                )
+                .await?;
+            settings::set_language(settings.user_id, langs[next]);
+            return Ok(settings);
+        }
+        self.db
+            .insert_or_update_user_settings(settings.clone())
+            .await?;
+        settings::set_clock(settings.user_id, settings.clock_12h);
+        settings::set_week_start(settings.user_id, settings.sunday_first);
+        Ok(settings)
+    }
+
+    /// Show, set or turn off the quiet hours of the user
+    pub(crate) async fn quiet_hours(&self, args: &str) -> Result<(), Error> {
+        let settings = self.user_settings().await?;
+        if args.trim().is_empty() {
+            self.reply(TgResponse::QuietHours(settings::quiet_hours(
+                &settings,
+            )))
+            .await?;
+            return Ok(());
+        }
+        let Some(window) = settings::parse_quiet_hours(args) else {
+            self.reply(TgResponse::QuietHoursUsage).await?;
+            return Ok(());
+        };
+        let (quiet_from, quiet_until) = window.unzip();
+        let settings = user_settings::Model {
+            quiet_from,
+            quiet_until,
+            ..settings
+        };
+        self.db
+            .insert_or_update_user_settings(settings.clone())
+            .await?;
+        self.reply(match settings::quiet_hours(&settings) {
+            Some(window) => TgResponse::QuietHoursSet(window),
+            None => TgResponse::QuietHoursOff,
+        })
+        .await?;
+        Ok(())
+    }
+
+    /// Show or change the times the parts of the day stand for
+    /// in the reminders, e.g. `tomorrow evening`
+    pub(crate) async fn day_times(&self, args: &str) -> Result<(), Error> {
+        let settings = self.user_settings().await?;
+        if args.trim().is_empty() {
+            self.reply(TgResponse::DayTimes(settings::day_parts(&settings)))
+                .await?;
+            return Ok(());
+        }
+        let Some((part, minutes)) = settings::parse_day_part(args) else {
+            self.reply(TgResponse::DayTimesUsage).await?;
+            return Ok(());
+        };
+        let settings = part.set(settings, minutes);
+        self.db
+            .insert_or_update_user_settings(settings.clone())
+            .await?;
+        settings::set_day_parts(&settings);
+        self.reply(TgResponse::DayTimesSet(settings::day_parts(&settings)))
+            .await?;
+        Ok(())
+    }
+
+    /// Show, set or turn off the digest of the upcoming reminders of the
+    /// chat. In groups only the administrators change it.
+    pub(crate) async fn digest(
+        &self,
+        args: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        if args.trim().is_empty() {
+            let digest = self.db.get_digest(self.chat_id.0).await?;
+            self.reply(TgResponse::Digest(
+                digest.as_ref().map(digest::describe),
+            ))
+            .await?;
+            return Ok(());
+        }
+        let Some(parsed) = digest::parse_args(args) else {
+            self.reply(TgResponse::DigestUsage).await?;
+            return Ok(());
+        };
+        if !self.chat_id.is_user() {
+            self.check_chat_admin().await?;
+        }
+        let response = match parsed {
+            Some((period, minute)) => {
+                let digest = digest::schedule(
+                    self.chat_id.0,
+                    self.user_id.0 as i64,
+                    period,
+                    minute,
+                    user_tz,
+                    parsers::now_time(),
+                );
+                let when = digest::describe(&digest);
+                self.db.insert_or_update_digest(digest).await?;
+                TgResponse::DigestSet(when)
+            }
+            None => {
+                self.db.delete_digest(self.chat_id.0).await?;
+                TgResponse::DigestOff
+            }
+        };
+        self.reply(response).await?;
+        Ok(())
+    }
+
+    /// Pause all the notifications of the chat until the day, end the
+    /// vacation earlier, or tell until when it's on vacation
+    pub(crate) async fn vacation(
+        &self,
+        args: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let vacation = self.db.get_vacation(self.chat_id.0).await?;
+        if args.trim().is_empty() {
+            self.reply(TgResponse::Vacation(
+                vacation
+                    .as_ref()
+                    .map(|vacation| vacation::describe(vacation, user_tz)),
+            ))
+            .await?;
+            return Ok(());
+        }
+        let now = parsers::now_time();
+        let today = user_tz.from_utc_datetime(&now).date_naive();
+        let Some(parsed) = vacation::parse_args(args, today) else {
+            self.reply(TgResponse::VacationUsage).await?;
+            return Ok(());
+        };
+        if !self.chat_id.is_user() {
+            self.check_chat_admin().await?;
+        }
+        match (parsed, vacation) {
+            (Some(until), current) => {
+                let mut vacation = vacation::start(
+                    self.chat_id.0,
+                    self.user_id.0 as i64,
+                    until,
+                    user_tz,
+                    now,
+                );
+                // Extending the vacation keeps what was missed so far
+                if let Some(current) = current {
+                    vacation.since = current.since;
+                }
+                let day = vacation::describe(&vacation, user_tz);
+                let digest = vacation.digest;
+                self.db.insert_or_update_vacation(vacation).await?;
+                self.reply(TgResponse::VacationSet(day, digest)).await?;
+            }
+            (None, Some(vacation)) => {
+                self.db.delete_vacation(self.chat_id.0).await?;
+                let missed = self
+                    .db
+                    .get_missed_events(self.chat_id.0, vacation.since)
+                    .await?;
+                for text in
+                    vacation::messages(&vacation, missed, user_tz, self.lang())
+                {
+                    self.reply(text).await?;
+                }
+            }
+            (None, None) => {
+                self.reply(TgResponse::Vacation(None)).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Start setting a reminder step by step, offering the days to pick
+    pub(crate) async fn start_new(&self, user_tz: Tz) -> Result<(), Error> {
+        if !self.chat_id.is_user() {
+            self.reply(TgResponse::NewReminderPrivateChat).await?;
+            return Ok(());
+        }
+        let now = user_tz
+            .from_utc_datetime(&parsers::now_time())
+            .naive_local();
+        tg::send_markup(
+            &TgResponse::PickDay.to_string_in(self.lang()),
+            picker::day_markup(
+                now,
+                settings::date_style(self.user_id.0 as i64),
+            ),
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Send the reminder replied to to another chat as well or stop it,
+    /// or show the chats it's sent to. It's up to the admins both of this
+    /// chat and of the other one.
+    pub(crate) async fn broadcast(&self, args: &str) -> Result<(), Error> {
+        let reminder = match self.reply_to_id {
+            Some(reply_to_id) => {
+                self.get_reminder_by_linked_msg(self.chat_id, reply_to_id)
+                    .await?
+            }
+            None => None,
+        };
+        let (Some(action), Some(reminder)) =
+            (broadcast::parse_args(args), reminder)
+        else {
+            self.reply(TgResponse::BroadcastUsage(self.chat_id.0))
+                .await?;
+            return Ok(());
+        };
+        self.check_chat_admin().await?;
+        let target = reminder.link_target();
+        let response = match action {
+            broadcast::Action::List => {
+                let chat_ids = self.db.get_broadcast_chats(target).await?;
+                if chat_ids.is_empty() {
+                    TgResponse::NoBroadcastChats
+                } else {
+                    TgResponse::BroadcastChats(chat_ids)
+                }
+            }
+            broadcast::Action::Add(chat_id) if chat_id == self.chat_id.0 => {
+                TgResponse::AlreadyBroadcast(chat_id)
+            }
+            broadcast::Action::Add(chat_id) => {
+                match self
+                    .bot
+                    .get_chat_member(ChatId(chat_id), self.user_id)
+                    .await
+                {
+                    Err(err) => {
+                        tracing::warn!("{}", err);
+                        TgResponse::BroadcastChatUnreachable(chat_id)
+                    }
+                    Ok(member) if !member.is_privileged() => {
+                        TgResponse::NotBroadcastChatAdmin(chat_id)
+                    }
+                    Ok(_) => {
+                        if self
+                            .db
+                            .insert_broadcast_chat(target, chat_id)
+                            .await?
+                        {
+                            TgResponse::BroadcastAdded(chat_id)
+                        } else {
+                            TgResponse::AlreadyBroadcast(chat_id)
+                        }
+                    }
+                }
+            }
+            broadcast::Action::Remove(chat_id) => {
+                if self.db.delete_broadcast_chat(target, chat_id).await? {
+                    TgResponse::BroadcastStopped(chat_id)
+                } else {
+                    TgResponse::NotBroadcast(chat_id)
+                }
+            }
+        };
+        self.reply(response).await?;
+        Ok(())
+    }
+
+    /// Save the reminder under a name to set it again with /t, or delete
+    /// the template given only its name
+    pub(crate) async fn save_template(
+        &self,
+        args: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let user_id = self.user_id.0 as i64;
+        let response = match template::parse_args(args) {
+            None => TgResponse::TemplateUsage,
+            Some((name, None)) => {
+                if self.db.delete_template(user_id, &name).await? {
+                    TgResponse::TemplateDeleted(name)
+                } else {
+                    TgResponse::TemplateNotFound(name)
+                }
+            }
+            Some((name, Some(text))) => {
+                if self.parse_reminder(&text, user_tz).await.is_some() {
+                    self.db.save_template(user_id, name.clone(), text).await?;
+                    TgResponse::TemplateSaved(name)
+                } else {
+                    TgResponse::TemplateInvalid(text)
+                }
+            }
+        };
+        self.reply(response).await?;
+        Ok(())
+    }
+
+    /// Set the reminder of the template, or offer the user's templates
+    /// to choose from without a name
+    pub(crate) async fn use_template(
+        &self,
+        arg: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        if arg.trim().is_empty() {
+            let templates =
+                self.db.get_templates(self.user_id.0 as i64).await?;
+            if templates.is_empty() {
+                self.reply(TgResponse::NoTemplates).await?;
+            } else {
+                tg::send_markup(
+                    &TgResponse::ChooseTemplate.to_string_in(self.lang()),
+                    template::markup(&templates),
+                    &self.bot,
+                    self.chat_id,
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+        match template::parse_name(arg) {
+            Some(name) => self.set_template_reminder(&name, user_tz).await,
+            None => {
+                self.reply(TgResponse::TemplateNotFound(arg.trim().to_owned()))
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Set the reminder of the user's template
+    pub(crate) async fn set_template_reminder(
+        &self,
+        name: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        match self.db.get_template(self.user_id.0 as i64, name).await? {
+            Some(template) => {
+                self.set_new_reminder(&template.text, user_tz).await?;
+            }
+            None => {
+                self.reply(TgResponse::TemplateNotFound(name.to_owned()))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ask to confirm deleting or pausing the reminders matching the
+    /// arguments, or all of them without any. In groups it's up to
+    /// the admins.
+    pub(crate) async fn start_bulk(
+        &self,
+        action: bulk::Action,
+        args: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        self.check_chat_admin().await?;
+        let filter = bulk::filter(action, args, user_tz, parsers::now_time());
+        let count = self
+            .db
+            .search_reminders(self.chat_id.0, filter)
+            .await?
+            .len();
+        if count == 0 {
+            self.reply(TgResponse::NoMatchingReminders).await?;
+            return Ok(());
+        }
+        let Some(markup) = bulk::markup(action, args, self.lang()) else {
+            self.reply(TgResponse::BulkArgsTooLong).await?;
+            return Ok(());
+        };
+        let response = match action {
+            bulk::Action::Delete => TgResponse::ConfirmDeleteAll(count),
+            bulk::Action::Pause => TgResponse::ConfirmPauseAll(count),
+        };
+        tg::send_markup(
+            &response.to_string_in(self.lang()),
+            markup,
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Send the totals of this year's bills in the chat
+    pub(crate) async fn stats(&self, user_tz: Tz) -> Result<(), Error> {
+        let (year, from, until) =
+            bill::year_bounds(user_tz, parsers::now_time());
+        let bills = self.db.get_chat_bills(self.chat_id.0, from, until).await?;
+        let summaries = bill::summarize(&bills);
+        if summaries.is_empty() {
+            self.reply(TgResponse::StatsEmpty(year)).await?;
+            return Ok(());
+        }
+        let lines = std::iter::once(
+            TgResponse::StatsHeader(year).to_string_in(self.lang()),
+        )
+        .chain(summaries.into_iter().map(|summary| {
+            TgResponse::StatsBill(
+                summary.name,
+                bill::format_amount(summary.total),
+                summary.paid,
+                summary.count,
+            )
+            .to_string_in(self.lang())
+        }));
+        for text in format::split_lines(lines, tg::MESSAGE_MAX_LEN) {
+            self.reply(text).await?;
+        }
+        Ok(())
+    }
+
+    /// Attach the message as a note to the occurrence marked done
+    pub(crate) async fn set_occurrence_note(
+        &self,
+        occ_id: i64,
+        note: &str,
+    ) -> Result<(), Error> {
+        let note = format::truncate(note.trim(), MAX_NOTE_LEN);
+        let response = if self
+            .db
+            .set_occurrence_note(occ_id, self.chat_id.0, note)
+            .await?
+        {
+            TgResponse::NoteSaved
+        } else {
+            TgResponse::ReminderGone
+        };
+        self.reply(response).await?;
+        Ok(())
+    }
+
+    /// Send what happened to the reminders lately: when they were sent,
+    /// snoozed or marked done along with the notes
+    pub(crate) async fn history(&self, user_tz: Tz) -> Result<(), Error> {
+        let events = self
+            .db
+            .get_reminder_events(self.chat_id.0, history::HISTORY_LEN)
+            .await?;
+        if events.is_empty() {
+            self.reply(TgResponse::HistoryEmpty).await?;
+            return Ok(());
+        }
+        let lines = std::iter::once(
+            TgResponse::HistoryHeader.to_string_in(self.lang()),
+        )
+        .chain(events.into_iter().map(|event| {
+            let time = user_tz.from_utc_datetime(&event.time);
+            TgResponse::HistoryEntry(
+                event.kind,
+                time.format("%d.%m %H:%M").to_string(),
+                event.desc,
+                event.note,
+            )
+            .to_string_in(self.lang())
+            .replace('@', "@\u{200B}")
+        }));
+        for text in format::split_lines(lines, tg::MESSAGE_MAX_LEN) {
+            self.reply(text).await?;
+        }
+        Ok(())
+    }
+
+    /// Start a sequence of work and break phases, each phase's end
+    /// is a step of a single countdown reminder
+    pub(crate) async fn start_pomodoro(
+        &self,
+        args: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let Some(plan) = pomodoro::Plan::parse(args) else {
+            self.reply(TgResponse::PomodoroUsage).await?;
+            return Ok(());
+        };
+        let session = self
+            .db
+            .insert_pomodoro(plan.new_session(self.chat_id.0))
+            .await?;
+        let session_id = session.id.unwrap();
+        let mut pattern =
+            Pattern::countdown_minutes(&plan.phase_ends(), user_tz);
+        let Some(time) = pattern.next(parsers::now_time()) else {
+            return Ok(());
+        };
+        self.db
+            .insert_reminder(reminder::ActiveModel {
+                id: NotSet,
+                chat_id: Set(self.chat_id.0),
+                user_id: Set(Some(self.user_id.0 as i64)),
+                time: Set(time),
+                desc: Set("🍅 Pomodoro".to_owned()),
+                paused: Set(false),
+                pattern: Set(pattern.to_stored(time)),
+                policy: Set(None),
+                version: Set(0),
+                pomodoro_id: Set(Some(session_id)),
+                target: Set(None),
+                meeting: Set(false),
+                advance: Set(None),
+                advance_time: Set(None),
+                media_kind: Set(None),
+                media_file_id: Set(None),
+                held_since: Set(None),
+                failures: Set(0),
+                retry_time: Set(None),
+                thread_id: Set(None),
+                deleted_time: Set(None),
+            })
+            .await?;
+        tg::send_markup(
+            &TgResponse::PomodoroStarted(
+                plan.work as i32,
+                plan.rest as i32,
+                plan.rounds as i32,
+            )
+            .to_string_in(self.lang()),
+            tg::pomodoro_markup(session_id, self.lang()),
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Create a read-only link to the chat's reminders
+    pub(crate) async fn share(
+        &self,
+        args: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let Some(public_url) = cli::public_url() else {
+            self.reply(TgResponse::ShareUnavailable).await?;
+            return Ok(());
+        };
+        let Some(days) = share::parse_days(args) else {
+            self.reply(TgResponse::ShareUsage).await?;
+            return Ok(());
+        };
+        let link = self
+            .db
+            .insert_share_link(share::new_link(
+                self.chat_id.0,
+                self.user_id.0 as i64,
+                days,
+            ))
+            .await?;
+        let Some(url) = share::link_url(&public_url, &link.token) else {
+            self.reply(TgResponse::ShareUnavailable).await?;
+            return Ok(());
+        };
+        self.reply(TgResponse::ShareLink(
+            url.to_string(),
+            user_tz
+                .from_utc_datetime(&link.expires_at)
+                .format("%d.%m.%Y %H:%M")
+                .to_string(),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// Revoke all links to the chat's reminders
+    pub(crate) async fn unshare(&self) -> Result<(), Error> {
+        let revoked = self.db.delete_chat_share_links(self.chat_id.0).await?;
+        self.reply(TgResponse::SharesRevoked(revoked)).await?;
+        Ok(())
+    }
+
+    /// Give the link to the calendar feed of the reminders of the private
+    /// chat, change it or turn it off
+    pub(crate) async fn calendar(&self, args: &str) -> Result<(), Error> {
+        let Some(action) = feed::parse_args(args) else {
+            self.reply(TgResponse::CalendarFeedUsage).await?;
+            return Ok(());
+        };
+        if !self.chat_id.is_user() {
+            self.reply(TgResponse::CalendarFeedPrivateChat).await?;
+            return Ok(());
+        }
+        let Some(public_url) = cli::public_url() else {
+            self.reply(TgResponse::CalendarFeedUnavailable).await?;
+            return Ok(());
+        };
+        let user_id = self.user_id.0 as i64;
+        let feed = match action {
+            feed::Action::Off => {
+                let deleted =
+                    self.db.delete_user_calendar_feed(user_id).await?;
+                self.reply(TgResponse::CalendarFeedOff(deleted)).await?;
+                return Ok(());
+            }
+            feed::Action::Show => {
+                match self.db.get_user_calendar_feed(user_id).await? {
+                    Some(feed) => feed,
+                    None => {
+                        self.db
+                            .replace_calendar_feed(feed::new_feed(user_id))
+                            .await?
+                    }
+                }
+            }
+            feed::Action::Reset => {
+                self.db
+                    .replace_calendar_feed(feed::new_feed(user_id))
+                    .await?
+            }
+        };
+        match feed::feed_url(&public_url, &feed.token) {
+            Some(url) => {
+                self.reply(TgResponse::CalendarFeed(url.to_string()))
+                    .await?
+            }
+            None => self.reply(TgResponse::CalendarFeedUnavailable).await?,
+        };
+        Ok(())
+    }
+
+    /// Issue a token for managing the chat's reminders over the HTTP API,
+    /// revoking the previous one, or revoke it. In groups it's up to the
+    /// admins, and the token is sent to the private chat with the user
+    pub(crate) async fn api_token(&self, args: &str) -> Result<(), Error> {
+        let Some(action) = api::parse_args(args) else {
+            self.reply(TgResponse::ApiTokenUsage).await?;
+            return Ok(());
+        };
+        let Some(url) =
+            cli::public_url().and_then(|url| api::reminders_url(&url))
+        else {
+            self.reply(TgResponse::ApiTokenUnavailable).await?;
+            return Ok(());
+        };
+        self.check_chat_admin().await?;
+        if action == api::Action::Off {
+            let deleted = self.db.delete_chat_api_token(self.chat_id.0).await?;
+            self.reply(TgResponse::ApiTokenRevoked(deleted)).await?;
+            return Ok(());
+        }
+        let user_id = self.user_id.0 as i64;
+        let (token, model) = api::new_token(self.chat_id.0, user_id);
+        let response = TgResponse::ApiToken(token, url.to_string());
+        if self.chat_id.is_user() {
+            self.db.replace_api_token(model).await?;
+            self.reply(response).await?;
+            return Ok(());
+        }
+        if tg::send_silent_message(
+            &response.to_string_in(self.lang()),
+            &self.bot,
+            ChatId(user_id),
+        )
+        .await
+        .is_err()
+        {
+            self.reply(TgResponse::ApiTokenPrivateChatNeeded).await?;
+            return Ok(());
+        }
+        self.db.replace_api_token(model).await?;
+        self.reply(TgResponse::ApiTokenSent).await?;
+        Ok(())
+    }
+
+    /// Check that the user administers the chat, private chats are
+    /// administered by their user
+    async fn check_chat_admin(&self) -> Result<(), Error> {
+        if self.chat_id.is_user() {
+            return Ok(());
+        }
+        let member =
+            self.bot.get_chat_member(self.chat_id, self.user_id).await?;
+        if member.is_privileged() {
+            Ok(())
+        } else {
+            Err(Error::NotChatAdmin)
+        }
+    }
+
+    /// Check that the user is a member of the group
+    async fn check_chat_member(&self, chat_id: ChatId) -> Result<(), Error> {
+        let member = self.bot.get_chat_member(chat_id, self.user_id).await?;
+        if member.is_present() {
+            Ok(())
+        } else {
+            Err(Error::NotChatMember)
+        }
+    }
+
+    /// Report whether the bot has what it needs in the group: privacy mode,
+    /// sending and pinning messages, and posting in topics of forums
+    pub(crate) async fn check_setup(&self) -> Result<(), RequestError> {
+        if self.chat_id.is_user() {
+            return self.reply(TgResponse::SetupPrivateChat).await.map(|_| ());
+        }
+        let me = self.bot.get_me().await?;
+        let chat = self.bot.get_chat(self.chat_id).await?;
+        let member = self.bot.get_chat_member(self.chat_id, me.id).await?;
+        let is_forum = matches!(
+            chat.kind,
+            ChatKind::Public(ChatPublic {
+                kind: PublicChatKind::Supergroup(PublicChatSupergroup {
+                    is_forum: true,
+                    ..
+                }),
+                ..
+            })
+        );
+        let checks = setup_checks(
+            !me.can_read_all_group_messages,
+            &member.kind,
+            chat.permissions().unwrap_or_else(ChatPermissions::all),
+            is_forum,
+        );
+        let text = std::iter::once(TgResponse::SetupHeader)
+            .chain(checks)
+            .map(|check| check.to_string_in(self.lang()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.reply(&text).await.map(|_| ())
+    }
+
+    /// Markup to claim or delete the orphaned reminders of the chat,
+    /// `None` if there are none
+    async fn get_markup_for_orphaned_reminders(
+        &self,
+        user_tz: Tz,
+    ) -> Result<Option<InlineKeyboardMarkup>, Error> {
+        let (reminders, cron_reminders) =
+            self.db.get_orphaned_reminders(self.chat_id.0).await?;
+        let mut orphans: Vec<Box<dyn GenericReminder>> = vec![];
+        orphans.extend(reminders.into_iter().map(
+            |rem| -> Box<dyn GenericReminder> {
+                Box::new(rem.into_active_model())
+            },
+        ));
+        orphans.extend(cron_reminders.into_iter().map(
+            |rem| -> Box<dyn GenericReminder> {
+                Box::new(rem.into_active_model())
+            },
+        ));
+        if orphans.is_empty() {
+            return Ok(None);
+        }
+        orphans.sort_unstable();
+        let button = |label: String, cb_data: String| {
+            InlineKeyboardButton::new(
+                label,
+                InlineKeyboardButtonKind::CallbackData(cb_data),
+            )
+        };
+        let markup = InlineKeyboardMarkup::new(
+            orphans
+                .iter()
+                .take(MAX_ORPHANS_SHOWN)
+                .map(|rem| {
+                    let target = format!(
+                        "{}::{}",
+                        rem.get_type(),
+                        rem.get_id().unwrap()
+                    );
+                    vec![
+                        button(
+                            format::truncate(
+                                &format!(
+                                    "🙋 {}",
+                                    rem.to_unescaped_string(user_tz)
+                                ),
+                                tg::BUTTON_LABEL_MAX_LEN,
+                            ),
+                            format!("orphan::claim::{}", target),
+                        ),
+                        button(
+                            "🗑".to_owned(),
+                            format!("orphan::del::{}", target),
+                        ),
+                    ]
+                })
+                .chain(std::iter::once(vec![
+                    button(
+                        self.lang().text("button_claim_all", &[]),
+                        "orphan::claim::all".to_owned(),
+                    ),
+                    button(
+                        self.lang().text("button_delete_all", &[]),
+                        "orphan::del::all".to_owned(),
+                    ),
+                ])),
+        );
+        Ok(Some(markup))
+    }
+
+    /// Send a markup to claim or delete the reminders that have no owner
+    /// with a known timezone, available to the chat administrators
+    pub(crate) async fn list_orphaned_reminders(
+        &self,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        self.check_chat_admin().await?;
+        match self.get_markup_for_orphaned_reminders(user_tz).await? {
+            Some(markup) => {
+                self.start_alter(TgResponse::ChooseOrphanedReminders, markup)
+                    .await?
+            }
+            None => {
+                self.reply(TgResponse::NoOrphanedReminders).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Show the orphaned reminders left after claiming or deleting some
+    async fn orphaned_reminders_set_page(
+        &self,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let markup = self
+            .get_markup_for_orphaned_reminders(user_tz)
+            .await?
+            .unwrap_or_default();
+        self.alter_reminder_set_page(markup).await?;
+        Ok(())
+    }
+
+    /// Reminders of the chat in the trash, the last deleted first
+    async fn get_trashed_reminders(&self) -> Result<Vec<Reminder>, Error> {
+        let (reminders, cron_reminders) =
+            self.db.get_trashed_reminders(self.chat_id.0).await?;
+        let mut trashed: Vec<Reminder> = reminders
+            .into_iter()
+            .map(Reminder::Reminder)
+            .chain(cron_reminders.into_iter().map(Reminder::CronReminder))
+            .collect();
+        trashed.sort_by_key(|rem| std::cmp::Reverse(rem.deleted_time()));
+        Ok(trashed)
+    }
+
+    /// Markup to restore the deleted reminders of the chat,
+    /// `None` if there are none
+    async fn get_markup_for_trashed_reminders(
+        &self,
+        user_tz: Tz,
+    ) -> Result<Option<InlineKeyboardMarkup>, Error> {
+        let trashed = self.get_trashed_reminders().await?;
+        if trashed.is_empty() {
+            return Ok(None);
+        }
+        let button = |label: String, cb_data: String| {
+            InlineKeyboardButton::new(
+                label,
+                InlineKeyboardButtonKind::CallbackData(cb_data),
+            )
+        };
+        let markup = InlineKeyboardMarkup::new(
+            trashed
+                .iter()
+                .take(MAX_TRASHED_SHOWN)
+                .map(|rem| {
+                    let target = match rem.link_target() {
+                        LinkTarget::Reminder(id) => format!("rem::{}", id),
+                        LinkTarget::CronReminder(id) => {
+                            format!("cron_rem::{}", id)
+                        }
+                    };
+                    vec![button(
+                        format::truncate(
+                            &format!("♻️ {}", rem.to_unescaped_string(user_tz)),
+                            tg::BUTTON_LABEL_MAX_LEN,
+                        ),
+                        format!("trash::restore::{}", target),
+                    )]
+                })
+                .chain(std::iter::once(vec![button(
+                    self.lang().text("button_restore_all", &[]),
+                    "trash::restore::all".to_owned(),
+                )])),
+        );
+        Ok(Some(markup))
+    }
+
+    /// Send a markup to restore the reminders deleted lately
+    pub(crate) async fn list_trashed_reminders(
+        &self,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        match self.get_markup_for_trashed_reminders(user_tz).await? {
+            Some(markup) => {
+                self.start_alter(TgResponse::ChooseTrashedReminders, markup)
+                    .await?
+            }
+            None => {
+                self.reply(TgResponse::EmptyTrash).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Show the deleted reminders left after restoring some
+    async fn trashed_reminders_set_page(
+        &self,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let markup = self
+            .get_markup_for_trashed_reminders(user_tz)
+            .await?
+            .unwrap_or_default();
+        self.alter_reminder_set_page(markup).await?;
+        Ok(())
+    }
+
+    /// Restore the reminder of the chat deleted last
+    pub(crate) async fn restore_last_deleted(
+        &self,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let Some(reminder) =
+            self.get_trashed_reminders().await?.into_iter().next()
+        else {
+            self.reply(TgResponse::EmptyTrash).await?;
+            return Ok(());
+        };
+        self.db
+            .restore_trashed_reminders(
+                self.chat_id.0,
+                Some(reminder.link_target()),
+            )
+            .await?;
+        self.reply(TgResponse::Undone(reminder.to_unescaped_string(user_tz)))
+            .await?;
+        Ok(())
+    }
+
+    /// Ask the AI backend to rewrite a message the grammar doesn't
+    /// understand and offer the result for confirmation.
+    /// Return the suggested reminder text and the message offering it.
+    #[cfg(feature = "ai")]
+    pub(crate) async fn suggest_reminder(
+        &self,
+        text: &str,
+        user_tz: Tz,
+    ) -> Result<Option<(String, Message)>, Error> {
+        let Some(backend) = ai::backend() else {
+            return Ok(None);
+        };
+        if self.parse_reminder(text, user_tz).await.is_some()
+            || !self.db.is_ai_opted_in(self.user_id.0 as i64).await?
+        {
+            return Ok(None);
+        }
+        let now = user_tz
+            .from_utc_datetime(&parsers::now_time())
+            .naive_local();
+        let suggestion = match backend.suggest(text, now).await {
+            Ok(Some(suggestion)) => suggestion,
+            Ok(None) => return Ok(None),
+            Err(err) => {
+                tracing::warn!("{}", err);
+                return Ok(None);
+            }
+        };
+        // Never offer what the bot won't be able to set anyway
+        if self.parse_reminder(&suggestion, user_tz).await.is_none() {
+            tracing::warn!("unparsable AI suggestion: {}", suggestion);
+            return Ok(None);
+        }
+        let markup = InlineKeyboardMarkup::new(vec![vec![
+            InlineKeyboardButton::new(
+                self.lang().text("button_yes", &[]),
+                InlineKeyboardButtonKind::CallbackData(
+                    "aiparse::yes".to_owned(),
+                ),
+            ),
+            InlineKeyboardButton::new(
+                self.lang().text("button_no", &[]),
+                InlineKeyboardButtonKind::CallbackData(
+                    "aiparse::no".to_owned(),
+                ),
+            ),
+        ]]);
+        let msg = tg::send_markup(
+            &TgResponse::AiSuggestion(suggestion.clone())
+                .to_string_in(self.lang()),
+            markup,
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(Some((suggestion, msg)))
+    }
+
+    /// Toggle passing the unparsed messages of the user to the AI backend
+    #[cfg(feature = "ai")]
+    pub(crate) async fn toggle_ai(&self) -> Result<(), Error> {
+        if ai::backend().is_none() {
+            self.reply(TgResponse::AiUnavailable).await?;
+            return Ok(());
+        }
+        let user_id = self.user_id.0 as i64;
+        let enabled = !self.db.is_ai_opted_in(user_id).await?;
+        self.db.set_ai_opt_in(user_id, enabled).await?;
+        self.reply(if enabled {
+            TgResponse::AiEnabled
+        } else {
+            TgResponse::AiDisabled
+        })
+        .await?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "ai"))]
+    pub(crate) async fn toggle_ai(&self) -> Result<(), Error> {
+        self.reply(TgResponse::AiUnavailable).await?;
+        Ok(())
+    }
+
+    /// Make the timezone the group's one unless it already has one, the
+    /// reminders without an owner with a known timezone fire in it
+    async fn init_chat_timezone(&self, tz_name: &str) -> Result<(), Error> {
+        if self.chat_id.is_user()
+            || self
+                .db
+                .get_user_timezone_name(self.chat_id.0)
+                .await?
+                .is_some()
+        {
+            return Ok(());
+        }
+        self.db
+            .insert_or_update_user_timezone(self.chat_id.0, tz_name)
+            .await
+    }
+
+    pub(crate) async fn set_timezone(
+        &self,
+        tz_name: &str,
+    ) -> Result<(), RequestError> {
+        let response = match self
+            .db
+            .insert_or_update_user_timezone(self.user_id.0 as i64, tz_name)
+            .await
+        {
+            Ok(()) => {
+                self.init_chat_timezone(tz_name)
+                    .await
+                    .unwrap_or_else(|err| {
+                        tracing::error!("{}", err);
+                    });
+                TgResponse::ChosenTimezone(tz_name.to_owned())
+            }
+            Err(err) => {
+                tracing::error!("{}", err);
+                TgResponse::FailedSetTimezone(tz_name.to_owned())
+            }
+        };
+        self.reply(response).await.map(|_| ())
+    }
+
+    /// Set the timezone the reminders of the group are set and fired in
+    /// regardless of their creators' timezones, available to the group
+    /// administrators. Without arguments show the current one.
+    pub(crate) async fn set_chat_timezone(
+        &self,
+        args: &str,
+    ) -> Result<(), Error> {
+        if self.chat_id.is_user() {
+            self.reply(TgResponse::ChatTimezonePrivateChat).await?;
+            return Ok(());
+        }
+        let args = args.trim();
+        let response = if args.is_empty() {
+            TgResponse::ChatTimezone(
+                self.db.get_chat_timezone_name(self.chat_id.0).await?,
+            )
+        } else {
+            self.check_chat_admin().await?;
+            if args == "reset" {
+                self.db.delete_chat_timezone(self.chat_id.0).await?;
+                TgResponse::ChatTimezoneReset
+            } else {
+                match args.parse::<Tz>() {
+                    Ok(tz) => {
+                        self.db
+                            .insert_or_update_chat_timezone(
+                                self.chat_id.0,
+                                tz.name(),
+                            )
+                            .await?;
+                        TgResponse::ChosenChatTimezone(tz.name().to_owned())
+                    }
+                    Err(_) => TgResponse::UnknownChatTimezone(args.to_owned()),
+                }
+            }
+        };
+        self.reply(response).await?;
+        Ok(())
+    }
+
+    async fn get_reminder_by_target(
+        &self,
+        target: LinkTarget,
+    ) -> Result<Option<Reminder>, Error> {
+        match target {
+            LinkTarget::Reminder(id) => {
+                Ok(self.db.get_reminder(id).await?.map(Reminder::Reminder))
+            }
+            LinkTarget::CronReminder(id) => Ok(self
+                .db
+                .get_cron_reminder(id)
+                .await?
+                .map(Reminder::CronReminder)),
+        }
+    }
+
+    /// Find the reminder a message in the chat is linked to
+    async fn get_reminder_by_linked_msg(
+        &self,
+        chat_id: ChatId,
+        msg_id: MessageId,
+    ) -> Result<Option<Reminder>, Error> {
+        let link = self.db.get_message_link(chat_id.0, msg_id.0).await?;
+        match link.as_ref().and_then(LinkTarget::of) {
+            Some(target) => self.get_reminder_by_target(target).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Timezone to show a group reminder in: the user's one if known,
+    /// otherwise the group's one
+    async fn group_reminder_timezone(
+        &self,
+        reminder: &Reminder,
+    ) -> Result<Tz, Error> {
+        Ok(tz::get_reminder_timezone(
+            &self.db,
+            Some(self.user_id.0 as i64),
+            reminder.chat_id().0,
+        )
+        .await?
+        .unwrap_or(Tz::UTC))
+    }
+
+    /// Offer to manage privately the reminder of a group notification
+    pub(crate) async fn manage(
+        &self,
+        chat_id: ChatId,
+        msg_id: MessageId,
+    ) -> Result<(), Error> {
+        self.check_chat_member(chat_id).await?;
+        let Some(reminder) =
+            self.get_reminder_by_linked_msg(chat_id, msg_id).await?
+        else {
+            self.reply(TgResponse::ReminderGone).await?;
+            return Ok(());
+        };
+        let user_tz = self.group_reminder_timezone(&reminder).await?;
+        tg::send_markup(
+            &TgResponse::ManageReminder(reminder.to_unescaped_string(user_tz))
+                .to_string_in(self.lang()),
+            manage::markup(
+                reminder.link_target(),
+                reminder.paused(),
+                true,
+                self.lang(),
+            ),
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Send the link to the private chat linking the group to it, or the
+    /// buttons to unlink the linked groups in private
+    pub(crate) async fn link(&self, title: &str) -> Result<(), Error> {
+        let user_id = self.user_id.0 as i64;
+        if self.chat_id.is_user() {
+            let links = self.db.get_account_links(user_id).await?;
+            if links.is_empty() {
+                self.reply(TgResponse::NoLinkedChats).await?;
+            } else {
+                tg::send_markup(
+                    &TgResponse::LinkedChats.to_string_in(self.lang()),
+                    account::unlink_markup(&links),
+                    &self.bot,
+                    self.chat_id,
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+        let token = account::new_token(
+            self.chat_id.0,
+            user_id,
+            title.to_owned(),
+            parsers::now_time(),
+        );
+        let username = tg::bot_username(&self.bot).await?;
+        tg::send_markup(
+            &TgResponse::LinkChat.to_string_in(self.lang()),
+            InlineKeyboardMarkup::new(
+                account::link_button(username, &token, self.lang())
+                    .map(|button| vec![button]),
+            ),
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Link the group the start payload was given for to the private chat,
+    /// if the user is still its member
+    pub(crate) async fn link_chat(&self, token: &str) -> Result<(), Error> {
+        let user_id = self.user_id.0 as i64;
+        let Some((chat_id, title)) =
+            account::take(token, user_id, parsers::now_time())
+        else {
+            self.reply(TgResponse::LinkExpired).await?;
+            return Ok(());
+        };
+        self.check_chat_member(ChatId(chat_id)).await?;
+        self.db
+            .save_account_link(user_id, chat_id, title.clone())
+            .await?;
+        self.reply(TgResponse::LinkedChat(title)).await?;
+        Ok(())
+    }
+
+    /// Send the reminders matching the filter that the user created here
+    /// and in the linked groups, with the buttons to manage them. The
+    /// groups the user has left are unlinked.
+    async fn list_all(&self, args: &str, user_tz: Tz) -> Result<(), Error> {
+        let user_id = self.user_id.0 as i64;
+        let now = parsers::now_time();
+        let mut chats = vec![(
+            self.chat_id.0,
+            TgResponse::PrivateChatTitle.to_unescaped_string_in(self.lang()),
+        )];
+        for link in self.db.get_account_links(user_id).await? {
+            match self.check_chat_member(ChatId(link.chat_id)).await {
+                Ok(()) => chats.push((link.chat_id, link.title)),
+                Err(Error::NotChatMember) => {
+                    self.db.delete_account_link(user_id, link.chat_id).await?;
+                }
+                // The bot may have been removed from the group
+                Err(err) => tracing::warn!("{}", err),
+            }
+        }
+        let mut lines =
+            vec![TgResponse::AllChatsListHeader.to_string_in(self.lang())];
+        let mut buttons = vec![];
+        for (chat_id, title) in chats {
+            let filter = search::Filter::parse(args, user_tz, now);
+            let sorted_reminders = self
+                .db
+                .search_user_reminders(chat_id, user_id, filter)
+                .await?;
+            if sorted_reminders.is_empty() {
+                continue;
+            }
+            buttons.extend(sorted_reminders.iter().filter_map(|rem| {
+                let label = format::truncate(
+                    &format!("⚙️ {}", rem.to_unescaped_string(user_tz)),
+                    tg::BUTTON_LABEL_MAX_LEN,
+                );
+                Some(vec![manage::show_button(label, rem.link_target()?)])
+            }));
+            lines.push(
+                TgResponse::ChatListHeader(title).to_string_in(self.lang()),
+            );
+            lines.extend(format::list_by_day(
+                sorted_reminders,
+                user_tz,
+                settings::date_style(user_id),
+                now,
+            ));
+        }
+        if buttons.is_empty() {
+            self.reply(TgResponse::NoMatchingReminders).await?;
+            return Ok(());
+        }
+        buttons.truncate(MAX_AGENDA_BUTTONS);
+        let mut messages = format::split_lines(lines, tg::MESSAGE_MAX_LEN);
+        let last = messages.pop().unwrap_or_default();
+        for text in messages {
+            self.reply(&text).await?;
+        }
+        tg::send_markup(
+            &last,
+            InlineKeyboardMarkup::new(buttons),
+            &self.bot,
+            self.chat_id,
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn edit_reminder_from_edited_message(
+        &self,
+        text: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        // Only the message the reminder was created from is editable
+        let Some(old_target) = self
+            .db
+            .get_message_link(self.chat_id.0, self.msg_id.0)
+            .await?
+            .filter(|link| link.role == MessageRole::Creation)
+            .as_ref()
+            .and_then(LinkTarget::of)
+        else {
+            return Ok(());
+        };
+        let (reminder, response) = match old_target {
+            LinkTarget::Reminder(id) => {
+                self.replace_reminder(text, id, user_tz).await
+            }
+            LinkTarget::CronReminder(id) => {
+                self.replace_cron_reminder(text, id, user_tz).await
+            }
+        };
+
+        // The confirmation is edited in place to keep the chat compact
+        if let Some(ref reminder) = reminder {
+            if self
+                .edit_confirmation(reminder, &response, old_target)
+                .await?
+            {
+                return Ok(());
+            }
+        }
+        let reply = self.reply(response).await?;
+        if let Some(ref reminder) = reminder {
+            self.update_reply_link(reminder, &reply, Some(old_target))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+impl TgCallbackController {
+    pub(crate) fn new(
+        db: Arc<Database>,
+        bot: Bot,
+        cb_query: CallbackQuery,
+    ) -> Option<TgCallbackController> {
+        Some(Self {
+            msg_ctl: TgMessageController::from_callback_query(
+                db, bot, &cb_query,
+            )?,
+            markup: cb_query
+                .regular_message()
+                .and_then(|msg| msg.reply_markup())
+                .cloned(),
+            cb_id: cb_query.id,
+        })
+    }
+
+    async fn answer_callback_query(
+        &self,
+        response: TgResponse,
+    ) -> Result<(), RequestError> {
+        self.msg_ctl.reply(response).await?;
+        self.acknowledge_callback().await
+    }
+
+    pub(crate) async fn acknowledge_callback(
+        &self,
+    ) -> Result<(), RequestError> {
+        self.msg_ctl
+            .bot
+            .answer_callback_query(&self.cb_id)
+            .send()
+            .await
+            .map(|_| ())
+    }
+
+    /// Remove the button that was pressed, keeping the others
+    async fn remove_pressed_button(
+        &self,
+        cb_data: &str,
+    ) -> Result<(), RequestError> {
+        self.remove_buttons(&[cb_data]).await
+    }
+
+    /// Remove the buttons with any of the callback data, keeping the others
+    async fn remove_buttons(
+        &self,
+        cb_data: &[&str],
+    ) -> Result<(), RequestError> {
+        let rows = self
+            .markup
+            .iter()
+            .flat_map(|markup| markup.inline_keyboard.iter())
+            .map(|row| {
+                row.iter()
+                    .filter(|button| {
+                        !matches!(
+                            button.kind,
+                            InlineKeyboardButtonKind::CallbackData(ref data)
+                                if cb_data.contains(&data.as_str())
+                        )
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .filter(|row| !row.is_empty());
+        tg::edit_markup(
+            InlineKeyboardMarkup::new(rows),
+            &self.msg_ctl.bot,
+            self.msg_ctl.msg_id,
+            self.msg_ctl.chat_id,
+        )
+        .await
+    }
+
+    /// Unpin the notification if it's pinned until marked done.
+    /// It may have been unpinned by hand already.
+    async fn unpin_occurrence(&self, occ: &occurrence::Model) {
+        let policy = NotificationPolicy::from_stored(occ.policy.as_deref());
+        if !policy.pin_until_done {
+            return;
+        }
+        tg::unpin_message(
+            &self.msg_ctl.bot,
+            ChatId(occ.chat_id),
+            MessageId(occ.msg_id),
+        )
+        .await
+        .unwrap_or_else(|err| tracing::warn!("{}", err));
+    }
+
+    /// Stop resending the notification and remove its "Done" button.
+    /// In private chats offer to add a note to the occurrence if asked,
+    /// return its id if offered.
+    pub(crate) async fn mark_done(
+        &self,
+        occ_id: i64,
+        ask_note: bool,
+    ) -> Result<Option<i64>, Error> {
+        let occ = self
+            .msg_ctl
+            .db
+            .get_occurrence(occ_id)
+            .await?
+            .filter(|occ| occ.done_time.is_none());
+        if let Some(ref occ) = occ {
+            self.msg_ctl
+                .db
+                .mark_occurrence_done(
+                    occ.clone(),
+                    history::keep_since(parsers::now_time()),
+                )
+                .await?;
+            self.unpin_occurrence(occ).await;
+        }
+        self.remove_pressed_button(&format!("donerem::occ::{}", occ_id))
+            .await?;
+        self.msg_ctl
+            .bot
+            .answer_callback_query(&self.cb_id)
+            .text(
+                TgResponse::MarkedDone
+                    .to_unescaped_string_in(self.msg_ctl.lang()),
+            )
+            .send()
+            .await?;
+        let Some(occ) =
+            occ.filter(|_| ask_note && self.msg_ctl.chat_id.is_user())
+        else {
+            return Ok(None);
+        };
+        tg::send_markup(
+            &TgResponse::AskOccurrenceNote.to_string_in(self.msg_ctl.lang()),
+            InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::new(
+                self.msg_ctl.lang().text("button_skip", &[]),
+                InlineKeyboardButtonKind::CallbackData(
+                    "donerem::skipnote".to_owned(),
+                ),
+            )]]),
+            &self.msg_ctl.bot,
+            self.msg_ctl.chat_id,
+        )
+        .await?;
+        Ok(Some(occ.id))
+    }
+
+    /// Don't add a note to the occurrence marked done
+    pub(crate) async fn skip_occurrence_note(
+        &self,
+    ) -> Result<(), RequestError> {
+        self.msg_ctl
+            .alter_reminder_set_page(InlineKeyboardMarkup::default())
+            .await?;
+        self.acknowledge_callback().await
+    }
+
+    /// Mark the bill as paid and remove its "Paid" button
+    /// Remind of the notification again once the snooze duration passes,
+    /// instead of resending it
+    pub(crate) async fn snooze(&self, occ_id: i64) -> Result<(), Error> {
+        let occ = self.msg_ctl.db.get_occurrence(occ_id).await?.filter(|occ| {
+            occ.chat_id == self.msg_ctl.chat_id.0 && occ.done_time.is_none()
+        });
+        let snooze = occ.as_ref().and_then(|occ| {
+            NotificationPolicy::from_stored(occ.policy.as_deref()).snooze
+        });
+        self.remove_buttons(&[
+            &format!("snooze::{}", occ_id),
+            &format!("donerem::occ::{}", occ_id),
+        ])
+        .await?;
+        let (Some(occ), Some(secs)) = (occ, snooze) else {
+            self.msg_ctl
+                .bot
+                .answer_callback_query(&self.cb_id)
+                .text(
+                    TgResponse::StaleButton
+                        .to_unescaped_string_in(self.msg_ctl.lang()),
+                )
+                .send()
+                .await?;
+            return Ok(());
+        };
+        self.msg_ctl
+            .db
+            .insert_reminder(reminder::ActiveModel {
+                id: NotSet,
+                chat_id: Set(occ.chat_id),
+                user_id: Set(Some(self.msg_ctl.user_id.0 as i64)),
+                time: Set(parsers::now_time()
+                    + TimeDelta::seconds(secs as i64)),
+                desc: Set(occ.desc.clone().unwrap_or_default()),
+                paused: Set(false),
+                pattern: Set(None),
+                policy: Set(occ.policy.clone()),
+                version: Set(0),
+                pomodoro_id: Set(None),
+                target: Set(None),
+                meeting: Set(false),
+                advance: Set(None),
+                advance_time: Set(None),
+                media_kind: Set(None),
+                media_file_id: Set(None),
+                held_since: Set(None),
+                failures: Set(0),
+                retry_time: Set(None),
+                thread_id: Set(occ.thread_id),
+                deleted_time: Set(None),
+            })
+            .await?;
+        self.msg_ctl.db.delete_occurrence(occ.id).await?;
+        self.unpin_occurrence(&occ).await;
+        history::record(
+            &self.msg_ctl.db,
+            occ.chat_id,
+            occ.desc.as_deref().unwrap_or_default(),
+            EventKind::Snoozed,
+        )
+        .await;
+        self.msg_ctl
+            .bot
+            .answer_callback_query(&self.cb_id)
+            .text(
+                TgResponse::Snoozed(format_lead(secs as i64))
+                    .to_unescaped_string_in(self.msg_ctl.lang()),
+            )
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Delete or pause the reminders once the confirmation is pressed.
+    /// The reminders are chosen again, so the ones set since the
+    /// confirmation was sent are included.
+    pub(crate) async fn bulk(
+        &self,
+        callback: bulk::Callback,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let bulk::Callback::Confirm(action, args) = callback else {
+            tg::edit_markup(
+                InlineKeyboardMarkup::default(),
+                &self.msg_ctl.bot,
+                self.msg_ctl.msg_id,
+                self.msg_ctl.chat_id,
+            )
+            .await?;
+            return Ok(self
+                .answer_callback_query(TgResponse::BulkCancelled)
+                .await?);
+        };
+        self.msg_ctl.check_chat_admin().await?;
+        tg::edit_markup(
+            InlineKeyboardMarkup::default(),
+            &self.msg_ctl.bot,
+            self.msg_ctl.msg_id,
+            self.msg_ctl.chat_id,
+        )
+        .await?;
+        let filter = bulk::filter(action, &args, user_tz, parsers::now_time());
+        let chat_id = self.msg_ctl.chat_id.0;
+        let response = match action {
+            bulk::Action::Delete => TgResponse::DeletedAll(
+                self.msg_ctl.db.delete_reminders(chat_id, filter).await?,
+            ),
+            bulk::Action::Pause => TgResponse::PausedAll(
+                self.msg_ctl.db.pause_reminders(chat_id, filter).await?,
+            ),
+        };
+        Ok(self.answer_callback_query(response).await?)
+    }
+
+    /// Put the deleted or edited reminder back as it was before,
+    /// unless the button is too old
+    pub(crate) async fn undo(
+        &self,
+        undo_id: u64,
+        user_tz: Tz,
     ) -> Result<(), Error> {
-        if let Some(old_reply_id) = old_reply_id {
-            tg::delete_message(&self.bot, self.chat_id, old_reply_id).await?;
+        self.remove_pressed_button(&format!("undo::{}", undo_id))
+            .await?;
+        let Some(snapshot) =
+            undo::take(undo_id, self.msg_ctl.chat_id.0, parsers::now_time())
+        else {
+            self.msg_ctl
+                .bot
+                .answer_callback_query(&self.cb_id)
+                .text(
+                    TgResponse::StaleButton
+                        .to_unescaped_string_in(self.msg_ctl.lang()),
+                )
+                .send()
+                .await?;
+            return Ok(());
+        };
+        let target = snapshot.previous.link_target();
+        if let Some(replacement) = snapshot.replacement {
+            if replacement != target {
+                self.msg_ctl
+                    .db
+                    .move_message_links(replacement, target)
+                    .await?;
+            }
         }
-        match reminder {
-            ActiveReminder::Reminder(ref reminder) => {
-                self.link_reminder_with_reply_msg(reminder.clone(), reply)
-                    .await
+        let response =
+            TgResponse::Undone(snapshot.previous.to_unescaped_string(user_tz));
+        let (reminders, cron_reminders) = match snapshot.previous {
+            Reminder::Reminder(reminder) => (vec![reminder], vec![]),
+            Reminder::CronReminder(cron_reminder) => {
+                (vec![], vec![cron_reminder])
             }
-            ActiveReminder::CronReminder(ref cron_reminder) => {
-                self.link_cron_reminder_with_reply_msg(
-                    cron_reminder.clone(),
-                    reply,
+        };
+        self.msg_ctl
+            .db
+            .restore_reminders(snapshot.replacement, reminders, cron_reminders)
+            .await?;
+        Ok(self.answer_callback_query(response).await?)
+    }
+
+    /// Stop resending the notification until tomorrow morning in the
+    /// user's timezone, without marking it done
+    pub(crate) async fn mute(
+        &self,
+        occ_id: i64,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let occ = self.msg_ctl.db.get_occurrence(occ_id).await?.filter(|occ| {
+            occ.chat_id == self.msg_ctl.chat_id.0 && occ.done_time.is_none()
+        });
+        self.remove_pressed_button(&format!("mute::{}", occ_id))
+            .await?;
+        let Some(occ) = occ else {
+            self.msg_ctl
+                .bot
+                .answer_callback_query(&self.cb_id)
+                .text(
+                    TgResponse::StaleButton
+                        .to_unescaped_string_in(self.msg_ctl.lang()),
                 )
-                .await
-            }
+                .send()
+                .await?;
+            return Ok(());
+        };
+        let tomorrow =
+            (user_tz.from_utc_datetime(&parsers::now_time()).date_naive()
+                + TimeDelta::days(1))
+            .and_hms_opt(MUTED_UNTIL_HOUR, 0, 0)
+            .unwrap();
+        let until = user_tz
+            .from_local_datetime(&tomorrow)
+            .earliest()
+            .map_or(tomorrow, |time| time.naive_utc());
+        self.msg_ctl
+            .db
+            .update_occurrence(occurrence::Model {
+                muted_until: Some(until),
+                nag_time: occ.nag_time.max(until),
+                ..occ
+            })
+            .await?;
+        let time = settings::format_clock(
+            MUTED_UNTIL_HOUR,
+            0,
+            settings::uses_12h_clock(self.msg_ctl.user_id.0 as i64),
+        );
+        self.msg_ctl
+            .bot
+            .answer_callback_query(&self.cb_id)
+            .text(
+                TgResponse::Muted(time)
+                    .to_unescaped_string_in(self.msg_ctl.lang()),
+            )
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Switch the pressed setting of the user to its next value
+    pub(crate) async fn change_setting(
+        &self,
+        setting: Setting,
+    ) -> Result<(), Error> {
+        let settings = self.msg_ctl.switch_setting(setting).await?;
+        tg::edit_markup(
+            settings::markup(&settings),
+            &self.msg_ctl.bot,
+            self.msg_ctl.msg_id,
+            self.msg_ctl.chat_id,
+        )
+        .await?;
+        self.msg_ctl
+            .bot
+            .answer_callback_query(&self.cb_id)
+            .text(
+                TgResponse::SettingsSaved
+                    .to_unescaped_string_in(self.msg_ctl.lang()),
+            )
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn mark_bill_paid(
+        &self,
+        bill_id: i64,
+    ) -> Result<(), Error> {
+        if let Some(bill) = self
+            .msg_ctl
+            .db
+            .get_bill(bill_id)
+            .await?
+            .filter(|bill| bill.chat_id == self.msg_ctl.chat_id.0)
+        {
+            self.msg_ctl.db.set_bill_paid(bill.id).await?;
         }
+        self.remove_pressed_button(&format!("bill::paid::{}", bill_id))
+            .await?;
+        self.msg_ctl
+            .bot
+            .answer_callback_query(&self.cb_id)
+            .text(
+                TgResponse::BillPaid
+                    .to_unescaped_string_in(self.msg_ctl.lang()),
+            )
+            .send()
+            .await?;
+        Ok(())
     }
 
     pub(crate) async fn set_timezone(
         &self,
         tz_name: &str,
     ) -> Result<(), RequestError> {
-        let response = match self
+        self.msg_ctl.set_timezone(tz_name).await?;
+        self.acknowledge_callback().await
+    }
+
+    pub(crate) async fn onboarding_set_timezone(
+        &self,
+        tz_name: &str,
+    ) -> Result<OnboardingStep, RequestError> {
+        let step = self.msg_ctl.onboarding_set_timezone(tz_name).await?;
+        self.acknowledge_callback().await?;
+        Ok(step)
+    }
+
+    /// Save the language chosen during onboarding and continue
+    /// with suggesting sample reminders
+    pub(crate) async fn onboarding_set_language(
+        &self,
+        lang: Language,
+    ) -> Result<(), RequestError> {
+        match self
+            .msg_ctl
             .db
-            .insert_or_update_user_timezone(self.user_id.0 as i64, tz_name)
+            .insert_or_update_user_language(
+                self.msg_ctl.user_id.0 as i64,
+                lang.code(),
+            )
             .await
         {
-            Ok(()) => TgResponse::ChosenTimezone(tz_name.to_owned()),
+            Ok(()) => {
+                settings::set_language(self.msg_ctl.user_id.0 as i64, lang);
+                self.msg_ctl.suggest_sample_reminders().await?
+            }
             Err(err) => {
-                log::error!("{}", err);
-                TgResponse::FailedSetTimezone(tz_name.to_owned())
+                tracing::error!("{}", err);
+                self.msg_ctl.reply(TgResponse::FailedSetLanguage).await?;
+            }
+        }
+        self.acknowledge_callback().await
+    }
+
+    /// Set the chosen sample reminder and finish onboarding
+    pub(crate) async fn onboarding_set_sample_reminder(
+        &self,
+        idx: usize,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        if let Some((_, text)) = SAMPLE_REMINDERS.get(idx) {
+            self.msg_ctl.set_new_reminder(text, user_tz).await?;
+        }
+        self.msg_ctl.finish_onboarding().await?;
+        self.acknowledge_callback().await.map_err(From::from)
+    }
+
+    /// Show another month on the calendar
+    pub(crate) async fn show_calendar(
+        &self,
+        purpose: picker::Purpose,
+        month: NaiveDate,
+        user_tz: Tz,
+    ) -> Result<(), RequestError> {
+        let today =
+            user_tz.from_utc_datetime(&parsers::now_time()).date_naive();
+        tg::edit_markup(
+            picker::calendar_markup(
+                purpose,
+                month,
+                today,
+                settings::date_style(self.msg_ctl.user_id.0 as i64),
+            ),
+            &self.msg_ctl.bot,
+            self.msg_ctl.msg_id,
+            self.msg_ctl.chat_id,
+        )
+        .await?;
+        self.acknowledge_callback().await
+    }
+
+    /// Move the one-time reminder to the date picked on the calendar,
+    /// keeping its time of day. Without the version of the editing started
+    /// the current one is used.
+    pub(crate) async fn move_reminder(
+        &self,
+        rem_id: i64,
+        date: NaiveDate,
+        version: Option<i32>,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let reminder = self
+            .msg_ctl
+            .db
+            .get_reminder(rem_id)
+            .await?
+            .ok_or(Error::ReminderNotFound(rem_id))?;
+        let time = user_tz.from_utc_datetime(&reminder.time);
+        let time_pattern =
+            picker::reminder_time(date, time.hour(), time.minute());
+        tg::edit_markup(
+            InlineKeyboardMarkup::default(),
+            &self.msg_ctl.bot,
+            self.msg_ctl.msg_id,
+            self.msg_ctl.chat_id,
+        )
+        .await?;
+        self.msg_ctl
+            .edit_reminder(
+                ReminderUpdate::ReminderTimePattern(rem_id, time_pattern),
+                version.unwrap_or(reminder.version),
+                user_tz,
+            )
+            .await?;
+        self.acknowledge_callback().await?;
+        Ok(())
+    }
+
+    /// Go on to the next step of setting a reminder with /new,
+    /// return the beginning of the reminder once its time is picked
+    pub(crate) async fn pick(
+        &self,
+        step: picker::Step,
+        user_tz: Tz,
+    ) -> Result<Option<String>, Error> {
+        let now = user_tz
+            .from_utc_datetime(&parsers::now_time())
+            .naive_local();
+        let clock_12h = settings::uses_12h_clock(self.msg_ctl.user_id.0 as i64);
+        let style = settings::date_style(self.msg_ctl.user_id.0 as i64);
+        let (response, markup, time) = match step {
+            picker::Step::Day(date) => (
+                TgResponse::PickHour(style.format_day(date, false)),
+                picker::hour_markup(date, now, clock_12h),
+                None,
+            ),
+            picker::Step::Hour(date, hour) => (
+                TgResponse::PickMinute(style.format_day(date, false)),
+                picker::minute_markup(date, hour, now, clock_12h),
+                None,
+            ),
+            picker::Step::Time(date, hour, minute) => (
+                TgResponse::AskNewReminderDescription(format!(
+                    "{} {}",
+                    style.format_day(date, false),
+                    settings::format_clock(hour, minute, clock_12h)
+                )),
+                InlineKeyboardMarkup::default(),
+                Some(picker::reminder_time(date, hour, minute)),
+            ),
+        };
+        tg::edit_text_markup(
+            &response.to_string_in(self.msg_ctl.lang()),
+            markup,
+            &self.msg_ctl.bot,
+            self.msg_ctl.msg_id,
+            self.msg_ctl.chat_id,
+        )
+        .await?;
+        self.acknowledge_callback().await?;
+        Ok(time)
+    }
+
+    /// Set the reminder of the template chosen among the favorites
+    pub(crate) async fn set_template_reminder(
+        &self,
+        name: &str,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        self.msg_ctl.set_template_reminder(name, user_tz).await?;
+        self.acknowledge_callback().await.map_err(From::from)
+    }
+
+    /// Save the user's answer to a meeting that isn't summed up yet
+    pub(crate) async fn set_rsvp(
+        &self,
+        occ_id: i64,
+        answer: Answer,
+        user: &User,
+    ) -> Result<(), Error> {
+        let occ = self.msg_ctl.db.get_occurrence(occ_id).await?.filter(|occ| {
+            occ.chat_id == self.msg_ctl.chat_id.0 && occ.meeting_time.is_some()
+        });
+        let response = match occ {
+            Some(occ) => {
+                self.msg_ctl
+                    .db
+                    .set_rsvp(
+                        occ.id,
+                        user.id.0 as i64,
+                        user.full_name(),
+                        answer,
+                    )
+                    .await?;
+                TgResponse::RsvpSaved
+            }
+            None => TgResponse::MeetingOver,
+        };
+        self.msg_ctl
+            .bot
+            .answer_callback_query(&self.cb_id)
+            .text(response.to_unescaped_string_in(self.msg_ctl.lang()))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Pause, edit or delete a group reminder from the private chat, or a
+    /// reminder of the chat listed by /today or /tomorrow.
+    /// Return the id and the version of the reminder to edit if asked.
+    pub(crate) async fn manage(
+        &self,
+        action: Action,
+        target: LinkTarget,
+    ) -> Result<Option<(i64, i32)>, Error> {
+        let ctl = &self.msg_ctl;
+        let reminder =
+            ctl.get_reminder_by_target(target)
+                .await?
+                .ok_or(match target {
+                    LinkTarget::Reminder(id) => Error::ReminderNotFound(id),
+                    LinkTarget::CronReminder(id) => {
+                        Error::CronReminderNotFound(id)
+                    }
+                })?;
+        // Only the members can press the buttons in the reminder's chat
+        if reminder.chat_id() != ctl.chat_id {
+            ctl.check_chat_member(reminder.chat_id()).await?;
+        }
+        let user_tz = ctl.group_reminder_timezone(&reminder).await?;
+        let reminder_str = reminder.to_unescaped_string(user_tz);
+        match (action, reminder) {
+            (Action::Show, reminder) => {
+                tg::send_markup(
+                    &TgResponse::ManageReminder(reminder_str)
+                        .to_string_in(ctl.lang()),
+                    manage::markup(
+                        target,
+                        reminder.paused(),
+                        ctl.chat_id.is_user(),
+                        ctl.lang(),
+                    ),
+                    &ctl.bot,
+                    ctl.chat_id,
+                )
+                .await?;
+                self.acknowledge_callback().await?;
+                Ok(None)
+            }
+            (Action::Pause, reminder) => {
+                let paused = match target {
+                    LinkTarget::Reminder(id) => {
+                        ctl.db.toggle_reminder_paused(id).await?
+                    }
+                    LinkTarget::CronReminder(id) => {
+                        ctl.db.toggle_cron_reminder_paused(id).await?
+                    }
+                };
+                tg::edit_markup(
+                    manage::markup(
+                        reminder.link_target(),
+                        paused,
+                        ctl.chat_id.is_user(),
+                        ctl.lang(),
+                    ),
+                    &ctl.bot,
+                    ctl.msg_id,
+                    ctl.chat_id,
+                )
+                .await?;
+                self.answer_callback_query(if paused {
+                    TgResponse::SuccessPause(reminder_str)
+                } else {
+                    TgResponse::SuccessResume(reminder_str)
+                })
+                .await?;
+                Ok(None)
+            }
+            (Action::Edit, Reminder::Reminder(reminder)) => {
+                self.answer_callback_query(TgResponse::EnterNewDescription)
+                    .await?;
+                Ok(Some((reminder.id, reminder.version)))
+            }
+            (Action::Edit, Reminder::CronReminder(_)) => {
+                self.acknowledge_callback().await?;
+                Ok(None)
+            }
+            (Action::Delete, _) => {
+                match target {
+                    LinkTarget::Reminder(id) => {
+                        ctl.db.trash_reminder(id).await?
+                    }
+                    LinkTarget::CronReminder(id) => {
+                        ctl.db.trash_cron_reminder(id).await?
+                    }
+                }
+                tg::edit_markup(
+                    InlineKeyboardMarkup::default(),
+                    &ctl.bot,
+                    ctl.msg_id,
+                    ctl.chat_id,
+                )
+                .await?;
+                self.answer_callback_query(TgResponse::SuccessDelete(
+                    reminder_str,
+                ))
+                .await?;
+                Ok(None)
             }
-        };
-        self.reply(response).await.map(|_| ())
-    }
-
-    async fn get_reminder_by_msg_id(
-        &self,
-        msg_id: MessageId,
-    ) -> Result<Option<Reminder>, Error> {
-        if let reminder @ Some(_) = self
-            .db
-            .get_reminder_by_msg_id(msg_id.0)
-            .await
-            .map(|rem| rem.map(Reminder::Reminder))?
-        {
-            Ok(reminder)
-        } else {
-            self.db
-                .get_cron_reminder_by_msg_id(msg_id.0)
-                .await
-                .map(|cron_rem| cron_rem.map(Reminder::CronReminder))
-                .map_err(From::from)
         }
     }
 
-    async fn get_reminder_by_reply_id(
+    /// Stop the focus sessions before all of them are over
+    pub(crate) async fn stop_pomodoro(
         &self,
-        reply_id: MessageId,
-    ) -> Result<Option<Reminder>, Error> {
-        if let reminder @ Some(_) = self
+        pomodoro_id: i64,
+    ) -> Result<(), Error> {
+        tg::edit_markup(
+            InlineKeyboardMarkup::default(),
+            &self.msg_ctl.bot,
+            self.msg_ctl.msg_id,
+            self.msg_ctl.chat_id,
+        )
+        .await?;
+        if let Some(session) = self
+            .msg_ctl
             .db
-            .get_reminder_by_reply_id(reply_id.0)
-            .await
-            .map(|rem| rem.map(Reminder::Reminder))?
+            .get_pomodoro(pomodoro_id)
+            .await?
+            .filter(|session| session.chat_id == self.msg_ctl.chat_id.0)
         {
-            Ok(reminder)
-        } else {
-            self.db
-                .get_cron_reminder_by_reply_id(reply_id.0)
-                .await
-                .map(|cron_rem| cron_rem.map(Reminder::CronReminder))
-                .map_err(From::from)
+            self.msg_ctl.db.delete_pomodoro(session.id).await?;
+            self.msg_ctl
+                .reply(TgResponse::PomodoroStopped(
+                    pomodoro::completed_rounds(&session),
+                    session.rounds,
+                ))
+                .await?;
         }
+        self.acknowledge_callback().await.map_err(From::from)
     }
 
-    async fn get_reminder_by_msg_or_reply_id(
+    /// Set the reminder suggested by the AI backend if the user accepted it
+    pub(crate) async fn confirm_suggestion(
         &self,
-        id: MessageId,
-    ) -> Result<Option<Reminder>, Error> {
-        if let reminder @ Some(_) = self.get_reminder_by_msg_id(id).await? {
-            Ok(reminder)
-        } else {
-            self.get_reminder_by_reply_id(id).await
+        suggestion: Option<String>,
+        accepted: bool,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        tg::edit_markup(
+            InlineKeyboardMarkup::default(),
+            &self.msg_ctl.bot,
+            self.msg_ctl.msg_id,
+            self.msg_ctl.chat_id,
+        )
+        .await?;
+        match suggestion {
+            Some(text) if accepted => {
+                self.msg_ctl.set_new_reminder(&text, user_tz).await?;
+            }
+            Some(_) => {
+                self.msg_ctl.reply(TgResponse::SuggestionDiscarded).await?;
+            }
+            None => {}
         }
+        self.acknowledge_callback().await.map_err(From::from)
     }
 
-    pub(crate) async fn edit_reminder_from_edited_message(
+    /// Set the reminder already set before if the user wants it twice
+    pub(crate) async fn confirm_duplicate(
         &self,
-        text: &str,
+        text: Option<String>,
+        add: bool,
         user_tz: Tz,
     ) -> Result<(), Error> {
-        let (reminder, old_reply_id, reply) = match self
-            .db
-            .get_reminder_by_msg_id(self.msg_id.0)
-            .await?
-        {
-            Some(old_rem) => self
-                .replace_reminder(text, old_rem.id, user_tz)
-                .await
-                .map(|(rem, msg)| (rem, old_rem.reply_id, Some(msg))),
-            None => {
-                match self.db.get_cron_reminder_by_msg_id(self.msg_id.0).await?
-                {
-                    Some(old_cron_rem) => self
-                        .replace_cron_reminder(text, old_cron_rem.id, user_tz)
-                        .await
-                        .map(|(rem, msg)| {
-                            (rem, old_cron_rem.reply_id, Some(msg))
-                        }),
-                    None => Ok((None, None, None)),
-                }
+        tg::edit_markup(
+            InlineKeyboardMarkup::default(),
+            &self.msg_ctl.bot,
+            self.msg_ctl.msg_id,
+            self.msg_ctl.chat_id,
+        )
+        .await?;
+        match text {
+            Some(text) if add => {
+                self.msg_ctl.set_new_reminder(&text, user_tz).await?;
             }
-        }?;
-
-        if let Some(ref reminder) = reminder {
-            if let Some(ref reply) = reply {
-                self.update_reply_link(
-                    reminder,
-                    reply,
-                    old_reply_id.map(MessageId),
-                )
-                .await?;
+            Some(_) => {
+                self.msg_ctl.reply(TgResponse::DuplicateDiscarded).await?;
             }
+            None => {}
         }
-        Ok(())
+        self.acknowledge_callback().await.map_err(From::from)
     }
-}
 
-impl TgCallbackController {
-    pub(crate) fn new(
-        db: Arc<Database>,
-        bot: Bot,
-        cb_query: CallbackQuery,
-    ) -> Option<TgCallbackController> {
-        Some(Self {
-            msg_ctl: TgMessageController::from_callback_query(
-                db, bot, &cb_query,
-            )?,
-            cb_id: cb_query.id,
-        })
+    pub(crate) async fn onboarding_skip(&self) -> Result<(), RequestError> {
+        self.msg_ctl.finish_onboarding().await?;
+        self.acknowledge_callback().await
     }
 
-    async fn answer_callback_query(
+    pub(crate) async fn delete_reminder(
         &self,
-        response: TgResponse,
+        rem_id: i64,
+        user_tz: Tz,
     ) -> Result<(), RequestError> {
-        self.msg_ctl.reply(response).await?;
-        self.acknowledge_callback().await
+        self.delete_listed(LinkTarget::Reminder(rem_id), user_tz)
+            .await
     }
 
-    pub(crate) async fn acknowledge_callback(
+    pub(crate) async fn delete_cron_reminder(
         &self,
+        cron_rem_id: i64,
+        user_tz: Tz,
     ) -> Result<(), RequestError> {
-        self.msg_ctl
-            .bot
-            .answer_callback_query(&self.cb_id)
-            .send()
+        self.delete_listed(LinkTarget::CronReminder(cron_rem_id), user_tz)
             .await
-            .map(|_| ())
     }
 
-    pub(crate) async fn set_timezone(
+    /// Delete the reminder chosen in the menu, a recurring one only once
+    /// it's answered what to delete of it
+    async fn delete_listed(
         &self,
-        tz_name: &str,
+        target: LinkTarget,
+        user_tz: Tz,
     ) -> Result<(), RequestError> {
-        self.msg_ctl.set_timezone(tz_name).await?;
+        if !self.msg_ctl.ask_delete_series(target, user_tz).await? {
+            self.msg_ctl.delete_target(target, user_tz).await?;
+            self.msg_ctl.delete_reminder_set_page(0, user_tz).await?;
+        }
         self.acknowledge_callback().await
     }
 
-    pub(crate) async fn delete_reminder(
+    /// Delete the series of a recurring reminder or skip its next
+    /// occurrence as answered, then show the menu again
+    pub(crate) async fn delete_series(
         &self,
-        rem_id: i64,
+        callback: series::Callback,
         user_tz: Tz,
     ) -> Result<(), RequestError> {
-        let response = match self.msg_ctl.db.get_reminder(rem_id).await {
-            Ok(Some(reminder)) => {
-                match self.msg_ctl.db.delete_reminder(rem_id).await {
-                    Ok(()) => TgResponse::SuccessDelete(
-                        reminder
-                            .into_active_model()
-                            .to_unescaped_string(user_tz),
-                    ),
-                    Err(err) => {
-                        log::error!("{}", err);
-                        TgResponse::FailedDelete
-                    }
-                }
-            }
-            Err(err) => {
-                log::error!("{}", err);
-                TgResponse::FailedDelete
+        let response = match callback {
+            series::Callback::DeleteSeries(target) => {
+                self.msg_ctl.delete_target(target, user_tz).await?;
+                None
             }
-            _ => {
-                log::error!("missing reminder with id: {}", rem_id);
-                TgResponse::FailedDelete
+            series::Callback::SkipNext(target) => {
+                Some(self.msg_ctl.skip_target(target, user_tz).await)
             }
+            series::Callback::Cancel => Some(TgResponse::NothingDeleted),
         };
-        self.msg_ctl.delete_reminder_set_page(0, user_tz).await?;
-        self.answer_callback_query(response).await
+        self.msg_ctl.delete_reminder_reset_menu(user_tz).await?;
+        match response {
+            Some(response) => self.answer_callback_query(response).await,
+            None => self.acknowledge_callback().await,
+        }
     }
 
-    pub(crate) async fn delete_cron_reminder(
+    /// Unlink the group whose button was pressed from the private chat
+    pub(crate) async fn unlink_chat(&self, chat_id: i64) -> Result<(), Error> {
+        let ctl = &self.msg_ctl;
+        let user_id = ctl.user_id.0 as i64;
+        let mut links = ctl.db.get_account_links(user_id).await?;
+        let Some(idx) = links.iter().position(|link| link.chat_id == chat_id)
+        else {
+            return Ok(self.acknowledge_callback().await?);
+        };
+        let link = links.remove(idx);
+        ctl.db.delete_account_link(user_id, chat_id).await?;
+        if links.is_empty() {
+            tg::edit_text_markup(
+                &TgResponse::NoLinkedChats.to_string_in(ctl.lang()),
+                InlineKeyboardMarkup::default(),
+                &ctl.bot,
+                ctl.msg_id,
+                ctl.chat_id,
+            )
+            .await?;
+        } else {
+            tg::edit_markup(
+                account::unlink_markup(&links),
+                &ctl.bot,
+                ctl.msg_id,
+                ctl.chat_id,
+            )
+            .await?;
+        }
+        Ok(self
+            .answer_callback_query(TgResponse::Unlinked(link.title))
+            .await?)
+    }
+
+    /// Make the user the owner of an orphaned reminder, or of all of them
+    pub(crate) async fn claim_orphaned_reminders(
         &self,
-        cron_rem_id: i64,
+        target: Option<LinkTarget>,
         user_tz: Tz,
-    ) -> Result<(), RequestError> {
-        let response = match self
+    ) -> Result<(), Error> {
+        self.msg_ctl.check_chat_admin().await?;
+        let claimed = self
             .msg_ctl
             .db
-            .get_cron_reminder(cron_rem_id)
-            .await
-        {
-            Ok(Some(cron_reminder)) => {
-                match self.msg_ctl.db.delete_cron_reminder(cron_rem_id).await {
-                    Ok(()) => TgResponse::SuccessDelete(
-                        cron_reminder
-                            .into_active_model()
-                            .to_unescaped_string(user_tz),
-                    ),
-                    Err(err) => {
-                        log::error!("{}", err);
-                        TgResponse::FailedDelete
-                    }
-                }
-            }
-            Err(err) => {
-                log::error!("{}", err);
-                TgResponse::FailedDelete
-            }
-            _ => {
-                log::error!("missing cron reminder with id: {}", cron_rem_id);
-                TgResponse::FailedDelete
-            }
-        };
-        self.msg_ctl.delete_reminder_set_page(0, user_tz).await?;
-        self.answer_callback_query(response).await
+            .claim_orphaned_reminders(
+                self.msg_ctl.chat_id.0,
+                target,
+                self.msg_ctl.user_id.0 as i64,
+            )
+            .await?;
+        self.msg_ctl.orphaned_reminders_set_page(user_tz).await?;
+        self.answer_callback_query(TgResponse::OrphansClaimed(claimed))
+            .await?;
+        Ok(())
+    }
+
+    /// Delete an orphaned reminder, or all of them
+    pub(crate) async fn delete_orphaned_reminders(
+        &self,
+        target: Option<LinkTarget>,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        self.msg_ctl.check_chat_admin().await?;
+        let deleted = self
+            .msg_ctl
+            .db
+            .delete_orphaned_reminders(self.msg_ctl.chat_id.0, target)
+            .await?;
+        self.msg_ctl.orphaned_reminders_set_page(user_tz).await?;
+        self.answer_callback_query(TgResponse::OrphansDeleted(deleted))
+            .await?;
+        Ok(())
+    }
+
+    /// Take a deleted reminder out of the trash, or all of them
+    pub(crate) async fn restore_trashed_reminders(
+        &self,
+        target: Option<LinkTarget>,
+        user_tz: Tz,
+    ) -> Result<(), Error> {
+        let restored = self
+            .msg_ctl
+            .db
+            .restore_trashed_reminders(self.msg_ctl.chat_id.0, target)
+            .await?;
+        self.msg_ctl.trashed_reminders_set_page(user_tz).await?;
+        self.answer_callback_query(TgResponse::RestoredReminders(restored))
+            .await?;
+        Ok(())
     }
 
-    pub(crate) async fn choose_edit_mode_reminder(
+    /// Start editing a reminder as a whole, return its current version
+    pub(crate) async fn edit_reminder(
         &self,
         rem_id: i64,
-    ) -> Result<(), RequestError> {
-        let markup = InlineKeyboardMarkup::default().append_row(vec![
-            InlineKeyboardButton::new(
-                "Time pattern",
-                InlineKeyboardButtonKind::CallbackData(format!(
-                    "edit_rem_mode::rem_time_pattern::{}",
-                    rem_id
-                )),
-            ),
-            InlineKeyboardButton::new(
-                "Description",
-                InlineKeyboardButtonKind::CallbackData(format!(
-                    "edit_rem_mode::rem_description::{}",
-                    rem_id
-                )),
-            ),
-        ]);
-        tg::send_markup(
-            "What would you like to edit?",
-            markup,
-            &self.msg_ctl.bot,
-            self.msg_ctl.chat_id,
-        )
-        .await?;
-        self.acknowledge_callback().await
+        user_tz: Tz,
+    ) -> Result<i32, Error> {
+        let version = self
+            .msg_ctl
+            .start_edit_reminder(rem_id, &EditMode::Whole, user_tz)
+            .await?;
+        self.acknowledge_callback().await?;
+        Ok(version)
     }
 
-    pub(crate) async fn edit_cron_reminder(&self) -> Result<(), RequestError> {
-        let response = TgResponse::EnterNewReminder;
-        self.answer_callback_query(response).await
+    /// Start editing a cron reminder, return its current version
+    pub(crate) async fn edit_cron_reminder(
+        &self,
+        cron_rem_id: i64,
+    ) -> Result<i32, Error> {
+        let version =
+            self.msg_ctl.start_edit_cron_reminder(cron_rem_id).await?;
+        self.acknowledge_callback().await?;
+        Ok(version)
     }
 
     pub(crate) async fn pause_reminder(
@@ -1088,30 +5216,10 @@ impl TgCallbackController {
         rem_id: i64,
         user_tz: Tz,
     ) -> Result<(), RequestError> {
-        let response = match self.msg_ctl.db.get_reminder(rem_id).await {
-            Ok(Some(reminder)) => {
-                match self.msg_ctl.db.toggle_reminder_paused(rem_id).await {
-                    Ok(true) => TgResponse::SuccessPause(
-                        reminder
-                            .into_active_model()
-                            .to_unescaped_string(user_tz),
-                    ),
-                    Ok(false) => TgResponse::SuccessResume(
-                        reminder
-                            .into_active_model()
-                            .to_unescaped_string(user_tz),
-                    ),
-                    Err(err) => {
-                        log::error!("{}", err);
-                        TgResponse::FailedPause
-                    }
-                }
-            }
-            _ => {
-                log::error!("missing reminder with id: {}", rem_id);
-                TgResponse::FailedPause
-            }
-        };
+        let response = self
+            .msg_ctl
+            .pause_target(LinkTarget::Reminder(rem_id), user_tz)
+            .await;
         self.msg_ctl.pause_reminder_set_page(0, user_tz).await?;
         self.answer_callback_query(response).await
     }
@@ -1121,51 +5229,121 @@ impl TgCallbackController {
         cron_rem_id: i64,
         user_tz: Tz,
     ) -> Result<(), RequestError> {
-        let response =
-            match self.msg_ctl.db.get_cron_reminder(cron_rem_id).await {
-                Ok(Some(cron_reminder)) => {
-                    match self
-                        .msg_ctl
-                        .db
-                        .toggle_cron_reminder_paused(cron_rem_id)
-                        .await
-                    {
-                        Ok(true) => TgResponse::SuccessPause(
-                            cron_reminder
-                                .into_active_model()
-                                .to_unescaped_string(user_tz),
-                        ),
-                        Ok(false) => TgResponse::SuccessResume(
-                            cron_reminder
-                                .into_active_model()
-                                .to_unescaped_string(user_tz),
-                        ),
-                        Err(err) => {
-                            log::error!("{}", err);
-                            TgResponse::FailedPause
-                        }
-                    }
-                }
-                _ => {
-                    log::error!(
-                        "missing cron reminder with id: {}",
-                        cron_rem_id
-                    );
-                    TgResponse::FailedPause
-                }
-            };
+        let response = self
+            .msg_ctl
+            .pause_target(LinkTarget::CronReminder(cron_rem_id), user_tz)
+            .await;
         self.msg_ctl.pause_reminder_set_page(0, user_tz).await?;
         self.answer_callback_query(response).await
     }
 
-    pub(crate) async fn set_edit_mode_reminder(
+    pub(crate) async fn skip_reminder(
         &self,
-        edit_mode: EditMode,
+        rem_id: i64,
+        user_tz: Tz,
     ) -> Result<(), RequestError> {
-        let response = match edit_mode {
-            EditMode::TimePattern => TgResponse::EnterNewTimePattern,
-            EditMode::Description => TgResponse::EnterNewDescription,
-        };
+        let response = self
+            .msg_ctl
+            .skip_target(LinkTarget::Reminder(rem_id), user_tz)
+            .await;
+        self.msg_ctl.skip_reminder_set_page(0, user_tz).await?;
+        self.answer_callback_query(response).await
+    }
+
+    pub(crate) async fn skip_cron_reminder(
+        &self,
+        cron_rem_id: i64,
+        user_tz: Tz,
+    ) -> Result<(), RequestError> {
+        let response = self
+            .msg_ctl
+            .skip_target(LinkTarget::CronReminder(cron_rem_id), user_tz)
+            .await;
+        self.msg_ctl.skip_reminder_set_page(0, user_tz).await?;
         self.answer_callback_query(response).await
     }
+
+    /// Edit only a part of the reminder keeping the other one,
+    /// return its current version
+    pub(crate) async fn set_edit_mode_reminder(
+        &self,
+        rem_id: i64,
+        edit_mode: EditMode,
+        user_tz: Tz,
+    ) -> Result<i32, Error> {
+        self.msg_ctl
+            .alter_reminder_set_page(InlineKeyboardMarkup::default())
+            .await?;
+        let version = self
+            .msg_ctl
+            .start_edit_reminder(rem_id, &edit_mode, user_tz)
+            .await?;
+        self.acknowledge_callback().await?;
+        Ok(version)
+    }
+
+    /// Copy the reminder from the edit menu to edit the copy's time
+    /// pattern, return the copy's id and version
+    pub(crate) async fn duplicate_reminder(
+        &self,
+        rem_id: i64,
+        user_tz: Tz,
+    ) -> Result<(i64, i32), Error> {
+        self.msg_ctl
+            .alter_reminder_set_page(InlineKeyboardMarkup::default())
+            .await?;
+        let copy = self.msg_ctl.start_edit_duplicate(rem_id, user_tz).await?;
+        self.acknowledge_callback().await?;
+        Ok(copy)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_setup_checks_member() {
+        let checks = setup_checks(
+            true,
+            &ChatMemberKind::Member,
+            ChatPermissions::SEND_MESSAGES,
+            true,
+        )
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+        assert_eq!(
+            checks,
+            [
+                TgResponse::SetupPrivacyMode(true),
+                TgResponse::SetupCanSend(true),
+                TgResponse::SetupCanPin(false),
+                TgResponse::SetupCanPostInTopics(false),
+            ]
+            .map(|check| check.to_string())
+        );
+    }
+
+    #[test]
+    fn test_setup_checks_left() {
+        let checks = setup_checks(
+            false,
+            &ChatMemberKind::Left,
+            ChatPermissions::all(),
+            false,
+        )
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+        assert_eq!(
+            checks,
+            [
+                TgResponse::SetupPrivacyMode(false),
+                TgResponse::SetupCanSend(false),
+                TgResponse::SetupCanPin(false),
+            ]
+            .map(|check| check.to_string())
+        );
+    }
 }
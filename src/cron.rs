@@ -0,0 +1,423 @@
+//! Scheduling of cron reminders in the user's timezone. The expressions
+//! match the wall-clock time, so that a reminder at 09:00 stays at 09:00
+//! across daylight saving changes:
+//! - a time repeated when the clocks go back fires once, the first time,
+//! - a time skipped when the clocks go forward fires right after the change.
+//!
+//! The expressions are shown described in words where possible,
+//! e.g. `55 10 * * 1-5` as "at 10:55 on weekdays".
+
+use crate::lang::Language;
+use crate::settings;
+use chrono::{NaiveDateTime, TimeDelta, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use cron_parser::{parse as parse_cron, parse_field, ParseError};
+
+/// Longest jump of the clocks going forward
+const MAX_GAP: TimeDelta = TimeDelta::hours(3);
+
+/// Names and ranges of the fields of an expression
+const FIELDS: [(&str, u32, u32); 5] = [
+    ("minute", 0, 59),
+    ("hour", 0, 23),
+    ("day of month", 1, 31),
+    ("month", 1, 12),
+    ("day of week", 0, 6),
+];
+
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Most times of the day listed in a description
+const MAX_LISTED_TIMES: usize = 6;
+
+/// Field of an expression as it's described
+#[derive(Debug, PartialEq)]
+enum Field {
+    Any,
+    /// `*/n`
+    Step(u32),
+    /// Values and ranges of the values, a value being a range of itself
+    Items(Vec<(u32, u32)>),
+}
+
+impl Field {
+    fn parse(field: &str) -> Option<Self> {
+        if field == "*" {
+            return Some(Self::Any);
+        }
+        if let Some(step) = field.strip_prefix("*/") {
+            return step.parse().ok().filter(|&step| step > 0).map(Self::Step);
+        }
+        let value = |s: &str| {
+            s.parse().ok().or_else(|| {
+                WEEKDAYS
+                    .iter()
+                    .position(|day| day.eq_ignore_ascii_case(s))
+                    .map(|idx| idx as u32)
+            })
+        };
+        field
+            .split(',')
+            .map(|item| match item.split_once('-') {
+                Some((from, until)) => Some((value(from)?, value(until)?)),
+                None => value(item).map(|value| (value, value)),
+            })
+            .collect::<Option<_>>()
+            .map(Self::Items)
+    }
+
+    /// Single values if there are no ranges
+    fn values(&self) -> Option<Vec<u32>> {
+        match self {
+            Self::Items(items) => items
+                .iter()
+                .map(|&(from, until)| (from == until).then_some(from))
+                .collect(),
+            _ => None,
+        }
+    }
+}
+
+/// First instant in UTC at or after the wall-clock time
+fn local_to_utc(local: NaiveDateTime, tz: Tz) -> Option<NaiveDateTime> {
+    (0..=MAX_GAP.num_minutes())
+        .map(|minutes| local + TimeDelta::minutes(minutes))
+        .find_map(|local| tz.from_local_datetime(&local).earliest())
+        .map(|time| time.naive_utc())
+}
+
+/// Time of the expression's next firing strictly after the UTC time.
+/// Reschedule after both the previous firing and the current time,
+/// so that the firings missed while the bot was down collapse into one.
+pub(crate) fn next_time(
+    cron_expr: &str,
+    after: NaiveDateTime,
+    tz: Tz,
+) -> Result<NaiveDateTime, ParseError> {
+    // walk the wall-clock times, the parser knows nothing about offsets
+    let mut local = tz.from_utc_datetime(&after).naive_local();
+    loop {
+        local =
+            parse_cron(cron_expr, &Utc.from_utc_datetime(&local))?.naive_utc();
+        if let Some(time) = local_to_utc(local, tz).filter(|&t| t > after) {
+            return Ok(time);
+        }
+    }
+}
+
+/// Items joined with commas and the last one with "and"
+fn and_list(mut items: Vec<String>, lang: Language) -> String {
+    match items.pop() {
+        Some(last) if !items.is_empty() => lang
+            .text("and_list", &[("items", &items.join(", ")), ("last", &last)]),
+        Some(last) => last,
+        None => String::new(),
+    }
+}
+
+/// Items of a field named with the function, e.g. `January to March`
+fn named_items(
+    items: &[(u32, u32)],
+    name: impl Fn(u32) -> Option<String>,
+    lang: Language,
+) -> Option<String> {
+    items
+        .iter()
+        .map(|&(from, until)| {
+            if from == until {
+                name(from)
+            } else {
+                Some(lang.text(
+                    "cron_range",
+                    &[("from", &name(from)?), ("until", &name(until)?)],
+                ))
+            }
+        })
+        .collect::<Option<_>>()
+        .map(|items| and_list(items, lang))
+}
+
+/// Weekday of the field, where both 0 and 7 are Sunday
+fn weekday(day: u32) -> Option<Weekday> {
+    Weekday::try_from(((day + 6) % 7) as u8)
+        .ok()
+        .filter(|_| day <= 7)
+}
+
+/// When in the day the expression fires, e.g. `at 9:00 and 18:00`
+fn describe_time(
+    minute: &Field,
+    hour: &Field,
+    clock_12h: bool,
+    lang: Language,
+) -> Option<String> {
+    let clock = |hour, minute| settings::format_clock(hour, minute, clock_12h);
+    let minutes = |minute: &Field| {
+        minute.values().map(|values| {
+            and_list(values.iter().map(u32::to_string).collect(), lang)
+        })
+    };
+    match (minute, hour) {
+        (Field::Any, Field::Any) => Some(lang.text("cron_every_minute", &[])),
+        (Field::Step(step), Field::Any) => {
+            Some(lang.text("cron_every_minutes", &[("step", step)]))
+        }
+        (minute, Field::Any) => Some(
+            lang.text("cron_every_hour_at", &[("minutes", &minutes(minute)?)]),
+        ),
+        (minute, Field::Step(step)) => Some(lang.text(
+            "cron_every_hours_at",
+            &[("step", step), ("minutes", &minutes(minute)?)],
+        )),
+        (minute, Field::Items(hours)) => {
+            let minutes = minute.values()?;
+            match (hours.as_slice(), minutes.as_slice()) {
+                (&[(from, until)], &[minute]) if from < until => {
+                    Some(lang.text(
+                        "cron_every_hour_between",
+                        &[
+                            ("from", &clock(from, minute)),
+                            ("until", &clock(until, minute)),
+                        ],
+                    ))
+                }
+                _ => {
+                    let times: Vec<_> = hour
+                        .values()?
+                        .into_iter()
+                        .flat_map(|hour| {
+                            minutes.iter().map(move |&minute| (hour, minute))
+                        })
+                        .map(|(hour, minute)| clock(hour, minute))
+                        .collect();
+                    (times.len() <= MAX_LISTED_TIMES).then(|| {
+                        lang.text(
+                            "cron_at",
+                            &[("times", &and_list(times, lang))],
+                        )
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// On which days the expression fires, nothing if on every day
+fn describe_days(
+    day: &Field,
+    month: &Field,
+    weekday_field: &Field,
+    lang: Language,
+) -> Option<Option<String>> {
+    let months = match month {
+        Field::Any => None,
+        Field::Items(items) => Some(named_items(
+            items,
+            |month| lang.month_name(month).map(str::to_owned),
+            lang,
+        )?),
+        Field::Step(_) => return None,
+    };
+    let name = |day| weekday(day).map(|day| lang.weekday_name(day).to_owned());
+    let days = match (day, weekday_field) {
+        (Field::Any, Field::Any) => {
+            return Some(months.map(|months| {
+                lang.text("cron_every_day_in", &[("months", &months)])
+            }))
+        }
+        (Field::Any, Field::Items(items)) => {
+            let days = match items.as_slice() {
+                [(1, 5)] => lang.text("cron_on_weekdays", &[]),
+                [(0, 0), (6, 6)] | [(6, 6), (0, 0)] => {
+                    lang.text("cron_on_weekends", &[])
+                }
+                [(from, until)] if from < until => lang.text(
+                    "cron_weekday_range",
+                    &[("from", &name(*from)?), ("until", &name(*until)?)],
+                ),
+                items => lang.text(
+                    "cron_on_days_of_week",
+                    &[(
+                        "days",
+                        &named_items(
+                            items,
+                            |day| {
+                                weekday(day).map(|day| {
+                                    lang.weekday_plural(day).to_owned()
+                                })
+                            },
+                            lang,
+                        )?,
+                    )],
+                ),
+            };
+            match months {
+                Some(months) => lang.text(
+                    "cron_in_months",
+                    &[("days", &days), ("months", &months)],
+                ),
+                None => days,
+            }
+        }
+        (Field::Items(items), Field::Any) => {
+            let days = named_items(items, |day| Some(lang.ordinal(day)), lang)?;
+            match months {
+                Some(months) => lang.text(
+                    "cron_on_days_of_month",
+                    &[("days", &days), ("months", &months)],
+                ),
+                None => {
+                    lang.text("cron_on_days_of_every_month", &[("days", &days)])
+                }
+            }
+        }
+        _ => return None,
+    };
+    Some(Some(days))
+}
+
+/// Expression described in words in the language, e.g.
+/// `at 10:55 on weekdays`, if it's simple enough for that
+pub(crate) fn describe(
+    cron_expr: &str,
+    clock_12h: bool,
+    lang: Language,
+) -> Option<String> {
+    let fields = cron_expr
+        .split_whitespace()
+        .map(Field::parse)
+        .collect::<Option<Vec<_>>>()?;
+    let [minute, hour, day, month, weekday] = fields.as_slice() else {
+        return None;
+    };
+    let time = describe_time(minute, hour, clock_12h, lang)?;
+    Some(match describe_days(day, month, weekday, lang)? {
+        Some(days) => format!("{} {}", time, days),
+        None => time,
+    })
+}
+
+/// Whether the word may be the field of an expression at the position
+fn is_field(word: &str, idx: usize) -> bool {
+    word.split(['*', '/', ',', '-']).all(|part| {
+        part.chars().all(|c| c.is_ascii_digit())
+            || idx == 4 && WEEKDAYS.contains(&part.to_lowercase().as_str())
+    })
+}
+
+/// What's wrong with the text starting like a cron expression, e.g. that
+/// `*/5 * * * call` has four fields. Nothing if it's a valid expression
+/// or doesn't look like one.
+pub(crate) fn diagnose(text: &str) -> Option<String> {
+    let fields: Vec<_> = text
+        .split_whitespace()
+        .take(FIELDS.len())
+        .enumerate()
+        .take_while(|&(idx, word)| is_field(word, idx))
+        .map(|(_, word)| word)
+        .collect();
+    if fields.len() < FIELDS.len() {
+        return fields
+            .iter()
+            .any(|field| field.contains('*'))
+            .then(|| format!("it has {} fields instead of 5", fields.len()));
+    }
+    for (field, (name, min, max)) in fields.iter().zip(FIELDS) {
+        // the parser takes the names of the days only as whole expressions
+        if name == "day of week"
+            && field.chars().any(|c| c.is_ascii_alphabetic())
+        {
+            continue;
+        }
+        if parse_field(field, min, max).is_err() {
+            return Some(format!(
+                "the {} \"{}\" should be from {} to {}",
+                name, field, min, max
+            ));
+        }
+    }
+    next_time(&fields.join(" "), Utc::now().naive_utc(), Tz::UTC)
+        .is_err()
+        .then(|| "it never fires".to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test_case("0 9 * * *", "2024-03-30 12:00" => vec![
+        "2024-03-31 07:00", "2024-04-01 07:00",
+    ] ; "same wall-clock time after spring forward")]
+    #[test_case("30 2 * * *", "2024-03-30 12:00" => vec![
+        "2024-03-31 01:00", "2024-04-01 00:30",
+    ] ; "skipped time fires after the change")]
+    #[test_case("30 2 * * *", "2024-10-26 12:00" => vec![
+        "2024-10-27 00:30", "2024-10-28 01:30",
+    ] ; "repeated time fires once")]
+    #[test_case("*/30 * * * *", "2024-10-27 00:10" => vec![
+        "2024-10-27 00:30", "2024-10-27 02:00",
+    ] ; "repeated hour is skipped")]
+    #[test_case("0 9 * * *", "2024-06-01 07:00" => vec![
+        "2024-06-02 07:00", "2024-06-03 07:00",
+    ] ; "strictly after")]
+    fn test_next_time(cron_expr: &str, after: &str) -> Vec<String> {
+        std::iter::successors(Some(utc(after)), |&after| {
+            next_time(cron_expr, after, chrono_tz::Europe::Amsterdam).ok()
+        })
+        .skip(1)
+        .take(2)
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .collect()
+    }
+
+    #[test_case("*/5 * * * call mom" => Some("it has 4 fields instead of 5".to_owned()) ; "missing field")]
+    #[test_case("0 25 * * * wake up" => Some("the hour \"25\" should be from 0 to 23".to_owned()) ; "out of range")]
+    #[test_case("0 9 * 13 mon-fri standup" => Some("the month \"13\" should be from 1 to 12".to_owned()) ; "month with weekday names")]
+    #[test_case("0 0 31 2 * never" => Some("it never fires".to_owned()) ; "never")]
+    #[test_case("0 9 * * mon-fri standup" => None ; "valid")]
+    #[test_case("10 30 call mom" => None ; "not cron")]
+    fn test_diagnose(text: &str) -> Option<String> {
+        diagnose(text)
+    }
+
+    #[test_case("55 10 * * 1-5" => Some("at 10:55 on weekdays".to_owned()) ; "weekdays")]
+    #[test_case("0 9,18 * * mon,fri" => Some("at 09:00 and 18:00 on Mondays and Fridays".to_owned()) ; "weekday names")]
+    #[test_case("0 10 * * 0,6" => Some("at 10:00 on weekends".to_owned()) ; "weekends")]
+    #[test_case("45 10-19 * * 1-6" => Some("every hour from 10:45 to 19:45 from Monday to Saturday".to_owned()) ; "hour range")]
+    #[test_case("*/5 * * * *" => Some("every 5 minutes".to_owned()) ; "step")]
+    #[test_case("30 * * * *" => Some("every hour at 30 minutes past".to_owned()) ; "hourly")]
+    #[test_case("0 12 1,15 * *" => Some("at 12:00 on the 1st and 15th of every month".to_owned()) ; "days of month")]
+    #[test_case("0 8 1 1-3 *" => Some("at 08:00 on the 1st of January to March".to_owned()) ; "months")]
+    #[test_case("0 8 * 12 *" => Some("at 08:00 every day in December".to_owned()) ; "every day in month")]
+    #[test_case("0 8 1 * 1" => None ; "both days of month and week")]
+    #[test_case("0 8-12/2 * * *" => None ; "step in range")]
+    fn test_describe(cron_expr: &str) -> Option<String> {
+        describe(cron_expr, false, Language::English)
+    }
+
+    #[test_case(Language::Russian, "55 10 * * 1-5" => Some("в 10:55 по будням".to_owned()) ; "russian weekdays")]
+    #[test_case(Language::Russian, "0 9 * * mon,fri" => Some("в 09:00 по понедельникам и пятницам".to_owned()) ; "russian weekday names")]
+    #[test_case(Language::German, "0 8 1 1-3 *" => Some("um 08:00 am 1. im Januar bis März".to_owned()) ; "german months")]
+    #[test_case(Language::Spanish, "0 12 1,15 * *" => Some("a las 12:00 el día 1 y 15 de cada mes".to_owned()) ; "spanish days of month")]
+    fn test_describe_in(lang: Language, cron_expr: &str) -> Option<String> {
+        describe(cron_expr, false, lang)
+    }
+
+    #[test]
+    fn test_describe_12h() {
+        assert_eq!(
+            describe("30 21 * * 5", true, Language::English),
+            Some("at 9:30 PM on Fridays".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(next_time("0 9 * *", utc("2024-06-01 07:00"), Tz::UTC).is_err());
+    }
+}
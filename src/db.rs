@@ -1,54 +1,230 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
 
-use crate::cli::CLI;
-use crate::entity::{cron_reminder, reminder, user_timezone};
+use crate::cli::{JournalMode, CLI};
+#[cfg(feature = "ai")]
+use crate::entity::ai_opt_in;
+use crate::entity::message_link::{self, MessageRole};
+use crate::entity::{
+    account_link, api_token, bill, calendar_feed, chat_timezone, cron_reminder,
+    digest, expiring_message, occurrence, pomodoro, reminder, reminder_event,
+    reminder_target, rsvp, sending, share_link, template, user_language,
+    user_settings, user_timezone, vacation,
+};
+use crate::err::Error;
 use crate::generic_reminder;
+use crate::lang::Language;
+use crate::meeting;
 use crate::migration::{DbErr, Migrator, MigratorTrait};
+use crate::retry::MAX_FAILURES;
+use crate::search::Filter;
+use crate::tz::ReminderTimezones;
 use chrono::{NaiveDateTime, Utc};
 #[cfg(test)]
 use mockall::automock;
+use sea_orm::sea_query::{
+    Alias, Expr, Func, LikeExpr, Order, Query, UnionType,
+};
+use sea_orm::sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions,
+    SqliteSynchronous,
+};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, ConnectOptions, Database as SeaOrmDatabase,
-    DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    ActiveModelTrait, ColumnTrait, Condition, ConnectOptions, ConnectionTrait,
+    Database as SeaOrmDatabase, DatabaseConnection, DatabaseTransaction,
+    DbBackend, EntityTrait, NotSet, PaginatorTrait, QueryFilter, QueryOrder,
+    QuerySelect, RuntimeErr, Set, SqlxSqliteConnector, TransactionTrait,
 };
 use tokio::sync::futures::Notified;
 use tokio::sync::Notify;
 
-#[derive(Debug)]
-pub(crate) enum Error {
-    Database(DbErr),
-    File(std::io::Error),
+/// Reminder of either kind, e.g. the one a message is linked to
+#[derive(
+    Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize,
+)]
+pub(crate) enum LinkTarget {
+    Reminder(i64),
+    CronReminder(i64),
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
-            Self::Database(ref err) => {
-                write!(f, "Database error: {}", err)
+impl LinkTarget {
+    fn condition(self) -> Condition {
+        match self {
+            Self::Reminder(id) => {
+                Condition::all().add(message_link::Column::ReminderId.eq(id))
+            }
+            Self::CronReminder(id) => Condition::all()
+                .add(message_link::Column::CronReminderId.eq(id)),
+        }
+    }
+
+    fn sending_condition(self) -> Condition {
+        match self {
+            Self::Reminder(id) => {
+                Condition::all().add(sending::Column::ReminderId.eq(id))
+            }
+            Self::CronReminder(id) => {
+                Condition::all().add(sending::Column::CronReminderId.eq(id))
+            }
+        }
+    }
+
+    fn broadcast_condition(self) -> Condition {
+        match self {
+            Self::Reminder(id) => {
+                Condition::all().add(reminder_target::Column::ReminderId.eq(id))
             }
-            Self::File(ref err) => write!(f, "File error: {}", err),
+            Self::CronReminder(id) => Condition::all()
+                .add(reminder_target::Column::CronReminderId.eq(id)),
         }
     }
+
+    /// Ids of the reminder and the cron reminder columns
+    fn ids(self) -> (Option<i64>, Option<i64>) {
+        match self {
+            Self::Reminder(id) => (Some(id), None),
+            Self::CronReminder(id) => (None, Some(id)),
+        }
+    }
+
+    pub(crate) fn of(link: &message_link::Model) -> Option<Self> {
+        link.reminder_id
+            .map(Self::Reminder)
+            .or(link.cron_reminder_id.map(Self::CronReminder))
+    }
 }
 
-impl From<DbErr> for Error {
-    fn from(err: DbErr) -> Self {
-        Self::Database(err)
+async fn get_db_pool(db_path: &Path) -> Result<DatabaseConnection, Error> {
+    if let Some(db_str) = CLI.database_url.clone() {
+        let mut opts = ConnectOptions::new(db_str);
+        opts.max_connections(CLI.sqlite_max_connections);
+        return Ok(SeaOrmDatabase::connect(opts).await?);
     }
+    // Syncing at every commit isn't needed for durability with WAL
+    let (journal_mode, synchronous) = match CLI.sqlite_journal_mode {
+        JournalMode::Wal => (SqliteJournalMode::Wal, SqliteSynchronous::Normal),
+        JournalMode::Delete => {
+            (SqliteJournalMode::Delete, SqliteSynchronous::Full)
+        }
+    };
+    let opts = SqliteConnectOptions::new()
+        .filename(db_path)
+        .create_if_missing(true)
+        .journal_mode(journal_mode)
+        .synchronous(synchronous)
+        .busy_timeout(Duration::from_millis(CLI.sqlite_busy_timeout));
+    let pool = SqlitePoolOptions::new()
+        .max_connections(CLI.sqlite_max_connections)
+        .connect_with(opts)
+        .await
+        .map_err(|err| DbErr::Conn(RuntimeErr::SqlxError(err)))?;
+    Ok(SqlxSqliteConnector::from_sqlx_sqlite_pool(pool))
+}
+
+/// Rows referring to a reminder or a cron reminder that is gone. Only the
+/// column of the kind referred to is set, the other one is NULL, which
+/// `NOT IN` an empty table would match.
+fn gone_target_condition<C: ColumnTrait>(
+    reminder_id_col: C,
+    cron_reminder_id_col: C,
+) -> Condition {
+    Condition::any()
+        .add(
+            Condition::all().add(reminder_id_col.is_not_null()).add(
+                reminder_id_col.not_in_subquery(
+                    Query::select()
+                        .column(reminder::Column::Id)
+                        .from(reminder::Entity)
+                        .to_owned(),
+                ),
+            ),
+        )
+        .add(
+            Condition::all()
+                .add(cron_reminder_id_col.is_not_null())
+                .add(
+                    cron_reminder_id_col.not_in_subquery(
+                        Query::select()
+                            .column(cron_reminder::Column::Id)
+                            .from(cron_reminder::Entity)
+                            .to_owned(),
+                    ),
+                ),
+        )
+}
+
+/// Reminders of a chat that have no owner with a known timezone
+fn orphaned_condition<C: ColumnTrait>(
+    chat_id_col: C,
+    user_id_col: C,
+    deleted_time_col: C,
+    chat_id: i64,
+) -> Condition {
+    Condition::all()
+        .add(chat_id_col.eq(chat_id))
+        .add(deleted_time_col.is_null())
+        .add(
+            Condition::any().add(user_id_col.is_null()).add(
+                user_id_col.not_in_subquery(
+                    Query::select()
+                        .column(user_timezone::Column::UserId)
+                        .from(user_timezone::Entity)
+                        .to_owned(),
+                ),
+            ),
+        )
 }
 
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Self {
-        Self::File(err)
+/// Reminders of a chat that match the filter
+fn filter_condition<C: ColumnTrait>(
+    columns: [C; 5],
+    chat_id: i64,
+    filter: &Filter,
+) -> Condition {
+    let [chat_id_col, time_col, desc_col, paused_col, deleted_time_col] =
+        columns;
+    let mut condition = Condition::all()
+        .add(chat_id_col.eq(chat_id))
+        .add(deleted_time_col.is_null());
+    if let Some(from) = filter.from {
+        condition = condition.add(time_col.gte(from));
     }
+    if let Some(until) = filter.until {
+        condition = condition.add(time_col.lt(until));
+    }
+    if let Some(paused) = filter.paused {
+        condition = condition.add(paused_col.eq(paused));
+    }
+    if let Some(ref text) = filter.text {
+        let escaped = text
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        condition =
+            condition
+                .add(Expr::expr(Func::lower(Expr::col(desc_col))).like(
+                    LikeExpr::new(format!("%{}%", escaped)).escape('\\'),
+                ));
+    }
+    condition
 }
 
-async fn get_db_pool(db_path: &Path) -> Result<DatabaseConnection, Error> {
-    let db_str = format!("sqlite:{}?mode=rwc", db_path.display());
-    let mut opts = ConnectOptions::new(&db_str);
-    opts.max_connections(CLI.sqlite_max_connections);
-    let pool = SeaOrmDatabase::connect(opts).await?;
-    Ok(pool)
+/// Reminders that are due to be sent unless they failed to be sent
+/// too many times or are waiting to be retried
+fn sendable_condition<C: ColumnTrait>(
+    failures_col: C,
+    retry_time_col: C,
+    now: NaiveDateTime,
+) -> Condition {
+    Condition::all().add(failures_col.lt(MAX_FAILURES)).add(
+        Condition::any()
+            .add(retry_time_col.is_null())
+            .add(retry_time_col.lt(now)),
+    )
 }
 
 struct ScopeCall<F: FnMut()> {
@@ -70,9 +246,114 @@ macro_rules! defer {
     };
 }
 
+/// Users whose timezones are kept in memory at most, the cache starts
+/// over once there are more of them
+const MAX_CACHED_TIMEZONES: usize = 10_000;
+
+/// Timezones of the users looked up, including the users without one
+#[derive(Default)]
+struct TimezoneCache {
+    timezones: HashMap<i64, Option<String>>,
+    /// Bumped on every change, so that a lookup that read the database
+    /// before a change doesn't cache the timezone it replaced
+    generation: u64,
+}
+
+impl TimezoneCache {
+    /// Cache the timezone read at the generation unless it changed since
+    fn insert_read(
+        &mut self,
+        user_id: i64,
+        timezone: Option<String>,
+        generation: u64,
+    ) {
+        if generation != self.generation {
+            return;
+        }
+        if self.timezones.len() >= MAX_CACHED_TIMEZONES {
+            self.timezones.clear();
+        }
+        self.timezones.insert(user_id, timezone);
+    }
+
+    /// Change the cached timezones, the lookups under way aren't cached
+    fn change(
+        &mut self,
+        change: impl FnOnce(&mut HashMap<i64, Option<String>>),
+    ) {
+        self.generation += 1;
+        change(&mut self.timezones);
+    }
+}
+
 pub(crate) struct Database {
     pool: DatabaseConnection,
     notify: Notify,
+    timezones: Mutex<TimezoneCache>,
+}
+
+/// Add the event to the history of its chat, forgetting the events
+/// before `keep_since`
+async fn insert_reminder_event(
+    event: reminder_event::ActiveModel,
+    keep_since: NaiveDateTime,
+    txn: &DatabaseTransaction,
+) -> Result<(), Error> {
+    reminder_event::Entity::delete_many()
+        .filter(
+            reminder_event::Column::ChatId.eq(event.chat_id.clone().unwrap()),
+        )
+        .filter(reminder_event::Column::Time.lt(keep_since))
+        .exec(txn)
+        .await?;
+    event.insert(txn).await?;
+    Ok(())
+}
+
+/// Broadcast the next occurrence of a reminder to the same chats,
+/// or stop broadcasting it if there's none
+async fn move_broadcast_chats(
+    from: LinkTarget,
+    to: Option<LinkTarget>,
+    txn: &DatabaseTransaction,
+) -> Result<(), Error> {
+    match to {
+        Some(to) => {
+            let (reminder_id, cron_reminder_id) = to.ids();
+            reminder_target::Entity::update_many()
+                .col_expr(
+                    reminder_target::Column::ReminderId,
+                    Expr::value(reminder_id),
+                )
+                .col_expr(
+                    reminder_target::Column::CronReminderId,
+                    Expr::value(cron_reminder_id),
+                )
+                .filter(from.broadcast_condition())
+                .exec(txn)
+                .await?;
+        }
+        None => {
+            reminder_target::Entity::delete_many()
+                .filter(from.broadcast_condition())
+                .exec(txn)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Forget that the reminder is being sent, so that a mark left over
+/// from a crash isn't kept once the reminder is gone or changed
+async fn forget_sending<C: ConnectionTrait>(
+    target: LinkTarget,
+    conn: &C,
+) -> Result<(), Error> {
+    sending::Entity::delete_many()
+        .filter(target.sending_condition())
+        .exec(conn)
+        .await?;
+    Ok(())
 }
 
 #[cfg_attr(test, automock, allow(dead_code))]
@@ -81,6 +362,7 @@ impl Database {
         get_db_pool(db_path).await.map(|pool| Self {
             pool,
             notify: Notify::new(),
+            timezones: Mutex::default(),
         })
     }
 
@@ -94,6 +376,28 @@ impl Database {
     ) -> Result<Option<reminder::Model>, Error> {
         Ok(reminder::Entity::find()
             .filter(reminder::Column::Id.eq(id))
+            .filter(reminder::Column::DeletedTime.is_null())
+            .one(&self.pool)
+            .await?)
+    }
+
+    /// Active reminder of the chat due at the time with the same
+    /// description regardless of the case
+    pub(crate) async fn get_duplicate_reminder(
+        &self,
+        chat_id: i64,
+        time: NaiveDateTime,
+        desc: &str,
+    ) -> Result<Option<reminder::Model>, Error> {
+        Ok(reminder::Entity::find()
+            .filter(reminder::Column::ChatId.eq(chat_id))
+            .filter(reminder::Column::Time.eq(time))
+            .filter(
+                Expr::expr(Func::lower(Expr::col(reminder::Column::Desc)))
+                    .eq(desc.to_lowercase()),
+            )
+            .filter(reminder::Column::Paused.eq(false))
+            .filter(reminder::Column::DeletedTime.is_null())
             .one(&self.pool)
             .await?)
     }
@@ -106,34 +410,318 @@ impl Database {
         Ok(rem.save(&self.pool).await?)
     }
 
-    pub(crate) async fn delete_reminder(&self, id: i64) -> Result<(), Error> {
+    /// Insert a copy of the reminder with a new id, `None` if it's gone.
+    /// The copy starts afresh: its failures and pomodoro aren't copied.
+    pub(crate) async fn duplicate_reminder(
+        &self,
+        id: i64,
+    ) -> Result<Option<reminder::ActiveModel>, Error> {
+        let Some(rem) = self.get_reminder(id).await? else {
+            return Ok(None);
+        };
+        defer!(self.notify.notify_one());
+        Ok(Some(
+            reminder::ActiveModel {
+                id: NotSet,
+                version: Set(0),
+                pomodoro_id: Set(None),
+                held_since: Set(None),
+                failures: Set(0),
+                retry_time: Set(None),
+                ..reminder::ActiveModel::from(rem).reset_all()
+            }
+            .save(&self.pool)
+            .await?,
+        ))
+    }
+
+    /// Delete the reminder for good bypassing the trash, e.g. when it's
+    /// replaced or won't fire anymore
+    pub(crate) async fn purge_reminder(&self, id: i64) -> Result<(), Error> {
+        let txn = self.pool.begin().await?;
         reminder::ActiveModel {
             id: Set(id),
             ..Default::default()
         }
-        .delete(&self.pool)
+        .delete(&txn)
+        .await?;
+        forget_sending(LinkTarget::Reminder(id), &txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Reminders whose sending was interrupted before they were
+    /// rescheduled, e.g. by a crash
+    pub(crate) async fn get_interrupted_sending(
+        &self,
+    ) -> Result<Vec<LinkTarget>, Error> {
+        Ok(sending::Entity::find()
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .filter_map(|sending| {
+                sending
+                    .reminder_id
+                    .map(LinkTarget::Reminder)
+                    .or(sending.cron_reminder_id.map(LinkTarget::CronReminder))
+            })
+            .collect())
+    }
+
+    /// Count another failure to send the reminder, it's retried at
+    /// the time if there's one
+    pub(crate) async fn record_send_failure(
+        &self,
+        target: LinkTarget,
+        failures: i32,
+        retry_time: Option<NaiveDateTime>,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        match target {
+            LinkTarget::Reminder(id) => {
+                reminder::Entity::update_many()
+                    .col_expr(reminder::Column::Failures, Expr::value(failures))
+                    .col_expr(
+                        reminder::Column::RetryTime,
+                        Expr::value(retry_time),
+                    )
+                    .filter(reminder::Column::Id.eq(id))
+                    .exec(&self.pool)
+                    .await?;
+            }
+            LinkTarget::CronReminder(id) => {
+                cron_reminder::Entity::update_many()
+                    .col_expr(
+                        cron_reminder::Column::Failures,
+                        Expr::value(failures),
+                    )
+                    .col_expr(
+                        cron_reminder::Column::RetryTime,
+                        Expr::value(retry_time),
+                    )
+                    .filter(cron_reminder::Column::Id.eq(id))
+                    .exec(&self.pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop sending the reminders of a chat the bot can't reach anymore,
+    /// e.g. after the user blocked it, by marking them failed
+    pub(crate) async fn fail_chat_reminders(
+        &self,
+        chat_id: i64,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        reminder::Entity::update_many()
+            .col_expr(reminder::Column::Failures, Expr::value(MAX_FAILURES))
+            .col_expr(
+                reminder::Column::RetryTime,
+                Expr::value(None::<NaiveDateTime>),
+            )
+            .filter(reminder::Column::ChatId.eq(chat_id))
+            .exec(&txn)
+            .await?;
+        cron_reminder::Entity::update_many()
+            .col_expr(
+                cron_reminder::Column::Failures,
+                Expr::value(MAX_FAILURES),
+            )
+            .col_expr(
+                cron_reminder::Column::RetryTime,
+                Expr::value(None::<NaiveDateTime>),
+            )
+            .filter(cron_reminder::Column::ChatId.eq(chat_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Try sending the failed reminders of the chat anew,
+    /// return the number of them
+    pub(crate) async fn resume_failed_reminders(
+        &self,
+        chat_id: i64,
+    ) -> Result<u64, Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        let resumed = reminder::Entity::update_many()
+            .col_expr(reminder::Column::Failures, Expr::value(0))
+            .filter(reminder::Column::ChatId.eq(chat_id))
+            .filter(reminder::Column::Failures.gte(MAX_FAILURES))
+            .exec(&txn)
+            .await?
+            .rows_affected
+            + cron_reminder::Entity::update_many()
+                .col_expr(cron_reminder::Column::Failures, Expr::value(0))
+                .filter(cron_reminder::Column::ChatId.eq(chat_id))
+                .filter(cron_reminder::Column::Failures.gte(MAX_FAILURES))
+                .exec(&txn)
+                .await?
+                .rows_affected;
+        txn.commit().await?;
+        Ok(resumed)
+    }
+
+    /// Remember that the reminder is being sent until it's rescheduled
+    pub(crate) async fn start_sending(
+        &self,
+        target: LinkTarget,
+    ) -> Result<(), Error> {
+        let (reminder_id, cron_reminder_id) = match target {
+            LinkTarget::Reminder(id) => (Some(id), None),
+            LinkTarget::CronReminder(id) => (None, Some(id)),
+        };
+        sending::ActiveModel {
+            id: NotSet,
+            reminder_id: Set(reminder_id),
+            cron_reminder_id: Set(cron_reminder_id),
+            since: Set(Utc::now().naive_utc()),
+        }
+        .insert(&self.pool)
         .await?;
         Ok(())
     }
 
+    /// Forget that the reminder is being sent, e.g. after failing to send it
+    pub(crate) async fn stop_sending(
+        &self,
+        target: LinkTarget,
+    ) -> Result<(), Error> {
+        forget_sending(target, &self.pool).await
+    }
+
+    /// Replace the sent reminder with its next occurrence if there's any,
+    /// so that stopping in between loses neither of them, and forget that
+    /// it was being sent. Return the next one as inserted.
+    pub(crate) async fn advance_reminder(
+        &self,
+        id: i64,
+        next: Option<reminder::ActiveModel>,
+    ) -> Result<Option<reminder::ActiveModel>, Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        reminder::Entity::delete_by_id(id).exec(&txn).await?;
+        sending::Entity::delete_many()
+            .filter(LinkTarget::Reminder(id).sending_condition())
+            .exec(&txn)
+            .await?;
+        let next = match next {
+            Some(next) => Some(next.save(&txn).await?),
+            None => None,
+        };
+        move_broadcast_chats(
+            LinkTarget::Reminder(id),
+            next.as_ref()
+                .map(|next| LinkTarget::Reminder(next.id.clone().unwrap())),
+            &txn,
+        )
+        .await?;
+        txn.commit().await?;
+        Ok(next)
+    }
+
     async fn next_reminder_time(&self) -> Result<Option<NaiveDateTime>, Error> {
-        Ok(reminder::Entity::find()
+        let next = |meeting: bool| {
+            reminder::Entity::find()
+                .filter(reminder::Column::Paused.eq(false))
+                .filter(reminder::Column::DeletedTime.is_null())
+                .filter(reminder::Column::Meeting.eq(meeting))
+                .filter(reminder::Column::Failures.lt(MAX_FAILURES))
+                .filter(reminder::Column::RetryTime.is_null())
+                .order_by_asc(reminder::Column::Time)
+                .one(&self.pool)
+        };
+        let next_time = next(false).await?.map(|r| r.time);
+        // Meetings are announced ahead of their time
+        let next_meeting_time =
+            next(true).await?.map(|r| r.time - meeting::INVITE_LEAD);
+        let next_retry_time = reminder::Entity::find()
+            .filter(reminder::Column::Paused.eq(false))
+            .filter(reminder::Column::DeletedTime.is_null())
+            .filter(reminder::Column::Failures.lt(MAX_FAILURES))
+            .filter(reminder::Column::RetryTime.is_not_null())
+            .order_by_asc(reminder::Column::RetryTime)
+            .one(&self.pool)
+            .await?
+            .and_then(|r| r.retry_time);
+        let next_advance_time = reminder::Entity::find()
             .filter(reminder::Column::Paused.eq(false))
-            .order_by_asc(reminder::Column::Time)
+            .filter(reminder::Column::DeletedTime.is_null())
+            .filter(reminder::Column::AdvanceTime.is_not_null())
+            .order_by_asc(reminder::Column::AdvanceTime)
             .one(&self.pool)
             .await?
-            .map(|r| r.time))
+            .and_then(|r| r.advance_time);
+        Ok([
+            next_time,
+            next_meeting_time,
+            next_retry_time,
+            next_advance_time,
+        ]
+        .into_iter()
+        .flatten()
+        .min())
     }
 
     async fn next_cron_reminder_time(
         &self,
     ) -> Result<Option<NaiveDateTime>, Error> {
-        Ok(cron_reminder::Entity::find()
+        let next_time = cron_reminder::Entity::find()
             .filter(cron_reminder::Column::Paused.eq(false))
+            .filter(cron_reminder::Column::DeletedTime.is_null())
+            .filter(cron_reminder::Column::Failures.lt(MAX_FAILURES))
+            .filter(cron_reminder::Column::RetryTime.is_null())
             .order_by_asc(cron_reminder::Column::Time)
             .one(&self.pool)
             .await?
-            .map(|r| r.time))
+            .map(|r| r.time);
+        let next_retry_time = cron_reminder::Entity::find()
+            .filter(cron_reminder::Column::Paused.eq(false))
+            .filter(cron_reminder::Column::DeletedTime.is_null())
+            .filter(cron_reminder::Column::Failures.lt(MAX_FAILURES))
+            .filter(cron_reminder::Column::RetryTime.is_not_null())
+            .order_by_asc(cron_reminder::Column::RetryTime)
+            .one(&self.pool)
+            .await?
+            .and_then(|r| r.retry_time);
+        Ok(next_time.into_iter().chain(next_retry_time).min())
+    }
+
+    async fn next_nag_time(&self) -> Result<Option<NaiveDateTime>, Error> {
+        Ok(occurrence::Entity::find()
+            .filter(occurrence::Column::DoneTime.is_null())
+            .order_by_asc(occurrence::Column::NagTime)
+            .one(&self.pool)
+            .await?
+            .map(|occ| occ.nag_time))
+    }
+
+    async fn next_digest_time(&self) -> Result<Option<NaiveDateTime>, Error> {
+        Ok(digest::Entity::find()
+            .order_by_asc(digest::Column::NextTime)
+            .one(&self.pool)
+            .await?
+            .map(|digest| digest.next_time))
+    }
+
+    async fn next_vacation_end(&self) -> Result<Option<NaiveDateTime>, Error> {
+        Ok(vacation::Entity::find()
+            .order_by_asc(vacation::Column::Until)
+            .one(&self.pool)
+            .await?
+            .map(|vacation| vacation.until))
+    }
+
+    async fn next_expiry_time(&self) -> Result<Option<NaiveDateTime>, Error> {
+        Ok(expiring_message::Entity::find()
+            .order_by_asc(expiring_message::Column::DeleteTime)
+            .one(&self.pool)
+            .await?
+            .map(|msg| msg.delete_time))
     }
 
     pub(crate) async fn get_next_reminder_time(
@@ -141,19 +729,84 @@ impl Database {
     ) -> Result<Option<NaiveDateTime>, Error> {
         let next_reminder_time = self.next_reminder_time().await?;
         let next_cron_reminder_time = self.next_cron_reminder_time().await?;
-        match (next_reminder_time, next_cron_reminder_time) {
-            (Some(rem), Some(cron_rem)) => Ok(Some(rem.min(cron_rem))),
-            (Some(rem), None) => Ok(Some(rem)),
-            (None, Some(cron_rem)) => Ok(Some(cron_rem)),
-            (None, None) => Ok(None),
-        }
+        let next_nag_time = self.next_nag_time().await?;
+        let next_digest_time = self.next_digest_time().await?;
+        let next_expiry_time = self.next_expiry_time().await?;
+        let next_vacation_end = self.next_vacation_end().await?;
+        Ok([
+            next_reminder_time,
+            next_cron_reminder_time,
+            next_nag_time,
+            next_digest_time,
+            next_expiry_time,
+            next_vacation_end,
+        ]
+        .into_iter()
+        .flatten()
+        .min())
     }
 
     pub(crate) async fn get_active_reminders(
         &self,
+    ) -> Result<Vec<reminder::Model>, Error> {
+        let now = Utc::now().naive_utc();
+        Ok(reminder::Entity::find()
+            .filter(reminder::Column::Paused.eq(false))
+            .filter(reminder::Column::DeletedTime.is_null())
+            .filter(sendable_condition(
+                reminder::Column::Failures,
+                reminder::Column::RetryTime,
+                now,
+            ))
+            .filter(
+                Condition::any().add(reminder::Column::Time.lt(now)).add(
+                    Condition::all()
+                        .add(reminder::Column::Meeting.eq(true))
+                        .add(
+                            reminder::Column::Time
+                                .lt(now + meeting::INVITE_LEAD),
+                        ),
+                ),
+            )
+            .all(&self.pool)
+            .await?)
+    }
+
+    /// Reminders whose advance warnings are due
+    pub(crate) async fn get_due_advance_warnings(
+        &self,
     ) -> Result<Vec<reminder::Model>, Error> {
         Ok(reminder::Entity::find()
             .filter(reminder::Column::Paused.eq(false))
+            .filter(reminder::Column::DeletedTime.is_null())
+            .filter(reminder::Column::AdvanceTime.lt(Utc::now().naive_utc()))
+            .all(&self.pool)
+            .await?)
+    }
+
+    /// Forget the advance warning of a reminder once it's sent
+    pub(crate) async fn clear_advance_time(
+        &self,
+        id: i64,
+    ) -> Result<(), Error> {
+        reminder::Entity::update_many()
+            .col_expr(
+                reminder::Column::AdvanceTime,
+                Expr::value(Option::<NaiveDateTime>::None),
+            )
+            .filter(reminder::Column::Id.eq(id))
+            .exec(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Paused reminders whose time has passed
+    pub(crate) async fn get_overdue_paused_reminders(
+        &self,
+    ) -> Result<Vec<reminder::Model>, Error> {
+        Ok(reminder::Entity::find()
+            .filter(reminder::Column::Paused.eq(true))
+            .filter(reminder::Column::DeletedTime.is_null())
             .filter(reminder::Column::Time.lt(Utc::now().naive_utc()))
             .all(&self.pool)
             .await?)
@@ -165,6 +818,7 @@ impl Database {
     ) -> Result<Vec<reminder::Model>, Error> {
         Ok(reminder::Entity::find()
             .filter(reminder::Column::ChatId.eq(chat_id))
+            .filter(reminder::Column::DeletedTime.is_null())
             .all(&self.pool)
             .await?)
     }
@@ -173,10 +827,57 @@ impl Database {
         &self,
         user_id: i64,
     ) -> Result<Option<String>, Error> {
-        Ok(user_timezone::Entity::find_by_id(user_id)
+        let generation = {
+            let cache = self.timezones.lock().unwrap();
+            if let Some(timezone) = cache.timezones.get(&user_id) {
+                return Ok(timezone.clone());
+            }
+            cache.generation
+        };
+        let timezone = user_timezone::Entity::find_by_id(user_id)
             .one(&self.pool)
             .await?
-            .map(|x| x.timezone))
+            .map(|x| x.timezone);
+        self.timezones.lock().unwrap().insert_read(
+            user_id,
+            timezone.clone(),
+            generation,
+        );
+        Ok(timezone)
+    }
+
+    /// Timezones of the owners and chats of the reminders given as
+    /// owner and chat pairs
+    pub(crate) async fn get_reminder_timezones(
+        &self,
+        owners: Vec<(Option<i64>, i64)>,
+    ) -> Result<ReminderTimezones, Error> {
+        if owners.is_empty() {
+            return Ok(ReminderTimezones::default());
+        }
+        let chat_ids: Vec<_> =
+            owners.iter().map(|&(_, chat_id)| chat_id).collect();
+        // The private chats' ids are their users' ones
+        let user_ids: Vec<_> = owners
+            .iter()
+            .filter_map(|&(user_id, _)| user_id)
+            .chain(chat_ids.iter().copied())
+            .collect();
+        let users = user_timezone::Entity::find()
+            .filter(user_timezone::Column::UserId.is_in(user_ids))
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|tz| (tz.user_id, tz.timezone))
+            .collect();
+        let chats = chat_timezone::Entity::find()
+            .filter(chat_timezone::Column::ChatId.is_in(chat_ids))
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|tz| (tz.chat_id, tz.timezone))
+            .collect();
+        Ok(ReminderTimezones { users, chats })
     }
 
     async fn insert_user_timezone_name(
@@ -199,6 +900,10 @@ impl Database {
         user_id: i64,
         timezone: &str,
     ) -> Result<(), Error> {
+        // Looked up again if the update fails
+        self.timezones.lock().unwrap().change(|timezones| {
+            timezones.remove(&user_id);
+        });
         if let Some(mut tz_act) = user_timezone::Entity::find_by_id(user_id)
             .one(&self.pool)
             .await?
@@ -209,84 +914,1362 @@ impl Database {
         } else {
             self.insert_user_timezone_name(user_id, timezone).await?;
         }
+        self.timezones.lock().unwrap().change(|timezones| {
+            timezones.insert(user_id, Some(timezone.to_owned()));
+        });
         Ok(())
     }
 
-    pub(crate) async fn get_cron_reminder(
+    /// Timezone set for the group with /setchattimezone
+    pub(crate) async fn get_chat_timezone_name(
         &self,
-        id: i64,
-    ) -> Result<Option<cron_reminder::Model>, Error> {
-        Ok(cron_reminder::Entity::find()
-            .filter(cron_reminder::Column::Id.eq(id))
+        chat_id: i64,
+    ) -> Result<Option<String>, Error> {
+        Ok(chat_timezone::Entity::find_by_id(chat_id)
             .one(&self.pool)
-            .await?)
+            .await?
+            .map(|x| x.timezone))
     }
 
-    pub(crate) async fn insert_cron_reminder(
+    pub(crate) async fn insert_or_update_chat_timezone(
         &self,
-        rem: cron_reminder::ActiveModel,
-    ) -> Result<cron_reminder::ActiveModel, Error> {
+        chat_id: i64,
+        timezone: &str,
+    ) -> Result<(), Error> {
         defer!(self.notify.notify_one());
-        Ok(rem.save(&self.pool).await?)
+        if let Some(mut tz_act) = chat_timezone::Entity::find_by_id(chat_id)
+            .one(&self.pool)
+            .await?
+            .map(Into::<chat_timezone::ActiveModel>::into)
+        {
+            tz_act.timezone = Set(timezone.to_string());
+            tz_act.update(&self.pool).await?;
+        } else {
+            chat_timezone::Entity::insert(chat_timezone::ActiveModel {
+                chat_id: Set(chat_id),
+                timezone: Set(timezone.to_string()),
+            })
+            .exec(&self.pool)
+            .await?;
+        }
+        Ok(())
     }
 
-    pub(crate) async fn delete_cron_reminder(
+    pub(crate) async fn delete_chat_timezone(
         &self,
-        id: i64,
+        chat_id: i64,
     ) -> Result<(), Error> {
-        cron_reminder::ActiveModel {
-            id: Set(id),
-            ..Default::default()
-        }
-        .delete(&self.pool)
-        .await?;
+        defer!(self.notify.notify_one());
+        chat_timezone::Entity::delete_by_id(chat_id)
+            .exec(&self.pool)
+            .await?;
         Ok(())
     }
 
-    pub(crate) async fn toggle_reminder_paused(
+    pub(crate) async fn insert_or_update_user_language(
         &self,
-        id: i64,
-    ) -> Result<bool, Error> {
-        defer!(self.notify.notify_one());
-        let rem: Option<reminder::Model> =
-            reminder::Entity::find_by_id(id).one(&self.pool).await?;
-        if let Some(rem) = rem {
-            let paused_value = !rem.paused;
-            let mut rem_act: reminder::ActiveModel = rem.into();
-            rem_act.paused = Set(paused_value);
-            rem_act.update(&self.pool).await?;
-            Ok(paused_value)
+        user_id: i64,
+        language: &str,
+    ) -> Result<(), Error> {
+        if let Some(mut lang_act) = user_language::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?
+            .map(Into::<user_language::ActiveModel>::into)
+        {
+            lang_act.language = Set(language.to_string());
+            lang_act.update(&self.pool).await?;
         } else {
-            Err(Error::Database(DbErr::RecordNotFound(id.to_string())))
+            user_language::Entity::insert(user_language::ActiveModel {
+                user_id: Set(user_id),
+                language: Set(language.to_string()),
+            })
+            .exec(&self.pool)
+            .await?;
         }
+        Ok(())
     }
 
-    pub(crate) async fn toggle_cron_reminder_paused(
+    /// Languages the users chose, except the ones the bot no longer speaks
+    pub(crate) async fn get_user_languages(
         &self,
-        id: i64,
-    ) -> Result<bool, Error> {
-        defer!(self.notify.notify_one());
-        let cron_rem: Option<cron_reminder::Model> =
-            cron_reminder::Entity::find_by_id(id)
-                .one(&self.pool)
-                .await?;
-        if let Some(cron_rem) = cron_rem {
-            let paused_value = !cron_rem.paused;
-            let mut cron_rem_act: cron_reminder::ActiveModel = cron_rem.into();
-            cron_rem_act.paused = Set(paused_value);
-            cron_rem_act.update(&self.pool).await?;
-            Ok(paused_value)
-        } else {
-            Err(Error::Database(DbErr::RecordNotFound(id.to_string())))
-        }
+    ) -> Result<Vec<(i64, Language)>, Error> {
+        Ok(user_language::Entity::find()
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .filter_map(|lang| {
+                Some((lang.user_id, Language::from_code(&lang.language)?))
+            })
+            .collect())
     }
 
-    pub(crate) async fn get_active_cron_reminders(
+    /// Preferences changed with /settings, if the user changed any
+    pub(crate) async fn get_user_settings(
         &self,
-    ) -> Result<Vec<cron_reminder::Model>, Error> {
+        user_id: i64,
+    ) -> Result<Option<user_settings::Model>, Error> {
+        Ok(user_settings::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?)
+    }
+
+    /// Users who want the times shown in the 12-hour clock
+    pub(crate) async fn get_12h_clock_users(&self) -> Result<Vec<i64>, Error> {
+        Ok(user_settings::Entity::find()
+            .filter(user_settings::Column::Clock12h.eq(true))
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|settings| settings.user_id)
+            .collect())
+    }
+
+    /// Users whose weeks start on Sunday
+    pub(crate) async fn get_sunday_first_users(
+        &self,
+    ) -> Result<Vec<i64>, Error> {
+        Ok(user_settings::Entity::find()
+            .filter(user_settings::Column::SundayFirst.eq(true))
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|settings| settings.user_id)
+            .collect())
+    }
+
+    /// Settings of the users who changed the times of the parts of the day
+    pub(crate) async fn get_day_part_users(
+        &self,
+    ) -> Result<Vec<user_settings::Model>, Error> {
+        Ok(user_settings::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(user_settings::Column::Morning.is_not_null())
+                    .add(user_settings::Column::Afternoon.is_not_null())
+                    .add(user_settings::Column::Evening.is_not_null())
+                    .add(user_settings::Column::Night.is_not_null()),
+            )
+            .all(&self.pool)
+            .await?)
+    }
+
+    pub(crate) async fn insert_or_update_user_settings(
+        &self,
+        settings: user_settings::Model,
+    ) -> Result<(), Error> {
+        let user_id = settings.user_id;
+        let settings_act: user_settings::ActiveModel = settings.into();
+        if user_settings::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?
+            .is_some()
+        {
+            settings_act.reset_all().update(&self.pool).await?;
+        } else {
+            user_settings::Entity::insert(settings_act)
+                .exec(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Digest of the upcoming reminders set for the chat with /digest
+    pub(crate) async fn get_digest(
+        &self,
+        chat_id: i64,
+    ) -> Result<Option<digest::Model>, Error> {
+        Ok(digest::Entity::find_by_id(chat_id).one(&self.pool).await?)
+    }
+
+    /// Digests whose time has come
+    pub(crate) async fn get_due_digests(
+        &self,
+    ) -> Result<Vec<digest::Model>, Error> {
+        Ok(digest::Entity::find()
+            .filter(digest::Column::NextTime.lt(Utc::now().naive_utc()))
+            .all(&self.pool)
+            .await?)
+    }
+
+    pub(crate) async fn insert_or_update_digest(
+        &self,
+        digest: digest::Model,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        let chat_id = digest.chat_id;
+        let digest_act: digest::ActiveModel = digest.into();
+        if digest::Entity::find_by_id(chat_id)
+            .one(&self.pool)
+            .await?
+            .is_some()
+        {
+            digest_act.reset_all().update(&self.pool).await?;
+        } else {
+            digest::Entity::insert(digest_act).exec(&self.pool).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn delete_digest(
+        &self,
+        chat_id: i64,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        digest::Entity::delete_by_id(chat_id)
+            .exec(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_vacation(
+        &self,
+        chat_id: i64,
+    ) -> Result<Option<vacation::Model>, Error> {
+        Ok(vacation::Entity::find_by_id(chat_id)
+            .one(&self.pool)
+            .await?)
+    }
+
+    /// Vacations of all the chats, including the ones that are over
+    pub(crate) async fn get_vacations(
+        &self,
+    ) -> Result<Vec<vacation::Model>, Error> {
+        Ok(vacation::Entity::find().all(&self.pool).await?)
+    }
+
+    pub(crate) async fn insert_or_update_vacation(
+        &self,
+        vacation: vacation::Model,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        let chat_id = vacation.chat_id;
+        let vacation_act: vacation::ActiveModel = vacation.into();
+        if vacation::Entity::find_by_id(chat_id)
+            .one(&self.pool)
+            .await?
+            .is_some()
+        {
+            vacation_act.reset_all().update(&self.pool).await?;
+        } else {
+            vacation::Entity::insert(vacation_act)
+                .exec(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn delete_vacation(
+        &self,
+        chat_id: i64,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        vacation::Entity::delete_by_id(chat_id)
+            .exec(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Occurrences of the chat's reminders missed on vacation since
+    /// the time, the earliest first
+    pub(crate) async fn get_missed_events(
+        &self,
+        chat_id: i64,
+        since: NaiveDateTime,
+    ) -> Result<Vec<reminder_event::Model>, Error> {
+        Ok(reminder_event::Entity::find()
+            .filter(reminder_event::Column::ChatId.eq(chat_id))
+            .filter(
+                reminder_event::Column::Kind
+                    .eq(reminder_event::EventKind::Missed),
+            )
+            .filter(reminder_event::Column::Time.gte(since))
+            .order_by_asc(reminder_event::Column::Time)
+            .order_by_asc(reminder_event::Column::Id)
+            .all(&self.pool)
+            .await?)
+    }
+
+    /// Delete the bot's message from the chat at the time
+    pub(crate) async fn insert_expiring_message(
+        &self,
+        chat_id: i64,
+        msg_id: i32,
+        delete_time: NaiveDateTime,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        expiring_message::ActiveModel {
+            id: NotSet,
+            chat_id: Set(chat_id),
+            msg_id: Set(msg_id),
+            delete_time: Set(delete_time),
+        }
+        .insert(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Messages of the bot whose time to be deleted has come
+    pub(crate) async fn get_expired_messages(
+        &self,
+    ) -> Result<Vec<expiring_message::Model>, Error> {
+        Ok(expiring_message::Entity::find()
+            .filter(
+                expiring_message::Column::DeleteTime.lt(Utc::now().naive_utc()),
+            )
+            .all(&self.pool)
+            .await?)
+    }
+
+    pub(crate) async fn delete_expiring_message(
+        &self,
+        id: i64,
+    ) -> Result<(), Error> {
+        expiring_message::Entity::delete_by_id(id)
+            .exec(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Delete the links and the sending marks of the reminders that are
+    /// gone along with the history, occurrences and messages to delete older than
+    /// `keep_since`, and the reminders in the trash since before
+    /// `trashed_before`. Returns the number of the records deleted.
+    pub(crate) async fn prune(
+        &self,
+        keep_since: NaiveDateTime,
+        trashed_before: NaiveDateTime,
+    ) -> Result<u64, Error> {
+        let txn = self.pool.begin().await?;
+        let mut deleted = reminder::Entity::delete_many()
+            .filter(reminder::Column::DeletedTime.lt(trashed_before))
+            .exec(&txn)
+            .await?
+            .rows_affected;
+        deleted += cron_reminder::Entity::delete_many()
+            .filter(cron_reminder::Column::DeletedTime.lt(trashed_before))
+            .exec(&txn)
+            .await?
+            .rows_affected;
+        deleted += message_link::Entity::delete_many()
+            .filter(gone_target_condition(
+                message_link::Column::ReminderId,
+                message_link::Column::CronReminderId,
+            ))
+            .exec(&txn)
+            .await?
+            .rows_affected;
+        deleted += sending::Entity::delete_many()
+            .filter(gone_target_condition(
+                sending::Column::ReminderId,
+                sending::Column::CronReminderId,
+            ))
+            .exec(&txn)
+            .await?
+            .rows_affected;
+        deleted += reminder_event::Entity::delete_many()
+            .filter(reminder_event::Column::Time.lt(keep_since))
+            .exec(&txn)
+            .await?
+            .rows_affected;
+        // Nothing nags about the occurrences not done by then anymore
+        deleted += occurrence::Entity::delete_many()
+            .filter(
+                Condition::any()
+                    .add(occurrence::Column::DoneTime.lt(keep_since))
+                    .add(
+                        Condition::all()
+                            .add(occurrence::Column::DoneTime.is_null())
+                            .add(occurrence::Column::NagTime.lt(keep_since)),
+                    ),
+            )
+            .exec(&txn)
+            .await?
+            .rows_affected;
+        deleted += rsvp::Entity::delete_many()
+            .filter(
+                rsvp::Column::OccurrenceId.not_in_subquery(
+                    Query::select()
+                        .column(occurrence::Column::Id)
+                        .from(occurrence::Entity)
+                        .to_owned(),
+                ),
+            )
+            .exec(&txn)
+            .await?
+            .rows_affected;
+        deleted += expiring_message::Entity::delete_many()
+            .filter(expiring_message::Column::DeleteTime.lt(keep_since))
+            .exec(&txn)
+            .await?
+            .rows_affected;
+        txn.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Give the space of the deleted records back and refresh the
+    /// statistics the queries are planned with, only done for SQLite
+    pub(crate) async fn vacuum(&self) -> Result<(), Error> {
+        if self.pool.get_database_backend() == DbBackend::Sqlite {
+            self.pool.execute_unprepared("VACUUM").await?;
+            self.pool.execute_unprepared("ANALYZE").await?;
+        }
+        Ok(())
+    }
+
+    /// Other chats the reminder's notifications are copied to
+    pub(crate) async fn get_broadcast_chats(
+        &self,
+        target: LinkTarget,
+    ) -> Result<Vec<i64>, Error> {
+        Ok(reminder_target::Entity::find()
+            .filter(target.broadcast_condition())
+            .order_by_asc(reminder_target::Column::Id)
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|reminder_target| reminder_target.chat_id)
+            .collect())
+    }
+
+    /// Copy the reminder's notifications to the chat, returns whether
+    /// they weren't already
+    pub(crate) async fn insert_broadcast_chat(
+        &self,
+        target: LinkTarget,
+        chat_id: i64,
+    ) -> Result<bool, Error> {
+        let existing = reminder_target::Entity::find()
+            .filter(target.broadcast_condition())
+            .filter(reminder_target::Column::ChatId.eq(chat_id))
+            .one(&self.pool)
+            .await?;
+        if existing.is_some() {
+            return Ok(false);
+        }
+        let (reminder_id, cron_reminder_id) = target.ids();
+        reminder_target::ActiveModel {
+            id: NotSet,
+            chat_id: Set(chat_id),
+            reminder_id: Set(reminder_id),
+            cron_reminder_id: Set(cron_reminder_id),
+        }
+        .insert(&self.pool)
+        .await?;
+        Ok(true)
+    }
+
+    /// Stop copying the reminder's notifications to the chat, returns
+    /// whether they were
+    pub(crate) async fn delete_broadcast_chat(
+        &self,
+        target: LinkTarget,
+        chat_id: i64,
+    ) -> Result<bool, Error> {
+        let res = reminder_target::Entity::delete_many()
+            .filter(target.broadcast_condition())
+            .filter(reminder_target::Column::ChatId.eq(chat_id))
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+
+    pub(crate) async fn get_template(
+        &self,
+        user_id: i64,
+        name: &str,
+    ) -> Result<Option<template::Model>, Error> {
+        Ok(template::Entity::find()
+            .filter(template::Column::UserId.eq(user_id))
+            .filter(template::Column::Name.eq(name))
+            .one(&self.pool)
+            .await?)
+    }
+
+    pub(crate) async fn get_templates(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<template::Model>, Error> {
+        Ok(template::Entity::find()
+            .filter(template::Column::UserId.eq(user_id))
+            .order_by_asc(template::Column::Name)
+            .all(&self.pool)
+            .await?)
+    }
+
+    /// Save the user's template, replacing the one with the same name
+    pub(crate) async fn save_template(
+        &self,
+        user_id: i64,
+        name: String,
+        text: String,
+    ) -> Result<(), Error> {
+        let existing = template::Entity::find()
+            .filter(template::Column::UserId.eq(user_id))
+            .filter(template::Column::Name.eq(&name))
+            .one(&self.pool)
+            .await?;
+        let mut template_act = match existing {
+            Some(template) => template.into(),
+            None => template::ActiveModel {
+                id: NotSet,
+                user_id: Set(user_id),
+                name: Set(name),
+                ..Default::default()
+            },
+        };
+        template_act.text = Set(text);
+        template_act.save(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Delete the user's template, returns whether there was one
+    pub(crate) async fn delete_template(
+        &self,
+        user_id: i64,
+        name: &str,
+    ) -> Result<bool, Error> {
+        let res = template::Entity::delete_many()
+            .filter(template::Column::UserId.eq(user_id))
+            .filter(template::Column::Name.eq(name))
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+
+    /// Link the group to the user's private chat, renaming it if it's
+    /// already linked
+    pub(crate) async fn save_account_link(
+        &self,
+        user_id: i64,
+        chat_id: i64,
+        title: String,
+    ) -> Result<(), Error> {
+        let existing = account_link::Entity::find()
+            .filter(account_link::Column::UserId.eq(user_id))
+            .filter(account_link::Column::ChatId.eq(chat_id))
+            .one(&self.pool)
+            .await?;
+        let mut link_act = match existing {
+            Some(link) => link.into(),
+            None => account_link::ActiveModel {
+                id: NotSet,
+                user_id: Set(user_id),
+                chat_id: Set(chat_id),
+                ..Default::default()
+            },
+        };
+        link_act.title = Set(title);
+        link_act.save(&self.pool).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_account_links(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<account_link::Model>, Error> {
+        Ok(account_link::Entity::find()
+            .filter(account_link::Column::UserId.eq(user_id))
+            .order_by_asc(account_link::Column::Title)
+            .all(&self.pool)
+            .await?)
+    }
+
+    /// Unlink the group from the user's private chat, returns whether
+    /// it was linked
+    pub(crate) async fn delete_account_link(
+        &self,
+        user_id: i64,
+        chat_id: i64,
+    ) -> Result<bool, Error> {
+        let res = account_link::Entity::delete_many()
+            .filter(account_link::Column::UserId.eq(user_id))
+            .filter(account_link::Column::ChatId.eq(chat_id))
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+
+    #[cfg(feature = "ai")]
+    pub(crate) async fn is_ai_opted_in(
+        &self,
+        user_id: i64,
+    ) -> Result<bool, Error> {
+        Ok(ai_opt_in::Entity::find_by_id(user_id)
+            .one(&self.pool)
+            .await?
+            .is_some())
+    }
+
+    #[cfg(feature = "ai")]
+    pub(crate) async fn set_ai_opt_in(
+        &self,
+        user_id: i64,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        ai_opt_in::Entity::delete_by_id(user_id)
+            .exec(&self.pool)
+            .await?;
+        if enabled {
+            ai_opt_in::Entity::insert(ai_opt_in::ActiveModel {
+                user_id: Set(user_id),
+            })
+            .exec(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn get_cron_reminder(
+        &self,
+        id: i64,
+    ) -> Result<Option<cron_reminder::Model>, Error> {
+        Ok(cron_reminder::Entity::find()
+            .filter(cron_reminder::Column::Id.eq(id))
+            .filter(cron_reminder::Column::DeletedTime.is_null())
+            .one(&self.pool)
+            .await?)
+    }
+
+    /// Insert a copy of the cron reminder with a new id, `None` if it's
+    /// gone
+    pub(crate) async fn duplicate_cron_reminder(
+        &self,
+        id: i64,
+    ) -> Result<Option<cron_reminder::ActiveModel>, Error> {
+        let Some(cron_rem) = self.get_cron_reminder(id).await? else {
+            return Ok(None);
+        };
+        defer!(self.notify.notify_one());
+        Ok(Some(
+            cron_reminder::ActiveModel {
+                id: NotSet,
+                version: Set(0),
+                failures: Set(0),
+                retry_time: Set(None),
+                ..cron_reminder::ActiveModel::from(cron_rem).reset_all()
+            }
+            .save(&self.pool)
+            .await?,
+        ))
+    }
+
+    /// Active cron reminder of the chat with the same expression and
+    /// description regardless of the case
+    pub(crate) async fn get_duplicate_cron_reminder(
+        &self,
+        chat_id: i64,
+        cron_expr: &str,
+        desc: &str,
+    ) -> Result<Option<cron_reminder::Model>, Error> {
+        Ok(cron_reminder::Entity::find()
+            .filter(cron_reminder::Column::ChatId.eq(chat_id))
+            .filter(cron_reminder::Column::CronExpr.eq(cron_expr))
+            .filter(
+                Expr::expr(Func::lower(Expr::col(cron_reminder::Column::Desc)))
+                    .eq(desc.to_lowercase()),
+            )
+            .filter(cron_reminder::Column::Paused.eq(false))
+            .filter(cron_reminder::Column::DeletedTime.is_null())
+            .one(&self.pool)
+            .await?)
+    }
+
+    pub(crate) async fn insert_cron_reminder(
+        &self,
+        rem: cron_reminder::ActiveModel,
+    ) -> Result<cron_reminder::ActiveModel, Error> {
+        defer!(self.notify.notify_one());
+        Ok(rem.save(&self.pool).await?)
+    }
+
+    /// Replace the sent cron reminder with the next one if there's any,
+    /// like [`Self::advance_reminder`]
+    pub(crate) async fn advance_cron_reminder(
+        &self,
+        id: i64,
+        next: Option<cron_reminder::ActiveModel>,
+    ) -> Result<Option<cron_reminder::ActiveModel>, Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        cron_reminder::Entity::delete_by_id(id).exec(&txn).await?;
+        sending::Entity::delete_many()
+            .filter(LinkTarget::CronReminder(id).sending_condition())
+            .exec(&txn)
+            .await?;
+        let next = match next {
+            Some(next) => Some(next.save(&txn).await?),
+            None => None,
+        };
+        move_broadcast_chats(
+            LinkTarget::CronReminder(id),
+            next.as_ref()
+                .map(|next| LinkTarget::CronReminder(next.id.clone().unwrap())),
+            &txn,
+        )
+        .await?;
+        txn.commit().await?;
+        Ok(next)
+    }
+
+    /// Insert reminders all at once, nothing is inserted on failure
+    pub(crate) async fn insert_reminders(
+        &self,
+        reminders: Vec<reminder::ActiveModel>,
+        cron_reminders: Vec<cron_reminder::ActiveModel>,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        if !reminders.is_empty() {
+            reminder::Entity::insert_many(reminders).exec(&txn).await?;
+        }
+        if !cron_reminders.is_empty() {
+            cron_reminder::Entity::insert_many(cron_reminders)
+                .exec(&txn)
+                .await?;
+        }
+        Ok(txn.commit().await?)
+    }
+
+    /// Put back the reminders as they were, with the same ids, in place
+    /// of the one that replaced them if there's any
+    pub(crate) async fn restore_reminders(
+        &self,
+        replacement: Option<LinkTarget>,
+        reminders: Vec<reminder::Model>,
+        cron_reminders: Vec<cron_reminder::Model>,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        match replacement {
+            Some(LinkTarget::Reminder(id)) => {
+                reminder::Entity::delete_by_id(id).exec(&txn).await?;
+            }
+            Some(LinkTarget::CronReminder(id)) => {
+                cron_reminder::Entity::delete_by_id(id).exec(&txn).await?;
+            }
+            None => {}
+        }
+        // The deleted ones are still in the trash
+        for rem in reminders {
+            reminder::Entity::delete_by_id(rem.id).exec(&txn).await?;
+            reminder::Entity::insert(
+                reminder::ActiveModel::from(rem).reset_all(),
+            )
+            .exec(&txn)
+            .await?;
+        }
+        for cron_rem in cron_reminders {
+            cron_reminder::Entity::delete_by_id(cron_rem.id)
+                .exec(&txn)
+                .await?;
+            cron_reminder::Entity::insert(
+                cron_reminder::ActiveModel::from(cron_rem).reset_all(),
+            )
+            .exec(&txn)
+            .await?;
+        }
+        Ok(txn.commit().await?)
+    }
+
+    /// Delete everything kept for the chats, and the users with the same
+    /// ids, return the number of deleted reminders
+    pub(crate) async fn delete_chats(
+        &self,
+        chat_ids: RangeInclusive<i64>,
+    ) -> Result<u64, Error> {
+        defer!(self.notify.notify_one());
+        let (first, last) = chat_ids.into_inner();
+        let txn = self.pool.begin().await?;
+        let deleted = reminder::Entity::delete_many()
+            .filter(reminder::Column::ChatId.between(first, last))
+            .exec(&txn)
+            .await?
+            .rows_affected
+            + cron_reminder::Entity::delete_many()
+                .filter(cron_reminder::Column::ChatId.between(first, last))
+                .exec(&txn)
+                .await?
+                .rows_affected;
+        occurrence::Entity::delete_many()
+            .filter(occurrence::Column::ChatId.between(first, last))
+            .exec(&txn)
+            .await?;
+        message_link::Entity::delete_many()
+            .filter(message_link::Column::ChatId.between(first, last))
+            .exec(&txn)
+            .await?;
+        user_timezone::Entity::delete_many()
+            .filter(user_timezone::Column::UserId.between(first, last))
+            .exec(&txn)
+            .await?;
+        vacation::Entity::delete_many()
+            .filter(vacation::Column::ChatId.between(first, last))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        self.timezones.lock().unwrap().change(|timezones| {
+            timezones.retain(|user_id, _| !(first..=last).contains(user_id))
+        });
+        Ok(deleted)
+    }
+
+    /// Move the reminder to the trash, it can be restored until it's
+    /// purged
+    pub(crate) async fn trash_reminder(&self, id: i64) -> Result<(), Error> {
+        let txn = self.pool.begin().await?;
+        reminder::Entity::update_many()
+            .col_expr(
+                reminder::Column::DeletedTime,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .filter(reminder::Column::Id.eq(id))
+            .exec(&txn)
+            .await?;
+        forget_sending(LinkTarget::Reminder(id), &txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Move the cron reminder to the trash, it can be restored until it's
+    /// purged
+    pub(crate) async fn trash_cron_reminder(
+        &self,
+        id: i64,
+    ) -> Result<(), Error> {
+        let txn = self.pool.begin().await?;
+        cron_reminder::Entity::update_many()
+            .col_expr(
+                cron_reminder::Column::DeletedTime,
+                Expr::value(Utc::now().naive_utc()),
+            )
+            .filter(cron_reminder::Column::Id.eq(id))
+            .exec(&txn)
+            .await?;
+        forget_sending(LinkTarget::CronReminder(id), &txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Reminders of the chat in the trash
+    pub(crate) async fn get_trashed_reminders(
+        &self,
+        chat_id: i64,
+    ) -> Result<(Vec<reminder::Model>, Vec<cron_reminder::Model>), Error> {
+        let reminders = reminder::Entity::find()
+            .filter(reminder::Column::ChatId.eq(chat_id))
+            .filter(reminder::Column::DeletedTime.is_not_null())
+            .all(&self.pool)
+            .await?;
+        let cron_reminders = cron_reminder::Entity::find()
+            .filter(cron_reminder::Column::ChatId.eq(chat_id))
+            .filter(cron_reminder::Column::DeletedTime.is_not_null())
+            .all(&self.pool)
+            .await?;
+        Ok((reminders, cron_reminders))
+    }
+
+    /// Take a reminder of the chat out of the trash, or all of them.
+    /// Returns the number of restored reminders.
+    pub(crate) async fn restore_trashed_reminders(
+        &self,
+        chat_id: i64,
+        target: Option<LinkTarget>,
+    ) -> Result<u64, Error> {
+        defer!(self.notify.notify_one());
+        let mut restored = 0;
+        if !matches!(target, Some(LinkTarget::CronReminder(_))) {
+            let mut query = reminder::Entity::update_many()
+                .col_expr(
+                    reminder::Column::DeletedTime,
+                    Expr::value(Option::<NaiveDateTime>::None),
+                )
+                .filter(reminder::Column::ChatId.eq(chat_id))
+                .filter(reminder::Column::DeletedTime.is_not_null());
+            if let Some(LinkTarget::Reminder(id)) = target {
+                query = query.filter(reminder::Column::Id.eq(id));
+            }
+            restored += query.exec(&self.pool).await?.rows_affected;
+        }
+        if !matches!(target, Some(LinkTarget::Reminder(_))) {
+            let mut query = cron_reminder::Entity::update_many()
+                .col_expr(
+                    cron_reminder::Column::DeletedTime,
+                    Expr::value(Option::<NaiveDateTime>::None),
+                )
+                .filter(cron_reminder::Column::ChatId.eq(chat_id))
+                .filter(cron_reminder::Column::DeletedTime.is_not_null());
+            if let Some(LinkTarget::CronReminder(id)) = target {
+                query = query.filter(cron_reminder::Column::Id.eq(id));
+            }
+            restored += query.exec(&self.pool).await?.rows_affected;
+        }
+        Ok(restored)
+    }
+
+    pub(crate) async fn get_occurrence(
+        &self,
+        id: i64,
+    ) -> Result<Option<occurrence::Model>, Error> {
+        Ok(occurrence::Entity::find_by_id(id).one(&self.pool).await?)
+    }
+
+    /// Occurrences to resend, except for the muted ones
+    pub(crate) async fn get_due_occurrences(
+        &self,
+    ) -> Result<Vec<occurrence::Model>, Error> {
+        let now = Utc::now().naive_utc();
+        Ok(occurrence::Entity::find()
+            .filter(occurrence::Column::DoneTime.is_null())
+            .filter(occurrence::Column::NagTime.lt(now))
+            .filter(
+                Condition::any()
+                    .add(occurrence::Column::MutedUntil.is_null())
+                    .add(occurrence::Column::MutedUntil.lt(now)),
+            )
+            .all(&self.pool)
+            .await?)
+    }
+
+    pub(crate) async fn insert_occurrence(
+        &self,
+        occ: occurrence::ActiveModel,
+    ) -> Result<occurrence::ActiveModel, Error> {
+        defer!(self.notify.notify_one());
+        Ok(occ.save(&self.pool).await?)
+    }
+
+    pub(crate) async fn update_occurrence(
+        &self,
+        occ: occurrence::Model,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        let mut occ_act: occurrence::ActiveModel = occ.into();
+        occ_act.msg_id = Set(occ_act.msg_id.unwrap());
+        occ_act.nag_time = Set(occ_act.nag_time.unwrap());
+        occ_act.nag_count = Set(occ_act.nag_count.unwrap());
+        occ_act.muted_until = Set(occ_act.muted_until.unwrap());
+        occ_act.update(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Delete the occurrence along with the answers to it if it's a meeting
+    pub(crate) async fn delete_occurrence(&self, id: i64) -> Result<(), Error> {
+        let txn = self.pool.begin().await?;
+        rsvp::Entity::delete_many()
+            .filter(rsvp::Column::OccurrenceId.eq(id))
+            .exec(&txn)
+            .await?;
+        occurrence::ActiveModel {
+            id: Set(id),
+            ..Default::default()
+        }
+        .delete(&txn)
+        .await?;
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Save a user's answer to a meeting, replacing the previous one
+    pub(crate) async fn set_rsvp(
+        &self,
+        occ_id: i64,
+        user_id: i64,
+        name: String,
+        answer: meeting::Answer,
+    ) -> Result<(), Error> {
+        let existing = rsvp::Entity::find()
+            .filter(rsvp::Column::OccurrenceId.eq(occ_id))
+            .filter(rsvp::Column::UserId.eq(user_id))
+            .one(&self.pool)
+            .await?;
+        let mut rsvp_act = match existing {
+            Some(rsvp) => rsvp.into(),
+            None => rsvp::ActiveModel {
+                id: NotSet,
+                occurrence_id: Set(occ_id),
+                user_id: Set(user_id),
+                ..Default::default()
+            },
+        };
+        rsvp_act.name = Set(name);
+        rsvp_act.answer = Set(answer.code().to_owned());
+        rsvp_act.save(&self.pool).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn get_rsvps(
+        &self,
+        occ_id: i64,
+    ) -> Result<Vec<rsvp::Model>, Error> {
+        Ok(rsvp::Entity::find()
+            .filter(rsvp::Column::OccurrenceId.eq(occ_id))
+            .order_by_asc(rsvp::Column::Id)
+            .all(&self.pool)
+            .await?)
+    }
+
+    /// Stop nagging about the occurrence and keep it in the chat's history,
+    /// forgetting the ones done before `keep_since`
+    pub(crate) async fn mark_occurrence_done(
+        &self,
+        occ: occurrence::Model,
+        keep_since: NaiveDateTime,
+    ) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        occurrence::Entity::delete_many()
+            .filter(occurrence::Column::ChatId.eq(occ.chat_id))
+            .filter(occurrence::Column::DoneTime.lt(keep_since))
+            .exec(&txn)
+            .await?;
+        let done_time = Utc::now().naive_utc();
+        let event = reminder_event::ActiveModel {
+            id: NotSet,
+            chat_id: Set(occ.chat_id),
+            time: Set(done_time),
+            desc: Set(occ.desc.clone().unwrap_or_default()),
+            kind: Set(reminder_event::EventKind::Done),
+            occurrence_id: Set(Some(occ.id)),
+            note: Set(None),
+        };
+        let mut occ_act: occurrence::ActiveModel = occ.into();
+        occ_act.done_time = Set(Some(done_time));
+        occ_act.update(&txn).await?;
+        insert_reminder_event(event, keep_since, &txn).await?;
+        Ok(txn.commit().await?)
+    }
+
+    /// Attach a note to a done occurrence of the chat.
+    /// Returns whether there was such an occurrence.
+    pub(crate) async fn set_occurrence_note(
+        &self,
+        id: i64,
+        chat_id: i64,
+        note: String,
+    ) -> Result<bool, Error> {
+        let txn = self.pool.begin().await?;
+        let res = occurrence::Entity::update_many()
+            .col_expr(occurrence::Column::Note, Expr::value(note.clone()))
+            .filter(occurrence::Column::Id.eq(id))
+            .filter(occurrence::Column::ChatId.eq(chat_id))
+            .filter(occurrence::Column::DoneTime.is_not_null())
+            .exec(&txn)
+            .await?;
+        reminder_event::Entity::update_many()
+            .col_expr(reminder_event::Column::Note, Expr::value(note))
+            .filter(reminder_event::Column::OccurrenceId.eq(id))
+            .filter(reminder_event::Column::ChatId.eq(chat_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(res.rows_affected == 1)
+    }
+
+    /// Keep what happened to a reminder in the chat's history,
+    /// forgetting the events before `keep_since`
+    pub(crate) async fn add_reminder_event(
+        &self,
+        event: reminder_event::ActiveModel,
+        keep_since: NaiveDateTime,
+    ) -> Result<(), Error> {
+        let txn = self.pool.begin().await?;
+        insert_reminder_event(event, keep_since, &txn).await?;
+        Ok(txn.commit().await?)
+    }
+
+    /// Events of the reminders of the chat, the latest first
+    pub(crate) async fn get_reminder_events(
+        &self,
+        chat_id: i64,
+        limit: u64,
+    ) -> Result<Vec<reminder_event::Model>, Error> {
+        Ok(reminder_event::Entity::find()
+            .filter(reminder_event::Column::ChatId.eq(chat_id))
+            .order_by_desc(reminder_event::Column::Time)
+            .order_by_desc(reminder_event::Column::Id)
+            .limit(limit)
+            .all(&self.pool)
+            .await?)
+    }
+
+    pub(crate) async fn get_bill(
+        &self,
+        id: i64,
+    ) -> Result<Option<bill::Model>, Error> {
+        Ok(bill::Entity::find_by_id(id).one(&self.pool).await?)
+    }
+
+    pub(crate) async fn insert_bill(
+        &self,
+        bill: bill::ActiveModel,
+    ) -> Result<bill::ActiveModel, Error> {
+        Ok(bill.save(&self.pool).await?)
+    }
+
+    pub(crate) async fn delete_bill(&self, id: i64) -> Result<(), Error> {
+        bill::ActiveModel {
+            id: Set(id),
+            ..Default::default()
+        }
+        .delete(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn set_bill_paid(&self, id: i64) -> Result<(), Error> {
+        bill::ActiveModel {
+            id: Set(id),
+            paid: Set(true),
+            ..Default::default()
+        }
+        .update(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Bills of the chat issued within `[from, until)`
+    pub(crate) async fn get_chat_bills(
+        &self,
+        chat_id: i64,
+        from: NaiveDateTime,
+        until: NaiveDateTime,
+    ) -> Result<Vec<bill::Model>, Error> {
+        Ok(bill::Entity::find()
+            .filter(bill::Column::ChatId.eq(chat_id))
+            .filter(bill::Column::Time.gte(from))
+            .filter(bill::Column::Time.lt(until))
+            .order_by_asc(bill::Column::Time)
+            .all(&self.pool)
+            .await?)
+    }
+
+    pub(crate) async fn get_pomodoro(
+        &self,
+        id: i64,
+    ) -> Result<Option<pomodoro::Model>, Error> {
+        Ok(pomodoro::Entity::find_by_id(id).one(&self.pool).await?)
+    }
+
+    pub(crate) async fn insert_pomodoro(
+        &self,
+        session: pomodoro::ActiveModel,
+    ) -> Result<pomodoro::ActiveModel, Error> {
+        Ok(session.save(&self.pool).await?)
+    }
+
+    /// Record the number of finished phases of the session
+    pub(crate) async fn set_pomodoro_phase(
+        &self,
+        id: i64,
+        phase: i32,
+    ) -> Result<(), Error> {
+        pomodoro::ActiveModel {
+            id: Set(id),
+            phase: Set(phase),
+            ..Default::default()
+        }
+        .update(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Delete the session along with its pending reminders
+    pub(crate) async fn delete_pomodoro(&self, id: i64) -> Result<(), Error> {
+        defer!(self.notify.notify_one());
+        reminder::Entity::delete_many()
+            .filter(reminder::Column::PomodoroId.eq(id))
+            .exec(&self.pool)
+            .await?;
+        pomodoro::ActiveModel {
+            id: Set(id),
+            ..Default::default()
+        }
+        .delete(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub(crate) async fn insert_share_link(
+        &self,
+        link: share_link::ActiveModel,
+    ) -> Result<share_link::Model, Error> {
+        Ok(link.insert(&self.pool).await?)
+    }
+
+    /// Link with the token unless it has expired
+    pub(crate) async fn get_share_link(
+        &self,
+        token: &str,
+    ) -> Result<Option<share_link::Model>, Error> {
+        Ok(share_link::Entity::find()
+            .filter(share_link::Column::Token.eq(token))
+            .filter(share_link::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+            .one(&self.pool)
+            .await?)
+    }
+
+    /// Revoke all links to the chat's reminders, return their number
+    pub(crate) async fn delete_chat_share_links(
+        &self,
+        chat_id: i64,
+    ) -> Result<u64, Error> {
+        Ok(share_link::Entity::delete_many()
+            .filter(share_link::Column::ChatId.eq(chat_id))
+            .exec(&self.pool)
+            .await?
+            .rows_affected)
+    }
+
+    /// Calendar feed with the token
+    pub(crate) async fn get_calendar_feed(
+        &self,
+        token: &str,
+    ) -> Result<Option<calendar_feed::Model>, Error> {
+        Ok(calendar_feed::Entity::find()
+            .filter(calendar_feed::Column::Token.eq(token))
+            .one(&self.pool)
+            .await?)
+    }
+
+    pub(crate) async fn get_user_calendar_feed(
+        &self,
+        user_id: i64,
+    ) -> Result<Option<calendar_feed::Model>, Error> {
+        Ok(calendar_feed::Entity::find()
+            .filter(calendar_feed::Column::UserId.eq(user_id))
+            .one(&self.pool)
+            .await?)
+    }
+
+    /// Give the user's calendar feed a new token, revoking the old one
+    pub(crate) async fn replace_calendar_feed(
+        &self,
+        feed: calendar_feed::ActiveModel,
+    ) -> Result<calendar_feed::Model, Error> {
+        let txn = self.pool.begin().await?;
+        calendar_feed::Entity::delete_many()
+            .filter(
+                calendar_feed::Column::UserId.eq(feed.user_id.clone().unwrap()),
+            )
+            .exec(&txn)
+            .await?;
+        let feed = feed.insert(&txn).await?;
+        txn.commit().await?;
+        Ok(feed)
+    }
+
+    /// Turn off the user's calendar feed, return whether there was one
+    pub(crate) async fn delete_user_calendar_feed(
+        &self,
+        user_id: i64,
+    ) -> Result<bool, Error> {
+        Ok(calendar_feed::Entity::delete_many()
+            .filter(calendar_feed::Column::UserId.eq(user_id))
+            .exec(&self.pool)
+            .await?
+            .rows_affected
+            > 0)
+    }
+
+    /// API token with the hash
+    pub(crate) async fn get_api_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<api_token::Model>, Error> {
+        Ok(api_token::Entity::find()
+            .filter(api_token::Column::TokenHash.eq(token_hash))
+            .one(&self.pool)
+            .await?)
+    }
+
+    /// Give the chat a new API token, revoking the old one
+    pub(crate) async fn replace_api_token(
+        &self,
+        token: api_token::ActiveModel,
+    ) -> Result<api_token::Model, Error> {
+        let txn = self.pool.begin().await?;
+        api_token::Entity::delete_many()
+            .filter(
+                api_token::Column::ChatId.eq(token.chat_id.clone().unwrap()),
+            )
+            .exec(&txn)
+            .await?;
+        let token = token.insert(&txn).await?;
+        txn.commit().await?;
+        Ok(token)
+    }
+
+    /// Revoke the chat's API token, return whether there was one
+    pub(crate) async fn delete_chat_api_token(
+        &self,
+        chat_id: i64,
+    ) -> Result<bool, Error> {
+        Ok(api_token::Entity::delete_many()
+            .filter(api_token::Column::ChatId.eq(chat_id))
+            .exec(&self.pool)
+            .await?
+            .rows_affected
+            > 0)
+    }
+
+    pub(crate) async fn toggle_reminder_paused(
+        &self,
+        id: i64,
+    ) -> Result<bool, Error> {
+        defer!(self.notify.notify_one());
+        let rem: Option<reminder::Model> =
+            reminder::Entity::find_by_id(id).one(&self.pool).await?;
+        if let Some(rem) = rem {
+            let paused_value = !rem.paused;
+            let mut rem_act: reminder::ActiveModel = rem.into();
+            rem_act.paused = Set(paused_value);
+            rem_act.version = Set(rem_act.version.unwrap() + 1);
+            // Resuming a failed reminder tries sending it anew
+            rem_act.failures = Set(0);
+            rem_act.retry_time = Set(None);
+            rem_act.update(&self.pool).await?;
+            Ok(paused_value)
+        } else {
+            Err(Error::Database(DbErr::RecordNotFound(id.to_string())))
+        }
+    }
+
+    pub(crate) async fn toggle_cron_reminder_paused(
+        &self,
+        id: i64,
+    ) -> Result<bool, Error> {
+        defer!(self.notify.notify_one());
+        let cron_rem: Option<cron_reminder::Model> =
+            cron_reminder::Entity::find_by_id(id)
+                .one(&self.pool)
+                .await?;
+        if let Some(cron_rem) = cron_rem {
+            let paused_value = !cron_rem.paused;
+            let mut cron_rem_act: cron_reminder::ActiveModel = cron_rem.into();
+            cron_rem_act.paused = Set(paused_value);
+            cron_rem_act.version = Set(cron_rem_act.version.unwrap() + 1);
+            cron_rem_act.failures = Set(0);
+            cron_rem_act.retry_time = Set(None);
+            cron_rem_act.update(&self.pool).await?;
+            Ok(paused_value)
+        } else {
+            Err(Error::Database(DbErr::RecordNotFound(id.to_string())))
+        }
+    }
+
+    pub(crate) async fn get_active_cron_reminders(
+        &self,
+    ) -> Result<Vec<cron_reminder::Model>, Error> {
+        let now = Utc::now().naive_utc();
         Ok(cron_reminder::Entity::find()
             .filter(cron_reminder::Column::Paused.eq(false))
-            .filter(cron_reminder::Column::Time.lt(Utc::now().naive_utc()))
+            .filter(cron_reminder::Column::DeletedTime.is_null())
+            .filter(sendable_condition(
+                cron_reminder::Column::Failures,
+                cron_reminder::Column::RetryTime,
+                now,
+            ))
+            .filter(cron_reminder::Column::Time.lt(now))
             .all(&self.pool)
             .await?)
     }
@@ -297,6 +2280,7 @@ impl Database {
     ) -> Result<Vec<cron_reminder::Model>, Error> {
         Ok(cron_reminder::Entity::find()
             .filter(cron_reminder::Column::ChatId.eq(chat_id))
+            .filter(cron_reminder::Column::DeletedTime.is_null())
             .all(&self.pool)
             .await?)
     }
@@ -327,79 +2311,888 @@ impl Database {
         Ok(all_reminders)
     }
 
-    pub(crate) async fn get_reminder_by_msg_id(
+    /// Page of the chat's reminders sorted like
+    /// [`Self::get_sorted_reminders`], without loading the rest of them
+    pub(crate) async fn get_sorted_reminders_page(
         &self,
-        msg_id: i32,
-    ) -> Result<Option<reminder::Model>, Error> {
+        chat_id: i64,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<Box<dyn generic_reminder::GenericReminder>>, Error> {
+        let kind = Alias::new("kind");
+        let query = Query::select()
+            .expr_as(Expr::val("rem"), kind.clone())
+            .columns([
+                reminder::Column::Id,
+                reminder::Column::Paused,
+                reminder::Column::Time,
+            ])
+            .from(reminder::Entity)
+            .and_where(reminder::Column::ChatId.eq(chat_id))
+            .and_where(reminder::Column::DeletedTime.is_null())
+            .union(
+                UnionType::All,
+                Query::select()
+                    .expr_as(Expr::val("cron_rem"), kind)
+                    .columns([
+                        cron_reminder::Column::Id,
+                        cron_reminder::Column::Paused,
+                        cron_reminder::Column::Time,
+                    ])
+                    .from(cron_reminder::Entity)
+                    .and_where(cron_reminder::Column::ChatId.eq(chat_id))
+                    .and_where(cron_reminder::Column::DeletedTime.is_null())
+                    .to_owned(),
+            )
+            .order_by(Alias::new("paused"), Order::Asc)
+            .order_by(Alias::new("time"), Order::Asc)
+            .limit(limit)
+            .offset(offset)
+            .to_owned();
+        let page = self
+            .pool
+            .query_all(self.pool.get_database_backend().build(&query))
+            .await?
+            .iter()
+            .map(|row| {
+                Ok((row.try_get::<String>("", "kind")?, row.try_get("", "id")?))
+            })
+            .collect::<Result<Vec<(String, i64)>, DbErr>>()?;
+        let ids = |of_kind: &str| {
+            page.iter()
+                .filter(|(kind, _)| kind == of_kind)
+                .map(|&(_, id)| id)
+                .collect::<Vec<_>>()
+        };
+        let mut reminders: HashMap<_, _> = reminder::Entity::find()
+            .filter(reminder::Column::Id.is_in(ids("rem")))
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|rem| (rem.id, rem))
+            .collect();
+        let mut cron_reminders: HashMap<_, _> = cron_reminder::Entity::find()
+            .filter(cron_reminder::Column::Id.is_in(ids("cron_rem")))
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|rem| (rem.id, rem))
+            .collect();
+        Ok(page
+            .into_iter()
+            .filter_map(
+                |(kind, id)| -> Option<Box<dyn generic_reminder::GenericReminder>> {
+                    if kind == "rem" {
+                        Some(Box::<reminder::ActiveModel>::new(
+                            reminders.remove(&id)?.into(),
+                        ))
+                    } else {
+                        Some(Box::<cron_reminder::ActiveModel>::new(
+                            cron_reminders.remove(&id)?.into(),
+                        ))
+                    }
+                },
+            )
+            .collect())
+    }
+
+    /// Number of the chat's reminders of both kinds
+    pub(crate) async fn count_chat_reminders(
+        &self,
+        chat_id: i64,
+    ) -> Result<u64, Error> {
         Ok(reminder::Entity::find()
-            .filter(reminder::Column::MsgId.eq(msg_id))
-            .one(&self.pool)
-            .await?)
+            .filter(reminder::Column::ChatId.eq(chat_id))
+            .filter(reminder::Column::DeletedTime.is_null())
+            .count(&self.pool)
+            .await?
+            + cron_reminder::Entity::find()
+                .filter(cron_reminder::Column::ChatId.eq(chat_id))
+                .filter(cron_reminder::Column::DeletedTime.is_null())
+                .count(&self.pool)
+                .await?)
+    }
+
+    /// Reminders of the chat that match the filter, sorted like
+    /// [`Self::get_sorted_reminders`]
+    pub(crate) async fn search_reminders(
+        &self,
+        chat_id: i64,
+        filter: Filter,
+    ) -> Result<Vec<Box<dyn generic_reminder::GenericReminder>>, Error> {
+        self.find_reminders(chat_id, None, filter).await
+    }
+
+    /// Reminders of the chat created by the user that match the filter,
+    /// sorted like [`Self::get_sorted_reminders`]
+    pub(crate) async fn search_user_reminders(
+        &self,
+        chat_id: i64,
+        user_id: i64,
+        filter: Filter,
+    ) -> Result<Vec<Box<dyn generic_reminder::GenericReminder>>, Error> {
+        self.find_reminders(chat_id, Some(user_id), filter).await
+    }
+
+    async fn find_reminders(
+        &self,
+        chat_id: i64,
+        user_id: Option<i64>,
+        filter: Filter,
+    ) -> Result<Vec<Box<dyn generic_reminder::GenericReminder>>, Error> {
+        let reminders = reminder::Entity::find()
+            .filter(Condition::all().add_option(
+                user_id.map(|user_id| reminder::Column::UserId.eq(user_id)),
+            ))
+            .filter(filter_condition(
+                [
+                    reminder::Column::ChatId,
+                    reminder::Column::Time,
+                    reminder::Column::Desc,
+                    reminder::Column::Paused,
+                    reminder::Column::DeletedTime,
+                ],
+                chat_id,
+                &filter,
+            ))
+            .all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|x| -> Box<dyn generic_reminder::GenericReminder> {
+                Box::<reminder::ActiveModel>::new(x.into())
+            });
+        let cron_reminders =
+            cron_reminder::Entity::find()
+                .filter(Condition::all().add_option(
+                    user_id.map(|user_id| {
+                        cron_reminder::Column::UserId.eq(user_id)
+                    }),
+                ))
+                .filter(filter_condition(
+                    [
+                        cron_reminder::Column::ChatId,
+                        cron_reminder::Column::Time,
+                        cron_reminder::Column::Desc,
+                        cron_reminder::Column::Paused,
+                        cron_reminder::Column::DeletedTime,
+                    ],
+                    chat_id,
+                    &filter,
+                ))
+                .all(&self.pool)
+                .await?
+                .into_iter()
+                .map(|x| -> Box<dyn generic_reminder::GenericReminder> {
+                    Box::<cron_reminder::ActiveModel>::new(x.into())
+                });
+
+        let mut all_reminders = vec![];
+        all_reminders.extend(reminders);
+        all_reminders.extend(cron_reminders);
+        all_reminders.sort_unstable();
+        Ok(all_reminders)
+    }
+
+    /// Move the reminders of the chat that match the filter to the trash,
+    /// return the number of deleted ones
+    pub(crate) async fn delete_reminders(
+        &self,
+        chat_id: i64,
+        filter: Filter,
+    ) -> Result<u64, Error> {
+        defer!(self.notify.notify_one());
+        let now = Utc::now().naive_utc();
+        let txn = self.pool.begin().await?;
+        let deleted = reminder::Entity::update_many()
+            .col_expr(reminder::Column::DeletedTime, Expr::value(now))
+            .filter(filter_condition(
+                [
+                    reminder::Column::ChatId,
+                    reminder::Column::Time,
+                    reminder::Column::Desc,
+                    reminder::Column::Paused,
+                    reminder::Column::DeletedTime,
+                ],
+                chat_id,
+                &filter,
+            ))
+            .exec(&txn)
+            .await?
+            .rows_affected
+            + cron_reminder::Entity::update_many()
+                .col_expr(cron_reminder::Column::DeletedTime, Expr::value(now))
+                .filter(filter_condition(
+                    [
+                        cron_reminder::Column::ChatId,
+                        cron_reminder::Column::Time,
+                        cron_reminder::Column::Desc,
+                        cron_reminder::Column::Paused,
+                        cron_reminder::Column::DeletedTime,
+                    ],
+                    chat_id,
+                    &filter,
+                ))
+                .exec(&txn)
+                .await?
+                .rows_affected;
+        txn.commit().await?;
+        Ok(deleted)
+    }
+
+    /// Pause the reminders of the chat that match the filter,
+    /// return the number of paused ones
+    pub(crate) async fn pause_reminders(
+        &self,
+        chat_id: i64,
+        filter: Filter,
+    ) -> Result<u64, Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        let paused = reminder::Entity::update_many()
+            .col_expr(reminder::Column::Paused, Expr::value(true))
+            .col_expr(
+                reminder::Column::Version,
+                Expr::col(reminder::Column::Version).add(1),
+            )
+            .filter(filter_condition(
+                [
+                    reminder::Column::ChatId,
+                    reminder::Column::Time,
+                    reminder::Column::Desc,
+                    reminder::Column::Paused,
+                    reminder::Column::DeletedTime,
+                ],
+                chat_id,
+                &filter,
+            ))
+            .exec(&txn)
+            .await?
+            .rows_affected
+            + cron_reminder::Entity::update_many()
+                .col_expr(cron_reminder::Column::Paused, Expr::value(true))
+                .col_expr(
+                    cron_reminder::Column::Version,
+                    Expr::col(cron_reminder::Column::Version).add(1),
+                )
+                .filter(filter_condition(
+                    [
+                        cron_reminder::Column::ChatId,
+                        cron_reminder::Column::Time,
+                        cron_reminder::Column::Desc,
+                        cron_reminder::Column::Paused,
+                        cron_reminder::Column::DeletedTime,
+                    ],
+                    chat_id,
+                    &filter,
+                ))
+                .exec(&txn)
+                .await?
+                .rows_affected;
+        txn.commit().await?;
+        Ok(paused)
+    }
+
+    /// Reminders of the chat that have no owner with a known timezone,
+    /// e.g. the ones created before reminders had owners
+    pub(crate) async fn get_orphaned_reminders(
+        &self,
+        chat_id: i64,
+    ) -> Result<(Vec<reminder::Model>, Vec<cron_reminder::Model>), Error> {
+        let reminders = reminder::Entity::find()
+            .filter(orphaned_condition(
+                reminder::Column::ChatId,
+                reminder::Column::UserId,
+                reminder::Column::DeletedTime,
+                chat_id,
+            ))
+            .all(&self.pool)
+            .await?;
+        let cron_reminders = cron_reminder::Entity::find()
+            .filter(orphaned_condition(
+                cron_reminder::Column::ChatId,
+                cron_reminder::Column::UserId,
+                cron_reminder::Column::DeletedTime,
+                chat_id,
+            ))
+            .all(&self.pool)
+            .await?;
+        Ok((reminders, cron_reminders))
+    }
+
+    /// Make the user the owner of an orphaned reminder of the chat,
+    /// or of all of them. Returns the number of claimed reminders.
+    pub(crate) async fn claim_orphaned_reminders(
+        &self,
+        chat_id: i64,
+        target: Option<LinkTarget>,
+        user_id: i64,
+    ) -> Result<u64, Error> {
+        defer!(self.notify.notify_one());
+        let mut claimed = 0;
+        if !matches!(target, Some(LinkTarget::CronReminder(_))) {
+            let mut query = reminder::Entity::update_many()
+                .col_expr(reminder::Column::UserId, Expr::value(user_id))
+                .filter(orphaned_condition(
+                    reminder::Column::ChatId,
+                    reminder::Column::UserId,
+                    reminder::Column::DeletedTime,
+                    chat_id,
+                ));
+            if let Some(LinkTarget::Reminder(id)) = target {
+                query = query.filter(reminder::Column::Id.eq(id));
+            }
+            claimed += query.exec(&self.pool).await?.rows_affected;
+        }
+        if !matches!(target, Some(LinkTarget::Reminder(_))) {
+            let mut query = cron_reminder::Entity::update_many()
+                .col_expr(cron_reminder::Column::UserId, Expr::value(user_id))
+                .filter(orphaned_condition(
+                    cron_reminder::Column::ChatId,
+                    cron_reminder::Column::UserId,
+                    cron_reminder::Column::DeletedTime,
+                    chat_id,
+                ));
+            if let Some(LinkTarget::CronReminder(id)) = target {
+                query = query.filter(cron_reminder::Column::Id.eq(id));
+            }
+            claimed += query.exec(&self.pool).await?.rows_affected;
+        }
+        Ok(claimed)
+    }
+
+    /// Delete an orphaned reminder of the chat, or all of them.
+    /// Returns the number of deleted reminders.
+    pub(crate) async fn delete_orphaned_reminders(
+        &self,
+        chat_id: i64,
+        target: Option<LinkTarget>,
+    ) -> Result<u64, Error> {
+        let mut deleted = 0;
+        if !matches!(target, Some(LinkTarget::CronReminder(_))) {
+            let mut query =
+                reminder::Entity::delete_many().filter(orphaned_condition(
+                    reminder::Column::ChatId,
+                    reminder::Column::UserId,
+                    reminder::Column::DeletedTime,
+                    chat_id,
+                ));
+            if let Some(LinkTarget::Reminder(id)) = target {
+                query = query.filter(reminder::Column::Id.eq(id));
+            }
+            deleted += query.exec(&self.pool).await?.rows_affected;
+        }
+        if !matches!(target, Some(LinkTarget::Reminder(_))) {
+            let mut query = cron_reminder::Entity::delete_many().filter(
+                orphaned_condition(
+                    cron_reminder::Column::ChatId,
+                    cron_reminder::Column::UserId,
+                    cron_reminder::Column::DeletedTime,
+                    chat_id,
+                ),
+            );
+            if let Some(LinkTarget::CronReminder(id)) = target {
+                query = query.filter(cron_reminder::Column::Id.eq(id));
+            }
+            deleted += query.exec(&self.pool).await?.rows_affected;
+        }
+        Ok(deleted)
     }
 
-    pub(crate) async fn get_cron_reminder_by_msg_id(
+    pub(crate) async fn insert_message_link(
         &self,
+        chat_id: i64,
         msg_id: i32,
-    ) -> Result<Option<cron_reminder::Model>, Error> {
-        Ok(cron_reminder::Entity::find()
-            .filter(cron_reminder::Column::MsgId.eq(msg_id))
-            .one(&self.pool)
-            .await?)
+        role: MessageRole,
+        target: LinkTarget,
+    ) -> Result<(), Error> {
+        let (reminder_id, cron_reminder_id) = match target {
+            LinkTarget::Reminder(id) => (Some(id), None),
+            LinkTarget::CronReminder(id) => (None, Some(id)),
+        };
+        message_link::ActiveModel {
+            id: NotSet,
+            chat_id: Set(chat_id),
+            msg_id: Set(msg_id),
+            role: Set(role),
+            reminder_id: Set(reminder_id),
+            cron_reminder_id: Set(cron_reminder_id),
+        }
+        .insert(&self.pool)
+        .await?;
+        Ok(())
     }
 
-    pub(crate) async fn get_reminder_by_reply_id(
+    /// Find what a message in the chat is linked to, nothing if it's
+    /// linked to several reminders, e.g. set from its lines at once
+    pub(crate) async fn get_message_link(
         &self,
-        reply_id: i32,
-    ) -> Result<Option<reminder::Model>, Error> {
-        Ok(reminder::Entity::find()
-            .filter(reminder::Column::ReplyId.eq(reply_id))
-            .one(&self.pool)
+        chat_id: i64,
+        msg_id: i32,
+    ) -> Result<Option<message_link::Model>, Error> {
+        let mut links = message_link::Entity::find()
+            .filter(message_link::Column::ChatId.eq(chat_id))
+            .filter(message_link::Column::MsgId.eq(msg_id))
+            .limit(2)
+            .all(&self.pool)
+            .await?;
+        Ok(links.pop().filter(|_| links.is_empty()))
+    }
+
+    pub(crate) async fn get_message_links(
+        &self,
+        target: LinkTarget,
+        role: MessageRole,
+    ) -> Result<Vec<message_link::Model>, Error> {
+        Ok(message_link::Entity::find()
+            .filter(target.condition())
+            .filter(message_link::Column::Role.eq(role))
+            .all(&self.pool)
             .await?)
     }
 
-    pub(crate) async fn get_cron_reminder_by_reply_id(
+    /// Links to the messages the reminders out of the trash were created
+    /// from, in the order of their ids starting after `after_id`
+    pub(crate) async fn get_creation_links(
         &self,
-        reply_id: i32,
-    ) -> Result<Option<cron_reminder::Model>, Error> {
-        Ok(cron_reminder::Entity::find()
-            .filter(cron_reminder::Column::ReplyId.eq(reply_id))
-            .one(&self.pool)
+        after_id: i64,
+        limit: u64,
+    ) -> Result<Vec<message_link::Model>, Error> {
+        Ok(message_link::Entity::find()
+            .filter(message_link::Column::Role.eq(MessageRole::Creation))
+            .filter(message_link::Column::Id.gt(after_id))
+            .filter(
+                Condition::any()
+                    .add(
+                        message_link::Column::ReminderId.in_subquery(
+                            Query::select()
+                                .column(reminder::Column::Id)
+                                .from(reminder::Entity)
+                                .and_where(
+                                    reminder::Column::DeletedTime.is_null(),
+                                )
+                                .to_owned(),
+                        ),
+                    )
+                    .add(
+                        message_link::Column::CronReminderId.in_subquery(
+                            Query::select()
+                                .column(cron_reminder::Column::Id)
+                                .from(cron_reminder::Entity)
+                                .and_where(
+                                    cron_reminder::Column::DeletedTime
+                                        .is_null(),
+                                )
+                                .to_owned(),
+                        ),
+                    ),
+            )
+            .order_by_asc(message_link::Column::Id)
+            .limit(limit)
+            .all(&self.pool)
             .await?)
     }
 
-    pub(crate) async fn set_reminder_reply_id(
+    /// Point all messages linked to a replaced reminder to the new one
+    pub(crate) async fn move_message_links(
         &self,
-        mut rem: reminder::ActiveModel,
-        reply_id: i32,
+        from: LinkTarget,
+        to: LinkTarget,
     ) -> Result<(), Error> {
-        rem.reply_id = Set(Some(reply_id));
-        rem.update(&self.pool).await?;
+        let (reminder_id, cron_reminder_id) = to.ids();
+        message_link::Entity::update_many()
+            .col_expr(
+                message_link::Column::ReminderId,
+                Expr::value(reminder_id),
+            )
+            .col_expr(
+                message_link::Column::CronReminderId,
+                Expr::value(cron_reminder_id),
+            )
+            .filter(from.condition())
+            .exec(&self.pool)
+            .await?;
         Ok(())
     }
 
-    pub(crate) async fn set_cron_reminder_reply_id(
+    pub(crate) async fn delete_message_link(
         &self,
-        mut cron_rem: cron_reminder::ActiveModel,
-        reply_id: i32,
+        id: i64,
     ) -> Result<(), Error> {
-        cron_rem.reply_id = Set(Some(reply_id));
-        cron_rem.update(&self.pool).await?;
+        message_link::ActiveModel {
+            id: Set(id),
+            ..Default::default()
+        }
+        .delete(&self.pool)
+        .await?;
         Ok(())
     }
 
+    /// Update the reminder's description and schedule in place unless it
+    /// was changed since `rem.version` was read, keeping its id, pause
+    /// and links. Returns whether the update was applied.
     pub(crate) async fn update_reminder(
         &self,
         rem: reminder::Model,
-    ) -> Result<(), Error> {
+    ) -> Result<bool, Error> {
         defer!(self.notify.notify_one());
-        let desc = rem.desc.clone();
-        let mut rem_act = Into::<reminder::ActiveModel>::into(rem);
-        rem_act.desc = Set(desc);
-        rem_act.update(&self.pool).await?;
-        Ok(())
+        let res = reminder::Entity::update_many()
+            .col_expr(reminder::Column::Desc, Expr::value(rem.desc))
+            .col_expr(reminder::Column::Time, Expr::value(rem.time))
+            .col_expr(reminder::Column::Pattern, Expr::value(rem.pattern))
+            .col_expr(reminder::Column::Target, Expr::value(rem.target))
+            .col_expr(
+                reminder::Column::AdvanceTime,
+                Expr::value(rem.advance_time),
+            )
+            .col_expr(reminder::Column::HeldSince, Expr::value(rem.held_since))
+            .col_expr(reminder::Column::Failures, Expr::value(rem.failures))
+            .col_expr(reminder::Column::RetryTime, Expr::value(rem.retry_time))
+            .col_expr(
+                reminder::Column::Version,
+                Expr::col(reminder::Column::Version).add(1),
+            )
+            .filter(reminder::Column::Id.eq(rem.id))
+            .filter(reminder::Column::Version.eq(rem.version))
+            .exec(&self.pool)
+            .await?;
+        if res.rows_affected == 1 {
+            // A mark left by an interrupted sending is about the old time
+            forget_sending(LinkTarget::Reminder(rem.id), &self.pool).await?;
+        }
+        Ok(res.rows_affected == 1)
+    }
+
+    /// Move the reminder to another time unless it was changed since
+    /// `rem.version` was read. Returns whether the update was applied.
+    pub(crate) async fn reschedule_reminder(
+        &self,
+        rem: reminder::Model,
+    ) -> Result<bool, Error> {
+        defer!(self.notify.notify_one());
+        let res = reminder::Entity::update_many()
+            .col_expr(reminder::Column::Time, Expr::value(rem.time))
+            .col_expr(reminder::Column::Pattern, Expr::value(rem.pattern))
+            .col_expr(
+                reminder::Column::AdvanceTime,
+                Expr::value(rem.advance_time),
+            )
+            .col_expr(
+                reminder::Column::Version,
+                Expr::col(reminder::Column::Version).add(1),
+            )
+            .filter(reminder::Column::Id.eq(rem.id))
+            .filter(reminder::Column::Version.eq(rem.version))
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected == 1)
+    }
+
+    /// Update the cron reminder's description and schedule in place, like
+    /// [`Self::update_reminder`]
+    pub(crate) async fn update_cron_reminder(
+        &self,
+        rem: cron_reminder::Model,
+    ) -> Result<bool, Error> {
+        defer!(self.notify.notify_one());
+        let res = cron_reminder::Entity::update_many()
+            .col_expr(cron_reminder::Column::Desc, Expr::value(rem.desc))
+            .col_expr(
+                cron_reminder::Column::CronExpr,
+                Expr::value(rem.cron_expr),
+            )
+            .col_expr(cron_reminder::Column::Time, Expr::value(rem.time))
+            .col_expr(
+                cron_reminder::Column::Failures,
+                Expr::value(rem.failures),
+            )
+            .col_expr(
+                cron_reminder::Column::RetryTime,
+                Expr::value(rem.retry_time),
+            )
+            .col_expr(
+                cron_reminder::Column::Version,
+                Expr::col(cron_reminder::Column::Version).add(1),
+            )
+            .filter(cron_reminder::Column::Id.eq(rem.id))
+            .filter(cron_reminder::Column::Version.eq(rem.version))
+            .exec(&self.pool)
+            .await?;
+        if res.rows_affected == 1 {
+            // A mark left by an interrupted sending is about the old time
+            forget_sending(LinkTarget::CronReminder(rem.id), &self.pool)
+                .await?;
+        }
+        Ok(res.rows_affected == 1)
+    }
+
+    /// Move the cron reminder to another time unless it was changed since
+    /// `rem.version` was read. Returns whether the update was applied.
+    pub(crate) async fn reschedule_cron_reminder(
+        &self,
+        rem: cron_reminder::Model,
+    ) -> Result<bool, Error> {
+        defer!(self.notify.notify_one());
+        let res = cron_reminder::Entity::update_many()
+            .col_expr(cron_reminder::Column::Time, Expr::value(rem.time))
+            .col_expr(
+                cron_reminder::Column::Version,
+                Expr::col(cron_reminder::Column::Version).add(1),
+            )
+            .filter(cron_reminder::Column::Id.eq(rem.id))
+            .filter(cron_reminder::Column::Version.eq(rem.version))
+            .exec(&self.pool)
+            .await?;
+        Ok(res.rows_affected == 1)
+    }
+
+    /// Replace the reminder with the cron reminder unless it was changed
+    /// since `version` was read. Returns the inserted cron reminder if
+    /// the replacement was applied.
+    pub(crate) async fn replace_reminder_with_cron(
+        &self,
+        id: i64,
+        version: i32,
+        new: cron_reminder::ActiveModel,
+    ) -> Result<Option<cron_reminder::ActiveModel>, Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        let res = reminder::Entity::delete_many()
+            .filter(reminder::Column::Id.eq(id))
+            .filter(reminder::Column::Version.eq(version))
+            .exec(&txn)
+            .await?;
+        if res.rows_affected != 1 {
+            return Ok(None);
+        }
+        forget_sending(LinkTarget::Reminder(id), &txn).await?;
+        let new = new.save(&txn).await?;
+        txn.commit().await?;
+        Ok(Some(new))
+    }
+
+    /// Replace the cron reminder with the reminder, like
+    /// [`Self::replace_reminder_with_cron`]
+    pub(crate) async fn replace_cron_reminder_with_reminder(
+        &self,
+        id: i64,
+        version: i32,
+        new: reminder::ActiveModel,
+    ) -> Result<Option<reminder::ActiveModel>, Error> {
+        defer!(self.notify.notify_one());
+        let txn = self.pool.begin().await?;
+        let res = cron_reminder::Entity::delete_many()
+            .filter(cron_reminder::Column::Id.eq(id))
+            .filter(cron_reminder::Column::Version.eq(version))
+            .exec(&txn)
+            .await?;
+        if res.rows_affected != 1 {
+            return Ok(None);
+        }
+        forget_sending(LinkTarget::CronReminder(id), &txn).await?;
+        let new = new.save(&txn).await?;
+        txn.commit().await?;
+        Ok(Some(new))
     }
 
     pub(crate) fn listen(&self) -> Notified<'_> {
         self.notify.notified()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Database in memory with the migrations applied, a single
+    /// connection keeps it alive
+    async fn sqlite_database() -> Database {
+        let mut opts = ConnectOptions::new("sqlite::memory:");
+        opts.max_connections(1).min_connections(1);
+        let db = Database {
+            pool: SeaOrmDatabase::connect(opts).await.unwrap(),
+            notify: Notify::new(),
+            timezones: Mutex::default(),
+        };
+        db.apply_migrations().await.unwrap();
+        db
+    }
+
+    fn time() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap()
+    }
+
+    async fn insert_reminder(db: &Database) -> i64 {
+        db.insert_reminder(reminder::ActiveModel {
+            chat_id: Set(1),
+            time: Set(time()),
+            desc: Set("tea".to_owned()),
+            paused: Set(false),
+            version: Set(0),
+            meeting: Set(false),
+            failures: Set(0),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .id
+        .unwrap()
+    }
+
+    #[test]
+    fn test_timezone_read_before_change_not_cached() {
+        let mut cache = TimezoneCache::default();
+        let generation = cache.generation;
+        cache.change(|timezones| {
+            timezones.insert(1, Some("Europe/Berlin".to_owned()));
+        });
+        cache.insert_read(1, Some("Europe/Moscow".to_owned()), generation);
+        assert_eq!(cache.timezones[&1].as_deref(), Some("Europe/Berlin"));
+
+        cache.insert_read(2, None, cache.generation);
+        assert_eq!(cache.timezones.get(&2), Some(&None));
+    }
+
+    #[tokio::test]
+    async fn test_prune_keeps_links_without_cron_reminders() {
+        let db = sqlite_database().await;
+        let kept = insert_reminder(&db).await;
+        let gone = insert_reminder(&db).await;
+        for (msg_id, id) in [(1, kept), (2, gone)] {
+            db.insert_message_link(
+                1,
+                msg_id,
+                MessageRole::Confirmation,
+                LinkTarget::Reminder(id),
+            )
+            .await
+            .unwrap();
+        }
+        db.purge_reminder(gone).await.unwrap();
+
+        assert_eq!(db.prune(time(), time()).await.unwrap(), 1);
+        assert!(db.get_message_link(1, 1).await.unwrap().is_some());
+        assert!(db.get_message_link(1, 2).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_creation_links_skip_trash() {
+        let db = sqlite_database().await;
+        let kept = insert_reminder(&db).await;
+        let trashed = insert_reminder(&db).await;
+        for (msg_id, id) in [(1, kept), (2, trashed)] {
+            db.insert_message_link(
+                1,
+                msg_id,
+                MessageRole::Creation,
+                LinkTarget::Reminder(id),
+            )
+            .await
+            .unwrap();
+        }
+        db.trash_reminder(trashed).await.unwrap();
+        let links = db.get_creation_links(0, 10).await.unwrap();
+        assert_eq!(
+            links
+                .iter()
+                .map(|link| link.reminder_id)
+                .collect::<Vec<_>>(),
+            vec![Some(kept)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trash_forgets_sending() {
+        let db = sqlite_database().await;
+        let id = insert_reminder(&db).await;
+        db.start_sending(LinkTarget::Reminder(id)).await.unwrap();
+        db.trash_reminder(id).await.unwrap();
+        assert_eq!(db.get_interrupted_sending().await.unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_prune_sending_of_gone_reminders() {
+        let db = sqlite_database().await;
+        let kept = insert_reminder(&db).await;
+        let gone = insert_reminder(&db).await;
+        for id in [kept, gone] {
+            db.start_sending(LinkTarget::Reminder(id)).await.unwrap();
+        }
+        // Left over from before the marks were deleted along with it
+        reminder::Entity::delete_by_id(gone)
+            .exec(&db.pool)
+            .await
+            .unwrap();
+
+        db.prune(time(), time()).await.unwrap();
+        assert_eq!(
+            db.get_interrupted_sending().await.unwrap(),
+            vec![LinkTarget::Reminder(kept)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replace_changed_reminder_with_cron() {
+        let db = sqlite_database().await;
+        let id = insert_reminder(&db).await;
+        let cron = cron_reminder::ActiveModel {
+            chat_id: Set(1),
+            cron_expr: Set("0 9 * * *".to_owned()),
+            time: Set(time()),
+            desc: Set("tea".to_owned()),
+            paused: Set(false),
+            version: Set(0),
+            failures: Set(0),
+            ..Default::default()
+        };
+        let changed = db.get_reminder(id).await.unwrap().unwrap();
+        assert!(db.update_reminder(changed).await.unwrap());
+
+        assert!(db
+            .replace_reminder_with_cron(id, 0, cron.clone())
+            .await
+            .unwrap()
+            .is_none());
+        assert!(db.get_reminder(id).await.unwrap().is_some());
+
+        let replaced = db
+            .replace_reminder_with_cron(id, 1, cron)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(db.get_reminder(id).await.unwrap().is_none());
+        assert!(db
+            .get_cron_reminder(replaced.id.unwrap())
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn test_replace_api_token() {
+        let db = sqlite_database().await;
+        let (old, model) = crate::api::new_token(-100, 1);
+        db.replace_api_token(model).await.unwrap();
+        let (new, model) = crate::api::new_token(-100, 2);
+        db.replace_api_token(model).await.unwrap();
+
+        // Only the last token of the chat works
+        assert!(db
+            .get_api_token(&crate::api::hash_token(&old))
+            .await
+            .unwrap()
+            .is_none());
+        let token = db
+            .get_api_token(&crate::api::hash_token(&new))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((token.chat_id, token.user_id), (-100, 2));
+
+        assert!(db.delete_chat_api_token(-100).await.unwrap());
+        assert!(!db.delete_chat_api_token(-100).await.unwrap());
+    }
+}
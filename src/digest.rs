@@ -0,0 +1,247 @@
+//! Digests of the upcoming reminders sent to the chats that opted in with
+//! /digest: every morning the reminders of the day, or every Monday morning
+//! the reminders of the week
+
+use crate::entity::digest;
+use crate::format;
+use crate::generic_reminder::GenericReminder;
+use crate::search::Filter;
+use crate::settings;
+use crate::tg::{self, TgResponse};
+use chrono::{Datelike, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+
+/// Time of the day the digest is sent at if not given, 8:00
+const DEFAULT_MINUTE: i32 = 8 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Period {
+    Daily,
+    /// Sent on Mondays
+    Weekly,
+}
+
+impl Period {
+    const ALL: [Self; 2] = [Self::Daily, Self::Weekly];
+
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+        }
+    }
+
+    pub(crate) fn from_code(code: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|period| period.code().eq_ignore_ascii_case(code))
+    }
+
+    /// Number of days the digest covers, starting with the day it's sent
+    fn days(self) -> u64 {
+        match self {
+            Self::Daily => 1,
+            Self::Weekly => 7,
+        }
+    }
+}
+
+/// Period and time of the day in minutes since midnight from the /digest
+/// arguments, e.g. `daily 8:30` or `weekly`. `off` turns the digest off.
+pub(crate) fn parse_args(args: &str) -> Option<Option<(Period, i32)>> {
+    let mut words = args.split_whitespace();
+    let first = words.next()?;
+    let parsed = if first.eq_ignore_ascii_case("off") {
+        None
+    } else {
+        let minute = match words.next() {
+            Some(time) => settings::parse_minutes(time)?,
+            None => DEFAULT_MINUTE,
+        };
+        Some((Period::from_code(first)?, minute))
+    };
+    words.next().is_none().then_some(parsed)
+}
+
+/// First time after now the digest is due, in UTC
+pub(crate) fn next_time(
+    period: Period,
+    minute: i32,
+    user_tz: Tz,
+    now: NaiveDateTime,
+) -> NaiveDateTime {
+    let local_now = user_tz.from_utc_datetime(&now).naive_local();
+    let time =
+        NaiveTime::from_hms_opt(minute as u32 / 60, minute as u32 % 60, 0)
+            .unwrap_or_default();
+    let mut local = local_now.date().and_time(time);
+    if period == Period::Weekly {
+        let days_to_monday = (7 - local.weekday().num_days_from_monday()) % 7;
+        local += TimeDelta::days(days_to_monday.into());
+    }
+    while local <= local_now {
+        local += TimeDelta::days(period.days() as i64);
+    }
+    user_tz
+        .from_local_datetime(&local)
+        .earliest()
+        .map_or(local, |time| time.naive_utc())
+}
+
+/// Period of the stored digest
+pub(crate) fn period(digest: &digest::Model) -> Period {
+    Period::from_code(&digest.period).unwrap_or(Period::Daily)
+}
+
+/// Digest of the chat set by the user, first due after now
+pub(crate) fn schedule(
+    chat_id: i64,
+    user_id: i64,
+    period: Period,
+    minute: i32,
+    user_tz: Tz,
+    now: NaiveDateTime,
+) -> digest::Model {
+    digest::Model {
+        chat_id,
+        user_id,
+        period: period.code().to_owned(),
+        minute,
+        next_time: next_time(period, minute, user_tz, now),
+    }
+}
+
+/// The digest moved to the next time it's due after now
+pub(crate) fn reschedule(
+    digest: digest::Model,
+    user_tz: Tz,
+    now: NaiveDateTime,
+) -> digest::Model {
+    digest::Model {
+        next_time: next_time(period(&digest), digest.minute, user_tz, now),
+        ..digest
+    }
+}
+
+/// When the digest is sent, e.g. `every Monday at 08:00`
+pub(crate) fn describe(digest: &digest::Model) -> String {
+    let days = match period(digest) {
+        Period::Daily => "every day",
+        Period::Weekly => "every Monday",
+    };
+    format!(
+        "{} at {}",
+        days,
+        settings::format_clock(
+            digest.minute as u32 / 60,
+            digest.minute as u32 % 60,
+            settings::uses_12h_clock(digest.user_id),
+        )
+    )
+}
+
+/// Reminders the digest sent now lists
+pub(crate) fn filter(
+    period: Period,
+    user_tz: Tz,
+    now: NaiveDateTime,
+) -> Filter {
+    Filter::upcoming(period.days(), user_tz, now)
+}
+
+/// Messages of the digest listing the sorted reminders, `None` if there
+/// are none to list
+pub(crate) fn messages(
+    period: Period,
+    sorted_reminders: Vec<Box<dyn GenericReminder>>,
+    user_tz: Tz,
+    style: settings::DateStyle,
+    now: NaiveDateTime,
+) -> Option<Vec<String>> {
+    if sorted_reminders.is_empty() {
+        return None;
+    }
+    let day = user_tz.from_utc_datetime(&now).date_naive();
+    let header = TgResponse::DigestHeader(
+        period == Period::Weekly,
+        style.format_day(day, false),
+    );
+    let lines = match period {
+        Period::Daily => sorted_reminders
+            .iter()
+            .map(|rem| {
+                format::agenda_line(rem.as_ref(), user_tz, now, style.language)
+            })
+            .collect(),
+        Period::Weekly => {
+            format::list_by_day(sorted_reminders, user_tz, style, now)
+        }
+    };
+    Some(format::split_lines(
+        std::iter::once(header.to_string_in(style.language)).chain(lines),
+        tg::MESSAGE_MAX_LEN,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test_case("daily" => Some(Some((Period::Daily, 480))) ; "default time")]
+    #[test_case("Weekly 9:30" => Some(Some((Period::Weekly, 570))) ; "weekly")]
+    #[test_case("off" => Some(None) ; "off")]
+    #[test_case("monthly" => None ; "unknown period")]
+    #[test_case("daily 25:00" => None ; "bad time")]
+    #[test_case("daily 8:00 9:00" => None ; "extra words")]
+    #[test_case("" => None ; "empty")]
+    fn test_parse_args(args: &str) -> Option<Option<(Period, i32)>> {
+        parse_args(args)
+    }
+
+    // 2024-06-01 is a Saturday, the times are in Europe/Amsterdam (UTC+2)
+    #[test_case(Period::Daily, "2024-06-01 05:00" => "2024-06-01 06:00" ; "later today")]
+    #[test_case(Period::Daily, "2024-06-01 06:00" => "2024-06-02 06:00" ; "tomorrow")]
+    #[test_case(Period::Weekly, "2024-06-01 05:00" => "2024-06-03 06:00" ; "next monday")]
+    #[test_case(Period::Weekly, "2024-06-03 05:59" => "2024-06-03 06:00" ; "this monday")]
+    #[test_case(Period::Weekly, "2024-06-03 06:00" => "2024-06-10 06:00" ; "monday after")]
+    fn test_next_time(period: Period, now: &str) -> String {
+        next_time(period, 8 * 60, chrono_tz::Europe::Amsterdam, utc(now))
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    }
+
+    #[test]
+    fn test_schedule() {
+        let tz = chrono_tz::Europe::Amsterdam;
+        let digest = schedule(
+            1,
+            1,
+            Period::Weekly,
+            9 * 60 + 5,
+            tz,
+            utc("2024-06-01 05:00"),
+        );
+        assert_eq!(digest.next_time, utc("2024-06-03 07:05"));
+        assert_eq!(describe(&digest), "every Monday at 09:05");
+        let digest = reschedule(digest, tz, utc("2024-06-03 07:05"));
+        assert_eq!(digest.next_time, utc("2024-06-10 07:05"));
+    }
+
+    #[test]
+    fn test_messages_empty() {
+        let now = utc("2024-06-01 06:00");
+        assert!(messages(
+            Period::Daily,
+            vec![],
+            chrono_tz::Europe::Amsterdam,
+            settings::DateStyle::default(),
+            now
+        )
+        .is_none());
+    }
+}
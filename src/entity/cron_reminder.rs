@@ -3,6 +3,8 @@
 use chrono::NaiveDateTime;
 use sea_orm::entity::prelude::*;
 
+use super::reminder::MediaKind;
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "cron_reminder")]
 pub struct Model {
@@ -14,8 +16,21 @@ pub struct Model {
     pub desc: String,
     pub user_id: Option<i64>,
     pub paused: bool,
-    pub msg_id: Option<i32>,
-    pub reply_id: Option<i32>,
+    pub policy: Option<String>,
+    pub version: i32,
+    pub media_kind: Option<MediaKind>,
+    pub media_file_id: Option<String>,
+    /// Failed attempts to send it in a row, it's not retried anymore
+    /// after too many of them
+    pub failures: i32,
+    /// Time of the next attempt to send it after a failure in UTC
+    pub retry_time: Option<NaiveDateTime>,
+    /// Forum topic the reminder was set in, its notifications are sent
+    /// there instead of the general topic
+    pub thread_id: Option<i32>,
+    /// Time the reminder was moved to the trash in UTC, it's purged some
+    /// days after
+    pub deleted_time: Option<NaiveDateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,6 +2,26 @@
 
 pub mod prelude;
 
+pub mod account_link;
+pub mod ai_opt_in;
+pub mod api_token;
+pub mod bill;
+pub mod calendar_feed;
+pub mod chat_timezone;
 pub mod cron_reminder;
+pub mod digest;
+pub mod expiring_message;
+pub mod message_link;
+pub mod occurrence;
+pub mod pomodoro;
 pub mod reminder;
+pub mod reminder_event;
+pub mod reminder_target;
+pub mod rsvp;
+pub mod sending;
+pub mod share_link;
+pub mod template;
+pub mod user_language;
+pub mod user_settings;
 pub mod user_timezone;
+pub mod vacation;
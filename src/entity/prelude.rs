@@ -1,6 +1,26 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.10.2
 #![allow(unused_imports)]
 
+pub use super::account_link::Entity as AccountLink;
+pub use super::ai_opt_in::Entity as AiOptIn;
+pub use super::api_token::Entity as ApiToken;
+pub use super::bill::Entity as Bill;
+pub use super::calendar_feed::Entity as CalendarFeed;
+pub use super::chat_timezone::Entity as ChatTimezone;
 pub use super::cron_reminder::Entity as CronReminder;
+pub use super::digest::Entity as Digest;
+pub use super::expiring_message::Entity as ExpiringMessage;
+pub use super::message_link::Entity as MessageLink;
+pub use super::occurrence::Entity as Occurrence;
+pub use super::pomodoro::Entity as Pomodoro;
 pub use super::reminder::Entity as Reminder;
+pub use super::reminder_event::Entity as ReminderEvent;
+pub use super::reminder_target::Entity as ReminderTarget;
+pub use super::rsvp::Entity as Rsvp;
+pub use super::sending::Entity as Sending;
+pub use super::share_link::Entity as ShareLink;
+pub use super::template::Entity as Template;
+pub use super::user_language::Entity as UserLanguage;
+pub use super::user_settings::Entity as UserSettings;
 pub use super::user_timezone::Entity as UserTimezone;
+pub use super::vacation::Entity as Vacation;
@@ -3,6 +3,18 @@
 use chrono::NaiveDateTime;
 use sea_orm::entity::prelude::*;
 
+/// Kind of the file re-sent along with the notification
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+pub enum MediaKind {
+    #[sea_orm(string_value = "photo")]
+    Photo,
+    #[sea_orm(string_value = "document")]
+    Document,
+    #[sea_orm(string_value = "voice")]
+    Voice,
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "reminder")]
 pub struct Model {
@@ -14,8 +26,28 @@ pub struct Model {
     pub user_id: Option<i64>,
     pub paused: bool,
     pub pattern: Option<String>,
-    pub msg_id: Option<i32>,
-    pub reply_id: Option<i32>,
+    pub policy: Option<String>,
+    pub version: i32,
+    pub pomodoro_id: Option<i64>,
+    pub target: Option<String>,
+    pub meeting: bool,
+    pub advance: Option<i64>,
+    pub advance_time: Option<NaiveDateTime>,
+    pub media_kind: Option<MediaKind>,
+    pub media_file_id: Option<String>,
+    /// Time the reminder was due at if it was held during quiet hours
+    pub held_since: Option<NaiveDateTime>,
+    /// Failed attempts to send it in a row, it's not retried anymore
+    /// after too many of them
+    pub failures: i32,
+    /// Time of the next attempt to send it after a failure in UTC
+    pub retry_time: Option<NaiveDateTime>,
+    /// Forum topic the reminder was set in, its notifications are sent
+    /// there instead of the general topic
+    pub thread_id: Option<i32>,
+    /// Time the reminder was moved to the trash in UTC, it's purged some
+    /// days after
+    pub deleted_time: Option<NaiveDateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -1,15 +1,51 @@
-use crate::db;
+use crate::migration::DbErr;
+use crate::tg::TgResponse;
 use std::fmt;
 
 #[derive(Debug)]
 pub(crate) enum Error {
-    Database(db::Error),
+    Database(DbErr),
     Parse(chrono_tz::ParseError),
     CronParse(cron_parser::ParseError),
     TeloxideRequest(teloxide::RequestError),
-    UnmatchedQuery(teloxide::types::CallbackQuery),
+    UnmatchedQuery(Box<teloxide::types::CallbackQuery>),
     ReminderNotFound(i64),
     CronReminderNotFound(i64),
+    NotChatAdmin,
+    NotChatMember,
+    #[cfg(feature = "ai")]
+    Ai(reqwest::Error),
+}
+
+/// How an error is surfaced to the user
+pub(crate) enum Category {
+    /// Caused by the user's request, explained with the response
+    User(TgResponse),
+    /// Failure of the bot or the services it relies on, logged and
+    /// reported to the user without the details
+    Internal,
+    /// Failure of a request to Telegram, only logged since the user
+    /// can't be reached either
+    Telegram,
+}
+
+impl Error {
+    pub(crate) fn category(&self) -> Category {
+        match *self {
+            Self::Parse(_) => Category::User(TgResponse::UnknownTimezone),
+            Self::CronParse(_) => Category::User(TgResponse::IncorrectRequest),
+            Self::UnmatchedQuery(_) => Category::User(TgResponse::StaleButton),
+            Self::ReminderNotFound(_) | Self::CronReminderNotFound(_) => {
+                Category::User(TgResponse::ReminderGone)
+            }
+            Self::NotChatAdmin => Category::User(TgResponse::NotChatAdmin),
+            Self::NotChatMember => Category::User(TgResponse::NotChatMember),
+            Self::TeloxideRequest(_) => Category::Telegram,
+            Self::Database(_) => Category::Internal,
+            #[cfg(feature = "ai")]
+            Self::Ai(_) => Category::Internal,
+        }
+    }
 }
 
 impl fmt::Display for Error {
@@ -30,16 +66,30 @@ impl fmt::Display for Error {
             Self::CronReminderNotFound(cron_rem_id) => {
                 write!(f, "Cron reminder with id {} not found", cron_rem_id)
             }
+            Self::NotChatAdmin => {
+                write!(f, "Only the chat administrators are allowed")
+            }
+            Self::NotChatMember => {
+                write!(f, "Only the chat members are allowed")
+            }
+            #[cfg(feature = "ai")]
+            Self::Ai(ref err) => write!(f, "AI backend error: {}", err),
         }
     }
 }
 
-impl From<db::Error> for Error {
-    fn from(err: db::Error) -> Self {
+impl From<DbErr> for Error {
+    fn from(err: DbErr) -> Self {
         Self::Database(err)
     }
 }
 
+impl From<chrono_tz::ParseError> for Error {
+    fn from(err: chrono_tz::ParseError) -> Self {
+        Self::Parse(err)
+    }
+}
+
 impl From<cron_parser::ParseError> for Error {
     fn from(err: cron_parser::ParseError) -> Self {
         Self::CronParse(err)
@@ -52,4 +102,11 @@ impl From<teloxide::RequestError> for Error {
     }
 }
 
+#[cfg(feature = "ai")]
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Ai(err)
+    }
+}
+
 impl std::error::Error for Error {}
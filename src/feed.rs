@@ -0,0 +1,241 @@
+//! Secret calendar feeds of the reminders set in the private chats for
+//! calendar apps to subscribe to with /calendar, listing their firings
+//! in the next weeks
+
+#[cfg(not(test))]
+use crate::db::Database;
+#[cfg(test)]
+use crate::db::MockDatabase as Database;
+use crate::entity::{calendar_feed, cron_reminder, reminder};
+use crate::ical::{self, Occurrence};
+use crate::serializers::Pattern;
+use crate::share::{self, RateLimiter};
+use crate::{cron, parsers};
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use chrono::{NaiveDateTime, TimeDelta};
+use chrono_tz::Tz;
+use sea_orm::{ActiveValue::NotSet, Set};
+use std::time::Instant;
+use url::Url;
+
+/// Days ahead the firings are listed for
+const FEED_DAYS: i64 = 60;
+/// Firings of a reminder listed at most, for the frequent ones
+const MAX_OCCURRENCES: usize = 100;
+const CALENDAR_NAME: &str = "Reminders";
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Action {
+    /// Link to the feed, created if there's none
+    Show,
+    /// Link to the feed under a new token, the old one stops working
+    Reset,
+    Off,
+}
+
+pub(crate) fn parse_args(args: &str) -> Option<Action> {
+    match args.trim().to_lowercase().as_str() {
+        "" => Some(Action::Show),
+        "reset" => Some(Action::Reset),
+        "off" => Some(Action::Off),
+        _ => None,
+    }
+}
+
+pub(crate) fn new_feed(user_id: i64) -> calendar_feed::ActiveModel {
+    calendar_feed::ActiveModel {
+        id: NotSet,
+        user_id: Set(user_id),
+        token: Set(share::new_token()),
+    }
+}
+
+/// Address of the feed to subscribe to
+pub(crate) fn feed_url(public_url: &Url, token: &str) -> Option<Url> {
+    share::public_path_url(public_url, &format!("calendar/{}.ics", token))
+}
+
+fn summary(desc: &str) -> String {
+    if desc.is_empty() {
+        "⏰".to_owned()
+    } else {
+        desc.to_owned()
+    }
+}
+
+/// Firings from the first one on until the time, in UTC
+fn firings(
+    first: NaiveDateTime,
+    mut next: impl FnMut(NaiveDateTime) -> Option<NaiveDateTime>,
+    until: NaiveDateTime,
+) -> impl Iterator<Item = NaiveDateTime> {
+    std::iter::successors(Some(first), move |&cur| next(cur))
+        .take_while(move |&time| time < until)
+        .take(MAX_OCCURRENCES)
+}
+
+/// Firings of the active reminders in the next [`FEED_DAYS`] days
+pub(crate) fn occurrences(
+    reminders: &[reminder::Model],
+    cron_reminders: &[cron_reminder::Model],
+    user_tz: Tz,
+    now: NaiveDateTime,
+) -> Vec<Occurrence> {
+    let until = now + TimeDelta::days(FEED_DAYS);
+    let occurrence =
+        |kind: &str, id: i64, desc: &str, time: NaiveDateTime| Occurrence {
+            uid: format!(
+                "{}-{}-{}@remindee",
+                kind,
+                id,
+                time.and_utc().timestamp()
+            ),
+            summary: summary(desc),
+            time,
+        };
+    let reminder_occurrences =
+        reminders.iter().filter(|rem| !rem.paused).flat_map(|rem| {
+            let mut pattern = rem.pattern.as_deref().and_then(|pattern| {
+                serde_json::from_str::<Pattern>(pattern).ok()
+            });
+            firings(rem.time, move |cur| pattern.as_mut()?.next(cur), until)
+                .map(|time| occurrence("rem", rem.id, &rem.desc, time))
+        });
+    let cron_occurrences = cron_reminders
+        .iter()
+        .filter(|rem| !rem.paused)
+        .flat_map(|rem| {
+            firings(
+                rem.time,
+                |cur| cron::next_time(&rem.cron_expr, cur, user_tz).ok(),
+                until,
+            )
+            .map(|time| occurrence("cron_rem", rem.id, &rem.desc, time))
+        });
+    let mut occurrences: Vec<_> =
+        reminder_occurrences.chain(cron_occurrences).collect();
+    occurrences.sort_by_key(|occurrence| occurrence.time);
+    occurrences
+}
+
+async fn feed(
+    db: &Database,
+    token: &str,
+) -> Result<Option<String>, crate::err::Error> {
+    let Some(feed) = db.get_calendar_feed(token).await? else {
+        return Ok(None);
+    };
+    let user_tz = db
+        .get_user_timezone_name(feed.user_id)
+        .await?
+        .and_then(|tz_name| tz_name.parse().ok())
+        .unwrap_or(Tz::UTC);
+    let reminders = db.get_pending_chat_reminders(feed.user_id).await?;
+    let cron_reminders =
+        db.get_pending_chat_cron_reminders(feed.user_id).await?;
+    let now = parsers::now_time();
+    Ok(Some(ical::calendar(
+        CALENDAR_NAME,
+        &occurrences(&reminders, &cron_reminders, user_tz, now),
+        now,
+    )))
+}
+
+pub(crate) async fn feed_page(
+    token: web::Path<String>,
+    req: HttpRequest,
+    db: web::Data<Database>,
+    limiter: web::Data<RateLimiter>,
+) -> HttpResponse {
+    let addr = req.peer_addr().map(|addr| addr.ip());
+    if addr.is_some_and(|addr| !limiter.check(addr, Instant::now())) {
+        return HttpResponse::TooManyRequests().finish();
+    }
+    let Some(token) = token.strip_suffix(".ics") else {
+        return HttpResponse::NotFound().finish();
+    };
+    match feed(&db, token).await {
+        Ok(Some(calendar)) => HttpResponse::Ok()
+            .insert_header((
+                header::CONTENT_TYPE,
+                "text/calendar; charset=utf-8",
+            ))
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .insert_header(("X-Robots-Tag", "noindex"))
+            .body(calendar),
+        Ok(None) => HttpResponse::NotFound().finish(),
+        Err(err) => {
+            tracing::error!("{}", err);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+    use test_case::test_case;
+
+    fn time(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+    }
+
+    #[test_case("" => Some(Action::Show) ; "show")]
+    #[test_case(" Reset " => Some(Action::Reset) ; "reset")]
+    #[test_case("off" => Some(Action::Off) ; "off")]
+    #[test_case("on" => None ; "unknown")]
+    fn test_parse_args(args: &str) -> Option<Action> {
+        parse_args(args)
+    }
+
+    #[test]
+    fn test_feed_url() {
+        assert_eq!(
+            feed_url(&Url::parse("https://remindee.example").unwrap(), "abc")
+                .unwrap()
+                .as_str(),
+            "https://remindee.example/calendar/abc.ics"
+        );
+    }
+
+    #[test]
+    fn test_occurrences() {
+        let cron_reminder = cron_reminder::Model {
+            id: 2,
+            chat_id: 1,
+            cron_expr: "0 9 * * *".to_owned(),
+            time: time(1, 9),
+            desc: "stretch".to_owned(),
+            user_id: Some(1),
+            paused: false,
+            policy: None,
+            version: 0,
+            media_kind: None,
+            media_file_id: None,
+            failures: 0,
+            retry_time: None,
+            thread_id: None,
+            deleted_time: None,
+        };
+        let paused = cron_reminder::Model {
+            id: 3,
+            paused: true,
+            ..cron_reminder.clone()
+        };
+        let occurrences =
+            occurrences(&[], &[cron_reminder, paused], Tz::UTC, time(1, 0));
+        assert_eq!(occurrences.len(), FEED_DAYS as usize);
+        assert_eq!(
+            occurrences[1],
+            Occurrence {
+                uid: "cron_rem-2-1717318800@remindee".to_owned(),
+                summary: "stretch".to_owned(),
+                time: time(2, 9),
+            }
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Fake Bot API failing on demand, so that the handling of Telegram
+//! errors is tested without the network: requests are answered with the
+//! queued failures first and succeed once the queue is empty
+
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde_json::json;
+use teloxide::Bot;
+use url::Url;
+
+/// Time after which the requests of the bot are timed out
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(300);
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Failure {
+    /// 429 asking to retry after the number of seconds
+    TooManyRequests(u32),
+    /// 403 of a user who blocked the bot
+    Blocked,
+    /// 400 with the description
+    BadRequest(&'static str),
+    /// No answer until the bot gives up
+    Timeout,
+}
+
+impl Failure {
+    async fn respond(self) -> HttpResponse {
+        match self {
+            Self::TooManyRequests(retry_after) => {
+                HttpResponse::TooManyRequests().json(json!({
+                    "ok": false,
+                    "error_code": 429,
+                    "description": format!(
+                        "Too Many Requests: retry after {}",
+                        retry_after
+                    ),
+                    "parameters": {"retry_after": retry_after},
+                }))
+            }
+            Self::Blocked => HttpResponse::Forbidden().json(json!({
+                "ok": false,
+                "error_code": 403,
+                "description": "Forbidden: bot was blocked by the user",
+            })),
+            Self::BadRequest(description) => {
+                HttpResponse::BadRequest().json(json!({
+                    "ok": false,
+                    "error_code": 400,
+                    "description": description,
+                }))
+            }
+            Self::Timeout => {
+                tokio::time::sleep(CLIENT_TIMEOUT * 2).await;
+                HttpResponse::GatewayTimeout().finish()
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    failures: VecDeque<Failure>,
+    /// Methods of the received requests, e.g. `SendMessage`,
+    /// with their JSON parameters
+    requests: Vec<(String, serde_json::Value)>,
+}
+
+/// Fake Bot API running while it's alive
+pub(crate) struct FlakyApi {
+    addr: SocketAddr,
+    state: Arc<Mutex<State>>,
+    handle: actix_web::dev::ServerHandle,
+}
+
+/// Every method succeeds with the same text message in the same chat
+async fn answer(
+    method: web::Path<(String, String)>,
+    body: web::Bytes,
+    state: web::Data<Mutex<State>>,
+) -> HttpResponse {
+    let failure = {
+        let mut state = state.lock().unwrap();
+        let params = serde_json::from_slice(&body).unwrap_or_default();
+        state.requests.push((method.into_inner().1, params));
+        state.failures.pop_front()
+    };
+    match failure {
+        Some(failure) => failure.respond().await,
+        None => HttpResponse::Ok().json(json!({
+            "ok": true,
+            "result": {
+                "message_id": 1,
+                "date": 0,
+                "chat": {"id": 1, "type": "private", "first_name": "Test"},
+                "text": "text",
+            },
+        })),
+    }
+}
+
+impl FlakyApi {
+    /// Start the server on a free local port
+    pub(crate) fn start() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let data = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .route("/{token}/{method}", web::post().to(answer))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        tokio::spawn(server);
+        Self {
+            addr,
+            state,
+            handle,
+        }
+    }
+
+    /// Fail the next requests, in order
+    pub(crate) fn fail(&self, failures: impl IntoIterator<Item = Failure>) {
+        self.state.lock().unwrap().failures.extend(failures);
+    }
+
+    /// Methods of the requests received so far
+    pub(crate) fn methods(&self) -> Vec<String> {
+        self.requests()
+            .into_iter()
+            .map(|(method, _)| method)
+            .collect()
+    }
+
+    /// Requests received so far, the parameters are `null` unless
+    /// sent as JSON
+    pub(crate) fn requests(&self) -> Vec<(String, serde_json::Value)> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Bot sending its requests to the server
+    pub(crate) fn bot(&self) -> Bot {
+        let client = teloxide::net::default_reqwest_settings()
+            .timeout(CLIENT_TIMEOUT)
+            .build()
+            .unwrap();
+        Bot::with_client("token", client)
+            .set_api_url(Url::parse(&format!("http://{}", self.addr)).unwrap())
+    }
+}
+
+impl Drop for FlakyApi {
+    fn drop(&mut self) {
+        // Don't wait for the running requests
+        drop(self.handle.stop(false));
+    }
+}
+
+mod test {
+    use super::*;
+    use crate::err::{Category, Error};
+    use crate::tg;
+    use teloxide::types::ChatId;
+    use teloxide::{ApiError, RequestError};
+
+    #[tokio::test]
+    async fn test_failures() {
+        let api = FlakyApi::start();
+        let bot = api.bot();
+        api.fail([
+            Failure::TooManyRequests(5),
+            Failure::Blocked,
+            Failure::Timeout,
+        ]);
+        let send = || tg::send_message("text", &bot, ChatId(1));
+        assert!(matches!(
+            send().await,
+            Err(RequestError::RetryAfter(seconds)) if seconds.seconds() == 5
+        ));
+        assert!(matches!(
+            send().await,
+            Err(RequestError::Api(ApiError::BotBlocked))
+        ));
+        let err = send().await.unwrap_err();
+        assert!(
+            matches!(err, RequestError::Network(ref err) if err.is_timeout())
+        );
+        assert!(matches!(Error::from(err).category(), Category::Telegram));
+        assert_eq!(send().await.unwrap().id.0, 1);
+        assert_eq!(api.methods(), vec!["SendMessage"; 4]);
+    }
+}
@@ -1,17 +1,35 @@
+use crate::advance::format_lead;
 use crate::entity::cron_reminder;
 use crate::generic_reminder::GenericReminder;
+use crate::lang::Language;
+use crate::policy::MentionPolicy;
+use crate::settings;
+use crate::tg::{self, TgResponse};
+use chrono::{Datelike, NaiveDateTime, TimeZone, Timelike};
 use chrono_tz::Tz;
 use sea_orm::{ActiveModelTrait, IntoActiveModel};
+use teloxide::utils::markdown::escape;
 
 pub(crate) fn format_reminder<T: ActiveModelTrait + GenericReminder>(
     reminder: &T,
     user_timezone: Tz,
 ) -> String {
-    match reminder.user_id() {
-        Some(user_id) if reminder.is_group() => {
-            reminder.to_string_with_mention(user_timezone, user_id.0 as i64)
-        }
-        _ => reminder.to_string(user_timezone),
+    let creator = reminder
+        .user_id()
+        .filter(|_| reminder.policy().mention == MentionPolicy::Creator);
+    let text = if reminder.is_group()
+        && (creator.is_some() || reminder.target().is_some())
+    {
+        reminder.to_string_with_mention(
+            user_timezone,
+            creator.map(|user_id| user_id.0 as i64),
+        )
+    } else {
+        reminder.to_string(user_timezone)
+    };
+    match reminder.priority() {
+        Some(_) => format!("🔴 {}", text),
+        None => text,
     }
 }
 
@@ -34,3 +52,267 @@ pub(crate) fn format_cron_reminder(
         None => formatted_reminder,
     }
 }
+
+/// Occurrences shown when a repeating reminder is set
+const PREVIEW_LEN: usize = 4;
+
+/// Upcoming times of a repeating reminder starting from the first one,
+/// e.g. `Sat 03.02 09:00`, so that the pattern can be checked right away
+pub(crate) fn preview_times(
+    first: NaiveDateTime,
+    mut next: impl FnMut(NaiveDateTime) -> Option<NaiveDateTime>,
+    user_timezone: Tz,
+    clock_12h: bool,
+    style: settings::DateStyle,
+    now: NaiveDateTime,
+) -> Vec<String> {
+    let this_year = user_timezone.from_utc_datetime(&now).year();
+    std::iter::successors(Some(first), |&cur| next(cur))
+        .take(PREVIEW_LEN)
+        .map(|time| {
+            let time = user_timezone.from_utc_datetime(&time);
+            format!(
+                "{} {}",
+                style.format_day(time.date_naive(), time.year() != this_year),
+                settings::format_clock(time.hour(), time.minute(), clock_12h)
+            )
+        })
+        .collect()
+}
+
+/// Lines of the sorted reminders with a header before each day's ones
+/// and before the paused ones
+pub(crate) fn list_by_day(
+    sorted_reminders: Vec<Box<dyn GenericReminder>>,
+    user_timezone: Tz,
+    style: settings::DateStyle,
+    now: NaiveDateTime,
+) -> Vec<String> {
+    let this_year = user_timezone.from_utc_datetime(&now).year();
+    let mut lines = vec![];
+    let mut section = None;
+    for rem in sorted_reminders {
+        let day = user_timezone
+            .from_utc_datetime(&rem.get_time())
+            .date_naive();
+        // paused reminders go after the active ones
+        let rem_section = (!rem.is_paused()).then_some(day);
+        if section != Some(rem_section) {
+            section = Some(rem_section);
+            let header = match rem_section {
+                Some(day) => TgResponse::ListDayHeader(
+                    style.format_day(day, day.year() != this_year),
+                ),
+                None => TgResponse::ListPausedHeader,
+            };
+            lines.push(header.to_string_in(style.language));
+        }
+        let rem_str = rem.to_string(user_timezone);
+        let rem_str = if rem_str.chars().count() > tg::MESSAGE_MAX_LEN {
+            // escaping at most doubles the length
+            escape(&truncate(
+                &rem.to_unescaped_string(user_timezone),
+                tg::MESSAGE_MAX_LEN / 2,
+            ))
+        } else {
+            rem_str
+        };
+        lines.push(rem_str.replace('@', "@\u{200B}"));
+    }
+    lines
+}
+
+/// Line of a reminder listed by /today or /tomorrow
+/// with the time left to it, in whole minutes
+pub(crate) fn agenda_line(
+    rem: &dyn GenericReminder,
+    user_timezone: Tz,
+    now: NaiveDateTime,
+    lang: Language,
+) -> String {
+    let minutes_left = ((rem.get_time() - now).num_seconds().max(0) + 59) / 60;
+    let left = match minutes_left {
+        0 => TgResponse::TimeLeftNow,
+        _ => TgResponse::TimeLeft(format_lead(minutes_left * 60)),
+    };
+    format!(
+        "{} {}",
+        rem.to_string(user_timezone),
+        left.to_string_in(lang)
+    )
+    .replace('@', "@\u{200B}")
+}
+
+/// Cut the string to at most `max_len` characters,
+/// marking the cut with an ellipsis
+pub(crate) fn truncate(s: &str, max_len: usize) -> String {
+    if s.chars().count() <= max_len {
+        return s.to_owned();
+    }
+    s.chars()
+        .take(max_len.saturating_sub(1))
+        .chain(std::iter::once('…'))
+        .collect()
+}
+
+/// Split the string into a head of at most `max_len` characters, cut
+/// at a word boundary if there's one, and the overflow, if any
+pub(crate) fn split_overflow(
+    s: &str,
+    max_len: usize,
+) -> (String, Option<String>) {
+    if s.chars().count() <= max_len {
+        return (s.to_owned(), None);
+    }
+    let cut = s
+        .char_indices()
+        .nth(max_len)
+        .map_or(s.len(), |(idx, _)| idx);
+    let cut = match s[..cut].rfind(char::is_whitespace) {
+        _ if s[cut..].starts_with(char::is_whitespace) => cut,
+        Some(space) if !s[..space].trim_end().is_empty() => space,
+        _ => cut,
+    };
+    (
+        s[..cut].trim_end().to_owned(),
+        Some(s[cut..].trim_start().to_owned()),
+    )
+}
+
+/// Join lines into as few messages as possible
+/// so that none of them exceeds `max_len` characters
+pub(crate) fn split_lines(
+    lines: impl IntoIterator<Item = String>,
+    max_len: usize,
+) -> Vec<String> {
+    let mut messages = vec![];
+    let mut cur = String::new();
+    let mut cur_len = 0;
+    for line in lines {
+        let line_len = line.chars().count();
+        if cur_len != 0 && cur_len + 1 + line_len > max_len {
+            messages.push(std::mem::take(&mut cur));
+            cur_len = 0;
+        }
+        if cur_len != 0 {
+            cur.push('\n');
+            cur_len += 1;
+        }
+        cur.push_str(&line);
+        cur_len += line_len;
+    }
+    if cur_len != 0 {
+        messages.push(cur);
+    }
+    messages
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::reminder;
+    use chrono::NaiveDateTime;
+    use sea_orm::Set;
+    use test_case::test_case;
+
+    #[test_case("short", 10 => "short".to_owned() ; "fits")]
+    #[test_case("exactly10!", 10 => "exactly10!".to_owned() ; "fits exactly")]
+    #[test_case("a bit too long", 10 => "a bit too…".to_owned() ; "cut")]
+    #[test_case("приветствие", 5 => "прив…".to_owned() ; "multibyte")]
+    fn test_truncate(s: &str, max_len: usize) -> String {
+        truncate(s, max_len)
+    }
+
+    #[test_case("short", 10 => ("short".to_owned(), None) ; "fits")]
+    #[test_case("a bit too long", 10 => ("a bit too".to_owned(), Some("long".to_owned())) ; "word boundary")]
+    #[test_case("a bit\ntoo long", 7 => ("a bit".to_owned(), Some("too long".to_owned())) ; "line break")]
+    #[test_case("abcdefghij", 4 => ("abcd".to_owned(), Some("efghij".to_owned())) ; "single word")]
+    #[test_case("приветствие всем", 11 => ("приветствие".to_owned(), Some("всем".to_owned())) ; "multibyte")]
+    fn test_split_overflow(
+        s: &str,
+        max_len: usize,
+    ) -> (String, Option<String>) {
+        split_overflow(s, max_len)
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let lines = ["aaaa", "bbb", "cc", "dddddd"].map(str::to_owned);
+        assert_eq!(split_lines(lines, 8), vec!["aaaa\nbbb", "cc", "dddddd"]);
+    }
+
+    #[test_case(false => vec!["Fri 29.12 09:00", "Sat 30.12 09:00", "Sun 31.12 09:00", "Mon 01.01.2024 09:00"] ; "24h")]
+    #[test_case(true => vec!["Fri 29.12 9:00 AM", "Sat 30.12 9:00 AM", "Sun 31.12 9:00 AM", "Mon 01.01.2024 9:00 AM"] ; "12h")]
+    fn test_preview_times(clock_12h: bool) -> Vec<String> {
+        let tz = chrono_tz::Europe::Amsterdam;
+        let first = tz
+            .with_ymd_and_hms(2023, 12, 29, 9, 0, 0)
+            .unwrap()
+            .naive_utc();
+        preview_times(
+            first,
+            |cur| Some(cur + chrono::TimeDelta::days(1)),
+            tz,
+            clock_12h,
+            settings::DateStyle::default(),
+            first,
+        )
+    }
+
+    #[test]
+    fn test_preview_times_ending() {
+        let first = NaiveDateTime::default();
+        let times = preview_times(
+            first,
+            |_| None,
+            chrono_tz::UTC,
+            false,
+            settings::DateStyle::default(),
+            first,
+        );
+        assert_eq!(times, vec!["Thu 01.01 00:00"]);
+    }
+
+    #[test_case(-1, Some("alice_w"), None => "[🔔](tg://user?id=1) @alice\\_w" ; "creator and target")]
+    #[test_case(-1, None, None => "[🔔](tg://user?id=1)" ; "creator")]
+    #[test_case(-1, Some("alice_w"), Some(r#"{"mention":"nobody"}"#) => "@alice\\_w" ; "target only")]
+    #[test_case(-1, None, Some(r#"{"mention":"nobody"}"#) => "" ; "nobody")]
+    #[test_case(1, Some("alice_w"), None => "" ; "private chat")]
+    #[test_case(1, None, Some(r#"{"priority":"high"}"#) => "🔴" ; "important")]
+    #[test_case(-1, None, Some(r#"{"priority":"critical"}"#) => "🔴 [🔔](tg://user?id=1)" ; "critical in group")]
+    fn test_format_reminder_mentions(
+        chat_id: i64,
+        target: Option<&str>,
+        policy: Option<&str>,
+    ) -> String {
+        let reminder = reminder::ActiveModel {
+            id: Set(1),
+            chat_id: Set(chat_id),
+            time: Set(NaiveDateTime::default()),
+            desc: Set("report".to_owned()),
+            user_id: Set(Some(1)),
+            paused: Set(false),
+            pattern: Set(None),
+            policy: Set(policy.map(str::to_owned)),
+            version: Set(0),
+            pomodoro_id: Set(None),
+            target: Set(target.map(str::to_owned)),
+            meeting: Set(false),
+            advance: Set(None),
+            advance_time: Set(None),
+            media_kind: Set(None),
+            media_file_id: Set(None),
+            held_since: Set(None),
+            failures: Set(0),
+            retry_time: Set(None),
+            thread_id: Set(None),
+            deleted_time: Set(None),
+        };
+        let text = format_reminder(&reminder, chrono_tz::UTC);
+        let body = reminder.to_string(chrono_tz::UTC);
+        text.strip_suffix(&body)
+            .unwrap()
+            .trim_end_matches(['\n', ' '])
+            .to_owned()
+    }
+}
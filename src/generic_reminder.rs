@@ -1,7 +1,13 @@
+use crate::advance::format_lead;
+use crate::cron;
+use crate::db::LinkTarget;
 use crate::entity::{cron_reminder, reminder};
+use crate::parsers::now_time;
+use crate::policy::{NotificationPolicy, Priority};
+use crate::retry;
 use crate::serializers::Pattern;
+use crate::settings;
 use chrono::prelude::*;
-use chrono::Utc;
 use chrono_tz::Tz;
 use serde_json::from_str;
 use std::cmp::Ord;
@@ -16,22 +22,25 @@ pub(crate) trait GenericReminder {
     fn get_time(&self) -> NaiveDateTime;
     fn get_id(&self) -> Option<i64>;
     fn get_type(&self) -> &'static str;
+    fn link_target(&self) -> Option<LinkTarget>;
     fn to_string(&self, user_timezone: Tz) -> String;
+    /// Mention the creator if given, and the target if there is one
     fn to_string_with_mention(
         &self,
         user_timezone: Tz,
-        user_id: i64,
+        user_id: Option<i64>,
     ) -> String {
-        format!(
-            "[🔔](tg://user?id={})\n{}",
-            user_id,
-            self.to_string(user_timezone),
-        )
+        let mentions: Vec<_> = user_id
+            .map(|user_id| format!("[🔔](tg://user?id={})", user_id))
+            .into_iter()
+            .chain(self.target().map(|target| escape(&format!("@{}", target))))
+            .collect();
+        format!("{}\n{}", mentions.join(" "), self.to_string(user_timezone))
     }
     fn to_unescaped_string(&self, user_timezone: Tz) -> String;
     fn serialize_time_unescaped(&self, user_timezone: Tz) -> String {
         let time = user_timezone.from_utc_datetime(&self.get_time());
-        let now = Utc::now().with_timezone(&user_timezone);
+        let now = user_timezone.from_utc_datetime(&now_time());
         let mut s = String::new();
         if time.date_naive() != now.date_naive() {
             s += &format!("{:02}.{:02}", time.day(), time.month());
@@ -40,11 +49,25 @@ pub(crate) trait GenericReminder {
             }
             s += " "
         }
-        s + &format!("{:02}:{:02}", time.hour(), time.minute())
+        s + &settings::format_clock(
+            time.hour(),
+            time.minute(),
+            self.uses_12h_clock(),
+        )
     }
     fn serialize_time(&self, user_timezone: Tz) -> String {
         escape(&self.serialize_time_unescaped(user_timezone))
     }
+    fn uses_12h_clock(&self) -> bool {
+        self.user_id()
+            .is_some_and(|user_id| settings::uses_12h_clock(user_id.0 as i64))
+    }
+    /// Names and order of the weekdays of the creator
+    fn date_style(&self) -> settings::DateStyle {
+        self.user_id()
+            .map(|user_id| settings::date_style(user_id.0 as i64))
+            .unwrap_or_default()
+    }
     fn user_id(&self) -> Option<UserId>;
     fn chat_id(&self) -> ChatId;
     fn is_group(&self) -> bool {
@@ -52,6 +75,15 @@ pub(crate) trait GenericReminder {
         chat_id.is_group() || chat_id.is_channel_or_supergroup()
     }
     fn is_paused(&self) -> bool;
+    fn policy(&self) -> NotificationPolicy;
+    /// Username of the group member the reminder is for
+    fn target(&self) -> Option<String> {
+        None
+    }
+    /// How important the reminder was marked with `!!` or `!!!`
+    fn priority(&self) -> Option<Priority> {
+        self.policy().priority
+    }
 }
 
 impl GenericReminder for reminder::ActiveModel {
@@ -67,6 +99,10 @@ impl GenericReminder for reminder::ActiveModel {
         "rem"
     }
 
+    fn link_target(&self) -> Option<LinkTarget> {
+        self.get_id().map(LinkTarget::Reminder)
+    }
+
     fn to_unescaped_string(&self, user_timezone: Tz) -> String {
         let main_part = format!(
             r"{} <{}>",
@@ -76,13 +112,31 @@ impl GenericReminder for reminder::ActiveModel {
         let s = match self.pattern.clone().unwrap() {
             Some(ref s) => {
                 let pattern: Pattern = from_str(s).unwrap();
-                match pattern.to_string().as_str() {
+                match pattern.to_styled_string(self.date_style()).as_str() {
                     "" => main_part,
                     s => format!(r"{} [{}]", main_part, s),
                 }
             }
             None => main_part,
         };
+        let s = match self.advance.clone().unwrap() {
+            Some(lead) => format!("{} ⏰ {} before", s, format_lead(lead)),
+            None => s,
+        };
+        let s = match self.target() {
+            Some(target) => format!("{} → {}", s, target),
+            None => s,
+        };
+        let s = if self.meeting.clone().unwrap() {
+            format!("🤝 {}", s)
+        } else {
+            s
+        };
+        let s = if retry::is_failed(self.failures.clone().unwrap()) {
+            format!("⚠️ {}", s)
+        } else {
+            s
+        };
         if self.paused.clone().unwrap() {
             format!("⏸ {}", s)
         } else {
@@ -99,13 +153,31 @@ impl GenericReminder for reminder::ActiveModel {
         let s = match self.pattern.clone().unwrap() {
             Some(ref s) => {
                 let pattern: Pattern = from_str(s).unwrap();
-                match pattern.to_string().as_str() {
+                match pattern.to_styled_string(self.date_style()).as_str() {
                     "" => main_part,
                     s => format!(r"{} \[{}\]", main_part, escape(s)),
                 }
             }
             None => main_part,
         };
+        let s = match self.advance.clone().unwrap() {
+            Some(lead) => format!("{} ⏰ {} before", s, format_lead(lead)),
+            None => s,
+        };
+        let s = match self.target() {
+            Some(target) => format!("{} → {}", s, escape(&target)),
+            None => s,
+        };
+        let s = if self.meeting.clone().unwrap() {
+            format!("🤝 {}", s)
+        } else {
+            s
+        };
+        let s = if retry::is_failed(self.failures.clone().unwrap()) {
+            format!("⚠️ {}", s)
+        } else {
+            s
+        };
         if self.paused.clone().unwrap() {
             format!("⏸ {}", s)
         } else {
@@ -124,6 +196,27 @@ impl GenericReminder for reminder::ActiveModel {
     fn is_paused(&self) -> bool {
         self.paused.clone().unwrap()
     }
+
+    fn policy(&self) -> NotificationPolicy {
+        NotificationPolicy::from_stored(self.policy.clone().unwrap().as_deref())
+    }
+
+    fn target(&self) -> Option<String> {
+        self.target.clone().unwrap()
+    }
+}
+
+impl cron_reminder::ActiveModel {
+    /// Cron expression in the creator's words if possible
+    fn describe_cron_expr(&self) -> String {
+        let cron_expr = self.cron_expr.clone().unwrap();
+        cron::describe(
+            &cron_expr,
+            self.uses_12h_clock(),
+            self.date_style().language,
+        )
+        .unwrap_or(cron_expr)
+    }
 }
 
 impl GenericReminder for cron_reminder::ActiveModel {
@@ -139,13 +232,22 @@ impl GenericReminder for cron_reminder::ActiveModel {
         "cron_rem"
     }
 
+    fn link_target(&self) -> Option<LinkTarget> {
+        self.get_id().map(LinkTarget::CronReminder)
+    }
+
     fn to_unescaped_string(&self, user_timezone: Tz) -> String {
         let s = format!(
             "{} <{}> [{}]",
             self.serialize_time_unescaped(user_timezone),
             self.desc.clone().unwrap(),
-            self.cron_expr.clone().unwrap()
+            self.describe_cron_expr()
         );
+        let s = if retry::is_failed(self.failures.clone().unwrap()) {
+            format!("⚠️ {}", s)
+        } else {
+            s
+        };
         if self.paused.clone().unwrap() {
             format!("⏸ {}", s)
         } else {
@@ -158,8 +260,13 @@ impl GenericReminder for cron_reminder::ActiveModel {
             r"{} <{}\> \[{}\]",
             self.serialize_time(user_timezone),
             bold(&escape(&self.desc.clone().unwrap())),
-            escape(&self.cron_expr.clone().unwrap())
+            escape(&self.describe_cron_expr())
         );
+        let s = if retry::is_failed(self.failures.clone().unwrap()) {
+            format!("⚠️ {}", s)
+        } else {
+            s
+        };
         if self.paused.clone().unwrap() {
             format!("⏸ {}", s)
         } else {
@@ -178,6 +285,10 @@ impl GenericReminder for cron_reminder::ActiveModel {
     fn is_paused(&self) -> bool {
         self.paused.clone().unwrap()
     }
+
+    fn policy(&self) -> NotificationPolicy {
+        NotificationPolicy::from_stored(self.policy.clone().unwrap().as_deref())
+    }
 }
 
 impl Ord for dyn GenericReminder {
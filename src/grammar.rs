@@ -1,12 +1,15 @@
 use bitmask_enum::bitmask;
 use nonempty::{nonempty, NonEmpty};
 
-use pest::{iterators::Pair, Parser};
+use pest::{error::InputLocation, iterators::Pair, Parser};
 
 #[derive(Parser)]
 #[grammar = "grammars/reminder.pest"]
 struct ReminderParser;
 
+/// Most firings a countdown chained with "then" may have
+const MAX_COUNTDOWN_FIRINGS: usize = 100;
+
 #[derive(Debug, Default)]
 pub(crate) struct HoleyDate {
     pub(crate) year: Option<i32>,
@@ -14,7 +17,7 @@ pub(crate) struct HoleyDate {
     pub(crate) day: Option<u32>,
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub(crate) struct Interval {
     pub(crate) years: i32,
     pub(crate) months: u32,
@@ -118,6 +121,8 @@ pub(crate) enum TimePattern {
 pub(crate) struct Recurrence {
     pub(crate) dates_patterns: NonEmpty<DatePattern>,
     pub(crate) time_patterns: Vec<TimePattern>,
+    /// Number of times to fire before stopping
+    pub(crate) limit: Option<u32>,
 }
 
 #[derive(Debug, Default)]
@@ -125,18 +130,40 @@ pub(crate) struct Countdown {
     pub(crate) durations: Vec<Interval>,
 }
 
+#[derive(Debug, Default)]
+pub(crate) struct CountdownUntil {
+    pub(crate) date: HoleyDate,
+    pub(crate) time: Time,
+    pub(crate) milestones: bool,
+}
+
 #[derive(Debug)]
 pub(crate) enum ReminderPattern {
     Recurrence(Recurrence),
     Countdown(Countdown),
+    CountdownUntil(CountdownUntil),
 }
 
 #[derive(Debug, Default)]
 pub(crate) struct Reminder {
+    pub(crate) meeting: bool,
+    /// How long before each occurrence to warn about it
+    pub(crate) advance: Option<Interval>,
+    /// Exclamation marks marking the reminder important
+    pub(crate) priority: Option<Priority>,
+    pub(crate) target: Option<Target>,
     pub(crate) description: Option<Description>,
     pub(crate) pattern: Option<ReminderPattern>,
 }
 
+/// `!!` or `!!!`
+#[derive(Debug, Default)]
+pub(crate) struct Priority(pub(crate) String);
+
+/// Username of the user to mention, without the `@`
+#[derive(Debug, Default)]
+pub(crate) struct Target(pub(crate) String);
+
 #[derive(Debug, Default)]
 pub(crate) struct Description(pub(crate) String);
 
@@ -170,6 +197,21 @@ impl Parse for HoleyDate {
     }
 }
 
+impl Interval {
+    /// Interval of both of the intervals one after another
+    fn plus(&self, other: &Self) -> Self {
+        Self {
+            years: self.years.saturating_add(other.years),
+            months: self.months.saturating_add(other.months),
+            weeks: self.weeks.saturating_add(other.weeks),
+            days: self.days.saturating_add(other.days),
+            hours: self.hours.saturating_add(other.hours),
+            minutes: self.minutes.saturating_add(other.minutes),
+            seconds: self.seconds.saturating_add(other.seconds),
+        }
+    }
+}
+
 impl Parse for Interval {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ()> {
         let mut interval = Self::default();
@@ -286,6 +328,48 @@ impl Parse for DateRange {
                     date_range.date_divisor =
                         DateDivisor::Interval(DateInterval::parse(rec)?);
                 }
+                Rule::every_weekend => {
+                    date_range.date_divisor = DateDivisor::Weekdays(
+                        Weekdays::Saturday | Weekdays::Sunday,
+                    );
+                }
+                Rule::every_workday => {
+                    date_range.date_divisor = DateDivisor::Weekdays(
+                        Weekdays::Monday
+                            | Weekdays::Tuesday
+                            | Weekdays::Wednesday
+                            | Weekdays::Thursday
+                            | Weekdays::Friday,
+                    );
+                }
+                Rule::every_day => {
+                    date_range.date_divisor =
+                        DateDivisor::Interval(DateInterval {
+                            days: 1,
+                            ..Default::default()
+                        });
+                }
+                Rule::every_week => {
+                    date_range.date_divisor =
+                        DateDivisor::Interval(DateInterval {
+                            weeks: 1,
+                            ..Default::default()
+                        });
+                }
+                Rule::every_month => {
+                    date_range.date_divisor =
+                        DateDivisor::Interval(DateInterval {
+                            months: 1,
+                            ..Default::default()
+                        });
+                }
+                Rule::every_year => {
+                    date_range.date_divisor =
+                        DateDivisor::Interval(DateInterval {
+                            years: 1,
+                            ..Default::default()
+                        });
+                }
                 Rule::weekdays_range => {
                     let weekdays = match date_range.date_divisor {
                         DateDivisor::Weekdays(ref mut w) => w,
@@ -411,6 +495,7 @@ impl Default for Recurrence {
         Self {
             dates_patterns: nonempty![DatePattern::Point(HoleyDate::default())],
             time_patterns: vec![],
+            limit: None,
         }
     }
 }
@@ -440,6 +525,11 @@ impl Parse for Recurrence {
                         .time_patterns
                         .push(TimePattern::Range(TimeRange::parse(rec)?));
                 }
+                Rule::recurrence_limit => {
+                    let count = rec.into_inner().next().ok_or(())?;
+                    recurrence.limit =
+                        Some(count.as_str().parse().map_err(|_| ())?);
+                }
                 _ => unreachable!(),
             }
         }
@@ -459,6 +549,50 @@ impl Parse for Countdown {
                 Rule::interval => {
                     countdown.durations.push(Interval::parse(rec)?);
                 }
+                Rule::countdown_then => {
+                    let mut interval = Interval::default();
+                    let mut times = 1;
+                    for rec in rec.into_inner() {
+                        match rec.as_rule() {
+                            Rule::interval => interval = Interval::parse(rec)?,
+                            Rule::recurrence_limit => {
+                                let count = rec.into_inner().next().ok_or(())?;
+                                times =
+                                    count.as_str().parse().map_err(|_| ())?;
+                            }
+                            _ => unreachable!(),
+                        }
+                    }
+                    if countdown.durations.len() + times > MAX_COUNTDOWN_FIRINGS
+                    {
+                        return Err(());
+                    }
+                    for _ in 0..times {
+                        let last = countdown.durations.last().ok_or(())?;
+                        countdown.durations.push(last.plus(&interval));
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        Ok(countdown)
+    }
+}
+
+impl Parse for CountdownUntil {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ()> {
+        let mut countdown = Self::default();
+        for rec in pair.into_inner() {
+            match rec.as_rule() {
+                Rule::dates_point => {
+                    countdown.date = HoleyDate::parse(rec)?;
+                }
+                Rule::time_point => {
+                    countdown.time = Time::parse(rec)?;
+                }
+                Rule::countdown_milestones => {
+                    countdown.milestones = true;
+                }
                 _ => unreachable!(),
             }
         }
@@ -472,11 +606,38 @@ impl Parse for Description {
     }
 }
 
+impl Parse for Priority {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ()> {
+        Ok(Self(pair.as_str().to_string()))
+    }
+}
+
+impl Parse for Target {
+    fn parse(pair: Pair<'_, Rule>) -> Result<Self, ()> {
+        let username = pair.into_inner().next().ok_or(())?;
+        Ok(Self(username.as_str().to_string()))
+    }
+}
+
 impl Parse for Reminder {
     fn parse(pair: Pair<'_, Rule>) -> Result<Self, ()> {
         let mut reminder = Self::default();
         for rec in pair.into_inner() {
             match rec.as_rule() {
+                Rule::meeting => {
+                    reminder.meeting = true;
+                }
+                Rule::advance => {
+                    reminder.advance = Some(Interval::parse(
+                        rec.into_inner().next().ok_or(())?,
+                    )?);
+                }
+                Rule::priority => {
+                    reminder.priority = Some(Priority::parse(rec)?);
+                }
+                Rule::target => {
+                    reminder.target = Some(Target::parse(rec)?);
+                }
                 Rule::description => {
                     reminder.description = Some(Description::parse(rec)?);
                 }
@@ -490,6 +651,11 @@ impl Parse for Reminder {
                         Countdown::parse(rec)?,
                     ));
                 }
+                Rule::countdown_until => {
+                    reminder.pattern = Some(ReminderPattern::CountdownUntil(
+                        CountdownUntil::parse(rec)?,
+                    ));
+                }
                 Rule::EOI => {}
                 _ => unreachable!(),
             }
@@ -498,13 +664,102 @@ impl Parse for Reminder {
     }
 }
 
-pub(crate) fn parse_reminder(s: &str) -> Result<Reminder, ()> {
-    Reminder::parse(
-        ReminderParser::parse(Rule::reminder, s)
-            .map_err(|err| {
-                log::debug!("{}", err);
-            })?
-            .next()
-            .ok_or(())?,
-    )
+/// Why a text isn't a reminder
+#[derive(Debug, PartialEq)]
+pub(crate) enum ParseError {
+    /// The text stops matching the grammar at the byte,
+    /// where one of the rules was expected
+    Syntax { pos: usize, expected: Vec<Rule> },
+    /// The text matches the grammar, but not the calendar,
+    /// e.g. `31.02 10:00`
+    Invalid,
+}
+
+impl ParseError {
+    /// Line of the text the syntax error is in with a caret under it
+    pub(crate) fn pointer(&self, s: &str) -> Option<String> {
+        let Self::Syntax { pos, .. } = *self else {
+            return None;
+        };
+        let line_start = s[..pos].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = s[pos..].find('\n').map_or(s.len(), |idx| pos + idx);
+        let column = s[line_start..pos].chars().count();
+        let line: String =
+            s[line_start..line_end].chars().take(column + 20).collect();
+        Some(format!("{}\n{}^", line.trim_end(), " ".repeat(column)))
+    }
+
+    /// What could go where the syntax error is, `None` if there's
+    /// nothing more helpful to say than that the text isn't a reminder
+    pub(crate) fn hint(&self) -> Option<String> {
+        let Self::Syntax { expected, .. } = self else {
+            return None;
+        };
+        let mut hints: Vec<&str> = vec![];
+        for rule in expected {
+            let hint = match rule {
+                Rule::every_weekend
+                | Rule::every_workday
+                | Rule::every_day
+                | Rule::every_week
+                | Rule::every_month
+                | Rule::every_year => "a repetition like daily or weekdays",
+                Rule::weekday_from | Rule::weekday_to => {
+                    "a weekday like mon or friday"
+                }
+                Rule::interval | Rule::time_interval => {
+                    "an interval like 1h30m"
+                }
+                Rule::date_interval => "an interval like 2d, 1w or 1mo",
+                Rule::year => "a year like 2025",
+                Rule::day => "a day from 1 to 31",
+                Rule::month => "a month from 1 to 12",
+                Rule::hour => "an hour from 0 to 23",
+                Rule::minute | Rule::second => "minutes from 0 to 59",
+                Rule::recurrence_limit_count => "a number of times like x3",
+                _ => continue,
+            };
+            if !hints.contains(&hint) {
+                hints.push(hint);
+            }
+        }
+        match hints.split_last() {
+            Some((last, [])) => Some(last.to_string()),
+            Some((last, rest)) => {
+                Some(format!("{} or {}", rest.join(", "), last))
+            }
+            None => None,
+        }
+    }
+}
+
+/// Interval like `1w` or `2mo3d` to move the occurrences of a reminder
+/// that much later
+pub(crate) fn parse_shift(s: &str) -> Option<DateInterval> {
+    ReminderParser::parse(Rule::shift, s)
+        .ok()?
+        .next()?
+        .into_inner()
+        .find(|pair| pair.as_rule() == Rule::date_interval)
+        .and_then(|pair| DateInterval::parse(pair).ok())
+}
+
+pub(crate) fn parse_reminder(s: &str) -> Result<Reminder, ParseError> {
+    let mut pairs =
+        ReminderParser::parse(Rule::reminder, s).map_err(|err| {
+            tracing::debug!("{}", err);
+            let pos = match err.location {
+                InputLocation::Pos(pos) | InputLocation::Span((pos, _)) => pos,
+            };
+            let expected = match err.variant {
+                pest::error::ErrorVariant::ParsingError {
+                    positives, ..
+                } => positives,
+                pest::error::ErrorVariant::CustomError { .. } => vec![],
+            };
+            ParseError::Syntax { pos, expected }
+        })?;
+    pairs.next().ok_or(ParseError::Invalid).and_then(|pair| {
+        Reminder::parse(pair).map_err(|()| ParseError::Invalid)
+    })
 }
@@ -1,9 +1,14 @@
 use chrono_tz::Tz;
-use dptree::case;
+use dptree::{
+    case,
+    di::{DependencyMap, DependencySupplier},
+    HandlerDescription,
+};
+use std::{ops::ControlFlow, sync::Arc};
 use teloxide::{
-    dispatching::{dialogue, UpdateHandler},
+    dispatching::{dialogue, DpHandlerDescription, UpdateHandler},
     prelude::*,
-    types::Location,
+    types::{Document, Location, MessageId},
     utils::command::BotCommands,
 };
 
@@ -11,13 +16,27 @@ use teloxide::{
 use teloxide::dispatching::dialogue::ErasedStorage;
 #[cfg(test)]
 use teloxide::dispatching::dialogue::InMemStorage;
+use tracing::Instrument;
 
 use crate::{
+    account, bulk,
+    cli::{self, Feature},
     controller::{
-        EditMode, ReminderUpdate, TgCallbackController, TgMessageController,
+        EditMode, OnboardingStep, ReminderUpdate, TgCallbackController,
+        TgMessageController,
     },
-    err::Error,
+    db::LinkTarget,
+    err::{Category, Error},
+    lang::Language,
+    manage::{self, Action},
+    meeting::{self, Answer},
+    parsers, picker, series,
+    settings::{self, Setting},
+    shortcut::{self, Shortcut},
+    text_menu::{self, Menu},
+    tg::{self, TgResponse},
     tz::{self, get_timezone_name_of_location},
+    undo,
 };
 
 #[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
@@ -27,9 +46,44 @@ pub(crate) enum State {
     Edit {
         id: i64,
         mode: EditMode,
+        #[serde(default)]
+        version: i32,
     },
     EditCron {
         id: i64,
+        #[serde(default)]
+        version: i32,
+    },
+    OnboardingTimezone,
+    OnboardingLanguage,
+    OnboardingSampleReminder,
+    /// Waiting for a note to the occurrence marked done
+    OccurrenceNote {
+        occ_id: i64,
+    },
+    AiConfirm {
+        text: String,
+        /// Message with the suggestion to confirm
+        msg_id: i32,
+    },
+    /// Waiting for the number of an option of the text menu
+    TextMenu {
+        menu: Menu,
+    },
+    /// Waiting for the name of a place or an offset to search
+    /// the timezones by
+    TimezoneSearch,
+    /// Waiting for the description of the reminder set with /new,
+    /// after the beginning of it with the picked time
+    NewReminder {
+        time: String,
+    },
+    /// Waiting for the answer whether to set a reminder that is
+    /// already set once more
+    DuplicateConfirm {
+        text: String,
+        /// Message with the question
+        msg_id: i32,
     },
 }
 
@@ -43,136 +97,583 @@ type MyDialogue = Dialogue<State, MyStorage>;
 #[derive(BotCommands, Clone)]
 #[command(description = "Commands:", rename_rule = "lowercase")]
 pub(crate) enum Command {
-    #[command(description = "list the set reminders")]
-    List,
+    #[command(
+        description = "list the set reminders, e.g. /list today, /list paused or /list <text>"
+    )]
+    List(String),
+    #[command(description = "show today's reminders")]
+    Today,
+    #[command(description = "show tomorrow's reminders")]
+    Tomorrow,
     #[command(description = "choose reminders to delete")]
     Delete,
+    #[command(
+        description = "delete all the reminders or the matching ones, e.g. /deleteall paused or /deleteall before 01.01.2025"
+    )]
+    DeleteAll(String),
     #[command(description = "choose reminders to edit")]
     Edit,
-    #[command(description = "cancel editing")]
+    #[command(description = "set a reminder step by step")]
+    New,
+    #[command(description = "cancel editing or setting a reminder")]
     Cancel,
     #[command(description = "choose reminders to pause")]
     Pause,
+    #[command(
+        description = "pause all the reminders or the matching ones, e.g. /pauseall week"
+    )]
+    PauseAll(String),
+    #[command(
+        description = "choose a repeating reminder to skip the next time of"
+    )]
+    Skip,
     #[command(description = "set a new reminder")]
     Set(String),
     #[command(description = "select a timezone")]
     SetTimezone,
+    #[command(
+        description = "set the group's timezone overriding the members' ones, e.g. /setchattimezone Europe/Berlin or /setchattimezone reset"
+    )]
+    SetChatTimezone(String),
     #[command(description = "show your timezone")]
     Timezone,
+    #[command(
+        description = "change the clock, notifications, snoozing and menus"
+    )]
+    Settings,
+    #[command(
+        description = "set the hours without notifications, e.g. /quiet 23:00-08:00 or /quiet off"
+    )]
+    Quiet(String),
+    #[command(
+        description = "change the times of morning, afternoon, evening and night, e.g. /daytimes evening 20:00 or /daytimes evening default"
+    )]
+    DayTimes(String),
+    #[command(
+        description = "send the upcoming reminders every morning or Monday, e.g. /digest daily 8:00, /digest weekly 9:00 or /digest off"
+    )]
+    Digest(String),
+    #[command(
+        description = "pause all the notifications of the chat until the date, e.g. /vacation until 15.08, add digest to sum up the missed reminders then, or end it with /vacation off"
+    )]
+    Vacation(String),
+    #[command(
+        description = "send the reminder replied to to another chat as well, e.g. /broadcast -1001234567890, or stop with /broadcast stop <chat id>"
+    )]
+    Broadcast(String),
+    #[command(
+        description = "save a reminder to set again by name, e.g. /savetemplate pills daily 9:00 take pills"
+    )]
+    SaveTemplate(String),
+    #[command(
+        description = "set a saved reminder, e.g. /t pills, or choose one of the saved"
+    )]
+    T(String),
     #[command(description = "show this text")]
     Help,
+    #[command(description = "share a read-only link to the reminders")]
+    Share(String),
+    #[command(description = "revoke the shared links")]
+    Unshare,
+    #[command(
+        description = "get a link to see the reminders in a calendar app, /calendar reset changes it, /calendar off turns it off"
+    )]
+    Calendar(String),
+    #[command(
+        description = "get a token for managing the reminders over the HTTP API, /apitoken off revokes it"
+    )]
+    ApiToken(String),
+    #[command(description = "export the reminders to a file")]
+    Export,
+    #[command(description = "claim or delete the reminders without an owner")]
+    Orphans,
+    #[command(description = "copy the reminder whose message is replied to")]
+    Copy,
+    #[command(description = "choose deleted reminders to restore")]
+    Trash,
+    #[command(description = "restore the reminder deleted last")]
+    Restore,
+    #[command(description = "show the reminders marked done lately")]
+    History,
+    #[command(description = "check my permissions in this group")]
+    CheckSetup,
+    #[command(
+        description = "link this group to the private chat, where /list all lists your reminders from the linked groups"
+    )]
+    Link,
+    #[command(description = "show this year's bills")]
+    Stats,
+    #[command(description = "start focus sessions, e.g. /pomodoro 25 5 x4")]
+    Pomodoro(String),
+    #[cfg_attr(not(feature = "ai"), command(hide))]
+    #[command(description = "toggle AI-assisted parsing of reminders")]
+    Ai,
     #[command(description = "start")]
-    Start,
-}
-
-pub(crate) fn get_handler(
-) -> UpdateHandler<Box<dyn std::error::Error + Send + Sync + 'static>> {
-    dialogue::enter::<Update, MyStorage, State, _>()
-        .branch(
-            Update::filter_message()
-                .filter_command::<Command>()
-                .filter_map(TgMessageController::from_msg)
-                .branch(case![Command::Help].endpoint(help_handler))
-                .branch(
-                    case![Command::Start]
-                        .branch(
-                            dptree::filter(|msg: Message| {
-                                msg.chat.id.is_user()
-                            })
-                            .endpoint(start_handler),
-                        )
-                        .endpoint(start_group_handler),
-                )
-                .branch(
-                    case![Command::SetTimezone].endpoint(set_timezone_handler),
-                )
-                .branch(
-                    dptree::filter_map_async(get_user_timezone)
-                        .branch(case![Command::List].endpoint(list_handler))
-                        .branch(
-                            case![Command::Timezone].endpoint(timezone_handler),
-                        )
-                        .branch(case![Command::Delete].endpoint(delete_handler))
-                        .branch(case![Command::Edit].endpoint(edit_handler))
-                        .branch(case![Command::Cancel].endpoint(cancel_handler))
-                        .branch(case![Command::Pause].endpoint(pause_handler))
-                        .branch(case![Command::Set(text)].endpoint(set_handler))
-                        .endpoint(incorrect_request_handler),
-                )
-                .endpoint(set_timezone_handler),
-        )
-        .branch(
-            Update::filter_message()
-                .filter(|msg: Message| msg.chat.id.is_user())
-                .filter_map(TgMessageController::from_msg)
-                .branch(
-                    dptree::filter_map(|msg: Message| msg.location().copied())
+    Start(String),
+}
+
+type HandlerError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Surface the error a handler ended with in the chat of the update
+async fn report_error(bot: &Bot, update: &Update, err: HandlerError) {
+    let category = err
+        .downcast_ref::<Error>()
+        .map(Error::category)
+        .or_else(|| {
+            err.is::<teloxide::RequestError>()
+                .then_some(Category::Telegram)
+        })
+        .unwrap_or(Category::Internal);
+    let response = match category {
+        Category::User(response) => response,
+        Category::Internal => {
+            tracing::error!("{}", err);
+            TgResponse::InternalError
+        }
+        Category::Telegram => {
+            tracing::error!("{}", err);
+            return;
+        }
+    };
+    let lang = update.from().map_or_else(Language::default, |user| {
+        settings::language(user.id.0 as i64)
+    });
+    if let Some(chat) = update.chat() {
+        tg::send_silent_message(&response.to_string_in(lang), bot, chat.id)
+            .await
+            .map(|_| ())
+            .unwrap_or_else(|err| tracing::error!("{}", err));
+    }
+}
+
+/// Branch of a feature, left out of the tree if the operator disabled it
+fn feature_branch(
+    feature: Feature,
+    handler: UpdateHandler<HandlerError>,
+) -> UpdateHandler<HandlerError> {
+    if cli::feature_enabled(feature) {
+        handler
+    } else {
+        dptree::filter(|| false)
+    }
+}
+
+/// Make every handler end with either a response or a logged error,
+/// logging the records of the update along with its chat and user
+fn report_errors() -> UpdateHandler<HandlerError> {
+    dptree::from_fn_with_description(
+        DpHandlerDescription::entry(),
+        |deps: DependencyMap, cont| async move {
+            let bot: Arc<Bot> = deps.get();
+            let update: Arc<Update> = deps.get();
+            let span = tracing::info_span!(
+                "update",
+                id = update.id.0,
+                chat_id = update.chat().map(|chat| chat.id.0),
+                user_id = update.from().map(|user| user.id.0),
+            );
+            match cont(deps).instrument(span).await {
+                ControlFlow::Break(Err(err)) => {
+                    report_error(&bot, &update, err).await;
+                    ControlFlow::Break(Ok(()))
+                }
+                flow => flow,
+            }
+        },
+    )
+}
+
+pub(crate) fn get_handler() -> UpdateHandler<HandlerError> {
+    report_errors().chain(
+        dialogue::enter::<Update, MyStorage, State, _>()
+            .branch(
+                Update::filter_message()
+                    .filter_command::<Command>()
+                    .filter_map(TgMessageController::from_msg)
+                    .branch(case![Command::Help].endpoint(help_handler))
+                    .branch(case![Command::Settings].endpoint(settings_handler))
+                    .branch(case![Command::Quiet(args)].endpoint(quiet_handler))
+                    .branch(
+                        case![Command::DayTimes(args)]
+                            .endpoint(day_times_handler),
+                    )
+                    .branch(
+                        case![Command::CheckSetup]
+                            .endpoint(check_setup_handler),
+                    )
+                    .branch(case![Command::Link].endpoint(link_handler))
+                    .branch(
+                        case![Command::Start(payload)]
+                            .branch(
+                                dptree::filter(|msg: Message| {
+                                    msg.chat.id.is_user()
+                                })
+                                .branch(
+                                    dptree::filter_map(|payload: String| {
+                                        manage::parse_payload(&payload)
+                                    })
+                                    .endpoint(manage_start_handler),
+                                )
+                                .branch(
+                                    dptree::filter_map(|payload: String| {
+                                        shortcut::parse_payload(&payload)
+                                    })
+                                    .endpoint(shortcut_start_handler),
+                                )
+                                .branch(
+                                    dptree::filter_map(|payload: String| {
+                                        account::parse_payload(&payload)
+                                    })
+                                    .endpoint(link_start_handler),
+                                )
+                                .endpoint(start_handler),
+                            )
+                            .endpoint(start_group_handler),
+                    )
+                    .branch(
+                        case![Command::SetTimezone]
+                            .endpoint(set_timezone_handler),
+                    )
+                    .branch(
+                        case![Command::SetChatTimezone(args)]
+                            .endpoint(set_chat_timezone_handler),
+                    )
+                    .branch(
+                        dptree::filter_map_async(get_user_timezone)
+                            .branch(
+                                case![Command::List(args)]
+                                    .endpoint(list_handler),
+                            )
+                            .branch(
+                                case![Command::Today].endpoint(today_handler),
+                            )
+                            .branch(
+                                case![Command::Tomorrow]
+                                    .endpoint(tomorrow_handler),
+                            )
+                            .branch(
+                                case![Command::Timezone]
+                                    .endpoint(timezone_handler),
+                            )
+                            .branch(
+                                case![Command::Delete].endpoint(delete_handler),
+                            )
+                            .branch(case![Command::Edit].endpoint(edit_handler))
+                            .branch(case![Command::New].endpoint(new_handler))
+                            .branch(
+                                case![Command::Cancel].endpoint(cancel_handler),
+                            )
+                            .branch(
+                                case![Command::Pause].endpoint(pause_handler),
+                            )
+                            .branch(case![Command::Skip].endpoint(skip_handler))
+                            .branch(
+                                case![Command::DeleteAll(args)]
+                                    .endpoint(delete_all_handler),
+                            )
+                            .branch(
+                                case![Command::PauseAll(args)]
+                                    .endpoint(pause_all_handler),
+                            )
+                            .branch(
+                                case![Command::Set(text)].endpoint(set_handler),
+                            )
+                            .branch(
+                                case![Command::Stats].endpoint(stats_handler),
+                            )
+                            .branch(
+                                case![Command::Digest(args)]
+                                    .endpoint(digest_handler),
+                            )
+                            .branch(
+                                case![Command::Vacation(args)]
+                                    .endpoint(vacation_handler),
+                            )
+                            .branch(
+                                case![Command::Broadcast(args)]
+                                    .endpoint(broadcast_handler),
+                            )
+                            .branch(
+                                case![Command::SaveTemplate(args)]
+                                    .endpoint(save_template_handler),
+                            )
+                            .branch(
+                                case![Command::T(args)]
+                                    .endpoint(use_template_handler),
+                            )
+                            .branch(
+                                case![Command::History]
+                                    .endpoint(history_handler),
+                            )
+                            .branch(
+                                case![Command::Orphans]
+                                    .endpoint(orphans_handler),
+                            )
+                            .branch(case![Command::Copy].endpoint(copy_handler))
+                            .branch(
+                                case![Command::Trash].endpoint(trash_handler),
+                            )
+                            .branch(
+                                case![Command::Restore]
+                                    .endpoint(restore_handler),
+                            )
+                            .branch(feature_branch(
+                                Feature::Export,
+                                case![Command::Export].endpoint(export_handler),
+                            ))
+                            .branch(feature_branch(
+                                Feature::Http,
+                                dptree::entry()
+                                    .branch(
+                                        case![Command::Share(args)]
+                                            .endpoint(share_handler),
+                                    )
+                                    .branch(
+                                        case![Command::Unshare]
+                                            .endpoint(unshare_handler),
+                                    )
+                                    .branch(
+                                        case![Command::Calendar(args)]
+                                            .endpoint(calendar_feed_handler),
+                                    )
+                                    .branch(
+                                        case![Command::ApiToken(args)]
+                                            .endpoint(api_token_handler),
+                                    ),
+                            ))
+                            .branch(feature_branch(
+                                Feature::Pomodoro,
+                                case![Command::Pomodoro(args)]
+                                    .endpoint(pomodoro_handler),
+                            ))
+                            .branch(feature_branch(
+                                Feature::Ai,
+                                case![Command::Ai].endpoint(ai_handler),
+                            ))
+                            .endpoint(incorrect_request_handler),
+                    )
+                    .endpoint(set_timezone_handler),
+            )
+            .branch(
+                Update::filter_message()
+                    .filter(|msg: Message| msg.chat.id.is_user())
+                    .filter_map(TgMessageController::from_msg)
+                    .branch(
+                        dptree::filter_map(|msg: Message| {
+                            msg.location().copied()
+                        })
                         .endpoint(location_handler),
-                )
-                .branch(
-                    dptree::filter_map_async(get_user_timezone)
-                        .branch(
-                            dptree::filter_map(|msg: Message| {
-                                msg.text().map(|text| text.to_owned())
-                            })
-                            .branch(
-                                case![State::Edit { id, mode }]
-                                    .endpoint(edit_message_handler),
-                            )
-                            .branch(
-                                case![State::EditCron { id }]
-                                    .endpoint(edit_cron_message_handler),
-                            )
-                            .endpoint(message_handler),
-                        )
-                        .endpoint(incorrect_request_handler),
-                )
-                .endpoint(set_timezone_handler),
-        )
-        .branch(
-            Update::filter_edited_message()
-                .filter_command::<Command>()
-                .filter_map(TgMessageController::from_msg)
-                .branch(
-                    dptree::filter_map_async(get_user_timezone)
-                        .branch(
-                            case![Command::Set(text)]
-                                .endpoint(set_edited_handler),
-                        )
-                        .endpoint(incorrect_request_handler),
-                )
-                .endpoint(set_timezone_handler),
-        )
-        .branch(
-            Update::filter_edited_message()
-                .filter(|msg: Message| msg.chat.id.is_user())
-                .filter_map(TgMessageController::from_msg)
-                .branch(
-                    dptree::filter_map_async(get_user_timezone)
-                        .endpoint(edited_message_handler),
-                )
-                .endpoint(set_timezone_handler),
-        )
-        .branch(
-            Update::filter_callback_query()
-                .filter_map(TgCallbackController::new)
-                .map(|cb_ctl: TgCallbackController| cb_ctl.msg_ctl)
-                .filter_map(|cb_query: CallbackQuery| cb_query.data)
-                .branch(
-                    dptree::filter(|cb_data: String| {
-                        cb_data.starts_with("seltz::")
-                    })
-                    .endpoint(select_timezone_handler),
-                )
-                .branch(
-                    dptree::filter_map_async(get_user_timezone)
-                        .endpoint(callback_handler),
-                ),
-        )
+                    )
+                    .branch(
+                        dptree::filter_map_async(get_user_timezone)
+                            .branch(feature_branch(
+                                Feature::Import,
+                                dptree::filter_map(|msg: Message| {
+                                    msg.document().cloned()
+                                })
+                                .endpoint(document_handler),
+                            ))
+                            .branch(
+                                dptree::filter_map(|msg: Message| {
+                                    msg.text().map(|text| text.to_owned())
+                                })
+                                .branch(
+                                    case![State::Edit { id, mode, version }]
+                                        .endpoint(edit_message_handler),
+                                )
+                                .branch(
+                                    case![State::EditCron { id, version }]
+                                        .endpoint(edit_cron_message_handler),
+                                )
+                                .branch(
+                                    case![State::OnboardingSampleReminder]
+                                        .endpoint(onboarding_message_handler),
+                                )
+                                .branch(
+                                    case![State::OccurrenceNote { occ_id }]
+                                        .endpoint(occurrence_note_handler),
+                                )
+                                .branch(
+                                    case![State::TextMenu { menu }]
+                                        .endpoint(text_menu_handler),
+                                )
+                                .branch(
+                                    case![State::NewReminder { time }]
+                                        .endpoint(new_reminder_handler),
+                                )
+                                .branch(
+                                    case![State::TimezoneSearch]
+                                        .endpoint(timezone_search_handler),
+                                )
+                                .endpoint(message_handler),
+                            )
+                            .endpoint(incorrect_request_handler),
+                    )
+                    .endpoint(set_timezone_handler),
+            )
+            .branch(
+                Update::filter_edited_message()
+                    .filter_command::<Command>()
+                    .filter_map(TgMessageController::from_msg)
+                    .branch(
+                        dptree::filter_map_async(get_user_timezone)
+                            .branch(
+                                case![Command::Set(text)]
+                                    .endpoint(set_edited_handler),
+                            )
+                            .endpoint(incorrect_request_handler),
+                    )
+                    .endpoint(set_timezone_handler),
+            )
+            .branch(
+                Update::filter_edited_message()
+                    .filter(|msg: Message| msg.chat.id.is_user())
+                    .filter_map(TgMessageController::from_msg)
+                    .branch(
+                        dptree::filter_map_async(get_user_timezone)
+                            .endpoint(edited_message_handler),
+                    )
+                    .endpoint(set_timezone_handler),
+            )
+            .branch(
+                Update::filter_callback_query()
+                    .filter_map(TgCallbackController::new)
+                    .map(|cb_ctl: TgCallbackController| cb_ctl.msg_ctl)
+                    .filter_map(|cb_query: CallbackQuery| cb_query.data)
+                    .branch(
+                        dptree::filter(|cb_data: String| {
+                            cb_data.starts_with("seltz::")
+                        })
+                        .endpoint(select_timezone_handler),
+                    )
+                    .branch(
+                        dptree::filter_map(|cb_data: String| {
+                            cb_data
+                                .strip_prefix("donerem::occ::")
+                                .and_then(|x| x.parse::<i64>().ok())
+                        })
+                        .endpoint(done_handler),
+                    )
+                    .branch(
+                        dptree::filter(|cb_data: String| {
+                            cb_data == "donerem::skipnote"
+                        })
+                        .endpoint(skip_note_handler),
+                    )
+                    .branch(
+                        dptree::filter_map(|cb_data: String| {
+                            cb_data
+                                .strip_prefix("snooze::")
+                                .and_then(|x| x.parse::<i64>().ok())
+                        })
+                        .endpoint(snooze_handler),
+                    )
+                    .branch(
+                        dptree::filter_map(|cb_data: String| {
+                            settings::parse_callback(&cb_data)
+                        })
+                        .endpoint(change_setting_handler),
+                    )
+                    .branch(
+                        dptree::filter_map(|cb_data: String| {
+                            cb_data
+                                .strip_prefix("pomodoro::stop::")
+                                .and_then(|x| x.parse::<i64>().ok())
+                        })
+                        .endpoint(stop_pomodoro_handler),
+                    )
+                    .branch(
+                        dptree::filter_map(|cb_data: String| {
+                            cb_data
+                                .strip_prefix("bill::paid::")
+                                .and_then(|x| x.parse::<i64>().ok())
+                        })
+                        .endpoint(bill_paid_handler),
+                    )
+                    .branch(
+                        dptree::filter_map(|cb_data: String| {
+                            meeting::parse_callback(&cb_data)
+                        })
+                        .endpoint(rsvp_handler),
+                    )
+                    .branch(
+                        dptree::filter_map(|cb_data: String| {
+                            manage::parse_callback(&cb_data)
+                        })
+                        .endpoint(manage_handler),
+                    )
+                    .branch(
+                        dptree::filter_map(|cb_data: String| {
+                            account::parse_callback(&cb_data)
+                        })
+                        .endpoint(unlink_handler),
+                    )
+                    .branch(
+                        dptree::filter_map_async(get_user_timezone)
+                            .branch(
+                                dptree::filter(|cb_data: String| {
+                                    cb_data.starts_with("onboard::")
+                                })
+                                .endpoint(onboarding_callback_handler),
+                            )
+                            .branch(
+                                dptree::filter(|cb_data: String| {
+                                    cb_data.starts_with("aiparse::")
+                                })
+                                .endpoint(suggestion_callback_handler),
+                            )
+                            .branch(
+                                dptree::filter(|cb_data: String| {
+                                    cb_data.starts_with("dup::")
+                                })
+                                .endpoint(duplicate_callback_handler),
+                            )
+                            .branch(
+                                dptree::filter_map(|cb_data: String| {
+                                    cb_data
+                                        .strip_prefix("mute::")
+                                        .and_then(|x| x.parse::<i64>().ok())
+                                })
+                                .endpoint(mute_handler),
+                            )
+                            .branch(
+                                dptree::filter_map(|cb_data: String| {
+                                    undo::parse_callback(&cb_data)
+                                })
+                                .endpoint(undo_handler),
+                            )
+                            .branch(
+                                dptree::filter_map(|cb_data: String| {
+                                    bulk::parse_callback(&cb_data)
+                                })
+                                .endpoint(bulk_handler),
+                            )
+                            .branch(
+                                dptree::filter_map(|cb_data: String| {
+                                    series::parse_callback(&cb_data)
+                                })
+                                .endpoint(series_handler),
+                            )
+                            .branch(
+                                dptree::filter_map(|cb_data: String| {
+                                    picker::parse_callback(&cb_data)
+                                })
+                                .endpoint(pick_handler),
+                            )
+                            .branch(
+                                dptree::filter_map(|cb_data: String| {
+                                    picker::parse_calendar_callback(&cb_data)
+                                })
+                                .endpoint(calendar_handler),
+                            )
+                            .endpoint(callback_handler),
+                    ),
+            ),
+    )
 }
 
 async fn get_user_timezone(ctl: TgMessageController) -> Option<Tz> {
-    tz::get_user_timezone(&ctl.db, ctl.user_id)
+    tz::get_chat_user_timezone(&ctl.db, ctl.user_id, ctl.chat_id)
         .await
         .ok()
         .flatten()
@@ -181,188 +682,998 @@ async fn get_user_timezone(ctl: TgMessageController) -> Option<Tz> {
 async fn help_handler(
     ctl: TgMessageController,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.reply(Command::descriptions())
+    let descriptions = Command::descriptions().to_string();
+    let help = descriptions
+        .lines()
+        .filter(|line| {
+            line.split_whitespace()
+                .next()
+                .is_none_or(cli::command_enabled)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    ctl.reply(help).await.map(|_| ()).map_err(From::from)
+}
+
+async fn check_setup_handler(
+    ctl: TgMessageController,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.check_setup().await.map_err(From::from)
+}
+
+async fn link_handler(
+    ctl: TgMessageController,
+    msg: Message,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.link(msg.chat.title().unwrap_or_default())
+        .await
+        .map_err(From::from)
+}
+
+async fn set_chat_timezone_handler(
+    ctl: TgMessageController,
+    args: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.set_chat_timezone(&args).await.map_err(From::from)
+}
+
+async fn start_handler(
+    ctl: TgMessageController,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.start().await?;
+    dialogue.update(State::OnboardingTimezone).await?;
+    Ok(())
+}
+
+/// Await the answer to the onboarding step
+async fn onboarding_continue(
+    step: OnboardingStep,
+    dialogue: &MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let state = match step {
+        OnboardingStep::Timezone => return Ok(()),
+        OnboardingStep::Language => State::OnboardingLanguage,
+        OnboardingStep::SampleReminder => State::OnboardingSampleReminder,
+    };
+    dialogue.update(state).await?;
+    Ok(())
+}
+
+async fn shortcut_start_handler(
+    ctl: TgMessageController,
+    shortcut: Shortcut,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match shortcut {
+        Shortcut::Timezone(tz_name) => {
+            let step = ctl.start_with_timezone(tz_name).await?;
+            onboarding_continue(step, &dialogue).await
+        }
+        Shortcut::Sample(idx) => {
+            // the sample can't be set before the timezone is known
+            if let Some(user_tz) = get_user_timezone(ctl.clone()).await {
+                if ctl.start_with_sample(idx, user_tz).await? {
+                    dialogue.update(State::Default).await?;
+                    return Ok(());
+                }
+            }
+            start_handler(ctl, dialogue).await
+        }
+    }
+}
+
+async fn manage_start_handler(
+    ctl: TgMessageController,
+    (chat_id, msg_id): (ChatId, MessageId),
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.manage(chat_id, msg_id).await.map_err(From::from)
+}
+
+async fn link_start_handler(
+    ctl: TgMessageController,
+    token: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.link_chat(&token).await.map_err(From::from)
+}
+
+async fn start_group_handler(
+    ctl: TgMessageController,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.start_group().await.map_err(From::from)
+}
+
+async fn today_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.agenda(false, user_tz).await.map_err(From::from)
+}
+
+async fn tomorrow_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.agenda(true, user_tz).await.map_err(From::from)
+}
+
+async fn list_handler(
+    ctl: TgMessageController,
+    args: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.list(&args, user_tz).await.map_err(From::from)
+}
+
+async fn timezone_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.get_timezone(user_tz).await.map_err(From::from)
+}
+
+/// Wait for the number of an option if a text menu was sent
+async fn await_choice(
+    dialogue: &MyDialogue,
+    menu: Option<Menu>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(menu) = menu {
+        dialogue.update(State::TextMenu { menu }).await?;
+    }
+    Ok(())
+}
+
+async fn delete_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    await_choice(&dialogue, ctl.start_delete(user_tz).await?).await
+}
+
+async fn edit_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    await_choice(&dialogue, ctl.start_edit(user_tz).await?).await
+}
+
+async fn cancel_handler(
+    ctl: TgMessageController,
+    state: State,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match state {
+        State::TextMenu { .. } => {
+            ctl.reply(TgResponse::MenuClosed).await?;
+        }
+        State::NewReminder { .. } => {
+            ctl.reply(TgResponse::NewReminderCanceled).await?;
+        }
+        State::TimezoneSearch => {
+            ctl.reply(TgResponse::TimezoneSearchCanceled).await?;
+        }
+        _ => ctl.cancel_edit().await?,
+    }
+    dialogue.update(State::Default).await?;
+    Ok(())
+}
+
+async fn pause_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    await_choice(&dialogue, ctl.start_pause(user_tz).await?).await
+}
+
+async fn skip_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    await_choice(&dialogue, ctl.start_skip(user_tz).await?).await
+}
+
+async fn settings_handler(
+    ctl: TgMessageController,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    await_choice(&dialogue, ctl.settings().await?).await
+}
+
+async fn quiet_handler(
+    ctl: TgMessageController,
+    args: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.quiet_hours(&args).await.map_err(From::from)
+}
+
+async fn day_times_handler(
+    ctl: TgMessageController,
+    args: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.day_times(&args).await.map_err(From::from)
+}
+
+async fn delete_all_handler(
+    ctl: TgMessageController,
+    args: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.start_bulk(bulk::Action::Delete, &args, user_tz)
+        .await
+        .map_err(From::from)
+}
+
+async fn pause_all_handler(
+    ctl: TgMessageController,
+    args: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.start_bulk(bulk::Action::Pause, &args, user_tz)
+        .await
+        .map_err(From::from)
+}
+
+async fn digest_handler(
+    ctl: TgMessageController,
+    args: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.digest(&args, user_tz).await.map_err(From::from)
+}
+
+async fn vacation_handler(
+    ctl: TgMessageController,
+    args: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.vacation(&args, user_tz).await.map_err(From::from)
+}
+
+async fn broadcast_handler(
+    ctl: TgMessageController,
+    args: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.broadcast(&args).await.map_err(From::from)
+}
+
+async fn save_template_handler(
+    ctl: TgMessageController,
+    args: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.save_template(&args, user_tz).await.map_err(From::from)
+}
+
+async fn use_template_handler(
+    ctl: TgMessageController,
+    args: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.use_template(&args, user_tz).await.map_err(From::from)
+}
+
+async fn stats_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.stats(user_tz).await.map_err(From::from)
+}
+
+async fn history_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.history(user_tz).await.map_err(From::from)
+}
+
+async fn export_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.export(user_tz).await.map_err(From::from)
+}
+
+async fn share_handler(
+    ctl: TgMessageController,
+    args: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.share(&args, user_tz).await.map_err(From::from)
+}
+
+async fn calendar_feed_handler(
+    ctl: TgMessageController,
+    args: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.calendar(&args).await.map_err(From::from)
+}
+
+async fn api_token_handler(
+    ctl: TgMessageController,
+    args: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.api_token(&args).await.map_err(From::from)
+}
+
+async fn unshare_handler(
+    ctl: TgMessageController,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.unshare().await.map_err(From::from)
+}
+
+async fn orphans_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.list_orphaned_reminders(user_tz)
+        .await
+        .map_err(From::from)
+}
+
+async fn copy_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.copy_replied(user_tz).await.map_err(From::from)
+}
+
+async fn trash_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.list_trashed_reminders(user_tz)
+        .await
+        .map_err(From::from)
+}
+
+async fn restore_handler(
+    ctl: TgMessageController,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.restore_last_deleted(user_tz).await.map_err(From::from)
+}
+
+async fn pomodoro_handler(
+    ctl: TgMessageController,
+    args: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.start_pomodoro(&args, user_tz).await.map_err(From::from)
+}
+
+async fn ai_handler(
+    ctl: TgMessageController,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.toggle_ai().await.map_err(From::from)
+}
+
+/// Ask whether to set the reminder once more if it's already set,
+/// return whether it's asked
+async fn ask_duplicate(
+    ctl: &TgMessageController,
+    text: &str,
+    user_tz: Tz,
+    dialogue: &MyDialogue,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let Some(msg) = ctl.warn_duplicate(text, user_tz).await? else {
+        return Ok(false);
+    };
+    dialogue
+        .update(State::DuplicateConfirm {
+            text: text.to_owned(),
+            msg_id: msg.id.0,
+        })
+        .await?;
+    Ok(true)
+}
+
+async fn set_handler(
+    ctl: TgMessageController,
+    reminder_text: String,
+    user_tz: Tz,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if ask_duplicate(&ctl, &reminder_text, user_tz, &dialogue).await? {
+        return Ok(());
+    }
+    ctl.set_new_reminder(&reminder_text, user_tz)
+        .await
+        .map(|_| ())
+        .map_err(From::from)
+}
+
+async fn set_edited_handler(
+    ctl: TgMessageController,
+    reminder_text: String,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    Ok(ctl
+        .edit_reminder_from_edited_message(&reminder_text, user_tz)
+        .await?)
+}
+
+async fn edited_message_handler(
+    ctl: TgMessageController,
+    msg: Message,
+    user_tz: Tz,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(text) = msg.text() {
+        Ok(ctl.edit_reminder_from_edited_message(text, user_tz).await?)
+    } else {
+        ctl.incorrect_request().await.map_err(From::from)
+    }
+}
+
+/// Offer the timezones to select, or search them by the text sent
+/// after they were offered in a private chat
+async fn set_timezone_handler(
+    ctl: TgMessageController,
+    msg: Message,
+    state: State,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let private = msg.chat.id.is_user();
+    let searching =
+        matches!(state, State::TimezoneSearch | State::OnboardingTimezone);
+    match msg.text() {
+        Some(query) if private && searching && !query.starts_with('/') => {
+            ctl.search_timezone(query).await?;
+        }
+        _ => {
+            ctl.choose_timezone().await?;
+            if private && !matches!(state, State::OnboardingTimezone) {
+                dialogue.update(State::TimezoneSearch).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn timezone_search_handler(
+    ctl: TgMessageController,
+    text: String,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.search_timezone(&text).await.map_err(From::from)
+}
+
+async fn location_handler(
+    ctl: TgMessageController,
+    loc: Location,
+    state: State,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let tz_name = get_timezone_name_of_location(loc.longitude, loc.latitude);
+    if let State::OnboardingTimezone = state {
+        let step = ctl.onboarding_set_timezone(tz_name).await?;
+        onboarding_continue(step, &dialogue).await
+    } else {
+        ctl.set_timezone(tz_name).await?;
+        if let State::TimezoneSearch = state {
+            dialogue.update(State::Default).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn incorrect_request_handler(
+    ctl: TgMessageController,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.incorrect_request().await.map_err(From::from)
+}
+
+async fn edit_message_handler(
+    ctl: TgMessageController,
+    text: String,
+    (rem_id, mode, version): (i64, EditMode, i32),
+    user_tz: Tz,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let update = match mode {
+        EditMode::TimePattern => {
+            ReminderUpdate::ReminderTimePattern(rem_id, text)
+        }
+        EditMode::Whole
+            if parsers::lacks_description(&text, ctl.user_id.0, user_tz) =>
+        {
+            ReminderUpdate::ReminderTimePattern(rem_id, text)
+        }
+        EditMode::Whole => ReminderUpdate::Reminder(rem_id, text),
+        EditMode::Description => {
+            ReminderUpdate::ReminderDescription(rem_id, text)
+        }
+        EditMode::Shift => ReminderUpdate::ReminderShift(rem_id, text),
+    };
+    ctl.edit_reminder(update, version, user_tz).await?;
+    dialogue.update(State::Default).await?;
+    Ok(())
+}
+
+async fn edit_cron_message_handler(
+    ctl: TgMessageController,
+    text: String,
+    (cron_rem_id, version): (i64, i32),
+    user_tz: Tz,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.edit_reminder(
+        ReminderUpdate::CronReminder(cron_rem_id, text),
+        version,
+        user_tz,
+    )
+    .await?;
+    dialogue.update(State::Default).await?;
+    Ok(())
+}
+
+#[cfg(not(feature = "ai"))]
+async fn message_handler(
+    ctl: TgMessageController,
+    text: String,
+    user_tz: Tz,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if ask_duplicate(&ctl, &text, user_tz, &dialogue).await? {
+        return Ok(());
+    }
+    ctl.set_new_reminder(&text, user_tz)
         .await
         .map(|_| ())
         .map_err(From::from)
 }
 
-async fn start_handler(
-    ctl: TgMessageController,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.start().await.map_err(From::from)
-}
-
-async fn start_group_handler(
+#[cfg(feature = "ai")]
+async fn message_handler(
     ctl: TgMessageController,
+    text: String,
+    user_tz: Tz,
+    dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.start_group().await.map_err(From::from)
+    if ask_duplicate(&ctl, &text, user_tz, &dialogue).await? {
+        return Ok(());
+    }
+    if let Some((text, msg)) = ctl.suggest_reminder(&text, user_tz).await? {
+        dialogue
+            .update(State::AiConfirm {
+                text,
+                msg_id: msg.id.0,
+            })
+            .await?;
+        Ok(())
+    } else {
+        ctl.set_new_reminder(&text, user_tz)
+            .await
+            .map(|_| ())
+            .map_err(From::from)
+    }
 }
 
-async fn list_handler(
+async fn document_handler(
     ctl: TgMessageController,
+    document: Document,
     user_tz: Tz,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.list(user_tz).await.map_err(From::from)
+    let file_name = document
+        .file_name
+        .as_deref()
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    if file_name.ends_with(".json") {
+        ctl.import_backup(&document, user_tz).await
+    } else if file_name.ends_with(".ics") {
+        ctl.import_calendar(&document, user_tz).await
+    } else {
+        ctl.import_crontab(&document, user_tz).await
+    }
+    .map_err(From::from)
 }
 
-async fn timezone_handler(
+async fn onboarding_message_handler(
     ctl: TgMessageController,
+    text: String,
     user_tz: Tz,
+    dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.get_timezone(user_tz).await.map_err(From::from)
+    if ctl.set_new_reminder(&text, user_tz).await? {
+        ctl.finish_onboarding().await?;
+        dialogue.update(State::Default).await?;
+    }
+    Ok(())
 }
 
-async fn delete_handler(
-    ctl: TgMessageController,
-    user_tz: Tz,
+async fn select_timezone_handler(
+    ctl: TgCallbackController,
+    msg_ctl: TgMessageController,
+    cb_query: CallbackQuery,
+    cb_data: String,
+    state: State,
+    dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.start_delete(user_tz).await.map_err(From::from)
+    if let Some(page_num) = cb_data
+        .strip_prefix("seltz::page::")
+        .and_then(|x| x.parse::<usize>().ok())
+    {
+        msg_ctl
+            .select_timezone_set_page(page_num)
+            .await
+            .map_err(From::from)
+    } else if let Some(tz_name) = cb_data.strip_prefix("seltz::tz::") {
+        if let State::OnboardingTimezone = state {
+            let step = ctl.onboarding_set_timezone(tz_name).await?;
+            onboarding_continue(step, &dialogue).await
+        } else {
+            ctl.set_timezone(tz_name).await?;
+            if let State::TimezoneSearch = state {
+                dialogue.update(State::Default).await?;
+            }
+            Ok(())
+        }
+    } else {
+        Err(Error::UnmatchedQuery(Box::new(cb_query)))?
+    }
 }
 
-async fn edit_handler(
-    ctl: TgMessageController,
-    user_tz: Tz,
+async fn done_handler(
+    ctl: TgCallbackController,
+    occ_id: i64,
+    state: State,
+    dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.start_edit(user_tz).await.map_err(From::from)
+    // Don't interrupt another dialogue with the note
+    let ask_note = matches!(state, State::Default);
+    if let Some(occ_id) = ctl.mark_done(occ_id, ask_note).await? {
+        dialogue.update(State::OccurrenceNote { occ_id }).await?;
+    }
+    Ok(())
 }
 
-async fn cancel_handler(
-    ctl: TgMessageController,
+async fn skip_note_handler(
+    ctl: TgCallbackController,
+    state: State,
     dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.cancel_edit().await?;
-    dialogue.update(State::Default).await.map_err(From::from)
+    if let State::OccurrenceNote { .. } = state {
+        dialogue.update(State::Default).await?;
+    }
+    ctl.skip_occurrence_note().await.map_err(From::from)
 }
 
-async fn pause_handler(
+/// Act on the option of the text menu whose number was sent, the same way
+/// as on the pressed button
+async fn text_menu_handler(
     ctl: TgMessageController,
+    menu: Menu,
+    text: String,
     user_tz: Tz,
+    dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.start_pause(user_tz).await.map_err(From::from)
+    let Some(idx) = text_menu::parse_choice(&text, menu.options()) else {
+        ctl.reply(TgResponse::WrongNumber(menu.options())).await?;
+        return Ok(());
+    };
+    let state = match menu {
+        Menu::Delete(targets) => {
+            ctl.delete_target(targets[idx], user_tz).await?;
+            State::Default
+        }
+        Menu::Pause(targets) => {
+            ctl.reply(ctl.pause_target(targets[idx], user_tz).await)
+                .await?;
+            State::Default
+        }
+        Menu::Skip(targets) => {
+            ctl.reply(ctl.skip_target(targets[idx], user_tz).await)
+                .await?;
+            State::Default
+        }
+        Menu::Edit(targets) => match targets[idx] {
+            LinkTarget::Reminder(id) => State::TextMenu {
+                menu: ctl.choose_edit_mode_text(id).await?,
+            },
+            LinkTarget::CronReminder(id) => State::EditCron {
+                id,
+                version: ctl.start_edit_cron_reminder(id).await?,
+            },
+        },
+        Menu::EditMode(id) => {
+            match [
+                EditMode::TimePattern,
+                EditMode::Description,
+                EditMode::Shift,
+            ]
+            .into_iter()
+            .nth(idx)
+            {
+                Some(mode) => {
+                    let version =
+                        ctl.start_edit_reminder(id, &mode, user_tz).await?;
+                    State::Edit { id, mode, version }
+                }
+                // The last option is to duplicate the reminder
+                None => {
+                    let (id, version) =
+                        ctl.start_edit_duplicate(id, user_tz).await?;
+                    State::Edit {
+                        id,
+                        mode: EditMode::TimePattern,
+                        version,
+                    }
+                }
+            }
+        }
+        Menu::Settings => {
+            ctl.switch_setting(Setting::ALL[idx]).await?;
+            match ctl.settings().await? {
+                Some(menu) => State::TextMenu { menu },
+                None => State::Default,
+            }
+        }
+    };
+    dialogue.update(state).await?;
+    Ok(())
 }
 
-async fn set_handler(
+async fn occurrence_note_handler(
     ctl: TgMessageController,
-    reminder_text: String,
-    user_tz: Tz,
+    occ_id: i64,
+    text: String,
+    dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.set_new_reminder(&reminder_text, user_tz)
+    dialogue.update(State::Default).await?;
+    ctl.set_occurrence_note(occ_id, &text)
         .await
-        .map(|_| ())
         .map_err(From::from)
 }
 
-async fn set_edited_handler(
-    ctl: TgMessageController,
-    reminder_text: String,
-    user_tz: Tz,
+async fn bill_paid_handler(
+    ctl: TgCallbackController,
+    bill_id: i64,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    Ok(ctl
-        .edit_reminder_from_edited_message(&reminder_text, user_tz)
-        .await?)
+    ctl.mark_bill_paid(bill_id).await.map_err(From::from)
 }
 
-async fn edited_message_handler(
-    ctl: TgMessageController,
-    msg: Message,
+async fn snooze_handler(
+    ctl: TgCallbackController,
+    occ_id: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.snooze(occ_id).await.map_err(From::from)
+}
+
+async fn mute_handler(
+    ctl: TgCallbackController,
+    occ_id: i64,
     user_tz: Tz,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    if let Some(text) = msg.text() {
-        Ok(ctl.edit_reminder_from_edited_message(text, user_tz).await?)
-    } else {
-        ctl.incorrect_request().await.map_err(From::from)
-    }
+    ctl.mute(occ_id, user_tz).await.map_err(From::from)
 }
 
-async fn set_timezone_handler(
-    ctl: TgMessageController,
+async fn bulk_handler(
+    ctl: TgCallbackController,
+    callback: bulk::Callback,
+    user_tz: Tz,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.choose_timezone().await.map_err(From::from)
+    ctl.bulk(callback, user_tz).await.map_err(From::from)
 }
 
-async fn location_handler(
-    ctl: TgMessageController,
-    loc: Location,
+async fn series_handler(
+    ctl: TgCallbackController,
+    callback: series::Callback,
+    user_tz: Tz,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.set_timezone(get_timezone_name_of_location(loc.longitude, loc.latitude))
+    ctl.delete_series(callback, user_tz)
         .await
         .map_err(From::from)
 }
 
-async fn incorrect_request_handler(
+async fn new_handler(
     ctl: TgMessageController,
+    user_tz: Tz,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.incorrect_request().await.map_err(From::from)
+    ctl.start_new(user_tz).await.map_err(From::from)
 }
 
-async fn edit_message_handler(
-    ctl: TgMessageController,
-    text: String,
-    rem_update: (i64, EditMode),
+async fn pick_handler(
+    ctl: TgCallbackController,
+    step: picker::Step,
     user_tz: Tz,
     dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    match rem_update.1 {
-        EditMode::TimePattern => {
-            ctl.edit_reminder(
-                ReminderUpdate::ReminderTimePattern(rem_update.0, text),
-                user_tz,
-            )
-            .await?
+    if let Some(time) = ctl.pick(step, user_tz).await? {
+        dialogue.update(State::NewReminder { time }).await?;
+    }
+    Ok(())
+}
+
+async fn calendar_handler(
+    ctl: TgCallbackController,
+    action: picker::CalendarAction,
+    user_tz: Tz,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match action {
+        picker::CalendarAction::Month(purpose, month) => {
+            ctl.show_calendar(purpose, month, user_tz).await?;
         }
-        EditMode::Description => {
-            ctl.edit_reminder(
-                ReminderUpdate::ReminderDescription(rem_update.0, text),
-                user_tz,
-            )
-            .await?
+        picker::CalendarAction::Day(picker::Purpose::New, date) => {
+            pick_handler(ctl, picker::Step::Day(date), user_tz, dialogue)
+                .await?;
         }
+        picker::CalendarAction::Day(picker::Purpose::Edit(rem_id), date) => {
+            let version = match dialogue.get().await? {
+                Some(State::Edit { id, version, .. }) if id == rem_id => {
+                    Some(version)
+                }
+                _ => None,
+            };
+            ctl.move_reminder(rem_id, date, version, user_tz).await?;
+            if version.is_some() {
+                dialogue.update(State::Default).await?;
+            }
+        }
+        picker::CalendarAction::Ignore => ctl.acknowledge_callback().await?,
     }
-    dialogue.update(State::Default).await.map_err(From::from)
+    Ok(())
 }
 
-async fn edit_cron_message_handler(
+async fn new_reminder_handler(
     ctl: TgMessageController,
-    text: String,
-    cron_rem_id: i64,
+    time: String,
+    msg: Message,
     user_tz: Tz,
     dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.edit_reminder(ReminderUpdate::CronReminder(cron_rem_id, text), user_tz)
-        .await?;
-    dialogue.update(State::Default).await.map_err(From::from)
+    // The time of the state shadows the text of the message
+    let text = msg.text().unwrap_or_default();
+    dialogue.update(State::Default).await?;
+    ctl.set_new_reminder(&format!("{} {}", time, text), user_tz)
+        .await
+        .map(|_| ())
+        .map_err(From::from)
 }
 
-async fn message_handler(
-    ctl: TgMessageController,
-    text: String,
+async fn undo_handler(
+    ctl: TgCallbackController,
+    undo_id: u64,
     user_tz: Tz,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    ctl.set_new_reminder(&text, user_tz)
+    ctl.undo(undo_id, user_tz).await.map_err(From::from)
+}
+
+async fn change_setting_handler(
+    ctl: TgCallbackController,
+    setting: Setting,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.change_setting(setting).await.map_err(From::from)
+}
+
+async fn rsvp_handler(
+    ctl: TgCallbackController,
+    (occ_id, answer): (i64, Answer),
+    cb_query: CallbackQuery,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.set_rsvp(occ_id, answer, &cb_query.from)
         .await
-        .map(|_| ())
         .map_err(From::from)
 }
 
-async fn select_timezone_handler(
+async fn manage_handler(
+    ctl: TgCallbackController,
+    (action, target): (Action, LinkTarget),
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some((id, version)) = ctl.manage(action, target).await? {
+        dialogue
+            .update(State::Edit {
+                id,
+                mode: EditMode::Description,
+                version,
+            })
+            .await?;
+    }
+    Ok(())
+}
+
+async fn unlink_handler(
+    ctl: TgCallbackController,
+    chat_id: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.unlink_chat(chat_id).await.map_err(From::from)
+}
+
+async fn stop_pomodoro_handler(
+    ctl: TgCallbackController,
+    pomodoro_id: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ctl.stop_pomodoro(pomodoro_id).await.map_err(From::from)
+}
+
+async fn onboarding_callback_handler(
     ctl: TgCallbackController,
-    msg_ctl: TgMessageController,
     cb_query: CallbackQuery,
     cb_data: String,
+    user_tz: Tz,
+    dialogue: MyDialogue,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    if let Some(page_num) = cb_data
-        .strip_prefix("seltz::page::")
+    if let Some(lang) = cb_data
+        .strip_prefix("onboard::lang::")
+        .and_then(Language::from_code)
+    {
+        ctl.onboarding_set_language(lang).await?;
+        dialogue.update(State::OnboardingSampleReminder).await?;
+        Ok(())
+    } else if let Some(idx) = cb_data
+        .strip_prefix("onboard::sample::")
         .and_then(|x| x.parse::<usize>().ok())
     {
-        msg_ctl
-            .select_timezone_set_page(page_num)
-            .await
-            .map_err(From::from)
-    } else if let Some(tz_name) = cb_data.strip_prefix("seltz::tz::") {
-        ctl.set_timezone(tz_name).await.map_err(From::from)
+        ctl.onboarding_set_sample_reminder(idx, user_tz).await?;
+        dialogue.update(State::Default).await?;
+        Ok(())
+    } else if cb_data == "onboard::skip" {
+        ctl.onboarding_skip().await?;
+        dialogue.update(State::Default).await?;
+        Ok(())
     } else {
-        Err(Error::UnmatchedQuery(cb_query))?
+        Err(Error::UnmatchedQuery(Box::new(cb_query)))?
+    }
+}
+
+async fn suggestion_callback_handler(
+    ctl: TgCallbackController,
+    cb_data: String,
+    user_tz: Tz,
+    state: State,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Buttons of the older suggestions are stale
+    let suggestion = match state {
+        State::AiConfirm { text, msg_id } if msg_id == ctl.msg_ctl.msg_id.0 => {
+            dialogue.update(State::Default).await?;
+            Some(text)
+        }
+        _ => None,
+    };
+    ctl.confirm_suggestion(suggestion, cb_data == "aiparse::yes", user_tz)
+        .await
+        .map_err(From::from)
+}
+
+async fn duplicate_callback_handler(
+    ctl: TgCallbackController,
+    cb_data: String,
+    user_tz: Tz,
+    state: State,
+    dialogue: MyDialogue,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // Buttons of the older questions are stale
+    let text = match state {
+        State::DuplicateConfirm { text, msg_id }
+            if msg_id == ctl.msg_ctl.msg_id.0 =>
+        {
+            dialogue.update(State::Default).await?;
+            Some(text)
+        }
+        _ => None,
+    };
+    ctl.confirm_duplicate(text, cb_data == "dup::add", user_tz)
+        .await
+        .map_err(From::from)
+}
+
+/// Orphaned or deleted reminder a button is for, `None` if it's for all
+/// of them
+fn listed_target(cb_data: &str) -> Option<Option<LinkTarget>> {
+    if cb_data == "all" {
+        return Some(None);
+    }
+    let (kind, id) = cb_data.split_once("::")?;
+    let id = id.parse().ok()?;
+    match kind {
+        "rem" => Some(Some(LinkTarget::Reminder(id))),
+        "cron_rem" => Some(Some(LinkTarget::CronReminder(id))),
+        _ => None,
     }
 }
 
@@ -384,6 +1695,15 @@ async fn callback_handler(
             .map_err(From::from)
     } else if let Some(tz_name) = cb_data.strip_prefix("seltz::tz::") {
         ctl.set_timezone(tz_name).await.map_err(From::from)
+    } else if let Some((page_num, args)) = cb_data
+        .strip_prefix("list::page::")
+        .and_then(|x| x.split_once("::"))
+        .and_then(|(num, args)| Some((num.parse::<usize>().ok()?, args)))
+    {
+        msg_ctl
+            .list_set_page(page_num, args, user_tz)
+            .await
+            .map_err(From::from)
     } else if let Some(page_num) = cb_data
         .strip_prefix("delrem::page::")
         .and_then(|x| x.parse::<usize>().ok())
@@ -418,18 +1738,27 @@ async fn callback_handler(
         .strip_prefix("editrem::rem_alt::")
         .and_then(|x| x.parse::<i64>().ok())
     {
-        ctl.choose_edit_mode_reminder(rem_id)
-            .await
-            .map_err(From::from)
+        let version = ctl.edit_reminder(rem_id, user_tz).await?;
+        dialogue
+            .update(State::Edit {
+                id: rem_id,
+                mode: EditMode::Whole,
+                version,
+            })
+            .await?;
+        Ok(())
     } else if let Some(cron_rem_id) = cb_data
         .strip_prefix("editrem::cron_rem_alt::")
         .and_then(|x| x.parse::<i64>().ok())
     {
-        ctl.edit_cron_reminder().await?;
+        let version = ctl.edit_cron_reminder(cron_rem_id).await?;
         dialogue
-            .update(State::EditCron { id: cron_rem_id })
-            .await
-            .map_err(From::from)
+            .update(State::EditCron {
+                id: cron_rem_id,
+                version,
+            })
+            .await?;
+        Ok(())
     } else if let Some(page_num) = cb_data
         .strip_prefix("pauserem::page::")
         .and_then(|x| x.parse::<usize>().ok())
@@ -452,31 +1781,110 @@ async fn callback_handler(
         ctl.pause_cron_reminder(cron_rem_id, user_tz)
             .await
             .map_err(From::from)
+    } else if let Some(page_num) = cb_data
+        .strip_prefix("skiprem::page::")
+        .and_then(|x| x.parse::<usize>().ok())
+    {
+        msg_ctl
+            .skip_reminder_set_page(page_num, user_tz)
+            .await
+            .map_err(From::from)
+    } else if let Some(rem_id) = cb_data
+        .strip_prefix("skiprem::rem_alt::")
+        .and_then(|x| x.parse::<i64>().ok())
+    {
+        ctl.skip_reminder(rem_id, user_tz).await.map_err(From::from)
+    } else if let Some(cron_rem_id) = cb_data
+        .strip_prefix("skiprem::cron_rem_alt::")
+        .and_then(|x| x.parse::<i64>().ok())
+    {
+        ctl.skip_cron_reminder(cron_rem_id, user_tz)
+            .await
+            .map_err(From::from)
+    } else if let Some(name) = cb_data.strip_prefix("template::") {
+        ctl.set_template_reminder(name, user_tz)
+            .await
+            .map_err(From::from)
+    } else if let Some(target) = cb_data
+        .strip_prefix("orphan::claim::")
+        .and_then(listed_target)
+    {
+        ctl.claim_orphaned_reminders(target, user_tz)
+            .await
+            .map_err(From::from)
+    } else if let Some(target) = cb_data
+        .strip_prefix("orphan::del::")
+        .and_then(listed_target)
+    {
+        ctl.delete_orphaned_reminders(target, user_tz)
+            .await
+            .map_err(From::from)
+    } else if let Some(target) = cb_data
+        .strip_prefix("trash::restore::")
+        .and_then(listed_target)
+    {
+        ctl.restore_trashed_reminders(target, user_tz)
+            .await
+            .map_err(From::from)
     } else if let Some(rem_id) = cb_data
         .strip_prefix("edit_rem_mode::rem_time_pattern::")
         .and_then(|x| x.parse::<i64>().ok())
     {
-        ctl.set_edit_mode_reminder(EditMode::TimePattern).await?;
+        let version = ctl
+            .set_edit_mode_reminder(rem_id, EditMode::TimePattern, user_tz)
+            .await?;
         dialogue
             .update(State::Edit {
                 id: rem_id,
                 mode: EditMode::TimePattern,
+                version,
             })
-            .await
-            .map_err(From::from)
+            .await?;
+        Ok(())
     } else if let Some(rem_id) = cb_data
         .strip_prefix("edit_rem_mode::rem_description::")
         .and_then(|x| x.parse::<i64>().ok())
     {
-        ctl.set_edit_mode_reminder(EditMode::Description).await?;
+        let version = ctl
+            .set_edit_mode_reminder(rem_id, EditMode::Description, user_tz)
+            .await?;
         dialogue
             .update(State::Edit {
                 id: rem_id,
                 mode: EditMode::Description,
+                version,
             })
-            .await
-            .map_err(From::from)
+            .await?;
+        Ok(())
+    } else if let Some(rem_id) = cb_data
+        .strip_prefix("edit_rem_mode::rem_shift::")
+        .and_then(|x| x.parse::<i64>().ok())
+    {
+        let version = ctl
+            .set_edit_mode_reminder(rem_id, EditMode::Shift, user_tz)
+            .await?;
+        dialogue
+            .update(State::Edit {
+                id: rem_id,
+                mode: EditMode::Shift,
+                version,
+            })
+            .await?;
+        Ok(())
+    } else if let Some(rem_id) = cb_data
+        .strip_prefix("duplicate::rem::")
+        .and_then(|x| x.parse::<i64>().ok())
+    {
+        let (id, version) = ctl.duplicate_reminder(rem_id, user_tz).await?;
+        dialogue
+            .update(State::Edit {
+                id,
+                mode: EditMode::TimePattern,
+                version,
+            })
+            .await?;
+        Ok(())
     } else {
-        Err(Error::UnmatchedQuery(cb_query))?
+        Err(Error::UnmatchedQuery(Box::new(cb_query)))?
     }
 }
@@ -0,0 +1,45 @@
+//! History of the chat's reminders shown with /history: when they were
+//! sent or given up on, snoozed or marked done along with the notes,
+//! kept for a month
+
+#[cfg(not(test))]
+use crate::db::Database;
+#[cfg(test)]
+use crate::db::MockDatabase as Database;
+use crate::entity::reminder_event::{self, EventKind};
+use crate::parsers::now_time;
+use chrono::{NaiveDateTime, TimeDelta};
+use sea_orm::{NotSet, Set};
+
+/// Number of days the events are kept for /history
+const HISTORY_DAYS: i64 = 30;
+/// Maximum number of events shown with /history
+pub(crate) const HISTORY_LEN: u64 = 20;
+
+/// Time before which the events are forgotten
+pub(crate) fn keep_since(now: NaiveDateTime) -> NaiveDateTime {
+    now - TimeDelta::days(HISTORY_DAYS)
+}
+
+/// Add the event to the chat's history, failing to do so doesn't affect
+/// the reminder itself
+pub(crate) async fn record(
+    db: &Database,
+    chat_id: i64,
+    desc: &str,
+    kind: EventKind,
+) {
+    let now = now_time();
+    let event = reminder_event::ActiveModel {
+        id: NotSet,
+        chat_id: Set(chat_id),
+        time: Set(now),
+        desc: Set(desc.to_owned()),
+        kind: Set(kind),
+        occurrence_id: Set(None),
+        note: Set(None),
+    };
+    db.add_reminder_event(event, keep_since(now))
+        .await
+        .unwrap_or_else(|err| tracing::error!("{}", err));
+}
@@ -0,0 +1,627 @@
+//! Import of iCalendar (.ics) files exported from calendar apps:
+//! recurring events become recurrences of the reminders. The upcoming
+//! firings of the reminders are exported as calendar feeds the other way.
+
+use crate::parsers::DEFAULT_TIME;
+use crate::serializers::{
+    DateDivisor, DateInterval, DatePattern, DateRange, Pattern, Weekdays,
+};
+use chrono::{
+    Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+const DATE_FORMAT: &str = "%Y%m%d";
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+/// Longest content line in octets, the longer ones are folded
+const MAX_LINE_LEN: usize = 75;
+
+/// Content line of a calendar, e.g. `DTSTART;TZID=Europe/Amsterdam:2024...`
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, &'a str)>,
+    value: &'a str,
+}
+
+impl<'a> Property<'a> {
+    fn parse(line: &'a str) -> Option<Self> {
+        // colons are allowed inside of quoted parameter values
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(i, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(i),
+            _ => None,
+        })?;
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut head = head.split(';');
+        let name = head.next()?.to_uppercase();
+        let params = head
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"')))
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value,
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&'a str> {
+        self.params
+            .iter()
+            .find(|(param_key, _)| param_key == key)
+            .map(|&(_, value)| value)
+    }
+}
+
+/// Event of a calendar with the properties relevant for reminders
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Event {
+    pub(crate) summary: String,
+    start: Option<(String, Option<String>)>,
+    rrule: Option<String>,
+    /// Whether the event overrides a single occurrence of another one
+    is_override: bool,
+}
+
+/// Lines of the content with the folded ones joined back
+fn unfold(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push(' '),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Events of a calendar, overrides of single occurrences are left out.
+/// Returns `None` if the content isn't a calendar.
+pub(crate) fn events(content: &str) -> Option<Vec<Event>> {
+    let lines = unfold(content);
+    let mut components: Vec<String> = vec![];
+    let mut is_calendar = false;
+    let mut events = vec![];
+    for line in &lines {
+        let Some(prop) = Property::parse(line) else {
+            continue;
+        };
+        match prop.name.as_str() {
+            "BEGIN" => {
+                let component = prop.value.trim().to_uppercase();
+                is_calendar |= component == "VCALENDAR";
+                if component == "VEVENT" {
+                    events.push(Event::default());
+                }
+                components.push(component);
+                continue;
+            }
+            "END" => {
+                components.pop();
+                continue;
+            }
+            _ => {}
+        }
+        // skip the properties of nested components, e.g. alarms
+        if components.last().map(String::as_str) != Some("VEVENT") {
+            continue;
+        }
+        let Some(event) = events.last_mut() else {
+            continue;
+        };
+        match prop.name.as_str() {
+            "SUMMARY" => event.summary = unescape_text(prop.value.trim()),
+            "DTSTART" => {
+                event.start = Some((
+                    prop.value.trim().to_owned(),
+                    prop.param("TZID").map(str::to_owned),
+                ))
+            }
+            "RRULE" => event.rrule = Some(prop.value.trim().to_owned()),
+            "RECURRENCE-ID" => event.is_override = true,
+            _ => {}
+        }
+    }
+    is_calendar.then(|| {
+        events.retain(|event| !event.is_override);
+        events
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, PartialEq)]
+struct Rule {
+    freq: Freq,
+    interval: u32,
+    until: Option<NaiveDate>,
+    count: Option<u32>,
+    by_day: Vec<Weekday>,
+}
+
+fn parse_weekday(s: &str) -> Option<Weekday> {
+    match s {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Parse a date or a date-time of a calendar in the given timezone
+fn parse_time(value: &str, tz: Tz) -> Option<NaiveDateTime> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, DATE_FORMAT) {
+        return Some(date.and_time(DEFAULT_TIME));
+    }
+    match value.strip_suffix('Z') {
+        Some(value) => Utc
+            .from_utc_datetime(
+                &NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT).ok()?,
+            )
+            .with_timezone(&tz)
+            .naive_local()
+            .into(),
+        None => NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT).ok(),
+    }
+}
+
+/// Parse a recurrence rule, `None` if it has parts without
+/// an equivalent recurrence of reminders
+fn parse_rule(rrule: &str, start: NaiveDate, tz: Tz) -> Option<Rule> {
+    let mut freq = None;
+    let mut rule = Rule {
+        freq: Freq::Daily,
+        interval: 1,
+        until: None,
+        count: None,
+        by_day: vec![],
+    };
+    for part in rrule.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part.split_once('=')?;
+        let value = value.to_uppercase();
+        match key.to_uppercase().as_str() {
+            "FREQ" => {
+                freq = Some(match value.as_str() {
+                    "DAILY" => Freq::Daily,
+                    "WEEKLY" => Freq::Weekly,
+                    "MONTHLY" => Freq::Monthly,
+                    "YEARLY" => Freq::Yearly,
+                    _ => return None,
+                })
+            }
+            "INTERVAL" => {
+                rule.interval = value.parse().ok().filter(|&int| int > 0)?
+            }
+            "UNTIL" => rule.until = Some(parse_time(&value, tz)?.date()),
+            "COUNT" => {
+                rule.count = Some(value.parse().ok().filter(|&n| n > 0)?)
+            }
+            "BYDAY" => {
+                rule.by_day = value
+                    .split(',')
+                    .map(parse_weekday)
+                    .collect::<Option<_>>()?
+            }
+            "BYMONTHDAY" if value.parse() == Ok(start.day()) => {}
+            "BYMONTH" if value.parse() == Ok(start.month()) => {}
+            "WKST" => {}
+            _ => return None,
+        }
+    }
+    rule.freq = freq?;
+    let by_day_allowed = match rule.freq {
+        Freq::Daily => rule.interval == 1,
+        Freq::Weekly => true,
+        Freq::Monthly | Freq::Yearly => false,
+    };
+    (rule.by_day.is_empty() || by_day_allowed).then_some(rule)
+}
+
+fn weekdays(days: &[Weekday]) -> Weekdays {
+    days.iter().fold(Weekdays::none(), |weekdays, day| {
+        weekdays
+            | match day {
+                Weekday::Mon => Weekdays::Monday,
+                Weekday::Tue => Weekdays::Tuesday,
+                Weekday::Wed => Weekdays::Wednesday,
+                Weekday::Thu => Weekdays::Thursday,
+                Weekday::Fri => Weekdays::Friday,
+                Weekday::Sat => Weekdays::Saturday,
+                Weekday::Sun => Weekdays::Sunday,
+            }
+    })
+}
+
+fn interval_range(from: NaiveDate, interval: DateInterval) -> DatePattern {
+    DatePattern::Range(DateRange {
+        from,
+        until: None,
+        date_divisor: DateDivisor::Interval(interval),
+    })
+}
+
+/// Date patterns of the recurrence rule starting at the date
+fn dates_patterns(rule: &Rule, start: NaiveDate) -> Vec<DatePattern> {
+    let zero = DateInterval {
+        years: 0,
+        months: 0,
+        weeks: 0,
+        days: 0,
+    };
+    let n = rule.interval;
+    match rule.freq {
+        Freq::Daily | Freq::Weekly
+            if n == 1
+                && (!rule.by_day.is_empty() || rule.freq == Freq::Weekly) =>
+        {
+            let days = match rule.by_day.as_slice() {
+                [] => &[start.weekday()][..],
+                days => days,
+            };
+            vec![DatePattern::Range(DateRange {
+                from: start,
+                until: None,
+                date_divisor: DateDivisor::Weekdays(weekdays(days)),
+            })]
+        }
+        Freq::Daily => {
+            vec![interval_range(start, DateInterval { days: n, ..zero })]
+        }
+        Freq::Weekly => {
+            // every n-th week counted from the week of the start,
+            // so the days before the start are moved to the next one
+            let week_start = start.week(Weekday::Mon).first_day();
+            let mut days = rule.by_day.clone();
+            if days.is_empty() {
+                days.push(start.weekday());
+            }
+            days.dedup();
+            days.into_iter()
+                .map(|day| {
+                    let mut from = week_start
+                        + Duration::days(day.num_days_from_monday() as i64);
+                    if from < start {
+                        from += Duration::weeks(n as i64);
+                    }
+                    interval_range(from, DateInterval { weeks: n, ..zero })
+                })
+                .collect()
+        }
+        Freq::Monthly => {
+            vec![interval_range(start, DateInterval { months: n, ..zero })]
+        }
+        Freq::Yearly => vec![interval_range(
+            start,
+            DateInterval {
+                years: n as i32,
+                ..zero
+            },
+        )],
+    }
+}
+
+/// Last date of the first `count` occurrences of the date patterns
+fn count_until(
+    dates_patterns: &[DatePattern],
+    start: NaiveDate,
+    count: u32,
+) -> Option<NaiveDate> {
+    let mut date = start;
+    let mut last = None;
+    for _ in 0..count {
+        let next = dates_patterns
+            .iter()
+            .filter_map(|pattern| match pattern {
+                DatePattern::Range(range) => range.get_nearest_date(date),
+                &DatePattern::Point(date) => Some(date),
+            })
+            .min()?;
+        last = Some(next);
+        date = next + Duration::days(1);
+    }
+    last
+}
+
+impl Event {
+    /// Pattern of the reminder for the event, `None` if the event has
+    /// no start or its recurrence rule can't be represented.
+    /// Times without a timezone are in the user's one.
+    pub(crate) fn pattern(&self, user_tz: Tz) -> Option<Pattern> {
+        let (value, tz_name) = self.start.as_ref()?;
+        // calendar apps on Windows may use the names unknown to tz database
+        let tz = tz_name
+            .as_deref()
+            .and_then(|tz_name| tz_name.trim_start_matches('/').parse().ok())
+            .unwrap_or(user_tz);
+        let start = parse_time(value, tz)?;
+        let Some(rrule) = &self.rrule else {
+            return Some(Pattern::recurrence(
+                vec![DatePattern::Point(start.date())],
+                start.time(),
+                tz,
+            ));
+        };
+        let rule = parse_rule(rrule, start.date(), tz)?;
+        let mut dates_patterns = dates_patterns(&rule, start.date());
+        let until = match rule.count {
+            Some(count) => count_until(&dates_patterns, start.date(), count),
+            None => rule.until,
+        };
+        for pattern in &mut dates_patterns {
+            if let DatePattern::Range(range) = pattern {
+                range.until = until;
+            }
+        }
+        Some(Pattern::recurrence(dates_patterns, start.time(), tz))
+    }
+}
+
+/// Event of an exported calendar at a firing of a reminder
+#[derive(Debug, PartialEq)]
+pub(crate) struct Occurrence {
+    /// Identifier unique across the calendars
+    pub(crate) uid: String,
+    pub(crate) summary: String,
+    /// Time of the firing in UTC
+    pub(crate) time: NaiveDateTime,
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Split the line into the ones of at most [`MAX_LINE_LEN`] octets,
+/// the continuations starting with a space
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_LEN {
+            folded += "\r\n ";
+            len = 1;
+        }
+        folded.push(c);
+        len += c.len_utf8();
+    }
+    folded
+}
+
+fn format_utc(time: NaiveDateTime) -> String {
+    format!("{}Z", time.format(DATE_TIME_FORMAT))
+}
+
+/// Calendar of the occurrences, stamped with the current UTC time
+pub(crate) fn calendar(
+    name: &str,
+    occurrences: &[Occurrence],
+    now: NaiveDateTime,
+) -> String {
+    let head = [
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        "PRODID:-//remindee-bot//EN".to_owned(),
+        "CALSCALE:GREGORIAN".to_owned(),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+    let events = occurrences.iter().flat_map(|occurrence| {
+        [
+            "BEGIN:VEVENT".to_owned(),
+            format!("UID:{}", occurrence.uid),
+            format!("DTSTAMP:{}", format_utc(now)),
+            format!("DTSTART:{}", format_utc(occurrence.time)),
+            format!("SUMMARY:{}", escape_text(&occurrence.summary)),
+            "END:VEVENT".to_owned(),
+        ]
+    });
+    head.into_iter()
+        .chain(events)
+        .chain(std::iter::once("END:VCALENDAR".to_owned()))
+        .map(|line| fold(&line) + "\r\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Amsterdam\r
+BEGIN:STANDARD\r
+DTSTART:19701025T030000\r
+END:STANDARD\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+DTSTART;TZID=Europe/Amsterdam:20240603T090000\r
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE\r
+SUMMARY:Stand-up\\, daily sync with a very long descr\r
+ iption\r
+BEGIN:VALARM\r
+ACTION:DISPLAY\r
+SUMMARY:Alarm\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;TZID=Europe/Amsterdam:20240605T100000\r
+RECURRENCE-ID;TZID=Europe/Amsterdam:20240605T090000\r
+SUMMARY:Stand-up\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+DTSTART;VALUE=DATE:20240610\r
+SUMMARY:Birthday\r
+RRULE:FREQ=YEARLY\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn event(start: &str, rrule: Option<&str>) -> Event {
+        Event {
+            summary: "event".to_owned(),
+            start: Some((start.to_owned(), None)),
+            rrule: rrule.map(str::to_owned),
+            is_override: false,
+        }
+    }
+
+    /// Next occurrences in UTC of the event in UTC
+    fn occurrences(event: Event, from: &str, n: usize) -> Vec<NaiveDateTime> {
+        let mut pattern = event.pattern(Tz::UTC).unwrap();
+        let mut cur = time(from);
+        let mut times = vec![];
+        while times.len() < n {
+            let Some(next) = pattern.next(cur) else { break };
+            times.push(next);
+            cur = next;
+        }
+        times
+    }
+
+    #[test]
+    fn test_events() {
+        let events = events(CALENDAR).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0],
+            Event {
+                summary: "Stand-up, daily sync with a very long description"
+                    .to_owned(),
+                start: Some((
+                    "20240603T090000".to_owned(),
+                    Some("Europe/Amsterdam".to_owned())
+                )),
+                rrule: Some("FREQ=WEEKLY;BYDAY=MO,WE".to_owned()),
+                is_override: false,
+            }
+        );
+        assert_eq!(events[1].summary, "Birthday");
+    }
+
+    #[test]
+    fn test_not_calendar() {
+        assert_eq!(events("0 9 * * * crontab"), None);
+    }
+
+    #[test]
+    fn test_timezone() {
+        let events = events(CALENDAR).unwrap();
+        let mut pattern = events[0].pattern(Tz::UTC).unwrap();
+        assert_eq!(
+            pattern.next(time("2024-06-03 00:00")),
+            Some(time("2024-06-03 07:00"))
+        );
+    }
+
+    #[test_case("20240603T090000", None, "2024-06-01 00:00"
+                => vec![time("2024-06-03 09:00")] ; "single")]
+    #[test_case("20240603T090000", None, "2024-06-04 00:00"
+                => Vec::<NaiveDateTime>::new() ; "past single")]
+    #[test_case("20240603T090000Z", Some("FREQ=DAILY;INTERVAL=2"), "2024-06-01 00:00"
+                => vec![time("2024-06-03 09:00"), time("2024-06-05 09:00"), time("2024-06-07 09:00")] ;
+                "every other day")]
+    #[test_case("20240603", Some("FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR"), "2024-06-06 12:00"
+                => vec![time("2024-06-07 09:00"), time("2024-06-10 09:00"), time("2024-06-11 09:00")] ;
+                "workdays")]
+    #[test_case("20240604T090000", Some("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TU"), "2024-06-01 00:00"
+                => vec![time("2024-06-04 09:00"), time("2024-06-17 09:00"), time("2024-06-18 09:00")] ;
+                "every other week")]
+    #[test_case("20240603T090000", Some("FREQ=WEEKLY;COUNT=3;BYDAY=MO,WE"), "2024-06-04 00:00"
+                => vec![time("2024-06-05 09:00"), time("2024-06-10 09:00")] ;
+                "count")]
+    #[test_case("20240115T090000", Some("FREQ=MONTHLY;UNTIL=20240401T000000Z"), "2024-01-15 10:00"
+                => vec![time("2024-02-15 09:00"), time("2024-03-15 09:00")] ;
+                "monthly until")]
+    #[test_case("20240229T090000", Some("FREQ=YEARLY;INTERVAL=4;BYMONTH=2"), "2024-03-01 00:00"
+                => vec![time("2028-02-29 09:00"), time("2032-02-29 09:00"), time("2036-02-29 09:00")] ;
+                "leap years")]
+    fn test_occurrences(
+        start: &str,
+        rrule: Option<&str>,
+        from: &str,
+    ) -> Vec<NaiveDateTime> {
+        occurrences(event(start, rrule), from, 3)
+    }
+
+    #[test_case("FREQ=HOURLY" ; "hourly")]
+    #[test_case("FREQ=MONTHLY;BYDAY=2TU" ; "nth weekday")]
+    #[test_case("FREQ=MONTHLY;BYMONTHDAY=1,15" ; "several month days")]
+    #[test_case("FREQ=DAILY;INTERVAL=2;BYDAY=MO" ; "weekdays with interval")]
+    #[test_case("INTERVAL=2" ; "without frequency")]
+    fn test_unsupported(rrule: &str) {
+        assert!(event("20240603T090000", Some(rrule))
+            .pattern(Tz::UTC)
+            .is_none());
+    }
+
+    #[test]
+    fn test_calendar() {
+        let occurrences = [Occurrence {
+            uid: "rem-1-1717228800@remindee".to_owned(),
+            summary: "Call mom, then dad; bring\ncake".to_owned(),
+            time: time("2024-06-01 08:00"),
+        }];
+        assert_eq!(
+            calendar("Reminders", &occurrences, time("2024-05-31 12:00")),
+            "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//remindee-bot//EN\r
+CALSCALE:GREGORIAN\r
+X-WR-CALNAME:Reminders\r
+BEGIN:VEVENT\r
+UID:rem-1-1717228800@remindee\r
+DTSTAMP:20240531T120000Z\r
+DTSTART:20240601T080000Z\r
+SUMMARY:Call mom\\, then dad\\; bring\\ncake\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+        );
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = format!("SUMMARY:{}", "ü".repeat(40));
+        let folded = fold(&line);
+        assert!(folded.split("\r\n").all(|line| line.len() <= MAX_LINE_LEN));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
@@ -0,0 +1,447 @@
+use chrono::Weekday;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Languages the bot can talk to users in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) enum Language {
+    #[default]
+    English,
+    Russian,
+    German,
+    Spanish,
+}
+
+/// Text of a response in a locale file, or the forms of a counted noun
+/// by the plural category of the count, e.g. `one` and `other`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Text(String),
+    Plural(HashMap<String, String>),
+}
+
+lazy_static! {
+    /// Responses of each language read from its file in `locales/`
+    static ref LOCALES: HashMap<Language, HashMap<String, Entry>> =
+        Language::ALL
+            .into_iter()
+            .map(|lang| {
+                let entries = serde_yaml::from_str(lang.locale())
+                    .unwrap_or_else(|err| {
+                        panic!("Malformed locale {}: {}", lang.code(), err)
+                    });
+                (lang, entries)
+            })
+            .collect();
+}
+
+impl Language {
+    pub(crate) const ALL: [Self; 4] =
+        [Self::English, Self::Russian, Self::German, Self::Spanish];
+
+    /// IETF language tag to store and pass in callbacks
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Self::English => "en",
+            Self::Russian => "ru",
+            Self::German => "de",
+            Self::Spanish => "es",
+        }
+    }
+
+    /// Name of the language in the language itself
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::English => "🇬🇧 English",
+            Self::Russian => "🇷🇺 Русский",
+            Self::German => "🇩🇪 Deutsch",
+            Self::Spanish => "🇪🇸 Español",
+        }
+    }
+
+    fn locale(&self) -> &'static str {
+        match self {
+            Self::English => include_str!("../locales/en.yml"),
+            Self::Russian => include_str!("../locales/ru.yml"),
+            Self::German => include_str!("../locales/de.yml"),
+            Self::Spanish => include_str!("../locales/es.yml"),
+        }
+    }
+
+    /// Abbreviated name of the weekday, e.g. `Mon`
+    pub(crate) fn weekday_abbr(&self, weekday: Weekday) -> &'static str {
+        match self {
+            Self::English => match weekday {
+                Weekday::Mon => "Mon",
+                Weekday::Tue => "Tue",
+                Weekday::Wed => "Wed",
+                Weekday::Thu => "Thu",
+                Weekday::Fri => "Fri",
+                Weekday::Sat => "Sat",
+                Weekday::Sun => "Sun",
+            },
+            Self::Russian => match weekday {
+                Weekday::Mon => "Пн",
+                Weekday::Tue => "Вт",
+                Weekday::Wed => "Ср",
+                Weekday::Thu => "Чт",
+                Weekday::Fri => "Пт",
+                Weekday::Sat => "Сб",
+                Weekday::Sun => "Вс",
+            },
+            Self::German => match weekday {
+                Weekday::Mon => "Mo",
+                Weekday::Tue => "Di",
+                Weekday::Wed => "Mi",
+                Weekday::Thu => "Do",
+                Weekday::Fri => "Fr",
+                Weekday::Sat => "Sa",
+                Weekday::Sun => "So",
+            },
+            Self::Spanish => match weekday {
+                Weekday::Mon => "lun",
+                Weekday::Tue => "mar",
+                Weekday::Wed => "mié",
+                Weekday::Thu => "jue",
+                Weekday::Fri => "vie",
+                Weekday::Sat => "sáb",
+                Weekday::Sun => "dom",
+            },
+        }
+    }
+
+    /// Name of the weekday, e.g. `Monday`
+    pub(crate) fn weekday_name(&self, weekday: Weekday) -> &'static str {
+        let names = match self {
+            Self::English => [
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+                "Sunday",
+            ],
+            Self::Russian => [
+                "понедельник",
+                "вторник",
+                "среда",
+                "четверг",
+                "пятница",
+                "суббота",
+                "воскресенье",
+            ],
+            Self::German => [
+                "Montag",
+                "Dienstag",
+                "Mittwoch",
+                "Donnerstag",
+                "Freitag",
+                "Samstag",
+                "Sonntag",
+            ],
+            Self::Spanish => [
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábado",
+                "domingo",
+            ],
+        };
+        names[weekday.num_days_from_monday() as usize]
+    }
+
+    /// Name of the weekday saying it recurs, e.g. `Mondays` in
+    /// `on Mondays`
+    pub(crate) fn weekday_plural(&self, weekday: Weekday) -> &'static str {
+        let names = match self {
+            Self::English => [
+                "Mondays",
+                "Tuesdays",
+                "Wednesdays",
+                "Thursdays",
+                "Fridays",
+                "Saturdays",
+                "Sundays",
+            ],
+            Self::Russian => [
+                "понедельникам",
+                "вторникам",
+                "средам",
+                "четвергам",
+                "пятницам",
+                "субботам",
+                "воскресеньям",
+            ],
+            Self::German => [
+                "montags",
+                "dienstags",
+                "mittwochs",
+                "donnerstags",
+                "freitags",
+                "samstags",
+                "sonntags",
+            ],
+            Self::Spanish => [
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábados",
+                "domingos",
+            ],
+        };
+        names[weekday.num_days_from_monday() as usize]
+    }
+
+    /// Name of the month numbered from 1, e.g. `January`
+    pub(crate) fn month_name(&self, month: u32) -> Option<&'static str> {
+        let names = match self {
+            Self::English => [
+                "January",
+                "February",
+                "March",
+                "April",
+                "May",
+                "June",
+                "July",
+                "August",
+                "September",
+                "October",
+                "November",
+                "December",
+            ],
+            Self::Russian => [
+                "январь",
+                "февраль",
+                "март",
+                "апрель",
+                "май",
+                "июнь",
+                "июль",
+                "август",
+                "сентябрь",
+                "октябрь",
+                "ноябрь",
+                "декабрь",
+            ],
+            Self::German => [
+                "Januar",
+                "Februar",
+                "März",
+                "April",
+                "Mai",
+                "Juni",
+                "Juli",
+                "August",
+                "September",
+                "Oktober",
+                "November",
+                "Dezember",
+            ],
+            Self::Spanish => [
+                "enero",
+                "febrero",
+                "marzo",
+                "abril",
+                "mayo",
+                "junio",
+                "julio",
+                "agosto",
+                "septiembre",
+                "octubre",
+                "noviembre",
+                "diciembre",
+            ],
+        };
+        names.get((month as usize).checked_sub(1)?).copied()
+    }
+
+    /// Day of the month as it's counted, e.g. `1st`
+    pub(crate) fn ordinal(&self, day: u32) -> String {
+        match self {
+            Self::English => {
+                let suffix = match (day % 10, day % 100) {
+                    (_, 11..=13) => "th",
+                    (1, _) => "st",
+                    (2, _) => "nd",
+                    (3, _) => "rd",
+                    _ => "th",
+                };
+                format!("{}{}", day, suffix)
+            }
+            Self::German => format!("{}.", day),
+            Self::Russian | Self::Spanish => day.to_string(),
+        }
+    }
+
+    /// Plural category of the count in the language's grammar
+    fn plural_category(&self, count: u64) -> &'static str {
+        match self {
+            Self::Russian => match (count % 10, count % 100) {
+                (1, n) if n != 11 => "one",
+                (2..=4, n) if !(12..=14).contains(&n) => "few",
+                _ => "many",
+            },
+            Self::English | Self::German | Self::Spanish => {
+                if count == 1 {
+                    "one"
+                } else {
+                    "other"
+                }
+            }
+        }
+    }
+
+    /// Entry of the locale file, the English one if it isn't translated
+    fn entry(&self, key: &str) -> Option<&'static Entry> {
+        LOCALES[self]
+            .get(key)
+            .or_else(|| LOCALES[&Self::English].get(key))
+    }
+
+    /// Response with the key with its `{name}` placeholders filled in
+    pub(crate) fn text(
+        &self,
+        key: &str,
+        args: &[(&str, &dyn Display)],
+    ) -> String {
+        match self.entry(key) {
+            Some(Entry::Text(template)) => fill(template, args),
+            _ => {
+                tracing::error!("no text {} in locale {}", key, self.code());
+                key.to_owned()
+            }
+        }
+    }
+
+    /// Count followed by the noun with the key in the form it takes,
+    /// e.g. `1 reminder` or `5 reminders`
+    pub(crate) fn plural(&self, key: &str, count: u64) -> String {
+        let forms = match self.entry(key) {
+            Some(Entry::Plural(forms)) => forms,
+            _ => {
+                tracing::error!("no noun {} in locale {}", key, self.code());
+                return count.to_string();
+            }
+        };
+        let template = forms
+            .get(self.plural_category(count))
+            .or_else(|| forms.get("other"))
+            .map_or("{count}", String::as_str);
+        fill(template, &[("count", &count)])
+    }
+
+    pub(crate) fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lang| lang.code() == code)
+    }
+
+    /// Language of a Telegram app set to the locale, e.g. `en-GB`,
+    /// `None` if the bot doesn't speak it
+    pub(crate) fn from_locale(locale: &str) -> Option<Self> {
+        let primary = locale.split(['-', '_']).next()?.to_lowercase();
+        Self::from_code(&primary)
+    }
+}
+
+/// Template with its `{name}` placeholders replaced by the arguments in
+/// one pass, so the braces in the arguments are kept as they are
+fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let arg = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            args.iter()
+                .find(|(arg_name, _)| *arg_name == name)
+                .map(|(_, value)| (end, value))
+        });
+        match arg {
+            Some((end, value)) => {
+                filled.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use regex::Regex;
+    use std::collections::BTreeSet;
+    use test_case::test_case;
+
+    #[test_case("en" => Some(Language::English) ; "language")]
+    #[test_case("en-GB" => Some(Language::English) ; "with region")]
+    #[test_case("EN_us" => Some(Language::English) ; "other case and separator")]
+    #[test_case("ru" => Some(Language::Russian) ; "other language")]
+    #[test_case("nl" => None ; "unsupported")]
+    #[test_case("" => None ; "empty")]
+    fn test_from_locale(locale: &str) -> Option<Language> {
+        Language::from_locale(locale)
+    }
+
+    #[test_case(Language::English, 1 => "1 reminder" ; "english one")]
+    #[test_case(Language::English, 0 => "0 reminders" ; "english zero")]
+    #[test_case(Language::Russian, 1 => "1 напоминание" ; "russian one")]
+    #[test_case(Language::Russian, 3 => "3 напоминания" ; "russian few")]
+    #[test_case(Language::Russian, 5 => "5 напоминаний" ; "russian many")]
+    #[test_case(Language::Russian, 12 => "12 напоминаний" ; "russian teen")]
+    #[test_case(Language::Russian, 21 => "21 напоминание" ; "russian compound one")]
+    #[test_case(Language::German, 2 => "2 Erinnerungen" ; "german other")]
+    #[test_case(Language::Spanish, 1 => "1 recordatorio" ; "spanish one")]
+    fn test_plural(lang: Language, count: u64) -> String {
+        lang.plural("reminders", count)
+    }
+
+    #[test_case("Added {reminder}", "{reminder} {x}" => "Added {reminder} {x}" ; "braces in argument")]
+    #[test_case("{unknown} {reminder}", "tea" => "{unknown} tea" ; "unknown placeholder")]
+    #[test_case("{reminder", "tea" => "{reminder" ; "unclosed")]
+    fn test_fill(template: &str, reminder: &str) -> String {
+        fill(template, &[("reminder", &reminder)])
+    }
+
+    /// Placeholders of each entry of the locale, the forms of the nouns
+    /// are told apart by a suffix
+    fn placeholders(lang: Language) -> BTreeSet<(String, String)> {
+        let placeholder = Regex::new(r"\{([a-z_]+)\}").unwrap();
+        let mut found = BTreeSet::new();
+        for (key, entry) in &LOCALES[&lang] {
+            let texts = match entry {
+                Entry::Text(text) => vec![(key.clone(), text)],
+                Entry::Plural(forms) => forms
+                    .values()
+                    .map(|form| (key.clone() + "#", form))
+                    .collect(),
+            };
+            for (key, text) in texts {
+                found.insert((key.clone(), String::new()));
+                for captures in placeholder.captures_iter(text) {
+                    found.insert((key.clone(), captures[1].to_owned()));
+                }
+            }
+        }
+        found
+    }
+
+    #[test_case(Language::Russian ; "russian")]
+    #[test_case(Language::German ; "german")]
+    #[test_case(Language::Spanish ; "spanish")]
+    fn test_locale_complete(lang: Language) {
+        assert_eq!(placeholders(lang), placeholders(Language::English));
+    }
+}
@@ -0,0 +1,17 @@
+//! Logs of the bot, as text for people or as JSON lines for log
+//! collectors. The records logged while handling an update or sending
+//! the reminders of a chat carry the chat and the user they're about.
+
+use crate::cli::LogFormat;
+use tracing_subscriber::EnvFilter;
+
+/// Log the records enabled with `RUST_LOG`, only the errors by default,
+/// including the ones of the libraries logging with `log`
+pub(crate) fn init(format: LogFormat) {
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env());
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
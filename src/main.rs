@@ -1,28 +1,66 @@
 #[macro_use]
 extern crate lazy_static;
 extern crate pest;
-extern crate pretty_env_logger;
 #[macro_use]
 extern crate pest_derive;
 
+mod account;
+mod advance;
+#[cfg(feature = "ai")]
+mod ai;
+mod api;
+mod backup;
+mod bill;
 mod bot;
+mod broadcast;
+mod bulk;
 mod cli;
 mod controller;
+mod cron;
 mod date;
 mod db;
+mod digest;
 mod entity;
 mod err;
+mod feed;
+#[cfg(test)]
+mod flaky;
 mod format;
 mod generic_reminder;
 mod grammar;
 mod handlers;
+mod history;
+mod ical;
+mod lang;
+mod logging;
+mod maintenance;
+mod manage;
+mod meeting;
 mod migration;
 mod parsers;
+mod picker;
+mod policy;
+mod pomodoro;
+mod probe;
+mod retry;
+mod search;
 mod serializers;
+mod series;
+mod settings;
+mod share;
+mod shortcut;
+mod stress;
+mod template;
+mod text_menu;
 mod tg;
 mod tz;
+mod undo;
+mod vacation;
 
 #[tokio::main]
 async fn main() {
-    bot::run().await;
+    match cli::CLI.command {
+        Some(cli::Command::Stress(ref args)) => stress::run(args).await,
+        None => bot::run().await,
+    }
 }
@@ -0,0 +1,73 @@
+//! Keeping the database of a long-running deployment small and fast:
+//! every few days the records nothing refers to anymore, the history
+//! older than it's kept for and the reminders long in the trash are
+//! deleted, then the database is vacuumed.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(not(test))]
+use crate::db::Database;
+#[cfg(test)]
+use crate::db::MockDatabase as Database;
+use crate::history;
+use crate::parsers::now_time;
+use chrono::TimeDelta;
+use tokio_util::sync::CancellationToken;
+
+/// Time the deleted reminders can be restored for
+pub(crate) const TRASH_KEPT_FOR: TimeDelta = TimeDelta::days(30);
+
+/// Maintain the database every `days` days until the shutdown
+pub(crate) async fn poll(
+    db: Arc<Database>,
+    days: u64,
+    shutdown: CancellationToken,
+) {
+    let interval = Duration::from_secs(days * 24 * 60 * 60);
+    loop {
+        tokio::select! {
+            () = shutdown.cancelled() => break,
+            () = tokio::time::sleep(interval) => {}
+        }
+        tokio::select! {
+            () = shutdown.cancelled() => break,
+            () = maintain(&db) => {}
+        }
+    }
+}
+
+async fn maintain(db: &Database) {
+    let now = now_time();
+    match db
+        .prune(history::keep_since(now), now - TRASH_KEPT_FOR)
+        .await
+    {
+        Ok(deleted) => tracing::info!("Pruned {} stale records", deleted),
+        Err(err) => tracing::error!("{}", err),
+    }
+    if let Err(err) = db.vacuum().await {
+        tracing::error!("{}", err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::err::Error;
+    use crate::migration::DbErr;
+    use mockall::predicate::always;
+
+    #[tokio::test]
+    async fn test_maintain_vacuums_after_failed_prune() {
+        let mut db = Database::new();
+        db.expect_prune()
+            .with(always(), always())
+            .times(1)
+            .returning(|_, _| {
+                Err(Error::Database(DbErr::Custom("locked".to_owned())))
+            });
+        db.expect_vacuum().times(1).returning(|| Ok(()));
+        maintain(&db).await;
+    }
+}
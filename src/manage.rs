@@ -0,0 +1,194 @@
+//! Managing group reminders in private: the notifications in groups link
+//! to the private chat with the bot, where the members of the group pause,
+//! edit or delete the reminder without pressing buttons in the group
+
+use crate::db::LinkTarget;
+use crate::lang::Language;
+use teloxide::types::{
+    ChatId, InlineKeyboardButton, InlineKeyboardButtonKind,
+    InlineKeyboardMarkup, MessageId,
+};
+use url::Url;
+
+const PAYLOAD_PREFIX: &str = "manage";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Action {
+    /// Send the buttons to manage the reminder
+    Show,
+    Pause,
+    Edit,
+    Delete,
+}
+
+impl Action {
+    const ALL: [Self; 4] = [Self::Show, Self::Pause, Self::Edit, Self::Delete];
+
+    fn code(self) -> &'static str {
+        match self {
+            Self::Show => "show",
+            Self::Pause => "pause",
+            Self::Edit => "edit",
+            Self::Delete => "del",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.code() == code)
+    }
+}
+
+/// Start payload pointing at a notification. Telegram only allows
+/// letters, digits, `_` and `-` in it.
+fn payload(chat_id: ChatId, msg_id: MessageId) -> String {
+    format!("{}{}_{}", PAYLOAD_PREFIX, chat_id.0, msg_id.0)
+}
+
+/// Chat and message of the notification the start payload points at
+pub(crate) fn parse_payload(payload: &str) -> Option<(ChatId, MessageId)> {
+    let (chat_id, msg_id) =
+        payload.strip_prefix(PAYLOAD_PREFIX)?.split_once('_')?;
+    Some((
+        ChatId(chat_id.parse().ok()?),
+        MessageId(msg_id.parse().ok()?),
+    ))
+}
+
+/// Button of a group notification opening the private chat with the bot
+pub(crate) fn link_button(
+    bot_username: &str,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    lang: Language,
+) -> Option<InlineKeyboardButton> {
+    let url = Url::parse(&format!(
+        "https://t.me/{}?start={}",
+        bot_username,
+        payload(chat_id, msg_id)
+    ))
+    .ok()?;
+    Some(InlineKeyboardButton::url(
+        lang.text("button_manage", &[]),
+        url,
+    ))
+}
+
+fn callback_data(action: Action, target: LinkTarget) -> String {
+    let target_data = match target {
+        LinkTarget::Reminder(id) => format!("rem::{}", id),
+        LinkTarget::CronReminder(id) => format!("cron_rem::{}", id),
+    };
+    format!("manage::{}::{}", action.code(), target_data)
+}
+
+/// Button sending the buttons to manage the reminder
+pub(crate) fn show_button(
+    label: String,
+    target: LinkTarget,
+) -> InlineKeyboardButton {
+    InlineKeyboardButton::new(
+        label,
+        InlineKeyboardButtonKind::CallbackData(callback_data(
+            Action::Show,
+            target,
+        )),
+    )
+}
+
+/// Buttons to manage the reminder. Only the descriptions of the reminders
+/// can be edited, and only in private: new time patterns would move them
+/// there, and the new descriptions aren't awaited in groups.
+pub(crate) fn markup(
+    target: LinkTarget,
+    paused: bool,
+    editable: bool,
+    lang: Language,
+) -> InlineKeyboardMarkup {
+    let button = |key: &str, action: Action| {
+        InlineKeyboardButton::new(
+            lang.text(key, &[]),
+            InlineKeyboardButtonKind::CallbackData(callback_data(
+                action, target,
+            )),
+        )
+    };
+    let mut buttons = vec![if paused {
+        button("button_resume", Action::Pause)
+    } else {
+        button("button_pause", Action::Pause)
+    }];
+    if let (LinkTarget::Reminder(_), true) = (target, editable) {
+        buttons.push(button("button_edit_description", Action::Edit));
+    }
+    buttons.push(button("button_delete", Action::Delete));
+    InlineKeyboardMarkup::new(vec![buttons])
+}
+
+/// Action of the pressed button and the reminder it's for
+pub(crate) fn parse_callback(cb_data: &str) -> Option<(Action, LinkTarget)> {
+    let (action, target) =
+        cb_data.strip_prefix("manage::")?.split_once("::")?;
+    let (kind, id) = target.split_once("::")?;
+    let id = id.parse().ok()?;
+    let target = match kind {
+        "rem" => LinkTarget::Reminder(id),
+        "cron_rem" => LinkTarget::CronReminder(id),
+        _ => return None,
+    };
+    Some((Action::from_code(action)?, target))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(ChatId(-1001234567890), MessageId(42) ; "supergroup")]
+    #[test_case(ChatId(-123), MessageId(1) ; "group")]
+    fn test_payload_round_trip(chat_id: ChatId, msg_id: MessageId) {
+        let payload = payload(chat_id, msg_id);
+        assert!(payload.len() <= 64);
+        assert!(payload
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+        assert_eq!(parse_payload(&payload), Some((chat_id, msg_id)));
+    }
+
+    #[test_case("" ; "empty")]
+    #[test_case("manage-1_x" ; "bad message id")]
+    #[test_case("ref_friend" ; "other payload")]
+    fn test_parse_bad_payload(payload: &str) {
+        assert_eq!(parse_payload(payload), None);
+    }
+
+    #[test]
+    fn test_markup_callbacks() {
+        for target in [LinkTarget::Reminder(7), LinkTarget::CronReminder(8)] {
+            let markup = markup(target, false, true, Language::English);
+            let parsed: Vec<_> = markup.inline_keyboard[0]
+                .iter()
+                .filter_map(|button| match button.kind {
+                    InlineKeyboardButtonKind::CallbackData(ref data) => {
+                        parse_callback(data)
+                    }
+                    _ => None,
+                })
+                .collect();
+            let expected = match target {
+                LinkTarget::Reminder(_) => {
+                    vec![Action::Pause, Action::Edit, Action::Delete]
+                }
+                LinkTarget::CronReminder(_) => {
+                    vec![Action::Pause, Action::Delete]
+                }
+            };
+            assert_eq!(
+                parsed,
+                expected
+                    .into_iter()
+                    .map(|action| (action, target))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+}
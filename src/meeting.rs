@@ -0,0 +1,150 @@
+//! Meetings: reminders starting with `meeting` invite the chat an hour
+//! before the start, let everyone answer whether they're coming and sum
+//! up the answers shortly before the start
+
+use crate::entity::rsvp;
+use crate::lang::Language;
+use crate::tg::TgResponse;
+use chrono::{NaiveDateTime, TimeDelta};
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+};
+
+/// How long before the start the invitation is sent
+pub(crate) const INVITE_LEAD: TimeDelta = TimeDelta::hours(1);
+/// How long before the start the answers are summed up
+pub(crate) const SUMMARY_LEAD: TimeDelta = TimeDelta::minutes(15);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Answer {
+    Yes,
+    Maybe,
+    No,
+}
+
+impl Answer {
+    const ALL: [Self; 3] = [Self::Yes, Self::Maybe, Self::No];
+
+    /// Stored and callback data representation
+    pub(crate) fn code(self) -> &'static str {
+        match self {
+            Self::Yes => "yes",
+            Self::Maybe => "maybe",
+            Self::No => "no",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|answer| answer.code() == code)
+    }
+
+    fn label(self, lang: Language) -> String {
+        let key = match self {
+            Self::Yes => "button_yes",
+            Self::Maybe => "button_maybe",
+            Self::No => "button_no",
+        };
+        lang.text(key, &[])
+    }
+}
+
+/// Time to sum up the answers of a meeting starting at the time
+pub(crate) fn summary_time(start: NaiveDateTime) -> NaiveDateTime {
+    start - SUMMARY_LEAD
+}
+
+pub(crate) fn rsvp_markup(occ_id: i64, lang: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![Answer::ALL
+        .into_iter()
+        .map(|answer| {
+            InlineKeyboardButton::new(
+                answer.label(lang),
+                InlineKeyboardButtonKind::CallbackData(format!(
+                    "rsvp::{}::{}",
+                    occ_id,
+                    answer.code()
+                )),
+            )
+        })
+        .collect::<Vec<_>>()])
+}
+
+/// Occurrence id and the answer of the pressed button
+pub(crate) fn parse_callback(cb_data: &str) -> Option<(i64, Answer)> {
+    let (occ_id, code) = cb_data.strip_prefix("rsvp::")?.split_once("::")?;
+    Some((occ_id.parse().ok()?, Answer::from_code(code)?))
+}
+
+/// Time left until the start and the names of those who answered,
+/// grouped by the answer
+pub(crate) fn summary(
+    desc: &str,
+    start: NaiveDateTime,
+    now: NaiveDateTime,
+    rsvps: &[rsvp::Model],
+    lang: Language,
+) -> String {
+    let minutes_left = (start - now).num_minutes();
+    std::iter::once(TgResponse::MeetingSummary(desc.to_owned(), minutes_left))
+        .chain(Answer::ALL.into_iter().map(|answer| {
+            TgResponse::MeetingAnswers(
+                answer.label(lang),
+                rsvps
+                    .iter()
+                    .filter(|rsvp| rsvp.answer == answer.code())
+                    .map(|rsvp| rsvp.name.clone())
+                    .collect(),
+            )
+        }))
+        .map(|response| response.to_string_in(lang))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("rsvp::12::yes" => Some((12, Answer::Yes)) ; "yes")]
+    #[test_case("rsvp::3::maybe" => Some((3, Answer::Maybe)) ; "maybe")]
+    #[test_case("rsvp::3::perhaps" => None ; "unknown answer")]
+    #[test_case("rsvp::x::no" => None ; "bad id")]
+    #[test_case("donerem::occ::3" => None ; "other button")]
+    fn test_parse_callback(cb_data: &str) -> Option<(i64, Answer)> {
+        parse_callback(cb_data)
+    }
+
+    fn rsvp(user_id: i64, name: &str, answer: Answer) -> rsvp::Model {
+        rsvp::Model {
+            id: user_id,
+            occurrence_id: 1,
+            user_id,
+            name: name.to_owned(),
+            answer: answer.code().to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let start = NaiveDateTime::default();
+        let rsvps = [
+            rsvp(1, "Alice", Answer::Yes),
+            rsvp(2, "Bob", Answer::No),
+            rsvp(3, "Carol", Answer::Yes),
+        ];
+        assert_eq!(
+            summary(
+                "standup",
+                start,
+                start - SUMMARY_LEAD,
+                &rsvps,
+                Language::English
+            ),
+            "🤝 standup starts in 15 minutes\n\
+             ✅ Yes \\(2\\): Alice, Carol\n\
+             🤔 Maybe: nobody\n\
+             ❌ No \\(1\\): Bob"
+        );
+    }
+}
@@ -13,7 +13,7 @@ impl MigrationTrait for Migration {
                     .if_not_exists()
                     .col(
                         ColumnDef::new(UserTimezone::UserId)
-                            .integer()
+                            .big_integer()
                             .not_null()
                             .primary_key(),
                     )
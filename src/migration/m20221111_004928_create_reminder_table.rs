@@ -13,12 +13,16 @@ impl MigrationTrait for Migration {
                     .if_not_exists()
                     .col(
                         ColumnDef::new(Reminder::Id)
-                            .integer()
+                            .big_integer()
                             .not_null()
                             .primary_key()
                             .auto_increment(),
                     )
-                    .col(ColumnDef::new(Reminder::UserId).integer().not_null())
+                    .col(
+                        ColumnDef::new(Reminder::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
                     .col(ColumnDef::new(Reminder::Time).date_time().not_null())
                     .col(ColumnDef::new(Reminder::Desc).text().not_null())
                     .col(ColumnDef::new(Reminder::Sent).boolean().not_null())
@@ -13,14 +13,14 @@ impl MigrationTrait for Migration {
                     .if_not_exists()
                     .col(
                         ColumnDef::new(CronReminder::Id)
-                            .integer()
+                            .big_integer()
                             .not_null()
                             .primary_key()
                             .auto_increment(),
                     )
                     .col(
                         ColumnDef::new(CronReminder::UserId)
-                            .integer()
+                            .big_integer()
                             .not_null(),
                     )
                     .col(
@@ -29,7 +29,9 @@ impl MigrationTrait for Migration {
             .alter_table(
                 Table::alter()
                     .table(CronReminder::Table)
-                    .add_column(ColumnDef::new(CronReminder::UserId).integer())
+                    .add_column(
+                        ColumnDef::new(CronReminder::UserId).big_integer(),
+                    )
                     .to_owned(),
             )
             .await?;
@@ -37,7 +39,7 @@ impl MigrationTrait for Migration {
             .alter_table(
                 Table::alter()
                     .table(Reminder::Table)
-                    .add_column(ColumnDef::new(Reminder::UserId).integer())
+                    .add_column(ColumnDef::new(Reminder::UserId).big_integer())
                     .to_owned(),
             )
             .await
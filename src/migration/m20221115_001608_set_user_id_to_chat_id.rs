@@ -1,4 +1,4 @@
-use sea_orm::{ConnectionTrait, Statement};
+use sea_orm::ConnectionTrait;
 use sea_orm_migration::prelude::*;
 
 #[derive(DeriveMigrationName)]
@@ -7,18 +7,35 @@ pub struct Migration;
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        let sql = r#"
-            UPDATE `reminder` SET `user_id` = `chat_id`;
-            UPDATE `cron_reminder` SET `user_id` = `chat_id`
-        "#;
-        let stmt = Statement::from_string(
-            manager.get_database_backend(),
-            sql.to_owned(),
-        );
-        manager.get_connection().execute(stmt).await.map(|_| ())
+        let conn = manager.get_connection();
+        let db_backend = manager.get_database_backend();
+        let query = Query::update()
+            .table(Reminder::Table)
+            .value(Reminder::UserId, Expr::col(Reminder::ChatId))
+            .to_owned();
+        conn.execute(db_backend.build(&query)).await?;
+        let query = Query::update()
+            .table(CronReminder::Table)
+            .value(CronReminder::UserId, Expr::col(CronReminder::ChatId))
+            .to_owned();
+        conn.execute(db_backend.build(&query)).await.map(|_| ())
     }
 
     async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
         Ok(())
     }
 }
+
+#[derive(Iden)]
+pub enum Reminder {
+    Table,
+    ChatId,
+    UserId,
+}
+
+#[derive(Iden)]
+pub enum CronReminder {
+    Table,
+    ChatId,
+    UserId,
+}
@@ -1,4 +1,4 @@
-use sea_orm::{ConnectionTrait, Statement};
+use sea_orm::ConnectionTrait;
 use sea_orm_migration::prelude::*;
 
 #[derive(DeriveMigrationName)]
@@ -10,18 +10,16 @@ impl MigrationTrait for Migration {
         let conn = manager.get_connection();
         let db_backend = manager.get_database_backend();
         // Delete sent reminders
-        let sql = Query::delete()
+        let query = Query::delete()
             .from_table(CronReminder::Table)
             .and_where(Expr::col(CronReminder::Sent).eq(true))
-            .to_string(SqliteQueryBuilder);
-        let stmt = Statement::from_string(db_backend, sql);
-        conn.execute(stmt).await?;
-        let sql = Query::delete()
+            .to_owned();
+        conn.execute(db_backend.build(&query)).await?;
+        let query = Query::delete()
             .from_table(Reminder::Table)
             .and_where(Expr::col(Reminder::Sent).eq(true))
-            .to_string(SqliteQueryBuilder);
-        let stmt = Statement::from_string(db_backend, sql);
-        conn.execute(stmt).await?;
+            .to_owned();
+        conn.execute(db_backend.build(&query)).await?;
 
         // Remove sent columns
         manager
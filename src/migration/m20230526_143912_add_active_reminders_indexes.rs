@@ -33,13 +33,17 @@ impl MigrationTrait for Migration {
     async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
         manager
             .drop_index(
-                Index::drop().name("ix_reminder_paused_time").to_owned(),
+                Index::drop()
+                    .name("ix_reminder_paused_time")
+                    .table(Reminder::Table)
+                    .to_owned(),
             )
             .await?;
         manager
             .drop_index(
                 Index::drop()
                     .name("ix_cron_reminder_paused_time")
+                    .table(CronReminder::Table)
                     .to_owned(),
             )
             .await
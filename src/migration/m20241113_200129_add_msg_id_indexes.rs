@@ -30,11 +30,19 @@ impl MigrationTrait for Migration {
 
     async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
         manager
-            .drop_index(Index::drop().name("ix_reminder_msg_id").to_owned())
+            .drop_index(
+                Index::drop()
+                    .name("ix_reminder_msg_id")
+                    .table(Reminder::Table)
+                    .to_owned(),
+            )
             .await?;
         manager
             .drop_index(
-                Index::drop().name("ix_cron_reminder_msg_id").to_owned(),
+                Index::drop()
+                    .name("ix_cron_reminder_msg_id")
+                    .table(CronReminder::Table)
+                    .to_owned(),
             )
             .await
     }
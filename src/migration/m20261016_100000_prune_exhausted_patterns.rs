@@ -0,0 +1,64 @@
+use sea_orm::ConnectionTrait;
+use sea_orm_migration::prelude::*;
+
+use crate::serializers::Pattern;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+        let db_backend = manager.get_database_backend();
+        let query = Query::select()
+            .columns([Reminder::Id, Reminder::Time, Reminder::Pattern])
+            .from(Reminder::Table)
+            .and_where(Expr::col(Reminder::Pattern).is_not_null())
+            .to_owned();
+        let rows = conn.query_all(db_backend.build(&query)).await?;
+
+        // Collect reminders whose patterns have no occurrences left
+        let mut ids = vec![];
+        for row in rows {
+            let id: i64 = row.try_get("", &Reminder::Id.to_string())?;
+            let time: chrono::NaiveDateTime =
+                row.try_get("", &Reminder::Time.to_string())?;
+            let pattern: String =
+                row.try_get("", &Reminder::Pattern.to_string())?;
+            match serde_json::from_str::<Pattern>(&pattern) {
+                Ok(pattern) if pattern.is_exhausted(time) => ids.push(id),
+                Ok(_) => {}
+                Err(err) => {
+                    tracing::warn!(
+                        "skip reminder {} with bad pattern: {}",
+                        id,
+                        err
+                    )
+                }
+            }
+        }
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let query = Query::update()
+            .table(Reminder::Table)
+            .value(Reminder::Pattern, Option::<String>::None)
+            .and_where(Expr::col(Reminder::Id).is_in(ids))
+            .to_owned();
+        conn.execute(db_backend.build(&query)).await.map(|_| ())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Reminder {
+    Table,
+    Id,
+    Time,
+    Pattern,
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create policy column
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .add_column(ColumnDef::new(CronReminder::Policy).string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .add_column(ColumnDef::new(Reminder::Policy).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove policy column
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .drop_column(CronReminder::Policy)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .drop_column(Reminder::Policy)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CronReminder {
+    Table,
+    Policy,
+}
+
+#[derive(Iden)]
+pub enum Reminder {
+    Table,
+    Policy,
+}
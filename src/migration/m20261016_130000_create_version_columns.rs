@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create version column
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .add_column(
+                        ColumnDef::new(CronReminder::Version)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .add_column(
+                        ColumnDef::new(Reminder::Version)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove version column
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .drop_column(CronReminder::Version)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .drop_column(Reminder::Version)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CronReminder {
+    Table,
+    Version,
+}
+
+#[derive(Iden)]
+pub enum Reminder {
+    Table,
+    Version,
+}
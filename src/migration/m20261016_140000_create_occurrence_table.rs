@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Occurrence::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Occurrence::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(
+                        ColumnDef::new(Occurrence::ChatId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Occurrence::MsgId).integer().not_null())
+                    .col(ColumnDef::new(Occurrence::Text).text().not_null())
+                    .col(ColumnDef::new(Occurrence::Policy).string())
+                    .col(
+                        ColumnDef::new(Occurrence::NagTime)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Occurrence::NagCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("ix_occurrence_nag_time")
+                    .table(Occurrence::Table)
+                    .col(Occurrence::NagTime)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Occurrence::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Occurrence {
+    Table,
+    Id,
+    ChatId,
+    MsgId,
+    Text,
+    Policy,
+    NagTime,
+    NagCount,
+}
@@ -0,0 +1,188 @@
+use sea_orm::{ConnectionTrait, Statement};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MessageLink::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MessageLink::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(
+                        ColumnDef::new(MessageLink::ChatId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(MessageLink::MsgId).integer().not_null(),
+                    )
+                    .col(ColumnDef::new(MessageLink::Role).string().not_null())
+                    .col(ColumnDef::new(MessageLink::ReminderId).big_integer())
+                    .col(
+                        ColumnDef::new(MessageLink::CronReminderId)
+                            .big_integer(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("ix_message_link_chat_id_msg_id")
+                    .table(MessageLink::Table)
+                    .col(MessageLink::ChatId)
+                    .col(MessageLink::MsgId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Move the links stored in the reminders' columns
+        let conn = manager.get_connection();
+        let db_backend = manager.get_database_backend();
+        for (table, target, column, role) in [
+            ("reminder", "reminder_id", "msg_id", "creation"),
+            ("reminder", "reminder_id", "reply_id", "confirmation"),
+            ("cron_reminder", "cron_reminder_id", "msg_id", "creation"),
+            (
+                "cron_reminder",
+                "cron_reminder_id",
+                "reply_id",
+                "confirmation",
+            ),
+        ] {
+            let sql = format!(
+                concat!(
+                    "INSERT INTO message_link (chat_id, msg_id, role, {}) ",
+                    "SELECT chat_id, {}, '{}', id FROM {} ",
+                    "WHERE {} IS NOT NULL"
+                ),
+                target, column, role, table, column
+            );
+            conn.execute(Statement::from_string(db_backend, sql))
+                .await?;
+        }
+
+        for (index, table) in [
+            ("ix_reminder_msg_id", "reminder"),
+            ("ix_cron_reminder_msg_id", "cron_reminder"),
+            ("ix_reminder_reply_id", "reminder"),
+            ("ix_cron_reminder_reply_id", "cron_reminder"),
+        ] {
+            manager
+                .drop_index(
+                    Index::drop()
+                        .name(index)
+                        .table(Alias::new(table))
+                        .to_owned(),
+                )
+                .await?;
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .drop_column(Reminder::MsgId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .drop_column(Reminder::ReplyId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .drop_column(CronReminder::MsgId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .drop_column(CronReminder::ReplyId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .add_column(ColumnDef::new(Reminder::MsgId).integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .add_column(ColumnDef::new(Reminder::ReplyId).integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .add_column(ColumnDef::new(CronReminder::MsgId).integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .add_column(ColumnDef::new(CronReminder::ReplyId).integer())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(MessageLink::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum MessageLink {
+    Table,
+    Id,
+    ChatId,
+    MsgId,
+    Role,
+    ReminderId,
+    CronReminderId,
+}
+
+#[derive(Iden)]
+pub enum Reminder {
+    Table,
+    MsgId,
+    ReplyId,
+}
+
+#[derive(Iden)]
+pub enum CronReminder {
+    Table,
+    MsgId,
+    ReplyId,
+}
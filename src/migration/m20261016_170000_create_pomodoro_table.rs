@@ -0,0 +1,81 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Pomodoro::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Pomodoro::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(
+                        ColumnDef::new(Pomodoro::ChatId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Pomodoro::Work).integer().not_null())
+                    .col(ColumnDef::new(Pomodoro::Rest).integer().not_null())
+                    .col(ColumnDef::new(Pomodoro::Rounds).integer().not_null())
+                    .col(
+                        ColumnDef::new(Pomodoro::Phase)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        // Reminders firing at the ends of the session's phases
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .add_column(
+                        ColumnDef::new(Reminder::PomodoroId).big_integer(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .drop_column(Reminder::PomodoroId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Pomodoro::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Pomodoro {
+    Table,
+    Id,
+    ChatId,
+    Work,
+    Rest,
+    Rounds,
+    Phase,
+}
+
+#[derive(Iden)]
+pub enum Reminder {
+    Table,
+    PomodoroId,
+}
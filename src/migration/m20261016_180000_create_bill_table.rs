@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Bill::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Bill::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(ColumnDef::new(Bill::ChatId).big_integer().not_null())
+                    .col(ColumnDef::new(Bill::Name).text().not_null())
+                    .col(ColumnDef::new(Bill::Amount).big_integer().not_null())
+                    .col(ColumnDef::new(Bill::Time).date_time().not_null())
+                    .col(
+                        ColumnDef::new(Bill::Paid)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("ix_bill_chat_id_time")
+                    .table(Bill::Table)
+                    .col(Bill::ChatId)
+                    .col(Bill::Time)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Bill::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Bill {
+    Table,
+    Id,
+    ChatId,
+    Name,
+    Amount,
+    Time,
+    Paid,
+}
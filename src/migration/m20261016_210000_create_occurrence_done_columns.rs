@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create desc, done_time and note columns
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Occurrence::Table)
+                    .add_column(ColumnDef::new(Occurrence::Desc).text())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Occurrence::Table)
+                    .add_column(
+                        ColumnDef::new(Occurrence::DoneTime).date_time(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Occurrence::Table)
+                    .add_column(ColumnDef::new(Occurrence::Note).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove desc, done_time and note columns
+        for column in [Occurrence::Desc, Occurrence::DoneTime, Occurrence::Note]
+        {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Occurrence::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum Occurrence {
+    Table,
+    Desc,
+    DoneTime,
+    Note,
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Rsvp::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Rsvp::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(
+                        ColumnDef::new(Rsvp::OccurrenceId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Rsvp::UserId).big_integer().not_null())
+                    .col(ColumnDef::new(Rsvp::Name).string().not_null())
+                    .col(ColumnDef::new(Rsvp::Answer).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("ix_rsvp_occurrence_id_user_id")
+                    .table(Rsvp::Table)
+                    .col(Rsvp::OccurrenceId)
+                    .col(Rsvp::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Rsvp::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Rsvp {
+    Table,
+    Id,
+    OccurrenceId,
+    UserId,
+    Name,
+    Answer,
+}
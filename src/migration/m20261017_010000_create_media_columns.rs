@@ -0,0 +1,97 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create media_kind and media_file_id columns
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .add_column(
+                        ColumnDef::new(CronReminder::MediaKind).string(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .add_column(
+                        ColumnDef::new(CronReminder::MediaFileId).string(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .add_column(ColumnDef::new(Reminder::MediaKind).string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .add_column(ColumnDef::new(Reminder::MediaFileId).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove media_kind and media_file_id columns
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .drop_column(CronReminder::MediaKind)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(CronReminder::Table)
+                    .drop_column(CronReminder::MediaFileId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .drop_column(Reminder::MediaKind)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .drop_column(Reminder::MediaFileId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CronReminder {
+    Table,
+    MediaKind,
+    MediaFileId,
+}
+
+#[derive(Iden)]
+pub enum Reminder {
+    Table,
+    MediaKind,
+    MediaFileId,
+}
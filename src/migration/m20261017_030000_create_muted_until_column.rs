@@ -0,0 +1,39 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create muted_until column
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Occurrence::Table)
+                    .add_column(
+                        ColumnDef::new(Occurrence::MutedUntil).date_time(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove muted_until column
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Occurrence::Table)
+                    .drop_column(Occurrence::MutedUntil)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Occurrence {
+    Table,
+    MutedUntil,
+}
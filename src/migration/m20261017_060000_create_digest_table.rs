@@ -0,0 +1,58 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Digest::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Digest::ChatId)
+                            .big_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Digest::UserId).big_integer().not_null(),
+                    )
+                    .col(ColumnDef::new(Digest::Period).text().not_null())
+                    .col(ColumnDef::new(Digest::Minute).integer().not_null())
+                    .col(
+                        ColumnDef::new(Digest::NextTime).date_time().not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("idx_digest_next_time")
+                    .table(Digest::Table)
+                    .col(Digest::NextTime)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Digest::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Digest {
+    Table,
+    ChatId,
+    UserId,
+    Period,
+    Minute,
+    NextTime,
+}
@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Sending::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Sending::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Sending::ReminderId).big_integer())
+                    .col(ColumnDef::new(Sending::CronReminderId).big_integer())
+                    .col(ColumnDef::new(Sending::Since).date_time().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Sending::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Sending {
+    Table,
+    Id,
+    ReminderId,
+    CronReminderId,
+    Since,
+}
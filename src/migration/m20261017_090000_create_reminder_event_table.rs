@@ -0,0 +1,107 @@
+use sea_orm::ConnectionTrait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReminderEvent::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ReminderEvent::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderEvent::ChatId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderEvent::Time)
+                            .date_time()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderEvent::Desc).string().not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderEvent::Kind).string().not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderEvent::OccurrenceId)
+                            .big_integer(),
+                    )
+                    .col(ColumnDef::new(ReminderEvent::Note).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Keep the occurrences marked done so far in the history
+        let conn = manager.get_connection();
+        let db_backend = manager.get_database_backend();
+        let query = Query::insert()
+            .into_table(ReminderEvent::Table)
+            .columns([
+                ReminderEvent::ChatId,
+                ReminderEvent::Time,
+                ReminderEvent::Desc,
+                ReminderEvent::Kind,
+                ReminderEvent::OccurrenceId,
+                ReminderEvent::Note,
+            ])
+            .select_from(
+                Query::select()
+                    .column(Occurrence::ChatId)
+                    .column(Occurrence::DoneTime)
+                    .expr(Func::coalesce([
+                        Expr::col(Occurrence::Desc).into(),
+                        Expr::val("").into(),
+                    ]))
+                    .expr(Expr::val("done"))
+                    .column(Occurrence::Id)
+                    .column(Occurrence::Note)
+                    .from(Occurrence::Table)
+                    .and_where(Expr::col(Occurrence::DoneTime).is_not_null())
+                    .to_owned(),
+            )
+            .map_err(|err| DbErr::Migration(err.to_string()))?
+            .to_owned();
+        conn.execute(db_backend.build(&query)).await.map(|_| ())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ReminderEvent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ReminderEvent {
+    Table,
+    Id,
+    ChatId,
+    Time,
+    Desc,
+    Kind,
+    OccurrenceId,
+    Note,
+}
+
+#[derive(Iden)]
+pub enum Occurrence {
+    Table,
+    Id,
+    ChatId,
+    Desc,
+    DoneTime,
+    Note,
+}
@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create morning, afternoon, evening and night columns
+        for column in [
+            UserSettings::Morning,
+            UserSettings::Afternoon,
+            UserSettings::Evening,
+            UserSettings::Night,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(UserSettings::Table)
+                        .add_column(ColumnDef::new(column).integer())
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Remove morning, afternoon, evening and night columns
+        for column in [
+            UserSettings::Morning,
+            UserSettings::Afternoon,
+            UserSettings::Evening,
+            UserSettings::Night,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(UserSettings::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub enum UserSettings {
+    Table,
+    Morning,
+    Afternoon,
+    Evening,
+    Night,
+}
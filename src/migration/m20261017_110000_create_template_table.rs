@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Template::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Template::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(
+                        ColumnDef::new(Template::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Template::Name).string().not_null())
+                    .col(ColumnDef::new(Template::Text).text().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("ix_template_user_id_name")
+                    .table(Template::Table)
+                    .col(Template::UserId)
+                    .col(Template::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Template::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Template {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Text,
+}
@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReminderTarget::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ReminderTarget::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderTarget::ChatId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderTarget::ReminderId)
+                            .big_integer(),
+                    )
+                    .col(
+                        ColumnDef::new(ReminderTarget::CronReminderId)
+                            .big_integer(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        for (name, column) in [
+            ("ix_reminder_target_reminder_id", ReminderTarget::ReminderId),
+            (
+                "ix_reminder_target_cron_reminder_id",
+                ReminderTarget::CronReminderId,
+            ),
+        ] {
+            manager
+                .create_index(
+                    Index::create()
+                        .if_not_exists()
+                        .name(name)
+                        .table(ReminderTarget::Table)
+                        .col(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ReminderTarget::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum ReminderTarget {
+    Table,
+    Id,
+    ChatId,
+    ReminderId,
+    CronReminderId,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CalendarFeed::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CalendarFeed::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(
+                        ColumnDef::new(CalendarFeed::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CalendarFeed::Token).string().not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("ix_calendar_feed_user_id")
+                    .table(CalendarFeed::Table)
+                    .col(CalendarFeed::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("ix_calendar_feed_token")
+                    .table(CalendarFeed::Table)
+                    .col(CalendarFeed::Token)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CalendarFeed::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CalendarFeed {
+    Table,
+    Id,
+    UserId,
+    Token,
+}
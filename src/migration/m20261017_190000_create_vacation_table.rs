@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Vacation::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Vacation::ChatId)
+                            .big_integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Vacation::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Vacation::Since).date_time().not_null())
+                    .col(ColumnDef::new(Vacation::Until).date_time().not_null())
+                    .col(
+                        ColumnDef::new(Vacation::Digest)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Vacation::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Vacation {
+    Table,
+    ChatId,
+    UserId,
+    Since,
+    Until,
+    Digest,
+}
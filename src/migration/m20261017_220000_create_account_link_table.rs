@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AccountLink::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AccountLink::Id)
+                            .big_integer()
+                            .not_null()
+                            .primary_key()
+                            .auto_increment(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountLink::UserId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AccountLink::ChatId)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AccountLink::Title).string().not_null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .if_not_exists()
+                    .name("ix_account_link_user_id_chat_id")
+                    .table(AccountLink::Table)
+                    .col(AccountLink::UserId)
+                    .col(AccountLink::ChatId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AccountLink::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum AccountLink {
+    Table,
+    Id,
+    UserId,
+    ChatId,
+    Title,
+}
@@ -0,0 +1,108 @@
+use sea_orm::ConnectionTrait;
+use sea_orm_migration::prelude::*;
+use serde_json::{Map, Value};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let conn = manager.get_connection();
+        let db_backend = manager.get_database_backend();
+        let query = Query::select()
+            .columns([Reminder::Id, Reminder::Policy, Reminder::Priority])
+            .from(Reminder::Table)
+            .and_where(Expr::col(Reminder::Priority).is_not_null())
+            .to_owned();
+        let rows = conn.query_all(db_backend.build(&query)).await?;
+
+        // Move the priorities into the stored policies
+        for row in rows {
+            let id: i64 = row.try_get("", &Reminder::Id.to_string())?;
+            let policy: Option<String> =
+                row.try_get("", &Reminder::Policy.to_string())?;
+            let priority: String =
+                row.try_get("", &Reminder::Priority.to_string())?;
+            let mut policy = policy_fields(id, policy.as_deref());
+            policy.insert("priority".to_owned(), Value::String(priority));
+            let query = Query::update()
+                .table(Reminder::Table)
+                .value(Reminder::Policy, Value::Object(policy).to_string())
+                .and_where(Expr::col(Reminder::Id).eq(id))
+                .to_owned();
+            conn.execute(db_backend.build(&query)).await?;
+        }
+
+        // Remove priority column
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .drop_column(Reminder::Priority)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create priority column
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reminder::Table)
+                    .add_column(ColumnDef::new(Reminder::Priority).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        let conn = manager.get_connection();
+        let db_backend = manager.get_database_backend();
+        let query = Query::select()
+            .columns([Reminder::Id, Reminder::Policy])
+            .from(Reminder::Table)
+            .and_where(Expr::col(Reminder::Policy).is_not_null())
+            .to_owned();
+        let rows = conn.query_all(db_backend.build(&query)).await?;
+
+        // Move the priorities back out of the stored policies
+        for row in rows {
+            let id: i64 = row.try_get("", &Reminder::Id.to_string())?;
+            let policy: Option<String> =
+                row.try_get("", &Reminder::Policy.to_string())?;
+            let mut policy = policy_fields(id, policy.as_deref());
+            let Some(Value::String(priority)) = policy.remove("priority")
+            else {
+                continue;
+            };
+            let query = Query::update()
+                .table(Reminder::Table)
+                .value(Reminder::Policy, Value::Object(policy).to_string())
+                .value(Reminder::Priority, priority)
+                .and_where(Expr::col(Reminder::Id).eq(id))
+                .to_owned();
+            conn.execute(db_backend.build(&query)).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Fields of the stored policy, a malformed one is replaced
+fn policy_fields(id: i64, policy: Option<&str>) -> Map<String, Value> {
+    match policy.map(serde_json::from_str::<Map<String, Value>>) {
+        Some(Ok(fields)) => fields,
+        Some(Err(err)) => {
+            tracing::warn!("replace bad policy of reminder {}: {}", id, err);
+            Map::new()
+        }
+        None => Map::new(),
+    }
+}
+
+#[derive(Iden)]
+pub enum Reminder {
+    Table,
+    Id,
+    Policy,
+    Priority,
+}
@@ -16,6 +16,47 @@ mod m20241113_200129_add_msg_id_indexes;
 mod m20241114_105214_create_reply_id_columns;
 mod m20241114_105217_add_reply_id_indexes;
 mod m20241217_154950_remove_edit_columns;
+mod m20261016_100000_prune_exhausted_patterns;
+mod m20261016_110000_create_user_language_table;
+mod m20261016_120000_create_policy_columns;
+mod m20261016_130000_create_version_columns;
+mod m20261016_140000_create_occurrence_table;
+mod m20261016_150000_create_message_link_table;
+mod m20261016_160000_create_ai_opt_in_table;
+mod m20261016_170000_create_pomodoro_table;
+mod m20261016_180000_create_bill_table;
+mod m20261016_190000_create_share_link_table;
+mod m20261016_200000_create_target_column;
+mod m20261016_210000_create_occurrence_done_columns;
+mod m20261016_220000_create_meeting_columns;
+mod m20261016_230000_create_rsvp_table;
+mod m20261016_233000_create_advance_columns;
+mod m20261017_000000_create_chat_timezone_table;
+mod m20261017_010000_create_media_columns;
+mod m20261017_020000_create_user_settings_table;
+mod m20261017_030000_create_muted_until_column;
+mod m20261017_040000_create_quiet_hours_columns;
+mod m20261017_050000_create_text_menus_column;
+mod m20261017_060000_create_digest_table;
+mod m20261017_070000_create_sending_table;
+mod m20261017_080000_create_retry_columns;
+mod m20261017_090000_create_reminder_event_table;
+mod m20261017_100000_create_day_part_columns;
+mod m20261017_110000_create_template_table;
+mod m20261017_120000_create_reminder_target_table;
+mod m20261017_130000_create_thread_id_columns;
+mod m20261017_140000_create_calendar_feed_table;
+mod m20261017_150000_create_priority_column;
+mod m20261017_160000_create_pin_until_done_column;
+mod m20261017_170000_create_expiring_message_table;
+mod m20261017_180000_create_deleted_time_columns;
+mod m20261017_190000_create_vacation_table;
+mod m20261017_200000_create_catch_up_column;
+mod m20261017_210000_create_confirm_delete_column;
+mod m20261017_220000_create_account_link_table;
+mod m20261017_230000_create_sunday_first_column;
+mod m20261018_000000_move_priority_to_policy;
+mod m20261018_010000_create_api_token_table;
 
 pub struct Migrator;
 
@@ -43,6 +84,49 @@ impl MigratorTrait for Migrator {
             Box::new(m20241114_105214_create_reply_id_columns::Migration),
             Box::new(m20241114_105217_add_reply_id_indexes::Migration),
             Box::new(m20241217_154950_remove_edit_columns::Migration),
+            Box::new(m20261016_100000_prune_exhausted_patterns::Migration),
+            Box::new(m20261016_110000_create_user_language_table::Migration),
+            Box::new(m20261016_120000_create_policy_columns::Migration),
+            Box::new(m20261016_130000_create_version_columns::Migration),
+            Box::new(m20261016_140000_create_occurrence_table::Migration),
+            Box::new(m20261016_150000_create_message_link_table::Migration),
+            Box::new(m20261016_160000_create_ai_opt_in_table::Migration),
+            Box::new(m20261016_170000_create_pomodoro_table::Migration),
+            Box::new(m20261016_180000_create_bill_table::Migration),
+            Box::new(m20261016_190000_create_share_link_table::Migration),
+            Box::new(m20261016_200000_create_target_column::Migration),
+            Box::new(
+                m20261016_210000_create_occurrence_done_columns::Migration,
+            ),
+            Box::new(m20261016_220000_create_meeting_columns::Migration),
+            Box::new(m20261016_230000_create_rsvp_table::Migration),
+            Box::new(m20261016_233000_create_advance_columns::Migration),
+            Box::new(m20261017_000000_create_chat_timezone_table::Migration),
+            Box::new(m20261017_010000_create_media_columns::Migration),
+            Box::new(m20261017_020000_create_user_settings_table::Migration),
+            Box::new(m20261017_030000_create_muted_until_column::Migration),
+            Box::new(m20261017_040000_create_quiet_hours_columns::Migration),
+            Box::new(m20261017_050000_create_text_menus_column::Migration),
+            Box::new(m20261017_060000_create_digest_table::Migration),
+            Box::new(m20261017_070000_create_sending_table::Migration),
+            Box::new(m20261017_080000_create_retry_columns::Migration),
+            Box::new(m20261017_090000_create_reminder_event_table::Migration),
+            Box::new(m20261017_100000_create_day_part_columns::Migration),
+            Box::new(m20261017_110000_create_template_table::Migration),
+            Box::new(m20261017_120000_create_reminder_target_table::Migration),
+            Box::new(m20261017_130000_create_thread_id_columns::Migration),
+            Box::new(m20261017_140000_create_calendar_feed_table::Migration),
+            Box::new(m20261017_150000_create_priority_column::Migration),
+            Box::new(m20261017_160000_create_pin_until_done_column::Migration),
+            Box::new(m20261017_170000_create_expiring_message_table::Migration),
+            Box::new(m20261017_180000_create_deleted_time_columns::Migration),
+            Box::new(m20261017_190000_create_vacation_table::Migration),
+            Box::new(m20261017_200000_create_catch_up_column::Migration),
+            Box::new(m20261017_210000_create_confirm_delete_column::Migration),
+            Box::new(m20261017_220000_create_account_link_table::Migration),
+            Box::new(m20261017_230000_create_sunday_first_column::Migration),
+            Box::new(m20261018_000000_move_priority_to_policy::Migration),
+            Box::new(m20261018_010000_create_api_token_table::Migration),
         ]
     }
 }
@@ -1,30 +1,132 @@
+use crate::advance;
+use crate::cron;
+use crate::date;
 use crate::grammar;
 use crate::serializers::Pattern;
+use crate::settings::{self, DayPart};
 
 use crate::entity::{cron_reminder, reminder};
+use crate::policy::{NotificationPolicy, Priority};
 use chrono::prelude::*;
-use chrono::Utc;
 use chrono_tz::Tz;
-use cron_parser::parse as parse_cron;
+use nonempty::NonEmpty;
 use sea_orm::ActiveValue::{NotSet, Set};
-use serde_json::to_string;
+use teloxide::types::ChatId;
 
 #[cfg(not(test))]
 pub(crate) fn now_time() -> NaiveDateTime {
     Utc::now().naive_utc()
 }
 
+/// Reminder written in the grammar, or else in the natural language
+fn parse_grammar(
+    s: &str,
+    user_id: u64,
+    user_timezone: Tz,
+) -> Option<grammar::Reminder> {
+    match grammar::parse_reminder(s) {
+        Ok(rem) => Some(rem),
+        Err(_) => {
+            let now =
+                user_timezone.from_utc_datetime(&now_time()).naive_local();
+            grammar::parse_reminder(&rewrite_natural(s, now, user_id)?).ok()
+        }
+    }
+}
+
+/// Whether the text is only a time pattern, e.g. `tomorrow at 10`,
+/// without a description or a mention
+pub(crate) fn lacks_description(
+    s: &str,
+    user_id: u64,
+    user_timezone: Tz,
+) -> bool {
+    parse_grammar(s, user_id, user_timezone).is_some_and(|rem| {
+        rem.pattern.is_some()
+            && rem.description.is_none()
+            && rem.target.is_none()
+    })
+}
+
+/// Where the text stops matching the grammar, if it starts like
+/// a reminder that can't be read in the natural language either
+pub(crate) fn syntax_error(
+    s: &str,
+    user_id: u64,
+    user_timezone: Tz,
+) -> Option<grammar::ParseError> {
+    if parse_grammar(s, user_id, user_timezone).is_some() {
+        return None;
+    }
+    match grammar::parse_reminder(s) {
+        // nothing is read at the start, it doesn't look like a reminder
+        Err(grammar::ParseError::Syntax { pos: 0, .. }) => None,
+        Err(err @ grammar::ParseError::Syntax { .. }) => Some(err),
+        _ => None,
+    }
+}
+
+/// Whether the text is a reminder firing more often than allowed
+pub(crate) fn too_frequent(s: &str, user_id: u64, user_timezone: Tz) -> bool {
+    parse_grammar(s, user_id, user_timezone)
+        .and_then(|rem| rem.pattern)
+        .and_then(|pattern| {
+            Pattern::from_with_tz_unchecked(pattern, user_timezone).ok()
+        })
+        .is_some_and(|pattern| pattern.too_frequent())
+}
+
 pub(crate) async fn parse_reminder(
     s: &str,
     chat_id: i64,
     user_id: u64,
-    msg_id: i32,
     user_timezone: Tz,
 ) -> Option<reminder::ActiveModel> {
-    let rem = grammar::parse_reminder(s).ok()?;
-    let description = rem.description.map(|x| x.0).unwrap_or("".to_owned());
-    let mut pattern =
-        Pattern::from_with_tz(rem.pattern?, user_timezone).ok()?;
+    let rem = parse_grammar(s, user_id, user_timezone)?;
+    let mut description = rem.description.map(|x| x.0).unwrap_or("".to_owned());
+    let mut target = rem.target.map(|x| x.0);
+    // Only group members are mentioned, elsewhere it's a part of the text
+    if ChatId(chat_id).is_user() {
+        if let Some(username) = target.take() {
+            description = format!("@{} {}", username, description)
+                .trim_end()
+                .to_owned();
+        }
+    }
+    let pattern = Pattern::from_with_tz(rem.pattern?, user_timezone).ok()?;
+    let mut reminder = new_reminder(pattern, description, chat_id, user_id)?;
+    reminder.target = Set(target);
+    reminder.meeting = Set(rem.meeting);
+    if let Some(priority) = rem.priority {
+        let priority = match priority.0.as_str() {
+            "!!" => Priority::High,
+            _ => Priority::Critical,
+        };
+        reminder.policy = Set(NotificationPolicy {
+            priority: Some(priority),
+            ..Default::default()
+        }
+        .to_stored());
+    }
+    if let Some(interval) = rem.advance {
+        let lead = advance::lead_seconds(&interval)?;
+        reminder.advance = Set(Some(lead));
+        reminder.advance_time = Set(advance::warning_time(
+            reminder.time.clone().unwrap(),
+            lead,
+            now_time(),
+        ));
+    }
+    Some(reminder)
+}
+
+/// Reminder at the next occurrence of the pattern, if there's any
+pub(crate) fn new_reminder(
+    mut pattern: Pattern,
+    desc: String,
+    chat_id: i64,
+    user_id: u64,
+) -> Option<reminder::ActiveModel> {
     let time = pattern.next(now_time())?;
     // Convert to UTC
     Some(reminder::ActiveModel {
@@ -32,11 +134,23 @@ pub(crate) async fn parse_reminder(
         chat_id: Set(chat_id),
         user_id: Set(Some(user_id as i64)),
         time: Set(time),
-        desc: Set(description),
+        desc: Set(desc),
         paused: Set(false),
-        pattern: Set(to_string(&pattern).ok()),
-        msg_id: Set(Some(msg_id)),
-        reply_id: Set(None), // set after replying
+        pattern: Set(pattern.to_stored(time)),
+        policy: Set(None),
+        version: Set(0),
+        pomodoro_id: Set(None),
+        target: Set(None),
+        meeting: Set(false),
+        advance: Set(None),
+        advance_time: Set(None),
+        media_kind: Set(None),
+        media_file_id: Set(None),
+        held_since: Set(None),
+        failures: Set(0),
+        retry_time: Set(None),
+        thread_id: Set(None),
+        deleted_time: Set(None),
     })
 }
 
@@ -44,7 +158,6 @@ pub(crate) async fn parse_cron_reminder(
     text: &str,
     chat_id: i64,
     user_id: u64,
-    msg_id: i32,
     user_timezone: Tz,
 ) -> Option<cron_reminder::ActiveModel> {
     let cron_fields: Vec<&str> = text.split_whitespace().take(5).collect();
@@ -52,26 +165,218 @@ pub(crate) async fn parse_cron_reminder(
         None
     } else {
         let cron_expr = cron_fields.join(" ");
-        parse_cron(&cron_expr, &Utc::now().with_timezone(&user_timezone))
+        cron::next_time(&cron_expr, now_time(), user_timezone)
             .map(|time| cron_reminder::ActiveModel {
                 id: NotSet,
                 chat_id: Set(chat_id),
                 user_id: Set(Some(user_id as i64)),
                 cron_expr: Set(cron_expr.clone()),
-                time: Set(time.with_timezone(&Utc).naive_utc()),
+                time: Set(time),
                 desc: Set(text
                     .strip_prefix(&(cron_expr.to_owned()))
                     .unwrap_or("")
                     .trim()
                     .to_owned()),
                 paused: Set(false),
-                msg_id: Set(Some(msg_id)),
-                reply_id: Set(None), // set after replying
+                policy: Set(None),
+                version: Set(0),
+                media_kind: Set(None),
+                media_file_id: Set(None),
+                failures: Set(0),
+                retry_time: Set(None),
+                thread_id: Set(None),
+                deleted_time: Set(None),
             })
             .ok()
     }
 }
 
+/// Time of the reminders given only a date
+pub(crate) const DEFAULT_TIME: NaiveTime =
+    match NaiveTime::from_hms_opt(9, 0, 0) {
+        Some(time) => time,
+        None => unreachable!(),
+    };
+
+/// First word of the text and the rest after it
+fn split_word(s: &str) -> (&str, &str) {
+    let s = s.trim_start();
+    s.split_once(char::is_whitespace).unwrap_or((s, ""))
+}
+
+fn interval_unit(word: &str) -> Option<&'static str> {
+    match word.to_lowercase().as_str() {
+        "second" | "seconds" | "sec" | "secs" => Some("s"),
+        "minute" | "minutes" | "min" | "mins" => Some("m"),
+        "hour" | "hours" => Some("h"),
+        "day" | "days" => Some("d"),
+        "week" | "weeks" => Some("w"),
+        "month" | "months" => Some("mo"),
+        "year" | "years" => Some("y"),
+        _ => None,
+    }
+}
+
+/// Countdown with spelled out units, e.g. `in 1 hour 30 minutes`
+fn rewrite_countdown(s: &str) -> Option<String> {
+    let (word, mut rest) = split_word(s);
+    if !word.eq_ignore_ascii_case("in") {
+        return None;
+    }
+    let mut interval = String::new();
+    loop {
+        let (value, after_value) = split_word(rest);
+        let value = match value.to_lowercase().as_str() {
+            "a" | "an" => 1,
+            value => match value.parse::<u32>() {
+                Ok(value) if value > 0 => value,
+                _ => break,
+            },
+        };
+        let (unit, after_unit) = split_word(after_value);
+        let Some(unit) = interval_unit(unit) else {
+            break;
+        };
+        interval.push_str(&format!("{}{}", value, unit));
+        rest = after_unit;
+    }
+    (!interval.is_empty())
+        .then(|| format!("in {} {}", interval, rest.trim_start()))
+}
+
+/// `today`, `tomorrow` or `next <weekday>`
+fn parse_natural_date(s: &str, today: NaiveDate) -> Option<(NaiveDate, &str)> {
+    let (word, rest) = split_word(s);
+    match word.to_lowercase().as_str() {
+        "today" => Some((today, rest)),
+        "tomorrow" => Some((today.succ_opt()?, rest)),
+        "next" => {
+            let (weekday, rest) = split_word(rest);
+            let weekday = weekday.parse::<Weekday>().ok()?;
+            let date = date::find_nearest_weekday(
+                today.succ_opt()?,
+                NonEmpty::new(weekday.num_days_from_monday()),
+            );
+            Some((date, rest))
+        }
+        _ => None,
+    }
+}
+
+/// Time of a 12-hour clock, e.g. `9am`, `9:30 pm` or `noon`, of a part
+/// of the day in the user's settings, e.g. `evening`, and also of
+/// a 24-hour clock, e.g. `21:30`, if `allow_24h` is set
+fn parse_natural_time(
+    s: &str,
+    allow_24h: bool,
+    user_id: u64,
+) -> Option<(NaiveTime, &str)> {
+    let (word, rest) = split_word(s);
+    let word = word.to_lowercase();
+    match word.as_str() {
+        "at" => return parse_natural_time(rest, allow_24h, user_id),
+        "noon" => return Some((NaiveTime::from_hms_opt(12, 0, 0)?, rest)),
+        "midnight" => return Some((NaiveTime::MIN, rest)),
+        _ => {}
+    }
+    if let Some(part) = DayPart::from_name(&word) {
+        return Some((settings::day_part_time(user_id as i64, part), rest));
+    }
+    let (clock, pm, rest) = if let Some(clock) = word.strip_suffix("am") {
+        (clock, Some(false), rest)
+    } else if let Some(clock) = word.strip_suffix("pm") {
+        (clock, Some(true), rest)
+    } else {
+        let (suffix, after_suffix) = split_word(rest);
+        match suffix.to_lowercase().as_str() {
+            "am" => (word.as_str(), Some(false), after_suffix),
+            "pm" => (word.as_str(), Some(true), after_suffix),
+            _ if allow_24h => (word.as_str(), None, rest),
+            _ => return None,
+        }
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour, minute),
+        Some(_) => return None,
+        None => (clock, "0"),
+    };
+    let (hour, minute): (u32, u32) = (hour.parse().ok()?, minute.parse().ok()?);
+    let hour = match pm {
+        Some(pm) if (1..=12).contains(&hour) => hour % 12 + 12 * pm as u32,
+        Some(_) => return None,
+        None => hour,
+    };
+    Some((NaiveTime::from_hms_opt(hour, minute, 0)?, rest))
+}
+
+/// Rewrite the natural language date and time at the start of a reminder,
+/// e.g. `tomorrow 9am standup` or `in 45 minutes tea`, into the patterns
+/// of the grammar. Returns `None` if there are no such phrases.
+fn rewrite_natural(
+    s: &str,
+    now: NaiveDateTime,
+    user_id: u64,
+) -> Option<String> {
+    if let Some(countdown) = rewrite_countdown(s) {
+        return Some(countdown);
+    }
+    let (mut date, mut time, mut rest) = (None, None, s);
+    loop {
+        if date.is_none() {
+            if let Some((found, after)) = parse_natural_date(rest, now.date()) {
+                (date, rest) = (Some(found), after);
+                continue;
+            }
+        }
+        if time.is_none() {
+            if let Some((found, after)) =
+                parse_natural_time(rest, date.is_some(), user_id)
+            {
+                (time, rest) = (Some(found), after);
+                continue;
+            }
+        }
+        break;
+    }
+    let rest = rest.trim_start();
+    // a time today is the next occurrence of the time anyway
+    match (date.filter(|&date| date != now.date()), time) {
+        (Some(date), time) => Some(format!(
+            "{} {} {}",
+            date.format("%d.%m.%Y"),
+            time.unwrap_or(DEFAULT_TIME).format("%H:%M"),
+            rest
+        )),
+        (None, Some(time)) => {
+            Some(format!("{} {}", time.format("%H:%M"), rest))
+        }
+        // `today` alone doesn't tell when to remind
+        (None, None) => None,
+    }
+}
+
+/// Job lines of a crontab file along with their line numbers.
+/// Empty lines, comments and environment settings are skipped.
+pub(crate) fn crontab_entries(
+    content: &str,
+) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| {
+            !line.is_empty() && !line.starts_with('#') && !is_env_setting(line)
+        })
+}
+
+fn is_env_setting(line: &str) -> bool {
+    line.split_once('=').is_some_and(|(name, _)| {
+        let name = name.trim();
+        !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
 #[cfg(test)]
 pub(crate) fn now_time() -> NaiveDateTime {
     DateTime::from_timestamp(*test::TEST_TIMESTAMP.read().unwrap(), 0)
@@ -82,6 +387,7 @@ pub(crate) fn now_time() -> NaiveDateTime {
 #[cfg(test)]
 pub(crate) mod test {
     use super::*;
+    use crate::generic_reminder::GenericReminder;
     use serial_test::serial;
     use test_case::test_case;
     extern crate strfmt;
@@ -113,6 +419,15 @@ pub(crate) mod test {
     #[test_case("{day} {hour} {desc}", Time(2007, 2, 1, 13, 0, 0) => Some(Time(2007, 3, 1, 13, 0, 0)) ; "day before" )]
     #[test_case("02.01 13:00 {desc}", Time(2007, 1, 2, 13, 0, 0) => Some(Time(2008, 1, 2, 13, 0, 0)) ; "month before" )]
     #[test_case("{hour}:{minute}{desc}", Time(2007, 2, 2, 12, 30, 0) => None ; "non-parsable" )]
+    #[test_case("tomorrow 9am {desc}", Time(2007, 2, 2, 0, 0, 0) => Some(Time(2007, 2, 3, 9, 0, 0)) ; "tomorrow" )]
+    #[test_case("next monday {desc}", Time(2007, 2, 2, 0, 0, 0) => Some(Time(2007, 2, 5, 9, 0, 0)) ; "next weekday" )]
+    #[test_case("next friday at 18:15 {desc}", Time(2007, 2, 2, 0, 0, 0) => Some(Time(2007, 2, 9, 18, 15, 0)) ; "next same weekday" )]
+    #[test_case("in 45 minutes {desc}", Time(2007, 2, 2, 0, 0, 0) => Some(Time(2007, 2, 2, 13, 15, 30)) ; "spelled countdown" )]
+    #[test_case("in an hour {desc}", Time(2007, 2, 2, 0, 0, 0) => Some(Time(2007, 2, 2, 13, 30, 30)) ; "article countdown" )]
+    #[test_case("today at 9:45 pm {desc}", Time(2007, 2, 2, 0, 0, 0) => Some(Time(2007, 2, 2, 21, 45, 0)) ; "today" )]
+    #[test_case("noon {desc}", Time(2007, 2, 2, 0, 0, 0) => Some(Time(2007, 2, 3, 12, 0, 0)) ; "passed time" )]
+    #[test_case("today {desc}", Time(2007, 2, 2, 0, 0, 0) => None ; "today without time" )]
+    #[test_case("13pm {desc}", Time(2007, 2, 2, 0, 0, 0) => None ; "wrong 12-hour time" )]
     #[tokio::test]
     #[serial]
     async fn test_parse_reminder(fmt_str: &str, time: Time) -> Option<Time> {
@@ -129,7 +444,7 @@ pub(crate) mod test {
         ]);
         *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
         let result =
-            parse_reminder(&strfmt(fmt_str, &vars).unwrap(), 0, 0, 0, *TEST_TZ)
+            parse_reminder(&strfmt(fmt_str, &vars).unwrap(), 0, 0, *TEST_TZ)
                 .await
                 .map(|reminder| {
                     (
@@ -152,4 +467,159 @@ pub(crate) mod test {
             None => None,
         }
     }
+
+    #[test_case("10:00 {desc}" => false ; "one-time")]
+    #[test_case("1h {desc}" => false ; "single countdown")]
+    #[test_case("1h,2h {desc}" => true ; "multiple countdowns")]
+    #[test_case("1h then 30m {desc}" => true ; "chained countdowns")]
+    #[test_case("10:00,11:00 {desc}" => true ; "multiple times")]
+    #[test_case("/mon 10:00 {desc}" => true ; "weekly")]
+    #[test_case("/mon 10:00 x2 {desc}" => true ; "weekly twice")]
+    #[test_case("/mon 10:00 x1 {desc}" => false ; "weekly once")]
+    #[tokio::test]
+    #[serial]
+    async fn test_pattern_stored(fmt_str: &str) -> bool {
+        let vars =
+            HashMap::from([("desc".to_owned(), TEST_DESCRIPTION.to_owned())]);
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        parse_reminder(&strfmt(fmt_str, &vars).unwrap(), 0, 0, *TEST_TZ)
+            .await
+            .unwrap()
+            .pattern
+            .unwrap()
+            .is_some()
+    }
+
+    #[test_case(-1, "17:00 @alice_w submit report" => (Some("alice_w".to_owned()), "submit report".to_owned()) ; "group")]
+    #[test_case(-1, "17:00 @alice_w" => (Some("alice_w".to_owned()), "".to_owned()) ; "without description")]
+    #[test_case(1, "17:00 @alice_w submit report" => (None, "@alice_w submit report".to_owned()) ; "private chat")]
+    #[test_case(-1, "17:00 @bob submit report" => (None, "@bob submit report".to_owned()) ; "too short username")]
+    #[test_case(-1, "17:00 ask @alice_w" => (None, "ask @alice_w".to_owned()) ; "inside description")]
+    #[tokio::test]
+    #[serial]
+    async fn test_parse_target(
+        chat_id: i64,
+        text: &str,
+    ) -> (Option<String>, String) {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let reminder =
+            parse_reminder(text, chat_id, 0, *TEST_TZ).await.unwrap();
+        (reminder.target.unwrap(), reminder.desc.unwrap())
+    }
+
+    #[test_case("meeting 10:00 standup" => (true, "standup".to_owned()) ; "meeting")]
+    #[test_case("meeting on fri 15:00 retro" => (true, "retro".to_owned()) ; "weekday")]
+    #[test_case("Meeting 10:00 @alice_w" => (true, "".to_owned()) ; "capitalized")]
+    #[test_case("10:00 meeting with Bob" => (false, "meeting with Bob".to_owned()) ; "inside description")]
+    #[tokio::test]
+    #[serial]
+    async fn test_parse_meeting(text: &str) -> (bool, String) {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let reminder = parse_reminder(text, -1, 0, *TEST_TZ).await.unwrap();
+        (reminder.meeting.unwrap(), reminder.desc.unwrap())
+    }
+
+    #[test_case("25.12 18:00 !30m party" => Some((Some(1800), true, "party".to_owned())) ; "advance")]
+    #[test_case("every mon 9:00 !1d2h standup" => Some((Some(93600), true, "standup".to_owned())) ; "recurrence")]
+    #[test_case("1m !5m call" => Some((Some(300), false, "call".to_owned())) ; "warning already past")]
+    #[test_case("25.12 18:00 !1mo party" => None ; "months")]
+    #[test_case("25.12 18:00 !party" => Some((None, false, "!party".to_owned())) ; "inside description")]
+    #[tokio::test]
+    #[serial]
+    async fn test_parse_advance(
+        text: &str,
+    ) -> Option<(Option<i64>, bool, String)> {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let reminder = parse_reminder(text, 1, 0, *TEST_TZ).await?;
+        Some((
+            reminder.advance.unwrap(),
+            reminder.advance_time.unwrap().is_some(),
+            reminder.desc.unwrap(),
+        ))
+    }
+
+    #[test_case("18:00 !! pick up the kids" => (Some(Priority::High), "pick up the kids".to_owned()) ; "high")]
+    #[test_case("every mon 9:00 !30m !!! pay rent" => (Some(Priority::Critical), "pay rent".to_owned()) ; "critical after advance")]
+    #[test_case("18:00 !!" => (Some(Priority::High), "".to_owned()) ; "no description")]
+    #[test_case("18:00 !!!! wow" => (None, "!!!! wow".to_owned()) ; "too many")]
+    #[test_case("18:00 wow!!" => (None, "wow!!".to_owned()) ; "inside description")]
+    #[tokio::test]
+    #[serial]
+    async fn test_parse_priority(text: &str) -> (Option<Priority>, String) {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let reminder = parse_reminder(text, 1, 0, *TEST_TZ).await.unwrap();
+        (reminder.priority(), reminder.desc.unwrap())
+    }
+
+    #[test_case("every mon 9:00" => true ; "recurrence")]
+    #[test_case("tomorrow 10" => true ; "natural")]
+    #[test_case("10:00 call mom" => false ; "description")]
+    #[test_case("10:00 @alice_w" => false ; "mention")]
+    #[test_case("call mom" => false ; "no pattern")]
+    #[serial]
+    fn test_lacks_description(text: &str) -> bool {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        lacks_description(text, 0, *TEST_TZ)
+    }
+
+    #[test_case("10:00/1x tea" => Some(("10:00/1x tea\n      ^".to_owned(), Some("an interval like 1h30m".to_owned()))) ; "interval")]
+    #[test_case("-/mon-frr 10 tea" => Some(("-/mon-frr 10 tea\n      ^".to_owned(), Some("a weekday like mon or friday".to_owned()))) ; "weekday")]
+    #[test_case("1.2.3.4 tea" => Some(("1.2.3.4 tea\n    ^".to_owned(), Some("a year like 2025".to_owned()))) ; "year")]
+    #[test_case("10:00 tea" => None ; "reminder")]
+    #[test_case("call mom" => None ; "no pattern")]
+    #[serial]
+    fn test_syntax_error(text: &str) -> Option<(String, Option<String>)> {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        syntax_error(text, 0, *TEST_TZ)
+            .map(|err| (err.pointer(text).unwrap(), err.hint()))
+    }
+
+    #[test_case("tomorrow 10 {desc}" => Some("03.02.2007 10:00 {desc}".to_owned()) ; "24-hour time after date")]
+    #[test_case("12am tomorrow {desc}" => Some("03.02.2007 00:00 {desc}".to_owned()) ; "time before date")]
+    #[test_case("in 1 hour 30 mins {desc}" => Some("in 1h30m {desc}".to_owned()) ; "several units")]
+    #[test_case("10:00 tomorrow {desc}" => None ; "numeric time before date")]
+    #[test_case("next week {desc}" => None ; "not weekday")]
+    #[test_case("in 2 {desc}" => None ; "no unit")]
+    #[test_case("tomorrow evening {desc}" => Some("03.02.2007 19:00 {desc}".to_owned()) ; "part of the day")]
+    #[test_case("at night {desc}" => Some("22:00 {desc}".to_owned()) ; "part of the day today")]
+    fn test_rewrite_natural(s: &str) -> Option<String> {
+        rewrite_natural(s, TEST_TIME.naive_local(), 0)
+    }
+
+    #[test]
+    fn test_rewrite_custom_day_part() {
+        // The id doesn't belong to the users of the other tests
+        let user_id = 44;
+        settings::set_day_parts(
+            &DayPart::Morning
+                .set(settings::defaults(user_id as i64), Some(7 * 60 + 30)),
+        );
+        assert_eq!(
+            rewrite_natural(
+                "tomorrow morning run",
+                TEST_TIME.naive_local(),
+                user_id
+            ),
+            Some("03.02.2007 07:30 run".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_crontab_entries() {
+        let content = concat!(
+            "# m h dom mon dow command\n",
+            "SHELL=/bin/bash\n",
+            "MAILTO = admin\n",
+            "\n",
+            "*/5 * * * * check disk space\n",
+            "  0 9 * * 1-5   standup  \n",
+        );
+        assert_eq!(
+            crontab_entries(content).collect::<Vec<_>>(),
+            vec![
+                (5, "*/5 * * * * check disk space"),
+                (6, "0 9 * * 1-5   standup")
+            ]
+        );
+    }
 }
@@ -0,0 +1,403 @@
+//! Setting a reminder step by step with /new for those who don't know
+//! the formats yet: the day and the time are picked on keyboards, then
+//! the description is sent as a message. Other dates are picked on a
+//! month calendar, which also moves a reminder being edited.
+
+use crate::settings;
+use chrono::{Datelike, Months, NaiveDate, NaiveDateTime, TimeDelta};
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+};
+
+/// Minutes between the times offered within an hour
+const MINUTE_STEP: u32 = 15;
+/// Days offered by their weekday after today and tomorrow
+const WEEKDAY_DAYS: i64 = 6;
+const DAYS_PER_ROW: usize = 3;
+const HOURS_PER_ROW: usize = 4;
+/// Callback data of the calendar buttons that do nothing
+const CALENDAR_IGNORE: &str = "cal::ignore";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Step {
+    Day(NaiveDate),
+    Hour(NaiveDate, u32),
+    Time(NaiveDate, u32, u32),
+}
+
+/// What the date picked on the calendar is for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Purpose {
+    New,
+    Edit(i64),
+}
+
+impl Purpose {
+    fn callback_prefix(self) -> String {
+        match self {
+            Self::New => "cal::new".to_owned(),
+            Self::Edit(rem_id) => format!("cal::edit::{}", rem_id),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CalendarAction {
+    /// Show the month starting on the date
+    Month(Purpose, NaiveDate),
+    Day(Purpose, NaiveDate),
+    Ignore,
+}
+
+fn button(label: String, data: String) -> InlineKeyboardButton {
+    InlineKeyboardButton::new(
+        label,
+        InlineKeyboardButtonKind::CallbackData(data),
+    )
+}
+
+fn is_ahead(
+    date: NaiveDate,
+    hour: u32,
+    minute: u32,
+    now: NaiveDateTime,
+) -> bool {
+    date.and_hms_opt(hour, minute, 0)
+        .is_some_and(|time| time > now)
+}
+
+fn minutes_ahead(
+    date: NaiveDate,
+    hour: u32,
+    now: NaiveDateTime,
+) -> impl Iterator<Item = u32> {
+    (0..60)
+        .step_by(MINUTE_STEP as usize)
+        .filter(move |&minute| is_ahead(date, hour, minute, now))
+}
+
+fn hours_ahead(
+    date: NaiveDate,
+    now: NaiveDateTime,
+) -> impl Iterator<Item = u32> {
+    (0..24).filter(move |&hour| minutes_ahead(date, hour, now).next().is_some())
+}
+
+/// Today unless it's too late for it, tomorrow and the days of the
+/// following week by their weekday, now being the user's local time
+pub(crate) fn day_markup(
+    now: NaiveDateTime,
+    style: settings::DateStyle,
+) -> InlineKeyboardMarkup {
+    let lang = style.language;
+    let today = now.date();
+    let tomorrow = today + TimeDelta::days(1);
+    let first_row = hours_ahead(today, now)
+        .next()
+        .map(|_| (lang.text("button_today", &[]), today))
+        .into_iter()
+        .chain(std::iter::once((
+            lang.text("button_tomorrow", &[]),
+            tomorrow,
+        )))
+        .map(|(label, date)| button(label, format!("new::day::{}", date)))
+        .collect();
+    let weekdays: Vec<_> = (2..2 + WEEKDAY_DAYS)
+        .map(|days| today + TimeDelta::days(days))
+        .map(|date| {
+            button(style.format_day(date, false), format!("new::day::{}", date))
+        })
+        .collect();
+    let other_date = button(
+        lang.text("button_other_date", &[]),
+        format!(
+            "{}::month::{}",
+            Purpose::New.callback_prefix(),
+            month_start(today)
+        ),
+    );
+    InlineKeyboardMarkup::new(
+        std::iter::once(first_row)
+            .chain(weekdays.chunks(DAYS_PER_ROW).map(<[_]>::to_vec))
+            .chain(std::iter::once(vec![other_date])),
+    )
+}
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+fn ignored(label: &str) -> InlineKeyboardButton {
+    button(label.to_owned(), CALENDAR_IGNORE.to_owned())
+}
+
+/// Days of the month in weeks starting on the user's first day of the
+/// week, the days before today are left blank and so are the months
+/// before the current one
+pub(crate) fn calendar_markup(
+    purpose: Purpose,
+    month: NaiveDate,
+    today: NaiveDate,
+    style: settings::DateStyle,
+) -> InlineKeyboardMarkup {
+    let month = month_start(month);
+    let prefix = purpose.callback_prefix();
+    let navigation = |label: &str, month: Option<NaiveDate>| match month {
+        Some(month) if month >= month_start(today) => {
+            button(label.to_owned(), format!("{}::month::{}", prefix, month))
+        }
+        _ => ignored(" "),
+    };
+    let header = vec![
+        navigation("◀️", month.checked_sub_months(Months::new(1))),
+        ignored(&format!(
+            "{} {}",
+            style.language.month_name(month.month()).unwrap_or_default(),
+            month.year()
+        )),
+        navigation("▶️", month.checked_add_months(Months::new(1))),
+    ];
+    let weekdays = style
+        .week()
+        .map(|day| {
+            ignored(&style.weekday(day).chars().take(2).collect::<String>())
+        })
+        .collect();
+    let leading_blanks = (7 + month.weekday().num_days_from_monday()
+        - style.week_start.num_days_from_monday())
+        as usize
+        % 7;
+    let days: Vec<_> = std::iter::repeat_with(|| ignored(" "))
+        .take(leading_blanks)
+        .chain(
+            month
+                .iter_days()
+                .take_while(|date| date.month() == month.month())
+                .map(|date| {
+                    if date < today {
+                        ignored(" ")
+                    } else {
+                        button(
+                            date.day().to_string(),
+                            format!("{}::day::{}", prefix, date),
+                        )
+                    }
+                }),
+        )
+        .collect();
+    InlineKeyboardMarkup::new([header, weekdays].into_iter().chain(
+        days.chunks(7).map(|week| {
+            let mut week = week.to_vec();
+            week.resize_with(7, || ignored(" "));
+            week
+        }),
+    ))
+}
+
+/// Hours of the day that aren't over yet
+pub(crate) fn hour_markup(
+    date: NaiveDate,
+    now: NaiveDateTime,
+    clock_12h: bool,
+) -> InlineKeyboardMarkup {
+    let hours: Vec<_> = hours_ahead(date, now)
+        .map(|hour| {
+            button(
+                settings::format_clock(hour, 0, clock_12h),
+                format!("new::hour::{}::{}", date, hour),
+            )
+        })
+        .collect();
+    InlineKeyboardMarkup::new(hours.chunks(HOURS_PER_ROW).map(<[_]>::to_vec))
+}
+
+/// Times within the hour that aren't over yet
+pub(crate) fn minute_markup(
+    date: NaiveDate,
+    hour: u32,
+    now: NaiveDateTime,
+    clock_12h: bool,
+) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([minutes_ahead(date, hour, now)
+        .map(|minute| {
+            button(
+                settings::format_clock(hour, minute, clock_12h),
+                format!("new::time::{}::{}::{}", date, hour, minute),
+            )
+        })
+        .collect::<Vec<_>>()])
+}
+
+/// Beginning of the reminder at the picked time, the description is
+/// appended to it, e.g. `03.06.2024 10:30`
+pub(crate) fn reminder_time(date: NaiveDate, hour: u32, minute: u32) -> String {
+    format!(
+        "{:02}.{:02}.{} {:02}:{:02}",
+        date.day(),
+        date.month(),
+        date.year(),
+        hour,
+        minute
+    )
+}
+
+pub(crate) fn parse_callback(cb_data: &str) -> Option<Step> {
+    let mut parts = cb_data.strip_prefix("new::")?.split("::");
+    let kind = parts.next()?;
+    let date = parts.next()?.parse().ok()?;
+    let mut numbers = parts.map(str::parse::<u32>);
+    let step = match kind {
+        "day" => Step::Day(date),
+        "hour" => Step::Hour(date, numbers.next()?.ok().filter(|&h| h < 24)?),
+        "time" => Step::Time(
+            date,
+            numbers.next()?.ok().filter(|&h| h < 24)?,
+            numbers.next()?.ok().filter(|&m| m < 60)?,
+        ),
+        _ => return None,
+    };
+    numbers.next().is_none().then_some(step)
+}
+
+pub(crate) fn parse_calendar_callback(cb_data: &str) -> Option<CalendarAction> {
+    if cb_data == CALENDAR_IGNORE {
+        return Some(CalendarAction::Ignore);
+    }
+    let mut parts = cb_data.strip_prefix("cal::")?.split("::");
+    let purpose = match parts.next()? {
+        "new" => Purpose::New,
+        "edit" => Purpose::Edit(parts.next()?.parse().ok()?),
+        _ => return None,
+    };
+    let kind = parts.next()?;
+    let date = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    match kind {
+        "month" => Some(CalendarAction::Month(purpose, date)),
+        "day" => Some(CalendarAction::Day(purpose, date)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    fn labels(markup: &InlineKeyboardMarkup) -> Vec<Vec<String>> {
+        markup
+            .inline_keyboard
+            .iter()
+            .map(|row| row.iter().map(|button| button.text.clone()).collect())
+            .collect()
+    }
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, 1)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_day_markup() {
+        assert_eq!(
+            labels(&day_markup(at(9, 0), settings::DateStyle::default())),
+            vec![
+                vec!["Today", "Tomorrow"],
+                vec!["Mon 03.06", "Tue 04.06", "Wed 05.06"],
+                vec!["Thu 06.06", "Fri 07.06", "Sat 08.06"],
+                vec!["📅 Other date"],
+            ]
+        );
+        assert_eq!(
+            labels(&day_markup(at(23, 50), settings::DateStyle::default()))[0],
+            vec!["Tomorrow"]
+        );
+    }
+
+    #[test]
+    fn test_time_markups() {
+        let today = at(0, 0).date();
+        assert_eq!(
+            labels(&hour_markup(today, at(21, 50), false)),
+            vec![vec!["22:00", "23:00"]]
+        );
+        assert_eq!(
+            labels(&minute_markup(today, 21, at(21, 20), true)),
+            vec![vec!["9:30 PM", "9:45 PM"]]
+        );
+    }
+
+    #[test_case("new::day::2024-06-03" => Some(Step::Day(NaiveDate::from_ymd_opt(2024, 6, 3).unwrap())) ; "day")]
+    #[test_case("new::time::2024-06-03::9::45" => Some(Step::Time(NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(), 9, 45)) ; "time")]
+    #[test_case("new::hour::2024-06-03::24" => None ; "hour out of range")]
+    #[test_case("new::hour::2024-06-03::9::45" => None ; "extra part")]
+    fn test_parse_callback(cb_data: &str) -> Option<Step> {
+        parse_callback(cb_data)
+    }
+
+    #[test]
+    fn test_calendar_markup() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 12).unwrap();
+        let markup = calendar_markup(
+            Purpose::New,
+            today,
+            today,
+            settings::DateStyle::default(),
+        );
+        let rows = labels(&markup);
+        assert_eq!(rows[0], vec![" ", "June 2024", "▶️"]);
+        assert_eq!(rows[1], vec!["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"]);
+        assert_eq!(rows[2], vec![" "; 7]);
+        assert_eq!(rows[4], vec![" ", " ", "12", "13", "14", "15", "16"]);
+        assert_eq!(rows[6], vec!["24", "25", "26", "27", "28", "29", "30"]);
+        assert_eq!(rows.len(), 7);
+
+        let next_month = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        let markup = calendar_markup(
+            Purpose::Edit(5),
+            next_month,
+            today,
+            settings::DateStyle::default(),
+        );
+        assert_eq!(labels(&markup)[0], vec!["◀️", "July 2024", "▶️"]);
+        assert_eq!(
+            markup.inline_keyboard[2][0].kind,
+            InlineKeyboardButtonKind::CallbackData(
+                "cal::edit::5::day::2024-07-01".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn test_calendar_markup_sunday_first() {
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let style = settings::DateStyle {
+            week_start: chrono::Weekday::Sun,
+            ..Default::default()
+        };
+        let rows = labels(&calendar_markup(Purpose::New, today, today, style));
+        assert_eq!(rows[1], vec!["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"]);
+        // June 2024 starts on Saturday
+        assert_eq!(rows[2], vec![" ", " ", " ", " ", " ", " ", "1"]);
+        assert_eq!(rows[3], vec!["2", "3", "4", "5", "6", "7", "8"]);
+    }
+
+    #[test_case("cal::ignore" => Some(CalendarAction::Ignore) ; "ignore")]
+    #[test_case("cal::new::month::2024-07-01" => Some(CalendarAction::Month(Purpose::New, NaiveDate::from_ymd_opt(2024, 7, 1).unwrap())) ; "month")]
+    #[test_case("cal::edit::5::day::2024-07-03" => Some(CalendarAction::Day(Purpose::Edit(5), NaiveDate::from_ymd_opt(2024, 7, 3).unwrap())) ; "edit day")]
+    #[test_case("cal::edit::day::2024-07-03" => None ; "missing reminder")]
+    #[test_case("cal::new::day::2024-07-03::1" => None ; "extra part")]
+    fn test_parse_calendar_callback(cb_data: &str) -> Option<CalendarAction> {
+        parse_calendar_callback(cb_data)
+    }
+
+    #[test]
+    fn test_reminder_time() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        assert_eq!(reminder_time(date, 9, 5), "03.06.2024 09:05");
+    }
+}
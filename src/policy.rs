@@ -0,0 +1,218 @@
+use crate::cli;
+use crate::entity::user_settings;
+use crate::err::Error;
+use crate::tg;
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::{Message, ThreadId};
+
+/// Whom to mention when a reminder fires in a group chat
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MentionPolicy {
+    #[default]
+    Creator,
+    Nobody,
+}
+
+/// How important the reminder is, the important ones are sent with a
+/// sound and marked, the critical ones are also pinned
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Priority {
+    High,
+    Critical,
+}
+
+/// Per-reminder delivery options, stored serialized alongside a reminder.
+/// Missing fields fall back to the defaults, so options can be added
+/// without migrating the stored values.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub(crate) struct NotificationPolicy {
+    /// Send without a sound
+    pub(crate) silent: bool,
+    /// Pin the sent message in the chat
+    pub(crate) pin: bool,
+    /// Pin the sent message until it's marked done
+    pub(crate) pin_until_done: bool,
+    /// Delete the sent message after this many seconds
+    pub(crate) autodelete: Option<u64>,
+    pub(crate) mention: MentionPolicy,
+    /// Resend the reminder every this many seconds until it's marked done
+    pub(crate) nag: Option<u64>,
+    /// Offer to remind again in this many seconds
+    pub(crate) snooze: Option<u64>,
+    /// How important the reminder was marked with `!!` or `!!!`
+    pub(crate) priority: Option<Priority>,
+}
+
+impl NotificationPolicy {
+    pub(crate) fn from_stored(stored: Option<&str>) -> Self {
+        stored
+            .and_then(|s| {
+                serde_json::from_str(s)
+                    .map_err(|err| tracing::warn!("bad policy {}: {}", s, err))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn to_stored(&self) -> Option<String> {
+        serde_json::to_string(self).ok()
+    }
+
+    /// Options of the reminder completed with the ones the creator
+    /// chose in /settings for all their reminders
+    pub(crate) fn with_settings(self, settings: &user_settings::Model) -> Self {
+        Self {
+            silent: self.silent || settings.silent,
+            pin_until_done: self.pin_until_done || settings.pin_until_done,
+            snooze: self.snooze.or(settings.snooze.map(|secs| secs as u64)),
+            ..self
+        }
+    }
+
+    /// Options of the reminder raised for its priority: the important
+    /// ones are sent with a sound, and the critical ones are also pinned
+    pub(crate) fn with_priority(self) -> Self {
+        match self.priority {
+            Some(Priority::High) => Self {
+                silent: false,
+                ..self
+            },
+            Some(Priority::Critical) => Self {
+                silent: false,
+                pin: true,
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    /// Interval of resending the reminder, if it should be resent at all
+    pub(crate) fn nag_interval(&self) -> Option<TimeDelta> {
+        self.nag
+            .or(cli::nag_interval().map(|minutes| minutes * 60))
+            .filter(|&secs| secs > 0)
+            .map(|secs| TimeDelta::seconds(secs as i64))
+    }
+}
+
+/// Send a formatted reminder text applying the delivery options,
+/// to the forum topic and in reply to a message if given
+pub(crate) async fn deliver(
+    text: &str,
+    policy: &NotificationPolicy,
+    bot: &Bot,
+    chat_id: ChatId,
+    thread_id: Option<ThreadId>,
+    reply: Option<&tg::Reply>,
+) -> Result<Message, Error> {
+    let msg =
+        tg::_send_message(text, bot, chat_id, thread_id, policy.silent, reply)
+            .await?;
+    if policy.pin || policy.pin_until_done {
+        // Lacking the rights to pin shouldn't fail the delivery
+        if let Err(err) =
+            tg::pin_message(bot, chat_id, msg.id, policy.silent).await
+        {
+            tracing::warn!("failed to pin message in {}: {}", chat_id, err);
+        }
+    }
+    if let Some(secs) = policy.autodelete {
+        let bot = bot.clone();
+        let msg_id = msg.id;
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(secs)).await;
+            tg::delete_message(&bot, chat_id, msg_id)
+                .await
+                .unwrap_or_else(|err| tracing::warn!("{}", err));
+        });
+    }
+    Ok(msg)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::settings;
+    use test_case::test_case;
+
+    #[test_case(None, NotificationPolicy::default() ; "missing")]
+    #[test_case(Some("{}"), NotificationPolicy::default() ; "empty")]
+    #[test_case(Some("not json"), NotificationPolicy::default() ; "malformed")]
+    #[test_case(
+        Some(r#"{"silent":true,"mention":"nobody"}"#),
+        NotificationPolicy {
+            silent: true,
+            mention: MentionPolicy::Nobody,
+            ..Default::default()
+        } ; "partial"
+    )]
+    #[test_case(
+        Some(r#"{"priority":"critical"}"#),
+        NotificationPolicy {
+            priority: Some(Priority::Critical),
+            ..Default::default()
+        } ; "priority"
+    )]
+    fn test_from_stored(stored: Option<&str>, expected: NotificationPolicy) {
+        assert_eq!(NotificationPolicy::from_stored(stored), expected);
+    }
+
+    #[test]
+    fn test_with_settings() {
+        let settings = user_settings::Model {
+            silent: true,
+            snooze: Some(900),
+            pin_until_done: true,
+            ..settings::defaults(1)
+        };
+        let policy = NotificationPolicy {
+            snooze: Some(60),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy.with_settings(&settings),
+            NotificationPolicy {
+                silent: true,
+                pin_until_done: true,
+                snooze: Some(60),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            NotificationPolicy::default()
+                .with_settings(&settings)
+                .snooze,
+            Some(900)
+        );
+    }
+
+    #[test_case(None, true, false ; "none")]
+    #[test_case(Some(Priority::High), false, false ; "high")]
+    #[test_case(Some(Priority::Critical), false, true ; "critical")]
+    fn test_with_priority(priority: Option<Priority>, silent: bool, pin: bool) {
+        let policy = NotificationPolicy {
+            silent: true,
+            priority,
+            ..Default::default()
+        }
+        .with_priority();
+        assert_eq!((policy.silent, policy.pin), (silent, pin));
+    }
+
+    #[test_case(None, None ; "disabled")]
+    #[test_case(Some(0), None ; "zero")]
+    #[test_case(Some(90), Some(TimeDelta::seconds(90)) ; "enabled")]
+    fn test_nag_interval(nag: Option<u64>, expected: Option<TimeDelta>) {
+        let policy = NotificationPolicy {
+            nag,
+            ..Default::default()
+        };
+        assert_eq!(policy.nag_interval(), expected);
+    }
+}
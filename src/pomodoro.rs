@@ -0,0 +1,150 @@
+//! Focus sessions alternating work and break phases, e.g. `/pomodoro 25 5 x4`
+
+use crate::entity::pomodoro;
+use sea_orm::{ActiveValue::NotSet, Set};
+
+const DEFAULT_WORK: u32 = 25;
+const DEFAULT_REST: u32 = 5;
+const DEFAULT_ROUNDS: u32 = 4;
+const MAX_MINUTES: u32 = 180;
+const MAX_ROUNDS: u32 = 12;
+
+/// Lengths of the phases in minutes and the number of work phases
+#[derive(Debug, PartialEq)]
+pub(crate) struct Plan {
+    pub(crate) work: u32,
+    pub(crate) rest: u32,
+    pub(crate) rounds: u32,
+}
+
+impl Default for Plan {
+    fn default() -> Self {
+        Self {
+            work: DEFAULT_WORK,
+            rest: DEFAULT_REST,
+            rounds: DEFAULT_ROUNDS,
+        }
+    }
+}
+
+impl Plan {
+    /// Parse `[work] [rest] [xrounds]`, the omitted values are defaulted
+    pub(crate) fn parse(args: &str) -> Option<Self> {
+        let mut plan = Self::default();
+        let mut minutes = vec![];
+        for arg in args.split_whitespace() {
+            if let Some(rounds) = arg.strip_prefix(['x', 'X']) {
+                plan.rounds = rounds.parse().ok()?;
+            } else {
+                minutes.push(arg.trim_end_matches('m').parse().ok()?);
+            }
+        }
+        match minutes[..] {
+            [] => {}
+            [work] => plan.work = work,
+            [work, rest] => {
+                plan.work = work;
+                plan.rest = rest;
+            }
+            _ => return None,
+        }
+        ((1..=MAX_MINUTES).contains(&plan.work)
+            && (1..=MAX_MINUTES).contains(&plan.rest)
+            && (1..=MAX_ROUNDS).contains(&plan.rounds))
+        .then_some(plan)
+    }
+
+    pub(crate) fn new_session(&self, chat_id: i64) -> pomodoro::ActiveModel {
+        pomodoro::ActiveModel {
+            id: NotSet,
+            chat_id: Set(chat_id),
+            work: Set(self.work as i32),
+            rest: Set(self.rest as i32),
+            rounds: Set(self.rounds as i32),
+            phase: Set(0),
+        }
+    }
+
+    /// Minutes from the start until the end of each phase,
+    /// there is no break after the last work phase
+    pub(crate) fn phase_ends(&self) -> Vec<u32> {
+        (0..self.rounds * 2 - 1)
+            .scan(0, |end, phase| {
+                *end += if phase % 2 == 0 { self.work } else { self.rest };
+                Some(*end)
+            })
+            .collect()
+    }
+}
+
+/// What has just finished in a session
+#[derive(Debug, PartialEq)]
+pub(crate) enum Progress {
+    /// Work phase with this number is over, a break starts
+    WorkDone(i32),
+    /// Break is over, work phase with this number starts
+    BreakDone(i32),
+    Finished,
+}
+
+/// Progress after `session.phase` phases are over
+pub(crate) fn progress(session: &pomodoro::Model) -> Progress {
+    if session.phase >= session.rounds * 2 - 1 {
+        Progress::Finished
+    } else if session.phase % 2 == 1 {
+        Progress::WorkDone((session.phase + 1) / 2)
+    } else {
+        Progress::BreakDone(session.phase / 2 + 1)
+    }
+}
+
+/// Number of completed work phases
+pub(crate) fn completed_rounds(session: &pomodoro::Model) -> i32 {
+    (session.phase + 1) / 2
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("" => Some(Plan::default()) ; "defaults")]
+    #[test_case("25 5 x4" => Some(Plan { work: 25, rest: 5, rounds: 4 }) ; "full")]
+    #[test_case("50m 10m" => Some(Plan { work: 50, rest: 10, rounds: 4 }) ; "minutes suffix")]
+    #[test_case("x2" => Some(Plan { work: 25, rest: 5, rounds: 2 }) ; "only rounds")]
+    #[test_case("45" => Some(Plan { work: 45, rest: 5, rounds: 4 }) ; "only work")]
+    #[test_case("25 5 5" => None ; "too many lengths")]
+    #[test_case("0 5" => None ; "zero work")]
+    #[test_case("25 5 x0" => None ; "zero rounds")]
+    #[test_case("25 5 x100" => None ; "too many rounds")]
+    #[test_case("soon" => None ; "garbage")]
+    fn test_parse(args: &str) -> Option<Plan> {
+        Plan::parse(args)
+    }
+
+    #[test]
+    fn test_phase_ends() {
+        let plan = Plan {
+            work: 25,
+            rest: 5,
+            rounds: 3,
+        };
+        assert_eq!(plan.phase_ends(), vec![25, 30, 55, 60, 85]);
+    }
+
+    #[test_case(1 => Progress::WorkDone(1))]
+    #[test_case(2 => Progress::BreakDone(2))]
+    #[test_case(3 => Progress::WorkDone(2))]
+    #[test_case(6 => Progress::BreakDone(4))]
+    #[test_case(7 => Progress::Finished)]
+    fn test_progress(phase: i32) -> Progress {
+        progress(&pomodoro::Model {
+            id: 1,
+            chat_id: 1,
+            work: 25,
+            rest: 5,
+            rounds: 4,
+            phase,
+        })
+    }
+}
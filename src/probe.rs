@@ -0,0 +1,170 @@
+//! Deleting the reminders whose messages that created them were deleted.
+//! Telegram doesn't tell bots about deleted messages, so with
+//! `--probe-chat-id` the messages are forwarded to that chat every few
+//! hours and the copies are deleted from it right away. The reminders of
+//! the messages that can't be forwarded anymore are deleted.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(not(test))]
+use crate::db::Database;
+use crate::db::LinkTarget;
+#[cfg(test)]
+use crate::db::MockDatabase as Database;
+use crate::entity::message_link;
+use teloxide::prelude::*;
+use teloxide::types::MessageId;
+use teloxide::{ApiError, RequestError};
+use tokio_util::sync::CancellationToken;
+
+/// Time between the rounds of checking all the messages
+const PROBE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+/// Number of the messages loaded from the database at once
+const BATCH_SIZE: u64 = 100;
+/// Pause between the forwards, so that the probe chat stays within
+/// the rate limits of Telegram
+const PROBE_DELAY: Duration = Duration::from_secs(3);
+
+/// Check the messages every few hours until the shutdown
+pub(crate) async fn poll(
+    db: Arc<Database>,
+    bot: Bot,
+    probe_chat: ChatId,
+    shutdown: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            () = shutdown.cancelled() => break,
+            () = check_all(&db, &bot, probe_chat) => {}
+        }
+        tokio::select! {
+            () = shutdown.cancelled() => break,
+            () = tokio::time::sleep(PROBE_INTERVAL) => {}
+        }
+    }
+}
+
+async fn check_all(db: &Database, bot: &Bot, probe_chat: ChatId) {
+    let mut after_id = 0;
+    loop {
+        let links = match db.get_creation_links(after_id, BATCH_SIZE).await {
+            Ok(links) => links,
+            Err(err) => {
+                tracing::error!("{}", err);
+                return;
+            }
+        };
+        let Some(last) = links.last() else {
+            return;
+        };
+        after_id = last.id;
+        for link in links {
+            check(&link, db, bot, probe_chat).await;
+            tokio::time::sleep(PROBE_DELAY).await;
+        }
+    }
+}
+
+/// Move the reminder to the trash if the message it was created from was
+/// deleted, it can be restored if the message only seemed to be gone
+async fn check(
+    link: &message_link::Model,
+    db: &Database,
+    bot: &Bot,
+    probe_chat: ChatId,
+) {
+    let Some(target) = LinkTarget::of(link) else {
+        return;
+    };
+    let chat_id = ChatId(link.chat_id);
+    match message_exists(chat_id, MessageId(link.msg_id), bot, probe_chat).await
+    {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::info!(
+                "Deleting {:?} since its message {} was deleted",
+                target,
+                link.msg_id
+            );
+            let deleted = match target {
+                LinkTarget::Reminder(id) => db.trash_reminder(id).await,
+                LinkTarget::CronReminder(id) => {
+                    db.trash_cron_reminder(id).await
+                }
+            };
+            deleted.unwrap_or_else(|err| tracing::error!("{}", err));
+        }
+        // E.g. the bot was removed from the chat or it forbids forwarding
+        Err(err) => tracing::debug!("Can't check {:?}: {}", target, err),
+    }
+}
+
+/// Whether the message can still be forwarded to the probe chat
+async fn message_exists(
+    chat_id: ChatId,
+    msg_id: MessageId,
+    bot: &Bot,
+    probe_chat: ChatId,
+) -> Result<bool, RequestError> {
+    match bot
+        .forward_message(probe_chat, chat_id, msg_id)
+        .disable_notification(true)
+        .await
+    {
+        Ok(copy) => {
+            if let Err(err) = bot.delete_message(probe_chat, copy.id).await {
+                tracing::warn!("Failed to delete the probe message: {}", err);
+            }
+            Ok(true)
+        }
+        Err(RequestError::Api(
+            ApiError::MessageToForwardNotFound | ApiError::MessageIdInvalid,
+        )) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::message_link::MessageRole;
+    use crate::flaky::{Failure, FlakyApi};
+    use mockall::predicate::eq;
+
+    fn creation_link() -> message_link::Model {
+        message_link::Model {
+            id: 1,
+            chat_id: 1,
+            msg_id: 2,
+            role: MessageRole::Creation,
+            reminder_id: Some(3),
+            cron_reminder_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_deleted() {
+        let api = FlakyApi::start();
+        api.fail([Failure::BadRequest(
+            "Bad Request: message to forward not found",
+        )]);
+        let mut db = Database::new();
+        db.expect_trash_reminder()
+            .with(eq(3))
+            .times(1)
+            .returning(|_| Ok(()));
+        check(&creation_link(), &db, &api.bot(), ChatId(-100)).await;
+        assert_eq!(api.methods(), vec!["ForwardMessage"]);
+    }
+
+    #[tokio::test]
+    async fn test_check_existing() {
+        let api = FlakyApi::start();
+        let mut db = Database::new();
+        db.expect_trash_reminder().never();
+        check(&creation_link(), &db, &api.bot(), ChatId(-100)).await;
+        assert_eq!(api.methods(), vec!["ForwardMessage", "DeleteMessage"]);
+        assert_eq!(api.requests()[0].1["chat_id"], -100);
+    }
+}
@@ -0,0 +1,117 @@
+//! Retrying the reminders that failed to be sent, e.g. while Telegram or
+//! the network is down, waiting longer after each failure in a row.
+//! Telegram asking to slow down is waited out without counting it as a
+//! failure. After too many failures the reminder isn't retried anymore
+//! and is shown as failed in /list until it's resumed with /pause.
+//! The reminders of a chat that blocked the bot fail right away and are
+//! resumed when the chat sends /start again.
+
+use crate::err::Error;
+use chrono::{NaiveDateTime, TimeDelta};
+use teloxide::{ApiError, RequestError};
+
+/// Failures in a row after which the reminder isn't retried
+pub(crate) const MAX_FAILURES: i32 = 5;
+
+/// Delay before the first retry, doubled after each failure
+const FIRST_DELAY: TimeDelta = TimeDelta::seconds(30);
+
+const MAX_DELAY: TimeDelta = TimeDelta::hours(1);
+
+/// Failures of the reminder in a row and the time to retry it at after
+/// another failed attempt, no time if it's not retried anymore
+pub(crate) fn after_failure(
+    failures: i32,
+    err: &Error,
+    now: NaiveDateTime,
+) -> (i32, Option<NaiveDateTime>) {
+    if let Error::TeloxideRequest(RequestError::RetryAfter(secs)) = err {
+        let wait = TimeDelta::seconds(secs.seconds() as i64);
+        return (failures, Some(now + wait));
+    }
+    let failures = failures + 1;
+    if is_failed(failures) {
+        return (failures, None);
+    }
+    let delay = FIRST_DELAY
+        .checked_mul(1 << (failures - 1).min(16))
+        .map_or(MAX_DELAY, |delay| delay.min(MAX_DELAY));
+    (failures, Some(now + delay))
+}
+
+/// Whether the chat can't receive the reminders anymore, e.g. the user
+/// blocked the bot or it was removed from the group, so retrying them is
+/// pointless until the chat comes back with /start
+pub(crate) fn is_unreachable(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::TeloxideRequest(RequestError::Api(
+            ApiError::BotBlocked
+                | ApiError::ChatNotFound
+                | ApiError::BotKicked
+                | ApiError::BotKickedFromSupergroup
+                | ApiError::UserDeactivated
+                | ApiError::CantInitiateConversation
+        ))
+    )
+}
+
+/// Whether the reminder failed to be sent too many times to retry it
+pub(crate) fn is_failed(failures: i32) -> bool {
+    failures >= MAX_FAILURES
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use teloxide::types::Seconds;
+    use test_case::test_case;
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2024-06-01 10:00", "%Y-%m-%d %H:%M")
+            .unwrap()
+    }
+
+    fn blocked() -> Error {
+        Error::TeloxideRequest(RequestError::Api(ApiError::BotBlocked))
+    }
+
+    #[test_case(0 => (1, Some(30)) ; "first")]
+    #[test_case(2 => (3, Some(120)) ; "doubled")]
+    #[test_case(4 => (5, None) ; "failed")]
+    fn test_after_failure(failures: i32) -> (i32, Option<i64>) {
+        let (failures, retry_time) = after_failure(failures, &blocked(), now());
+        (
+            failures,
+            retry_time.map(|time| (time - now()).num_seconds()),
+        )
+    }
+
+    #[test]
+    fn test_after_failure_capped() {
+        let mut delays = vec![];
+        for failures in 0..MAX_FAILURES - 1 {
+            let (_, retry_time) = after_failure(failures, &blocked(), now());
+            delays.push(retry_time.unwrap() - now());
+        }
+        assert!(delays.iter().all(|&delay| delay <= MAX_DELAY));
+    }
+
+    #[test_case(ApiError::BotBlocked => true ; "blocked")]
+    #[test_case(ApiError::ChatNotFound => true ; "chat not found")]
+    #[test_case(ApiError::MessageTextIsEmpty => false ; "other")]
+    fn test_is_unreachable(err: ApiError) -> bool {
+        is_unreachable(&Error::TeloxideRequest(RequestError::Api(err)))
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let err = Error::TeloxideRequest(RequestError::RetryAfter(
+            Seconds::from_seconds(42),
+        ));
+        assert_eq!(
+            after_failure(3, &err, now()),
+            (3, Some(now() + TimeDelta::seconds(42)))
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Arguments of /list narrowing down the listed reminders, e.g.
+//! `/list today`, `/list paused` or `/list 01.03-15.03 meeting`,
+//! also choosing the reminders of /deleteall and /pauseall
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Filter {
+    /// Text the description contains, ignoring the case
+    pub(crate) text: Option<String>,
+    /// Start of the time range in UTC
+    pub(crate) from: Option<NaiveDateTime>,
+    /// End of the time range in UTC, exclusive
+    pub(crate) until: Option<NaiveDateTime>,
+    pub(crate) paused: Option<bool>,
+}
+
+/// Date written as `dd.mm`, `dd.mm.yyyy` or `yyyy-mm-dd`,
+/// the current year by default
+pub(crate) fn parse_date(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    if let Ok(day) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Some(day);
+    }
+    let mut parts = s.split('.');
+    let day = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let year = match parts.next() {
+        Some(year) => year.parse().ok()?,
+        None => today.year(),
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// Range of days from the first to the last one inclusive
+fn parse_days(word: &str, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    match word.to_lowercase().as_str() {
+        "today" => Some((today, today)),
+        "tomorrow" => today.succ_opt().map(|day| (day, day)),
+        "week" => Some((today, today + TimeDelta::days(6))),
+        _ => match parse_date(word, today) {
+            Some(day) => Some((day, day)),
+            None => {
+                let (first, last) = word.split_once('-')?;
+                let first = parse_date(first, today)?;
+                let last = parse_date(last, today)?;
+                (first <= last).then_some((first, last))
+            }
+        },
+    }
+}
+
+/// Start of the day in the timezone as UTC
+pub(crate) fn day_start(day: NaiveDate, user_tz: Tz) -> NaiveDateTime {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap();
+    user_tz
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map_or(midnight, |time| time.naive_utc())
+}
+
+impl Filter {
+    /// Active reminders of the day the given number of days after today
+    pub(crate) fn day(
+        days_after_today: u64,
+        user_tz: Tz,
+        now: NaiveDateTime,
+    ) -> Self {
+        let day = user_tz.from_utc_datetime(&now).date_naive()
+            + TimeDelta::days(days_after_today as i64);
+        Self {
+            from: Some(day_start(day, user_tz)),
+            until: day.succ_opt().map(|next_day| day_start(next_day, user_tz)),
+            paused: Some(false),
+            ..Default::default()
+        }
+    }
+
+    /// Active reminders from now until the end of the given number
+    /// of days starting with today
+    pub(crate) fn upcoming(days: u64, user_tz: Tz, now: NaiveDateTime) -> Self {
+        let today = user_tz.from_utc_datetime(&now).date_naive();
+        Self {
+            from: Some(now),
+            until: Some(day_start(
+                today + TimeDelta::days(days as i64),
+                user_tz,
+            )),
+            paused: Some(false),
+            ..Default::default()
+        }
+    }
+
+    /// Understand the words of the arguments: `today`, `tomorrow`, `week`,
+    /// a date or a range of dates, `before` or `after` a date, `paused`
+    /// or `active`. The rest of the words are the text to search for.
+    pub(crate) fn parse(args: &str, user_tz: Tz, now: NaiveDateTime) -> Self {
+        let today = user_tz.from_utc_datetime(&now).date_naive();
+        let mut filter = Self::default();
+        let mut text = vec![];
+        let mut words = args.split_whitespace().peekable();
+        while let Some(word) = words.next() {
+            let bound = match word.to_lowercase().as_str() {
+                "before" | "after" => {
+                    words.peek().and_then(|next| parse_date(next, today))
+                }
+                _ => None,
+            };
+            if let Some(day) = bound {
+                words.next();
+                if word.eq_ignore_ascii_case("before") {
+                    filter.until = Some(day_start(day, user_tz));
+                } else {
+                    filter.from =
+                        day.succ_opt().map(|next| day_start(next, user_tz));
+                }
+            } else if let Some((first, last)) = parse_days(word, today) {
+                filter.from = Some(day_start(first, user_tz));
+                filter.until = last
+                    .succ_opt()
+                    .map(|after_last| day_start(after_last, user_tz));
+            } else if word.eq_ignore_ascii_case("paused") {
+                filter.paused = Some(true);
+            } else if word.eq_ignore_ascii_case("active") {
+                filter.paused = Some(false);
+            } else {
+                text.push(word);
+            }
+        }
+        if !text.is_empty() {
+            filter.text = Some(text.join(" "));
+        }
+        filter
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test_case("" => Filter::default() ; "empty")]
+    #[test_case("today" => Filter {
+        from: Some(utc("2007-02-01 21:00")),
+        until: Some(utc("2007-02-02 21:00")),
+        ..Default::default()
+    } ; "today")]
+    #[test_case("Tomorrow paused" => Filter {
+        from: Some(utc("2007-02-02 21:00")),
+        until: Some(utc("2007-02-03 21:00")),
+        paused: Some(true),
+        ..Default::default()
+    } ; "tomorrow paused")]
+    #[test_case("01.03-15.03 team meeting" => Filter {
+        text: Some("team meeting".to_owned()),
+        from: Some(utc("2007-02-28 21:00")),
+        until: Some(utc("2007-03-15 21:00")),
+        ..Default::default()
+    } ; "date range with text")]
+    #[test_case("15.03-01.03" => Filter {
+        text: Some("15.03-01.03".to_owned()),
+        ..Default::default()
+    } ; "reversed range")]
+    #[test_case("active 31.12.2008" => Filter {
+        from: Some(utc("2008-12-30 21:00")),
+        until: Some(utc("2008-12-31 21:00")),
+        paused: Some(false),
+        ..Default::default()
+    } ; "active on date")]
+    #[test_case("before 2008-01-01" => Filter {
+        until: Some(utc("2007-12-31 21:00")),
+        ..Default::default()
+    } ; "before iso date")]
+    #[test_case("after 01.03 before 15.03 paused" => Filter {
+        from: Some(utc("2007-03-01 21:00")),
+        until: Some(utc("2007-03-14 21:00")),
+        paused: Some(true),
+        ..Default::default()
+    } ; "between dates")]
+    #[test_case("before lunch" => Filter {
+        text: Some("before lunch".to_owned()),
+        ..Default::default()
+    } ; "before text")]
+    fn test_parse(args: &str) -> Filter {
+        Filter::parse(args, chrono_tz::Europe::Moscow, utc("2007-02-02 09:30"))
+    }
+
+    #[test]
+    fn test_day() {
+        let now = utc("2007-02-02 22:30");
+        assert_eq!(
+            Filter::day(1, chrono_tz::Europe::Moscow, now),
+            Filter {
+                from: Some(utc("2007-02-03 21:00")),
+                until: Some(utc("2007-02-04 21:00")),
+                paused: Some(false),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_upcoming() {
+        let now = utc("2007-02-02 05:00");
+        assert_eq!(
+            Filter::upcoming(7, chrono_tz::Europe::Moscow, now),
+            Filter {
+                from: Some(now),
+                until: Some(utc("2007-02-08 21:00")),
+                paused: Some(false),
+                ..Default::default()
+            }
+        );
+    }
+}
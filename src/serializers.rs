@@ -2,16 +2,18 @@ use std::cmp::max;
 use std::fmt::Formatter;
 
 use bitmask_enum::bitmask;
-use chrono::offset::TimeZone;
+use chrono::offset::{LocalResult, TimeZone};
 use chrono::prelude::*;
 use chrono::Duration;
 use chronoutil::{shift_months, shift_years};
 use nonempty::NonEmpty;
 use serde::{Deserialize, Serialize};
 
+use crate::cli;
 use crate::date;
 use crate::grammar;
 use crate::parsers::now_time;
+use crate::settings::DateStyle;
 
 #[derive(Debug)]
 pub(crate) struct Tz(chrono_tz::Tz);
@@ -112,6 +114,9 @@ pub(crate) struct Recurrence {
     pub(crate) time_patterns: Vec<TimePattern>,
     #[serde(rename = "tz")]
     pub(crate) timezone: Tz,
+    /// Number of firings left to schedule, unlimited if absent
+    #[serde(rename = "left", default, skip_serializing_if = "Option::is_none")]
+    pub(crate) remaining: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -135,6 +140,7 @@ trait DateDisplay {
         &self,
         f: &mut Formatter<'_>,
         now: &D,
+        style: DateStyle,
     ) -> Result<bool, std::fmt::Error>;
 }
 
@@ -183,14 +189,47 @@ impl<'de> Deserialize<'de> for Tz {
 }
 
 impl Tz {
+    /// UTC time of the wall-clock time. Of the times repeated when the
+    /// clocks go back the earlier is taken, and the times skipped when
+    /// they go forward are moved forward along with the clocks, so that
+    /// a recurrence keeps firing on such days.
     fn local_to_utc(&self, time: &NaiveDateTime) -> Option<NaiveDateTime> {
-        self.0
-            .from_local_datetime(time)
-            .earliest()
-            .map(|dt| dt.naive_utc())
+        match self.0.from_local_datetime(time) {
+            LocalResult::None => {
+                // the offset in effect before the clocks went forward
+                let offset = self
+                    .0
+                    .offset_from_local_datetime(&(*time - Duration::days(1)))
+                    .earliest()?
+                    .fix();
+                Some(*time - Duration::seconds(offset.local_minus_utc().into()))
+            }
+            res => res.earliest().map(|dt| dt.naive_utc()),
+        }
     }
 }
 
+impl Tz {
+    /// UTC time the interval later on the wall clock
+    fn shift(
+        &self,
+        time: NaiveDateTime,
+        offset: &DateInterval,
+    ) -> Option<NaiveDateTime> {
+        let local = self.0.from_utc_datetime(&time).naive_local();
+        self.local_to_utc(&date::add_interval(local, &(*offset).into()))
+    }
+}
+
+/// UTC time the interval later on the wall clock of the timezone
+pub(crate) fn shift_time(
+    time: NaiveDateTime,
+    offset: &DateInterval,
+    tz: chrono_tz::Tz,
+) -> Option<NaiveDateTime> {
+    Tz(tz).shift(time, offset)
+}
+
 impl From<grammar::Interval> for Interval {
     fn from(interval: grammar::Interval) -> Self {
         Self {
@@ -296,6 +335,24 @@ impl DateRange {
     }
 }
 
+impl DateRange {
+    /// Move the range the interval later, the weekdays along with it
+    fn shift(&mut self, offset: &DateInterval) {
+        let from = date::add_date_interval(self.from, offset);
+        if let DateDivisor::Weekdays(ref mut weekdays) = self.date_divisor {
+            let days = (from - self.from).num_days().rem_euclid(7) as u32;
+            let bits = weekdays.bits() as u32;
+            *weekdays = Weekdays::from(
+                ((bits << days | bits >> (7 - days)) & 0x7f) as u8,
+            );
+        }
+        self.from = from;
+        self.until = self
+            .until
+            .map(|until| date::add_date_interval(until, offset));
+    }
+}
+
 impl Time {
     fn from(time: &grammar::Time) -> Option<NaiveTime> {
         NaiveTime::from_hms_opt(time.hour, time.minute, time.second)
@@ -331,6 +388,20 @@ impl From<grammar::DateInterval> for DateInterval {
     }
 }
 
+impl From<DateInterval> for Interval {
+    fn from(date_interval: DateInterval) -> Self {
+        Self {
+            years: date_interval.years,
+            months: date_interval.months,
+            weeks: date_interval.weeks,
+            days: date_interval.days,
+            hours: 0,
+            minutes: 0,
+            seconds: 0,
+        }
+    }
+}
+
 impl From<grammar::TimeRange> for TimeRange {
     fn from(time_range: grammar::TimeRange) -> Self {
         let from = time_range.from.and_then(|ref time| Time::from(time));
@@ -450,9 +521,36 @@ impl Recurrence {
             dates_patterns,
             time_patterns,
             timezone: Tz(tz),
+            remaining: recurrence.limit,
         })
     }
 
+    /// Shortest interval of the time ranges in seconds
+    fn shortest_interval(&self) -> Option<i64> {
+        self.time_patterns
+            .iter()
+            .filter_map(|pattern| match pattern {
+                TimePattern::Range(range) => {
+                    Some(Duration::from(range.interval).num_seconds())
+                }
+                TimePattern::Point(_) => None,
+            })
+            .min()
+    }
+
+    /// Schedule the next firing, counting it against the limit
+    fn next_limited(&mut self, cur: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self.remaining {
+            Some(0) => None,
+            Some(remaining) => {
+                let next_time = self.next(cur)?;
+                self.remaining = Some(remaining - 1);
+                Some(next_time)
+            }
+            None => self.next(cur),
+        }
+    }
+
     pub(crate) fn next(&self, cur: NaiveDateTime) -> Option<NaiveDateTime> {
         let cur = self.timezone.0.from_utc_datetime(&cur).naive_local();
         let cur_date = cur.date();
@@ -461,7 +559,7 @@ impl Recurrence {
             .dates_patterns
             .iter()
             .flat_map(|pattern| match pattern {
-                &DatePattern::Point(date) => Some(date),
+                &DatePattern::Point(date) => (date >= cur_date).then_some(date),
                 DatePattern::Range(ref range) => {
                     range.get_nearest_date(cur_date)
                 }
@@ -584,6 +682,93 @@ impl Countdown {
 }
 
 impl Countdown {
+    /// Shortest time between the firings in seconds
+    fn shortest_gap(&self) -> Option<i64> {
+        let mut times: Vec<_> = self
+            .durations
+            .iter()
+            .map(|duration| date::add_interval(self.time_from, duration))
+            .collect();
+        times.sort();
+        times
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).num_seconds())
+            .min()
+    }
+}
+
+impl Interval {
+    /// Interval of the number of seconds in weeks and smaller units
+    fn from_seconds(seconds: i64) -> Self {
+        let seconds = seconds.max(0) as u64;
+        Self {
+            years: 0,
+            months: 0,
+            weeks: (seconds / (7 * 24 * 3600)) as u32,
+            days: (seconds / (24 * 3600) % 7) as u32,
+            hours: (seconds / 3600 % 24) as u32,
+            minutes: (seconds / 60 % 60) as u32,
+            seconds: (seconds % 60) as u32,
+        }
+    }
+}
+
+/// Shares in percent of the time left to a countdown's end
+/// at which its milestones fire
+const COUNTDOWN_MILESTONES: [i64; 2] = [50, 90];
+
+impl Countdown {
+    /// Countdown from now to the time, firing at the end
+    /// and optionally at the milestones on the way
+    fn until_with_tz(
+        countdown: grammar::CountdownUntil,
+        tz: chrono_tz::Tz,
+    ) -> Result<Self, ()> {
+        let time_from = now_time();
+        let end = Recurrence::from_with_tz(
+            grammar::Recurrence {
+                dates_patterns: NonEmpty::new(grammar::DatePattern::Point(
+                    countdown.date,
+                )),
+                time_patterns: vec![grammar::TimePattern::Point(
+                    countdown.time,
+                )],
+                limit: None,
+            },
+            tz,
+        )?
+        .next(time_from)
+        .ok_or(())?;
+        // count in the local time, so that the end doesn't move
+        // with daylight saving changes on the way
+        let total = (tz.from_utc_datetime(&end).naive_local()
+            - tz.from_utc_datetime(&time_from).naive_local())
+        .num_seconds();
+        if total <= 0 {
+            return Err(());
+        }
+        let milestones = if countdown.milestones {
+            &COUNTDOWN_MILESTONES[..]
+        } else {
+            &[]
+        };
+        let mut seconds = milestones
+            .iter()
+            .map(|share| total * share / 100)
+            .filter(|&seconds| seconds > 0)
+            .chain(std::iter::once(total))
+            .collect::<Vec<_>>();
+        seconds.dedup();
+        Ok(Self {
+            time_from,
+            durations: seconds
+                .into_iter()
+                .map(Interval::from_seconds)
+                .collect(),
+            timezone: Tz(tz),
+        })
+    }
+
     fn from_with_tz(countdown: grammar::Countdown, tz: chrono_tz::Tz) -> Self {
         Self {
             time_from: now_time(),
@@ -598,9 +783,21 @@ impl Countdown {
 }
 
 impl Pattern {
+    /// Pattern firing no more often than allowed with `--min-interval`
     pub(crate) fn from_with_tz(
         reminder_pattern: grammar::ReminderPattern,
         tz: chrono_tz::Tz,
+    ) -> Result<Self, ()> {
+        let pattern = Self::from_with_tz_unchecked(reminder_pattern, tz)?;
+        if pattern.too_frequent() {
+            return Err(());
+        }
+        Ok(pattern)
+    }
+
+    pub(crate) fn from_with_tz_unchecked(
+        reminder_pattern: grammar::ReminderPattern,
+        tz: chrono_tz::Tz,
     ) -> Result<Self, ()> {
         match reminder_pattern {
             grammar::ReminderPattern::Recurrence(recurrence) => {
@@ -609,28 +806,161 @@ impl Pattern {
             grammar::ReminderPattern::Countdown(countdown) => {
                 Ok(Self::Countdown(Countdown::from_with_tz(countdown, tz)))
             }
+            grammar::ReminderPattern::CountdownUntil(countdown) => {
+                Ok(Self::Countdown(Countdown::until_with_tz(countdown, tz)?))
+            }
         }
     }
 
+    /// Whether the firings may come closer to each other
+    /// than allowed with `--min-interval`
+    pub(crate) fn too_frequent(&self) -> bool {
+        let shortest = match self {
+            Self::Recurrence(recurrence) => recurrence.shortest_interval(),
+            Self::Countdown(countdown) => countdown.shortest_gap(),
+        };
+        shortest.is_some_and(|seconds| seconds < cli::min_interval() as i64)
+    }
+
+    /// Recurrence at a time of day on the given dates
+    pub(crate) fn recurrence(
+        dates_patterns: Vec<DatePattern>,
+        time: NaiveTime,
+        tz: chrono_tz::Tz,
+    ) -> Self {
+        Self::Recurrence(Recurrence {
+            dates_patterns,
+            time_patterns: vec![TimePattern::Point(time)],
+            timezone: Tz(tz),
+            remaining: None,
+        })
+    }
+
+    /// Countdown firing after each of the given numbers of minutes from now
+    pub(crate) fn countdown_minutes(
+        minutes: &[u32],
+        tz: chrono_tz::Tz,
+    ) -> Self {
+        Self::Countdown(Countdown {
+            time_from: now_time(),
+            durations: minutes
+                .iter()
+                .map(|&minutes| Interval {
+                    years: 0,
+                    months: 0,
+                    weeks: 0,
+                    days: 0,
+                    hours: 0,
+                    minutes,
+                    seconds: 0,
+                })
+                .collect(),
+            timezone: Tz(tz),
+        })
+    }
+
     pub(crate) fn next(&mut self, cur: NaiveDateTime) -> Option<NaiveDateTime> {
         match self {
-            Self::Recurrence(recurrence) => recurrence.next(cur),
+            Self::Recurrence(recurrence) => recurrence.next_limited(cur),
             Self::Countdown(countdown) => countdown.next(),
         }
     }
+
+    /// First occurrence after `now`, skipping the missed ones
+    pub(crate) fn next_after(
+        &mut self,
+        now: NaiveDateTime,
+    ) -> Option<NaiveDateTime> {
+        // countdowns go through their occurrences regardless of `now`
+        std::iter::from_fn(|| self.next(now)).find(|&time| time > now)
+    }
+
+    /// Check if there are no more occurrences after `cur`
+    pub(crate) fn is_exhausted(&self, cur: NaiveDateTime) -> bool {
+        match self {
+            Self::Recurrence(recurrence) => {
+                recurrence.remaining == Some(0)
+                    || recurrence.next(cur).is_none()
+            }
+            Self::Countdown(countdown) => countdown.durations.is_empty(),
+        }
+    }
+
+    /// Move all the occurrences the interval later, return where
+    /// the occurrence at `time` moves to
+    pub(crate) fn shift(
+        &mut self,
+        offset: &DateInterval,
+        time: NaiveDateTime,
+    ) -> Option<NaiveDateTime> {
+        let timezone = match self {
+            Self::Recurrence(recurrence) => {
+                for pattern in recurrence.dates_patterns.iter_mut() {
+                    match pattern {
+                        DatePattern::Point(date) => {
+                            *date = date::add_date_interval(*date, offset);
+                        }
+                        DatePattern::Range(range) => range.shift(offset),
+                    }
+                }
+                &recurrence.timezone
+            }
+            Self::Countdown(countdown) => {
+                countdown.time_from =
+                    countdown.timezone.shift(countdown.time_from, offset)?;
+                &countdown.timezone
+            }
+        };
+        timezone.shift(time, offset)
+    }
+
+    /// Serialize the pattern to store alongside a reminder set at `time`.
+    /// Exhausted patterns are redundant since the time is already known.
+    pub(crate) fn to_stored(&self, time: NaiveDateTime) -> Option<String> {
+        if self.is_exhausted(time) {
+            None
+        } else {
+            serde_json::to_string(self).ok()
+        }
+    }
+
+    /// Pattern written with the weekdays named and ordered for the user
+    pub(crate) fn to_styled_string(&self, style: DateStyle) -> String {
+        struct Styled<'a>(&'a Pattern, DateStyle);
+
+        impl std::fmt::Display for Styled<'_> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                match self.0 {
+                    Pattern::Recurrence(recurrence) => {
+                        recurrence.fmt_styled(f, self.1)
+                    }
+                    Pattern::Countdown(countdown) => write!(f, "{}", countdown),
+                }
+            }
+        }
+
+        Styled(self, style).to_string()
+    }
 }
 
 impl std::fmt::Display for Pattern {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::Recurrence(recurrence) => write!(f, "{}", recurrence),
-            Self::Countdown(countdown) => write!(f, "{}", countdown),
-        }
+        write!(f, "{}", self.to_styled_string(DateStyle::default()))
     }
 }
 
 impl std::fmt::Display for Recurrence {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_styled(f, DateStyle::default())
+    }
+}
+
+impl Recurrence {
+    fn fmt_styled(
+        &self,
+        f: &mut Formatter<'_>,
+        style: DateStyle,
+    ) -> std::fmt::Result {
         let now = self.timezone.0.from_utc_datetime(&now_time());
         if self.time_patterns.len() == 1
             && self.dates_patterns.len() == 1
@@ -644,7 +974,7 @@ impl std::fmt::Display for Recurrence {
             if i != 0 {
                 write!(f, ",")?;
             }
-            nonempty |= dates_pattern.relfmt(f, &now)?;
+            nonempty |= dates_pattern.relfmt(f, &now, style)?;
         }
         if nonempty {
             write!(f, " ")?;
@@ -655,6 +985,10 @@ impl std::fmt::Display for Recurrence {
             }
             write!(f, "{}", time_pattern)?;
         }
+        if let Some(remaining) = self.remaining {
+            // including the scheduled firing
+            write!(f, " x{}", remaining + 1)?;
+        }
         Ok(())
     }
 }
@@ -687,10 +1021,11 @@ impl DateDisplay for DatePattern {
         &self,
         f: &mut Formatter<'_>,
         now: &D,
+        style: DateStyle,
     ) -> Result<bool, std::fmt::Error> {
         match self {
-            Self::Point(date) => date.relfmt(f, now),
-            Self::Range(range) => range.relfmt(f, now),
+            Self::Point(date) => date.relfmt(f, now, style),
+            Self::Range(range) => range.relfmt(f, now, style),
         }
     }
 }
@@ -715,6 +1050,7 @@ impl DateDisplay for DateRange {
         &self,
         f: &mut Formatter<'_>,
         now: &D,
+        style: DateStyle,
     ) -> Result<bool, std::fmt::Error> {
         if self.from.year() > now.year()
             || self.from.year() == now.year()
@@ -722,12 +1058,12 @@ impl DateDisplay for DateRange {
                     || self.from.month() == now.month()
                         && self.from.day() >= now.day())
         {
-            self.from.relfmt(f, now)?;
+            self.from.relfmt(f, now, style)?;
         }
         write!(f, "—")?;
         if let Some(until) = self.until {
             if self.from != until {
-                until.relfmt(f, now)?;
+                until.relfmt(f, now, style)?;
             }
         }
         if !matches!(
@@ -739,7 +1075,8 @@ impl DateDisplay for DateRange {
                 days: 1,
             })
         ) {
-            write!(f, "/{}", self.date_divisor)?;
+            write!(f, "/")?;
+            self.date_divisor.fmt_styled(f, style)?;
         }
         Ok(true)
     }
@@ -750,6 +1087,7 @@ impl DateDisplay for NaiveDate {
         &self,
         f: &mut Formatter<'_>,
         now: &D,
+        _style: DateStyle,
     ) -> Result<bool, std::fmt::Error> {
         let same_year = self.year() == now.year();
         let same_month = same_year && self.month() == now.month();
@@ -769,37 +1107,51 @@ impl DateDisplay for NaiveDate {
     }
 }
 
-impl std::fmt::Display for DateDivisor {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl DateDivisor {
+    fn fmt_styled(
+        &self,
+        f: &mut Formatter<'_>,
+        style: DateStyle,
+    ) -> std::fmt::Result {
         match *self {
-            DateDivisor::Weekdays(weekdays) => weekdays.fmt(f),
-            DateDivisor::Interval(interval) => interval.fmt(f),
+            DateDivisor::Weekdays(weekdays) => weekdays.fmt_styled(f, style),
+            DateDivisor::Interval(interval) => write!(f, "{}", interval),
         }
     }
 }
 
-impl std::fmt::Display for Weekdays {
+impl std::fmt::Display for DateDivisor {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let weekdays = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
-            .iter()
-            .enumerate()
-            .filter_map(|(i, w)| {
-                if self.bits() & (1 << i) != 0 {
-                    Some(w)
-                } else {
-                    None
-                }
-            });
+        self.fmt_styled(f, DateStyle::default())
+    }
+}
+
+impl Weekdays {
+    /// Write the weekdays in the order of the user's week
+    fn fmt_styled(
+        &self,
+        f: &mut Formatter<'_>,
+        style: DateStyle,
+    ) -> std::fmt::Result {
+        let weekdays = style.week().filter(|weekday| {
+            self.bits() & (1 << weekday.num_days_from_monday()) != 0
+        });
         for (i, weekday) in weekdays.enumerate() {
             if i != 0 {
                 write!(f, ",")?;
             }
-            write!(f, "{}", weekday)?;
+            write!(f, "{}", style.weekday(weekday))?;
         }
         Ok(())
     }
 }
 
+impl std::fmt::Display for Weekdays {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_styled(f, DateStyle::default())
+    }
+}
+
 impl std::fmt::Display for TimeInterval {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         if self.hours != 0 {
@@ -863,6 +1215,7 @@ impl std::fmt::Display for Interval {
 #[cfg(test)]
 mod test {
     use serial_test::serial;
+    use test_case::test_case;
 
     use super::*;
     use crate::{
@@ -929,6 +1282,193 @@ mod test {
         );
     }
 
+    #[test_case("in 10m, then every 5m x3 tea" => vec![tz(2007, 2, 2, 12, 40, 30), tz(2007, 2, 2, 12, 45, 30), tz(2007, 2, 2, 12, 50, 30), tz(2007, 2, 2, 12, 55, 30)] ; "repeated")]
+    #[test_case("30m then 10m then 5m tea" => vec![tz(2007, 2, 2, 13, 0, 30), tz(2007, 2, 2, 13, 10, 30), tz(2007, 2, 2, 13, 15, 30)] ; "escalating")]
+    #[test_case("1h then every 1h x1000 tea" => Vec::<NaiveDateTime>::new() ; "too many")]
+    #[serial]
+    fn test_countdown_chain(s: &str) -> Vec<NaiveDateTime> {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let Ok(parsed_rem) = parse_reminder(s) else {
+            return vec![];
+        };
+        assert_eq!(parsed_rem.description.map(|x| x.0), Some("tea".to_owned()));
+        let parsed = parsed_rem.pattern.unwrap();
+        let pattern = Pattern::from_with_tz(parsed, *TEST_TZ).unwrap();
+        get_all_times(pattern).collect()
+    }
+
+    #[test]
+    #[serial]
+    fn test_seconds_time_range() {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let parsed = parse_reminder("12:31-12:32/30s hydration check")
+            .unwrap()
+            .pattern
+            .unwrap();
+        let pattern =
+            Pattern::from_with_tz_unchecked(parsed, *TEST_TZ).unwrap();
+        assert!(pattern.too_frequent());
+        assert_eq!(
+            get_all_times(pattern).collect::<Vec<_>>(),
+            vec![
+                tz(2007, 2, 2, 12, 31, 0),
+                tz(2007, 2, 2, 12, 31, 30),
+                tz(2007, 2, 2, 12, 32, 0),
+            ]
+        );
+    }
+
+    #[test_case("-/30s tea" => true ; "sub-minute range")]
+    #[test_case("-/1m tea" => false ; "minute range")]
+    #[test_case("1m then every 20s x3 tea" => true ; "sub-minute countdown chain")]
+    #[test_case("30s tea" => false ; "single countdown")]
+    #[serial]
+    fn test_too_frequent(s: &str) -> bool {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let parsed = parse_reminder(s).unwrap().pattern.unwrap();
+        let too_frequent = Pattern::from_with_tz_unchecked(parsed, *TEST_TZ)
+            .unwrap()
+            .too_frequent();
+        let parsed = parse_reminder(s).unwrap().pattern.unwrap();
+        assert_eq!(
+            Pattern::from_with_tz(parsed, *TEST_TZ).is_err(),
+            too_frequent
+        );
+        too_frequent
+    }
+
+    #[test]
+    #[serial]
+    fn test_countdown_until() {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let s = "until 4.2 12:30:30 countdown project deadline";
+        let parsed_rem = parse_reminder(s).unwrap();
+        assert_eq!(
+            parsed_rem.description.map(|x| x.0),
+            Some("project deadline".to_owned())
+        );
+        let parsed = parsed_rem.pattern.unwrap();
+        let pattern = Pattern::from_with_tz(parsed, *TEST_TZ).unwrap();
+        assert_eq!(pattern.to_string(), "1d,1d19h12m,2d");
+        assert_eq!(
+            get_all_times(pattern).collect::<Vec<_>>(),
+            vec![
+                tz(2007, 2, 3, 12, 30, 30),
+                tz(2007, 2, 4, 7, 42, 30),
+                tz(2007, 2, 4, 12, 30, 30)
+            ]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_countdown_until_time() {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let parsed_rem = parse_reminder("till 12:00 lunch").unwrap();
+        assert_eq!(
+            parsed_rem.description.map(|x| x.0),
+            Some("lunch".to_owned())
+        );
+        let parsed = parsed_rem.pattern.unwrap();
+        let pattern = Pattern::from_with_tz(parsed, *TEST_TZ).unwrap();
+        assert_eq!(
+            get_all_times(pattern).collect::<Vec<_>>(),
+            vec![tz(2007, 2, 3, 12, 0, 0)]
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_next_after() {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let parsed = parse_reminder("1h,2h,3h countdown").unwrap();
+        let mut countdown =
+            Pattern::from_with_tz(parsed.pattern.unwrap(), *TEST_TZ).unwrap();
+        let parsed = parse_reminder("2.2-4.2/1d 10:00 range").unwrap();
+        let mut range =
+            Pattern::from_with_tz(parsed.pattern.unwrap(), *TEST_TZ).unwrap();
+        let now = TEST_TZ
+            .from_local_datetime(&tz(2007, 2, 3, 14, 0, 0))
+            .unwrap()
+            .naive_utc();
+        assert_eq!(
+            range
+                .next_after(now)
+                .map(|x| TEST_TZ.from_utc_datetime(&x).naive_local()),
+            Some(tz(2007, 2, 4, 10, 0, 0))
+        );
+        assert_eq!(range.next_after(now + Duration::days(1)), None);
+        let now = now_time() + Duration::minutes(90);
+        assert_eq!(
+            countdown
+                .next_after(now)
+                .map(|x| TEST_TZ.from_utc_datetime(&x).naive_local()),
+            Some(tz(2007, 2, 2, 14, 30, 30))
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_recurrence_across_dst() {
+        // Moscow moved the clocks forward at 2:00 on 25.03.2007
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TZ
+            .with_ymd_and_hms(2007, 3, 23, 12, 0, 0)
+            .unwrap()
+            .timestamp();
+        let times = |s: &str| {
+            let parsed = parse_reminder(s).unwrap();
+            let pattern =
+                Pattern::from_with_tz(parsed.pattern.unwrap(), *TEST_TZ)
+                    .unwrap();
+            get_all_times(pattern).take(3).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            times("/1d 9:00 daily"),
+            vec![
+                tz(2007, 3, 24, 9, 0, 0),
+                tz(2007, 3, 25, 9, 0, 0),
+                tz(2007, 3, 26, 9, 0, 0)
+            ]
+        );
+        assert_eq!(
+            times("/1d 2:30 skipped"),
+            vec![
+                tz(2007, 3, 24, 2, 30, 0),
+                tz(2007, 3, 25, 3, 30, 0),
+                tz(2007, 3, 26, 2, 30, 0)
+            ]
+        );
+    }
+
+    #[test_case("every mon 9:00 x3 standup" ; "short")]
+    #[test_case("every mon 9:00 for 3 times standup" ; "spelled out")]
+    #[serial]
+    fn test_recurrence_limit(s: &str) {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let parsed_rem = parse_reminder(s).unwrap();
+        assert_eq!(
+            parsed_rem.description.map(|x| x.0),
+            Some("standup".to_owned())
+        );
+        let mut pattern =
+            Pattern::from_with_tz(parsed_rem.pattern.unwrap(), *TEST_TZ)
+                .unwrap();
+        let first = pattern.next(now_time()).unwrap();
+        assert_eq!(pattern.to_string(), "—/Mon 09:00 x3");
+        let mut stored: Pattern =
+            serde_json::from_str(&pattern.to_stored(first).unwrap()).unwrap();
+        assert_eq!(
+            std::iter::successors(Some(first), |&cur| stored.next(cur))
+                .map(|x| TEST_TZ.from_utc_datetime(&x).naive_local())
+                .collect::<Vec<_>>(),
+            vec![
+                tz(2007, 2, 5, 9, 0, 0),
+                tz(2007, 2, 12, 9, 0, 0),
+                tz(2007, 2, 19, 9, 0, 0)
+            ]
+        );
+    }
+
     #[test]
     #[serial]
     fn test_periodic() {
@@ -1049,6 +1589,23 @@ mod test {
         );
     }
 
+    #[test]
+    #[serial]
+    fn test_weekdays_styled() {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let parsed = parse_reminder("/sun,fri,mon 11:00 weekdays")
+            .unwrap()
+            .pattern
+            .unwrap();
+        let pattern = Pattern::from_with_tz(parsed, *TEST_TZ).unwrap();
+        assert_eq!(pattern.to_string(), "—/Mon,Fri,Sun 11:00");
+        let style = DateStyle {
+            week_start: Weekday::Sun,
+            ..Default::default()
+        };
+        assert_eq!(pattern.to_styled_string(style), "—/Sun,Mon,Fri 11:00");
+    }
+
     #[test]
     #[serial]
     fn test_weekdays() {
@@ -1152,6 +1709,64 @@ mod test {
         );
     }
 
+    #[test_case("каждый пн в 10:00 d" => Some(tz(2007, 2, 5, 10, 0, 0)) ; "russian every")]
+    #[test_case("по средам 18:30 d" => Some(tz(2007, 2, 7, 18, 30, 0)) ; "russian on")]
+    #[test_case("/Вс 12:00 d" => Some(tz(2007, 2, 4, 12, 0, 0)) ; "russian capitalized")]
+    #[test_case("через 1h d" => Some(tz(2007, 2, 2, 13, 30, 30)) ; "russian in")]
+    #[test_case("elke ma om 9:00 d" => Some(tz(2007, 2, 5, 9, 0, 0)) ; "dutch")]
+    #[test_case("/wo-vr 9:00 d" => Some(tz(2007, 2, 7, 9, 0, 0)) ; "dutch range")]
+    #[test_case("chaque sam à 9:00 d" => Some(tz(2007, 2, 3, 9, 0, 0)) ; "french")]
+    #[test_case("/mar,jeu 8:00 d" => Some(tz(2007, 2, 6, 8, 0, 0)) ; "french list")]
+    #[test_case("/mars 8:00 d" => None ; "not weekday")]
+    #[serial]
+    fn test_localized_keywords(s: &str) -> Option<NaiveDateTime> {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let parsed = parse_reminder(s).ok()?;
+        assert_eq!(parsed.description.map(|x| x.0), Some("d".to_owned()));
+        let pattern =
+            Pattern::from_with_tz(parsed.pattern.unwrap(), *TEST_TZ).unwrap();
+        get_all_times(pattern).next()
+    }
+
+    #[test_case("weekend 10:00 d", "/sat-sun 10:00 d" ; "weekend")]
+    #[test_case("on weekends at 9:00 d", "/sat,sun 9:00 d" ; "on weekends")]
+    #[test_case("every weekday 8:00 d", "/mon-fri 8:00 d" ; "every weekday")]
+    #[test_case("daily 13:00 d", "/1d 13:00 d" ; "daily")]
+    #[test_case("1.03-1.04 weekly 10:00 d", "1.03-1.04/1w 10:00 d" ; "weekly in range")]
+    #[test_case("monthly 9:00 d", "/1m 9:00 d" ; "monthly")]
+    #[test_case("yearly 9:00 d", "/1y 9:00 d" ; "yearly")]
+    #[test_case("по выходным в 11:00 d", "/sat-sun 11:00 d" ; "russian weekend")]
+    #[test_case("Будни 7:30 d", "/mon-fri 7:30 d" ; "russian weekdays")]
+    #[test_case("ежемесячно 9:00 d", "/1m 9:00 d" ; "russian monthly")]
+    #[test_case("werkdagen om 9:00 d", "/ma-vr 9:00 d" ; "dutch weekdays")]
+    #[test_case("le week-end à 10:00 d", "/sam-dim 10:00 d" ; "french weekend")]
+    #[test_case("hebdomadaire 10:00 d", "/1w 10:00 d" ; "french weekly")]
+    #[serial]
+    fn test_divisor_keywords(s: &str, explicit: &str) {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let times = |s: &str| {
+            let parsed = parse_reminder(s).unwrap();
+            assert_eq!(parsed.description.map(|x| x.0), Some("d".to_owned()));
+            let pattern =
+                Pattern::from_with_tz(parsed.pattern.unwrap(), *TEST_TZ)
+                    .unwrap();
+            get_all_times(pattern).take(5).collect::<Vec<_>>()
+        };
+        assert_eq!(times(s), times(explicit));
+    }
+
+    #[test_case("10:00 daily standup" ; "after time")]
+    #[test_case("10:00 weekends" ; "whole description")]
+    #[serial]
+    fn test_divisor_keywords_in_description(s: &str) {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let parsed = parse_reminder(s).unwrap();
+        assert_eq!(
+            parsed.description.map(|x| x.0).as_deref(),
+            s.strip_prefix("10:00 ")
+        );
+    }
+
     #[test]
     #[serial]
     fn test_date_range_over_year() {
@@ -1175,6 +1790,25 @@ mod test {
         );
     }
 
+    #[test_case("/fri,mon 11:00 tea", "1w" => vec![tz(2007, 2, 12, 11, 0, 0), tz(2007, 2, 16, 11, 0, 0), tz(2007, 2, 19, 11, 0, 0)] ; "weeks")]
+    #[test_case("/fri,mon 11:00 tea", "1d" => vec![tz(2007, 2, 6, 11, 0, 0), tz(2007, 2, 10, 11, 0, 0), tz(2007, 2, 13, 11, 0, 0)] ; "weekdays")]
+    #[test_case("3-6/2d 13:37 tea", "1w" => vec![tz(2007, 2, 10, 13, 37, 0), tz(2007, 2, 12, 13, 37, 0)] ; "date range")]
+    #[test_case("10.02 11:00 tea", "1mo" => vec![tz(2007, 3, 10, 11, 0, 0)] ; "point")]
+    #[test_case("1h, 2h tea", "2d" => vec![tz(2007, 2, 4, 13, 30, 30), tz(2007, 2, 4, 14, 30, 30)] ; "countdown")]
+    #[serial]
+    fn test_shift(s: &str, shift: &str) -> Vec<NaiveDateTime> {
+        *TEST_TIMESTAMP.write().unwrap() = TEST_TIME.timestamp();
+        let parsed = parse_reminder(s).unwrap().pattern.unwrap();
+        let mut pattern = Pattern::from_with_tz(parsed, *TEST_TZ).unwrap();
+        let first = pattern.next(now_time()).unwrap();
+        let offset = grammar::parse_shift(shift).unwrap().into();
+        let first = pattern.shift(&offset, first);
+        std::iter::successors(first, |&cur| pattern.next(cur))
+            .take(3)
+            .map(|x| TEST_TZ.from_utc_datetime(&x).naive_local())
+            .collect()
+    }
+
     #[test]
     fn test_intervals_display() {
         let int1 = Interval {
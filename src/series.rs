@@ -0,0 +1,119 @@
+//! Asking what to delete of a recurring reminder chosen in the /delete
+//! menu: the whole series, only its next occurrence, or nothing. The
+//! question can be turned off in /settings.
+
+use crate::db::LinkTarget;
+use crate::lang::Language;
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+};
+
+const CALLBACK_PREFIX: &str = "series::";
+
+/// Pressed button of the question
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Callback {
+    DeleteSeries(LinkTarget),
+    /// Move the reminder past its next occurrence, keeping the series
+    SkipNext(LinkTarget),
+    Cancel,
+}
+
+fn target_code(target: LinkTarget) -> String {
+    match target {
+        LinkTarget::Reminder(id) => format!("rem::{}", id),
+        LinkTarget::CronReminder(id) => format!("cron_rem::{}", id),
+    }
+}
+
+fn parse_target(code: &str) -> Option<LinkTarget> {
+    let (kind, id) = code.split_once("::")?;
+    let id = id.parse().ok()?;
+    match kind {
+        "rem" => Some(LinkTarget::Reminder(id)),
+        "cron_rem" => Some(LinkTarget::CronReminder(id)),
+        _ => None,
+    }
+}
+
+/// Buttons answering what to delete of the reminder
+pub(crate) fn markup(
+    target: LinkTarget,
+    lang: Language,
+) -> InlineKeyboardMarkup {
+    let button = |key: &str, data: String| {
+        InlineKeyboardButton::new(
+            lang.text(key, &[]),
+            InlineKeyboardButtonKind::CallbackData(data),
+        )
+    };
+    let target = target_code(target);
+    InlineKeyboardMarkup::new(vec![
+        vec![button(
+            "button_delete_series",
+            format!("{}del::{}", CALLBACK_PREFIX, target),
+        )],
+        vec![button(
+            "button_skip_next",
+            format!("{}next::{}", CALLBACK_PREFIX, target),
+        )],
+        vec![button(
+            "button_cancel",
+            format!("{}cancel", CALLBACK_PREFIX),
+        )],
+    ])
+}
+
+pub(crate) fn parse_callback(cb_data: &str) -> Option<Callback> {
+    let data = cb_data.strip_prefix(CALLBACK_PREFIX)?;
+    if data == "cancel" {
+        return Some(Callback::Cancel);
+    }
+    let (choice, target) = data.split_once("::")?;
+    let target = parse_target(target)?;
+    match choice {
+        "del" => Some(Callback::DeleteSeries(target)),
+        "next" => Some(Callback::SkipNext(target)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("series::del::rem::1" => Some(Callback::DeleteSeries(LinkTarget::Reminder(1))) ; "delete series")]
+    #[test_case("series::next::cron_rem::2" => Some(Callback::SkipNext(LinkTarget::CronReminder(2))) ; "skip next")]
+    #[test_case("series::cancel" => Some(Callback::Cancel) ; "cancel")]
+    #[test_case("series::del::bill::1" => None ; "unknown target")]
+    #[test_case("series::pause::rem::1" => None ; "unknown choice")]
+    #[test_case("delrem::rem_alt::1" => None ; "other prefix")]
+    fn test_parse_callback(cb_data: &str) -> Option<Callback> {
+        parse_callback(cb_data)
+    }
+
+    #[test]
+    fn test_markup_round_trip() {
+        let target = LinkTarget::CronReminder(7);
+        let callbacks: Vec<_> = markup(target, Language::English)
+            .inline_keyboard
+            .into_iter()
+            .flatten()
+            .filter_map(|button| match button.kind {
+                InlineKeyboardButtonKind::CallbackData(data) => {
+                    parse_callback(&data)
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            callbacks,
+            vec![
+                Callback::DeleteSeries(target),
+                Callback::SkipNext(target),
+                Callback::Cancel
+            ]
+        );
+    }
+}
@@ -0,0 +1,841 @@
+//! Preferences of a user changed with /settings: the clock the times are
+//! shown in, notifications without a sound, the duration of the "Snooze"
+//! button under the notifications, the quiet hours set with /quiet, the
+//! numbered text menus replacing the buttons, pinning the notifications
+//! until they're marked done, deleting the bot's menus and confirmations
+//! after a while, catching up on the reminders missed during the bot's
+//! downtime, confirming the deletion of the recurring reminders, the first
+//! day of the week and the times of the parts of the day set with /daytimes
+
+use crate::advance::format_lead;
+use crate::entity::user_settings;
+use crate::lang::Language;
+use chrono::{
+    Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Weekday,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+};
+
+const CALLBACK_PREFIX: &str = "settings::";
+
+/// Snooze durations in seconds the button cycles through, no duration
+/// hides the "Snooze" button
+const SNOOZE_STEPS: [Option<i64>; 6] = [
+    None,
+    Some(5 * 60),
+    Some(15 * 60),
+    Some(30 * 60),
+    Some(60 * 60),
+    Some(3 * 60 * 60),
+];
+
+/// Times in seconds the button cycles through to delete the bot's menus
+/// and confirmations after, no time keeps them. Telegram lets bots
+/// delete messages only in the first two days.
+const CLEANUP_STEPS: [Option<i64>; 5] = [
+    None,
+    Some(60),
+    Some(10 * 60),
+    Some(60 * 60),
+    Some(24 * 60 * 60),
+];
+
+/// What the button cycles through to catch up on the reminders missed
+/// during the bot's downtime: no time delivers them late with a note,
+/// a time in seconds skips the ones late by more and zero sums them up
+const CATCH_UP_STEPS: [Option<i64>; 4] =
+    [None, Some(60 * 60), Some(24 * 60 * 60), Some(0)];
+
+lazy_static! {
+    /// Users with the 12-hour clock, kept in memory since the times
+    /// are formatted without access to the database
+    static ref CLOCK_12H_USERS: RwLock<HashSet<i64>> = RwLock::default();
+    /// Times of the parts of the day of the users who changed them, kept
+    /// in memory since the reminders are parsed without access to the
+    /// database
+    static ref DAY_PART_TIMES: RwLock<HashMap<i64, [i32; 4]>> =
+        RwLock::default();
+    /// Users whose weeks start on Sunday and the languages of the users,
+    /// kept in memory since the dates are formatted without access to
+    /// the database
+    static ref SUNDAY_FIRST_USERS: RwLock<HashSet<i64>> = RwLock::default();
+    static ref USER_LANGUAGES: RwLock<HashMap<i64, Language>> =
+        RwLock::default();
+}
+
+/// Settings of a user who hasn't changed any
+pub(crate) fn defaults(user_id: i64) -> user_settings::Model {
+    user_settings::Model {
+        user_id,
+        clock_12h: false,
+        silent: false,
+        snooze: None,
+        quiet_from: None,
+        quiet_until: None,
+        quiet_silent: false,
+        text_menus: false,
+        morning: None,
+        afternoon: None,
+        evening: None,
+        night: None,
+        pin_until_done: false,
+        cleanup_after: None,
+        catch_up: None,
+        confirm_delete: true,
+        sunday_first: false,
+    }
+}
+
+/// How to deliver a reminder found overdue after the bot's downtime
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CatchUp {
+    /// Deliver with a note of the time it was due at
+    Late,
+    /// Leave out as it's too late to be of use
+    Skip,
+    /// Sum up in one message along with the other overdue reminders
+    Summary,
+}
+
+/// How the creator of a reminder overdue by `late` wants it delivered
+pub(crate) fn catch_up(
+    settings: &user_settings::Model,
+    late: TimeDelta,
+) -> CatchUp {
+    match settings.catch_up {
+        None => CatchUp::Late,
+        Some(0) => CatchUp::Summary,
+        Some(max_late) if late > TimeDelta::seconds(max_late) => CatchUp::Skip,
+        Some(_) => CatchUp::Late,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Setting {
+    Clock,
+    Silent,
+    Snooze,
+    /// Holding or silencing the notifications during the quiet hours
+    Quiet,
+    TextMenus,
+    PinUntilDone,
+    Cleanup,
+    CatchUp,
+    ConfirmDelete,
+    WeekStart,
+    /// Language of the responses, kept apart from the other settings
+    Language,
+}
+
+impl Setting {
+    pub(crate) const ALL: [Self; 11] = [
+        Self::Clock,
+        Self::Silent,
+        Self::Snooze,
+        Self::Quiet,
+        Self::TextMenus,
+        Self::PinUntilDone,
+        Self::Cleanup,
+        Self::CatchUp,
+        Self::ConfirmDelete,
+        Self::WeekStart,
+        Self::Language,
+    ];
+
+    fn code(self) -> &'static str {
+        match self {
+            Self::Clock => "clock",
+            Self::Silent => "silent",
+            Self::Snooze => "snooze",
+            Self::Quiet => "quiet",
+            Self::TextMenus => "textmenus",
+            Self::PinUntilDone => "pinuntildone",
+            Self::Cleanup => "cleanup",
+            Self::CatchUp => "catchup",
+            Self::ConfirmDelete => "confirmdelete",
+            Self::WeekStart => "weekstart",
+            Self::Language => "language",
+        }
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|setting| setting.code() == code)
+    }
+
+    /// Label of the button showing the current value in the user's
+    /// language
+    pub(crate) fn label(self, settings: &user_settings::Model) -> String {
+        let lang = language(settings.user_id);
+        let on_off =
+            |on| lang.text(if on { "setting_on" } else { "setting_off" }, &[]);
+        let lead_or_off = |lead: Option<i64>| {
+            lead.map(format_lead).unwrap_or_else(|| on_off(false))
+        };
+        match self {
+            Self::Clock => lang.text(
+                "setting_clock",
+                &[("clock", &if settings.clock_12h { "12h" } else { "24h" })],
+            ),
+            Self::Silent => lang
+                .text("setting_silent", &[("value", &on_off(settings.silent))]),
+            Self::Snooze => lang.text(
+                "setting_snooze",
+                &[("value", &lead_or_off(settings.snooze))],
+            ),
+            Self::Quiet => match quiet_hours(settings) {
+                Some(window) => lang.text(
+                    "setting_quiet",
+                    &[
+                        ("window", &window),
+                        (
+                            "mode",
+                            &lang.text(
+                                if settings.quiet_silent {
+                                    "setting_quiet_silent"
+                                } else {
+                                    "setting_quiet_hold"
+                                },
+                                &[],
+                            ),
+                        ),
+                    ],
+                ),
+                None => lang.text("setting_quiet_unset", &[]),
+            },
+            Self::TextMenus => lang.text(
+                "setting_text_menus",
+                &[("value", &on_off(settings.text_menus))],
+            ),
+            Self::PinUntilDone => lang.text(
+                "setting_pin_until_done",
+                &[("value", &on_off(settings.pin_until_done))],
+            ),
+            Self::Cleanup => lang.text(
+                "setting_cleanup",
+                &[("value", &lead_or_off(settings.cleanup_after))],
+            ),
+            Self::CatchUp => lang.text(
+                "setting_catch_up",
+                &[(
+                    "value",
+                    &match settings.catch_up {
+                        None => lang.text("setting_catch_up_late", &[]),
+                        Some(0) => lang.text("setting_catch_up_summed_up", &[]),
+                        Some(max_late) => lang.text(
+                            "setting_catch_up_skipped",
+                            &[("lead", &format_lead(max_late))],
+                        ),
+                    },
+                )],
+            ),
+            Self::ConfirmDelete => lang.text(
+                "setting_confirm_delete",
+                &[("value", &on_off(settings.confirm_delete))],
+            ),
+            Self::WeekStart => lang.text(
+                "setting_week_start",
+                &[(
+                    "day",
+                    &lang.weekday_name(if settings.sunday_first {
+                        Weekday::Sun
+                    } else {
+                        Weekday::Mon
+                    }),
+                )],
+            ),
+            Self::Language => {
+                lang.text("setting_language", &[("language", &lang.name())])
+            }
+        }
+    }
+
+    /// Settings with the value of this one switched to the next, the
+    /// language is switched by the caller as it's stored separately
+    pub(crate) fn switch(
+        self,
+        settings: user_settings::Model,
+    ) -> user_settings::Model {
+        match self {
+            Self::Clock => user_settings::Model {
+                clock_12h: !settings.clock_12h,
+                ..settings
+            },
+            Self::Silent => user_settings::Model {
+                silent: !settings.silent,
+                ..settings
+            },
+            Self::Snooze => {
+                let next = SNOOZE_STEPS
+                    .iter()
+                    .position(|&step| step == settings.snooze)
+                    .map_or(0, |idx| (idx + 1) % SNOOZE_STEPS.len());
+                user_settings::Model {
+                    snooze: SNOOZE_STEPS[next],
+                    ..settings
+                }
+            }
+            Self::Quiet => user_settings::Model {
+                quiet_silent: !settings.quiet_silent,
+                ..settings
+            },
+            Self::TextMenus => user_settings::Model {
+                text_menus: !settings.text_menus,
+                ..settings
+            },
+            Self::PinUntilDone => user_settings::Model {
+                pin_until_done: !settings.pin_until_done,
+                ..settings
+            },
+            Self::Cleanup => {
+                let next = CLEANUP_STEPS
+                    .iter()
+                    .position(|&step| step == settings.cleanup_after)
+                    .map_or(0, |idx| (idx + 1) % CLEANUP_STEPS.len());
+                user_settings::Model {
+                    cleanup_after: CLEANUP_STEPS[next],
+                    ..settings
+                }
+            }
+            Self::CatchUp => {
+                let next = CATCH_UP_STEPS
+                    .iter()
+                    .position(|&step| step == settings.catch_up)
+                    .map_or(0, |idx| (idx + 1) % CATCH_UP_STEPS.len());
+                user_settings::Model {
+                    catch_up: CATCH_UP_STEPS[next],
+                    ..settings
+                }
+            }
+            Self::ConfirmDelete => user_settings::Model {
+                confirm_delete: !settings.confirm_delete,
+                ..settings
+            },
+            Self::WeekStart => user_settings::Model {
+                sunday_first: !settings.sunday_first,
+                ..settings
+            },
+            Self::Language => settings,
+        }
+    }
+}
+
+/// Part of the day written instead of a time, e.g. `tomorrow evening`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum DayPart {
+    Morning,
+    Afternoon,
+    Evening,
+    Night,
+}
+
+impl DayPart {
+    pub(crate) const ALL: [Self; 4] =
+        [Self::Morning, Self::Afternoon, Self::Evening, Self::Night];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            Self::Morning => "morning",
+            Self::Afternoon => "afternoon",
+            Self::Evening => "evening",
+            Self::Night => "night",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|part| part.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Minutes since midnight of a user who hasn't changed the time
+    fn default_minutes(self) -> i32 {
+        match self {
+            Self::Morning => 9 * 60,
+            Self::Afternoon => 14 * 60,
+            Self::Evening => 19 * 60,
+            Self::Night => 22 * 60,
+        }
+    }
+
+    fn custom_minutes(self, settings: &user_settings::Model) -> Option<i32> {
+        match self {
+            Self::Morning => settings.morning,
+            Self::Afternoon => settings.afternoon,
+            Self::Evening => settings.evening,
+            Self::Night => settings.night,
+        }
+    }
+
+    /// Minutes since midnight the part of the day stands for
+    pub(crate) fn minutes(self, settings: &user_settings::Model) -> i32 {
+        self.custom_minutes(settings)
+            .unwrap_or_else(|| self.default_minutes())
+    }
+
+    /// Settings with the time of this part of the day changed,
+    /// or reset to the default one if there's no time
+    pub(crate) fn set(
+        self,
+        settings: user_settings::Model,
+        minutes: Option<i32>,
+    ) -> user_settings::Model {
+        match self {
+            Self::Morning => user_settings::Model {
+                morning: minutes,
+                ..settings
+            },
+            Self::Afternoon => user_settings::Model {
+                afternoon: minutes,
+                ..settings
+            },
+            Self::Evening => user_settings::Model {
+                evening: minutes,
+                ..settings
+            },
+            Self::Night => user_settings::Model {
+                night: minutes,
+                ..settings
+            },
+        }
+    }
+}
+
+/// Setting whose button was pressed
+pub(crate) fn parse_callback(cb_data: &str) -> Option<Setting> {
+    Setting::from_code(cb_data.strip_prefix(CALLBACK_PREFIX)?)
+}
+
+/// Buttons switching the settings, one per row
+pub(crate) fn markup(settings: &user_settings::Model) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(Setting::ALL.into_iter().map(|setting| {
+        vec![InlineKeyboardButton::new(
+            setting.label(settings),
+            InlineKeyboardButtonKind::CallbackData(format!(
+                "{}{}",
+                CALLBACK_PREFIX,
+                setting.code()
+            )),
+        )]
+    }))
+}
+
+/// Remember the clock of a user for formatting the times
+pub(crate) fn set_clock(user_id: i64, clock_12h: bool) {
+    let mut users = CLOCK_12H_USERS.write().unwrap();
+    if clock_12h {
+        users.insert(user_id);
+    } else {
+        users.remove(&user_id);
+    }
+}
+
+pub(crate) fn uses_12h_clock(user_id: i64) -> bool {
+    CLOCK_12H_USERS.read().unwrap().contains(&user_id)
+}
+
+/// How the dates are written for a user: the names of the weekdays
+/// and the day the weeks start on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct DateStyle {
+    pub(crate) language: Language,
+    pub(crate) week_start: Weekday,
+}
+
+impl Default for DateStyle {
+    fn default() -> Self {
+        Self {
+            language: Language::default(),
+            week_start: Weekday::Mon,
+        }
+    }
+}
+
+impl DateStyle {
+    pub(crate) fn weekday(self, weekday: Weekday) -> &'static str {
+        self.language.weekday_abbr(weekday)
+    }
+
+    /// Days of the week in the order the user's weeks go
+    pub(crate) fn week(self) -> impl Iterator<Item = Weekday> {
+        std::iter::successors(Some(self.week_start), |day| Some(day.succ()))
+            .take(7)
+    }
+
+    /// Date with its weekday, e.g. `Mon 01.01`, and the year if asked
+    pub(crate) fn format_day(self, date: NaiveDate, with_year: bool) -> String {
+        let day = if with_year {
+            date.format("%d.%m.%Y")
+        } else {
+            date.format("%d.%m")
+        };
+        format!("{} {}", self.weekday(date.weekday()), day)
+    }
+}
+
+/// Remember the first day of the week of a user for formatting the dates
+pub(crate) fn set_week_start(user_id: i64, sunday_first: bool) {
+    let mut users = SUNDAY_FIRST_USERS.write().unwrap();
+    if sunday_first {
+        users.insert(user_id);
+    } else {
+        users.remove(&user_id);
+    }
+}
+
+/// Remember the language of a user for formatting the dates
+pub(crate) fn set_language(user_id: i64, language: Language) {
+    USER_LANGUAGES.write().unwrap().insert(user_id, language);
+}
+
+pub(crate) fn language(user_id: i64) -> Language {
+    USER_LANGUAGES
+        .read()
+        .unwrap()
+        .get(&user_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+pub(crate) fn date_style(user_id: i64) -> DateStyle {
+    DateStyle {
+        language: language(user_id),
+        week_start: if SUNDAY_FIRST_USERS.read().unwrap().contains(&user_id) {
+            Weekday::Sun
+        } else {
+            Weekday::Mon
+        },
+    }
+}
+
+/// Remember the times of the parts of the day of a user
+/// for parsing the reminders
+pub(crate) fn set_day_parts(settings: &user_settings::Model) {
+    let mut users = DAY_PART_TIMES.write().unwrap();
+    if DayPart::ALL
+        .iter()
+        .any(|part| part.custom_minutes(settings).is_some())
+    {
+        users.insert(
+            settings.user_id,
+            DayPart::ALL.map(|part| part.minutes(settings)),
+        );
+    } else {
+        users.remove(&settings.user_id);
+    }
+}
+
+/// Time the part of the day stands for in the reminders of the user
+pub(crate) fn day_part_time(user_id: i64, part: DayPart) -> NaiveTime {
+    let minutes = DAY_PART_TIMES
+        .read()
+        .unwrap()
+        .get(&user_id)
+        .map_or_else(|| part.default_minutes(), |times| times[part as usize]);
+    NaiveTime::from_hms_opt(minutes as u32 / 60, minutes as u32 % 60, 0)
+        .unwrap()
+}
+
+/// Time of the day, e.g. `21:05` or `9:05 PM`
+pub(crate) fn format_clock(hour: u32, minute: u32, clock_12h: bool) -> String {
+    if !clock_12h {
+        return format!("{:02}:{:02}", hour, minute);
+    }
+    let suffix = if hour < 12 { "AM" } else { "PM" };
+    let hour = match hour % 12 {
+        0 => 12,
+        hour => hour,
+    };
+    format!("{}:{:02} {}", hour, minute, suffix)
+}
+
+/// Time of the day written as `8:30` or `8` in minutes since midnight
+pub(crate) fn parse_minutes(time: &str) -> Option<i32> {
+    let time = time.trim();
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .or_else(|_| {
+            NaiveTime::parse_from_str(&format!("{}:00", time), "%H:%M")
+        })
+        .ok()
+        .map(|time| (time.hour() * 60 + time.minute()) as i32)
+}
+
+/// Start and end of the quiet hours, e.g. `23:00-8:00`, in minutes
+/// since midnight. `off` turns them off.
+pub(crate) fn parse_quiet_hours(args: &str) -> Option<Option<(i32, i32)>> {
+    let args = args.trim();
+    if args.eq_ignore_ascii_case("off") {
+        return Some(None);
+    }
+    let (from, until) = args.split_once(['-', '—'])?;
+    let (from, until) = (parse_minutes(from)?, parse_minutes(until)?);
+    (from != until).then_some(Some((from, until)))
+}
+
+/// Part of the day and its new time, e.g. `evening 20:00`. `default`
+/// resets the time.
+pub(crate) fn parse_day_part(args: &str) -> Option<(DayPart, Option<i32>)> {
+    let (name, time) = args.trim().split_once(char::is_whitespace)?;
+    let part = DayPart::from_name(name)?;
+    let time = time.trim();
+    if time.eq_ignore_ascii_case("default") {
+        return Some((part, None));
+    }
+    Some((part, Some(parse_minutes(time)?)))
+}
+
+/// Times of the parts of the day shown in the user's clock,
+/// e.g. `morning 09:00, afternoon 14:00, evening 19:00, night 22:00`
+pub(crate) fn day_parts(settings: &user_settings::Model) -> String {
+    DayPart::ALL
+        .map(|part| {
+            let minutes = part.minutes(settings) as u32;
+            format!(
+                "{} {}",
+                part.name(),
+                format_clock(minutes / 60, minutes % 60, settings.clock_12h)
+            )
+        })
+        .join(", ")
+}
+
+/// Quiet hours of the user shown in their clock, e.g. `23:00–08:00`
+pub(crate) fn quiet_hours(settings: &user_settings::Model) -> Option<String> {
+    let (from, until) = settings.quiet_from.zip(settings.quiet_until)?;
+    let format = |minutes: i32| {
+        format_clock(
+            minutes as u32 / 60,
+            minutes as u32 % 60,
+            settings.clock_12h,
+        )
+    };
+    Some(format!("{}–{}", format(from), format(until)))
+}
+
+/// End of the quiet hours the local time falls into, if it does.
+/// The hours may span midnight, e.g. from 23:00 to 8:00.
+pub(crate) fn quiet_end(
+    settings: &user_settings::Model,
+    local: NaiveDateTime,
+) -> Option<NaiveDateTime> {
+    let (from, until) = settings.quiet_from.zip(settings.quiet_until)?;
+    let now = (local.hour() * 60 + local.minute()) as i32;
+    let midnight = local.date().and_hms_opt(0, 0, 0).unwrap();
+    let end = |days| {
+        midnight + TimeDelta::days(days) + TimeDelta::minutes(until as i64)
+    };
+    if from < until {
+        (from..until).contains(&now).then(|| end(0))
+    } else if now >= from {
+        Some(end(1))
+    } else {
+        (now < until).then(|| end(0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(21, 5, false => "21:05" ; "24h")]
+    #[test_case(21, 5, true => "9:05 PM" ; "afternoon")]
+    #[test_case(0, 30, true => "12:30 AM" ; "midnight")]
+    #[test_case(12, 0, true => "12:00 PM" ; "noon")]
+    #[test_case(7, 45, true => "7:45 AM" ; "morning")]
+    fn test_format_clock(hour: u32, minute: u32, clock_12h: bool) -> String {
+        format_clock(hour, minute, clock_12h)
+    }
+
+    #[test]
+    fn test_switch_snooze() {
+        let mut settings = defaults(1);
+        let mut seen = vec![];
+        for _ in 0..SNOOZE_STEPS.len() {
+            settings = Setting::Snooze.switch(settings);
+            seen.push(settings.snooze);
+        }
+        assert_eq!(seen.last(), Some(&None));
+        assert_eq!(seen[..2], [Some(300), Some(900)]);
+    }
+
+    #[test]
+    fn test_switch_cleanup() {
+        let settings = Setting::Cleanup.switch(defaults(1));
+        assert_eq!(settings.cleanup_after, Some(60));
+        assert_eq!(
+            Setting::Cleanup.label(&settings),
+            "🧹 Delete menus after: 1m"
+        );
+        let settings = (1..CLEANUP_STEPS.len())
+            .fold(settings, |settings, _| Setting::Cleanup.switch(settings));
+        assert_eq!(settings.cleanup_after, None);
+    }
+
+    #[test]
+    fn test_switch_catch_up() {
+        let mut settings = defaults(1);
+        let mut labels = vec![];
+        for _ in 0..CATCH_UP_STEPS.len() {
+            settings = Setting::CatchUp.switch(settings);
+            labels.push(Setting::CatchUp.label(&settings));
+        }
+        assert_eq!(
+            labels,
+            [
+                "⏰ Missed reminders: skipped after 1h",
+                "⏰ Missed reminders: skipped after 1d",
+                "⏰ Missed reminders: summed up",
+                "⏰ Missed reminders: late"
+            ]
+        );
+    }
+
+    #[test_case(None, 90 => CatchUp::Late ; "late by default")]
+    #[test_case(Some(3600), 30 => CatchUp::Late ; "recent")]
+    #[test_case(Some(3600), 90 => CatchUp::Skip ; "too old")]
+    #[test_case(Some(0), 90 => CatchUp::Summary ; "summary")]
+    fn test_catch_up(max_late: Option<i64>, late_minutes: i64) -> CatchUp {
+        let settings = user_settings::Model {
+            catch_up: max_late,
+            ..defaults(1)
+        };
+        catch_up(&settings, TimeDelta::minutes(late_minutes))
+    }
+
+    #[test]
+    fn test_markup() {
+        let settings = user_settings::Model {
+            clock_12h: true,
+            snooze: Some(900),
+            ..defaults(1)
+        };
+        let labels: Vec<_> = markup(&settings)
+            .inline_keyboard
+            .into_iter()
+            .flatten()
+            .map(|button| button.text)
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "🕐 Clock: 12h",
+                "🔕 Silent notifications: off",
+                "💤 Snooze button: 15m",
+                "🌙 Quiet hours: set with /quiet",
+                "🔢 Numbered text menus: off",
+                "📌 Pin until done: off",
+                "🧹 Delete menus after: off",
+                "⏰ Missed reminders: late",
+                "❓ Confirm deleting a series: on",
+                "📅 Week starts on: Monday",
+                "🌐 Language: 🇬🇧 English"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markup_in_user_language() {
+        let settings = user_settings::Model {
+            sunday_first: true,
+            ..defaults(7703)
+        };
+        set_language(settings.user_id, Language::German);
+        assert_eq!(
+            [Setting::Silent, Setting::WeekStart, Setting::Language]
+                .map(|setting| setting.label(&settings)),
+            [
+                "🔕 Lautlose Benachrichtigungen: aus",
+                "📅 Woche beginnt am: Sonntag",
+                "🌐 Sprache: 🇩🇪 Deutsch",
+            ]
+        );
+    }
+
+    #[test_case("settings::silent" => Some(Setting::Silent) ; "silent")]
+    #[test_case("settings::volume" => None ; "unknown")]
+    #[test_case("manage::show::rem::1" => None ; "other prefix")]
+    fn test_parse_callback(cb_data: &str) -> Option<Setting> {
+        parse_callback(cb_data)
+    }
+
+    #[test_case("23:00-08:00" => Some(Some((1380, 480))) ; "overnight")]
+    #[test_case("13:30 - 14" => Some(Some((810, 840))) ; "hour only")]
+    #[test_case("Off" => Some(None) ; "off")]
+    #[test_case("23:00" => None ; "no end")]
+    #[test_case("8-8" => None ; "empty")]
+    #[test_case("25:00-8:00" => None ; "bad hour")]
+    fn test_parse_quiet_hours(args: &str) -> Option<Option<(i32, i32)>> {
+        parse_quiet_hours(args)
+    }
+
+    #[test_case(1380, 480, "2024-06-01 23:30" => Some("2024-06-02 08:00".to_owned()) ; "before midnight")]
+    #[test_case(1380, 480, "2024-06-02 07:59" => Some("2024-06-02 08:00".to_owned()) ; "after midnight")]
+    #[test_case(1380, 480, "2024-06-02 08:00" => None ; "ended")]
+    #[test_case(780, 840, "2024-06-02 13:15" => Some("2024-06-02 14:00".to_owned()) ; "daytime")]
+    #[test_case(780, 840, "2024-06-02 12:59" => None ; "not started")]
+    fn test_quiet_end(from: i32, until: i32, local: &str) -> Option<String> {
+        let settings = user_settings::Model {
+            quiet_from: Some(from),
+            quiet_until: Some(until),
+            ..defaults(1)
+        };
+        let local =
+            NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M").unwrap();
+        quiet_end(&settings, local)
+            .map(|end| end.format("%Y-%m-%d %H:%M").to_string())
+    }
+
+    #[test_case("evening 20:30" => Some((DayPart::Evening, Some(1230))) ; "set")]
+    #[test_case("Morning default" => Some((DayPart::Morning, None)) ; "default")]
+    #[test_case("dusk 20:00" => None ; "unknown part")]
+    #[test_case("night" => None ; "no time")]
+    fn test_parse_day_part(args: &str) -> Option<(DayPart, Option<i32>)> {
+        parse_day_part(args)
+    }
+
+    #[test]
+    fn test_day_part_time() {
+        // The id doesn't belong to the users of the other tests
+        let user_id = -43;
+        let time = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+        assert_eq!(day_part_time(user_id, DayPart::Evening), time(19));
+        let settings = DayPart::Evening.set(defaults(user_id), Some(20 * 60));
+        set_day_parts(&settings);
+        assert_eq!(day_part_time(user_id, DayPart::Evening), time(20));
+        assert_eq!(day_part_time(user_id, DayPart::Night), time(22));
+        set_day_parts(&DayPart::Evening.set(settings, None));
+        assert_eq!(day_part_time(user_id, DayPart::Evening), time(19));
+    }
+
+    #[test]
+    fn test_set_clock() {
+        // The id doesn't belong to the users of the other tests
+        let user_id = -42;
+        assert!(!uses_12h_clock(user_id));
+        set_clock(user_id, true);
+        assert!(uses_12h_clock(user_id));
+        set_clock(user_id, false);
+        assert!(!uses_12h_clock(user_id));
+    }
+
+    #[test]
+    fn test_set_week_start() {
+        // The id doesn't belong to the users of the other tests
+        let user_id = -44;
+        let week = |user_id| {
+            date_style(user_id)
+                .week()
+                .map(|day| date_style(user_id).weekday(day))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        assert_eq!(week(user_id), "Mon,Tue,Wed,Thu,Fri,Sat,Sun");
+        set_week_start(user_id, true);
+        assert_eq!(week(user_id), "Sun,Mon,Tue,Wed,Thu,Fri,Sat");
+        set_week_start(user_id, false);
+        assert_eq!(date_style(user_id), DateStyle::default());
+    }
+}
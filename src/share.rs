@@ -0,0 +1,224 @@
+//! Read-only links to a chat's reminder list for the people without
+//! Telegram, created with /share and served over HTTP until they expire
+
+use crate::api;
+#[cfg(not(test))]
+use crate::db::Database;
+#[cfg(test)]
+use crate::db::MockDatabase as Database;
+use crate::entity::share_link;
+use crate::feed;
+use crate::generic_reminder::GenericReminder;
+use actix_web::{
+    dev::Server, http::header, web, App, HttpRequest, HttpResponse, HttpServer,
+};
+use chrono::{TimeDelta, Utc};
+use chrono_tz::Tz;
+use rand::{distributions::Alphanumeric, Rng};
+use sea_orm::{ActiveValue::NotSet, Set};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+const TOKEN_LEN: usize = 32;
+const DEFAULT_DAYS: u32 = 7;
+const MAX_DAYS: u32 = 90;
+/// Number of requests allowed from an address within a window
+const RATE_LIMIT: u32 = 30;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Number of tracked addresses before forgetting the stale ones
+const MAX_TRACKED_ADDRS: usize = 1024;
+
+/// Parse `[days]` the link stays valid for, e.g. `30` or `30d`
+pub(crate) fn parse_days(args: &str) -> Option<u32> {
+    let args = args.trim();
+    let days = if args.is_empty() {
+        DEFAULT_DAYS
+    } else {
+        args.trim_end_matches(['d', 'D']).parse().ok()?
+    };
+    (1..=MAX_DAYS).contains(&days).then_some(days)
+}
+
+/// Random token hard to guess for the links to the reminders
+pub(crate) fn new_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_LEN)
+        .map(char::from)
+        .collect()
+}
+
+pub(crate) fn new_link(
+    chat_id: i64,
+    user_id: i64,
+    days: u32,
+) -> share_link::ActiveModel {
+    share_link::ActiveModel {
+        id: NotSet,
+        chat_id: Set(chat_id),
+        user_id: Set(user_id),
+        token: Set(new_token()),
+        expires_at: Set(Utc::now().naive_utc() + TimeDelta::days(days as i64)),
+    }
+}
+
+/// Address of the path on the HTTP server
+pub(crate) fn public_path_url(public_url: &Url, path: &str) -> Option<Url> {
+    let mut public_url = public_url.clone();
+    if !public_url.path().ends_with('/') {
+        public_url.set_path(&format!("{}/", public_url.path()));
+    }
+    public_url.join(path).ok()
+}
+
+/// Address of the page with the reminder list
+pub(crate) fn link_url(public_url: &Url, token: &str) -> Option<Url> {
+    public_path_url(public_url, &format!("share/{}", token))
+}
+
+/// Requests counted per address in fixed windows
+#[derive(Default)]
+pub(crate) struct RateLimiter {
+    windows: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    /// Count a request, return whether it's within the limit
+    pub(crate) fn check(&self, addr: IpAddr, now: Instant) -> bool {
+        let mut windows = self.windows.lock().unwrap();
+        if windows.len() >= MAX_TRACKED_ADDRS {
+            windows.retain(|_, (start, _)| now - *start < RATE_WINDOW);
+        }
+        let (start, count) = windows.entry(addr).or_insert((now, 0));
+        if now - *start >= RATE_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= RATE_LIMIT
+    }
+}
+
+/// Plain text list of the reminders
+pub(crate) fn render(
+    reminders: &[Box<dyn GenericReminder>],
+    user_tz: Tz,
+) -> String {
+    if reminders.is_empty() {
+        return "No reminders are set".to_owned();
+    }
+    std::iter::once(format!("Reminders ({}):", user_tz.name()))
+        .chain(reminders.iter().map(|rem| rem.to_unescaped_string(user_tz)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn share_page(
+    token: web::Path<String>,
+    req: HttpRequest,
+    db: web::Data<Database>,
+    limiter: web::Data<RateLimiter>,
+) -> HttpResponse {
+    let addr = req.peer_addr().map(|addr| addr.ip());
+    if addr.is_some_and(|addr| !limiter.check(addr, Instant::now())) {
+        return HttpResponse::TooManyRequests().finish();
+    }
+    let link = match db.get_share_link(&token).await {
+        Ok(Some(link)) => link,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(err) => {
+            tracing::error!("{}", err);
+            return HttpResponse::InternalServerError().finish();
+        }
+    };
+    let user_tz = db
+        .get_user_timezone_name(link.user_id)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|tz_name| tz_name.parse().ok())
+        .unwrap_or(Tz::UTC);
+    match db.get_sorted_reminders(link.chat_id).await {
+        Ok(reminders) => HttpResponse::Ok()
+            .content_type(header::ContentType::plaintext())
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .insert_header(("X-Robots-Tag", "noindex"))
+            .body(render(&reminders, user_tz)),
+        Err(err) => {
+            tracing::error!("{}", err);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Server of the shared reminder lists, the calendar feeds and the API,
+/// has to be awaited to run
+pub(crate) fn serve(
+    addr: SocketAddr,
+    db: Arc<Database>,
+) -> std::io::Result<Server> {
+    let db = web::Data::from(db);
+    let limiter = web::Data::new(RateLimiter::default());
+    Ok(HttpServer::new(move || {
+        App::new()
+            .app_data(db.clone())
+            .app_data(limiter.clone())
+            .route("/share/{token}", web::get().to(share_page))
+            .route("/calendar/{token}", web::get().to(feed::feed_page))
+            .configure(api::configure)
+    })
+    .bind(addr)?
+    .run())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("" => Some(DEFAULT_DAYS) ; "default")]
+    #[test_case("30" => Some(30) ; "days")]
+    #[test_case(" 14d " => Some(14) ; "with suffix")]
+    #[test_case("0" => None ; "zero")]
+    #[test_case("365" => None ; "too long")]
+    #[test_case("week" => None ; "garbage")]
+    fn test_parse_days(args: &str) -> Option<u32> {
+        parse_days(args)
+    }
+
+    #[test_case("https://remindee.example" ; "without slash")]
+    #[test_case("https://remindee.example/" ; "with slash")]
+    fn test_link_url(public_url: &str) {
+        assert_eq!(
+            link_url(&Url::parse(public_url).unwrap(), "abc")
+                .unwrap()
+                .as_str(),
+            "https://remindee.example/share/abc"
+        );
+    }
+
+    #[test]
+    fn test_new_link() {
+        let link = new_link(1, 2, 7);
+        let token = link.token.unwrap();
+        assert_eq!(token.len(), TOKEN_LEN);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::default();
+        let addr = IpAddr::from([127, 0, 0, 1]);
+        let other = IpAddr::from([127, 0, 0, 2]);
+        let now = Instant::now();
+        for _ in 0..RATE_LIMIT {
+            assert!(limiter.check(addr, now));
+        }
+        assert!(!limiter.check(addr, now));
+        assert!(limiter.check(other, now));
+        assert!(limiter.check(addr, now + RATE_WINDOW));
+    }
+}
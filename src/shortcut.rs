@@ -0,0 +1,37 @@
+//! Shortcuts of the links to the bot skipping onboarding steps, e.g.
+//! `https://t.me/<bot>?start=tz_Europe_Berlin` selects the timezone and
+//! `https://t.me/<bot>?start=sample_2` sets the second sample reminder
+
+use crate::tz;
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Shortcut {
+    Timezone(&'static str),
+    /// Index of the sample reminder, counted from 0
+    Sample(usize),
+}
+
+/// Shortcut the start payload stands for
+pub(crate) fn parse_payload(payload: &str) -> Option<Shortcut> {
+    if let Some(tz_name) = payload.strip_prefix("tz_") {
+        return tz::tz_name_from_underscored(tz_name).map(Shortcut::Timezone);
+    }
+    let num = payload.strip_prefix("sample_")?.parse::<usize>().ok()?;
+    num.checked_sub(1).map(Shortcut::Sample)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("tz_Europe_Berlin" => Some(Shortcut::Timezone("Europe/Berlin")) ; "timezone")]
+    #[test_case("tz_America_Argentina_Buenos_Aires" => Some(Shortcut::Timezone("America/Argentina/Buenos_Aires")) ; "underscore in name")]
+    #[test_case("tz_Mars_Olympus" => None ; "unknown timezone")]
+    #[test_case("sample_2" => Some(Shortcut::Sample(1)) ; "sample")]
+    #[test_case("sample_0" => None ; "sample counted from 1")]
+    #[test_case("manage-1_2" => None ; "other payload")]
+    fn test_parse_payload(payload: &str) -> Option<Shortcut> {
+        parse_payload(payload)
+    }
+}
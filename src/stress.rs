@@ -0,0 +1,205 @@
+//! Synthetic load for benchmarking the scheduler and the database before
+//! upgrades: `remindee-bot stress` fills a staging database with reminders
+//! of fake chats, `remindee-bot stress --teardown` deletes them
+
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+use crate::bot::init_database;
+use crate::cli::{StressArgs, CLI};
+#[cfg(not(test))]
+use crate::db::Database;
+#[cfg(test)]
+use crate::db::MockDatabase as Database;
+use crate::entity::{cron_reminder, reminder};
+use crate::err::Error;
+use crate::logging;
+use crate::search::Filter;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use cron_parser::parse as parse_cron;
+use rand::Rng;
+use sea_orm::ActiveValue::{NotSet, Set};
+
+/// Ids of the fake chats and their users, far below the ones of real
+/// chats, so that the teardown can't touch them
+const FAKE_CHAT_IDS: RangeInclusive<i64> =
+    -9_000_000_000_000_000_000..=-8_999_999_999_000_000_000;
+/// Every n-th synthetic reminder is a cron reminder
+const CRON_EVERY: u32 = 10;
+const CRON_EXPR: &str = "0 9 * * *";
+const TIMEZONES: [&str; 4] =
+    ["UTC", "Europe/Amsterdam", "America/New_York", "Asia/Tokyo"];
+/// Number of rows inserted with a single query
+const BATCH_SIZE: usize = 1000;
+
+fn fake_chat_id(idx: u32) -> i64 {
+    FAKE_CHAT_IDS.start() + idx as i64
+}
+
+/// Reminders spread evenly across the chats and randomly over the days
+fn generate(
+    args: &StressArgs,
+    now: NaiveDateTime,
+) -> (Vec<reminder::ActiveModel>, Vec<cron_reminder::ActiveModel>) {
+    let mut rng = rand::thread_rng();
+    let span = TimeDelta::days(args.days.max(1) as i64).num_seconds();
+    let mut reminders = vec![];
+    let mut cron_reminders = vec![];
+    for i in 0..args.reminders {
+        let chat_id = fake_chat_id(i % args.chats.max(1));
+        let time = now + TimeDelta::seconds(rng.gen_range(1..=span));
+        let desc = format!("synthetic reminder {}", i);
+        if i % CRON_EVERY == CRON_EVERY - 1 {
+            let time = parse_cron(CRON_EXPR, &Utc::now())
+                .map_or(time, |time| time.naive_utc());
+            cron_reminders.push(cron_reminder::ActiveModel {
+                id: NotSet,
+                chat_id: Set(chat_id),
+                user_id: Set(Some(chat_id)),
+                cron_expr: Set(CRON_EXPR.to_owned()),
+                time: Set(time),
+                desc: Set(desc),
+                paused: Set(false),
+                policy: Set(None),
+                version: Set(0),
+                media_kind: Set(None),
+                media_file_id: Set(None),
+                failures: Set(0),
+                retry_time: Set(None),
+                thread_id: Set(None),
+                deleted_time: Set(None),
+            });
+        } else {
+            reminders.push(reminder::ActiveModel {
+                id: NotSet,
+                chat_id: Set(chat_id),
+                user_id: Set(Some(chat_id)),
+                time: Set(time),
+                desc: Set(desc),
+                paused: Set(false),
+                pattern: Set(None),
+                policy: Set(None),
+                version: Set(0),
+                pomodoro_id: Set(None),
+                target: Set(None),
+                meeting: Set(false),
+                advance: Set(None),
+                advance_time: Set(None),
+                media_kind: Set(None),
+                media_file_id: Set(None),
+                held_since: Set(None),
+                failures: Set(0),
+                retry_time: Set(None),
+                thread_id: Set(None),
+                deleted_time: Set(None),
+            });
+        }
+    }
+    (reminders, cron_reminders)
+}
+
+/// Run the future and print how long it took
+async fn timed<T>(name: &str, fut: impl std::future::Future<Output = T>) -> T {
+    let start = Instant::now();
+    let res = fut.await;
+    println!("{:<32} {:>10.2?}", name, start.elapsed());
+    res
+}
+
+async fn fill(db: &Database, args: &StressArgs) -> Result<(), Error> {
+    let chats = args.chats.max(1);
+    timed("insert user timezones", async {
+        for idx in 0..chats {
+            let tz_name = TIMEZONES[idx as usize % TIMEZONES.len()];
+            db.insert_or_update_user_timezone(fake_chat_id(idx), tz_name)
+                .await?;
+        }
+        Ok::<_, Error>(())
+    })
+    .await?;
+
+    let (mut reminders, mut cron_reminders) =
+        generate(args, Utc::now().naive_utc());
+    println!(
+        "generated {} reminders and {} cron reminders in {} chats",
+        reminders.len(),
+        cron_reminders.len(),
+        chats
+    );
+    timed("insert reminders", async {
+        while !reminders.is_empty() || !cron_reminders.is_empty() {
+            let batch =
+                reminders.split_off(reminders.len().saturating_sub(BATCH_SIZE));
+            let cron_batch = cron_reminders
+                .split_off(cron_reminders.len().saturating_sub(BATCH_SIZE));
+            db.insert_reminders(batch, cron_batch).await?;
+        }
+        Ok::<_, Error>(())
+    })
+    .await?;
+
+    timed("get next reminder time", db.get_next_reminder_time()).await?;
+    timed("get active reminders", db.get_active_reminders()).await?;
+    timed("get active cron reminders", db.get_active_cron_reminders()).await?;
+    timed("get due occurrences", db.get_due_occurrences()).await?;
+    let chat_id = fake_chat_id(0);
+    timed("list a chat", async {
+        db.get_sorted_reminders(chat_id)
+            .await
+            .map(|rems| rems.len())
+    })
+    .await?;
+    timed("search a chat", async {
+        let filter = Filter {
+            text: Some("reminder 1".to_owned()),
+            ..Default::default()
+        };
+        db.search_reminders(chat_id, filter)
+            .await
+            .map(|rems| rems.len())
+    })
+    .await?;
+    Ok(())
+}
+
+pub(crate) async fn run(args: &StressArgs) {
+    logging::init(CLI.log_format);
+    let db = init_database().await;
+    db.apply_migrations()
+        .await
+        .expect("Failed to apply migrations");
+    if args.teardown {
+        match timed("teardown", db.delete_chats(FAKE_CHAT_IDS)).await {
+            Ok(deleted) => println!("deleted {} reminders", deleted),
+            Err(err) => tracing::error!("{}", err),
+        }
+    } else if let Err(err) = fill(&db, args).await {
+        tracing::error!("{}", err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let args = StressArgs {
+            reminders: 100,
+            chats: 3,
+            days: 2,
+            teardown: false,
+        };
+        let now = Utc::now().naive_utc();
+        let (reminders, cron_reminders) = generate(&args, now);
+        assert_eq!(reminders.len(), 90);
+        assert_eq!(cron_reminders.len(), 10);
+        for rem in reminders {
+            let chat_id = rem.chat_id.unwrap();
+            assert!(FAKE_CHAT_IDS.contains(&chat_id));
+            assert!(chat_id < fake_chat_id(3));
+            let time = rem.time.unwrap();
+            assert!(now < time && time <= now + TimeDelta::days(2));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Reminders saved under short names to set them again without typing,
+//! e.g. `/savetemplate pills daily 9:00 take pills` and then `/t pills`
+//! or a tap on the favorites keyboard shown by a bare `/t`
+
+use crate::entity::template;
+use crate::format;
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+};
+
+/// Longest name in bytes, short enough to fit the callback data
+const NAME_MAX_LEN: usize = 32;
+
+/// Longest reminder text shown on a favorites button
+const BUTTON_TEXT_LEN: usize = 40;
+
+fn is_valid_name(name: &str) -> bool {
+    (1..=NAME_MAX_LEN).contains(&name.len())
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Lowercase name and reminder text from the /savetemplate arguments,
+/// e.g. `pills daily 9:00 take pills`. A name alone deletes the template.
+pub(crate) fn parse_args(args: &str) -> Option<(String, Option<String>)> {
+    let args = args.trim();
+    let (name, text) = match args.split_once(char::is_whitespace) {
+        Some((name, text)) => (name, Some(text.trim().to_owned())),
+        None => (args, None),
+    };
+    is_valid_name(name).then(|| (name.to_lowercase(), text))
+}
+
+/// Lowercase name from the /t argument
+pub(crate) fn parse_name(arg: &str) -> Option<String> {
+    let name = arg.trim();
+    is_valid_name(name).then(|| name.to_lowercase())
+}
+
+/// Keyboard of the user's templates, one per row
+pub(crate) fn markup(templates: &[template::Model]) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(templates.iter().map(|template| {
+        vec![InlineKeyboardButton::new(
+            format!(
+                "⭐ {}: {}",
+                template.name,
+                format::truncate(&template.text, BUTTON_TEXT_LEN)
+            ),
+            InlineKeyboardButtonKind::CallbackData(format!(
+                "template::{}",
+                template.name
+            )),
+        )]
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("pills daily 9:00 take pills" => Some(("pills".to_owned(), Some("daily 9:00 take pills".to_owned()))) ; "save")]
+    #[test_case("  Pills  " => Some(("pills".to_owned(), None)) ; "delete")]
+    #[test_case("" => None ; "empty")]
+    #[test_case("a:b 9:00 x" => None ; "punctuation in name")]
+    fn test_parse_args(args: &str) -> Option<(String, Option<String>)> {
+        parse_args(args)
+    }
+
+    #[test]
+    fn test_name_max_len() {
+        assert!(parse_name(&"a".repeat(NAME_MAX_LEN)).is_some());
+        assert!(parse_name(&"a".repeat(NAME_MAX_LEN + 1)).is_none());
+    }
+}
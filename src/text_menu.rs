@@ -0,0 +1,115 @@
+//! Numbered text menus replacing the buttons for the users who can't press
+//! them, e.g. with a screen reader or an old client: the options are listed
+//! with numbers, and the option is chosen by sending its number
+
+use crate::db::LinkTarget;
+use crate::format;
+use crate::lang::Language;
+use crate::settings::Setting;
+use crate::tg::{self, TgResponse};
+use teloxide::utils::markdown::escape;
+
+/// Locale keys of the options of the menu choosing what to edit
+/// in a reminder
+pub(crate) const EDIT_MODES: [&str; 4] = [
+    "edit_mode_time_pattern",
+    "edit_mode_description",
+    "edit_mode_postpone",
+    "edit_mode_duplicate",
+];
+
+/// Menu awaiting the number of the chosen option
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) enum Menu {
+    /// Reminders to delete, in the order of their numbers
+    Delete(Vec<LinkTarget>),
+    /// Reminders to edit, in the order of their numbers
+    Edit(Vec<LinkTarget>),
+    /// Reminders to pause or resume, in the order of their numbers
+    Pause(Vec<LinkTarget>),
+    /// Reminders to skip the next occurrence of, in the order of their
+    /// numbers
+    Skip(Vec<LinkTarget>),
+    /// Editing the time pattern or the description of the reminder
+    EditMode(i64),
+    Settings,
+}
+
+impl Menu {
+    /// Number of the options to choose from
+    pub(crate) fn options(&self) -> usize {
+        match self {
+            Self::Delete(targets)
+            | Self::Edit(targets)
+            | Self::Pause(targets)
+            | Self::Skip(targets) => targets.len(),
+            Self::EditMode(_) => EDIT_MODES.len(),
+            Self::Settings => Setting::ALL.len(),
+        }
+    }
+}
+
+/// Messages with the header and the options numbered from 1,
+/// split if they don't fit into one
+pub(crate) fn messages(
+    header: TgResponse,
+    options: impl IntoIterator<Item = String>,
+    lang: Language,
+) -> Vec<String> {
+    let lines =
+        std::iter::once(header.to_string_in(lang))
+            .chain(options.into_iter().enumerate().map(|(idx, option)| {
+                escape(&format!("{}. {}", idx + 1, option))
+            }))
+            .chain(std::iter::once(TgResponse::SendNumber.to_string_in(lang)));
+    format::split_lines(lines, tg::MESSAGE_MAX_LEN)
+}
+
+/// Index of the option whose number was sent
+pub(crate) fn parse_choice(text: &str, options: usize) -> Option<usize> {
+    text.trim()
+        .trim_end_matches('.')
+        .parse::<usize>()
+        .ok()
+        .filter(|num| (1..=options).contains(num))
+        .map(|num| num - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("2" => Some(1) ; "number")]
+    #[test_case(" 3. " => Some(2) ; "as listed")]
+    #[test_case("4" => None ; "out of range")]
+    #[test_case("0" => None ; "zero")]
+    #[test_case("tomorrow at 10 call mom" => None ; "not a number")]
+    fn test_parse_choice(text: &str) -> Option<usize> {
+        parse_choice(text, 3)
+    }
+
+    #[test]
+    fn test_messages() {
+        assert_eq!(
+            messages(
+                TgResponse::ChooseEditMode,
+                EDIT_MODES.map(|key| Language::English.text(key, &[])),
+                Language::English
+            ),
+            vec![format!(
+                "{}\n1\\. Time pattern\n2\\. Description\n3\\. Postpone\n4\\. Duplicate\n{}",
+                TgResponse::ChooseEditMode,
+                TgResponse::SendNumber
+            )]
+        );
+    }
+
+    #[test]
+    fn test_options() {
+        let targets =
+            vec![LinkTarget::Reminder(1), LinkTarget::CronReminder(1)];
+        assert_eq!(Menu::Pause(targets).options(), 2);
+        assert_eq!(Menu::EditMode(1).options(), 4);
+    }
+}
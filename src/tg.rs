@@ -1,101 +1,908 @@
-use std::fmt::Display;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
+use teloxide::net::Download;
 use teloxide::payloads::SendMessageSetters;
 use teloxide::prelude::*;
 use teloxide::types::ParseMode::MarkdownV2;
 use teloxide::types::{
-    ChatId, InlineKeyboardMarkup, LinkPreviewOptions, MessageId,
+    ChatId, InlineKeyboardButton, InlineKeyboardButtonKind,
+    InlineKeyboardMarkup, InputFile, LinkPreviewOptions, MessageId,
+    ReplyParameters, ThreadId,
 };
-use teloxide::utils::markdown::escape;
-use teloxide::RequestError;
+use teloxide::utils::markdown::{code_block, escape};
+use teloxide::{ApiError, RequestError};
+
+use crate::advance::format_lead;
+use crate::entity::reminder::MediaKind;
+use crate::entity::reminder_event::EventKind;
+use crate::lang::Language;
+use crate::maintenance;
+
+/// Maximum length of a quote of the message replied to
+pub(crate) const QUOTE_MAX_LEN: usize = 1024;
+/// Maximum length of a message text accepted by Telegram
+pub(crate) const MESSAGE_MAX_LEN: usize = 4096;
+/// Maximum length of an inline button label that is still readable
+pub(crate) const BUTTON_LABEL_MAX_LEN: usize = 64;
+/// Maximum length of an inline button's callback data in bytes
+pub(crate) const CALLBACK_DATA_MAX_LEN: usize = 64;
+/// Messages sent in a second at most across all the chats
+const GLOBAL_SENDS_PER_SEC: u32 = 30;
+/// Pause between the messages sent to a chat
+const CHAT_SEND_INTERVAL: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    static ref SEND_LIMITER: SendLimiter = SendLimiter::new();
+}
+
+/// Slots spacing out the sent messages to stay under Telegram's flood
+/// limits of a message per second in a chat and 30 in all the chats when
+/// many reminders fire at once. Each send reserves the earliest slot free
+/// both in its chat and globally in the order the sends ask for them, so a
+/// chat's messages keep their order.
+struct SendLimiter {
+    origin: Instant,
+    slots: Mutex<Slots>,
+}
+
+#[derive(Default)]
+struct Slots {
+    /// Sends reserved in each second since the origin
+    per_second: BTreeMap<u64, u32>,
+    /// Time each chat can be sent a message again at
+    chats: HashMap<ChatId, Duration>,
+}
+
+impl SendLimiter {
+    fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            slots: Mutex::new(Slots::default()),
+        }
+    }
+
+    /// Wait for the slot reserved to send a message to the chat
+    async fn wait(&self, chat_id: ChatId) {
+        let now = self.origin.elapsed();
+        let slot = self.slots.lock().unwrap().reserve(chat_id, now);
+        tokio::time::sleep(slot.saturating_sub(now)).await;
+    }
+}
+
+impl Slots {
+    /// Reserve the earliest slot to send a message to the chat in,
+    /// as time since the origin
+    fn reserve(&mut self, chat_id: ChatId, now: Duration) -> Duration {
+        self.per_second = self.per_second.split_off(&now.as_secs());
+        self.chats.retain(|_, free_at| *free_at > now);
+
+        let mut slot = self.chats.get(&chat_id).copied().unwrap_or(now);
+        loop {
+            let second = slot.as_secs();
+            let reserved = self.per_second.entry(second).or_default();
+            if *reserved < GLOBAL_SENDS_PER_SEC {
+                // Spread evenly over the second
+                slot = slot.max(
+                    Duration::from_secs(second)
+                        + Duration::from_secs(1) * *reserved
+                            / GLOBAL_SENDS_PER_SEC,
+                );
+                *reserved += 1;
+                break;
+            }
+            slot = Duration::from_secs(second + 1);
+        }
+        self.chats.insert(chat_id, slot + CHAT_SEND_INTERVAL);
+        slot
+    }
+}
+
+/// Wait until a message can be sent to the chat without being flood
+/// limited, the tests don't wait
+async fn throttle(chat_id: ChatId) {
+    if cfg!(not(test)) {
+        SEND_LIMITER.wait(chat_id).await;
+    }
+}
 
 pub(crate) enum TgResponse {
     SuccessInsert(String),
-    SuccessPeriodicInsert(String),
+    SuccessPeriodicInsert(String, Vec<String>),
     FailedInsert,
+    BatchInsertHeader(usize, usize),
+    BatchLineInserted(String),
+    BatchLineFailed(String),
+    DescriptionTrimmed(usize, String),
     IncorrectRequest,
-    QueryingError,
+    ForwardedHint,
+    SyntaxError(String, Option<String>),
+    InvalidCron(String),
+    TooFrequent(u64),
+    UnknownTimezone,
+    StaleButton,
+    ReminderGone,
+    InternalError,
     RemindersListHeader,
+    ListDayHeader(String),
+    ListPausedHeader,
+    NoMatchingReminders,
     SelectTimezone,
     ChosenTimezone(String),
+    FoundTimezones,
+    NoTimezonesFound(String),
+    TimezoneSearchCanceled,
     FailedSetTimezone(String),
     ChooseDeleteReminder,
     SuccessDelete(String),
     FailedDelete,
+    Undone(String),
+    SuccessCopy(String),
+    CopyUsage,
     ChooseEditReminder,
     EnterNewReminder,
     SuccessEdit(String, String),
+    SuccessShift(String, String),
+    IncorrectShift,
     FailedEdit,
+    EditConflict(String),
+    MarkedDone,
+    CrontabImportHeader(usize, usize),
+    CrontabEntryImported(usize, String),
+    CrontabEntryFailed(usize, String),
+    CrontabEmpty,
+    FileTooLarge(u32),
+    CrontabNotText,
+    PausedReminderEnded(String),
+    CalendarImportHeader(usize, usize),
+    CalendarEventImported(String),
+    CalendarEventFailed(String),
+    CalendarEmpty,
+    CalendarInvalid,
     CancelEdit,
     ChoosePauseReminder,
     SuccessPause(String),
     SuccessResume(String),
     FailedPause,
+    ChooseSkipReminder,
+    SuccessSkip(String),
+    NothingToSkip(String),
+    FailedSkip,
     Hello,
+    HelloShortcut,
     HelloGroup,
+    OnboardingSelectLanguage(String),
+    OnboardingLanguageDetected(String, String),
+    FailedSetLanguage,
+    OnboardingSampleReminder,
+    OnboardingDone,
     EnterNewTimePattern,
     EnterNewDescription,
+    EnterEditedReminder,
+    EnterShift,
+    #[cfg(feature = "ai")]
+    AiSuggestion(String),
+    #[cfg(feature = "ai")]
+    AiEnabled,
+    #[cfg(feature = "ai")]
+    AiDisabled,
+    AiUnavailable,
+    SuggestionDiscarded,
+    PomodoroUsage,
+    PomodoroStarted(i32, i32, i32),
+    PomodoroWorkDone(i32, i32, i32),
+    PomodoroBreakDone(i32, i32, i32),
+    PomodoroFinished(i32),
+    PomodoroStopped(i32, i32),
+    BillIssued(String, String, i32),
+    BillPaid,
+    StatsHeader(i32),
+    StatsBill(String, String, usize, usize),
+    StatsEmpty(i32),
+    ExportCaption(usize),
+    ExportEmpty,
+    BackupImported(usize, usize, String),
+    BackupInvalid,
+    ShareLink(String, String),
+    ShareUsage,
+    ShareUnavailable,
+    SharesRevoked(u64),
+    CalendarFeed(String),
+    CalendarFeedUsage,
+    CalendarFeedUnavailable,
+    CalendarFeedPrivateChat,
+    CalendarFeedOff(bool),
+    ApiToken(String, String),
+    ApiTokenUsage,
+    ApiTokenUnavailable,
+    ApiTokenSent,
+    ApiTokenPrivateChatNeeded,
+    ApiTokenRevoked(bool),
+    NotChatAdmin,
+    ChooseOrphanedReminders,
+    NoOrphanedReminders,
+    OrphansClaimed(u64),
+    OrphansDeleted(u64),
+    ChooseTrashedReminders,
+    EmptyTrash,
+    RestoredReminders(u64),
+    ConfirmDeleteAll(usize),
+    ConfirmPauseAll(usize),
+    DeletedAll(u64),
+    PausedAll(u64),
+    BulkArgsTooLong,
+    BulkCancelled,
+    FailedRemindersResumed(u64),
+    AskOccurrenceNote,
+    NoteSaved,
+    HistoryHeader,
+    HistoryEntry(EventKind, String, String, Option<String>),
+    HistoryEmpty,
+    SetupPrivateChat,
+    SetupHeader,
+    SetupPrivacyMode(bool),
+    SetupCanSend(bool),
+    SetupCanPin(bool),
+    SetupCanPostInTopics(bool),
+    MeetingSummary(String, i64),
+    MeetingAnswers(String, Vec<String>),
+    RsvpSaved,
+    MeetingOver,
+    ManageReminder(String),
+    NotChatMember,
+    AdvanceWarning(String, String),
+    ChatTimezonePrivateChat,
+    ChatTimezone(Option<String>),
+    ChosenChatTimezone(String),
+    ChatTimezoneReset,
+    UnknownChatTimezone(String),
+    AgendaHeader(bool, String),
+    EmptyAgenda(bool),
+    TimeLeft(String),
+    TimeLeftNow,
+    Settings,
+    SettingsSaved,
+    Snoozed(String),
+    Muted(String),
+    QuietHours(Option<String>),
+    QuietHoursUsage,
+    QuietHoursSet(String),
+    QuietHoursOff,
+    DayTimes(String),
+    DayTimesUsage,
+    DayTimesSet(String),
+    HeldDuringQuietHours(String),
+    ChooseEditMode,
+    TextSettings,
+    SendNumber,
+    WrongNumber(usize),
+    NothingToChoose,
+    MenuClosed,
+    Digest(Option<String>),
+    DigestUsage,
+    DigestSet(String),
+    DigestOff,
+    Vacation(Option<String>),
+    VacationUsage,
+    VacationSet(String, bool),
+    VacationOver,
+    VacationMissed(usize),
+    VacationMissedEntry(String, String),
+    MissedDuringDowntime(String),
+    CatchUpSummary(usize),
+    CatchUpEntry(String, String),
+    ConfirmDeleteSeries(String),
+    NothingDeleted,
+    LinkChat,
+    LinkedChat(String),
+    LinkExpired,
+    LinkedChats,
+    NoLinkedChats,
+    Unlinked(String),
+    AllChatsListHeader,
+    ChatListHeader(String),
+    PrivateChatTitle,
+    DuplicateReminder(String),
+    DuplicateDiscarded,
+    PickDay,
+    PickHour(String),
+    PickMinute(String),
+    AskNewReminderDescription(String),
+    NewReminderCanceled,
+    NewReminderPrivateChat,
+    BroadcastUsage(i64),
+    BroadcastChats(Vec<i64>),
+    NoBroadcastChats,
+    BroadcastAdded(i64),
+    AlreadyBroadcast(i64),
+    BroadcastStopped(i64),
+    NotBroadcast(i64),
+    BroadcastChatUnreachable(i64),
+    NotBroadcastChatAdmin(i64),
+    TemplateUsage,
+    TemplateSaved(String),
+    TemplateDeleted(String),
+    TemplateNotFound(String),
+    TemplateInvalid(String),
+    ChooseTemplate,
+    NoTemplates,
+    DigestHeader(bool, String),
 }
 
 impl TgResponse {
+    /// Text of the response in English the tests compare the replies with
+    #[cfg(test)]
     pub(crate) fn to_unescaped_string(&self) -> String {
+        self.to_unescaped_string_in(Language::default())
+    }
+
+    /// Text of the response in the language
+    pub(crate) fn to_unescaped_string_in(&self, lang: Language) -> String {
+        let text = |key| lang.text(key, &[]);
+        let reminders = |count: u64| lang.plural("reminders", count);
+        match self {
+            Self::SuccessInsert(reminder_str) => {
+                lang.text("success_insert", &[("reminder", reminder_str)])
+            }
+            Self::SuccessPeriodicInsert(reminder_str, next_times) => lang.text(
+                "success_periodic_insert",
+                &[
+                    ("reminder", reminder_str),
+                    ("next_times", &next_times.join("\n")),
+                ],
+            ),
+            Self::FailedInsert => text("failed_insert"),
+            Self::BatchInsertHeader(inserted, total) => lang.text(
+                "batch_insert_header",
+                &[("inserted", inserted), ("total", &reminders(*total as u64))],
+            ),
+            Self::BatchLineInserted(reminder_str) => {
+                format!("✅ {}", reminder_str)
+            }
+            Self::BatchLineFailed(line) => format!("❌ {}", line),
+            Self::DescriptionTrimmed(max_len, overflow) => lang.text(
+                "description_trimmed",
+                &[("max_len", max_len), ("overflow", overflow)],
+            ),
+            Self::IncorrectRequest => text("incorrect_request"),
+            Self::ForwardedHint => text("forwarded_hint"),
+            Self::SyntaxError(pointer, None) => {
+                lang.text("syntax_error", &[("pointer", pointer)])
+            }
+            Self::SyntaxError(pointer, Some(hint)) => lang.text(
+                "syntax_error_expected",
+                &[("pointer", pointer), ("hint", hint)],
+            ),
+            Self::InvalidCron(problem) => {
+                lang.text("invalid_cron", &[("problem", problem)])
+            }
+            Self::TooFrequent(seconds) => {
+                lang.text("too_frequent", &[("seconds", seconds)])
+            }
+            Self::UnknownTimezone => text("unknown_timezone"),
+            Self::StaleButton => text("stale_button"),
+            Self::ReminderGone => text("reminder_gone"),
+            Self::InternalError => text("internal_error"),
+            Self::RemindersListHeader => text("reminders_list_header"),
+            Self::ListDayHeader(day) => format!("📅 {}", day),
+            Self::ListPausedHeader => text("list_paused_header"),
+            Self::NoMatchingReminders => text("no_matching_reminders"),
+            Self::SelectTimezone => text("select_timezone"),
+            Self::FoundTimezones => text("found_timezones"),
+            Self::NoTimezonesFound(query) => {
+                lang.text("no_timezones_found", &[("query", query)])
+            }
+            Self::TimezoneSearchCanceled => text("timezone_search_canceled"),
+            Self::ChosenTimezone(tz_name) => {
+                lang.text("chosen_timezone", &[("timezone", tz_name)])
+            }
+            Self::FailedSetTimezone(tz_name) => {
+                lang.text("failed_set_timezone", &[("timezone", tz_name)])
+            }
+            Self::ChooseDeleteReminder => text("choose_delete_reminder"),
+            Self::SuccessDelete(reminder_str) => {
+                lang.text("success_delete", &[("reminder", reminder_str)])
+            }
+            Self::FailedDelete => text("failed_delete"),
+            Self::Undone(reminder_str) => {
+                lang.text("undone", &[("reminder", reminder_str)])
+            }
+            Self::SuccessCopy(reminder_str) => {
+                lang.text("success_copy", &[("reminder", reminder_str)])
+            }
+            Self::CopyUsage => text("copy_usage"),
+            Self::ChooseEditReminder => text("choose_edit_reminder"),
+            Self::EnterNewReminder => text("enter_new_reminder"),
+            Self::SuccessEdit(old_reminder_str, reminder_str) => lang.text(
+                "success_edit",
+                &[
+                    ("old_reminder", old_reminder_str),
+                    ("reminder", reminder_str),
+                ],
+            ),
+            Self::SuccessShift(shift, reminder_str) => lang.text(
+                "success_shift",
+                &[("shift", shift), ("reminder", reminder_str)],
+            ),
+            Self::IncorrectShift => text("incorrect_shift"),
+            Self::FailedEdit => text("failed_edit"),
+            Self::MarkedDone => text("marked_done"),
+            Self::CrontabImportHeader(imported, total) => lang.text(
+                "crontab_import_header",
+                &[("imported", imported), ("total", total)],
+            ),
+            Self::CrontabEntryImported(line_no, reminder_str) => {
+                format!("✅ {}: {}", line_no, reminder_str)
+            }
+            Self::CrontabEntryFailed(line_no, line) => {
+                format!("❌ {}: {}", line_no, line)
+            }
+            Self::CrontabEmpty => text("crontab_empty"),
+            Self::FileTooLarge(max_size) => {
+                lang.text("file_too_large", &[("max_size", max_size)])
+            }
+            Self::CrontabNotText => text("crontab_not_text"),
+            Self::PausedReminderEnded(reminder_str) => lang
+                .text("paused_reminder_ended", &[("reminder", reminder_str)]),
+            Self::CalendarImportHeader(imported, total) => lang.text(
+                "calendar_import_header",
+                &[("imported", imported), ("total", total)],
+            ),
+            Self::CalendarEventImported(reminder_str) => {
+                format!("✅ {}", reminder_str)
+            }
+            Self::CalendarEventFailed(summary) => format!("❌ {}", summary),
+            Self::CalendarEmpty => text("calendar_empty"),
+            Self::CalendarInvalid => text("calendar_invalid"),
+            Self::EditConflict(reminder_str) => {
+                lang.text("edit_conflict", &[("reminder", reminder_str)])
+            }
+            Self::CancelEdit => text("cancel_edit"),
+            Self::ChoosePauseReminder => text("choose_pause_reminder"),
+            Self::SuccessPause(reminder_str) => {
+                lang.text("success_pause", &[("reminder", reminder_str)])
+            }
+            Self::SuccessResume(reminder_str) => {
+                lang.text("success_resume", &[("reminder", reminder_str)])
+            }
+            Self::FailedPause => text("failed_pause"),
+            Self::ChooseSkipReminder => text("choose_skip_reminder"),
+            Self::SuccessSkip(reminder_str) => {
+                lang.text("success_skip", &[("reminder", reminder_str)])
+            }
+            Self::NothingToSkip(reminder_str) => {
+                lang.text("nothing_to_skip", &[("reminder", reminder_str)])
+            }
+            Self::FailedSkip => text("failed_skip"),
+            Self::Hello => text("hello"),
+            Self::HelloShortcut => text("hello_shortcut"),
+            Self::HelloGroup => text("hello_group"),
+            Self::OnboardingSelectLanguage(tz_name) => lang
+                .text("onboarding_select_language", &[("timezone", tz_name)]),
+            Self::OnboardingLanguageDetected(tz_name, lang_name) => lang.text(
+                "onboarding_language_detected",
+                &[("timezone", tz_name), ("language", lang_name)],
+            ),
+            Self::FailedSetLanguage => text("failed_set_language"),
+            Self::OnboardingSampleReminder => {
+                text("onboarding_sample_reminder")
+            }
+            Self::OnboardingDone => text("onboarding_done"),
+            Self::EnterNewTimePattern => text("enter_new_time_pattern"),
+            Self::EnterNewDescription => text("enter_new_description"),
+            Self::EnterEditedReminder => text("enter_edited_reminder"),
+            Self::EnterShift => text("enter_shift"),
+            #[cfg(feature = "ai")]
+            Self::AiSuggestion(suggestion) => {
+                lang.text("ai_suggestion", &[("text", suggestion)])
+            }
+            #[cfg(feature = "ai")]
+            Self::AiEnabled => text("ai_enabled"),
+            #[cfg(feature = "ai")]
+            Self::AiDisabled => text("ai_disabled"),
+            Self::AiUnavailable => text("ai_unavailable"),
+            Self::SuggestionDiscarded => text("suggestion_discarded"),
+            Self::PomodoroUsage => text("pomodoro_usage"),
+            Self::PomodoroStarted(work, rest, rounds) => lang.text(
+                "pomodoro_started",
+                &[("work", work), ("rest", rest), ("rounds", rounds)],
+            ),
+            Self::PomodoroWorkDone(round, rounds, rest) => lang.text(
+                "pomodoro_work_done",
+                &[("round", round), ("rounds", rounds), ("rest", rest)],
+            ),
+            Self::PomodoroBreakDone(round, rounds, work) => lang.text(
+                "pomodoro_break_done",
+                &[("round", round), ("rounds", rounds), ("work", work)],
+            ),
+            Self::PomodoroFinished(rounds) => {
+                lang.text("pomodoro_finished", &[("rounds", rounds)])
+            }
+            Self::PomodoroStopped(completed, rounds) => lang.text(
+                "pomodoro_stopped",
+                &[("completed", completed), ("rounds", rounds)],
+            ),
+            Self::BillIssued(amount, total, year) => lang.text(
+                "bill_issued",
+                &[("amount", amount), ("total", total), ("year", year)],
+            ),
+            Self::BillPaid => text("bill_paid"),
+            Self::StatsHeader(year) => {
+                lang.text("stats_header", &[("year", year)])
+            }
+            Self::StatsBill(name, total, paid, count) => lang.text(
+                "stats_bill",
+                &[
+                    ("name", name),
+                    ("total", total),
+                    ("paid", paid),
+                    ("count", count),
+                ],
+            ),
+            Self::StatsEmpty(year) => {
+                lang.text("stats_empty", &[("year", year)])
+            }
+            Self::ExportCaption(count) => lang.text(
+                "export_caption",
+                &[("reminders", &reminders(*count as u64))],
+            ),
+            Self::ExportEmpty => text("export_empty"),
+            Self::BackupImported(imported, 0, tz_name) => lang.text(
+                "backup_imported",
+                &[
+                    ("reminders", &reminders(*imported as u64)),
+                    ("timezone", tz_name),
+                ],
+            ),
+            Self::BackupImported(imported, skipped, tz_name) => lang.text(
+                "backup_imported_partly",
+                &[
+                    ("reminders", &reminders(*imported as u64)),
+                    ("skipped", skipped),
+                    ("timezone", tz_name),
+                ],
+            ),
+            Self::BackupInvalid => text("backup_invalid"),
+            Self::ShareLink(url, expires) => {
+                lang.text("share_link", &[("url", url), ("expires", expires)])
+            }
+            Self::ShareUsage => text("share_usage"),
+            Self::ShareUnavailable => text("share_unavailable"),
+            Self::SharesRevoked(count) => lang.text(
+                "shares_revoked",
+                &[("links", &lang.plural("links", *count))],
+            ),
+            Self::CalendarFeed(url) => {
+                lang.text("calendar_feed", &[("url", url)])
+            }
+            Self::CalendarFeedUsage => text("calendar_feed_usage"),
+            Self::CalendarFeedUnavailable => text("calendar_feed_unavailable"),
+            Self::CalendarFeedPrivateChat => text("calendar_feed_private_chat"),
+            Self::CalendarFeedOff(true) => text("calendar_feed_off"),
+            Self::CalendarFeedOff(false) => text("calendar_feed_missing"),
+            Self::ApiToken(token, url) => {
+                lang.text("api_token", &[("token", token), ("url", url)])
+            }
+            Self::ApiTokenUsage => text("api_token_usage"),
+            Self::ApiTokenUnavailable => text("api_token_unavailable"),
+            Self::ApiTokenSent => text("api_token_sent"),
+            Self::ApiTokenPrivateChatNeeded => {
+                text("api_token_private_chat_needed")
+            }
+            Self::ApiTokenRevoked(true) => text("api_token_revoked"),
+            Self::ApiTokenRevoked(false) => text("api_token_missing"),
+            Self::NotChatAdmin => text("not_chat_admin"),
+            Self::ChooseOrphanedReminders => text("choose_orphaned_reminders"),
+            Self::NoOrphanedReminders => text("no_orphaned_reminders"),
+            Self::OrphansClaimed(count) => lang
+                .text("orphans_claimed", &[("reminders", &reminders(*count))]),
+            Self::OrphansDeleted(count) => lang
+                .text("orphans_deleted", &[("reminders", &reminders(*count))]),
+            Self::ChooseTrashedReminders => lang.text(
+                "choose_trashed_reminders",
+                &[("days", &maintenance::TRASH_KEPT_FOR.num_days())],
+            ),
+            Self::EmptyTrash => text("empty_trash"),
+            Self::RestoredReminders(count) => lang.text(
+                "restored_reminders",
+                &[("reminders", &reminders(*count))],
+            ),
+            Self::ConfirmDeleteAll(count) => lang.text(
+                "confirm_delete_all",
+                &[("reminders", &reminders(*count as u64))],
+            ),
+            Self::ConfirmPauseAll(count) => lang.text(
+                "confirm_pause_all",
+                &[("reminders", &reminders(*count as u64))],
+            ),
+            Self::DeletedAll(count) => {
+                lang.text("deleted_all", &[("reminders", &reminders(*count))])
+            }
+            Self::PausedAll(count) => {
+                lang.text("paused_all", &[("reminders", &reminders(*count))])
+            }
+            Self::BulkArgsTooLong => text("bulk_args_too_long"),
+            Self::BulkCancelled => text("bulk_cancelled"),
+            Self::FailedRemindersResumed(count) => lang.text(
+                "failed_reminders_resumed",
+                &[("reminders", &reminders(*count))],
+            ),
+            Self::AskOccurrenceNote => text("ask_occurrence_note"),
+            Self::NoteSaved => text("note_saved"),
+            Self::HistoryHeader => text("history_header"),
+            Self::HistoryEntry(kind, time, desc, note) => {
+                let icon = match kind {
+                    EventKind::Sent => "🔔",
+                    EventKind::Failed => "⚠️",
+                    EventKind::Done => "✅",
+                    EventKind::Snoozed => "💤",
+                    EventKind::Missed => "🏖",
+                    EventKind::Skipped => "⏭",
+                };
+                match note {
+                    Some(note) => {
+                        format!("{} {} {} — {}", icon, time, desc, note)
+                    }
+                    None => format!("{} {} {}", icon, time, desc),
+                }
+            }
+            Self::HistoryEmpty => text("history_empty"),
+            Self::SetupPrivateChat => text("setup_private_chat"),
+            Self::SetupHeader => text("setup_header"),
+            Self::SetupPrivacyMode(true) => text("setup_privacy_mode_on"),
+            Self::SetupPrivacyMode(false) => text("setup_privacy_mode_off"),
+            Self::SetupCanSend(true) => text("setup_can_send"),
+            Self::SetupCanSend(false) => text("setup_cannot_send"),
+            Self::SetupCanPin(true) => text("setup_can_pin"),
+            Self::SetupCanPin(false) => text("setup_cannot_pin"),
+            Self::SetupCanPostInTopics(true) => {
+                text("setup_can_post_in_topics")
+            }
+            Self::SetupCanPostInTopics(false) => {
+                text("setup_cannot_post_in_topics")
+            }
+            Self::MeetingSummary(desc, minutes) if *minutes > 0 => lang.text(
+                "meeting_summary",
+                &[
+                    ("meeting", desc),
+                    ("minutes", &lang.plural("minutes", *minutes as u64)),
+                ],
+            ),
+            Self::MeetingSummary(desc, _) => {
+                lang.text("meeting_starting", &[("meeting", desc)])
+            }
+            Self::MeetingAnswers(answer, names) if names.is_empty() => {
+                lang.text("meeting_answers_nobody", &[("answer", answer)])
+            }
+            Self::MeetingAnswers(answer, names) => {
+                format!("{} ({}): {}", answer, names.len(), names.join(", "))
+            }
+            Self::RsvpSaved => text("rsvp_saved"),
+            Self::MeetingOver => text("meeting_over"),
+            Self::ManageReminder(reminder_str) => {
+                format!("⚙️ {}", reminder_str)
+            }
+            Self::NotChatMember => text("not_chat_member"),
+            Self::AdvanceWarning(lead, reminder_str) => lang.text(
+                "advance_warning",
+                &[("lead", lead), ("reminder", reminder_str)],
+            ),
+            Self::ChatTimezonePrivateChat => text("chat_timezone_private_chat"),
+            Self::ChatTimezone(Some(tz_name)) => {
+                lang.text("chat_timezone", &[("timezone", tz_name)])
+            }
+            Self::ChatTimezone(None) => text("chat_timezone_missing"),
+            Self::ChosenChatTimezone(tz_name) => {
+                lang.text("chosen_chat_timezone", &[("timezone", tz_name)])
+            }
+            Self::ChatTimezoneReset => text("chat_timezone_reset"),
+            Self::UnknownChatTimezone(tz_name) => {
+                lang.text("unknown_chat_timezone", &[("timezone", tz_name)])
+            }
+            Self::AgendaHeader(false, day) => {
+                lang.text("agenda_header_today", &[("day", day)])
+            }
+            Self::AgendaHeader(true, day) => {
+                lang.text("agenda_header_tomorrow", &[("day", day)])
+            }
+            Self::EmptyAgenda(false) => text("empty_agenda_today"),
+            Self::EmptyAgenda(true) => text("empty_agenda_tomorrow"),
+            Self::TimeLeft(left) => lang.text("time_left", &[("left", left)]),
+            Self::TimeLeftNow => text("time_left_now"),
+            Self::Settings => text("settings"),
+            Self::SettingsSaved => text("settings_saved"),
+            Self::Snoozed(lead) => lang.text("snoozed", &[("lead", lead)]),
+            Self::Muted(time) => lang.text("muted", &[("time", time)]),
+            Self::QuietHours(Some(window)) => {
+                lang.text("quiet_hours", &[("window", window)])
+            }
+            Self::QuietHours(None) => text("quiet_hours_missing"),
+            Self::QuietHoursUsage => text("quiet_hours_usage"),
+            Self::QuietHoursSet(window) => {
+                lang.text("quiet_hours_set", &[("window", window)])
+            }
+            Self::QuietHoursOff => text("quiet_hours_off"),
+            Self::DayTimes(times) => {
+                lang.text("day_times", &[("times", times)])
+            }
+            Self::DayTimesUsage => text("day_times_usage"),
+            Self::DayTimesSet(times) => {
+                lang.text("day_times_set", &[("times", times)])
+            }
+            Self::HeldDuringQuietHours(time) => {
+                lang.text("held_during_quiet_hours", &[("time", time)])
+            }
+            Self::ChooseEditMode => text("choose_edit_mode"),
+            Self::TextSettings => text("text_settings"),
+            Self::SendNumber => text("send_number"),
+            Self::WrongNumber(options) => {
+                lang.text("wrong_number", &[("options", options)])
+            }
+            Self::NothingToChoose => text("nothing_to_choose"),
+            Self::MenuClosed => text("menu_closed"),
+            Self::Digest(Some(when)) => lang.text("digest", &[("when", when)]),
+            Self::Digest(None) => text("digest_missing"),
+            Self::DigestUsage => text("digest_usage"),
+            Self::DigestSet(when) => lang.text("digest_set", &[("when", when)]),
+            Self::DigestOff => text("digest_off"),
+            Self::Vacation(Some(day)) => lang.text("vacation", &[("day", day)]),
+            Self::Vacation(None) => text("vacation_missing"),
+            Self::VacationUsage => text("vacation_usage"),
+            Self::VacationSet(day, false) => {
+                lang.text("vacation_set", &[("day", day)])
+            }
+            Self::VacationSet(day, true) => {
+                lang.text("vacation_set_digest", &[("day", day)])
+            }
+            Self::VacationOver => text("vacation_over"),
+            Self::VacationMissed(count) => lang.text(
+                "vacation_missed",
+                &[("missed", &lang.plural("missed_reminders", *count as u64))],
+            ),
+            Self::VacationMissedEntry(time, desc) => {
+                format!("• {} {}", time, desc)
+            }
+            Self::MissedDuringDowntime(time) => {
+                lang.text("missed_during_downtime", &[("time", time)])
+            }
+            Self::CatchUpSummary(count) => lang.text(
+                "catch_up_summary",
+                &[("missed", &lang.plural("missed_reminders", *count as u64))],
+            ),
+            Self::CatchUpEntry(time, desc) => format!("• {} {}", time, desc),
+            Self::ConfirmDeleteSeries(reminder_str) => lang
+                .text("confirm_delete_series", &[("reminder", reminder_str)]),
+            Self::NothingDeleted => text("nothing_deleted"),
+            Self::LinkChat => text("link_chat"),
+            Self::LinkedChat(title) => {
+                lang.text("linked_chat", &[("title", title)])
+            }
+            Self::LinkExpired => text("link_expired"),
+            Self::LinkedChats => text("linked_chats"),
+            Self::NoLinkedChats => text("no_linked_chats"),
+            Self::Unlinked(title) => lang.text("unlinked", &[("title", title)]),
+            Self::AllChatsListHeader => text("all_chats_list_header"),
+            Self::ChatListHeader(title) => format!("👥 {}:", title),
+            Self::PrivateChatTitle => text("private_chat_title"),
+            Self::DuplicateReminder(reminder_str) => {
+                lang.text("duplicate_reminder", &[("reminder", reminder_str)])
+            }
+            Self::DuplicateDiscarded => text("duplicate_discarded"),
+            Self::PickDay => text("pick_day"),
+            Self::PickHour(day) => lang.text("pick_hour", &[("day", day)]),
+            Self::PickMinute(day) => lang.text("pick_minute", &[("day", day)]),
+            Self::AskNewReminderDescription(when) => {
+                lang.text("ask_new_reminder_description", &[("when", when)])
+            }
+            Self::NewReminderCanceled => text("new_reminder_canceled"),
+            Self::NewReminderPrivateChat => text("new_reminder_private_chat"),
+            Self::BroadcastUsage(chat_id) => {
+                lang.text("broadcast_usage", &[("chat_id", chat_id)])
+            }
+            Self::BroadcastChats(chat_ids) => lang.text(
+                "broadcast_chats",
+                &[(
+                    "chat_ids",
+                    &chat_ids
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                )],
+            ),
+            Self::NoBroadcastChats => text("no_broadcast_chats"),
+            Self::BroadcastAdded(chat_id) => {
+                lang.text("broadcast_added", &[("chat_id", chat_id)])
+            }
+            Self::AlreadyBroadcast(chat_id) => {
+                lang.text("already_broadcast", &[("chat_id", chat_id)])
+            }
+            Self::BroadcastStopped(chat_id) => {
+                lang.text("broadcast_stopped", &[("chat_id", chat_id)])
+            }
+            Self::NotBroadcast(chat_id) => {
+                lang.text("not_broadcast", &[("chat_id", chat_id)])
+            }
+            Self::BroadcastChatUnreachable(chat_id) => {
+                lang.text("broadcast_chat_unreachable", &[("chat_id", chat_id)])
+            }
+            Self::NotBroadcastChatAdmin(chat_id) => {
+                lang.text("not_broadcast_chat_admin", &[("chat_id", chat_id)])
+            }
+            Self::TemplateUsage => text("template_usage"),
+            Self::TemplateSaved(name) => {
+                lang.text("template_saved", &[("name", name)])
+            }
+            Self::TemplateDeleted(name) => {
+                lang.text("template_deleted", &[("name", name)])
+            }
+            Self::TemplateNotFound(name) => {
+                lang.text("template_not_found", &[("name", name)])
+            }
+            Self::TemplateInvalid(template) => {
+                lang.text("template_invalid", &[("text", template)])
+            }
+            Self::ChooseTemplate => text("choose_template"),
+            Self::NoTemplates => text("no_templates"),
+            Self::DigestHeader(false, day) => {
+                lang.text("digest_header_today", &[("day", day)])
+            }
+            Self::DigestHeader(true, day) => {
+                lang.text("digest_header_week", &[("day", day)])
+            }
+        }
+    }
+
+    /// Escaped text of the response in the language
+    pub(crate) fn to_string_in(&self, lang: Language) -> String {
+        let text = escape(&self.to_unescaped_string_in(lang));
         match self {
-            Self::SuccessInsert(reminder_str) => format!("Added a reminder:\n{}", reminder_str),
-            Self::SuccessPeriodicInsert(reminder_str) => format!("Added a periodic reminder:\n{}", reminder_str),
-            Self::FailedInsert => "Failed to create a reminder...".to_owned(),
-            Self::IncorrectRequest => "Incorrect request!".to_owned(),
-            Self::QueryingError => "Error occured while querying reminders...".to_owned(),
-            Self::RemindersListHeader => "List of reminders:".to_owned(),
-            Self::SelectTimezone => "Select your timezone:".to_owned(),
-            Self::ChosenTimezone(tz_name) => format!(
-                concat!(
-                    "Selected timezone {}. Now you can set some reminders.\n\n",
-                    "You can get the commands I understand with /help."
-                ),
-                tz_name
-            ),
-            Self::FailedSetTimezone(tz_name) => format!("Failed to set timezone {}", tz_name),
-            Self::ChooseDeleteReminder => "Choose a reminder to delete:".to_owned(),
-            Self::SuccessDelete(reminder_str) => format!("🗑 Deleted a reminder: {}", reminder_str),
-            Self::FailedDelete => "Failed to delete...".to_owned(),
-            Self::ChooseEditReminder => "Choose a reminder to edit:".to_owned(),
-            Self::EnterNewReminder => "Enter reminder to replace with:".to_owned(),
-            Self::SuccessEdit(old_reminder_str, reminder_str) => format!("📝 Replaced a reminder: {}\nwith ➡️ {}", old_reminder_str, reminder_str),
-            Self::FailedEdit => "Failed to edit... You can try again or cancel editing with /cancel".to_owned(),
-            Self::CancelEdit => "Canceled editing".to_owned(),
-            Self::ChoosePauseReminder => "Choose a reminder to pause/resume:".to_owned(),
-            Self::SuccessPause(reminder_str) => format!("⏸ Paused a reminder: {}", reminder_str),
-            Self::SuccessResume(reminder_str) => format!("▶️ Resumed a reminder: {}", reminder_str),
-            Self::FailedPause => "Failed to pause...".to_owned(),
-            Self::Hello => concat!(
-                "Hello! I'm remindee bot. My purpose is to remind you of whatever you ask and ",
-                "whenever you ask.\n\n",
-                "Examples:\n17:30 go to restaurant => notify today at 5:30 PM\n",
-                "01.01 00:00 Happy New Year => notify at 1st of January at 12 AM\n",
-                "55 10 * * 1-5 meeting call => notify at 10:55 AM every weekday ",
-                "(CRON expression format)\n\n",
-                "Before we start, please either send me your location 📍 or manually select the timezone using the /settimezone command first."
-            )
-            .to_owned(),
-            Self::HelloGroup => concat!(
-                "Hello! I'm remindee bot. My purpose is to remind you of whatever you ask and ",
-                "whenever you ask.\n\n",
-                "Examples:\n17:30 go to restaurant => notify today at 5:30 PM\n",
-                "01.01 00:00 Happy New Year => notify at 1st of January at 12 AM\n",
-                "55 10 * * 1-5 meeting call => notify at 10:55 AM every weekday ",
-                "(CRON expression format)\n\n",
-                "Before we start, please select the timezone using the /settimezone command first."
-            )
-            .to_owned(),
-            Self::EnterNewTimePattern => "Enter a new time pattern for the reminder".to_owned(),
-            Self::EnterNewDescription => "Enter a new description for the reminder".to_owned(),
+            // the caret lines up with the text only in monospace
+            Self::SyntaxError(pointer, _) => {
+                text.replacen(&escape(pointer), &code_block(pointer), 1)
+            }
+            _ => text,
         }
     }
 }
 
-impl Display for TgResponse {
+/// Escaped text of the response in English the tests compare the replies
+/// with, the bot always renders it in the language of the user
+#[cfg(test)]
+impl std::fmt::Display for TgResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", escape(&self.to_unescaped_string()))
+        write!(f, "{}", self.to_string_in(Language::default()))
+    }
+}
+
+/// Reply to a user, rendered in their language if it's a response and
+/// sent as it is if it's a text made up already
+pub(crate) trait Localized {
+    fn localized(&self, lang: Language) -> String;
+}
+
+impl Localized for TgResponse {
+    fn localized(&self, lang: Language) -> String {
+        self.to_string_in(lang)
+    }
+}
+
+impl Localized for String {
+    fn localized(&self, _lang: Language) -> String {
+        self.clone()
+    }
+}
+
+impl Localized for str {
+    fn localized(&self, _lang: Language) -> String {
+        self.to_owned()
+    }
+}
+
+impl<T: Localized + ?Sized> Localized for &T {
+    fn localized(&self, lang: Language) -> String {
+        (**self).localized(lang)
     }
 }
 
@@ -103,20 +910,45 @@ pub(crate) async fn _send_message(
     text: &str,
     bot: &Bot,
     chat_id: ChatId,
+    thread_id: Option<ThreadId>,
     silent: bool,
+    reply: Option<&Reply>,
 ) -> Result<Message, RequestError> {
-    bot.send_message(chat_id, text)
-        .parse_mode(MarkdownV2)
-        .link_preview_options(LinkPreviewOptions {
-            is_disabled: true,
-            url: Default::default(),
-            prefer_small_media: Default::default(),
-            prefer_large_media: Default::default(),
-            show_above_text: Default::default(),
-        })
-        .disable_notification(silent)
-        .send()
-        .await
+    let request = |quote: Option<&str>| {
+        let mut request = bot
+            .send_message(chat_id, text)
+            .parse_mode(MarkdownV2)
+            .link_preview_options(LinkPreviewOptions {
+                is_disabled: true,
+                url: Default::default(),
+                prefer_small_media: Default::default(),
+                prefer_large_media: Default::default(),
+                show_above_text: Default::default(),
+            })
+            .disable_notification(silent);
+        if let Some(thread_id) = thread_id {
+            request = request.message_thread_id(thread_id);
+        }
+        if let Some(reply) = reply {
+            // The message may have been deleted since
+            let mut params = ReplyParameters::new(reply.msg_id)
+                .allow_sending_without_reply();
+            if let Some(quote) = quote {
+                params = params.quote(quote.to_owned());
+            }
+            request = request.reply_parameters(params);
+        }
+        request.send()
+    };
+    let quote = reply.and_then(|reply| reply.quote.as_deref());
+    throttle(chat_id).await;
+    match request(quote).await {
+        // The quoted text may be gone from the message after an edit
+        Err(RequestError::Api(ApiError::Unknown(_))) if quote.is_some() => {
+            request(None).await
+        }
+        res => res,
+    }
 }
 
 pub(crate) async fn send_message(
@@ -124,7 +956,7 @@ pub(crate) async fn send_message(
     bot: &Bot,
     chat_id: ChatId,
 ) -> Result<Message, RequestError> {
-    _send_message(text, bot, chat_id, false).await
+    _send_message(text, bot, chat_id, None, false, None).await
 }
 
 pub(crate) async fn send_silent_message(
@@ -132,7 +964,7 @@ pub(crate) async fn send_silent_message(
     bot: &Bot,
     chat_id: ChatId,
 ) -> Result<Message, RequestError> {
-    _send_message(text, bot, chat_id, true).await
+    _send_message(text, bot, chat_id, None, true, None).await
 }
 
 pub(crate) async fn delete_message(
@@ -143,12 +975,36 @@ pub(crate) async fn delete_message(
     bot.delete_message(chat_id, msg_id).await.map(|_| ())
 }
 
+/// Pin the message without notifying the chat about it if silent
+pub(crate) async fn pin_message(
+    bot: &Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+    silent: bool,
+) -> Result<(), RequestError> {
+    bot.pin_chat_message(chat_id, msg_id)
+        .disable_notification(silent)
+        .await
+        .map(|_| ())
+}
+
+pub(crate) async fn unpin_message(
+    bot: &Bot,
+    chat_id: ChatId,
+    msg_id: MessageId,
+) -> Result<(), RequestError> {
+    bot.unpin_chat_message(chat_id)
+        .message_id(msg_id)
+        .await
+        .map(|_| ())
+}
+
 pub(crate) async fn send_markup(
     text: &str,
     markup: InlineKeyboardMarkup,
     bot: &Bot,
     chat_id: ChatId,
-) -> Result<(), RequestError> {
+) -> Result<Message, RequestError> {
     bot.send_message(chat_id, text)
         .parse_mode(MarkdownV2)
         .link_preview_options(LinkPreviewOptions {
@@ -162,7 +1018,6 @@ pub(crate) async fn send_markup(
         .reply_markup(markup)
         .send()
         .await
-        .map(|_| ())
 }
 
 pub(crate) async fn edit_markup(
@@ -177,3 +1032,274 @@ pub(crate) async fn edit_markup(
         .await
         .map(|_| ())
 }
+
+/// Attach the buttons to a sent message, return it along with them
+pub(crate) async fn attach_markup(
+    markup: InlineKeyboardMarkup,
+    bot: &Bot,
+    msg: &Message,
+) -> Result<Message, RequestError> {
+    bot.edit_message_reply_markup(msg.chat.id, msg.id)
+        .reply_markup(markup)
+        .send()
+        .await
+}
+
+pub(crate) async fn edit_message_text(
+    text: &str,
+    bot: &Bot,
+    msg_id: MessageId,
+    chat_id: ChatId,
+) -> Result<(), RequestError> {
+    bot.edit_message_text(chat_id, msg_id, text)
+        .parse_mode(MarkdownV2)
+        .link_preview_options(LinkPreviewOptions {
+            is_disabled: true,
+            url: Default::default(),
+            prefer_small_media: Default::default(),
+            prefer_large_media: Default::default(),
+            show_above_text: Default::default(),
+        })
+        .send()
+        .await
+        .map(|_| ())
+}
+
+pub(crate) async fn edit_text_markup(
+    text: &str,
+    markup: InlineKeyboardMarkup,
+    bot: &Bot,
+    msg_id: MessageId,
+    chat_id: ChatId,
+) -> Result<(), RequestError> {
+    bot.edit_message_text(chat_id, msg_id, text)
+        .parse_mode(MarkdownV2)
+        .link_preview_options(LinkPreviewOptions {
+            is_disabled: true,
+            url: Default::default(),
+            prefer_small_media: Default::default(),
+            prefer_large_media: Default::default(),
+            show_above_text: Default::default(),
+        })
+        .reply_markup(markup)
+        .send()
+        .await
+        .map(|_| ())
+}
+
+/// Username of the bot, asked once
+pub(crate) async fn bot_username(
+    bot: &Bot,
+) -> Result<&'static str, RequestError> {
+    static USERNAME: OnceLock<String> = OnceLock::new();
+    if let Some(username) = USERNAME.get() {
+        return Ok(username);
+    }
+    let me = bot.get_me().await?;
+    Ok(USERNAME.get_or_init(|| me.username().to_owned()))
+}
+
+pub(crate) fn done_button(occ_id: i64, lang: Language) -> InlineKeyboardButton {
+    InlineKeyboardButton::new(
+        lang.text("button_done", &[]),
+        InlineKeyboardButtonKind::CallbackData(format!(
+            "donerem::occ::{}",
+            occ_id
+        )),
+    )
+}
+
+/// Button stopping the resending of the notification until tomorrow
+pub(crate) fn mute_button(occ_id: i64, lang: Language) -> InlineKeyboardButton {
+    InlineKeyboardButton::new(
+        lang.text("button_mute", &[]),
+        InlineKeyboardButtonKind::CallbackData(format!("mute::{}", occ_id)),
+    )
+}
+
+/// Button reminding of the notification again in this many seconds
+pub(crate) fn snooze_button(
+    occ_id: i64,
+    secs: u64,
+    lang: Language,
+) -> InlineKeyboardButton {
+    InlineKeyboardButton::new(
+        lang.text("button_snooze", &[("lead", &format_lead(secs as i64))]),
+        InlineKeyboardButtonKind::CallbackData(format!("snooze::{}", occ_id)),
+    )
+}
+
+pub(crate) fn paid_button(
+    bill_id: i64,
+    lang: Language,
+) -> InlineKeyboardButton {
+    InlineKeyboardButton::new(
+        lang.text("button_paid", &[]),
+        InlineKeyboardButtonKind::CallbackData(format!(
+            "bill::paid::{}",
+            bill_id
+        )),
+    )
+}
+
+pub(crate) fn pomodoro_markup(
+    pomodoro_id: i64,
+    lang: Language,
+) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::new(
+        lang.text("button_stop", &[]),
+        InlineKeyboardButtonKind::CallbackData(format!(
+            "pomodoro::stop::{}",
+            pomodoro_id
+        )),
+    )]])
+}
+
+pub(crate) async fn send_document(
+    content: Vec<u8>,
+    file_name: &str,
+    caption: &str,
+    bot: &Bot,
+    chat_id: ChatId,
+) -> Result<Message, RequestError> {
+    bot.send_document(
+        chat_id,
+        InputFile::memory(content).file_name(file_name.to_owned()),
+    )
+    .caption(caption)
+    .disable_notification(true)
+    .send()
+    .await
+}
+
+pub(crate) async fn download_file(
+    bot: &Bot,
+    file_id: &str,
+) -> Result<Vec<u8>, RequestError> {
+    let file = bot.get_file(file_id).await?;
+    let mut content = vec![];
+    bot.download_file(&file.path, &mut content).await?;
+    Ok(content)
+}
+
+/// Forum topic a message was sent in, if any
+pub(crate) fn topic(msg: &Message) -> Option<ThreadId> {
+    msg.thread_id.filter(|_| msg.is_topic_message)
+}
+
+/// Message replied to, not counting the first message of the forum topic
+/// that the messages in it reply to by default
+pub(crate) fn reply_to_message(msg: &Message) -> Option<&Message> {
+    msg.reply_to_message()
+        .filter(|reply| topic(msg) != Some(ThreadId(reply.id)))
+}
+
+/// Forum topic of a stored thread id
+pub(crate) fn thread(thread_id: Option<i32>) -> Option<ThreadId> {
+    thread_id.map(|id| ThreadId(MessageId(id)))
+}
+
+/// Message to reply to, quoting a part of its text if given
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Reply {
+    pub(crate) msg_id: MessageId,
+    pub(crate) quote: Option<String>,
+}
+
+/// Message replied to, that a new reminder can be about
+#[derive(Clone)]
+pub(crate) struct RepliedTo {
+    /// Text or caption of the message
+    pub(crate) text: Option<String>,
+    /// File of the message that a reminder can be sent along with
+    pub(crate) media: Option<(MediaKind, String)>,
+}
+
+pub(crate) fn replied_to(msg: &Message) -> Option<RepliedTo> {
+    let reply = reply_to_message(msg)?;
+    Some(RepliedTo {
+        text: reply.text().or(reply.caption()).map(str::to_owned),
+        media: media(reply),
+    })
+}
+
+fn media(msg: &Message) -> Option<(MediaKind, String)> {
+    if let Some(photo) = msg.photo() {
+        // The last size is the largest
+        photo
+            .last()
+            .map(|size| (MediaKind::Photo, size.file.id.clone()))
+    } else if let Some(document) = msg.document() {
+        Some((MediaKind::Document, document.file.id.clone()))
+    } else {
+        msg.voice()
+            .map(|voice| (MediaKind::Voice, voice.file.id.clone()))
+    }
+}
+
+/// Send an already uploaded file in reply to a message
+pub(crate) async fn send_media(
+    kind: MediaKind,
+    file_id: &str,
+    bot: &Bot,
+    chat_id: ChatId,
+    reply_to: MessageId,
+) -> Result<Message, RequestError> {
+    let file = InputFile::file_id(file_id);
+    let reply = ReplyParameters::new(reply_to).allow_sending_without_reply();
+    throttle(chat_id).await;
+    match kind {
+        MediaKind::Photo => {
+            bot.send_photo(chat_id, file).reply_parameters(reply).await
+        }
+        MediaKind::Document => {
+            bot.send_document(chat_id, file)
+                .reply_parameters(reply)
+                .await
+        }
+        MediaKind::Voice => {
+            bot.send_voice(chat_id, file).reply_parameters(reply).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(TgResponse::DeletedAll(1) => "🗑 Deleted 1 reminder" ; "one")]
+    #[test_case(TgResponse::DeletedAll(5) => "🗑 Deleted 5 reminders" ; "many")]
+    #[test_case(TgResponse::DeletedAll(0) => "🗑 Deleted 0 reminders" ; "zero")]
+    #[test_case(TgResponse::MeetingSummary("Standup".to_owned(), 1) => "🤝 Standup starts in 1 minute" ; "signed count")]
+    fn test_plural(response: TgResponse) -> String {
+        response.to_unescaped_string()
+    }
+
+    #[test]
+    fn test_reserve_chat() {
+        let mut slots = Slots::default();
+        let now = Duration::from_secs(10);
+        let reserved = (0..5)
+            .map(|_| slots.reserve(ChatId(1), now).as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(reserved, [10, 11, 12, 13, 14]);
+    }
+
+    #[test]
+    fn test_reserve_global() {
+        let mut slots = Slots::default();
+        let now = Duration::from_secs(10);
+        let reserved = (0..=GLOBAL_SENDS_PER_SEC as i64)
+            .map(|chat_id| slots.reserve(ChatId(chat_id), now))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reserved[1],
+            now + Duration::from_secs(1) / GLOBAL_SENDS_PER_SEC
+        );
+        assert_eq!(reserved.last(), Some(&Duration::from_secs(11)));
+        // A chat can be sent a message again after a while
+        let later = Duration::from_secs(20);
+        assert_eq!(slots.reserve(ChatId(1), later), later);
+    }
+}
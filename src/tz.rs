@@ -4,10 +4,15 @@ use crate::db::Database;
 use crate::db::MockDatabase as Database;
 use crate::err;
 
+use chrono::{NaiveDateTime, Offset, TimeZone};
 use chrono_tz::Tz;
-use teloxide::types::UserId;
+use std::collections::HashMap;
+use teloxide::types::{ChatId, UserId};
 use tzf_rs::DefaultFinder;
 
+/// Timezones suggested for a search at most
+const MAX_SEARCH_RESULTS: usize = 8;
+
 const TZ_NAMES: &[&str] = &[
     "Africa/Abidjan",
     "Africa/Accra",
@@ -369,6 +374,114 @@ pub(crate) fn get_tz_names_for_page_idx(
     TZ_NAMES.chunks(30).nth(num).map(|v| v.to_vec())
 }
 
+/// Timezone written with underscores instead of slashes as in the links
+/// to the bot, e.g. `Europe_Berlin`
+pub(crate) fn tz_name_from_underscored(name: &str) -> Option<&'static str> {
+    TZ_NAMES
+        .iter()
+        .copied()
+        .find(|tz_name| tz_name.replace('/', "_") == name)
+}
+
+/// Offset from UTC in seconds written like `gmt+3`, `UTC-05:30` or `+4`
+fn parse_offset(query: &str) -> Option<i32> {
+    let query = query.trim().to_lowercase();
+    let rest = query
+        .strip_prefix("gmt")
+        .or_else(|| query.strip_prefix("utc"))
+        .unwrap_or(&query)
+        .trim_start();
+    if rest.is_empty() {
+        return (rest.len() != query.len()).then_some(0);
+    }
+    let sign = match rest.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let (hours, minutes) =
+        rest[1..].split_once(':').unwrap_or((&rest[1..], "0"));
+    let hours: i32 = hours.trim().parse().ok()?;
+    let minutes: i32 = minutes.trim().parse().ok()?;
+    (hours <= 14 && minutes < 60)
+        .then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Offset of the timezone from UTC at the time, e.g. `UTC+02:00`
+pub(crate) fn utc_offset(tz: Tz, now: NaiveDateTime) -> String {
+    format!("UTC{}", tz.offset_from_utc_datetime(&now).fix())
+}
+
+/// Number of single-character edits turning one string into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// How well the timezone matches the lowercase query, lower is better:
+/// its city first, then any part of it, then a misspelled city
+fn match_score(tz_name: &str, query: &str) -> Option<usize> {
+    let name = tz_name.to_lowercase();
+    let city = name.rsplit('/').next().unwrap_or(&name);
+    if city == query {
+        Some(0)
+    } else if city.starts_with(query) {
+        Some(1)
+    } else if name.contains(query) {
+        Some(2)
+    } else {
+        let max_distance = if query.chars().count() < 5 { 1 } else { 2 };
+        let distance = edit_distance(city, query);
+        (distance <= max_distance).then_some(2 + distance)
+    }
+}
+
+/// Timezones of the place, possibly misspelled, or with the offset from
+/// UTC at the time, the best matches first
+pub(crate) fn search_timezones(
+    query: &str,
+    now: NaiveDateTime,
+) -> Vec<&'static str> {
+    if let Some(offset) = parse_offset(query) {
+        return TZ_NAMES
+            .iter()
+            .copied()
+            .filter(|tz_name| {
+                tz_name.parse::<Tz>().is_ok_and(|tz| {
+                    tz.offset_from_utc_datetime(&now).fix().local_minus_utc()
+                        == offset
+                })
+            })
+            .take(MAX_SEARCH_RESULTS)
+            .collect();
+    }
+    let query = query.trim().to_lowercase().replace(' ', "_");
+    if query.is_empty() {
+        return vec![];
+    }
+    let mut matches: Vec<_> = TZ_NAMES
+        .iter()
+        .copied()
+        .filter_map(|tz_name| Some((match_score(tz_name, &query)?, tz_name)))
+        .collect();
+    matches.sort();
+    matches
+        .into_iter()
+        .map(|(_, tz_name)| tz_name)
+        .take(MAX_SEARCH_RESULTS)
+        .collect()
+}
+
 pub(crate) async fn get_user_timezone(
     db: &Database,
     user_id: UserId,
@@ -379,9 +492,148 @@ pub(crate) async fn get_user_timezone(
         .transpose()
 }
 
+/// Timezone set for a group with /setchattimezone, it takes
+/// precedence over the timezones of the group's members
+pub(crate) async fn get_chat_timezone(
+    db: &Database,
+    chat_id: ChatId,
+) -> Result<Option<Tz>, err::Error> {
+    if chat_id.is_user() {
+        return Ok(None);
+    }
+    db.get_chat_timezone_name(chat_id.0)
+        .await?
+        .map(|tz_name| tz_name.parse::<Tz>().map_err(err::Error::Parse))
+        .transpose()
+}
+
+/// Timezone to set and show the reminders of the chat in for the user
+pub(crate) async fn get_chat_user_timezone(
+    db: &Database,
+    user_id: UserId,
+    chat_id: ChatId,
+) -> Result<Option<Tz>, err::Error> {
+    match get_chat_timezone(db, chat_id).await? {
+        Some(tz) => Ok(Some(tz)),
+        None => get_user_timezone(db, user_id).await,
+    }
+}
+
+/// Timezone set for the reminder's group, otherwise of the reminder's
+/// owner, or of its chat for the reminders without an owner with a known
+/// timezone. A group's fallback timezone is the first one chosen in it.
+pub(crate) async fn get_reminder_timezone(
+    db: &Database,
+    user_id: Option<i64>,
+    chat_id: i64,
+) -> Result<Option<Tz>, err::Error> {
+    if let Some(tz) = get_chat_timezone(db, ChatId(chat_id)).await? {
+        return Ok(Some(tz));
+    }
+    if let Some(user_id) = user_id {
+        if let Some(tz) = get_user_timezone(db, UserId(user_id as u64)).await? {
+            return Ok(Some(tz));
+        }
+    }
+    db.get_user_timezone_name(chat_id)
+        .await?
+        .map(|tz_name| tz_name.parse::<Tz>().map_err(err::Error::Parse))
+        .transpose()
+}
+
+/// Timezones of the due reminders fetched at once
+#[derive(Debug, Default)]
+pub(crate) struct ReminderTimezones {
+    /// Timezones of the users by their ids
+    pub(crate) users: HashMap<i64, String>,
+    /// Timezones set for the groups with /setchattimezone
+    pub(crate) chats: HashMap<i64, String>,
+}
+
+impl ReminderTimezones {
+    /// Timezone of the reminder picked the same way as by
+    /// [`get_reminder_timezone`]
+    pub(crate) fn get(&self, user_id: Option<i64>, chat_id: i64) -> Option<Tz> {
+        self.chats
+            .get(&chat_id)
+            .filter(|_| !ChatId(chat_id).is_user())
+            .or_else(|| user_id.and_then(|user_id| self.users.get(&user_id)))
+            .or_else(|| self.users.get(&chat_id))
+            .and_then(|tz_name| tz_name.parse().ok())
+    }
+}
+
 pub(crate) fn get_timezone_name_of_location(
     lng: f64,
     lat: f64,
 ) -> &'static str {
     FINDER.get_tz_name(lng, lat)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+    use test_case::test_case;
+
+    #[test_case(Some(1), -10 => Some(Tz::Asia__Tokyo) ; "group timezone")]
+    #[test_case(Some(1), -20 => Some(Tz::Europe__Berlin) ; "owner")]
+    #[test_case(None, 2 => Some(Tz::America__New_York) ; "private chat")]
+    #[test_case(Some(3), -20 => None ; "unknown")]
+    fn test_reminder_timezones(
+        user_id: Option<i64>,
+        chat_id: i64,
+    ) -> Option<Tz> {
+        let timezones = ReminderTimezones {
+            users: HashMap::from([
+                (1, "Europe/Berlin".to_owned()),
+                (2, "America/New_York".to_owned()),
+            ]),
+            chats: HashMap::from([(-10, "Asia/Tokyo".to_owned())]),
+        };
+        timezones.get(user_id, chat_id)
+    }
+
+    fn summer() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 6, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    #[test_case("gmt+3" => Some(3 * 3600) ; "gmt")]
+    #[test_case("UTC -05:30" => Some(-(5 * 3600 + 30 * 60)) ; "utc with minutes")]
+    #[test_case("+4" => Some(4 * 3600) ; "sign only")]
+    #[test_case("utc" => Some(0) ; "utc")]
+    #[test_case("gmt+15" => None ; "too far")]
+    #[test_case("berlin" => None ; "place")]
+    #[test_case("" => None ; "empty")]
+    fn test_parse_offset(query: &str) -> Option<i32> {
+        parse_offset(query)
+    }
+
+    #[test_case("berlin" => vec!["Europe/Berlin"] ; "city")]
+    #[test_case("New York" => vec!["America/New_York"] ; "with space")]
+    #[test_case("Amsterdm" => vec!["Europe/Amsterdam"] ; "misspelled")]
+    #[test_case("xyzzy" => Vec::<&str>::new() ; "nothing")]
+    fn test_search_timezones(query: &str) -> Vec<&'static str> {
+        search_timezones(query, summer())
+    }
+
+    #[test]
+    fn test_search_timezones_by_offset() {
+        let found = search_timezones("gmt+2", summer());
+        assert!(found.contains(&"Africa/Johannesburg"));
+        assert_eq!(found.len(), MAX_SEARCH_RESULTS);
+        assert!(found
+            .iter()
+            .all(|tz_name| utc_offset(tz_name.parse().unwrap(), summer())
+                == "UTC+02:00"));
+    }
+
+    #[test]
+    fn test_search_prefers_cities() {
+        assert_eq!(search_timezones("america", summer()).len(), 8);
+        assert_eq!(search_timezones("paris", summer())[0], "Europe/Paris");
+    }
+}
@@ -0,0 +1,159 @@
+//! Undoing a deletion or an edit of a reminder with the "Undo" button under
+//! the response, for a few minutes after it. The previous states are only
+//! kept in memory, so the buttons stop working after a restart.
+
+use crate::controller::Reminder;
+use crate::db::LinkTarget;
+use crate::lang::Language;
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use teloxide::types::{
+    InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup,
+};
+
+const CALLBACK_PREFIX: &str = "undo::";
+
+/// Time the "Undo" button works for after the change
+const UNDO_WINDOW: TimeDelta = TimeDelta::minutes(5);
+
+/// Changes kept at most, the oldest ones are forgotten first
+const MAX_SNAPSHOTS: usize = 1024;
+
+/// State of a reminder before a change
+pub(crate) struct Snapshot {
+    pub(crate) chat_id: i64,
+    /// Reminder as it was before the change
+    pub(crate) previous: Reminder,
+    /// Reminder it was replaced with, `None` if it was deleted
+    pub(crate) replacement: Option<LinkTarget>,
+}
+
+struct Saved {
+    id: u64,
+    time: NaiveDateTime,
+    snapshot: Snapshot,
+}
+
+struct Snapshots {
+    next_id: u64,
+    saved: VecDeque<Saved>,
+}
+
+lazy_static! {
+    /// The ids start from the startup time, so that the buttons sent
+    /// before a restart don't undo the changes made after it
+    static ref SNAPSHOTS: Mutex<Snapshots> = Mutex::new(Snapshots {
+        next_id: Utc::now().timestamp_millis() as u64,
+        saved: VecDeque::new(),
+    });
+}
+
+/// Keep the state before a change, return the id to undo it with
+pub(crate) fn save(snapshot: Snapshot, now: NaiveDateTime) -> u64 {
+    let mut snapshots = SNAPSHOTS.lock().unwrap();
+    while snapshots.saved.len() >= MAX_SNAPSHOTS
+        || snapshots
+            .saved
+            .front()
+            .is_some_and(|saved| saved.time + UNDO_WINDOW <= now)
+    {
+        snapshots.saved.pop_front();
+    }
+    let id = snapshots.next_id;
+    snapshots.next_id += 1;
+    snapshots.saved.push_back(Saved {
+        id,
+        time: now,
+        snapshot,
+    });
+    id
+}
+
+/// Take the state before the change made in the chat to undo it,
+/// `None` if it's too late
+pub(crate) fn take(
+    id: u64,
+    chat_id: i64,
+    now: NaiveDateTime,
+) -> Option<Snapshot> {
+    let mut snapshots = SNAPSHOTS.lock().unwrap();
+    let idx = snapshots.saved.iter().position(|saved| {
+        saved.id == id && saved.snapshot.chat_id == chat_id
+    })?;
+    let saved = snapshots.saved.remove(idx)?;
+    (saved.time + UNDO_WINDOW > now).then_some(saved.snapshot)
+}
+
+/// "Undo" button of the change
+pub(crate) fn markup(id: u64, lang: Language) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::new(
+        lang.text("button_undo", &[]),
+        InlineKeyboardButtonKind::CallbackData(format!(
+            "{}{}",
+            CALLBACK_PREFIX, id
+        )),
+    )]])
+}
+
+/// Id of the change whose "Undo" button was pressed
+pub(crate) fn parse_callback(cb_data: &str) -> Option<u64> {
+    cb_data.strip_prefix(CALLBACK_PREFIX)?.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::cron_reminder;
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn snapshot(chat_id: i64) -> Snapshot {
+        Snapshot {
+            chat_id,
+            previous: Reminder::CronReminder(cron_reminder::Model {
+                id: 1,
+                chat_id,
+                user_id: None,
+                cron_expr: "0 9 * * *".to_owned(),
+                time: utc("2024-06-01 09:00"),
+                desc: "".to_owned(),
+                paused: false,
+                policy: None,
+                version: 0,
+                media_kind: None,
+                media_file_id: None,
+                failures: 0,
+                retry_time: None,
+                thread_id: None,
+                deleted_time: None,
+            }),
+            replacement: None,
+        }
+    }
+
+    #[test]
+    fn test_take() {
+        let now = utc("2024-06-01 10:00");
+        let id = save(snapshot(1), now);
+        assert!(take(id, 2, now).is_none());
+        assert!(take(id, 1, now + TimeDelta::minutes(1)).is_some());
+        assert!(take(id, 1, now).is_none());
+    }
+
+    #[test]
+    fn test_take_expired() {
+        let now = utc("2024-06-01 10:00");
+        let id = save(snapshot(1), now);
+        assert!(take(id, 1, now + UNDO_WINDOW).is_none());
+    }
+
+    #[test_case::test_case("undo::42" => Some(42) ; "undo")]
+    #[test_case::test_case("undo::x" => None ; "bad id")]
+    #[test_case::test_case("snooze::42" => None ; "other prefix")]
+    fn test_parse_callback(cb_data: &str) -> Option<u64> {
+        parse_callback(cb_data)
+    }
+}
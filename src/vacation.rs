@@ -0,0 +1,183 @@
+//! Vacations set with /vacation: all the notifications of the chat are
+//! paused until the given day, the occurrences missed meanwhile are
+//! skipped and may be summed up once it's over
+
+use crate::entity::{reminder_event, vacation};
+use crate::format;
+use crate::lang::Language;
+use crate::search;
+use crate::tg::{self, TgResponse};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+
+/// Day the vacation lasts until and whether to sum up the missed
+/// occurrences after it from the /vacation arguments, e.g. `until 15.08`
+/// or `until 15.08 digest`. `off` ends the vacation.
+pub(crate) fn parse_args(
+    args: &str,
+    today: NaiveDate,
+) -> Option<Option<(NaiveDate, bool)>> {
+    let mut words = args.split_whitespace();
+    let first = words.next()?;
+    if first.eq_ignore_ascii_case("off") {
+        return words.next().is_none().then_some(None);
+    }
+    let date = if first.eq_ignore_ascii_case("until") {
+        words.next()?
+    } else {
+        first
+    };
+    let mut day = search::parse_date(date, today)?;
+    // A day of the current year that has passed is meant for the next one
+    if day <= today && date.matches('.').count() == 1 {
+        day = day.with_year(day.year() + 1)?;
+    }
+    let digest = match words.next() {
+        Some(word) if word.eq_ignore_ascii_case("digest") => true,
+        Some(_) => return None,
+        None => false,
+    };
+    (day > today && words.next().is_none()).then_some(Some((day, digest)))
+}
+
+/// Vacation of the chat from now until the start of the day
+pub(crate) fn start(
+    chat_id: i64,
+    user_id: i64,
+    (day, digest): (NaiveDate, bool),
+    user_tz: Tz,
+    now: NaiveDateTime,
+) -> vacation::Model {
+    vacation::Model {
+        chat_id,
+        user_id,
+        since: now,
+        until: search::day_start(day, user_tz),
+        digest,
+    }
+}
+
+/// Day the notifications are resumed on, e.g. `Fri 15.08`
+pub(crate) fn describe(vacation: &vacation::Model, user_tz: Tz) -> String {
+    user_tz
+        .from_utc_datetime(&vacation.until)
+        .format("%a %d.%m")
+        .to_string()
+}
+
+/// Messages telling that the vacation is over, listing the occurrences
+/// missed meanwhile if asked to
+pub(crate) fn messages(
+    vacation: &vacation::Model,
+    missed: Vec<reminder_event::Model>,
+    user_tz: Tz,
+    lang: Language,
+) -> Vec<String> {
+    if !vacation.digest || missed.is_empty() {
+        return vec![TgResponse::VacationOver.to_string_in(lang)];
+    }
+    let header = TgResponse::VacationMissed(missed.len()).to_string_in(lang);
+    let lines = missed.into_iter().map(|event| {
+        let time = user_tz.from_utc_datetime(&event.time);
+        TgResponse::VacationMissedEntry(
+            time.format("%d.%m %H:%M").to_string(),
+            event.desc,
+        )
+        .to_string_in(lang)
+        .replace('@', "@\u{200B}")
+    });
+    format::split_lines(
+        std::iter::once(header).chain(lines),
+        tg::MESSAGE_MAX_LEN,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::entity::reminder_event::EventKind;
+    use test_case::test_case;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, 10).unwrap()
+    }
+
+    fn utc(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test_case("until 15.08" => Some(Some(("2024-08-15".to_owned(), false))) ; "until")]
+    #[test_case("15.08 digest" => Some(Some(("2024-08-15".to_owned(), true))) ; "digest")]
+    #[test_case("until 01.02" => Some(Some(("2025-02-01".to_owned(), false))) ; "next year")]
+    #[test_case("until 01.02.2024" => None ; "past")]
+    #[test_case("until 10.06" => Some(Some(("2025-06-10".to_owned(), false))) ; "today")]
+    #[test_case("OFF" => Some(None) ; "off")]
+    #[test_case("until 15.08 now" => None ; "extra words")]
+    #[test_case("until" => None ; "no date")]
+    #[test_case("" => None ; "empty")]
+    fn test_parse_args(args: &str) -> Option<Option<(String, bool)>> {
+        parse_args(args, today())
+            .map(|parsed| parsed.map(|(day, digest)| (day.to_string(), digest)))
+    }
+
+    #[test]
+    fn test_start() {
+        let vacation = start(
+            1,
+            2,
+            (NaiveDate::from_ymd_opt(2024, 8, 15).unwrap(), true),
+            chrono_tz::Europe::Amsterdam,
+            utc("2024-06-10 12:00"),
+        );
+        assert_eq!(vacation.since, utc("2024-06-10 12:00"));
+        assert_eq!(vacation.until, utc("2024-08-14 22:00"));
+        assert_eq!(
+            describe(&vacation, chrono_tz::Europe::Amsterdam),
+            "Thu 15.08"
+        );
+    }
+
+    #[test]
+    fn test_messages() {
+        let vacation = vacation::Model {
+            chat_id: 1,
+            user_id: 1,
+            since: utc("2024-06-10 12:00"),
+            until: utc("2024-06-20 00:00"),
+            digest: true,
+        };
+        let missed = vec![reminder_event::Model {
+            id: 1,
+            chat_id: 1,
+            time: utc("2024-06-12 07:30"),
+            desc: "water the plants".to_owned(),
+            kind: EventKind::Missed,
+            occurrence_id: None,
+            note: None,
+        }];
+        assert_eq!(
+            messages(
+                &vacation,
+                missed.clone(),
+                chrono_tz::UTC,
+                Language::English
+            ),
+            vec![format!(
+                "{}\n{}",
+                TgResponse::VacationMissed(1),
+                TgResponse::VacationMissedEntry(
+                    "12.06 07:30".to_owned(),
+                    "water the plants".to_owned()
+                )
+            )]
+        );
+        let vacation = vacation::Model {
+            digest: false,
+            ..vacation
+        };
+        assert_eq!(
+            messages(&vacation, missed, chrono_tz::UTC, Language::English),
+            vec![TgResponse::VacationOver.to_string()]
+        );
+    }
+}